@@ -0,0 +1,211 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::{
+    transform::symbols,
+    turing::{Direction, TapeEntry, TuringMachine},
+};
+
+/// One side of the tape as seen from the head: the nearest cells, nearest
+/// first, and beyond them either only blanks or anything at all.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+struct Side {
+    cells: Vec<TapeEntry>,
+    /// Whether all cells beyond are blank, rather than unknown.
+    blank: bool,
+}
+
+impl Side {
+    /// The side with `entry` pushed next to the head, forgetting the
+    /// farthest cell if more than `window` are known.
+    fn push(&self, entry: TapeEntry, window: usize) -> Side {
+        let mut side = Side {
+            cells: [entry].into_iter().chain(self.cells.clone()).collect(),
+            blank: self.blank,
+        };
+        if side.cells.len() > window {
+            side.blank &= side.cells.pop() == Some(0);
+        }
+        side.trim();
+        side
+    }
+
+    /// The symbols that may be next to the head, each with the rest of the
+    /// side.
+    fn pop(&self, symbols: &[TapeEntry]) -> Vec<(TapeEntry, Side)> {
+        match self.cells.split_first() {
+            Some((entry, rest)) => vec![(
+                *entry,
+                Side {
+                    cells: rest.to_vec(),
+                    blank: self.blank,
+                },
+            )],
+            None if self.blank => vec![(0, self.clone())],
+            None => symbols.iter().map(|entry| (*entry, self.clone())).collect(),
+        }
+    }
+
+    /// Drops blanks at the far end that the blanks beyond cover.
+    fn trim(&mut self) {
+        while self.blank && self.cells.last() == Some(&0) {
+            self.cells.pop();
+        }
+    }
+}
+
+/// An abstract configuration, standing for all configurations in the
+/// state and with the symbol under the head that match both sides.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+struct Abstract {
+    state: usize,
+    entry: TapeEntry,
+    left: Side,
+    right: Side,
+}
+
+/// What the abstract interpretation of a machine found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Summary {
+    pub window: usize,
+    /// Number of abstract configurations reached.
+    pub configurations: usize,
+    /// Whether the search reached a fixpoint, rather than the maximum
+    /// number of configurations. Only then do the facts below hold.
+    pub complete: bool,
+    /// States and symbols the machine may read from the blank tape.
+    pub reached: BTreeSet<(usize, TapeEntry)>,
+    /// The ones of those without a transition to a state, where the
+    /// machine may halt.
+    pub halting: BTreeSet<(usize, TapeEntry)>,
+}
+
+impl Summary {
+    /// Whether the machine provably never halts from the blank tape.
+    pub fn never_halts(&self) -> bool {
+        self.complete && self.halting.is_empty()
+    }
+}
+
+/// Over-approximates the configurations `tm` reaches from the blank tape,
+/// without simulating it.
+///
+/// Each side of the tape is abstracted to the `window` cells next to the
+/// head, followed by either blanks only or anything, so a tape is seen as
+/// `blank* content blank*` until it grows beyond the window. Reading a cell
+/// beyond the known ones of an unknown side may give any symbol, so every
+/// real run is covered by the abstract one, which is finite. If no
+/// abstract configuration reads a symbol without a transition to a state,
+/// the machine never halts, and transitions no abstract configuration uses
+/// are never used by the machine either. The converse doesn't hold: a
+/// window too small finds halting transitions the machine never reaches.
+pub fn analyze(tm: &TuringMachine, window: usize, max_configurations: usize) -> Summary {
+    let tm = tm.with_zero_blank();
+    let symbols = symbols(&tm);
+    let empty = Side {
+        cells: vec![],
+        blank: true,
+    };
+    let start = Abstract {
+        state: 0,
+        entry: 0,
+        left: empty.clone(),
+        right: empty,
+    };
+    let mut summary = Summary {
+        window,
+        configurations: 0,
+        complete: true,
+        reached: BTreeSet::new(),
+        halting: BTreeSet::new(),
+    };
+    if tm.states().is_empty() {
+        return summary;
+    }
+
+    let mut seen = HashSet::from([start.clone()]);
+    let mut queue = vec![start];
+    while let Some(configuration) = queue.pop() {
+        let read = (configuration.state, configuration.entry);
+        summary.reached.insert(read);
+        let instruction = tm
+            .instructions()
+            .iter()
+            .find(|i| (i.state, i.entry) == read);
+        let Some((instruction, new_state)) =
+            instruction.and_then(|i| i.new_state.map(|new_state| (i, new_state)))
+        else {
+            summary.halting.insert(read);
+            continue;
+        };
+        let (left, right) = (&configuration.left, &configuration.right);
+        let next: Vec<Abstract> = match instruction.direction {
+            Direction::Right => {
+                let left = left.push(instruction.new_entry, window);
+                right
+                    .pop(&symbols)
+                    .into_iter()
+                    .map(|(entry, right)| Abstract {
+                        state: new_state,
+                        entry,
+                        left: left.clone(),
+                        right,
+                    })
+                    .collect()
+            }
+            Direction::Left => {
+                let right = right.push(instruction.new_entry, window);
+                left.pop(&symbols)
+                    .into_iter()
+                    .map(|(entry, left)| Abstract {
+                        state: new_state,
+                        entry,
+                        left,
+                        right: right.clone(),
+                    })
+                    .collect()
+            }
+        };
+        for configuration in next {
+            if seen.len() >= max_configurations {
+                summary.complete = false;
+                break;
+            }
+            if seen.insert(configuration.clone()) {
+                queue.push(configuration);
+            }
+        }
+    }
+    summary.configurations = seen.len();
+    summary
+}
+
+#[test]
+fn test_abstraction() {
+    // Writes ones to the right forever, so it never reads the 1 it halts
+    // on.
+    let tm = TuringMachine::parse("A 0 -> B 1 R\nA 1 -> Halt 1 R\nB 0 -> A 1 R\n").unwrap();
+    let summary = analyze(&tm, 2, 1000);
+    assert!(summary.never_halts());
+    assert_eq!(summary.reached, BTreeSet::from([(0, 0), (1, 0)]));
+    assert_eq!(summary.configurations, 5);
+
+    // Writes three ones and goes back and forth over them, halting beyond
+    // them. A window of one cell forgets the cells at the ends.
+    let tm = TuringMachine::parse(
+        "A 0 -> B 1 R\nB 0 -> C 1 R\nC 0 -> D 1 L\nD 1 -> E 1 L\nE 0 -> Halt 0 R\n\
+         E 1 -> F 1 R\nF 1 -> G 1 R\nG 0 -> Halt 0 R\nG 1 -> D 1 L\n",
+    )
+    .unwrap();
+    assert!(analyze(&tm, 2, 1000).never_halts());
+    let summary = analyze(&tm, 1, 1000);
+    assert!(summary.complete);
+    assert_eq!(summary.halting, BTreeSet::from([(4, 0), (6, 0)]));
+
+    let tm = TuringMachine::new(std::path::Path::new(
+        "examples/busy_bever/busy_bever_2.turing",
+    ));
+    let summary = analyze(&tm, 4, 1000);
+    assert!(!summary.never_halts());
+    assert_eq!(summary.halting, BTreeSet::from([(1, 1)]));
+    assert!(!analyze(&tm, 4, 1).complete);
+}
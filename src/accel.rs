@@ -0,0 +1,643 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    str::FromStr,
+};
+
+use crate::{
+    steps::Steps,
+    turing::{Direction, HaltReason, Instruction, TapeEntry, TuringMachine},
+};
+
+/// Largest block size considered by [`Accel::Auto`].
+const MAX_AUTO_BLOCK_SIZE: usize = 8;
+/// Steps simulated without acceleration before [`Accel::Auto`] looks at the
+/// tape for the first time.
+const WARMUP_STEPS: u128 = 10_000;
+/// Number of macro steps after which [`Accel::Auto`] checks whether the
+/// acceleration still pays off.
+const TUNE_WINDOW: u128 = 100_000;
+/// Cells on either side of the head that are compared to find the tape
+/// period.
+const PERIOD_WINDOW: usize = 256;
+/// Largest tape that is expanded into single cells to change the block size.
+const MAX_RECHUNK_CELLS: u128 = 1 << 20;
+
+/// How the macro machine groups the tape into blocks.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Accel {
+    /// Blocks of a fixed number of cells.
+    Block(usize),
+    /// Blocks matching the period of the tape near the head, chosen after a
+    /// short warm-up and re-chosen whenever the acceleration stalls.
+    Auto,
+}
+
+impl FromStr for Accel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "auto" {
+            return Ok(Accel::Auto);
+        }
+        match s.parse() {
+            Ok(0) => Err("block size must be at least 1".to_string()),
+            Ok(cells) => Ok(Accel::Block(cells)),
+            Err(_) => Err(format!("expected a block size or 'auto', got '{s}'")),
+        }
+    }
+}
+
+type Block = Box<[TapeEntry]>;
+
+/// `count` consecutive copies of `block`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Run {
+    block: Block,
+    count: u128,
+}
+
+/// Effect of entering a block in some state until the head leaves it again.
+#[derive(Debug, Clone)]
+enum Exit {
+    Moved {
+        state: usize,
+        block: Block,
+        direction: Direction,
+        steps: u128,
+    },
+    Halted {
+        reason: HaltReason,
+        block: Block,
+        steps: u128,
+    },
+    /// The machine never leaves the block.
+    Loops,
+}
+
+/// Simulates a machine on blocks of cells instead of single cells.
+///
+/// The tape is stored as runs of identical blocks on either side of the
+/// head, which always sits between two blocks. The effect of entering a
+/// block is computed once by simulating the machine inside the block and
+/// then cached. When a block is left in the same state and direction it was
+/// entered, the whole run of identical blocks ahead is crossed in a single
+/// macro step.
+pub struct MacroMachine {
+    transitions: HashMap<(usize, TapeEntry), Instruction>,
+    block_size: usize,
+    /// The symbol of the cells never written.
+    blank: TapeEntry,
+    left: Vec<Run>,
+    right: Vec<Run>,
+    state: Option<usize>,
+    facing: Direction,
+    cache: HashMap<(usize, Block, Direction), Exit>,
+    tune: bool,
+    window_steps: u128,
+    window_macro_steps: u128,
+
+    /// Steps of the simulated machine.
+    pub num_steps: Steps,
+    pub macro_steps: u128,
+    pub halt_reason: Option<HaltReason>,
+    /// Set when the machine was found to never halt.
+    pub proof: Option<Proof>,
+}
+
+/// Why a macro machine never halts.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Proof {
+    /// The head never leaves a block again.
+    LoopsInBlock,
+    /// The head keeps moving into the blank tape in the same state.
+    EndlessSweep,
+}
+
+impl Display for Proof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Proof::LoopsInBlock => "the head never leaves a block again",
+            Proof::EndlessSweep => "the head keeps moving into the blank tape",
+        })
+    }
+}
+
+impl MacroMachine {
+    /// Continues the simulation of `tm` from its current configuration. With
+    /// [`Accel::Auto`] the machine first runs a few unaccelerated steps.
+    pub fn new(tm: &TuringMachine, accel: Accel) -> Self {
+        let mut tm = tm.clone();
+        let block_size = match accel {
+            Accel::Block(cells) => cells,
+            Accel::Auto => {
+                while tm.num_steps < WARMUP_STEPS && tm.step() {}
+                let tape: Vec<TapeEntry> = tm.tape().iter().copied().collect();
+                tape_period(&tape, tm.head())
+            }
+        };
+
+        let mut transitions = HashMap::new();
+        for instruction in tm.instructions() {
+            transitions
+                .entry((instruction.state, instruction.entry))
+                .or_insert_with(|| instruction.clone());
+        }
+
+        let tape: Vec<TapeEntry> = tm.tape().iter().copied().collect();
+        let (left, right) = chunk(&tape, tm.head(), block_size, tm.blank());
+        MacroMachine {
+            transitions,
+            block_size,
+            blank: tm.blank(),
+            left,
+            right,
+            state: tm.state(),
+            facing: Direction::Right,
+            cache: HashMap::new(),
+            tune: accel == Accel::Auto,
+            window_steps: 0,
+            window_macro_steps: 0,
+            num_steps: Steps::new(tm.num_steps),
+            macro_steps: 0,
+            halt_reason: tm.halt_reason,
+            proof: None,
+        }
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// The current state, or `None` once the machine stopped.
+    pub fn state(&self) -> Option<usize> {
+        self.state
+    }
+
+    pub fn blank(&self) -> TapeEntry {
+        self.blank
+    }
+
+    /// Number of cells between the outermost non-blank blocks.
+    pub fn tape_len(&self) -> u128 {
+        let blocks = self.left.iter().chain(&self.right).map(|run| run.count);
+        blocks
+            .fold(0u128, u128::saturating_add)
+            .saturating_mul(self.block_size as u128)
+    }
+
+    /// Approximate number of bytes taken by the tape and the cache of block
+    /// transitions.
+    pub fn memory(&self) -> usize {
+        let runs = self.left.len() + self.right.len();
+        let run = std::mem::size_of::<Run>() + self.block_size;
+        let exit = std::mem::size_of::<((usize, Block, Direction), Exit)>() + 2 * self.block_size;
+        let transition = std::mem::size_of::<((usize, TapeEntry), Instruction)>();
+        std::mem::size_of::<Self>()
+            + runs * run
+            + self.cache.len() * exit
+            + self.transitions.len() * transition
+    }
+
+    /// Performs one macro step. Returns whether the machine is still
+    /// running, or an error if it has no instruction for a configuration.
+    pub fn step(&mut self) -> Result<bool, String> {
+        let state = match self.state {
+            Some(state) => state,
+            None => return Ok(false),
+        };
+
+        let blank = || vec![self.blank; self.block_size].into_boxed_slice();
+        let ahead = match self.facing {
+            Direction::Left => &self.left,
+            Direction::Right => &self.right,
+        };
+        let (block, available) = match ahead.last() {
+            Some(run) => (run.block.clone(), Some(run.count)),
+            None => (blank(), None),
+        };
+
+        let exit = self.exit(state, block, self.facing)?;
+        let (steps, count) = match exit {
+            Exit::Loops => {
+                self.proof = Some(Proof::LoopsInBlock);
+                self.state = None;
+                return Ok(false);
+            }
+            Exit::Halted {
+                reason,
+                block,
+                steps,
+            } => {
+                self.take(self.facing, 1);
+                self.push(self.facing, block, 1);
+                self.state = None;
+                self.halt_reason = Some(reason);
+                (steps, 1)
+            }
+            Exit::Moved {
+                state: new_state,
+                block,
+                direction,
+                steps,
+            } => {
+                let count = if new_state == state && direction == self.facing {
+                    match available {
+                        Some(count) => count,
+                        None => {
+                            self.proof = Some(Proof::EndlessSweep);
+                            self.state = None;
+                            return Ok(false);
+                        }
+                    }
+                } else {
+                    1
+                };
+                self.take(self.facing, count);
+                self.push(opposite(direction), block, count);
+                self.state = Some(new_state);
+                self.facing = direction;
+                (steps, count)
+            }
+        };
+
+        self.num_steps.add(steps, count)?;
+        self.macro_steps += 1;
+        self.window_steps = self
+            .window_steps
+            .saturating_add(steps.saturating_mul(count));
+        self.window_macro_steps += 1;
+        if self.tune && self.window_macro_steps >= TUNE_WINDOW {
+            self.retune();
+        }
+        Ok(self.state.is_some())
+    }
+
+    /// The tape as single cells and the index of the cell the head is about
+    /// to enter, or `None` if the tape is too long to expand.
+    pub fn cells(&self) -> Option<(Vec<TapeEntry>, usize)> {
+        let blocks: u128 = self.left.iter().chain(&self.right).map(|r| r.count).sum();
+        if blocks * self.block_size as u128 > MAX_RECHUNK_CELLS {
+            return None;
+        }
+        let mut cells = vec![];
+        for run in self.left.iter().rev() {
+            for _ in 0..run.count {
+                cells.extend(run.block.iter().rev());
+            }
+        }
+        if cells.is_empty() && self.facing == Direction::Left {
+            cells.push(self.blank);
+        }
+        cells.reverse();
+        let boundary = cells.len();
+        for run in self.right.iter().rev() {
+            for _ in 0..run.count {
+                cells.extend(run.block.iter());
+            }
+        }
+        let head = match self.facing {
+            Direction::Left => boundary - 1,
+            Direction::Right => boundary,
+        };
+        Some((cells, head))
+    }
+
+    /// Counts the ones and the blank cells on the part of the tape that was
+    /// visited, rounded to whole blocks.
+    pub fn eval_busy_bever(&self) -> (u128, u128, Steps) {
+        let mut ones: u128 = 0;
+        let mut zeros: u128 = 0;
+        for run in self.left.iter().chain(&self.right) {
+            for entry in run.block.iter() {
+                if *entry == 1 {
+                    ones += run.count;
+                } else if *entry == 0 {
+                    zeros += run.count;
+                }
+            }
+        }
+        (ones, zeros, self.num_steps.clone())
+    }
+
+    /// Looks up or simulates the effect of entering `block` in `state`,
+    /// moving in `facing`.
+    fn exit(&mut self, state: usize, block: Block, facing: Direction) -> Result<Exit, String> {
+        let key = (state, block, facing);
+        if let Some(exit) = self.cache.get(&key) {
+            return Ok(exit.clone());
+        }
+
+        let mut cells = key.1.to_vec();
+        let mut pos = match facing {
+            Direction::Left => self.block_size - 1,
+            Direction::Right => 0,
+        };
+        let mut current = state;
+        let mut steps = 0;
+        let mut seen = HashSet::new();
+        let exit = loop {
+            if !seen.insert((current, pos, cells.clone())) {
+                break Exit::Loops;
+            }
+            let instruction = match self.transitions.get(&(current, cells[pos])) {
+                Some(instruction) => instruction,
+                None => {
+                    return Err(format!(
+                        "no instruction for state {current} reading {}",
+                        cells[pos]
+                    ))
+                }
+            };
+            steps += 1;
+            cells[pos] = instruction.new_entry;
+            let new_state = match instruction.new_state {
+                Some(new_state) => new_state,
+                None => {
+                    break Exit::Halted {
+                        reason: instruction.halt,
+                        block: cells.into(),
+                        steps,
+                    }
+                }
+            };
+            let left_block = match instruction.direction {
+                Direction::Left if pos == 0 => Some(Direction::Left),
+                Direction::Right if pos + 1 == self.block_size => Some(Direction::Right),
+                Direction::Left => {
+                    pos -= 1;
+                    None
+                }
+                Direction::Right => {
+                    pos += 1;
+                    None
+                }
+            };
+            if let Some(direction) = left_block {
+                break Exit::Moved {
+                    state: new_state,
+                    block: cells.into(),
+                    direction,
+                    steps,
+                };
+            }
+            current = new_state;
+        };
+        self.cache.insert(key, exit.clone());
+        Ok(exit)
+    }
+
+    /// Removes `count` blocks from the run next to the head on `side`.
+    fn take(&mut self, side: Direction, count: u128) {
+        let stack = match side {
+            Direction::Left => &mut self.left,
+            Direction::Right => &mut self.right,
+        };
+        if let Some(run) = stack.last_mut() {
+            run.count -= count;
+            if run.count == 0 {
+                stack.pop();
+            }
+        }
+    }
+
+    /// Puts `count` copies of `block` next to the head on `side`.
+    fn push(&mut self, side: Direction, block: Block, count: u128) {
+        let stack = match side {
+            Direction::Left => &mut self.left,
+            Direction::Right => &mut self.right,
+        };
+        push(stack, block, count, self.blank);
+    }
+
+    /// Switches to the block size matching the current tape if the last
+    /// window of macro steps was barely faster than simulating single steps.
+    fn retune(&mut self) {
+        let stalled = self.window_steps < 2 * self.block_size as u128 * self.window_macro_steps;
+        self.window_steps = 0;
+        self.window_macro_steps = 0;
+        if !stalled {
+            return;
+        }
+        if let Some((cells, head)) = self.cells() {
+            let block_size = tape_period(&cells, head);
+            if block_size != self.block_size {
+                self.rechunk(block_size);
+            }
+        }
+    }
+
+    /// Regroups the tape into blocks of `block_size` cells, if it is short
+    /// enough to be expanded.
+    fn rechunk(&mut self, block_size: usize) {
+        let (cells, head) = match self.cells() {
+            Some(cells) => cells,
+            None => return,
+        };
+        let boundary = match self.facing {
+            Direction::Left => head + 1,
+            Direction::Right => head,
+        };
+        let (left, right) = chunk(&cells, boundary, block_size, self.blank);
+        self.left = left;
+        self.right = right;
+        self.block_size = block_size;
+        self.cache.clear();
+    }
+}
+
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+    }
+}
+
+/// Pushes `count` copies of `block` onto `stack`, merging it with the run on
+/// top. Blocks of `blank` at the far end of the tape are dropped.
+fn push(stack: &mut Vec<Run>, block: Block, count: u128, blank: TapeEntry) {
+    match stack.last_mut() {
+        Some(run) if run.block == block => run.count += count,
+        None if block.iter().all(|entry| *entry == blank) => {}
+        _ => stack.push(Run { block, count }),
+    }
+}
+
+/// Splits `cells` into blocks of `block_size` cells on either side of
+/// `boundary`, padding the outermost blocks with `blank`.
+fn chunk(
+    cells: &[TapeEntry],
+    boundary: usize,
+    block_size: usize,
+    blank: TapeEntry,
+) -> (Vec<Run>, Vec<Run>) {
+    let mut left = vec![];
+    let mut starts: Vec<isize> = vec![];
+    let mut start = boundary as isize - block_size as isize;
+    while start + block_size as isize > 0 {
+        starts.push(start);
+        start -= block_size as isize;
+    }
+    for start in starts.iter().rev() {
+        let block: Block = (*start..*start + block_size as isize)
+            .map(|i| if i < 0 { blank } else { cells[i as usize] })
+            .collect();
+        push(&mut left, block, 1, blank);
+    }
+
+    let mut right = vec![];
+    let mut starts = vec![];
+    let mut start = boundary;
+    while start < cells.len() {
+        starts.push(start);
+        start += block_size;
+    }
+    for start in starts.iter().rev() {
+        let block: Block = (*start..*start + block_size)
+            .map(|i| cells.get(i).copied().unwrap_or(blank))
+            .collect();
+        push(&mut right, block, 1, blank);
+    }
+    (left, right)
+}
+
+/// The block size up to [`MAX_AUTO_BLOCK_SIZE`] for which the tape near
+/// `head` best matches itself shifted by one block, preferring small blocks.
+pub fn tape_period(cells: &[TapeEntry], head: usize) -> usize {
+    let window =
+        &cells[head.saturating_sub(PERIOD_WINDOW)..(head + PERIOD_WINDOW).min(cells.len())];
+    let scores: Vec<f64> = (1..=MAX_AUTO_BLOCK_SIZE)
+        .map(|shift| {
+            let pairs = window.len().saturating_sub(shift);
+            if pairs == 0 {
+                return 0.0;
+            }
+            let matches = (0..pairs)
+                .filter(|i| window[*i] == window[*i + shift])
+                .count();
+            matches as f64 / pairs as f64
+        })
+        .collect();
+    let best = scores.iter().copied().fold(0.0, f64::max);
+    scores
+        .iter()
+        .position(|score| *score >= 0.99 * best)
+        .map_or(1, |index| index + 1)
+}
+
+#[test]
+fn test_macro_machine() {
+    use std::path::Path;
+
+    for (path, ones, steps) in [
+        ("examples/busy_bever/busy_bever_2.turing", 4, 6),
+        ("examples/busy_bever/busy_bever_3.turing", 6, 14),
+        ("examples/busy_bever/busy_bever_4.turing", 13, 107),
+        (
+            "examples/busy_bever/busy_bever_2_states_3_symbols.turing",
+            1,
+            38,
+        ),
+    ] {
+        for block_size in 1..=4 {
+            let tm = TuringMachine::new(Path::new(path));
+            let mut accelerated = MacroMachine::new(&tm, Accel::Block(block_size));
+            while accelerated.step().unwrap() {}
+            assert_eq!(accelerated.halt_reason, Some(HaltReason::Halt), "{path}");
+            assert_eq!(accelerated.num_steps, steps, "{path}, {block_size}");
+            assert_eq!(accelerated.eval_busy_bever().0, ones, "{path}");
+        }
+    }
+}
+
+#[test]
+fn test_macro_machine_tape_len() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    let mut accelerated = MacroMachine::new(&tm, Accel::Block(2));
+    assert_eq!(accelerated.tape_len(), 0);
+    while accelerated.step().unwrap() {}
+    assert_eq!(accelerated.tape_len(), 14);
+    assert!(accelerated.memory() > std::mem::size_of::<MacroMachine>());
+}
+
+#[test]
+fn test_macro_machine_cells() {
+    use std::path::Path;
+
+    use crate::encoding::Encoding;
+
+    for block_size in 1..=4 {
+        let mut tm = TuringMachine::new(Path::new("examples/arithmetic/unary_add.turing"));
+        let input = Encoding::Unary.encode(&[2, 3], tm.blank());
+        tm.set_input(&input);
+        let mut accelerated = MacroMachine::new(&tm, Accel::Block(block_size));
+        while accelerated.step().unwrap() {}
+
+        let (cells, _) = accelerated.cells().unwrap();
+        assert_eq!(Encoding::Unary.decode(&cells.into(), 0), Ok(vec![5]));
+    }
+}
+
+#[test]
+fn test_macro_machine_busy_bever_5() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new(
+        "examples/busy_bever/busy_bever_5_best_currently_known.turing",
+    ));
+    let mut accelerated = MacroMachine::new(&tm, Accel::Auto);
+    while accelerated.step().unwrap() {}
+
+    assert_eq!(accelerated.num_steps, 47_176_870);
+    assert_eq!(accelerated.eval_busy_bever().0, 4098);
+    assert!(accelerated.macro_steps < 1_000_000);
+}
+
+#[test]
+fn test_rechunk() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new(
+        "examples/busy_bever/busy_bever_5_best_currently_known.turing",
+    ));
+    let mut accelerated = MacroMachine::new(&tm, Accel::Block(1));
+    for _ in 0..20_000 {
+        accelerated.step().unwrap();
+    }
+    accelerated.rechunk(3);
+
+    assert_eq!(accelerated.block_size(), 3);
+    while accelerated.step().unwrap() {}
+    assert_eq!(accelerated.num_steps, 47_176_870);
+    assert_eq!(accelerated.eval_busy_bever().0, 4098);
+}
+
+#[test]
+fn test_tape_period() {
+    assert_eq!(tape_period(&[1; 40], 20), 1);
+    let tape: Vec<TapeEntry> = (0..60).map(|i| (i % 3 != 0) as TapeEntry).collect();
+    assert_eq!(tape_period(&tape, 30), 3);
+    assert_eq!("auto".parse(), Ok(Accel::Auto));
+    assert_eq!("3".parse(), Ok(Accel::Block(3)));
+    assert!("0".parse::<Accel>().is_err());
+}
+
+#[test]
+fn test_runs_forever() {
+    let tm = TuringMachine::from_instructions(
+        vec!["A".to_string()],
+        vec![Instruction {
+            state: 0,
+            entry: 0,
+            new_state: Some(0),
+            halt: HaltReason::Halt,
+            new_entry: 1,
+            direction: Direction::Right,
+        }],
+    );
+    let mut accelerated = MacroMachine::new(&tm, Accel::Block(2));
+    while accelerated.step().unwrap() {}
+    assert_eq!(accelerated.proof, Some(Proof::EndlessSweep));
+}
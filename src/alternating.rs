@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    scan,
+    turing::{HaltReason, TapeEntry, TuringMachine},
+};
+
+/// Whether an alternating machine accepts from a configuration.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Value {
+    Accept,
+    Reject,
+    /// A budget ran out before the value was known.
+    Unknown,
+}
+
+/// A configuration relative to the starting cell, with the tape cut down to
+/// the written cells and the head, so configurations that only differ in
+/// blanks are the same.
+type Key = (usize, isize, usize, Vec<TapeEntry>);
+
+fn key(tm: &TuringMachine, state: usize) -> Key {
+    let tape = tm.tape();
+    let head = tm.head();
+    let (start, end) = match scan::written(tape, tm.blank()) {
+        Some((first, last)) => (first.min(head), last.max(head)),
+        None => (head, head),
+    };
+    (
+        state,
+        start as isize - tm.origin() as isize,
+        head - start,
+        tape.range(start..=end).copied().collect(),
+    )
+}
+
+/// The result of [`evaluate`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Evaluation {
+    pub value: Value,
+    /// Configurations evaluated, each once thanks to the memoization.
+    pub configurations: usize,
+}
+
+/// Evaluates the game tree of the alternating machine `tm` from its current
+/// configuration.
+///
+/// Universal states (see [`TuringMachine::set_universal`]) accept if all
+/// their branches accept, existential ones if any branch does, and a
+/// configuration without an instruction rejects. A branch running forever
+/// rejects, so configurations that come back to themselves only accept if
+/// they do through another branch. Values are memoized per configuration.
+///
+/// The tree is searched with a depth limit doubling up to `max_depth`
+/// steps, so shallow accepting or rejecting branches are found before deep
+/// ones. Branches beyond that and the configurations beyond the first
+/// `max_configurations` are [`Value::Unknown`].
+pub fn evaluate(tm: &TuringMachine, max_depth: u128, max_configurations: usize) -> Evaluation {
+    let mut evaluator = Evaluator {
+        memo: HashMap::new(),
+        path: HashSet::new(),
+        max_depth: 1,
+        max_configurations,
+        configurations: 0,
+    };
+    let mut tm = tm.clone();
+    tm.set_reject_undefined(true);
+    loop {
+        evaluator.max_depth = evaluator.max_depth.min(max_depth);
+        let value = evaluator.evaluate(tm.clone());
+        if value != Value::Unknown
+            || evaluator.max_depth == max_depth
+            || evaluator.configurations >= max_configurations
+        {
+            return Evaluation {
+                value,
+                configurations: evaluator.configurations,
+            };
+        }
+        evaluator.max_depth = evaluator.max_depth.saturating_mul(2);
+    }
+}
+
+struct Evaluator {
+    /// Values known for sure.
+    memo: HashMap<Key, Value>,
+    /// Configurations being evaluated, on the way from the start.
+    path: HashSet<Key>,
+    max_depth: u128,
+    max_configurations: usize,
+    configurations: usize,
+}
+
+/// A configuration whose branches are being evaluated.
+struct Frame {
+    tm: TuringMachine,
+    key: Key,
+    depth: u128,
+    choices: Vec<usize>,
+    next: usize,
+    /// The value of a branch deciding the value of the configuration.
+    decisive: Value,
+    value: Value,
+    exact: bool,
+}
+
+impl Frame {
+    /// Takes in the value of the next branch, giving whether that decided
+    /// the configuration.
+    fn merge(&mut self, (value, exact): (Value, bool)) -> bool {
+        self.exact &= exact;
+        if value == self.decisive {
+            (self.value, self.exact) = (value, exact);
+            return true;
+        }
+        if value == Value::Unknown {
+            self.value = Value::Unknown;
+        }
+        false
+    }
+}
+
+impl Evaluator {
+    /// The value of the configuration of `tm`, with an explicit stack
+    /// instead of recursion so deep trees don't overflow the stack.
+    fn evaluate(&mut self, tm: TuringMachine) -> Value {
+        let mut stack = vec![];
+        let mut result = match self.open(tm, 0) {
+            Ok(result) => return result.0,
+            Err(frame) => {
+                stack.push(frame);
+                None
+            }
+        };
+        while let Some(frame) = stack.last_mut() {
+            let decided = match result.take() {
+                Some(result) => frame.merge(result),
+                None => false,
+            };
+            if !decided && frame.next < frame.choices.len() {
+                let mut branch = frame.tm.clone();
+                branch.step_with(frame.choices[frame.next]);
+                frame.next += 1;
+                match self.open(branch, frame.depth + 1) {
+                    Ok(value) => result = Some(value),
+                    Err(frame) => stack.push(frame),
+                }
+                continue;
+            }
+            let frame = stack.pop().expect("the frame is on the stack");
+            result = Some(self.close(frame));
+        }
+        result.expect("the start has a value").0
+    }
+
+    /// The value of the configuration of `tm` and whether it is exact, if
+    /// it is known without looking at its branches, or a frame to look at
+    /// them.
+    fn open(&mut self, tm: TuringMachine, depth: u128) -> Result<(Value, bool), Box<Frame>> {
+        let Some(state) = tm.state() else {
+            return match tm.halt_reason {
+                Some(HaltReason::Accept | HaltReason::Halt) => Ok((Value::Accept, true)),
+                _ => Ok((Value::Reject, true)),
+            };
+        };
+        let key = key(&tm, state);
+        if let Some(value) = self.memo.get(&key) {
+            return Ok((*value, true));
+        }
+        if self.path.contains(&key) {
+            return Ok((Value::Reject, false));
+        }
+        if depth >= self.max_depth || self.configurations >= self.max_configurations {
+            return Ok((Value::Unknown, false));
+        }
+        self.configurations += 1;
+
+        let choices = tm.choices();
+        if choices.is_empty() {
+            self.memo.insert(key, Value::Reject);
+            return Ok((Value::Reject, true));
+        }
+        // A rejecting branch decides a universal state, an accepting one an
+        // existential state.
+        let (decisive, otherwise) = match tm.is_universal(state) {
+            true => (Value::Reject, Value::Accept),
+            false => (Value::Accept, Value::Reject),
+        };
+        self.path.insert(key.clone());
+        Err(Box::new(Frame {
+            tm,
+            key,
+            depth,
+            choices,
+            next: 0,
+            decisive,
+            value: otherwise,
+            exact: true,
+        }))
+    }
+
+    /// The value of a configuration whose branches were all looked at, or
+    /// one that decided it. Values that assumed a configuration on the path
+    /// rejects, or that ran into a budget, may change and aren't memoized.
+    fn close(&mut self, frame: Box<Frame>) -> (Value, bool) {
+        self.path.remove(&frame.key);
+        // Accepting is certain even if it assumed that configurations on
+        // the path reject, as those can only turn out to accept.
+        let exact = match frame.value {
+            Value::Accept => true,
+            Value::Reject => frame.exact,
+            Value::Unknown => false,
+        };
+        if exact {
+            self.memo.insert(frame.key, frame.value);
+        }
+        (frame.value, exact)
+    }
+}
+
+#[test]
+fn test_alternating() {
+    // A existentially guesses a cell and B universally checks that there
+    // are 1s somewhere on both sides of it.
+    let tm = |word: &[TapeEntry]| {
+        let mut tm = TuringMachine::parse(
+            "%universal B\n\
+             A 0 -> A 0 R\n\
+             A 1 -> A 1 R\n\
+             A 0 -> B 0 R\n\
+             A 1 -> B 1 R\n\
+             B 0 -> L 0 L\n\
+             B 1 -> L 1 L\n\
+             B 0 -> R 0 R\n\
+             B 1 -> R 1 R\n\
+             L 0 -> L 0 L\n\
+             L 1 -> Accept 1 L\n\
+             R 0 -> R 0 R\n\
+             R 1 -> Accept 1 R",
+        )
+        .unwrap();
+        tm.set_input(word);
+        tm
+    };
+    assert!(tm(&[]).is_universal(1));
+    assert!(!tm(&[]).is_universal(0));
+
+    let evaluation = evaluate(&tm(&[1, 0, 1]), 50, 10_000);
+    assert_eq!(evaluation.value, Value::Accept);
+    assert!(evaluation.configurations > 0);
+    // Without a 1 to the right, the universal branch going right runs off
+    // forever and stops at the depth budget.
+    assert_eq!(evaluate(&tm(&[1, 0, 0]), 50, 10_000).value, Value::Unknown);
+    assert_eq!(evaluate(&tm(&[1, 0, 1]), 50, 1).value, Value::Unknown);
+
+    // Branches coming back to a configuration on the path reject.
+    let mut looping = TuringMachine::parse(
+        "%universal A\n\
+         A 0 -> B 0 R\n\
+         A 0 -> Accept 0 R\n\
+         B 0 -> A 0 L",
+    )
+    .unwrap();
+    assert_eq!(evaluate(&looping, 100, 100).value, Value::Reject);
+    looping.set_universal(Default::default());
+    assert_eq!(evaluate(&looping, 100, 100).value, Value::Accept);
+}
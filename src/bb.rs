@@ -0,0 +1,425 @@
+use std::{ops::Range, str::FromStr, time::Instant};
+
+use crate::{
+    bbchallenge,
+    decide::{self, Decision},
+    experiment::{self, Log},
+    leaderboard::Entry,
+    turing::{Direction, HaltReason, Instruction, TapeEntry, TuringMachine},
+};
+
+/// An inclusive range of numbers of states or symbols, written `2..4` or
+/// just `3`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub first: usize,
+    pub last: usize,
+}
+
+impl Span {
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        self.first..=self.last
+    }
+}
+
+impl FromStr for Span {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, last) = s.split_once("..").unwrap_or((s, s));
+        let parse = |n: &str| n.parse().map_err(|_| format!("invalid number '{n}'"));
+        let (first, last) = (parse(first)?, parse(last)?);
+        if first == 0 || first > last {
+            return Err(format!("invalid range '{s}'"));
+        }
+        Ok(Span { first, last })
+    }
+}
+
+/// Parses a range of indices like `10..20`, `10..` or `..20`.
+pub fn parse_range(s: &str) -> Result<Range<u64>, String> {
+    let (start, end) = s.split_once("..").ok_or("expected START..END")?;
+    let parse = |n: &str, default| match n {
+        "" => Ok(default),
+        _ => n.parse().map_err(|_| format!("invalid index '{n}'")),
+    };
+    Ok(parse(start, 0)?..parse(end, u64::MAX)?)
+}
+
+/// A transition of a partially defined machine: the symbol to write, the
+/// direction and the next state.
+type Transition = (TapeEntry, Direction, usize);
+
+/// Outcome of searching all machines with some number of states and
+/// symbols, see [`search`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Search {
+    pub states: usize,
+    pub symbols: usize,
+    /// The halting machine taking the most steps, and the one leaving the
+    /// most non-blank cells.
+    pub steps: Option<Entry>,
+    pub sigma: Option<Entry>,
+    pub halting: u64,
+    pub never_halting: u64,
+    /// Machines none of the deciders could decide, in the bbchallenge
+    /// format. The champions are only lower bounds if there are any.
+    pub undecided: Vec<String>,
+}
+
+/// Where a partially defined machine is after running it from the blank
+/// tape.
+enum Run {
+    /// It reached the undefined transition for `state` and `entry` after
+    /// `steps` steps, with `sigma` non-blank cells on the tape.
+    Undefined {
+        state: usize,
+        entry: TapeEntry,
+        steps: u128,
+        sigma: u128,
+    },
+    /// It was still running after the maximum number of steps.
+    Running,
+}
+
+/// A machine looked up in the enumeration instead of deciding machines.
+enum Lookup {
+    Index(u64),
+    Machine(String),
+}
+
+/// Enumerates the machines in tree normal form: starting with no
+/// transitions, a machine is run until it reaches an undefined one, which
+/// is either made the halting transition or defined in every possible way,
+/// and each of those machines is run again. Machines only differing in the
+/// names of states or non-blank symbols, or mirrored, are generated once.
+///
+/// The machines are numbered in the order they are generated, which is
+/// depth first with the halting machine before the ones defining its
+/// halting transition, and those by the next state, the symbol written and
+/// the direction, left first. A machine still running after the maximum
+/// number of steps isn't extended, so the numbering only stays the same for
+/// the same maximum.
+struct Enumeration<'a> {
+    states: usize,
+    symbols: usize,
+    max_steps: u128,
+    depth: usize,
+    search: Search,
+    log: Option<&'a mut Log>,
+    /// The first error writing the log.
+    error: Option<String>,
+    /// The indices of the machines to decide, and of the next machine.
+    range: Range<u64>,
+    next: u64,
+    lookup: Option<Lookup>,
+    found: Option<(u64, String)>,
+}
+
+impl Enumeration<'_> {
+    fn new(states: usize, symbols: usize, max_steps: u128, depth: usize) -> Self {
+        Enumeration {
+            states,
+            symbols,
+            max_steps,
+            depth,
+            search: Search {
+                states,
+                symbols,
+                steps: None,
+                sigma: None,
+                halting: 0,
+                never_halting: 0,
+                undecided: vec![],
+            },
+            log: None,
+            error: None,
+            range: 0..u64::MAX,
+            next: 0,
+            lookup: None,
+            found: None,
+        }
+    }
+
+    fn start(&mut self) {
+        self.explore(&mut vec![None; self.states * self.symbols]);
+    }
+    fn index(&self, state: usize, entry: TapeEntry) -> usize {
+        state * self.symbols + entry as usize
+    }
+
+    fn run(&self, table: &[Option<Transition>]) -> Run {
+        let mut tape: Vec<TapeEntry> = vec![0];
+        let (mut head, mut state, mut steps) = (0usize, 0usize, 0u128);
+        while steps < self.max_steps {
+            let entry = tape[head];
+            let Some((new_entry, direction, new_state)) = table[self.index(state, entry)] else {
+                return Run::Undefined {
+                    state,
+                    entry,
+                    steps,
+                    sigma: tape.iter().filter(|entry| **entry != 0).count() as u128,
+                };
+            };
+            tape[head] = new_entry;
+            state = new_state;
+            steps += 1;
+            match direction {
+                Direction::Left if head == 0 => tape.insert(0, 0),
+                Direction::Left => head -= 1,
+                Direction::Right => {
+                    head += 1;
+                    if head == tape.len() {
+                        tape.push(0);
+                    }
+                }
+            }
+        }
+        Run::Running
+    }
+
+    /// Numbers the next machine, the one of `table` halting at `halt` if
+    /// given, and gives whether to decide it.
+    fn number(&mut self, table: &[Option<Transition>], halt: Option<usize>) -> bool {
+        let index = self.next;
+        self.next += 1;
+        let Some(lookup) = &self.lookup else {
+            return self.range.contains(&index);
+        };
+        let machine = bbchallenge::format_machine(&self.machine(table, halt));
+        if match lookup {
+            Lookup::Index(wanted) => index == *wanted,
+            Lookup::Machine(wanted) => machine == *wanted,
+        } {
+            self.found = Some((index, machine));
+        }
+        false
+    }
+
+    fn log(&mut self, tm: &TuringMachine, decision: &Decision, steps: u128, start: Instant) {
+        let id = self.next - 1;
+        let Some(log) = &mut self.log else {
+            return;
+        };
+        if self.error.is_none() {
+            let time = start.elapsed();
+            self.error = log
+                .record(id, tm, decision, "simulation", steps, time)
+                .err();
+        }
+    }
+
+    fn machine(&self, table: &[Option<Transition>], halt: Option<usize>) -> TuringMachine {
+        let names = (0..self.states)
+            .map(|state| ((b'A' + state as u8) as char).to_string())
+            .collect();
+        let mut instructions = vec![];
+        for (index, transition) in table.iter().enumerate() {
+            let (state, entry) = (index / self.symbols, (index % self.symbols) as TapeEntry);
+            let (new_entry, direction, new_state) = match transition {
+                Some((new_entry, direction, new_state)) => {
+                    (*new_entry, *direction, Some(*new_state))
+                }
+                None if halt == Some(index) => (1, Direction::Right, None),
+                None => continue,
+            };
+            instructions.push(Instruction {
+                state,
+                entry,
+                new_state,
+                halt: HaltReason::Halt,
+                new_entry,
+                direction,
+            });
+        }
+        TuringMachine::from_instructions(names, instructions)
+    }
+
+    /// Records the machine halting at the undefined transition at `index`.
+    /// Ties in one function are broken by the other.
+    fn halts(
+        &mut self,
+        table: &[Option<Transition>],
+        index: usize,
+        steps: u128,
+        sigma: u128,
+        start: Instant,
+    ) {
+        self.search.halting += 1;
+        if self.log.is_some() {
+            let decision = Decision::Halts {
+                steps,
+                reason: HaltReason::Halt,
+            };
+            self.log(&self.machine(table, Some(index)), &decision, steps, start);
+        }
+        let best = |entry: &Option<Entry>| entry.as_ref().map_or((0, 0), |e| (e.steps, e.sigma));
+        let beats_steps = (steps, sigma) > best(&self.search.steps);
+        let (best_steps, best_sigma) = best(&self.search.sigma);
+        let beats_sigma = (sigma, steps) > (best_sigma, best_steps);
+        if !beats_steps && !beats_sigma {
+            return;
+        }
+        let entry = Entry::of(&self.machine(table, Some(index)), steps)
+            .expect("the machine halts within its steps");
+        if beats_steps {
+            self.search.steps = Some(entry.clone());
+        }
+        if beats_sigma {
+            self.search.sigma = Some(entry);
+        }
+    }
+
+    fn explore(&mut self, table: &mut Vec<Option<Transition>>) {
+        if self.next >= self.range.end || self.found.is_some() {
+            return;
+        }
+        let start = Instant::now();
+        let (state, entry, steps, sigma) = match self.run(table) {
+            Run::Undefined {
+                state,
+                entry,
+                steps,
+                sigma,
+            } => (state, entry, steps, sigma),
+            Run::Running => {
+                if !self.number(table, None) {
+                    return;
+                }
+                let tm = self.machine(table, None);
+                let decision = decide::decide(&tm, self.max_steps, self.depth);
+                let steps = experiment::simulated(&decision, self.max_steps);
+                self.log(&tm, &decision, steps, start);
+                match decision {
+                    Decision::NeverHalts(_) => self.search.never_halting += 1,
+                    _ => self.search.undecided.push(bbchallenge::format_machine(&tm)),
+                }
+                return;
+            }
+        };
+        let index = self.index(state, entry);
+        if self.number(table, Some(index)) {
+            // The halting transition writes a non-blank symbol.
+            self.halts(table, index, steps + 1, sigma + (entry == 0) as u128, start);
+        }
+
+        // A machine without undefined transitions left can't halt.
+        if table.iter().filter(|t| t.is_none()).count() == 1 {
+            return;
+        }
+        let used = |f: fn(&Transition) -> usize| table.iter().flatten().map(f).max();
+        let states = used(|t| t.2).unwrap_or(0).max(state) + 2;
+        let symbols = used(|t| t.0 as usize).unwrap_or(0).max(entry as usize) + 2;
+        // Mirrored machines halt alike, so the first move is to the right.
+        let directions: &[Direction] = match steps {
+            0 => &[Direction::Right],
+            _ => &[Direction::Left, Direction::Right],
+        };
+        for new_state in 0..states.min(self.states) {
+            for new_entry in 0..symbols.min(self.symbols) {
+                for direction in directions {
+                    table[index] = Some((new_entry as TapeEntry, *direction, new_state));
+                    self.explore(table);
+                }
+            }
+        }
+        table[index] = None;
+    }
+}
+
+/// Runs every machine with `states` states and `symbols` symbols from the
+/// blank tape for up to `max_steps` steps, deciding the ones still running
+/// with [`decide::decide`], and keeps the champions of both busy beaver
+/// functions. The halting transition counts as a step. Only the machines
+/// with an index in `range` are decided, and written to `log` if given
+/// with their index as the id.
+pub fn search(
+    states: usize,
+    symbols: usize,
+    max_steps: u128,
+    depth: usize,
+    range: Range<u64>,
+    log: Option<&mut Log>,
+) -> Result<Search, String> {
+    let mut enumeration = Enumeration::new(states, symbols, max_steps, depth);
+    enumeration.range = range;
+    enumeration.log = log;
+    enumeration.start();
+    match enumeration.error {
+        Some(why) => Err(why),
+        None => Ok(enumeration.search),
+    }
+}
+
+/// The machine at `index` in the enumeration of [`search`], in the
+/// bbchallenge format.
+pub fn machine_at(states: usize, symbols: usize, max_steps: u128, index: u64) -> Option<String> {
+    let mut enumeration = Enumeration::new(states, symbols, max_steps, 0);
+    enumeration.lookup = Some(Lookup::Index(index));
+    enumeration.start();
+    enumeration.found.map(|(_, machine)| machine)
+}
+
+/// The index of `machine` in the enumeration of [`search`] with `symbols`
+/// symbols, if it is one of its machines.
+pub fn index_of(machine: &str, symbols: usize, max_steps: u128) -> Result<Option<u64>, String> {
+    let tm = bbchallenge::parse_machine(machine)?;
+    let mut enumeration = Enumeration::new(tm.states().len(), symbols, max_steps, 0);
+    enumeration.lookup = Some(Lookup::Machine(bbchallenge::format_machine(&tm)));
+    enumeration.start();
+    Ok(enumeration.found.map(|(index, _)| index))
+}
+
+#[test]
+fn test_bb() {
+    assert_eq!("2..4".parse(), Ok(Span { first: 2, last: 4 }));
+    assert_eq!("3".parse(), Ok(Span { first: 3, last: 3 }));
+    assert!("4..2".parse::<Span>().is_err());
+    assert!("0..2".parse::<Span>().is_err());
+    assert_eq!(parse_range("10..20"), Ok(10..20));
+    assert_eq!(parse_range("..20"), Ok(0..20));
+    assert_eq!(parse_range("10.."), Ok(10..u64::MAX));
+    assert!(parse_range("10").is_err());
+
+    let path = std::env::temp_dir().join(format!("bb_{}.csv", std::process::id()));
+    let mut log = Log::open(Some(&path), None).unwrap().unwrap();
+    let two = search(2, 2, 1000, 10, 0..u64::MAX, Some(&mut log)).unwrap();
+    log.finish().unwrap();
+    let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+    assert_eq!(lines as u64, 1 + two.halting + two.never_halting);
+    std::fs::remove_file(&path).unwrap();
+    assert!(two.undecided.is_empty());
+    let steps = two.steps.unwrap();
+    assert_eq!((steps.steps, steps.sigma), (6, 4));
+    let two_champion = bbchallenge::parse_machine(&steps.machine).unwrap();
+    assert_eq!(two.sigma.unwrap().sigma, 4);
+
+    // The deciders leave a few binary counters with three states
+    // undecided, which don't change the champions.
+    let three = search(3, 2, 1000, 10, 0..u64::MAX, None).unwrap();
+    assert_eq!(three.undecided.len(), 4);
+    assert_eq!(three.steps.unwrap().steps, 21);
+    assert_eq!(three.sigma.unwrap().sigma, 6);
+
+    // Slices of the enumeration split it without overlapping.
+    let total = three.halting + three.never_halting + three.undecided.len() as u64;
+    let first = search(3, 2, 1000, 10, 0..1000, None).unwrap();
+    let rest = search(3, 2, 1000, 10, 1000..u64::MAX, None).unwrap();
+    let counts =
+        |search: &Search| search.halting + search.never_halting + search.undecided.len() as u64;
+    assert_eq!(counts(&first), 1000);
+    assert_eq!(counts(&rest), total - 1000);
+
+    let machine = machine_at(3, 2, 1000, 1234).unwrap();
+    assert_eq!(index_of(&machine, 2, 1000), Ok(Some(1234)));
+    assert_eq!(machine_at(2, 2, 1000, 0).as_deref(), Some("1RZ---_------"));
+    // The champions are in the enumeration, other machines aren't.
+    let champion = bbchallenge::format_machine(&two_champion);
+    assert!(index_of(&champion, 2, 1000).unwrap().is_some());
+    assert_eq!(index_of("1LB1LB_1LA1RZ", 2, 1000), Ok(None));
+
+    let three_symbols = search(2, 3, 1000, 10, 0..u64::MAX, None).unwrap();
+    assert!(three_symbols.undecided.is_empty());
+    assert_eq!(three_symbols.steps.unwrap().steps, 38);
+    assert_eq!(three_symbols.sigma.unwrap().sigma, 9);
+}
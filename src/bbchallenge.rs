@@ -0,0 +1,500 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{
+    decide::{self, Certificate, Decision, Proof},
+    holdouts::Holdout,
+    turing::{Direction, HaltReason, Instruction, TuringMachine},
+};
+
+/// Size of the header of the seed database and of each machine in it.
+const RECORD_SIZE: u64 = 30;
+
+/// Reads a machine in the standard text format of the bbchallenge project,
+/// e.g. `1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA`. States are separated by `_`
+/// and named `A`, `B`, ... in order. Each state lists a transition per
+/// symbol as the symbol to write, the direction and the next state, where
+/// `Z` halts and `---` means the transition is undefined.
+pub fn parse_machine(text: &str) -> Result<TuringMachine, String> {
+    let rows: Vec<&str> = text.trim().split('_').collect();
+    let names: Vec<String> = (0..rows.len()).map(state_name).collect();
+
+    let mut instructions = vec![];
+    for (state, row) in rows.iter().enumerate() {
+        let cells: Vec<char> = row.chars().collect();
+        if cells.is_empty() || !cells.len().is_multiple_of(3) {
+            return Err(format!("invalid transitions '{row}'"));
+        }
+        for (entry, transition) in cells.chunks(3).enumerate() {
+            if transition == ['-', '-', '-'] {
+                continue;
+            }
+            let new_entry = match transition[0].to_digit(10) {
+                Some(new_entry) => new_entry as u8,
+                None => return Err(format!("invalid symbol '{}' in '{row}'", transition[0])),
+            };
+            let direction = match transition[1] {
+                'L' => Direction::Left,
+                'R' => Direction::Right,
+                c => return Err(format!("invalid direction '{c}' in '{row}'")),
+            };
+            let new_state = match transition[2] {
+                'Z' => None,
+                c if c.is_ascii_uppercase() && ((c as u8 - b'A') as usize) < rows.len() => {
+                    Some((c as u8 - b'A') as usize)
+                }
+                c => return Err(format!("invalid state '{c}' in '{row}'")),
+            };
+            instructions.push(Instruction {
+                state,
+                entry: entry as u8,
+                new_state,
+                halt: HaltReason::Halt,
+                new_entry,
+                direction,
+            });
+        }
+    }
+    Ok(TuringMachine::from_instructions(names, instructions))
+}
+
+/// Writes a machine with states named in order in the format read by
+/// [`parse_machine`]. Every state gets a transition for each symbol up to
+/// the largest one used, and at least for `0` and `1`, so writing a parsed
+/// machine gives the same text if every state has the same number of
+/// transitions.
+pub fn format_machine(tm: &TuringMachine) -> String {
+    let symbols = tm
+        .instructions()
+        .iter()
+        .map(|i| i.entry.max(i.new_entry) + 1)
+        .max()
+        .unwrap_or(0)
+        .max(2);
+    let rows: Vec<String> = (0..tm.states().len())
+        .map(|state| {
+            (0..symbols)
+                .map(|entry| {
+                    let instruction = tm
+                        .instructions()
+                        .iter()
+                        .find(|i| i.state == state && i.entry == entry);
+                    match instruction {
+                        None => "---".to_string(),
+                        Some(instruction) => format!(
+                            "{}{}{}",
+                            instruction.new_entry,
+                            instruction.direction.letter(),
+                            instruction.new_state.map_or("Z".to_string(), state_name)
+                        ),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    rows.join("_")
+}
+
+fn state_name(state: usize) -> String {
+    ((b'A' + state as u8) as char).to_string()
+}
+
+/// Reads a machine from its 30 byte record in the seed database. For every
+/// state and symbol there are three bytes: the symbol to write, the
+/// direction (`0` for right, `1` for left) and the next state, counting
+/// from `1`. A next state of `0` marks an undefined transition.
+pub fn decode_record(record: &[u8]) -> Result<TuringMachine, String> {
+    if record.len() as u64 != RECORD_SIZE {
+        return Err(format!(
+            "a machine record has {RECORD_SIZE} bytes, not {}",
+            record.len()
+        ));
+    }
+    let mut instructions = vec![];
+    for (index, transition) in record.chunks(3).enumerate() {
+        if transition[2] == 0 {
+            continue;
+        }
+        if transition[0] > 1 || transition[1] > 1 || transition[2] > 5 {
+            return Err(format!("invalid transition {transition:?}"));
+        }
+        instructions.push(Instruction {
+            state: index / 2,
+            entry: (index % 2) as u8,
+            new_state: Some(transition[2] as usize - 1),
+            halt: HaltReason::Halt,
+            new_entry: transition[0],
+            direction: match transition[1] {
+                0 => Direction::Right,
+                _ => Direction::Left,
+            },
+        });
+    }
+    Ok(TuringMachine::from_instructions(
+        (0..5).map(state_name).collect(),
+        instructions,
+    ))
+}
+
+/// Writes a machine as a record of the seed database, as read by
+/// [`decode_record`]. Only machines with up to 5 states and the symbols `0`
+/// and `1` fit, and halting transitions have to be left undefined.
+#[allow(dead_code)]
+pub fn encode_record(tm: &TuringMachine) -> Result<Vec<u8>, String> {
+    if tm.states().len() > 5 {
+        return Err(format!("a record has 5 states, not {}", tm.states().len()));
+    }
+    let mut record = vec![0; RECORD_SIZE as usize];
+    for instruction in tm.instructions() {
+        if instruction.entry > 1 || instruction.new_entry > 1 {
+            return Err("a record only has the symbols 0 and 1".to_string());
+        }
+        let new_state = match instruction.new_state {
+            Some(new_state) => new_state as u8 + 1,
+            None => return Err("a record can't have halting transitions".to_string()),
+        };
+        let index = 3 * (2 * instruction.state + instruction.entry as usize);
+        record[index..index + 3].copy_from_slice(&[
+            instruction.new_entry,
+            match instruction.direction {
+                Direction::Right => 0,
+                Direction::Left => 1,
+            },
+            new_state,
+        ]);
+    }
+    Ok(record)
+}
+
+/// The seed database of the bbchallenge project: a 30 byte header followed
+/// by one 30 byte record per machine. Machines are read from the file as
+/// needed, so the database doesn't have to fit into memory.
+pub struct SeedDb {
+    reader: BufReader<File>,
+    len: u64,
+    next: u64,
+}
+
+impl SeedDb {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|why| why.to_string())?;
+        let size = file.metadata().map_err(|why| why.to_string())?.len();
+        if size < RECORD_SIZE || size % RECORD_SIZE != 0 {
+            return Err(format!(
+                "size of {size} bytes is not a multiple of {RECORD_SIZE}"
+            ));
+        }
+        let mut db = SeedDb {
+            reader: BufReader::new(file),
+            len: size / RECORD_SIZE - 1,
+            next: u64::MAX,
+        };
+        db.seek(0)?;
+        Ok(db)
+    }
+
+    /// Number of machines in the database.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Reads the machine with index `id`. Reading machines in order doesn't
+    /// need to seek.
+    pub fn machine(&mut self, id: u64) -> Result<TuringMachine, String> {
+        if id >= self.len {
+            return Err(format!("the database has only {} machines", self.len));
+        }
+        if id != self.next {
+            self.seek(id)?;
+        }
+        let mut record = [0; RECORD_SIZE as usize];
+        self.reader
+            .read_exact(&mut record)
+            .map_err(|why| why.to_string())?;
+        self.next = id + 1;
+        decode_record(&record)
+    }
+
+    fn seek(&mut self, id: u64) -> Result<(), String> {
+        self.reader
+            .seek(SeekFrom::Start((id + 1) * RECORD_SIZE))
+            .map_err(|why| why.to_string())?;
+        self.next = id;
+        Ok(())
+    }
+}
+
+/// Where numbered machines come from: either lines in the text format of
+/// [`parse_machine`], numbered from `0`, or the seed database.
+pub enum Machines {
+    Text(Vec<String>),
+    Db(SeedDb),
+    Holdouts(Vec<Holdout>),
+}
+
+impl Machines {
+    pub fn len(&self) -> u64 {
+        match self {
+            Machines::Text(lines) => lines.len() as u64,
+            Machines::Db(db) => db.len(),
+            Machines::Holdouts(holdouts) => holdouts.len() as u64,
+        }
+    }
+
+    /// Id of the machine at `index`. Holdouts keep the ids of the run that
+    /// left them undecided, the other inputs number their machines from 0.
+    pub fn id(&self, index: u64) -> u64 {
+        match self {
+            Machines::Holdouts(holdouts) => holdouts[index as usize].id,
+            _ => index,
+        }
+    }
+
+    pub fn machine(&mut self, id: u64) -> Result<TuringMachine, String> {
+        match self {
+            Machines::Text(lines) => match lines.get(id as usize) {
+                Some(line) => parse_machine(line),
+                None => Err("no such machine".to_string()),
+            },
+            Machines::Db(db) => db.machine(id),
+            Machines::Holdouts(holdouts) => {
+                match holdouts.binary_search_by_key(&id, |holdout| holdout.id) {
+                    Ok(index) => parse_machine(&holdouts[index].machine),
+                    Err(_) => Err("no such machine".to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// The decider that produced an entry of a decider verification file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeciderType {
+    Cycler,
+    TranslatedCycler,
+    BackwardReasoning,
+    Other(u32),
+}
+
+impl DeciderType {
+    fn from_id(id: u32) -> Self {
+        match id {
+            1 => DeciderType::Cycler,
+            2 => DeciderType::TranslatedCycler,
+            3 => DeciderType::BackwardReasoning,
+            id => DeciderType::Other(id),
+        }
+    }
+}
+
+/// One machine proven not to halt in a decider verification file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Index of the machine in the seed database.
+    pub machine_id: u32,
+    pub decider: DeciderType,
+    /// Decider specific data, e.g. the search depth of backward reasoning.
+    pub info: Vec<u8>,
+}
+
+/// Reads a decider verification file as published by bbchallenge: a
+/// big-endian `u32` entry count followed by the entries. Each entry is the
+/// machine id, the decider type and the length of the info as big-endian
+/// `u32`s, followed by the info bytes.
+pub fn read_dvf(bytes: &[u8]) -> Result<Vec<Entry>, String> {
+    let mut pos = 0;
+    let mut take = |len: usize| -> Result<&[u8], String> {
+        let field = bytes
+            .get(pos..pos + len)
+            .ok_or(format!("the file ends inside an entry at byte {pos}"))?;
+        pos += len;
+        Ok(field)
+    };
+    let word = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    let count = word(take(4)?);
+    let mut read = 4;
+    let mut entries = vec![];
+    for _ in 0..count {
+        let header = take(12)?;
+        let machine_id = word(&header[0..4]);
+        let decider = DeciderType::from_id(word(&header[4..8]));
+        let len = word(&header[8..12]) as usize;
+        entries.push(Entry {
+            machine_id,
+            decider,
+            info: take(len)?.to_vec(),
+        });
+        read += 12 + len;
+    }
+    if read != bytes.len() {
+        return Err(format!(
+            "{} bytes left after the last entry",
+            bytes.len() - read
+        ));
+    }
+    Ok(entries)
+}
+
+/// Checks the claim of `entry` that `tm` never halts by finding and
+/// verifying a proof with this crate's deciders. The proof found may be of
+/// a different kind than the decider of the entry.
+pub fn check(tm: &TuringMachine, entry: &Entry, max_steps: u128) -> Result<Proof, String> {
+    let proof = match entry.decider {
+        DeciderType::Cycler | DeciderType::TranslatedCycler => {
+            match decide::decide(tm, max_steps, 0) {
+                Decision::NeverHalts(proof) => proof,
+                Decision::Halts { steps, .. } => {
+                    return Err(format!("the machine halts after {steps} steps"))
+                }
+                Decision::Undecided => {
+                    return Err(format!("no cycle found within {max_steps} steps"))
+                }
+            }
+        }
+        DeciderType::BackwardReasoning => {
+            let depth = match entry.info.get(0..4) {
+                Some(bytes) => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                None => 0,
+            } as usize;
+            Proof::BackwardReasoning { depth }
+        }
+        DeciderType::Other(id) => return Err(format!("decider type {id} is not supported")),
+    };
+
+    let certificate = Certificate {
+        machine: tm.to_turing(),
+        proof,
+    };
+    decide::verify(tm, &certificate)?;
+    Ok(certificate.proof)
+}
+
+#[test]
+fn test_parse_machine() {
+    use std::path::Path;
+
+    let tm = parse_machine("1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA").unwrap();
+    let known = TuringMachine::new(Path::new(
+        "examples/busy_bever/busy_bever_5_best_currently_known.turing",
+    ));
+    assert_eq!(tm.to_turing(), known.to_turing());
+
+    let three_symbols = "1RB2LB1RZ_2LA2RB1LB";
+    assert_eq!(
+        format_machine(&parse_machine(three_symbols).unwrap()),
+        three_symbols
+    );
+
+    let undefined = parse_machine("1RB---_1LA1RZ").unwrap();
+    assert_eq!(undefined.instructions().len(), 3);
+    assert!(parse_machine("1RB1LX").is_err());
+    assert!(parse_machine("1RB1L").is_err());
+}
+
+#[test]
+fn test_read_dvf() {
+    let mut bytes = vec![0, 0, 0, 2];
+    bytes.extend([0, 0, 0, 7, 0, 0, 0, 1, 0, 0, 0, 0]);
+    bytes.extend([0, 0, 1, 0, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0, 5]);
+    let entries = read_dvf(&bytes).unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            Entry {
+                machine_id: 7,
+                decider: DeciderType::Cycler,
+                info: vec![],
+            },
+            Entry {
+                machine_id: 256,
+                decider: DeciderType::BackwardReasoning,
+                info: vec![0, 0, 0, 5],
+            },
+        ]
+    );
+    assert!(read_dvf(&bytes[..bytes.len() - 1]).is_err());
+
+    let cycler = parse_machine("1RB1RB_0LA1RZ").unwrap();
+    assert!(matches!(
+        check(&cycler, &entries[0], 1000),
+        Ok(Proof::Cycler { .. })
+    ));
+    let halting = parse_machine("1RB1LB_1LA1RZ").unwrap();
+    assert!(check(&halting, &entries[0], 1000).is_err());
+}
+
+#[test]
+fn test_seed_db() {
+    let mut bytes = vec![0; RECORD_SIZE as usize];
+    // 1RB1LB_1LA--- followed by 1RB1RB_0LA---
+    bytes.extend([1, 0, 2, 1, 1, 2, 1, 1, 1, 0, 0, 0]);
+    bytes.extend([0; 18]);
+    bytes.extend([1, 0, 2, 1, 0, 2, 0, 1, 1, 0, 0, 0]);
+    bytes.extend([0; 18]);
+    let path = std::env::temp_dir().join(format!("seed_db_{}", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+
+    let mut db = SeedDb::open(&path).unwrap();
+    assert_eq!(db.len(), 2);
+    let second = db.machine(1).unwrap();
+    let first = db.machine(0).unwrap();
+    assert_eq!(format_machine(&first), "1RB1LB_1LA---_------_------_------");
+    assert_eq!(
+        format_machine(&second),
+        "1RB1RB_0LA---_------_------_------"
+    );
+    assert!(db.machine(2).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    // Unlike `1RZ`, the undefined transition doesn't count as a step.
+    assert!(matches!(
+        decide::decide(&first, 1000, 0),
+        Decision::Halts { steps: 5, .. }
+    ));
+}
+
+#[test]
+fn test_round_trip() {
+    for seed in 0..2000 {
+        let mut next = crate::turing::random(seed);
+        let states = 1 + next(5);
+        let symbols = 2 + next(2);
+        let halting = next(2) == 0;
+        let rows: Vec<String> = (0..states)
+            .map(|_| {
+                (0..symbols)
+                    .map(|_| match next(8) {
+                        0 => "---".to_string(),
+                        _ => format!(
+                            "{}{}{}",
+                            next(symbols),
+                            ["L", "R"][next(2)],
+                            match next(states + halting as usize) {
+                                state if state < states => state_name(state),
+                                _ => "Z".to_string(),
+                            }
+                        ),
+                    })
+                    .collect()
+            })
+            .collect();
+        let tm = parse_machine(&rows.join("_")).unwrap();
+        let text = format_machine(&tm);
+        let parsed = parse_machine(&text).unwrap();
+        assert_eq!(parsed, tm);
+        assert_eq!(format_machine(&parsed), text);
+
+        if let Ok(record) = encode_record(&tm) {
+            let decoded = decode_record(&record).unwrap();
+            let padded = text + &"_------".repeat(5 - states);
+            assert_eq!(format_machine(&decoded), padded);
+        } else {
+            assert!(halting || symbols > 2);
+        }
+    }
+    assert!(encode_record(&parse_machine("1RB1LB_1LA1RZ").unwrap()).is_err());
+}
@@ -0,0 +1,748 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Display,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    json::Json,
+    turing::{Direction, TapeEntry, TuringMachine, DEFAULT_ENTRY},
+};
+
+/// Longest word looked for in the repeated parts of the tape.
+const MAX_PERIOD: usize = 8;
+/// Number of records kept per side of the tape.
+const MAX_RECORDS: usize = 32;
+/// Number of earlier records paired with a new one when guessing formulas.
+const MAX_SPACING: usize = 4;
+/// Most transitions and shifts simulated while proving a formula.
+const MAX_MACRO_STEPS: usize = 10_000;
+/// Most steps simulated on a single copy of a word for a shift.
+const MAX_SHIFT_STEPS: usize = 1000;
+/// Most cells behind the head carried along over repeated words.
+const MAX_CONTEXT: usize = 2;
+/// Most items on a side of the tape while proving a formula.
+const MAX_ITEMS: usize = 256;
+
+/// How often a word repeats: `slope * n + offset` times.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Count {
+    pub slope: u64,
+    pub offset: u64,
+}
+
+impl Display for Count {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.slope, self.offset) {
+            (0, offset) => write!(f, "{offset}"),
+            (1, 0) => write!(f, "n"),
+            (slope, 0) => write!(f, "{slope}n"),
+            (1, offset) => write!(f, "(n+{offset})"),
+            (slope, offset) => write!(f, "({slope}n+{offset})"),
+        }
+    }
+}
+
+/// Part of a tape formula.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Item {
+    Word(Vec<TapeEntry>),
+    Repeat(Vec<TapeEntry>, Count),
+}
+
+fn word_to_json(word: &[TapeEntry]) -> Json {
+    Json::Array(word.iter().map(|entry| (*entry).into()).collect())
+}
+
+fn word_from_json(json: &Json) -> Result<Vec<TapeEntry>, String> {
+    json.as_array()
+        .ok_or("a word is not an array")?
+        .iter()
+        .map(|entry| {
+            entry
+                .as_int()
+                .and_then(|entry| TapeEntry::try_from(entry).ok())
+                .ok_or("invalid symbol in a word".to_string())
+        })
+        .collect()
+}
+
+impl Item {
+    fn to_json(&self) -> Json {
+        match self {
+            Item::Word(word) => word_to_json(word),
+            Item::Repeat(word, count) => Json::object([
+                ("word", word_to_json(word)),
+                ("slope", count.slope.into()),
+                ("offset", count.offset.into()),
+            ]),
+        }
+    }
+
+    fn from_json(json: &Json) -> Result<Self, String> {
+        if json.as_array().is_some() {
+            return Ok(Item::Word(word_from_json(json)?));
+        }
+        let count = |key: &str| -> Result<u64, String> {
+            u64::try_from(json.int_field(key)?).map_err(|_| format!("field '{key}' is negative"))
+        };
+        let word = word_from_json(json.field("word")?)?;
+        if word.is_empty() {
+            return Err("a repeated word is empty".to_string());
+        }
+        Ok(Item::Repeat(
+            word,
+            Count {
+                slope: count("slope")?,
+                offset: count("offset")?,
+            },
+        ))
+    }
+
+    fn reversed(&self) -> Self {
+        let reverse = |word: &[TapeEntry]| word.iter().rev().copied().collect();
+        match self {
+            Item::Word(word) => Item::Word(reverse(word)),
+            Item::Repeat(word, count) => Item::Repeat(reverse(word), *count),
+        }
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let word =
+            |word: &[TapeEntry]| -> String { word.iter().map(|entry| entry.to_string()).collect() };
+        match self {
+            Item::Word(cells) => write!(f, "{}", word(cells)),
+            Item::Repeat(cells, count) => write!(f, "({})^{count}", word(cells)),
+        }
+    }
+}
+
+/// A family of configurations, one for every `n >= 0`: the machine is in
+/// `state` reading `head`, between the `left` and `right` items in tape
+/// order, with blanks beyond them.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Formula {
+    pub state: String,
+    pub head: TapeEntry,
+    pub left: Vec<Item>,
+    pub right: Vec<Item>,
+}
+
+impl Formula {
+    /// The cells left and right of the head for `n`, in tape order.
+    pub fn expand(&self, n: u64) -> (Vec<TapeEntry>, Vec<TapeEntry>) {
+        let cells = |items: &[Item]| -> Vec<TapeEntry> {
+            items
+                .iter()
+                .flat_map(|item| match item {
+                    Item::Word(word) => word.clone(),
+                    Item::Repeat(word, count) => {
+                        word.repeat((count.slope * n + count.offset) as usize)
+                    }
+                })
+                .collect()
+        };
+        (cells(&self.left), cells(&self.right))
+    }
+
+    pub fn to_json(&self) -> Json {
+        let items = |items: &[Item]| Json::Array(items.iter().map(Item::to_json).collect());
+        Json::object([
+            ("state", self.state.as_str().into()),
+            ("head", self.head.into()),
+            ("left", items(&self.left)),
+            ("right", items(&self.right)),
+        ])
+    }
+
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let items = |key: &str| -> Result<Vec<Item>, String> {
+            json.field(key)?
+                .as_array()
+                .ok_or(format!("field '{key}' is not an array"))?
+                .iter()
+                .map(Item::from_json)
+                .collect()
+        };
+        Ok(Formula {
+            state: json.str_field("state")?.to_string(),
+            head: TapeEntry::try_from(json.int_field("head")?)
+                .map_err(|_| "invalid symbol under the head".to_string())?,
+            left: items("left")?,
+            right: items("right")?,
+        })
+    }
+}
+
+impl Display for Formula {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for item in &self.left {
+            write!(f, "{item} ")?;
+        }
+        write!(f, "{}[{}]", self.state, self.head)?;
+        for item in &self.right {
+            write!(f, " {item}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The transitions of a machine by state and symbol.
+struct Rules(BTreeMap<(usize, TapeEntry), (TapeEntry, Direction, Option<usize>)>);
+
+impl Rules {
+    fn of(tm: &TuringMachine) -> Self {
+        Rules(
+            tm.instructions()
+                .iter()
+                .map(|i| ((i.state, i.entry), (i.new_entry, i.direction, i.new_state)))
+                .collect(),
+        )
+    }
+
+    /// The transition for `state` and `entry`, unless it halts or is
+    /// undefined.
+    fn get(&self, state: usize, entry: TapeEntry) -> Option<(TapeEntry, Direction, usize)> {
+        let (new_entry, direction, new_state) = self.0.get(&(state, entry))?;
+        Some((*new_entry, *direction, (*new_state)?))
+    }
+
+    /// Runs the machine on a single copy of `word` ahead of the head in
+    /// `direction`, with its nearest cell last. If the head leaves the copy
+    /// on the far side in `state` again, it does the same for any number of
+    /// copies, and then gives the changed copy behind the head with its
+    /// farthest cell first.
+    fn shift(
+        &self,
+        word: &[TapeEntry],
+        state: usize,
+        direction: Direction,
+    ) -> Option<Vec<TapeEntry>> {
+        let mut cells: Vec<TapeEntry> = word.iter().rev().copied().collect();
+        let (mut pos, mut current) = (0, state);
+        for _ in 0..MAX_SHIFT_STEPS {
+            if pos == cells.len() {
+                return (current == state).then_some(cells);
+            }
+            let (new_entry, towards, new_state) = self.get(current, cells[pos])?;
+            cells[pos] = new_entry;
+            current = new_state;
+            if towards == direction {
+                pos += 1;
+            } else {
+                pos = pos.checked_sub(1)?;
+            }
+        }
+        None
+    }
+}
+
+impl Rules {
+    /// Runs the machine on the `context` cells behind the head, the head
+    /// reading `head` in `state` and a single copy of `word` ahead of it in
+    /// `direction`, with the nearest cell of each last. If all of that
+    /// moves on by the copy, as the copy changed behind the context, it
+    /// does the same for any number of copies, and then gives the changed
+    /// copy with its farthest cell first. The head must not leave those
+    /// cells on the way.
+    fn carry(
+        &self,
+        context: &[TapeEntry],
+        head: TapeEntry,
+        state: usize,
+        word: &[TapeEntry],
+        direction: Direction,
+    ) -> Option<Vec<TapeEntry>> {
+        let mut carried = context.to_vec();
+        carried.push(head);
+        let mut cells = carried.clone();
+        cells.extend(word.iter().rev());
+        let start = context.len();
+        let (mut pos, mut current) = (start, state);
+        for _ in 0..MAX_SHIFT_STEPS {
+            let (new_entry, towards, new_state) = self.get(current, cells[pos])?;
+            cells[pos] = new_entry;
+            current = new_state;
+            if towards == direction {
+                pos += 1;
+            } else {
+                pos = pos.checked_sub(1)?;
+            }
+            if pos == cells.len() {
+                return None;
+            }
+            if current == state && pos == start + word.len() && cells[word.len()..] == carried {
+                return Some(cells[..word.len()].to_vec());
+            }
+        }
+        None
+    }
+}
+
+/// Adds `item` on the inner end of a side, merging it with the item there.
+fn push(side: &mut Vec<Item>, item: Item) {
+    match (side.last_mut(), item) {
+        (Some(Item::Word(last)), Item::Word(word)) => last.extend(word),
+        (Some(Item::Repeat(last, total)), Item::Repeat(word, count)) if *last == word => {
+            total.slope += count.slope;
+            total.offset += count.offset;
+        }
+        (_, Item::Word(word)) if word.is_empty() => {}
+        (_, item) => side.push(item),
+    }
+}
+
+/// The same side in a canonical form, so that equal families of tapes
+/// give the same items: constant copies are written out, cells are moved
+/// inwards of repeated words as far as they go, and blanks on the outer
+/// end are dropped. A cell `a` moves over `(y a)^n` as `a (y a)^n` is
+/// `(a y)^n a`.
+fn canonical(side: &[Item]) -> Vec<Item> {
+    let mut side = side.to_vec();
+    strip_blanks(&mut side);
+    let mut items = vec![];
+    for item in &side {
+        let (word, count) = match item {
+            Item::Word(word) => {
+                push(&mut items, Item::Word(word.clone()));
+                continue;
+            }
+            Item::Repeat(word, count) => (word, count),
+        };
+        let mut after = vec![];
+        if count.slope > 0 {
+            let mut rotated = word.clone();
+            while let Some(Item::Word(last)) = items.last_mut() {
+                if last.last() != rotated.last() {
+                    break;
+                }
+                after.extend(last.pop());
+                rotated.rotate_right(1);
+                if last.is_empty() {
+                    items.pop();
+                }
+            }
+            after.reverse();
+            let count = Count {
+                slope: count.slope,
+                offset: 0,
+            };
+            push(&mut items, Item::Repeat(rotated, count));
+        }
+        after.extend(word.repeat(count.offset as usize));
+        push(&mut items, Item::Word(after));
+    }
+
+    strip_blanks(&mut items);
+    items
+}
+
+/// Drops the blanks on the outer end of a side.
+fn strip_blanks(items: &mut Vec<Item>) {
+    loop {
+        match items.first_mut() {
+            Some(Item::Word(word)) => {
+                let blanks = word.iter().take_while(|entry| **entry == DEFAULT_ENTRY);
+                let blanks = blanks.count();
+                word.drain(..blanks);
+                if !word.is_empty() {
+                    break;
+                }
+            }
+            Some(Item::Repeat(word, _)) if word.iter().all(|entry| *entry == DEFAULT_ENTRY) => {}
+            _ => break,
+        }
+        items.remove(0);
+    }
+}
+
+/// A [`Formula`] being simulated. Both sides list their items from the
+/// outer end to the head, and so do the words in them.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Symbolic {
+    state: usize,
+    head: TapeEntry,
+    sides: [Vec<Item>; 2],
+}
+
+fn side(direction: Direction) -> usize {
+    match direction {
+        Direction::Left => 0,
+        Direction::Right => 1,
+    }
+}
+
+impl Symbolic {
+    fn of(tm: &TuringMachine, formula: &Formula) -> Option<Self> {
+        Some(Symbolic {
+            state: tm.states().iter().position(|s| *s == formula.state)?,
+            head: formula.head,
+            sides: [
+                formula.left.clone(),
+                formula.right.iter().rev().map(Item::reversed).collect(),
+            ],
+        })
+    }
+
+    /// The same family with `n + 1` in place of `n`.
+    fn next(&self) -> Self {
+        let mut next = self.clone();
+        for item in next.sides.iter_mut().flatten() {
+            if let Item::Repeat(_, count) = item {
+                count.offset += count.slope;
+            }
+        }
+        next
+    }
+
+    fn canonical(&self) -> Self {
+        Symbolic {
+            state: self.state,
+            head: self.head,
+            sides: [canonical(&self.sides[0]), canonical(&self.sides[1])],
+        }
+    }
+
+    /// Carries the head with a few cells behind it over the repeated word
+    /// ahead of it in `direction`, see [`Rules::carry`].
+    fn carry(&mut self, rules: &Rules, direction: Direction) -> bool {
+        let (behind, ahead) = (1 - side(direction), side(direction));
+        let Some(Item::Repeat(word, count)) = self.sides[ahead].last() else {
+            return false;
+        };
+        for length in 0..=MAX_CONTEXT {
+            let context = match self.sides[behind].last() {
+                _ if length == 0 => vec![],
+                Some(Item::Word(cells)) if cells.len() >= length => {
+                    cells[cells.len() - length..].to_vec()
+                }
+                _ => break,
+            };
+            let Some(shifted) = rules.carry(&context, self.head, self.state, word, direction)
+            else {
+                continue;
+            };
+            let count = *count;
+            self.sides[ahead].pop();
+            if length > 0 {
+                let Some(Item::Word(cells)) = self.sides[behind].last_mut() else {
+                    unreachable!("the context is taken from a word");
+                };
+                cells.truncate(cells.len() - length);
+                if cells.is_empty() {
+                    self.sides[behind].pop();
+                }
+            }
+            push(&mut self.sides[behind], Item::Repeat(shifted, count));
+            push(&mut self.sides[behind], Item::Word(context));
+            return true;
+        }
+        false
+    }
+
+    /// Does a transition, and shifts the head over repeated words ahead of
+    /// it where it passes them unchanged in its state. Before that, the
+    /// head may be [`carry`](Self::carry)ed over the word. Gives `None` if
+    /// the machine might halt or it isn't known what the head reads next.
+    fn step(&mut self, rules: &Rules) -> Option<()> {
+        let (new_entry, direction, new_state) = rules.get(self.state, self.head)?;
+        if self.carry(rules, direction) {
+            return Some(());
+        }
+        let (behind, ahead) = (1 - side(direction), side(direction));
+        push(&mut self.sides[behind], Item::Word(vec![new_entry]));
+        self.state = new_state;
+        loop {
+            let Some(item) = self.sides[ahead].pop() else {
+                self.head = DEFAULT_ENTRY;
+                return Some(());
+            };
+            match item {
+                Item::Word(mut word) => {
+                    self.head = word.pop().expect("words on the sides aren't empty");
+                    if !word.is_empty() {
+                        self.sides[ahead].push(Item::Word(word));
+                    }
+                    return Some(());
+                }
+                Item::Repeat(word, count) => {
+                    if let Some(shifted) = rules.shift(&word, self.state, direction) {
+                        push(&mut self.sides[behind], Item::Repeat(shifted, count));
+                    } else if count.offset > 0 {
+                        let rest = Count {
+                            slope: count.slope,
+                            offset: count.offset - 1,
+                        };
+                        if rest.slope > 0 || rest.offset > 0 {
+                            self.sides[ahead].push(Item::Repeat(word.clone(), rest));
+                        }
+                        self.sides[ahead].push(Item::Word(word));
+                    } else {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether the configurations described by `formula` for `n` always lead
+/// to the one for `n + 1`, found by simulating all of them at once. Words
+/// repeated `n` times are passed over with shift rules, and constant
+/// copies are taken off on the way. If the machine reaches the formula for
+/// some `n`, it never halts.
+pub fn prove(tm: &TuringMachine, formula: &Formula) -> bool {
+    let rules = Rules::of(tm);
+    let Some(mut symbolic) = Symbolic::of(tm, formula) else {
+        return false;
+    };
+    let target = symbolic.next().canonical();
+    for _ in 0..MAX_MACRO_STEPS {
+        if symbolic.step(&rules).is_none() {
+            return false;
+        }
+        if symbolic.sides.iter().any(|side| side.len() > MAX_ITEMS) {
+            return false;
+        }
+        if symbolic.canonical() == target {
+            return true;
+        }
+    }
+    false
+}
+
+/// Splits `cells` into words and words repeated at least twice in a row,
+/// looking for repeated words of `period` symbols from left to right.
+fn compress(cells: &[TapeEntry], period: usize) -> Vec<Item> {
+    let mut items = vec![];
+    let mut i = 0;
+    while i < cells.len() {
+        let word = &cells[i..cells.len().min(i + period)];
+        let copies = cells[i..]
+            .chunks_exact(period)
+            .take_while(|chunk| *chunk == word)
+            .count();
+        if copies >= 2 {
+            let count = Count {
+                slope: 0,
+                offset: copies as u64,
+            };
+            items.push(Item::Repeat(word.to_vec(), count));
+            i += copies * period;
+        } else {
+            push(&mut items, Item::Word(vec![cells[i]]));
+            i += 1;
+        }
+    }
+    items
+}
+
+/// The formula through three compressed tapes, if they only differ in how
+/// often their repeated words repeat and that grows evenly.
+fn through(tapes: [&[Item]; 3]) -> Option<Vec<Item>> {
+    let [first, second, third] = tapes;
+    if first.len() != second.len() || first.len() != third.len() {
+        return None;
+    }
+    let mut items = vec![];
+    for ((a, b), c) in first.iter().zip(second).zip(third) {
+        match (a, b, c) {
+            (Item::Word(a), Item::Word(b), Item::Word(c)) if a == b && b == c => {
+                items.push(Item::Word(a.clone()));
+            }
+            (Item::Repeat(a, i), Item::Repeat(b, j), Item::Repeat(c, k)) if a == b && b == c => {
+                let (i, j, k) = (i.offset, j.offset, k.offset);
+                if j < i || k < j || k - j != j - i {
+                    return None;
+                }
+                let count = Count {
+                    slope: j - i,
+                    offset: i,
+                };
+                items.push(Item::Repeat(a.clone(), count));
+            }
+            _ => return None,
+        }
+    }
+    Some(items)
+}
+
+/// The configuration when the head was on a new outermost cell.
+struct Record {
+    step: u128,
+    state: usize,
+    left: Vec<TapeEntry>,
+    right: Vec<TapeEntry>,
+}
+
+impl Record {
+    fn of(tm: &TuringMachine) -> Self {
+        let tape = tm.tape();
+        let head = tm.head();
+        let (first, last) = crate::scan::written(tape, tm.blank()).unwrap_or((head, head));
+        Record {
+            step: tm.num_steps,
+            state: tm.state().expect("a running machine has a state"),
+            left: tape.range(first.min(head)..head).copied().collect(),
+            right: tape
+                .range(head + 1..(last + 1).max(head + 1))
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+/// Tries to find a formula the machine reaches, built from the records
+/// `earlier`, `middle` and `latest` spaced evenly apart.
+fn guess(tm: &TuringMachine, records: [&Record; 3]) -> Option<Formula> {
+    for period in 1..=MAX_PERIOD {
+        let left = records.map(|record| compress(&record.left, period));
+        let right = records.map(|record| compress(&record.right, period));
+        let left = through([&left[0], &left[1], &left[2]]);
+        let right = through([&right[0], &right[1], &right[2]]);
+        let (Some(left), Some(right)) = (left, right) else {
+            continue;
+        };
+        let formula = Formula {
+            state: tm.states()[records[0].state].clone(),
+            head: DEFAULT_ENTRY,
+            left,
+            right,
+        };
+        if prove(tm, &formula) {
+            return Some(formula);
+        }
+    }
+    None
+}
+
+/// Runs `tm` on the blank tape for up to `max_steps` steps, looking for a
+/// bouncer: a machine moving back and forth over a tape that keeps growing
+/// in the same pattern. Whenever the head is on a new outermost cell, the
+/// configuration is compared with earlier ones in the same state on that
+/// side, and a [`Formula`] through them is guessed and [`prove`]n. Gives
+/// the step at which the machine is in the formula for `n = 0`, until
+/// `stop` is set or the tape grew past `max_tape_cells`.
+pub fn find(
+    tm: &TuringMachine,
+    max_steps: u128,
+    max_tape_cells: usize,
+    stop: &AtomicBool,
+) -> Option<(u128, Formula)> {
+    let mut tm = TuringMachine::from_instructions(tm.states().to_vec(), tm.instructions().to_vec());
+    tm.set_reject_undefined(true);
+    let position = |tm: &TuringMachine| tm.head() as isize - tm.origin() as isize;
+    let mut extremes = [0, 0];
+    let mut records: [VecDeque<Record>; 2] = [VecDeque::new(), VecDeque::new()];
+
+    while tm.num_steps < max_steps && tm.step() {
+        if tm.tape().len() > max_tape_cells
+            || tm.num_steps.is_multiple_of(4096) && stop.load(Ordering::Relaxed)
+        {
+            return None;
+        }
+        let head = position(&tm);
+        let direction = if head < extremes[0] {
+            extremes[0] = head;
+            Direction::Left
+        } else if head > extremes[1] {
+            extremes[1] = head;
+            Direction::Right
+        } else {
+            continue;
+        };
+        let records = &mut records[side(direction)];
+        let latest = Record::of(&tm);
+        let same: Vec<&Record> = records
+            .iter()
+            .rev()
+            .filter(|record| record.state == latest.state)
+            .collect();
+        for spacing in 1..=MAX_SPACING {
+            let (Some(middle), Some(earlier)) = (same.get(spacing - 1), same.get(2 * spacing - 1))
+            else {
+                break;
+            };
+            if let Some(formula) = guess(&tm, [earlier, middle, &latest]) {
+                return Some((earlier.step, formula));
+            }
+        }
+        records.push_back(latest);
+        if records.len() > MAX_RECORDS {
+            records.pop_front();
+        }
+    }
+    None
+}
+
+/// Whether `tm` is described by `formula` for `n = 0`.
+pub fn is_in(tm: &TuringMachine, formula: &Formula) -> bool {
+    let record = Record::of(tm);
+    let trim = |cells: &[TapeEntry], outer_first: bool| -> Vec<TapeEntry> {
+        let mut cells = cells.to_vec();
+        if !outer_first {
+            cells.reverse();
+        }
+        let blanks = cells.iter().take_while(|entry| **entry == DEFAULT_ENTRY);
+        cells.drain(..blanks.count());
+        cells
+    };
+    let (left, right) = formula.expand(0);
+    tm.state().map(|state| &tm.states()[state]) == Some(&formula.state)
+        && tm.tape()[tm.head()] == formula.head
+        && trim(&record.left, true) == trim(&left, true)
+        && trim(&record.right, false) == trim(&right, false)
+}
+
+#[test]
+fn test_bouncer() {
+    use crate::bbchallenge;
+
+    let count = |slope, offset| Count { slope, offset };
+    assert_eq!(
+        compress(&[1, 0, 1, 0, 1, 1, 0], 2),
+        [
+            Item::Repeat(vec![1, 0], count(0, 2)),
+            Item::Word(vec![1, 1, 0]),
+        ]
+    );
+    // Constant copies move inwards and blanks outside go away.
+    assert_eq!(
+        canonical(&[
+            Item::Word(vec![0, 1, 1]),
+            Item::Repeat(vec![1], count(1, 2)),
+            Item::Word(vec![0]),
+        ]),
+        [
+            Item::Repeat(vec![1], count(1, 0)),
+            Item::Word(vec![1, 1, 1, 1, 0]),
+        ]
+    );
+    assert_eq!(count(2, 1).to_string(), "(2n+1)");
+
+    // Moves right over a growing run of ones, adds one and walks back.
+    let tm = bbchallenge::parse_machine("1RB1LA_0LA1RB").unwrap();
+    let (start, formula) = find(&tm, 1000, usize::MAX, &AtomicBool::new(false)).unwrap();
+    assert!(prove(&tm, &formula));
+    let mut run = tm.clone();
+    run.set_reject_undefined(true);
+    while run.num_steps < start {
+        run.step();
+    }
+    assert!(is_in(&run, &formula));
+    let json = Json::parse(&formula.to_json().to_string()).unwrap();
+    assert_eq!(Formula::from_json(&json), Ok(formula.clone()));
+
+    assert_eq!(formula.to_string(), "A[0] (1)^(n+2)");
+    let mut constant = formula;
+    constant.right = vec![Item::Repeat(vec![1], count(0, 2))];
+    assert!(!prove(&tm, &constant));
+
+    let halting = bbchallenge::parse_machine("1RB1LB_1LA1RZ").unwrap();
+    assert_eq!(
+        find(&halting, 1000, usize::MAX, &AtomicBool::new(false)),
+        None
+    );
+}
@@ -0,0 +1,111 @@
+use crate::turing::TapeEntry;
+
+/// An elementary cellular automaton on a ring of cells.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Automaton {
+    /// Wolfram code: bit `4l + 2c + r` of the rule is the new value of a
+    /// cell `c` between `l` and `r`.
+    pub rule: u8,
+    pub cells: Vec<bool>,
+}
+
+impl Automaton {
+    /// An automaton of `width` cells with `start` in the middle, where
+    /// non-zero symbols are live cells.
+    pub fn new(rule: u8, width: usize, start: &[TapeEntry]) -> Result<Self, String> {
+        if start.len() > width {
+            return Err(format!(
+                "the start of {} cells doesn't fit into {} cells",
+                start.len(),
+                width
+            ));
+        }
+        let mut cells = vec![false; width];
+        let offset = (width - start.len()) / 2;
+        for (cell, symbol) in cells[offset..].iter_mut().zip(start) {
+            *cell = *symbol != 0;
+        }
+        Ok(Automaton { rule, cells })
+    }
+
+    /// Updates all cells at once, the first and the last cell being
+    /// neighbors.
+    pub fn step(&mut self) {
+        let width = self.cells.len();
+        let cells = &self.cells;
+        self.cells = (0..width)
+            .map(|index| {
+                let left = cells[(index + width - 1) % width] as u8;
+                let right = cells[(index + 1) % width] as u8;
+                let pattern = left << 2 | (cells[index] as u8) << 1 | right;
+                self.rule >> pattern & 1 == 1
+            })
+            .collect();
+    }
+}
+
+/// A row with `█` for live and a space for dead cells.
+pub fn format_row(cells: &[bool]) -> String {
+    cells
+        .iter()
+        .map(|cell| if *cell { '█' } else { ' ' })
+        .collect()
+}
+
+/// The rows of `automaton` from its current one over `steps` steps.
+pub fn space_time(automaton: &mut Automaton, steps: usize) -> Vec<Vec<bool>> {
+    let mut rows = vec![automaton.cells.clone()];
+    for _ in 0..steps {
+        automaton.step();
+        rows.push(automaton.cells.clone());
+    }
+    rows
+}
+
+/// Grayscale pixels of `rows` with live cells black on white, every cell
+/// taking `scale` by `scale` pixels. Gives the width, the height and the
+/// pixels for [`crate::png::encode_gray`].
+pub fn pixels(rows: &[Vec<bool>], scale: usize) -> (usize, usize, Vec<u8>) {
+    let width = rows.first().map_or(0, Vec::len) * scale;
+    let mut pixels = Vec::with_capacity(width * rows.len() * scale);
+    for row in rows {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|cell| std::iter::repeat_n(if *cell { 0 } else { 255 }, scale))
+            .collect();
+        for _ in 0..scale {
+            pixels.extend(&line);
+        }
+    }
+    (width, rows.len() * scale, pixels)
+}
+
+#[test]
+fn test_ca() {
+    // Rule 90 draws a Sierpinski triangle.
+    let mut automaton = Automaton::new(90, 9, &[1]).unwrap();
+    let rows: Vec<String> = space_time(&mut automaton, 3)
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| if *cell { '1' } else { '0' })
+                .collect()
+        })
+        .collect();
+    assert_eq!(rows, ["000010000", "000101000", "001000100", "010101010"]);
+
+    // Rule 110 grows to the left, and wraps around the edge.
+    let mut automaton = Automaton::new(110, 4, &[0, 0, 0, 1]).unwrap();
+    assert_eq!(format_row(&automaton.cells), "   █");
+    automaton.step();
+    assert_eq!(format_row(&automaton.cells), "  ██");
+    automaton.step();
+    assert_eq!(format_row(&automaton.cells), " ███");
+    automaton.step();
+    assert_eq!(format_row(&automaton.cells), "██ █");
+
+    let (width, height, pixels) = pixels(&[vec![true, false]], 2);
+    assert_eq!((width, height), (4, 2));
+    assert_eq!(pixels, [0, 0, 255, 255, 0, 0, 255, 255]);
+    assert!(Automaton::new(30, 2, &[1, 1, 1]).is_err());
+}
@@ -0,0 +1,175 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::Config,
+    sandbox::Limits,
+    spec::{self, Outcome, Spec},
+};
+
+/// File name of a collection, looked up in the current directory.
+pub const COLLECTION_FILE: &str = "collection.toml";
+
+/// A machine of a collection, a section of its file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Member {
+    pub name: String,
+    /// The machine file, relative to the current directory.
+    pub file: PathBuf,
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+    /// How the machine is run and what it is expected to do.
+    pub spec: Spec,
+    /// The exact number of steps it is expected to take.
+    pub steps: Option<u128>,
+}
+
+impl Member {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Runs the machine and compares it with what is expected, like
+    /// [`spec::check`] does, and the number of steps.
+    pub fn check(&self, limits: &Limits) -> Result<Outcome, String> {
+        let mut outcome = spec::check(&self.file, &self.spec, limits)?;
+        if let Some(steps) = self
+            .steps
+            .filter(|steps| outcome.failure.is_none() && *steps != outcome.steps)
+        {
+            outcome.failure = Some(format!("expected {} steps, got {}", steps, outcome.steps));
+        }
+        Ok(outcome)
+    }
+}
+
+/// A curated set of machines, like the ones of a course or of a research
+/// question, with their tags and expected results.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Collection {
+    /// In the order of their names.
+    pub members: Vec<Member>,
+}
+
+impl Collection {
+    /// Reads a collection file, whose machine files are relative to `dir`.
+    ///
+    /// Every `[name]` section is a machine with these keys:
+    ///
+    /// - `file`, the machine file, which is required,
+    /// - `tags`, an array of tags to select machines by,
+    /// - `description`, what the machine is about,
+    /// - `steps`, the exact number of steps it is expected to take,
+    ///
+    /// and the keys of a test of [`spec::parse`] for its input, step budget
+    /// and expected halting state and tape. Keys outside of a section are
+    /// defaults for every machine.
+    pub fn parse(content: &str, dir: &Path) -> Result<Self, String> {
+        let config = Config::parse(content)?;
+        let sections = config.sections();
+        let defaults = sections.get("").cloned().unwrap_or_default();
+        let mut members = vec![];
+        for (name, options) in sections.iter().filter(|(name, _)| !name.is_empty()) {
+            let error = |why: &str| format!("[{name}]: {why}");
+            let mut options = options.clone();
+            for (key, value) in &defaults {
+                options.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            let mut single = |key: &str| match options.remove(key).as_deref() {
+                None => Ok(None),
+                Some([value]) => Ok(Some(value.clone())),
+                Some(_) => Err(error(&format!("'{key}' takes a single value"))),
+            };
+            let file = single("file")?.ok_or_else(|| error("missing 'file'"))?;
+            let description = single("description")?;
+            let steps = single("steps")?
+                .map(|steps| steps.parse())
+                .transpose()
+                .map_err(|why| error(&format!("invalid steps: {why}")))?;
+            let tags = options.remove("tags").unwrap_or_default();
+            members.push(Member {
+                name: name.clone(),
+                file: dir.join(file),
+                tags,
+                description,
+                spec: spec::from_options(name, &options)?,
+                steps,
+            });
+        }
+        Ok(Collection { members })
+    }
+
+    /// Reads the collection file at `path`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|why| why.to_string())?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Collection::parse(&content, dir)
+    }
+
+    /// The members with `tag`, or all of them without one.
+    pub fn select<'a>(&'a self, tag: Option<&'a str>) -> impl Iterator<Item = &'a Member> {
+        self.members
+            .iter()
+            .filter(move |member| tag.is_none_or(|tag| member.has_tag(tag)))
+    }
+}
+
+#[test]
+fn test_collection() {
+    use crate::spec::Halt;
+    use crate::turing::HaltReason;
+
+    let collection = Collection::parse(
+        "max-steps = 1000\n\
+         \n\
+         [bb2]\n\
+         file = \"busy_bever/busy_bever_2.turing\"\n\
+         tags = [\"bb\", \"bb2\"]\n\
+         halt = \"Halt\"\n\
+         steps = 6\n\
+         \n\
+         [bb3]\n\
+         file = \"busy_bever/busy_bever_3.turing\"\n\
+         tags = [\"bb\"]\n\
+         description = \"Busy beaver with three states\"\n\
+         steps = 13\n",
+        Path::new("examples"),
+    )
+    .unwrap();
+    assert_eq!(collection.members.len(), 2);
+    let bb2 = &collection.members[0];
+    assert_eq!(
+        bb2.file,
+        Path::new("examples/busy_bever/busy_bever_2.turing")
+    );
+    assert_eq!(bb2.spec.max_steps, 1000);
+    assert_eq!(bb2.spec.halt, Some(Halt::Halted(HaltReason::Halt)));
+    assert_eq!(collection.select(Some("bb2")).count(), 1);
+    assert_eq!(collection.select(None).count(), 2);
+    assert_eq!(collection.select(Some("bb5")).count(), 0);
+
+    assert_eq!(bb2.check(&Limits::NONE).unwrap().failure, None);
+    assert_eq!(
+        collection.members[1]
+            .check(&Limits::NONE)
+            .unwrap()
+            .failure
+            .as_deref(),
+        Some("expected 13 steps, got 14")
+    );
+
+    assert_eq!(
+        Collection::parse("[a]\ntags = [\"x\"]", Path::new("")).unwrap_err(),
+        "[a]: missing 'file'"
+    );
+    assert_eq!(
+        Collection::parse(
+            "[a]\nfile = \"a.turing\"\nsteps = 1\nhalts = 1",
+            Path::new("")
+        )
+        .unwrap_err(),
+        "[a]: unknown key 'halts'"
+    );
+}
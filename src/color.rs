@@ -0,0 +1,54 @@
+use std::io::IsTerminal;
+
+/// Colors for terminal output using ANSI escape codes. Output is only
+/// colored if it goes to a terminal and neither `--no-color` is given nor
+/// the `NO_COLOR` environment variable is set.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Colors {
+    enabled: bool,
+}
+
+impl Colors {
+    pub fn new(no_color: bool) -> Self {
+        let disabled = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Colors {
+            enabled: !no_color && !disabled && std::io::stdout().is_terminal(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn off() -> Self {
+        Colors { enabled: false }
+    }
+
+    #[cfg(test)]
+    pub fn on() -> Self {
+        Colors { enabled: true }
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn bold(&self, text: &str) -> String {
+        self.paint("1", text)
+    }
+
+    /// The cell under the head, in reverse video.
+    pub fn head(&self, text: &str) -> String {
+        self.paint("7", text)
+    }
+
+    /// The transition taken last, blinking.
+    pub fn flash(&self, text: &str) -> String {
+        self.paint("5", text)
+    }
+
+    pub fn state(&self, text: &str) -> String {
+        self.paint("36", text)
+    }
+}
@@ -0,0 +1,304 @@
+/// A shell to generate a completion script for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// An option as far as completion is concerned.
+struct Opt {
+    long: String,
+    short: Option<char>,
+    help: String,
+    /// `None` for flags, otherwise the possible values, which are empty if
+    /// any value can be given.
+    values: Option<Vec<String>>,
+}
+
+/// A command or subcommand with the words that can follow it.
+struct Node {
+    /// Names of the subcommands leading to this one, starting with the
+    /// binary.
+    path: Vec<String>,
+    subcommands: Vec<(String, String)>,
+    options: Vec<Opt>,
+    /// Possible values of the positional arguments.
+    values: Vec<String>,
+}
+
+fn first_line(help: Option<&clap::builder::StyledStr>) -> String {
+    help.map(|help| help.to_string())
+        .and_then(|help| help.lines().next().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn collect(command: &clap::Command, path: Vec<String>, nodes: &mut Vec<Node>) {
+    let options = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .filter_map(|arg| {
+            let values = arg.get_action().takes_values().then(|| {
+                arg.get_possible_values()
+                    .iter()
+                    .filter(|value| !value.is_hide_set())
+                    .map(|value| value.get_name().to_string())
+                    .collect()
+            });
+            Some(Opt {
+                long: arg.get_long()?.to_string(),
+                short: arg.get_short(),
+                help: first_line(arg.get_help()),
+                values,
+            })
+        })
+        .collect();
+    let subcommands = command
+        .get_subcommands()
+        .map(|subcommand| {
+            (
+                subcommand.get_name().to_string(),
+                first_line(subcommand.get_about()),
+            )
+        })
+        .collect();
+    let values = command
+        .get_positionals()
+        .flat_map(|arg| arg.get_possible_values())
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    nodes.push(Node {
+        path: path.clone(),
+        subcommands,
+        options,
+        values,
+    });
+    for subcommand in command.get_subcommands() {
+        let mut path = path.clone();
+        path.push(subcommand.get_name().to_string());
+        collect(subcommand, path, nodes);
+    }
+}
+
+/// Generates a script completing subcommands, options and their values
+/// for `command` in `shell`. Other words complete to machine files, which
+/// end in `.turing`.
+pub fn generate(shell: Shell, command: &mut clap::Command) -> String {
+    command.build();
+    let mut nodes = vec![];
+    collect(command, vec![command.get_name().to_string()], &mut nodes);
+    match shell {
+        Shell::Bash => bash(&nodes),
+        Shell::Zsh => format!(
+            "#compdef {}\n\nautoload -U +X bashcompinit && bashcompinit\n\n{}",
+            nodes[0].path[0],
+            bash(&nodes)
+        ),
+        Shell::Fish => fish(&nodes),
+        Shell::Powershell => powershell(&nodes),
+    }
+}
+
+fn bash(nodes: &[Node]) -> String {
+    let name = &nodes[0].path[0];
+    let function = format!("_{}", name.replace('-', "_"));
+    let mut script = format!(
+        "{function}() {{\n    \
+             local cur prev path i\n    \
+             cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    \
+             prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n    \
+             path=\"{name}\"\n    \
+             for ((i = 1; i < COMP_CWORD; i++)); do\n        \
+                 case \"$path,${{COMP_WORDS[i]}}\" in\n"
+    );
+    for node in nodes {
+        for (subcommand, _) in &node.subcommands {
+            let path = node.path.join("__");
+            script +=
+                &format!("            {path},{subcommand}) path=\"{path}__{subcommand}\" ;;\n");
+        }
+    }
+    script += "        esac\n    done\n\n    case \"$path,$prev\" in\n";
+    for node in nodes {
+        for option in &node.options {
+            let candidates = match &option.values {
+                None => continue,
+                Some(values) if values.is_empty() => "$(compgen -f -- \"$cur\")".to_string(),
+                Some(values) => format!("$(compgen -W \"{}\" -- \"$cur\")", values.join(" ")),
+            };
+            let path = node.path.join("__");
+            let mut patterns = vec![format!("{path},--{}", option.long)];
+            patterns.extend(option.short.map(|short| format!("{path},-{short}")));
+            script += &format!(
+                "        {}) COMPREPLY=({candidates}); return ;;\n",
+                patterns.join("|")
+            );
+        }
+    }
+    script += "    esac\n\n    local words\n    case \"$path\" in\n";
+    for node in nodes {
+        let mut words: Vec<String> = node
+            .options
+            .iter()
+            .map(|option| format!("--{}", option.long))
+            .collect();
+        words.extend(node.subcommands.iter().map(|(name, _)| name.clone()));
+        words.extend(node.values.iter().cloned());
+        script += &format!(
+            "        {}) words=\"{}\" ;;\n",
+            node.path.join("__"),
+            words.join(" ")
+        );
+    }
+    script += &format!(
+        "    esac\n    \
+         if [[ \"$cur\" == -* ]]; then\n        \
+             COMPREPLY=($(compgen -W \"$words\" -- \"$cur\"))\n    \
+         else\n        \
+             COMPREPLY=($(compgen -W \"$words\" -- \"$cur\" | grep -v '^-') \
+         $(compgen -f -X '!*.turing' -- \"$cur\") $(compgen -d -- \"$cur\"))\n    \
+         fi\n\
+         }}\n\n\
+         complete -o filenames -F {function} {name}\n"
+    );
+    script
+}
+
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish(nodes: &[Node]) -> String {
+    let name = &nodes[0].path[0];
+    let mut script = format!("complete -c {name} -f -a '(__fish_complete_suffix .turing)'\n");
+    for node in nodes {
+        let condition = match node.path.last() {
+            Some(last) if node.path.len() > 1 => format!("__fish_seen_subcommand_from {last}"),
+            _ => "__fish_use_subcommand".to_string(),
+        };
+        for (subcommand, help) in &node.subcommands {
+            script += &format!(
+                "complete -c {name} -n '{condition}' -a {} -d {}\n",
+                fish_quote(subcommand),
+                fish_quote(help)
+            );
+        }
+        if !node.values.is_empty() {
+            script += &format!(
+                "complete -c {name} -n '{condition}' -a {}\n",
+                fish_quote(&node.values.join(" "))
+            );
+        }
+        for option in &node.options {
+            script += &format!("complete -c {name} -n '{condition}' -l {}", option.long);
+            if let Some(short) = option.short {
+                script += &format!(" -s {short}");
+            }
+            match &option.values {
+                None => {}
+                Some(values) if values.is_empty() => script += " -r -F",
+                Some(values) => script += &format!(" -x -a {}", fish_quote(&values.join(" "))),
+            }
+            script += &format!(" -d {}\n", fish_quote(&option.help));
+        }
+    }
+    script
+}
+
+fn powershell_list(words: &[String]) -> String {
+    let words: Vec<String> = words
+        .iter()
+        .map(|word| format!("'{}'", word.replace('\'', "''")))
+        .collect();
+    format!("@({})", words.join(", "))
+}
+
+fn powershell(nodes: &[Node]) -> String {
+    let name = &nodes[0].path[0];
+    let mut words = String::new();
+    let mut values = String::new();
+    for node in nodes {
+        let path = node.path.join(";");
+        let mut candidates: Vec<String> = node
+            .options
+            .iter()
+            .map(|option| format!("--{}", option.long))
+            .collect();
+        candidates.extend(node.subcommands.iter().map(|(name, _)| name.clone()));
+        candidates.extend(node.values.iter().cloned());
+        words += &format!("        '{path}' = {}\n", powershell_list(&candidates));
+        for option in &node.options {
+            if let Some(option_values) = option.values.as_ref().filter(|v| !v.is_empty()) {
+                values += &format!(
+                    "        '{path};--{}' = {}\n",
+                    option.long,
+                    powershell_list(option_values)
+                );
+            }
+        }
+    }
+    format!(
+        "Register-ArgumentCompleter -Native -CommandName '{name}' -ScriptBlock {{\n    \
+             param($wordToComplete, $commandAst, $cursorPosition)\n    \
+             $words = @{{\n{words}    }}\n    \
+             $values = @{{\n{values}    }}\n    \
+             $path = '{name}'\n    \
+             $prev = ''\n    \
+             foreach ($element in $commandAst.CommandElements | Select-Object -Skip 1) {{\n        \
+                 if ($element.Extent.StartOffset -ge $cursorPosition) {{ break }}\n        \
+                 $word = $element.ToString()\n        \
+                 if ($word -eq $wordToComplete) {{ break }}\n        \
+                 if ($words.ContainsKey(\"$path;$word\")) {{ $path = \"$path;$word\" }}\n        \
+                 $prev = $word\n    \
+             }}\n    \
+             $candidates = $words[$path]\n    \
+             if ($values.ContainsKey(\"$path;$prev\")) {{ $candidates = $values[\"$path;$prev\"] }}\n    \
+             else {{ $candidates += Get-ChildItem -Name -Filter \"$wordToComplete*.turing\" }}\n    \
+             $candidates | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{\n        \
+                 [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n    \
+             }}\n\
+         }}\n"
+    )
+}
+
+#[test]
+fn test_completions() {
+    use clap::{Arg, ArgAction, Command};
+
+    let mut command = Command::new("touring")
+        .arg(
+            Arg::new("quiet")
+                .long("quiet")
+                .short('q')
+                .action(ArgAction::SetTrue),
+        )
+        .subcommand(
+            Command::new("batch").about("Decide many machines").arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_parser(["text", "json"]),
+            ),
+        );
+
+    let bash = generate(Shell::Bash, &mut command);
+    assert!(bash.contains("touring,batch) path=\"touring__batch\" ;;"));
+    assert!(bash.contains(
+        "touring__batch,--format) COMPREPLY=($(compgen -W \"text json\" -- \"$cur\")); return ;;"
+    ));
+    assert!(bash.contains("touring) words=\"--quiet --help batch help\" ;;"));
+    assert!(bash.ends_with("complete -o filenames -F _touring touring\n"));
+    assert!(generate(Shell::Zsh, &mut command).starts_with("#compdef touring\n"));
+
+    let fish = generate(Shell::Fish, &mut command);
+    assert!(fish.contains(
+        "complete -c touring -n '__fish_use_subcommand' -a 'batch' -d 'Decide many machines'\n"
+    ));
+    assert!(fish.contains(
+        "complete -c touring -n '__fish_seen_subcommand_from batch' -l format -x -a 'text json'"
+    ));
+
+    let powershell = generate(Shell::Powershell, &mut command);
+    assert!(powershell.contains("'touring;batch;--format' = @('text', 'json')"));
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// File name of the configuration of a project, looked up in the current
+/// directory and its parents.
+const PROJECT_FILE: &str = "touring.toml";
+
+/// Defaults for command line options, read from `touring.toml` files.
+///
+/// The files use a small part of TOML: `key = value` lines, where the key
+/// is the long name of an option and the value a string, number, boolean
+/// or array of them, and `[command]` sections, named like `[batch]` or
+/// `[leaderboard.add]`, for options of a single command. Options outside of
+/// a section apply to every command that has them. Options given on the
+/// command line take precedence.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Values of the options of every section, where `""` holds the options
+    /// outside of a section.
+    sections: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl Config {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut sections: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+        let mut section = String::new();
+        for (number, line) in content.lines().enumerate() {
+            let error = |why: &str| format!("line {}: {}", number + 1, why);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                let name = name
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("expected ']'"))?;
+                section = name.trim().to_string();
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected 'key = value'"))?;
+            let values = parse_value(value.trim()).map_err(|why| error(&why))?;
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_string(), values);
+        }
+        Ok(Config { sections })
+    }
+
+    /// Values of the options of every section, where `""` holds the options
+    /// outside of a section.
+    pub fn sections(&self) -> &BTreeMap<String, BTreeMap<String, Vec<String>>> {
+        &self.sections
+    }
+
+    /// Reads the configuration of the user from
+    /// `~/.config/touring/config.toml` and the one of the project from the
+    /// nearest `touring.toml`, whose options take precedence. Missing files
+    /// are skipped.
+    pub fn load() -> Result<Self, String> {
+        let mut config = Config::default();
+        let user = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(|dir| dir.join("touring").join("config.toml"));
+        let project = std::env::current_dir().ok().and_then(|dir| {
+            dir.ancestors()
+                .map(|dir| dir.join(PROJECT_FILE))
+                .find(|path| path.is_file())
+        });
+        for path in user.into_iter().chain(project) {
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(why) if why.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(why) => return Err(format!("{}: {}", path.display(), why)),
+            };
+            let other =
+                Config::parse(&content).map_err(|why| format!("{}: {}", path.display(), why))?;
+            for (section, options) in other.sections {
+                config.sections.entry(section).or_default().extend(options);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Sets the options of `command` and its subcommands to the values of
+    /// the configuration as their defaults.
+    pub fn apply(&self, command: clap::Command) -> Result<clap::Command, String> {
+        let mut used = BTreeSet::new();
+        let command = self.apply_to(command, "", &mut used)?;
+        for (section, options) in &self.sections {
+            for key in options.keys() {
+                if !used.contains(&(section.clone(), key.clone())) {
+                    return match section.as_str() {
+                        "" => Err(format!("no command has an option '{key}'")),
+                        _ => Err(format!("[{section}] has no option '{key}'")),
+                    };
+                }
+            }
+        }
+        Ok(command)
+    }
+
+    fn apply_to(
+        &self,
+        mut command: clap::Command,
+        section: &str,
+        used: &mut BTreeSet<(String, String)>,
+    ) -> Result<clap::Command, String> {
+        let options: Vec<(String, String)> = command
+            .get_arguments()
+            .filter_map(|arg| Some((arg.get_id().to_string(), arg.get_long()?.to_string())))
+            .collect();
+        for (id, long) in options {
+            // Options of the section take precedence over the global ones.
+            let values = [section, ""].into_iter().find_map(|name| {
+                let values = self.sections.get(name)?.get(&long)?;
+                used.insert((name.to_string(), long.clone()));
+                Some(values)
+            });
+            let values = match values {
+                Some(values) => values,
+                None => continue,
+            };
+            if let Some(arg) = command
+                .get_arguments()
+                .find(|arg| arg.get_id() == id.as_str())
+            {
+                for value in values {
+                    check(arg, &long, value)?;
+                }
+            }
+            // Defaults have to outlive the command, which lives as long as
+            // the program does.
+            let values: Vec<&'static str> = values
+                .iter()
+                .map(|value| &*Box::leak(value.clone().into_boxed_str()))
+                .collect();
+            command = command.mut_arg(id, |arg| arg.default_values(values));
+        }
+
+        let subcommands: Vec<String> = command
+            .get_subcommands()
+            .map(|subcommand| subcommand.get_name().to_string())
+            .collect();
+        for name in subcommands {
+            let path = match section {
+                "" => name.clone(),
+                _ => format!("{section}.{name}"),
+            };
+            let mut result = Ok(());
+            command = command.mut_subcommand(&name, |subcommand| {
+                match self.apply_to(subcommand.clone(), &path, used) {
+                    Ok(subcommand) => subcommand,
+                    Err(why) => {
+                        result = Err(why);
+                        subcommand
+                    }
+                }
+            });
+            result?;
+        }
+        Ok(command)
+    }
+}
+
+/// Checks `value` the way clap would when it's given for `arg` on the
+/// command line.
+fn check(arg: &clap::Arg, long: &str, value: &str) -> Result<(), String> {
+    let mut option = clap::Arg::new(arg.get_id().clone())
+        .long(&*Box::leak(long.to_string().into_boxed_str()))
+        .value_parser(arg.get_value_parser().clone())
+        .action(clap::ArgAction::Append);
+    if let Some(names) = arg.get_value_names() {
+        option = option.value_names(names.to_vec());
+    }
+    clap::Command::new("touring")
+        .no_binary_name(true)
+        .arg(option)
+        .try_get_matches_from([format!("--{long}={value}")])
+        .map_err(|why| {
+            let why = why.to_string();
+            let why = why.lines().next().unwrap_or_default();
+            why.trim_start_matches("error: ").to_string()
+        })?;
+    Ok(())
+}
+
+/// Cuts off a `#` comment that isn't part of a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Parses a value into the strings clap would get on the command line.
+fn parse_value(value: &str) -> Result<Vec<String>, String> {
+    match value.strip_prefix('[') {
+        Some(items) => {
+            let items = items.strip_suffix(']').ok_or("expected ']'")?;
+            let mut values = vec![];
+            for item in split_items(items)? {
+                let item = item.trim();
+                if item.is_empty() {
+                    continue;
+                }
+                values.push(parse_scalar(item)?);
+            }
+            Ok(values)
+        }
+        None => Ok(vec![parse_scalar(value)?]),
+    }
+}
+
+/// Splits the items of an array on the commas outside of strings.
+fn split_items(items: &str) -> Result<Vec<&str>, String> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in items.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(&items[start..i]);
+                start = i + 1;
+            }
+            '[' if !quoted => return Err("nested arrays aren't supported".to_string()),
+            _ => {}
+        }
+    }
+    parts.push(&items[start..]);
+    Ok(parts)
+}
+
+fn parse_scalar(value: &str) -> Result<String, String> {
+    if let Some(string) = value.strip_prefix('"') {
+        let string = string
+            .strip_suffix('"')
+            .ok_or_else(|| format!("unterminated string {value}"))?;
+        let mut unescaped = String::new();
+        let mut chars = string.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('"') => unescaped.push('"'),
+                Some('\\') => unescaped.push('\\'),
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                _ => return Err(format!("invalid escape in {value}")),
+            }
+        }
+        return Ok(unescaped);
+    }
+    match value {
+        "true" | "false" => Ok(value.to_string()),
+        _ if value.parse::<f64>().is_ok() || value.replace('_', "").parse::<i128>().is_ok() => {
+            Ok(value.replace('_', ""))
+        }
+        _ => Err(format!("invalid value {value}, strings need quotes")),
+    }
+}
+
+#[test]
+fn test_config() {
+    use clap::{Arg, ArgAction, Command};
+
+    let config = Config::parse(
+        "# defaults\n\
+         max-steps = 1_000 # everywhere\n\
+         quiet = true\n\
+         \n\
+         [batch]\n\
+         max-steps = 5\n\
+         accel = [\"2\", \"auto\"]\n\
+         name = \"a # b\"\n",
+    )
+    .unwrap();
+    let steps = || {
+        Arg::new("max_steps")
+            .long("max-steps")
+            .value_parser(clap::value_parser!(u128))
+            .default_value("10")
+    };
+    let command = Command::new("touring")
+        .arg(Arg::new("quiet").long("quiet").action(ArgAction::SetTrue))
+        .subcommand(Command::new("accept").arg(steps()))
+        .subcommand(
+            Command::new("batch")
+                .arg(steps())
+                .arg(Arg::new("accel").long("accel").num_args(1..))
+                .arg(Arg::new("name").long("name")),
+        );
+    let command = config.apply(command).unwrap();
+
+    let matches = command.clone().get_matches_from(["touring"]);
+    assert!(matches.get_flag("quiet"));
+    let accept = command.clone().get_matches_from(["touring", "accept"]);
+    let accept = accept.subcommand_matches("accept").unwrap();
+    assert_eq!(accept.get_one::<u128>("max_steps"), Some(&1000));
+    let batch = command.clone().get_matches_from(["touring", "batch"]);
+    let batch = batch.subcommand_matches("batch").unwrap();
+    assert_eq!(batch.get_one::<u128>("max_steps"), Some(&5));
+    let accel: Vec<&String> = batch.get_many("accel").unwrap().collect();
+    assert_eq!(accel, ["2", "auto"]);
+    assert_eq!(batch.get_one::<String>("name").unwrap(), "a # b");
+    let given = command.get_matches_from(["touring", "batch", "--max-steps", "7"]);
+    let given = given.subcommand_matches("batch").unwrap();
+    assert_eq!(given.get_one::<u128>("max_steps"), Some(&7));
+
+    let unknown = Config::parse("[accept]\nname = \"x\"").unwrap();
+    assert_eq!(
+        unknown
+            .apply(Command::new("touring").subcommand(Command::new("accept")))
+            .err(),
+        Some("[accept] has no option 'name'".to_string())
+    );
+    let invalid = Config::parse("max-steps = -1").unwrap();
+    let command = Command::new("touring").subcommand(Command::new("accept").arg(steps()));
+    assert_eq!(
+        invalid.apply(command).err(),
+        Some(
+            "invalid value '-1' for '--max-steps <max_steps>': invalid digit found in string"
+                .to_string()
+        )
+    );
+    assert!(Config::parse("max-steps = many").is_err());
+}
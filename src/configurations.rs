@@ -0,0 +1,187 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::turing::{EdgeBehavior, HaltReason, TapeEntry, TuringMachine};
+
+/// A configuration of a machine on a bounded tape.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Configuration {
+    /// `None` once the machine halted.
+    pub state: Option<usize>,
+    pub halt: Option<HaltReason>,
+    pub head: usize,
+    pub tape: Vec<TapeEntry>,
+    /// Fewest steps from the start to reach it.
+    pub steps: u128,
+}
+
+impl Configuration {
+    fn of(tm: &TuringMachine, cells: usize) -> Self {
+        let mut tape: Vec<TapeEntry> = tm.tape().iter().copied().collect();
+        tape.resize(tape.len().max(cells), tm.blank());
+        Configuration {
+            state: tm.state(),
+            halt: tm.halt_reason.filter(|_| tm.is_halted()),
+            head: tm.head(),
+            tape,
+            steps: tm.num_steps,
+        }
+    }
+
+    /// What tells configurations apart, which is all but the steps.
+    fn key(&self) -> (Option<usize>, Option<HaltReason>, usize, Vec<TapeEntry>) {
+        (self.state, self.halt, self.head, self.tape.clone())
+    }
+}
+
+/// All configurations reachable from the start, with a transition from
+/// every configuration to each of its successors.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Graph {
+    /// In breadth-first order, starting with the start configuration.
+    pub configurations: Vec<Configuration>,
+    pub transitions: Vec<(usize, usize)>,
+    /// Whether all reachable configurations were found, and not only up to
+    /// the maximum.
+    pub complete: bool,
+}
+
+/// Builds the graph of the configurations `tm` reaches from `input` on a
+/// tape of `cells` cells, where moving off the tape is handled by `edge`,
+/// by a breadth-first search. Non-deterministic machines have a successor
+/// for every matching instruction, and missing instructions reject.
+///
+/// A tape of `n` cells over `m` symbols gives at most `states · n · mⁿ`
+/// configurations, and the search stops after `max_configurations`.
+pub fn explore(
+    tm: &TuringMachine,
+    input: &[TapeEntry],
+    cells: usize,
+    edge: EdgeBehavior,
+    max_configurations: usize,
+) -> Graph {
+    let mut start = tm.clone();
+    start.set_reject_undefined(true);
+    start.set_input(input);
+    start.set_bound(Some((cells, edge)));
+
+    let mut graph = Graph {
+        configurations: vec![Configuration::of(&start, cells)],
+        transitions: vec![],
+        complete: true,
+    };
+    let mut index = HashMap::from([(graph.configurations[0].key(), 0)]);
+    let mut machines = vec![Some(start)];
+    let mut next = 0;
+    while next < machines.len() {
+        let tm = machines[next].take().unwrap();
+        let mut successors = vec![];
+        match tm.choices().as_slice() {
+            _ if tm.is_halted() => {}
+            [] => {
+                let mut successor = tm.clone();
+                successor.step();
+                successors.push(successor);
+            }
+            choices => {
+                for choice in choices {
+                    let mut successor = tm.clone();
+                    successor.step_with(*choice);
+                    successors.push(successor);
+                }
+            }
+        }
+        for successor in successors {
+            let configuration = Configuration::of(&successor, cells);
+            let target = match index.get(&configuration.key()) {
+                Some(target) => *target,
+                None if graph.configurations.len() >= max_configurations => {
+                    graph.complete = false;
+                    continue;
+                }
+                None => {
+                    index.insert(configuration.key(), graph.configurations.len());
+                    graph.configurations.push(configuration);
+                    machines.push(Some(successor));
+                    graph.configurations.len() - 1
+                }
+            };
+            graph.transitions.push((next, target));
+        }
+        next += 1;
+    }
+    graph
+}
+
+impl Graph {
+    /// The halted configuration reached in the fewest steps, if any.
+    pub fn first_halt(&self) -> Option<&Configuration> {
+        self.configurations
+            .iter()
+            .find(|configuration| configuration.halt.is_some())
+    }
+
+    /// The graph in the DOT language of Graphviz, with the state and the
+    /// tape of every configuration, the head in brackets. Halted
+    /// configurations have a double border.
+    pub fn to_dot(&self, tm: &TuringMachine) -> String {
+        let mut out = String::from("digraph configurations {\n  node [shape=box];\n");
+        for (index, configuration) in self.configurations.iter().enumerate() {
+            let state = match (configuration.state, configuration.halt) {
+                (Some(state), _) => tm.states()[state].as_str(),
+                (None, Some(reason)) => reason.name(),
+                (None, None) => "",
+            };
+            let tape: Vec<String> = configuration
+                .tape
+                .iter()
+                .enumerate()
+                .map(|(cell, entry)| match cell == configuration.head {
+                    true => format!("[{entry}]"),
+                    false => entry.to_string(),
+                })
+                .collect();
+            let border = match configuration.halt {
+                Some(_) => ", peripheries=2",
+                None => "",
+            };
+            let _ = writeln!(
+                out,
+                "  c{index} [label=\"{state}\\n{}\"{border}];",
+                tape.join(" ")
+            );
+        }
+        for (from, to) in &self.transitions {
+            let _ = writeln!(out, "  c{from} -> c{to};");
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[test]
+fn test_configurations() {
+    let tm =
+        TuringMachine::parse("A 0 -> B 1 R\nA 1 -> A 0 R\nB 0 -> A 0 L\nB 1 -> B 1 R\n").unwrap();
+    // A [0] 0, B 1 [0], A [1] 0, A 0 [0], then off the edge.
+    let graph = explore(&tm, &[], 2, EdgeBehavior::Crash, 100);
+    assert!(graph.complete);
+    assert_eq!(graph.configurations.len(), 5);
+    assert_eq!(graph.transitions.len(), 4);
+    let halt = graph.first_halt().unwrap();
+    assert_eq!((halt.halt, halt.steps), (Some(HaltReason::Crash), 4));
+
+    // Staying on the tape instead, it loops without halting.
+    let graph = explore(&tm, &[], 2, EdgeBehavior::Stay, 100);
+    assert!(graph.complete);
+    assert_eq!(graph.first_halt(), None);
+    assert_eq!(graph.configurations.len(), 5);
+    assert_eq!(graph.transitions.last(), Some(&(4, 4)));
+    let dot = graph.to_dot(&tm);
+    assert!(dot.starts_with("digraph configurations {"));
+    assert!(dot.contains("  c1 [label=\"B\\n1 [0]\"];"));
+    assert!(dot.contains("  c0 -> c1;"));
+
+    let graph = explore(&tm, &[], 2, EdgeBehavior::Stay, 2);
+    assert!(!graph.complete);
+    assert_eq!(graph.configurations.len(), 2);
+}
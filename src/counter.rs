@@ -0,0 +1,429 @@
+use crate::{
+    transform::{Builder, Target},
+    turing::{strip_comment, Direction, HaltReason, TapeEntry, TuringMachine},
+};
+
+/// Most registers a program can have to be compiled into a Turing machine,
+/// as every cell holds a bit per register and a marker bit.
+pub const MAX_COMPILED_REGISTERS: usize = 7;
+
+/// An instruction of a register machine.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instruction {
+    /// Adds one to the register and goes on with instruction `next`.
+    Inc {
+        register: usize,
+        next: usize,
+    },
+    /// Goes on with instruction `zero` if the register is zero, otherwise
+    /// subtracts one from it and goes on with instruction `next`.
+    Dec {
+        register: usize,
+        next: usize,
+        zero: usize,
+    },
+    Halt,
+}
+
+/// A Minsky register machine, starting with its first instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Program {
+    /// A name per instruction, used for the states of the compiled machine.
+    pub labels: Vec<String>,
+    pub instructions: Vec<Instruction>,
+    pub registers: usize,
+}
+
+impl Program {
+    /// Reads a register machine with a labelled instruction per line, e.g.
+    ///
+    /// ```text
+    /// // adds r0 to r1
+    /// loop: dec r0 add done
+    /// add: inc r1 loop
+    /// done: halt
+    /// ```
+    ///
+    /// `dec r<k> <next> <zero>` goes on with `<zero>` if the register is
+    /// zero. Comments start with `//`.
+    pub fn parse_registers(content: &str) -> Result<Self, String> {
+        let mut lines = vec![];
+        for line in content.lines() {
+            let code = strip_comment(line).0.trim();
+            if code.is_empty() {
+                continue;
+            }
+            let (label, rest) = code
+                .split_once(':')
+                .ok_or_else(|| format!("expected 'label: instruction' in '{line}'"))?;
+            let label = label.trim();
+            if label.is_empty()
+                || label.contains(char::is_whitespace)
+                || label.contains('~')
+                || ["Halt", "Accept", "Reject"].contains(&label)
+            {
+                return Err(format!("invalid label '{label}'"));
+            }
+            lines.push((label, rest.split_whitespace().collect::<Vec<_>>(), line));
+        }
+
+        let labels: Vec<String> = lines.iter().map(|(label, ..)| label.to_string()).collect();
+        if let Some(label) = labels
+            .iter()
+            .enumerate()
+            .find_map(|(index, label)| labels[..index].contains(label).then_some(label))
+        {
+            return Err(format!("label '{label}' is used twice"));
+        }
+        let target = |label: &str| {
+            labels
+                .iter()
+                .position(|known| known == label)
+                .ok_or_else(|| format!("unknown label '{label}'"))
+        };
+        let register = |name: &str| {
+            name.strip_prefix('r')
+                .and_then(|index| index.parse::<usize>().ok())
+                .ok_or_else(|| format!("invalid register '{name}', expected r0, r1, ..."))
+        };
+        let mut instructions = vec![];
+        for (_, words, line) in &lines {
+            instructions.push(match words.as_slice() {
+                ["inc", name, next] => Instruction::Inc {
+                    register: register(name)?,
+                    next: target(next)?,
+                },
+                ["dec", name, next, zero] => Instruction::Dec {
+                    register: register(name)?,
+                    next: target(next)?,
+                    zero: target(zero)?,
+                },
+                ["halt"] => Instruction::Halt,
+                _ => return Err(format!("invalid instruction '{line}'")),
+            });
+        }
+        Ok(Program::new(labels, instructions))
+    }
+
+    /// Reads a 2-counter machine with an instruction per line, numbered
+    /// from 0, over the counters `a` and `b`:
+    ///
+    /// - `inc a` adds one to `a`,
+    /// - `dec a` subtracts one from `a` unless it is zero,
+    /// - `jz a 4` goes on with instruction 4 if `a` is zero,
+    /// - `goto 4` goes on with instruction 4,
+    /// - `halt` stops, as does running past the last instruction.
+    ///
+    /// The other instructions go on with the next line. Instructions that
+    /// aren't a single register machine instruction get a helper after all
+    /// lines, labelled like `l2b` for line 2.
+    pub fn parse_counters(content: &str) -> Result<Self, String> {
+        let lines: Vec<(Vec<&str>, &str)> = content
+            .lines()
+            .map(|line| (strip_comment(line).0.split_whitespace().collect(), line))
+            .filter(|(words, _): &(Vec<_>, _)| !words.is_empty())
+            .collect();
+        let end = lines.len();
+        let counter = |name: &str| match name {
+            "a" => Ok(0),
+            "b" => Ok(1),
+            _ => Err(format!("invalid counter '{name}', expected a or b")),
+        };
+        let line = |number: &str| match number.parse::<usize>() {
+            Ok(number) if number <= end => Ok(number),
+            _ => Err(format!("invalid line number '{number}'")),
+        };
+
+        let mut labels: Vec<String> = (0..=end).map(|line| format!("l{line}")).collect();
+        let mut instructions = vec![Instruction::Halt; end + 1];
+        for (index, (words, text)) in lines.iter().enumerate() {
+            let helper = instructions.len();
+            instructions[index] = match words.as_slice() {
+                ["inc", name] => Instruction::Inc {
+                    register: counter(name)?,
+                    next: index + 1,
+                },
+                ["dec", name] => Instruction::Dec {
+                    register: counter(name)?,
+                    next: index + 1,
+                    zero: index + 1,
+                },
+                ["jz", name, target] => {
+                    // Subtracting found the counter non-zero, so add back.
+                    let register = counter(name)?;
+                    labels.push(format!("l{index}b"));
+                    instructions.push(Instruction::Inc {
+                        register,
+                        next: index + 1,
+                    });
+                    Instruction::Dec {
+                        register,
+                        next: helper,
+                        zero: line(target)?,
+                    }
+                }
+                ["goto", target] => {
+                    let target = line(target)?;
+                    labels.push(format!("l{index}b"));
+                    instructions.push(Instruction::Dec {
+                        register: 0,
+                        next: target,
+                        zero: target,
+                    });
+                    Instruction::Inc {
+                        register: 0,
+                        next: helper,
+                    }
+                }
+                ["halt"] => Instruction::Halt,
+                _ => return Err(format!("invalid instruction '{text}'")),
+            };
+        }
+        let mut program = Program::new(labels, instructions);
+        program.registers = 2;
+        Ok(program)
+    }
+
+    fn new(labels: Vec<String>, instructions: Vec<Instruction>) -> Self {
+        let registers = instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::Inc { register, .. } | Instruction::Dec { register, .. } => {
+                    Some(register + 1)
+                }
+                Instruction::Halt => None,
+            })
+            .max()
+            .unwrap_or(0);
+        Program {
+            labels,
+            instructions,
+            registers,
+        }
+    }
+
+    /// Runs the program on `registers`, which has to hold a value per
+    /// register, for up to `max_steps` instructions. Gives the number of
+    /// instructions executed before halting, or `None` if the program
+    /// still ran.
+    pub fn run(&self, registers: &mut [u64], max_steps: u128) -> Option<u128> {
+        let mut current = 0;
+        let mut steps = 0;
+        while steps < max_steps {
+            match self.instructions.get(current) {
+                None | Some(Instruction::Halt) => return Some(steps),
+                Some(Instruction::Inc { register, next }) => {
+                    registers[*register] += 1;
+                    current = *next;
+                }
+                Some(Instruction::Dec {
+                    register,
+                    next,
+                    zero,
+                }) => match registers[*register] {
+                    0 => current = *zero,
+                    _ => {
+                        registers[*register] -= 1;
+                        current = *next;
+                    }
+                },
+            }
+            steps += 1;
+        }
+        None
+    }
+
+    /// The bit of the marker that the compiled machine keeps on the first
+    /// cell.
+    fn marker(&self) -> TapeEntry {
+        1 << self.registers
+    }
+
+    /// Compiles the program into a Turing machine started on [`Self::tape`].
+    ///
+    /// Cell `j` holds bit `i` if register `i` is above `j`, so a register
+    /// is the length of a run of cells with its bit starting at the first
+    /// cell, which holds a marker bit as well. Every instruction starts and
+    /// ends with the head on the first cell: `inc` sets the bit on the first
+    /// cell without it, `dec` clears it on the last cell with it, and both
+    /// go back to the marker.
+    pub fn to_turing(&self) -> Result<TuringMachine, String> {
+        if self.registers > MAX_COMPILED_REGISTERS {
+            return Err(format!(
+                "can't compile {} registers, at most {} fit into a cell",
+                self.registers, MAX_COMPILED_REGISTERS
+            ));
+        }
+        let marker = self.marker();
+        let symbols = 0..=(marker | (marker - 1));
+        let label = |index: usize| Target::State(self.labels[index].clone());
+        let back = |next: usize| Target::State(format!("back~{}", self.labels[next]));
+        let home = |next: usize| Target::State(format!("home~{}", self.labels[next]));
+        let mut builder = Builder::new();
+        let mut returns = vec![];
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            let name = self.labels[index].as_str();
+            match *instruction {
+                Instruction::Inc { register, next } => {
+                    let bit = 1 << register;
+                    for symbol in symbols.clone() {
+                        if symbol & bit != 0 {
+                            builder.add(name, symbol, &label(index), symbol, Direction::Right);
+                        } else if symbol & marker != 0 {
+                            builder.add(name, symbol, &home(next), symbol | bit, Direction::Right);
+                        } else {
+                            builder.add(name, symbol, &back(next), symbol | bit, Direction::Left);
+                        }
+                    }
+                    returns.push(next);
+                }
+                Instruction::Dec {
+                    register,
+                    next,
+                    zero,
+                } => {
+                    let bit = 1 << register;
+                    let (find, clear) = (format!("{name}~find"), format!("{name}~clear"));
+                    let found = Target::State(find.clone());
+                    let cleared = Target::State(clear.clone());
+                    for symbol in symbols.clone() {
+                        match symbol & bit {
+                            0 => builder.add(name, symbol, &home(zero), symbol, Direction::Right),
+                            _ => builder.add(name, symbol, &found, symbol, Direction::Right),
+                        }
+                        match symbol & bit {
+                            0 => builder.add(&find, symbol, &cleared, symbol, Direction::Left),
+                            _ => builder.add(&find, symbol, &found, symbol, Direction::Right),
+                        }
+                    }
+                    for symbol in symbols.clone().filter(|symbol| symbol & bit != 0) {
+                        let (target, direction) = match symbol & marker {
+                            0 => (back(next), Direction::Left),
+                            _ => (home(next), Direction::Right),
+                        };
+                        builder.add(&clear, symbol, &target, symbol & !bit, direction);
+                    }
+                    returns.extend([next, zero]);
+                }
+                Instruction::Halt => {
+                    let halt = Target::Halt(HaltReason::Halt);
+                    for symbol in symbols.clone() {
+                        builder.add(name, symbol, &halt, symbol, Direction::Right);
+                    }
+                }
+            }
+        }
+
+        // Going back to the marker and then onto the first cell, which takes
+        // a step to the right and one back.
+        returns.sort();
+        returns.dedup();
+        for next in returns {
+            let (back_state, home_state) = (back(next), home(next));
+            let (Target::State(back_name), Target::State(home_name)) = (&back_state, &home_state)
+            else {
+                unreachable!("returns lead to states");
+            };
+            for symbol in symbols.clone() {
+                match symbol & marker {
+                    0 => builder.add(back_name, symbol, &back_state, symbol, Direction::Left),
+                    _ => builder.add(back_name, symbol, &home_state, symbol, Direction::Right),
+                }
+                builder.add(home_name, symbol, &label(next), symbol, Direction::Left);
+            }
+        }
+        Ok(builder.build())
+    }
+
+    /// The tape the compiled machine starts on to run with `registers`,
+    /// with the head on its first cell.
+    pub fn tape(&self, registers: &[u64]) -> Vec<TapeEntry> {
+        let len = registers.iter().copied().max().unwrap_or(0).max(1);
+        (0..len)
+            .map(|cell| {
+                let bits = registers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, value)| **value > cell)
+                    .map(|(register, _)| 1 << register)
+                    .sum::<TapeEntry>();
+                if cell == 0 {
+                    bits | self.marker()
+                } else {
+                    bits
+                }
+            })
+            .collect()
+    }
+
+    /// Reads the registers back from the tape of the compiled machine,
+    /// starting at the marker.
+    pub fn registers_of(&self, tape: &[TapeEntry]) -> Vec<u64> {
+        let start = tape
+            .iter()
+            .position(|symbol| symbol & self.marker() != 0)
+            .unwrap_or(0);
+        (0..self.registers)
+            .map(|register| {
+                tape[start..]
+                    .iter()
+                    .take_while(|symbol| *symbol & (1 << register) != 0)
+                    .count() as u64
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_counter() {
+    let program = Program::parse_registers(
+        "// moves r0 onto r1 and r2\n\
+         loop: dec r0 add done\n\
+         add: inc r1 more\n\
+         more: inc r2 loop\n\
+         done: halt",
+    )
+    .unwrap();
+    assert_eq!(program.registers, 3);
+    let mut registers = [3, 1, 0];
+    assert_eq!(program.run(&mut registers, 100), Some(10));
+    assert_eq!(registers, [0, 4, 3]);
+    assert_eq!(program.run(&mut [3, 1, 0], 5), None);
+
+    // The compiled machine ends with the same registers.
+    let mut tm = program.to_turing().unwrap();
+    let tape = program.tape(&[3, 1, 0]);
+    assert_eq!(tape, [0b1011, 0b001, 0b001]);
+    tm.set_input(&tape);
+    while tm.step() {}
+    assert_eq!(tm.halt_reason, Some(HaltReason::Halt));
+    let cells: Vec<TapeEntry> = tm.tape().iter().copied().collect();
+    assert_eq!(program.registers_of(&cells), [0, 4, 3]);
+
+    // 2-counter machine doubling a into b.
+    let counters = Program::parse_counters(
+        "jz a 5\n\
+         dec a\n\
+         inc b\n\
+         inc b\n\
+         goto 0\n\
+         halt",
+    )
+    .unwrap();
+    let mut registers = [4, 0];
+    assert!(counters.run(&mut registers, 1000).is_some());
+    assert_eq!(registers, [0, 8]);
+    let mut tm = counters.to_turing().unwrap();
+    tm.set_input(&counters.tape(&[2, 1]));
+    while tm.step() {}
+    let cells: Vec<TapeEntry> = tm.tape().iter().copied().collect();
+    assert_eq!(counters.registers_of(&cells), [0, 5]);
+
+    assert!(Program::parse_registers("a: inc r0 b").is_err());
+    assert!(Program::parse_registers("a: inc x0 a").is_err());
+    assert!(Program::parse_registers("a: halt\na: halt").is_err());
+    assert!(Program::parse_counters("jz c 0").is_err());
+    assert!(Program::parse_counters("goto 3").is_err());
+    let wide = Program::parse_registers("a: inc r7 a").unwrap();
+    assert!(wide.to_turing().is_err());
+}
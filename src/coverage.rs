@@ -0,0 +1,93 @@
+use crate::{json::Json, turing::TuringMachine};
+
+/// Which transitions of a machine a run took, like the coverage of a
+/// program by its tests. Transitions never taken are dead for the input of
+/// the run.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Coverage {
+    pub transitions: usize,
+    /// Indices into [`TuringMachine::instructions`] of the transitions never
+    /// taken, in order.
+    pub untaken: Vec<usize>,
+}
+
+impl Coverage {
+    /// The coverage of a run that took the instruction at every index as
+    /// often as in `counts`.
+    pub fn new(counts: &[u128]) -> Self {
+        Coverage {
+            transitions: counts.len(),
+            untaken: (0..counts.len())
+                .filter(|index| counts[*index] == 0)
+                .collect(),
+        }
+    }
+
+    pub fn taken(&self) -> usize {
+        self.transitions - self.untaken.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.untaken.is_empty()
+    }
+
+    /// A line with the share of transitions taken, then one per transition
+    /// never taken.
+    pub fn report(&self, tm: &TuringMachine) -> Vec<String> {
+        let share = match self.transitions {
+            0 => 100.0,
+            transitions => 100.0 * self.taken() as f64 / transitions as f64,
+        };
+        let mut lines = vec![format!(
+            "Coverage: {} of {} transitions taken ({:.1}%)",
+            self.taken(),
+            self.transitions,
+            share
+        )];
+        for index in &self.untaken {
+            lines.push(format!(" Never taken: {}", tm.format_instruction(*index)));
+        }
+        lines
+    }
+
+    pub fn to_json(&self, tm: &TuringMachine) -> Json {
+        Json::object([
+            ("transitions", self.transitions.into()),
+            ("taken", self.taken().into()),
+            (
+                "untaken",
+                Json::Array(
+                    self.untaken
+                        .iter()
+                        .map(|index| tm.format_instruction(*index).into())
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+#[test]
+fn test_coverage() {
+    let mut tm = TuringMachine::parse(
+        "A 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> Halt 1 R\nC 0 -> A 0 R",
+    )
+    .unwrap();
+    let mut counts = vec![0; tm.instructions().len()];
+    while tm.step() {
+        counts[tm.last_instruction().unwrap()] += 1;
+    }
+    let coverage = Coverage::new(&counts);
+    assert_eq!(coverage.untaken, [4]);
+    assert!(!coverage.is_full());
+    let report = coverage.report(&tm);
+    assert_eq!(report[0], "Coverage: 4 of 5 transitions taken (80.0%)");
+    assert_eq!(report[1], " Never taken: (C, 0) -> (A, 0, Right)");
+    assert_eq!(
+        coverage.to_json(&tm).to_string(),
+        Json::parse(r#"{"transitions":5,"taken":4,"untaken":["(C, 0) -> (A, 0, Right)"]}"#)
+            .unwrap()
+            .to_string()
+    );
+    assert!(Coverage::new(&[1, 2]).is_full());
+}
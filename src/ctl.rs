@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    json::Json,
+    turing::{Direction, TapeEntry, TuringMachine, DEFAULT_ENTRY},
+};
+
+/// Longest n-grams tried by [`crate::decide::decide`].
+pub const MAX_N: usize = 4;
+/// Most windows around the head explored for a language.
+const MAX_WINDOWS: usize = 100_000;
+
+/// A regular language of configurations: the ones where every run of `n`
+/// cells on the left of the head is in `left` and on the right in
+/// `right`, with the blanks beyond counted in. Each set is a finite
+/// automaton over the tape, going from the last `n - 1` cells read to the
+/// next ones along the n-grams. Both list the n-grams in tape order.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Language {
+    pub n: usize,
+    pub left: BTreeSet<Vec<TapeEntry>>,
+    pub right: BTreeSet<Vec<TapeEntry>>,
+}
+
+fn ngrams_to_json(ngrams: &BTreeSet<Vec<TapeEntry>>) -> Json {
+    Json::Array(
+        ngrams
+            .iter()
+            .map(|ngram| Json::Array(ngram.iter().map(|entry| (*entry).into()).collect()))
+            .collect(),
+    )
+}
+
+fn ngrams_from_json(json: &Json, n: usize) -> Result<BTreeSet<Vec<TapeEntry>>, String> {
+    json.as_array()
+        .ok_or("the n-grams are not an array")?
+        .iter()
+        .map(|ngram| {
+            let ngram: Vec<TapeEntry> = ngram
+                .as_array()
+                .ok_or("an n-gram is not an array")?
+                .iter()
+                .map(|entry| {
+                    entry
+                        .as_int()
+                        .and_then(|entry| TapeEntry::try_from(entry).ok())
+                        .ok_or("invalid symbol in an n-gram".to_string())
+                })
+                .collect::<Result<_, String>>()?;
+            if ngram.len() != n {
+                return Err(format!(
+                    "an n-gram has {} cells instead of {n}",
+                    ngram.len()
+                ));
+            }
+            Ok(ngram)
+        })
+        .collect()
+}
+
+impl Language {
+    pub fn to_json(&self) -> Json {
+        Json::object([
+            ("n", self.n.into()),
+            ("left", ngrams_to_json(&self.left)),
+            ("right", ngrams_to_json(&self.right)),
+        ])
+    }
+
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let n = usize::try_from(json.int_field("n")?)
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or("field 'n' is not positive")?;
+        Ok(Language {
+            n,
+            left: ngrams_from_json(json.field("left")?, n)?,
+            right: ngrams_from_json(json.field("right")?, n)?,
+        })
+    }
+}
+
+/// The `n` cells on either side of the head, the nearest last on the left
+/// and first on the right.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+struct Window {
+    state: usize,
+    left: Vec<TapeEntry>,
+    head: TapeEntry,
+    right: Vec<TapeEntry>,
+}
+
+/// Explores every window of `language` reachable from the blank tape.
+/// With `grow`, the n-grams the machine writes are added to the language,
+/// and otherwise they have to be in it already. Gives whether it is closed
+/// without a halting configuration, and if the language changed.
+fn explore(tm: &TuringMachine, language: &mut Language, grow: bool) -> Option<bool> {
+    let rules: BTreeMap<_, _> = tm
+        .instructions()
+        .iter()
+        .map(|i| ((i.state, i.entry), (i.new_entry, i.direction, i.new_state)))
+        .collect();
+    let n = language.n;
+    let blank = vec![DEFAULT_ENTRY; n];
+    let mut changed = false;
+    for ngrams in [&mut language.left, &mut language.right] {
+        if !ngrams.contains(&blank) {
+            if !grow {
+                return None;
+            }
+            ngrams.insert(blank.clone());
+            changed = true;
+        }
+    }
+
+    let start = Window {
+        state: 0,
+        left: blank.clone(),
+        head: DEFAULT_ENTRY,
+        right: blank,
+    };
+    let mut seen = BTreeSet::from([start.clone()]);
+    let mut stack = vec![start];
+    while let Some(window) = stack.pop() {
+        if seen.len() > MAX_WINDOWS {
+            return None;
+        }
+        let (new_entry, direction, new_state) = rules.get(&(window.state, window.head))?;
+        let state = (*new_state)?;
+        // The cells the head leaves behind and the ones it moves into.
+        let (behind, ahead, written) = match direction {
+            Direction::Right => {
+                let mut behind = window.left[1..].to_vec();
+                behind.push(*new_entry);
+                (behind, &window.right, &mut language.left)
+            }
+            Direction::Left => {
+                let mut behind = vec![*new_entry];
+                behind.extend(&window.right[..n - 1]);
+                (behind, &window.left, &mut language.right)
+            }
+        };
+        if !written.contains(&behind) {
+            if !grow {
+                return None;
+            }
+            written.insert(behind.clone());
+            changed = true;
+        }
+        let (head, rest, ngrams) = match direction {
+            Direction::Right => (ahead[0], &ahead[1..], &language.right),
+            Direction::Left => (ahead[n - 1], &ahead[..n - 1], &language.left),
+        };
+        for ngram in ngrams {
+            let next = match direction {
+                Direction::Right if &ngram[..n - 1] == rest => Window {
+                    state,
+                    left: behind.clone(),
+                    head,
+                    right: ngram.clone(),
+                },
+                Direction::Left if &ngram[1..] == rest => Window {
+                    state,
+                    left: ngram.clone(),
+                    head,
+                    right: behind.clone(),
+                },
+                _ => continue,
+            };
+            if seen.insert(next.clone()) {
+                stack.push(next);
+            }
+        }
+    }
+    Some(changed)
+}
+
+/// Whether every configuration reachable from the blank tape is in
+/// `language`, and none of them halts or reaches a missing transition.
+/// If so, the machine never halts.
+pub fn check(tm: &TuringMachine, language: &Language) -> bool {
+    let mut language = language.clone();
+    explore(tm, &mut language, false) == Some(false)
+}
+
+/// Looks for a closed [`Language`] with n-grams of up to `max_n` cells,
+/// starting from the blank n-grams and adding the ones the machine writes
+/// until nothing changes.
+pub fn find(tm: &TuringMachine, max_n: usize) -> Option<Language> {
+    for n in 1..=max_n {
+        let mut language = Language {
+            n,
+            left: BTreeSet::new(),
+            right: BTreeSet::new(),
+        };
+        loop {
+            match explore(tm, &mut language, true) {
+                Some(true) => continue,
+                Some(false) => return Some(language),
+                None => break,
+            }
+        }
+    }
+    None
+}
+
+#[test]
+fn test_ctl() {
+    use crate::bbchallenge;
+
+    // Never writes a 2 on the left of the head, so it can't read one in A.
+    let tm = bbchallenge::parse_machine("1RB2LA---_1LB1LA0RB").unwrap();
+    let language = find(&tm, MAX_N).unwrap();
+    assert_eq!(language.n, 1);
+    assert_eq!(language.left, BTreeSet::from([vec![0], vec![1]]));
+    assert!(check(&tm, &language));
+    let json = Json::parse(&language.to_json().to_string()).unwrap();
+    assert_eq!(Language::from_json(&json).as_ref(), Ok(&language));
+
+    let mut smaller = language;
+    smaller.right.remove(&vec![2]);
+    assert!(!check(&tm, &smaller));
+
+    let halting = bbchallenge::parse_machine("1RB1LB_1LA1RZ").unwrap();
+    assert_eq!(find(&halting, MAX_N), None);
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use crate::{color::Colors, turing::TuringMachine};
+
+/// Help text of the commands of [`Debugger::execute`].
+pub const HELP: &str = "\
+Commands:
+  step [N]        simulate N steps, 1 by default
+  run             simulate until the machine halts
+  goto-step N     go to step N, forward or back
+  back [N]        go back N steps, 1 by default
+  tape [compact]  print the tape, boxed or on a single line
+  graph           print the state graph with the last transition
+  view            print the state graph and the tape
+  snapshots       print the steps that have snapshots
+  help            print this help
+  quit            leave the debugger";
+
+/// Cells between the indices under the boxed tape.
+const INDEX_EVERY: usize = 5;
+
+/// Steps back and forth through a run by keeping snapshots of the machine.
+///
+/// Going forward, the machine is copied every `every` steps. Going to an
+/// earlier step restores the nearest snapshot before it and simulates from
+/// there, so any step of a long run is at most `every` steps away.
+pub struct Debugger {
+    tm: TuringMachine,
+    every: u128,
+    snapshots: BTreeMap<u128, TuringMachine>,
+}
+
+impl Debugger {
+    pub fn new(tm: TuringMachine, every: u128) -> Self {
+        let snapshots = BTreeMap::from([(tm.num_steps, tm.clone())]);
+        Debugger {
+            tm,
+            every: every.max(1),
+            snapshots,
+        }
+    }
+
+    pub fn machine(&self) -> &TuringMachine {
+        &self.tm
+    }
+
+    /// Simulates up to `steps` steps, fewer if the machine halts, taking
+    /// snapshots on the way.
+    pub fn forward(&mut self, steps: u128) -> u128 {
+        let start = self.tm.num_steps;
+        while self.tm.num_steps - start < steps && self.tm.step() {
+            if self.tm.num_steps.is_multiple_of(self.every) {
+                self.snapshots
+                    .entry(self.tm.num_steps)
+                    .or_insert_with(|| self.tm.clone());
+            }
+        }
+        self.tm.num_steps - start
+    }
+
+    /// Goes to the configuration after `step` steps.
+    pub fn goto(&mut self, step: u128) -> Result<(), String> {
+        let (snapshot, tm) = self
+            .snapshots
+            .range(..=step)
+            .next_back()
+            .expect("there is a snapshot of the start");
+        // Simulating from the current step is faster if it's closer.
+        if step < self.tm.num_steps || *snapshot > self.tm.num_steps {
+            self.tm = tm.clone();
+        }
+        let missing = step - self.tm.num_steps;
+        if self.forward(missing) < missing {
+            return Err(format!(
+                "the machine halted after {} steps",
+                self.tm.num_steps
+            ));
+        }
+        Ok(())
+    }
+
+    /// Steps that have a snapshot.
+    pub fn snapshots(&self) -> impl Iterator<Item = u128> + '_ {
+        self.snapshots.keys().copied()
+    }
+
+    /// Executes a command described in [`HELP`] and gives what to print.
+    pub fn execute(&mut self, line: &str, colors: Colors) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let count = |word: Option<&&str>| match word {
+            Some(word) => word
+                .replace('_', "")
+                .parse::<u128>()
+                .map_err(|why| format!("invalid number '{word}': {why}")),
+            None => Ok(1),
+        };
+        match words.as_slice() {
+            [] => Ok(String::new()),
+            ["step" | "s", rest @ ..] if rest.len() <= 1 => {
+                self.forward(count(rest.first())?);
+                Ok(self.tm.format_state(colors))
+            }
+            ["run" | "r"] => {
+                self.forward(u128::MAX);
+                Ok(self.tm.format_state(colors))
+            }
+            ["goto-step" | "g", step] => {
+                self.goto(count(Some(step))?)?;
+                Ok(self.tm.format_state(colors))
+            }
+            ["back" | "b", rest @ ..] if rest.len() <= 1 => {
+                let step = self.tm.num_steps.saturating_sub(count(rest.first())?);
+                self.goto(step)?;
+                Ok(self.tm.format_state(colors))
+            }
+            ["tape" | "t"] => Ok(self
+                .tm
+                .format_tape_boxed(INDEX_EVERY, colors)
+                .trim_end()
+                .to_string()),
+            ["tape" | "t", "compact"] => {
+                Ok(self.tm.format_tape(true, colors).trim_end().to_string())
+            }
+            ["graph"] => Ok(self.tm.format_graph(colors).trim_end().to_string()),
+            ["view" | "v"] => Ok(format!(
+                "{}{}",
+                self.tm.format_graph(colors),
+                self.tm.format_tape_boxed(INDEX_EVERY, colors).trim_end()
+            )),
+            ["snapshots"] => {
+                let steps: Vec<String> = self.snapshots().map(|step| step.to_string()).collect();
+                Ok(format!(
+                    "{} snapshots, every {} steps: {}",
+                    steps.len(),
+                    self.every,
+                    steps.join(" ")
+                ))
+            }
+            ["help" | "h"] => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command '{}', try 'help'", line.trim())),
+        }
+    }
+}
+
+#[test]
+fn test_debugger() {
+    use std::path::Path;
+
+    let bb4 = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    let after = |steps| {
+        let mut tm = bb4.clone();
+        for _ in 0..steps {
+            tm.step();
+        }
+        tm
+    };
+
+    let mut debugger = Debugger::new(bb4.clone(), 10);
+    assert_eq!(debugger.forward(50), 50);
+    assert_eq!(
+        debugger.snapshots().collect::<Vec<_>>(),
+        [0, 10, 20, 30, 40, 50]
+    );
+    for step in [23, 5, 49, 70, 0, 107] {
+        debugger.goto(step).unwrap();
+        assert_eq!(debugger.machine(), &after(step), "step {step}");
+    }
+    assert_eq!(
+        debugger.goto(200),
+        Err("the machine halted after 107 steps".to_string())
+    );
+
+    let colors = Colors::off();
+    let mut debugger = Debugger::new(bb4.clone(), 10);
+    debugger.execute("step 3", colors).unwrap();
+    debugger.execute("back", colors).unwrap();
+    assert_eq!(debugger.machine(), &after(2));
+    assert!(debugger
+        .execute("run", colors)
+        .unwrap()
+        .ends_with("107 steps"));
+    assert!(debugger.execute("tape", colors).unwrap().contains("┌─"));
+    assert!(!debugger
+        .execute("tape compact", colors)
+        .unwrap()
+        .contains('┌'));
+    assert!(debugger
+        .execute("view", colors)
+        .unwrap()
+        .starts_with("  A ─0:1R→ B"));
+    assert!(debugger.execute("goto-step x", colors).is_err());
+    assert!(debugger.execute("jump", colors).is_err());
+}
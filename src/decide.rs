@@ -0,0 +1,1115 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bouncer::{self, Formula},
+    ctl::{self, Language},
+    json::Json,
+    log::{self, Level},
+    sandbox::Limits,
+    scan, transform,
+    turing::{Direction, HaltReason, TapeEntry, TuringMachine},
+};
+
+/// Largest number of cells behind the head compared by the translated
+/// cycler decider.
+const MAX_WINDOW: usize = 1024;
+/// Number of recent record positions kept per direction by the translated
+/// cycler decider.
+const MAX_RECORDS: usize = 64;
+/// Largest number of configurations explored by the backward search.
+const MAX_NODES: usize = 100_000;
+
+/// State, head and non-blank part of the tape. Positions are relative to
+/// the starting cell.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Configuration {
+    pub state: String,
+    pub head: isize,
+    /// Position of the first cell of `tape`.
+    pub first: isize,
+    pub tape: Vec<TapeEntry>,
+}
+
+impl Configuration {
+    pub fn of(tm: &TuringMachine) -> Self {
+        let state = match tm.state() {
+            Some(state) => tm.states()[state].clone(),
+            None => tm
+                .halt_reason
+                .map_or("Halt", |reason| reason.name())
+                .to_string(),
+        };
+        let tape = tm.tape();
+        let (first, tape) = match scan::written(tape, tm.blank()) {
+            Some((start, end)) => (
+                start as isize - tm.origin() as isize,
+                tape.range(start..=end).copied().collect(),
+            ),
+            None => (0, vec![]),
+        };
+        Configuration {
+            state,
+            head: position(tm),
+            first,
+            tape,
+        }
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("state", self.state.as_str().into()),
+            ("head", self.head.into()),
+            ("first", self.first.into()),
+            (
+                "tape",
+                Json::Array(self.tape.iter().map(|entry| (*entry).into()).collect()),
+            ),
+        ])
+    }
+
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let tape = json
+            .field("tape")?
+            .as_array()
+            .ok_or("field 'tape' is not an array")?
+            .iter()
+            .map(|entry| {
+                entry
+                    .as_int()
+                    .and_then(|entry| TapeEntry::try_from(entry).ok())
+                    .ok_or("invalid symbol on the tape".to_string())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Configuration {
+            state: json.str_field("state")?.to_string(),
+            head: json.int_field("head")? as isize,
+            first: json.int_field("first")? as isize,
+            tape,
+        })
+    }
+}
+
+/// Why a machine never halts.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Proof {
+    /// The configuration at step `start` recurs `period` steps later.
+    Cycler {
+        start: u128,
+        period: u128,
+        configuration: Configuration,
+    },
+    /// At steps `start` and `start + period` the machine is in the same state
+    /// with only blanks ahead of the head in `direction`, and the `window`
+    /// cells behind the head, which is as far back as it moved in between,
+    /// are the same. So the same steps repeat forever, shifted each time.
+    TranslatedCycler {
+        start: u128,
+        period: u128,
+        direction: Direction,
+        window: usize,
+        before: Configuration,
+        after: Configuration,
+    },
+    /// Searching backwards from every halting transition, no configuration
+    /// has a predecessor after at most `depth` steps, and the machine does
+    /// not halt within `depth` steps from the blank tape.
+    BackwardReasoning { depth: usize },
+    /// At step `start` the machine is in `formula` for `n = 0`, and every
+    /// configuration of the formula leads to the one for `n + 1`, see
+    /// [`bouncer::prove`].
+    Bouncer { start: u128, formula: Formula },
+    /// Every configuration reachable from the blank tape is in `language`,
+    /// which has no halting configurations, see [`ctl::check`].
+    ClosedTapeLanguage { language: Language },
+}
+
+impl Proof {
+    /// Name of the decider that found the proof, as used in certificates.
+    pub fn decider(&self) -> &'static str {
+        match self {
+            Proof::Cycler { .. } => "cycler",
+            Proof::TranslatedCycler { .. } => "translated_cycler",
+            Proof::BackwardReasoning { .. } => "backward_reasoning",
+            Proof::Bouncer { .. } => "bouncer",
+            Proof::ClosedTapeLanguage { .. } => "closed_tape_language",
+        }
+    }
+}
+
+impl Display for Proof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Proof::Cycler { start, period, .. } => write!(
+                f,
+                "cycler, repeating every {period} steps from step {start}"
+            ),
+            Proof::TranslatedCycler {
+                start,
+                period,
+                direction,
+                ..
+            } => write!(
+                f,
+                "translated cycler, repeating every {period} steps from step {start} \
+                 while moving {}",
+                direction.to_string().to_lowercase()
+            ),
+            Proof::BackwardReasoning { depth } => write!(
+                f,
+                "backward reasoning, no halting transition is reachable from {depth} steps away"
+            ),
+            Proof::Bouncer { start, formula } => {
+                write!(f, "bouncer, growing as {formula} from step {start}")
+            }
+            Proof::ClosedTapeLanguage { language } => write!(
+                f,
+                "closed tape language of {} and {} {}-grams left and right of the head",
+                language.left.len(),
+                language.right.len(),
+                language.n
+            ),
+        }
+    }
+}
+
+/// A machine together with the proof that it never halts when started on
+/// the blank tape.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Certificate {
+    /// The machine in the format of [`TuringMachine::to_turing`].
+    pub machine: String,
+    pub proof: Proof,
+}
+
+impl Certificate {
+    pub fn to_json(&self) -> Json {
+        let mut fields = vec![("machine".to_string(), self.machine.as_str().into())];
+        let proof = match &self.proof {
+            Proof::Cycler {
+                start,
+                period,
+                configuration,
+            } => Json::object([
+                ("decider", self.proof.decider().into()),
+                ("start", (*start).into()),
+                ("period", (*period).into()),
+                ("configuration", configuration.to_json()),
+            ]),
+            Proof::TranslatedCycler {
+                start,
+                period,
+                direction,
+                window,
+                before,
+                after,
+            } => Json::object([
+                ("decider", self.proof.decider().into()),
+                ("start", (*start).into()),
+                ("period", (*period).into()),
+                ("direction", direction.letter().into()),
+                ("window", (*window).into()),
+                ("before", before.to_json()),
+                ("after", after.to_json()),
+            ]),
+            Proof::BackwardReasoning { depth } => Json::object([
+                ("decider", self.proof.decider().into()),
+                ("depth", (*depth).into()),
+            ]),
+            Proof::Bouncer { start, formula } => Json::object([
+                ("decider", self.proof.decider().into()),
+                ("start", (*start).into()),
+                ("formula", formula.to_json()),
+            ]),
+            Proof::ClosedTapeLanguage { language } => Json::object([
+                ("decider", self.proof.decider().into()),
+                ("language", language.to_json()),
+            ]),
+        };
+        if let Json::Object(proof) = proof {
+            fields.extend(proof);
+        }
+        Json::Object(fields)
+    }
+
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let count = |key: &str| json.u128_field(key);
+        let size = |key: &str| -> Result<usize, String> {
+            usize::try_from(count(key)?).map_err(|_| format!("field '{key}' is too large"))
+        };
+        let proof = match json.str_field("decider")? {
+            "cycler" => Proof::Cycler {
+                start: count("start")?,
+                period: count("period")?,
+                configuration: Configuration::from_json(json.field("configuration")?)?,
+            },
+            "translated_cycler" => Proof::TranslatedCycler {
+                start: count("start")?,
+                period: count("period")?,
+                direction: match json.str_field("direction")? {
+                    "L" => Direction::Left,
+                    "R" => Direction::Right,
+                    other => return Err(format!("invalid direction '{other}'")),
+                },
+                window: size("window")?,
+                before: Configuration::from_json(json.field("before")?)?,
+                after: Configuration::from_json(json.field("after")?)?,
+            },
+            "backward_reasoning" => Proof::BackwardReasoning {
+                depth: size("depth")?,
+            },
+            "bouncer" => Proof::Bouncer {
+                start: count("start")?,
+                formula: Formula::from_json(json.field("formula")?)?,
+            },
+            "closed_tape_language" => Proof::ClosedTapeLanguage {
+                language: Language::from_json(json.field("language")?)?,
+            },
+            other => return Err(format!("unknown decider '{other}'")),
+        };
+        Ok(Certificate {
+            machine: json.str_field("machine")?.to_string(),
+            proof,
+        })
+    }
+}
+
+/// Result of running the deciders on a machine.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Decision {
+    Halts { steps: u128, reason: HaltReason },
+    NeverHalts(Proof),
+    Undecided,
+}
+
+impl Decision {
+    /// `halts`, `never_halts` or `undecided`.
+    pub fn verdict(&self) -> &'static str {
+        match self {
+            Decision::Halts { .. } => "halts",
+            Decision::NeverHalts(_) => "never_halts",
+            Decision::Undecided => "undecided",
+        }
+    }
+}
+
+/// Runs the decider `name`, logging its verdict and how long it took.
+pub fn logged(name: &str, decider: impl FnOnce() -> Decision) -> Decision {
+    let start = Instant::now();
+    let decision = decider();
+    log::event(
+        Level::Debug,
+        "decider finished",
+        &[
+            ("decider", name.into()),
+            ("verdict", decision.verdict().into()),
+            ("seconds", Json::Float(start.elapsed().as_secs_f64())),
+        ],
+    );
+    decision
+}
+
+/// Position of the head relative to the starting cell.
+fn position(tm: &TuringMachine) -> isize {
+    tm.head() as isize - tm.origin() as isize
+}
+
+/// Symbol at `position` relative to the starting cell.
+fn cell(tm: &TuringMachine, position: isize) -> TapeEntry {
+    let index = position + tm.origin() as isize;
+    if index < 0 {
+        return 0;
+    }
+    tm.tape().get(index as usize).copied().unwrap_or(0)
+}
+
+fn sign(direction: Direction) -> isize {
+    match direction {
+        Direction::Left => -1,
+        Direction::Right => 1,
+    }
+}
+
+/// Whether every cell beyond the head in `direction` is blank.
+fn blank_ahead(tm: &TuringMachine, direction: Direction) -> bool {
+    match direction {
+        Direction::Left => tm.tape().range(..tm.head()).all(|entry| *entry == 0),
+        Direction::Right => tm.tape().range(tm.head() + 1..).all(|entry| *entry == 0),
+    }
+}
+
+/// A time where the head was on the outermost visited cell on one side.
+struct Record {
+    step: u128,
+    state: Option<usize>,
+    head: isize,
+    /// How far the head moved back from `head` since.
+    back: usize,
+    /// The cells from the head backwards, up to [`MAX_WINDOW`] of them.
+    view: Vec<TapeEntry>,
+    configuration: Configuration,
+}
+
+/// Looks for translated cycles moving in one direction.
+struct Records {
+    direction: Direction,
+    /// Outermost visited position in `direction`.
+    extreme: isize,
+    records: VecDeque<Record>,
+}
+
+impl Records {
+    fn new(tm: &TuringMachine, direction: Direction) -> Self {
+        let extreme = match direction {
+            Direction::Left => -(tm.origin() as isize),
+            Direction::Right => tm.tape().len() as isize - 1 - tm.origin() as isize,
+        };
+        Records {
+            direction,
+            extreme,
+            records: VecDeque::new(),
+        }
+    }
+
+    fn check(&mut self, tm: &TuringMachine) -> Option<Proof> {
+        let s = sign(self.direction);
+        let head = position(tm);
+        for record in self.records.iter_mut() {
+            record.back = record.back.max((s * (record.head - head)).max(0) as usize);
+        }
+        if s * head < s * self.extreme {
+            return None;
+        }
+        self.extreme = head;
+
+        let view: Vec<TapeEntry> = (0..MAX_WINDOW as isize)
+            .map(|i| cell(tm, head - s * i))
+            .collect();
+        for record in self.records.iter().rev() {
+            if record.state == tm.state()
+                && record.back < MAX_WINDOW
+                && record.view[..=record.back] == view[..=record.back]
+            {
+                return Some(Proof::TranslatedCycler {
+                    start: record.step,
+                    period: tm.num_steps - record.step,
+                    direction: self.direction,
+                    window: record.back,
+                    before: record.configuration.clone(),
+                    after: Configuration::of(tm),
+                });
+            }
+        }
+
+        self.records.push_back(Record {
+            step: tm.num_steps,
+            state: tm.state(),
+            head,
+            back: 0,
+            view,
+            configuration: Configuration::of(tm),
+        });
+        if self.records.len() > MAX_RECORDS {
+            self.records.pop_front();
+        }
+        None
+    }
+}
+
+/// Names of the deciders [`decide`] tries, as given by [`Proof::decider`].
+pub const DECIDERS: [&str; 5] = [
+    "cycler",
+    "translated_cycler",
+    "bouncer",
+    "closed_tape_language",
+    "backward_reasoning",
+];
+
+/// Runs `tm` on the blank tape for up to `max_steps` steps, looking for
+/// cycles and translated cycles, then for bouncers over as many steps, then
+/// for a closed tape language, and then tries backward reasoning up to
+/// `depth` steps. Missing transitions count as halting.
+pub fn decide(tm: &TuringMachine, max_steps: u128, depth: usize) -> Decision {
+    let limits = Limits {
+        max_steps,
+        ..Limits::NONE
+    };
+    decide_within(tm, &limits, depth)
+}
+
+/// Decides like [`decide`] for a machine from an untrusted source: the
+/// simulations take at most the steps of `limits` and give up once the tape
+/// grew past its cells, and all deciders give up when the time is up.
+pub fn decide_within(tm: &TuringMachine, limits: &Limits, depth: usize) -> Decision {
+    let stop = AtomicBool::new(false);
+    let (max_steps, max_tape_cells) = (limits.max_steps, limits.max_tape_cells);
+    let deciders: [(&str, &dyn Fn() -> Decision); 4] = [
+        ("simulation", &|| {
+            simulate(tm, max_steps, max_tape_cells, Analyses::ALL, &stop)
+        }),
+        ("bouncer", &|| bouncer(tm, max_steps, max_tape_cells, &stop)),
+        ("closed_tape_language", &|| closed_tape_language(tm)),
+        ("backward_reasoning", &|| backward(tm, depth, &stop)),
+    ];
+    let (done, finished) = mpsc::channel::<()>();
+    thread::scope(|scope| {
+        if limits.max_time < Duration::MAX {
+            let stop = &stop;
+            scope.spawn(move || {
+                if finished.recv_timeout(limits.max_time) == Err(RecvTimeoutError::Timeout) {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            });
+        }
+        let mut decision = Decision::Undecided;
+        for (name, decider) in deciders {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            decision = logged(name, decider);
+            if decision != Decision::Undecided {
+                break;
+            }
+        }
+        drop(done);
+        decision
+    })
+}
+
+fn bouncer(
+    tm: &TuringMachine,
+    max_steps: u128,
+    max_tape_cells: usize,
+    stop: &AtomicBool,
+) -> Decision {
+    match bouncer::find(tm, max_steps, max_tape_cells, stop) {
+        Some((start, formula)) => Decision::NeverHalts(Proof::Bouncer { start, formula }),
+        None => Decision::Undecided,
+    }
+}
+
+fn closed_tape_language(tm: &TuringMachine) -> Decision {
+    match ctl::find(tm, ctl::MAX_N) {
+        Some(language) => Decision::NeverHalts(Proof::ClosedTapeLanguage { language }),
+        None => Decision::Undecided,
+    }
+}
+
+fn backward(tm: &TuringMachine, depth: usize, stop: &AtomicBool) -> Decision {
+    match backward_search(tm, depth, stop) {
+        true => Decision::NeverHalts(Proof::BackwardReasoning { depth }),
+        false => Decision::Undecided,
+    }
+}
+
+/// Decides like [`decide`], but looks for cycles, translated cycles,
+/// bouncers and a closed tape language and runs the backward reasoning at
+/// the same time on their own threads. The first analysis to decide stops the others.
+pub fn decide_parallel(tm: &TuringMachine, max_steps: u128, depth: usize) -> Decision {
+    let stop = AtomicBool::new(false);
+    let decisive = |decision: Decision| {
+        if decision != Decision::Undecided {
+            stop.store(true, Ordering::Relaxed);
+        }
+        decision
+    };
+    let cycler = Analyses {
+        cycler: true,
+        translated_cycler: false,
+    };
+    let translated_cycler = Analyses {
+        cycler: false,
+        translated_cycler: true,
+    };
+    thread::scope(|scope| {
+        let threads = [
+            scope.spawn(|| {
+                decisive(logged("cycler", || {
+                    simulate(tm, max_steps, usize::MAX, cycler, &stop)
+                }))
+            }),
+            scope.spawn(|| {
+                decisive(logged("translated_cycler", || {
+                    simulate(tm, max_steps, usize::MAX, translated_cycler, &stop)
+                }))
+            }),
+            scope.spawn(|| {
+                decisive(logged("bouncer", || {
+                    bouncer(tm, max_steps, usize::MAX, &stop)
+                }))
+            }),
+            scope.spawn(|| decisive(logged("closed_tape_language", || closed_tape_language(tm)))),
+            scope.spawn(|| decisive(logged("backward_reasoning", || backward(tm, depth, &stop)))),
+        ];
+        threads
+            .map(|thread| thread.join().expect("an analysis panicked"))
+            .into_iter()
+            .find(|decision| decision != &Decision::Undecided)
+            .unwrap_or(Decision::Undecided)
+    })
+}
+
+/// The analyses [`simulate`] runs after every step.
+#[derive(Debug, Clone, Copy)]
+pub struct Analyses {
+    pub cycler: bool,
+    pub translated_cycler: bool,
+}
+
+impl Analyses {
+    pub const ALL: Analyses = Analyses {
+        cycler: true,
+        translated_cycler: true,
+    };
+    pub const NONE: Analyses = Analyses {
+        cycler: false,
+        translated_cycler: false,
+    };
+}
+
+/// Steps between looking whether another analysis asked to stop.
+const STOP_CHECK_INTERVAL: u128 = 4096;
+
+/// The Mersenne prime 2^61 - 1 the tape is hashed modulo, and the base of
+/// the hash.
+const MODULUS: u64 = (1 << 61) - 1;
+const BASE: u64 = 0x1f3a_9c27_5e6d_b041;
+
+/// Reduces a number below `2 * MODULUS`.
+fn reduce(n: u64) -> u64 {
+    if n >= MODULUS {
+        n - MODULUS
+    } else {
+        n
+    }
+}
+
+fn multiply(a: u64, b: u64) -> u64 {
+    let product = a as u128 * b as u128;
+    reduce((product as u64 & MODULUS) + (product >> 61) as u64)
+}
+
+fn power(mut base: u64, mut exponent: u64) -> u64 {
+    let mut result = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = multiply(result, base);
+        }
+        base = multiply(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// A hash of the tape that is updated in constant time with every step:
+/// the sum of `entry * BASE^position` over the cells relative to the
+/// starting cell, where blank cells add nothing. With the state and the
+/// head it tells configurations apart without storing them, up to rare
+/// collisions.
+struct TapeHash {
+    hash: u64,
+    /// `BASE` to the position of the head, and the inverse of `BASE`.
+    power: u64,
+    inverse: u64,
+}
+
+impl TapeHash {
+    fn of(tm: &TuringMachine) -> Self {
+        let inverse = power(BASE, MODULUS - 2);
+        let at = |position: isize| match position {
+            0.. => power(BASE, position as u64),
+            _ => power(inverse, position.unsigned_abs() as u64),
+        };
+        let mut hash = 0;
+        for (index, entry) in tm.tape().iter().enumerate() {
+            let position = index as isize - tm.origin() as isize;
+            hash = reduce(hash + multiply(*entry as u64, at(position)));
+        }
+        TapeHash {
+            hash,
+            power: at(position(tm)),
+            inverse,
+        }
+    }
+
+    /// Takes the step that read `entry` and wrote `new_entry`.
+    fn step(&mut self, entry: TapeEntry, new_entry: TapeEntry, direction: Direction) {
+        let change = reduce(MODULUS + new_entry as u64 - entry as u64);
+        self.hash = reduce(self.hash + multiply(change, self.power));
+        self.power = match direction {
+            Direction::Left => multiply(self.power, self.inverse),
+            Direction::Right => multiply(self.power, BASE),
+        };
+    }
+}
+
+/// The configuration of `tm` if a fresh copy of it is in the same one
+/// after `start` steps, which confirms a cycle found by hashes.
+fn recurs(tm: &TuringMachine, start: u128) -> Option<Configuration> {
+    let mut copy =
+        TuringMachine::from_instructions(tm.states().to_vec(), tm.instructions().to_vec());
+    copy.set_reject_undefined(true);
+    while copy.num_steps < start && copy.step() {}
+    let configuration = Configuration::of(tm);
+    (Configuration::of(&copy) == configuration).then_some(configuration)
+}
+
+/// Runs `tm` on the blank tape for up to `max_steps` steps with `analyses`,
+/// until one of them finds a proof, `stop` is set or the tape grew past
+/// `max_tape_cells`.
+pub fn simulate(
+    tm: &TuringMachine,
+    max_steps: u128,
+    max_tape_cells: usize,
+    analyses: Analyses,
+    stop: &AtomicBool,
+) -> Decision {
+    let mut tm = TuringMachine::from_instructions(tm.states().to_vec(), tm.instructions().to_vec());
+    tm.set_reject_undefined(true);
+
+    // Brent's algorithm: the configuration is saved at every power of two
+    // steps and compared with the following ones, only by their hashes.
+    let mut hash = TapeHash::of(&tm);
+    let mut saved = (tm.num_steps, (hash.hash, tm.state(), position(&tm)));
+    let mut power = 1;
+    let mut records = [
+        Records::new(&tm, Direction::Left),
+        Records::new(&tm, Direction::Right),
+    ];
+
+    while tm.num_steps < max_steps {
+        if !tm.step() {
+            return Decision::Halts {
+                steps: tm.num_steps,
+                reason: tm.halt_reason.unwrap_or(HaltReason::Halt),
+            };
+        }
+        if tm.tape().len() > max_tape_cells
+            || tm.num_steps.is_multiple_of(STOP_CHECK_INTERVAL) && stop.load(Ordering::Relaxed)
+        {
+            return Decision::Undecided;
+        }
+
+        if analyses.cycler {
+            let instruction = &tm.instructions()[tm.last_instruction().expect("a step was taken")];
+            hash.step(
+                instruction.entry,
+                instruction.new_entry,
+                instruction.direction,
+            );
+            let current = (hash.hash, tm.state(), position(&tm));
+            let (start, configuration) = saved;
+            if current == configuration {
+                if let Some(configuration) = recurs(&tm, start) {
+                    return Decision::NeverHalts(Proof::Cycler {
+                        start,
+                        period: tm.num_steps - start,
+                        configuration,
+                    });
+                }
+            }
+            if tm.num_steps - start == power {
+                saved = (tm.num_steps, current);
+                power *= 2;
+            }
+        }
+
+        if analyses.translated_cycler {
+            for records in records.iter_mut() {
+                if let Some(proof) = records.check(&tm) {
+                    return Decision::NeverHalts(proof);
+                }
+            }
+        }
+    }
+    Decision::Undecided
+}
+
+/// A partially known configuration in the backward search.
+#[derive(Clone)]
+struct Partial {
+    state: usize,
+    head: isize,
+    tape: BTreeMap<isize, TapeEntry>,
+    depth: usize,
+}
+
+/// Whether searching backwards from every halting transition runs out of
+/// predecessors within `depth` steps, while the machine doesn't halt within
+/// `depth` steps from the blank tape. Together, these mean it never halts.
+pub fn backward_reasoning(tm: &TuringMachine, depth: usize) -> bool {
+    backward_search(tm, depth, &AtomicBool::new(false))
+}
+
+/// Does the [`backward_reasoning`], giving up once `stop` is set.
+fn backward_search(tm: &TuringMachine, depth: usize, stop: &AtomicBool) -> bool {
+    let mut stack = vec![];
+    for state in 0..tm.states().len() {
+        for symbol in transform::symbols(tm) {
+            let instruction = tm
+                .instructions()
+                .iter()
+                .find(|instruction| instruction.state == state && instruction.entry == symbol);
+            if instruction.is_none_or(|instruction| instruction.new_state.is_none()) {
+                stack.push(Partial {
+                    state,
+                    head: 0,
+                    tape: BTreeMap::from([(0, symbol)]),
+                    depth: 0,
+                });
+            }
+        }
+    }
+
+    let mut nodes = 0;
+    while let Some(partial) = stack.pop() {
+        nodes += 1;
+        if partial.depth >= depth || nodes > MAX_NODES || stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        for instruction in tm.instructions() {
+            if instruction.new_state != Some(partial.state) {
+                continue;
+            }
+            let head = partial.head - sign(instruction.direction);
+            if partial
+                .tape
+                .get(&head)
+                .is_some_and(|entry| *entry != instruction.new_entry)
+            {
+                continue;
+            }
+            let mut predecessor = partial.clone();
+            predecessor.state = instruction.state;
+            predecessor.head = head;
+            predecessor.tape.insert(head, instruction.entry);
+            predecessor.depth += 1;
+            stack.push(predecessor);
+        }
+    }
+
+    let mut tm = TuringMachine::from_instructions(tm.states().to_vec(), tm.instructions().to_vec());
+    tm.set_reject_undefined(true);
+    while tm.num_steps < depth as u128 {
+        if !tm.step() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Checks `certificate` against `tm` by simulating the machine, without
+/// relying on the deciders that produced it.
+pub fn verify(tm: &TuringMachine, certificate: &Certificate) -> Result<(), String> {
+    let mut tm = TuringMachine::from_instructions(tm.states().to_vec(), tm.instructions().to_vec());
+    if tm.to_turing() != certificate.machine {
+        return Err("the certificate is for a different machine".to_string());
+    }
+    tm.set_reject_undefined(true);
+    let run_to = |tm: &mut TuringMachine, step: u128| -> Result<(), String> {
+        while tm.num_steps < step {
+            if !tm.step() {
+                return Err(format!("the machine halts after {} steps", tm.num_steps));
+            }
+        }
+        Ok(())
+    };
+
+    match &certificate.proof {
+        Proof::Cycler {
+            start,
+            period,
+            configuration,
+        } => {
+            if *period == 0 {
+                return Err("the period is zero".to_string());
+            }
+            run_to(&mut tm, *start)?;
+            if &Configuration::of(&tm) != configuration {
+                return Err(format!("wrong configuration at step {start}"));
+            }
+            run_to(&mut tm, start + period)?;
+            if &Configuration::of(&tm) != configuration {
+                return Err(format!(
+                    "the configuration at step {} differs from step {start}",
+                    start + period
+                ));
+            }
+            Ok(())
+        }
+        Proof::TranslatedCycler {
+            start,
+            period,
+            direction,
+            window,
+            before,
+            after,
+        } => {
+            if *period == 0 {
+                return Err("the period is zero".to_string());
+            }
+            let s = sign(*direction);
+            run_to(&mut tm, *start)?;
+            if &Configuration::of(&tm) != before {
+                return Err(format!("wrong configuration at step {start}"));
+            }
+            if !blank_ahead(&tm, *direction) {
+                return Err(format!("the tape ahead is not blank at step {start}"));
+            }
+            let state = tm.state();
+            let head = position(&tm);
+            let cells = |tm: &TuringMachine, window: usize| -> Vec<TapeEntry> {
+                (0..=window as isize)
+                    .map(|i| cell(tm, position(tm) - s * i))
+                    .collect()
+            };
+            let view = cells(&tm, *window);
+
+            let mut back = 0;
+            while tm.num_steps < start + period {
+                let next = tm.num_steps + 1;
+                run_to(&mut tm, next)?;
+                back = back.max(s * (head - position(&tm)));
+            }
+            let end = start + period;
+            if &Configuration::of(&tm) != after {
+                return Err(format!("wrong configuration at step {end}"));
+            }
+            if back > *window as isize {
+                return Err(format!(
+                    "the head moves back {back} cells, more than the window of {window}"
+                ));
+            }
+            if tm.state() != state || s * (position(&tm) - head) < 0 {
+                return Err(format!("step {end} doesn't continue the cycle"));
+            }
+            if !blank_ahead(&tm, *direction) {
+                return Err(format!("the tape ahead is not blank at step {end}"));
+            }
+            if cells(&tm, *window) != view {
+                return Err(format!(
+                    "the cells behind the head differ between steps {start} and {end}"
+                ));
+            }
+            Ok(())
+        }
+        Proof::Bouncer { start, formula } => {
+            run_to(&mut tm, *start)?;
+            if !bouncer::is_in(&tm, formula) {
+                return Err(format!("the machine isn't in the formula at step {start}"));
+            }
+            if !bouncer::prove(&tm, formula) {
+                return Err("the formula doesn't lead to itself for n + 1".to_string());
+            }
+            Ok(())
+        }
+        Proof::ClosedTapeLanguage { language } => {
+            if ctl::check(&tm, language) {
+                Ok(())
+            } else {
+                Err("the language isn't closed or has halting configurations".to_string())
+            }
+        }
+        Proof::BackwardReasoning { depth } => {
+            if backward_reasoning(&tm, *depth) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "a halting transition is reachable from {depth} steps away"
+                ))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_tape_hash() {
+    use crate::bbchallenge;
+
+    let mut tm = bbchallenge::parse_machine("1RB1LB_1LA0LC_1RZ1LD_1RD0RA").unwrap();
+    let mut hash = TapeHash::of(&tm);
+    let mut hashes = vec![];
+    while tm.step() && !tm.is_halted() {
+        let instruction = &tm.instructions()[tm.last_instruction().unwrap()];
+        hash.step(
+            instruction.entry,
+            instruction.new_entry,
+            instruction.direction,
+        );
+        let fresh = TapeHash::of(&tm);
+        assert_eq!((hash.hash, hash.power), (fresh.hash, fresh.power));
+        hashes.push(hash.hash);
+    }
+    // The blank tape hashes to 0, and the tapes written are different.
+    assert_eq!(
+        TapeHash::of(&TuringMachine::from_instructions(vec![], vec![])).hash,
+        0
+    );
+    hashes.sort();
+    hashes.dedup();
+    assert!(hashes.len() > 50);
+}
+
+#[test]
+fn test_decide_cycler() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/deciders/cycler.turing"));
+    let proof = match decide(&tm, 1000, 0) {
+        Decision::NeverHalts(proof @ Proof::Cycler { .. }) => proof,
+        decision => panic!("{decision:?}"),
+    };
+
+    let certificate = Certificate {
+        machine: tm.to_turing(),
+        proof,
+    };
+    assert_eq!(verify(&tm, &certificate), Ok(()));
+    let json = Json::parse(&certificate.to_json().to_string()).unwrap();
+    assert_eq!(Certificate::from_json(&json), Ok(certificate));
+}
+
+#[test]
+fn test_decide_translated_cycler() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/deciders/translated_cycler.turing"));
+    let proof = match decide(&tm, 1000, 0) {
+        Decision::NeverHalts(proof @ Proof::TranslatedCycler { .. }) => proof,
+        decision => panic!("{decision:?}"),
+    };
+
+    let mut certificate = Certificate {
+        machine: tm.to_turing(),
+        proof,
+    };
+    assert_eq!(verify(&tm, &certificate), Ok(()));
+    let json = Json::parse(&certificate.to_json().to_string()).unwrap();
+    assert_eq!(Certificate::from_json(&json).as_ref(), Ok(&certificate));
+
+    if let Proof::TranslatedCycler { window, .. } = &mut certificate.proof {
+        *window = 0;
+    }
+    assert!(verify(&tm, &certificate).is_err());
+}
+
+#[test]
+fn test_decide_backward_reasoning() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/deciders/backward.turing"));
+    assert!(backward_reasoning(&tm, 5));
+
+    let certificate = Certificate {
+        machine: tm.to_turing(),
+        proof: Proof::BackwardReasoning { depth: 5 },
+    };
+    assert_eq!(verify(&tm, &certificate), Ok(()));
+
+    let tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    assert!(!backward_reasoning(&tm, 5));
+    assert_eq!(
+        decide(&tm, 1000, 5),
+        Decision::Halts {
+            steps: 107,
+            reason: HaltReason::Halt
+        }
+    );
+}
+
+#[test]
+fn test_decide_bouncer() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/deciders/bouncer.turing"));
+    let proof = match decide(&tm, 1000, 0) {
+        Decision::NeverHalts(proof @ Proof::Bouncer { .. }) => proof,
+        decision => panic!("{decision:?}"),
+    };
+
+    let mut certificate = Certificate {
+        machine: tm.to_turing(),
+        proof,
+    };
+    assert_eq!(verify(&tm, &certificate), Ok(()));
+    let json = Json::parse(&certificate.to_json().to_string()).unwrap();
+    assert_eq!(Certificate::from_json(&json).as_ref(), Ok(&certificate));
+
+    if let Proof::Bouncer { start, .. } = &mut certificate.proof {
+        *start += 1;
+    }
+    assert!(verify(&tm, &certificate).is_err());
+}
+
+#[test]
+fn test_decide_closed_tape_language() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/deciders/closed_tape_language.turing"));
+    let proof = match decide(&tm, 1000, 0) {
+        Decision::NeverHalts(proof @ Proof::ClosedTapeLanguage { .. }) => proof,
+        decision => panic!("{decision:?}"),
+    };
+
+    let mut certificate = Certificate {
+        machine: tm.to_turing(),
+        proof,
+    };
+    assert_eq!(verify(&tm, &certificate), Ok(()));
+    let json = Json::parse(&certificate.to_json().to_string()).unwrap();
+    assert_eq!(Certificate::from_json(&json).as_ref(), Ok(&certificate));
+
+    if let Proof::ClosedTapeLanguage { language } = &mut certificate.proof {
+        language.left.clear();
+    }
+    assert!(verify(&tm, &certificate).is_err());
+}
+
+#[test]
+fn test_decide_parallel() {
+    use std::path::Path;
+
+    for path in [
+        "cycler",
+        "translated_cycler",
+        "bouncer",
+        "closed_tape_language",
+        "backward",
+    ] {
+        let tm = TuringMachine::new(Path::new(&format!("examples/deciders/{path}.turing")));
+        let decision = decide_parallel(&tm, 1_000_000, 5);
+        assert!(matches!(decision, Decision::NeverHalts(_)), "{path}");
+    }
+
+    let tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    assert_eq!(decide_parallel(&tm, 1000, 5), decide(&tm, 1000, 5));
+    let tm = TuringMachine::new(Path::new(
+        "examples/busy_bever/busy_bever_5_best_currently_known.turing",
+    ));
+    assert_eq!(decide_parallel(&tm, 1000, 5), Decision::Undecided);
+}
+
+#[test]
+fn test_decide_within() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    let limits = Limits {
+        max_steps: 1000,
+        ..Limits::NONE
+    };
+    assert_eq!(decide_within(&tm, &limits, 0), decide(&tm, 1000, 0));
+    let limits = Limits {
+        max_tape_cells: 5,
+        ..limits
+    };
+    assert_eq!(decide_within(&tm, &limits, 0), Decision::Undecided);
+}
@@ -0,0 +1,216 @@
+use std::{collections::VecDeque, fmt::Display};
+
+use crate::turing::{Instruction, TapeEntry, TuringMachine};
+
+/// A difference between the transition tables of two machines.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change<'a> {
+    /// The second machine has a transition the first one lacks.
+    Added(&'a Instruction),
+    Removed(&'a Instruction),
+    /// Both machines have a transition for the state and symbol, but they
+    /// write, move or continue differently.
+    Changed(&'a Instruction, &'a Instruction),
+}
+
+/// Differences between two machines, after matching their states.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diff<'a> {
+    a: &'a TuringMachine,
+    b: &'a TuringMachine,
+    /// State of `b` matched with each state of `a`.
+    matching: Vec<Option<usize>>,
+    pub changes: Vec<Change<'a>>,
+}
+
+impl Diff<'_> {
+    /// Pairs of matched states with different names.
+    pub fn renamed(&self) -> Vec<(&str, &str)> {
+        self.matching
+            .iter()
+            .enumerate()
+            .filter_map(|(state, other)| {
+                let (a, b) = (&self.a.states()[state], &self.b.states()[(*other)?]);
+                (a != b).then_some((a.as_str(), b.as_str()))
+            })
+            .collect()
+    }
+}
+
+/// An instruction in the format of machine files.
+struct Line<'a>(&'a TuringMachine, &'a Instruction);
+
+impl Display for Line<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Line(tm, instruction) = self;
+        write!(
+            f,
+            "{} {} -> {} {} {}",
+            tm.states()[instruction.state],
+            instruction.entry,
+            match instruction.new_state {
+                Some(state) => &tm.states()[state],
+                None => instruction.halt.name(),
+            },
+            instruction.new_entry,
+            instruction.direction.letter()
+        )
+    }
+}
+
+impl Display for Diff<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (a, b) in self.renamed() {
+            writeln!(f, "renamed {a} -> {b}")?;
+        }
+        for change in &self.changes {
+            match change {
+                Change::Added(b) => writeln!(f, "added   {}", Line(self.b, b))?,
+                Change::Removed(a) => writeln!(f, "removed {}", Line(self.a, a))?,
+                Change::Changed(a, b) => {
+                    writeln!(f, "changed {}  to  {}", Line(self.a, a), Line(self.b, b))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn instruction(tm: &TuringMachine, state: usize, entry: TapeEntry) -> Option<&Instruction> {
+    tm.instructions()
+        .iter()
+        .find(|instruction| instruction.state == state && instruction.entry == entry)
+}
+
+/// Compares the transition tables of two machines regardless of the names
+/// and order of their states.
+///
+/// States are matched by walking both machines in parallel from their start
+/// states: whenever matched states go to yet unmatched states on the same
+/// symbol, those are matched as well. States left over after the walk are
+/// matched by name. Transitions of unmatched states count as added or
+/// removed.
+pub fn diff<'a>(a: &'a TuringMachine, b: &'a TuringMachine) -> Diff<'a> {
+    let mut matching = vec![None; a.states().len()];
+    let mut matched = vec![false; b.states().len()];
+    let mut queue = VecDeque::new();
+    if !matching.is_empty() && !matched.is_empty() {
+        matching[0] = Some(0);
+        matched[0] = true;
+        queue.push_back((0, 0));
+    }
+    while let Some((state_a, state_b)) = queue.pop_front() {
+        for instruction_a in a.instructions() {
+            if instruction_a.state != state_a {
+                continue;
+            }
+            let next = instruction(b, state_b, instruction_a.entry)
+                .and_then(|instruction_b| instruction_a.new_state.zip(instruction_b.new_state));
+            if let Some((next_a, next_b)) = next {
+                if matching[next_a].is_none() && !matched[next_b] {
+                    matching[next_a] = Some(next_b);
+                    matched[next_b] = true;
+                    queue.push_back((next_a, next_b));
+                }
+            }
+        }
+    }
+    for (state, name) in a.states().iter().enumerate() {
+        if matching[state].is_none() {
+            let other = b.states().iter().position(|other| other == name);
+            if let Some(other) = other.filter(|other| !matched[*other]) {
+                matching[state] = Some(other);
+                matched[other] = true;
+            }
+        }
+    }
+
+    let mut changes = vec![];
+    for (state, other) in matching.iter().enumerate() {
+        let other = match other {
+            Some(other) => *other,
+            None => {
+                changes.extend(
+                    a.instructions()
+                        .iter()
+                        .filter(|instruction| instruction.state == state)
+                        .map(Change::Removed),
+                );
+                continue;
+            }
+        };
+        let mut symbols: Vec<TapeEntry> = a
+            .instructions()
+            .iter()
+            .filter(|instruction| instruction.state == state)
+            .chain(
+                b.instructions()
+                    .iter()
+                    .filter(|instruction| instruction.state == other),
+            )
+            .map(|instruction| instruction.entry)
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        for entry in symbols {
+            match (instruction(a, state, entry), instruction(b, other, entry)) {
+                (Some(ia), Some(ib)) => {
+                    let same_target = match (ia.new_state, ib.new_state) {
+                        (Some(next_a), Some(next_b)) => matching[next_a] == Some(next_b),
+                        (None, None) => ia.halt == ib.halt,
+                        _ => false,
+                    };
+                    if !same_target || ia.new_entry != ib.new_entry || ia.direction != ib.direction
+                    {
+                        changes.push(Change::Changed(ia, ib));
+                    }
+                }
+                (Some(ia), None) => changes.push(Change::Removed(ia)),
+                (None, Some(ib)) => changes.push(Change::Added(ib)),
+                (None, None) => unreachable!("the symbol is read by one of the states"),
+            }
+        }
+    }
+    for (state, _) in matched.iter().enumerate().filter(|(_, matched)| !**matched) {
+        changes.extend(
+            b.instructions()
+                .iter()
+                .filter(|instruction| instruction.state == state)
+                .map(Change::Added),
+        );
+    }
+
+    Diff {
+        a,
+        b,
+        matching,
+        changes,
+    }
+}
+
+#[test]
+fn test_diff() {
+    let a = TuringMachine::new(std::path::Path::new(
+        "examples/busy_bever/busy_bever_2.turing",
+    ));
+    // The same machine with renamed states in a different order.
+    let b =
+        TuringMachine::parse("X 0 -> Y 1 R\nY 1 -> Halt 1 R\nY 0 -> X 1 L\nX 1 -> Y 1 L").unwrap();
+    let renamed = diff(&a, &b);
+    assert!(renamed.changes.is_empty());
+    assert_eq!(renamed.renamed(), vec![("A", "X"), ("B", "Y")]);
+
+    let b = TuringMachine::parse(
+        "X 0 -> Y 1 R\nX 1 -> Y 1 L\nY 0 -> X 0 L\nY 2 -> Halt 1 R\nZ 0 -> X 1 R",
+    )
+    .unwrap();
+    assert_eq!(
+        diff(&a, &b).to_string(),
+        "renamed A -> X\n\
+         renamed B -> Y\n\
+         changed B 0 -> A 1 L  to  Y 0 -> X 0 L\n\
+         removed B 1 -> Halt 1 R\n\
+         added   Y 2 -> Halt 1 R\n\
+         added   Z 0 -> X 1 R\n"
+    );
+}
@@ -0,0 +1,81 @@
+use std::fmt::Display;
+
+use crate::turing::TuringMachine;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A rolling hash of every step of a run, to check that two runs or two
+/// implementations behave exactly the same.
+///
+/// Every step contributes 17 bytes to a 64 bit FNV-1a hash: the new state as
+/// a little-endian `u64` (`u64::MAX` once the machine halted), the head
+/// position relative to the starting cell as a little-endian `i64` and the
+/// symbol written.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Digest {
+    hash: u64,
+    pub steps: u128,
+}
+
+impl Digest {
+    pub fn new() -> Self {
+        Digest {
+            hash: FNV_OFFSET_BASIS,
+            steps: 0,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Adds the step `tm` just took.
+    pub fn record(&mut self, tm: &TuringMachine) {
+        let written = match tm.last_instruction() {
+            Some(instruction) => tm.instructions()[instruction].new_entry,
+            None => return,
+        };
+        let state = tm.state().map_or(u64::MAX, |state| state as u64);
+        let position = tm.head() as i64 - tm.origin() as i64;
+        self.write(&state.to_le_bytes());
+        self.write(&position.to_le_bytes());
+        self.write(&[written]);
+        self.steps += 1;
+    }
+}
+
+impl Display for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.hash)
+    }
+}
+
+#[cfg(test)]
+fn digest(tm: &mut TuringMachine) -> Digest {
+    let mut digest = Digest::new();
+    while tm.step() {
+        digest.record(tm);
+    }
+    digest
+}
+
+#[test]
+fn test_digest() {
+    use std::path::Path;
+
+    assert_eq!(Digest::new().to_string(), "cbf29ce484222325");
+
+    let path = Path::new("examples/busy_bever/busy_bever_2.turing");
+    let bb2 = digest(&mut TuringMachine::new(path));
+    assert_eq!(bb2.steps, 6);
+    assert_eq!(bb2, digest(&mut TuringMachine::new(path)));
+    assert_eq!(bb2.to_string(), "98ba45acec941834");
+
+    // Writing a different symbol on the last step changes the digest.
+    let changed = TuringMachine::parse("A 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> Halt 0 R");
+    assert_ne!(digest(&mut changed.unwrap()), bb2);
+}
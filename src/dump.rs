@@ -0,0 +1,220 @@
+use std::{fs, path::Path};
+
+use crate::{
+    json::Json,
+    turing::{self, TapeEntry, TuringMachine},
+};
+
+/// Starts a tape in the binary format.
+const MAGIC: &[u8; 4] = b"TAPE";
+
+/// A format to write a tape in, chosen by the extension of the file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    /// `.bin`: [`MAGIC`], then the number of cells, the head and the
+    /// starting cell as little endian `u64`s, then a byte per cell.
+    Binary,
+    /// `.txt`: a line `head <H> origin <O>`, then the cells separated by
+    /// spaces, which `--input` reads back as a word. Without the first line
+    /// it is just a word, with the head on its first cell.
+    Text,
+    /// `.json`: an object with the fields `head`, `origin` and `cells`.
+    Json,
+}
+
+impl Format {
+    pub fn of(path: &Path) -> Result<Self, String> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("bin") => Ok(Format::Binary),
+            Some("txt") => Ok(Format::Text),
+            Some("json") => Ok(Format::Json),
+            _ => Err(format!(
+                "can't tell the format of {}, expected .bin, .txt or .json",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// Cells of a tape, with the head and the starting cell as indices on it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Tape {
+    pub cells: Vec<TapeEntry>,
+    pub head: usize,
+    pub origin: usize,
+}
+
+/// The tape of `tm` with the head and the starting cell as indices on it.
+pub fn encode(tm: &TuringMachine, format: Format) -> Vec<u8> {
+    let (head, origin) = (tm.head(), tm.origin());
+    match format {
+        Format::Binary => {
+            let mut bytes = MAGIC.to_vec();
+            for number in [tm.tape().len(), head, origin] {
+                bytes.extend((number as u64).to_le_bytes());
+            }
+            bytes.extend(tm.tape());
+            bytes
+        }
+        Format::Text => {
+            let cells: Vec<String> = tm.tape().iter().map(u8::to_string).collect();
+            format!("head {} origin {}\n{}\n", head, origin, cells.join(" ")).into_bytes()
+        }
+        Format::Json => {
+            let cells = tm.tape().iter().map(|entry| Json::from(*entry)).collect();
+            let json = Json::object([
+                ("head", head.into()),
+                ("origin", origin.into()),
+                ("cells", Json::Array(cells)),
+            ]);
+            format!("{}\n", json).into_bytes()
+        }
+    }
+}
+
+/// Writes the tape of `tm` to `path` in the format of its extension.
+pub fn write(path: &Path, tm: &TuringMachine) -> Result<(), String> {
+    let bytes = encode(tm, Format::of(path)?);
+    fs::write(path, bytes).map_err(|why| why.to_string())
+}
+
+/// Reads a tape from `bytes` in `format`.
+pub fn decode(bytes: &[u8], format: Format) -> Result<Tape, String> {
+    let tape = match format {
+        Format::Binary => {
+            let header = bytes
+                .strip_prefix(MAGIC)
+                .filter(|rest| rest.len() >= 24)
+                .ok_or("not a binary tape")?;
+            let number = |index: usize| {
+                let word = header[index * 8..(index + 1) * 8].try_into();
+                u64::from_le_bytes(word.expect("a number has eight bytes")) as usize
+            };
+            let cells = &header[24..];
+            if cells.len() != number(0) {
+                return Err(format!(
+                    "expected {} cells, found {}",
+                    number(0),
+                    cells.len()
+                ));
+            }
+            Tape {
+                cells: cells.to_vec(),
+                head: number(1),
+                origin: number(2),
+            }
+        }
+        Format::Text => {
+            let text = std::str::from_utf8(bytes).map_err(|why| why.to_string())?;
+            let (head, origin, word) = match text.split_once('\n') {
+                Some((first, word)) if first.starts_with("head ") => {
+                    match first.split_whitespace().collect::<Vec<_>>().as_slice() {
+                        ["head", head, "origin", origin] => (
+                            head.parse().map_err(|_| format!("invalid head '{head}'"))?,
+                            origin
+                                .parse()
+                                .map_err(|_| format!("invalid origin '{origin}'"))?,
+                            word,
+                        ),
+                        _ => return Err(format!("invalid first line '{first}'")),
+                    }
+                }
+                _ => (0, 0, text),
+            };
+            Tape {
+                cells: turing::parse_word(word.trim())?,
+                head,
+                origin,
+            }
+        }
+        Format::Json => {
+            let text = std::str::from_utf8(bytes).map_err(|why| why.to_string())?;
+            let json = Json::parse(text)?;
+            let index = |key: &str| {
+                usize::try_from(json.int_field(key)?).map_err(|_| format!("invalid {key}"))
+            };
+            let cells = json
+                .field("cells")?
+                .as_array()
+                .ok_or("cells isn't an array")?
+                .iter()
+                .map(|cell| {
+                    cell.as_int()
+                        .and_then(|cell| TapeEntry::try_from(cell).ok())
+                        .ok_or_else(|| format!("invalid cell {cell}"))
+                })
+                .collect::<Result<_, _>>()?;
+            Tape {
+                cells,
+                head: index("head")?,
+                origin: index("origin")?,
+            }
+        }
+    };
+    let len = tape.cells.len().max(1);
+    if tape.head >= len || tape.origin >= len {
+        return Err(format!(
+            "head {} or origin {} isn't on the {} cells",
+            tape.head, tape.origin, len
+        ));
+    }
+    Ok(tape)
+}
+
+/// Reads a tape from `path`, written by [`write`] or holding just a word.
+/// Files that aren't `.bin` or `.json` are read as text.
+pub fn read(path: &Path) -> Result<Tape, String> {
+    let bytes = fs::read(path).map_err(|why| why.to_string())?;
+    decode(&bytes, Format::of(path).unwrap_or(Format::Text))
+}
+
+#[test]
+fn test_dump() {
+    let mut tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_2.turing"));
+    while tm.step() {}
+    let (head, origin) = (tm.head(), tm.origin());
+    let len = tm.tape().len();
+
+    let bytes = encode(&tm, Format::Binary);
+    assert_eq!(&bytes[..4], MAGIC);
+    assert_eq!(bytes[4..12], (len as u64).to_le_bytes());
+    assert_eq!(bytes[12..20], (head as u64).to_le_bytes());
+    assert_eq!(bytes[20..28], (origin as u64).to_le_bytes());
+    assert!(bytes[28..].iter().eq(tm.tape()));
+
+    let text = String::from_utf8(encode(&tm, Format::Text)).unwrap();
+    let (first, word) = text.trim_end().split_once('\n').unwrap();
+    assert_eq!(first, format!("head {head} origin {origin}"));
+    assert!(turing::parse_word(word).unwrap().iter().eq(tm.tape()));
+
+    let json = Json::parse(&String::from_utf8(encode(&tm, Format::Json)).unwrap()).unwrap();
+    assert_eq!(json.int_field("head"), Ok(head as i128));
+    assert_eq!(json.int_field("origin"), Ok(origin as i128));
+    assert_eq!(json.field("cells").unwrap().as_array().unwrap().len(), len);
+
+    // All formats read back what they wrote.
+    let expected = Tape {
+        cells: tm.tape().iter().copied().collect(),
+        head,
+        origin,
+    };
+    for format in [Format::Binary, Format::Text, Format::Json] {
+        assert_eq!(decode(&encode(&tm, format), format), Ok(expected.clone()));
+    }
+    let mut loaded = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_2.turing"));
+    loaded.set_tape(&expected.cells, head, origin);
+    assert_eq!(encode(&loaded, Format::Binary), encode(&tm, Format::Binary));
+    assert_eq!(
+        decode(b"0 12 3\n", Format::Text),
+        Ok(Tape {
+            cells: vec![0, 12, 3],
+            head: 0,
+            origin: 0
+        })
+    );
+    assert!(decode(b"head 4 origin 0\n1 1", Format::Text).is_err());
+    assert!(decode(&encode(&tm, Format::Binary)[..30], Format::Binary).is_err());
+
+    assert_eq!(Format::of(Path::new("out.json")), Ok(Format::Json));
+    assert!(Format::of(Path::new("out")).is_err());
+}
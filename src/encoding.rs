@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+
+use crate::{scan, turing::TapeEntry};
+
+/// How numbers are written onto and read back from the tape.
+///
+/// Multiple numbers are separated by a single blank cell. The digits are the
+/// two smallest symbols other than the blank, `1` and `2` with the blank `0`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum Encoding {
+    /// `n` is written as `n + 1` cells containing the first digit, so that
+    /// `0` takes a cell as well.
+    Unary,
+    /// `n` is written in binary, most significant bit first, a `0` bit as
+    /// the first digit and a `1` bit as the second.
+    Binary,
+}
+
+/// The two smallest symbols other than `blank`.
+fn digits(blank: TapeEntry) -> [TapeEntry; 2] {
+    match blank {
+        0 => [1, 2],
+        1 => [0, 2],
+        _ => [0, 1],
+    }
+}
+
+impl Encoding {
+    /// Writes `numbers` for a machine with the blank symbol `blank`.
+    pub fn encode(&self, numbers: &[u128], blank: TapeEntry) -> Vec<TapeEntry> {
+        let [zero, one] = digits(blank);
+        let mut tape = vec![];
+        for (i, number) in numbers.iter().enumerate() {
+            if i > 0 {
+                tape.push(blank);
+            }
+            match self {
+                Encoding::Unary => tape.extend((0..=*number).map(|_| zero)),
+                Encoding::Binary => {
+                    let bits = (u128::BITS - number.leading_zeros()).max(1);
+                    for bit in (0..bits).rev() {
+                        tape.push(if number >> bit & 1 == 1 { one } else { zero });
+                    }
+                }
+            }
+        }
+        tape
+    }
+
+    /// Reads the numbers written with [`Self::encode`] for `blank`,
+    /// ignoring blank cells at either end.
+    pub fn decode(
+        &self,
+        tape: &VecDeque<TapeEntry>,
+        blank: TapeEntry,
+    ) -> Result<Vec<u128>, String> {
+        let Some((start, end)) = scan::written(tape, blank) else {
+            return Ok(vec![]);
+        };
+
+        let content: Vec<TapeEntry> = tape.range(start..=end).copied().collect();
+        content
+            .split(|entry| *entry == blank)
+            .map(|number| self.decode_number(number, digits(blank)))
+            .collect()
+    }
+
+    fn decode_number(
+        &self,
+        cells: &[TapeEntry],
+        [zero, one]: [TapeEntry; 2],
+    ) -> Result<u128, String> {
+        if *self == Encoding::Unary && cells.is_empty() {
+            return Err("empty Unary number".to_string());
+        }
+        let mut number: u128 = 0;
+        for cell in cells {
+            number = match (self, *cell) {
+                (Encoding::Unary, cell) if cell == zero => number.checked_add(1),
+                (Encoding::Binary, cell) if cell == zero => number.checked_mul(2),
+                (Encoding::Binary, cell) if cell == one => {
+                    number.checked_mul(2).and_then(|n| n.checked_add(1))
+                }
+                _ => return Err(format!("unexpected symbol {} in {:?} number", cell, self)),
+            }
+            .ok_or("number too large")?;
+        }
+        match self {
+            Encoding::Unary => Ok(number - 1),
+            Encoding::Binary => Ok(number),
+        }
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    for encoding in [Encoding::Unary, Encoding::Binary] {
+        for blank in [0, 1, 2, 5] {
+            for numbers in [&[5, 3, 1, 12][..], &[0, 3, 0], &[0], &[1]] {
+                let tape = encoding.encode(numbers, blank).into();
+                assert_eq!(encoding.decode(&tape, blank), Ok(numbers.to_vec()));
+            }
+        }
+    }
+    assert_eq!(Encoding::Unary.encode(&[0, 2], 0), vec![1, 0, 1, 1, 1]);
+    assert!(Encoding::Unary.decode(&vec![1, 0, 0, 1].into(), 0).is_err());
+    // With the blank `2`, the `1` bits are `1` rather than blanks.
+    assert_eq!(Encoding::Binary.encode(&[5], 2), vec![1, 0, 1]);
+    assert_eq!(
+        Encoding::Binary.decode(&vec![2, 1, 0, 1, 2].into(), 2),
+        Ok(vec![5])
+    );
+    assert_eq!(Encoding::Binary.encode(&[0, 6], 0), vec![1, 0, 2, 2, 1]);
+}
+
+#[test]
+fn test_unary_add() {
+    use crate::turing::TuringMachine;
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/arithmetic/unary_add.turing"));
+    for (a, b) in [(5, 3), (0, 4), (0, 0)] {
+        let mut tm = tm.clone();
+        tm.set_input(&Encoding::Unary.encode(&[a, b], tm.blank()));
+        while tm.step() {}
+        assert_eq!(
+            Encoding::Unary.decode(tm.tape(), tm.blank()),
+            Ok(vec![a + b])
+        );
+    }
+}
+
+#[test]
+fn test_binary_increment() {
+    use crate::turing::TuringMachine;
+    use std::path::Path;
+
+    for number in [0, 5, 7, 8] {
+        let mut tm = TuringMachine::new(Path::new("examples/arithmetic/binary_increment.turing"));
+        tm.set_input(&Encoding::Binary.encode(&[number], tm.blank()));
+        while tm.step() {}
+        assert_eq!(
+            Encoding::Binary.decode(tm.tape(), tm.blank()),
+            Ok(vec![number + 1])
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::io::Write;
+
+use crate::{lz::Compressor, turing::TapeEntry};
+
+/// How much structure a tape has: machines writing a few repeated patterns
+/// compress to a small part of their tape, chaotic ones don't.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TapeStats {
+    /// Shannon entropy of the symbols in bits per cell, `0` if all cells
+    /// are the same.
+    pub entropy: f64,
+    /// Bytes the tape takes compressed by [`Compressor`], a byte per cell.
+    pub compressed: usize,
+}
+
+impl TapeStats {
+    pub fn new<'a>(cells: impl IntoIterator<Item = &'a TapeEntry> + Clone) -> Self {
+        let mut counts = [0u128; 1 << TapeEntry::BITS];
+        for cell in cells.clone() {
+            counts[*cell as usize] += 1;
+        }
+        let total: u128 = counts.iter().sum();
+        let entropy = counts
+            .iter()
+            .filter(|count| **count > 0)
+            .map(|count| {
+                let p = *count as f64 / total as f64;
+                -p * p.log2()
+            })
+            .sum::<f64>()
+            .max(0.0);
+        let mut out = vec![];
+        let mut compressor = Compressor::new(&mut out);
+        let bytes: Vec<TapeEntry> = cells.into_iter().copied().collect();
+        compressor
+            .write_all(&bytes)
+            .and_then(|()| compressor.flush())
+            .expect("writing to memory doesn't fail");
+        TapeStats {
+            entropy,
+            compressed: out.len(),
+        }
+    }
+}
+
+#[test]
+fn test_entropy() {
+    let stats = TapeStats::new(&[1; 1000]);
+    assert_eq!(stats.entropy, 0.0);
+    assert!(stats.compressed < 20);
+
+    let stats = TapeStats::new(&[0, 1, 0, 1]);
+    assert_eq!(stats.entropy, 1.0);
+    assert_eq!(TapeStats::new(&[0, 1, 2, 3]).entropy, 2.0);
+
+    // A counter in binary is less regular than one repeating pattern.
+    let counter: Vec<TapeEntry> = (0u32..1000)
+        .flat_map(|n| (0..10).map(move |bit| (n >> bit & 1) as TapeEntry))
+        .collect();
+    let pattern: Vec<TapeEntry> = [1, 0, 1, 1].iter().copied().cycle().take(10_000).collect();
+    assert!(TapeStats::new(&counter).compressed > TapeStats::new(&pattern).compressed);
+    assert_eq!(TapeStats::new(&[]).compressed, 0);
+}
@@ -0,0 +1,205 @@
+use std::fmt::Display;
+
+use crate::{
+    scan,
+    turing::{HaltReason, TapeEntry, TuringMachine},
+};
+
+/// How a run ended and what was left on the tape.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Outcome {
+    /// `None` if the step budget ran out.
+    pub halt: Option<HaltReason>,
+    /// Position of the first non-blank cell relative to the starting cell.
+    pub start: isize,
+    /// Tape contents without blanks at either end.
+    pub tape: Vec<TapeEntry>,
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tape: Vec<String> = self.tape.iter().map(TapeEntry::to_string).collect();
+        match self.halt {
+            Some(reason) => write!(f, "{}", reason)?,
+            None => write!(f, "step limit")?,
+        }
+        write!(f, ", tape at {}: {}", self.start, tape.join(" "))
+    }
+}
+
+/// Runs a copy of `tm` on `input`. Missing transitions reject.
+pub fn outcome(tm: &TuringMachine, input: &[TapeEntry], max_steps: u128) -> Outcome {
+    let mut tm = tm.clone();
+    tm.set_reject_undefined(true);
+    tm.set_input(input);
+    while tm.num_steps < max_steps && tm.step() {}
+    observe(&tm)
+}
+
+/// How `tm` halted so far and what is on its tape.
+fn observe(tm: &TuringMachine) -> Outcome {
+    let tape = tm.tape();
+    let (start, tape) = match scan::written(tape, tm.blank()) {
+        Some((first, last)) => (
+            first as isize - tm.origin() as isize,
+            tape.range(first..=last).copied().collect(),
+        ),
+        None => (0, vec![]),
+    };
+
+    Outcome {
+        halt: tm.halt_reason,
+        start,
+        tape,
+    }
+}
+
+/// Position of the head relative to the starting cell.
+fn head(tm: &TuringMachine) -> isize {
+    tm.head() as isize - tm.origin() as isize
+}
+
+/// Runs copies of `a` and `b` on `input` side by side, a step of each at
+/// a time, until both halted or `max_steps` steps were taken. `lap` gets
+/// both machines after every step. Missing transitions reject.
+///
+/// Gives the first step after which the machines differ in how they
+/// halted, their tapes or their heads. State names aren't compared, so a
+/// machine can be raced against a minimized copy of itself.
+pub fn race(
+    a: &TuringMachine,
+    b: &TuringMachine,
+    input: &[TapeEntry],
+    max_steps: u128,
+    mut lap: impl FnMut(u128, &TuringMachine, &TuringMachine),
+) -> Option<u128> {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    for tm in [&mut a, &mut b] {
+        tm.set_reject_undefined(true);
+        tm.set_input(input);
+    }
+    for step in 1..=max_steps {
+        let stepped_a = a.step();
+        let stepped_b = b.step();
+        if !stepped_a && !stepped_b {
+            break;
+        }
+        lap(step, &a, &b);
+        if head(&a) != head(&b) || observe(&a) != observe(&b) {
+            return Some(step);
+        }
+    }
+    None
+}
+
+/// The state of `tm`, or how it halted, and its tape with brackets around
+/// the cell under the head.
+pub fn view(tm: &TuringMachine) -> String {
+    let state = match (tm.state(), tm.halt_reason) {
+        (Some(state), _) => tm.states()[state].clone(),
+        (None, Some(reason)) => reason.to_string(),
+        (None, None) => "stopped".to_string(),
+    };
+    let mut tape = String::new();
+    for (i, entry) in tm.tape().iter().enumerate() {
+        let glyph = tm.glyph(*entry);
+        if i == tm.head() {
+            tape += &format!("[{glyph}]");
+        } else if i == tm.head() + 1 {
+            tape += &glyph;
+        } else {
+            tape += &format!(" {glyph}");
+        }
+    }
+    format!("{} {}", state, tape.trim_start())
+}
+
+/// Result of co-simulating two machines on a list of inputs.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// Both machines behaved the same on every input. `undecided` inputs hit
+    /// the step budget in both machines.
+    Equivalent { undecided: usize },
+    /// The machines behaved differently on the input with this index.
+    Diverged {
+        input: usize,
+        a: Outcome,
+        b: Outcome,
+    },
+}
+
+/// Runs both machines on every input and compares their halting behavior
+/// and final tapes.
+pub fn compare(
+    a: &TuringMachine,
+    b: &TuringMachine,
+    inputs: &[Vec<TapeEntry>],
+    max_steps: u128,
+) -> Comparison {
+    let mut undecided = 0;
+    for (i, input) in inputs.iter().enumerate() {
+        let outcome_a = outcome(a, input, max_steps);
+        let outcome_b = outcome(b, input, max_steps);
+        if outcome_a.halt.is_none() && outcome_b.halt.is_none() {
+            undecided += 1;
+        } else if outcome_a != outcome_b {
+            return Comparison::Diverged {
+                input: i,
+                a: outcome_a,
+                b: outcome_b,
+            };
+        }
+    }
+    Comparison::Equivalent { undecided }
+}
+
+#[test]
+fn test_equivalent() {
+    use crate::minimize::minimize;
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/minimize/redundant.turing"));
+    let inputs = vec![vec![], vec![1], vec![0, 1, 1], vec![1, 1, 0, 1]];
+
+    assert_eq!(
+        compare(&tm, &minimize(&tm), &inputs, 1000),
+        Comparison::Equivalent { undecided: 0 }
+    );
+}
+
+#[test]
+fn test_diverged() {
+    use std::path::Path;
+
+    let a = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_2.turing"));
+    let b = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_3.turing"));
+
+    match compare(&a, &b, &[vec![]], 1000) {
+        Comparison::Diverged { input, a, b } => {
+            assert_eq!(input, 0);
+            assert_eq!(a.tape, vec![1; 4]);
+            assert_eq!(b.tape, vec![1; 6]);
+        }
+        comparison => panic!("unexpected {:?}", comparison),
+    }
+}
+
+#[test]
+fn test_race() {
+    use crate::minimize::minimize;
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/minimize/redundant.turing"));
+    let mut laps = 0;
+    let diverged = race(&tm, &minimize(&tm), &[0, 1, 1], 1000, |_, _, _| laps += 1);
+    assert_eq!(diverged, None);
+    assert!(laps > 0);
+
+    // Busy beaver 2 halts after 6 steps, when busy beaver 3 keeps running.
+    let a = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_2.turing"));
+    let b = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_3.turing"));
+    let mut last = (String::new(), String::new());
+    let diverged = race(&a, &b, &[], 1000, |_, a, b| last = (view(a), view(b)));
+    assert!(diverged.is_some_and(|step| step <= 6));
+    assert!(last.0 != last.1);
+}
@@ -0,0 +1,253 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use crate::{
+    bbchallenge,
+    decide::{Decision, Proof},
+    sqlite::{self, Value},
+    turing::TuringMachine,
+};
+
+const HEADER: &str = "id,machine,verdict,decider,steps,seconds,peak_memory";
+/// The table of a results database, and its columns.
+pub const TABLE: &str = "results";
+const COLUMNS: [&str; 7] = [
+    "id INTEGER",
+    "machine TEXT",
+    "verdict TEXT",
+    "decider TEXT",
+    "steps INTEGER",
+    "seconds REAL",
+    "peak_memory INTEGER",
+];
+
+/// What is logged about a machine.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Row {
+    pub id: u64,
+    /// The machine in the bbchallenge format.
+    pub machine: String,
+    /// `halts`, `never_halts` or `undecided`.
+    pub verdict: &'static str,
+    /// The decider that reached the verdict, empty for undecided machines.
+    pub decider: String,
+    pub steps: u128,
+    pub seconds: f64,
+    /// In bytes, see [`peak_memory`].
+    pub peak_memory: Option<u64>,
+}
+
+enum Sink {
+    Csv(BufWriter<File>),
+    #[cfg(feature = "parquet")]
+    Parquet(crate::parquet::Writer),
+    /// The rows of a SQLite database, written all at once at the end.
+    Sqlite(Box<Path>, Vec<Vec<Value>>),
+}
+
+impl Sink {
+    /// A CSV file, or a Parquet file if the name ends in `.parquet`, which
+    /// needs the `parquet` feature.
+    fn create(path: &Path) -> Result<Self, String> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "parquet")
+        {
+            #[cfg(feature = "parquet")]
+            return Ok(Sink::Parquet(crate::parquet::Writer::create(path)?));
+            #[cfg(not(feature = "parquet"))]
+            return Err("Parquet logs need the parquet feature".to_string());
+        }
+        let mut file = BufWriter::new(File::create(path).map_err(|why| why.to_string())?);
+        writeln!(file, "{HEADER}").map_err(|why| why.to_string())?;
+        Ok(Sink::Csv(file))
+    }
+}
+
+/// Files with a [`Row`] per machine of a run, for analyzing a search
+/// afterwards.
+pub struct Log {
+    sinks: Vec<Sink>,
+}
+
+impl Log {
+    /// Creates the log `file` and the SQLite database `database` with a
+    /// [`TABLE`] of the rows, replacing existing files. Gives no log
+    /// without either.
+    pub fn open(file: Option<&Path>, database: Option<&Path>) -> Result<Option<Self>, String> {
+        let mut sinks = vec![];
+        if let Some(path) = file {
+            sinks.push(Sink::create(path)?);
+        }
+        if let Some(path) = database {
+            // Fail early rather than after the run.
+            sqlite::write(path, TABLE, &COLUMNS, &[])?;
+            sinks.push(Sink::Sqlite(path.into(), vec![]));
+        }
+        Ok((!sinks.is_empty()).then_some(Log { sinks }))
+    }
+
+    /// Appends the line for machine `id`, which was decided with `decider`
+    /// if it halts. Resets the peak memory for the next machine.
+    pub fn record(
+        &mut self,
+        id: u64,
+        tm: &TuringMachine,
+        decision: &Decision,
+        decider: &str,
+        steps: u128,
+        time: Duration,
+    ) -> Result<(), String> {
+        let (verdict, decider) = match decision {
+            Decision::Halts { .. } => ("halts", decider),
+            Decision::NeverHalts(proof) => ("never_halts", proof.decider()),
+            Decision::Undecided => ("undecided", ""),
+        };
+        let row = Row {
+            id,
+            machine: bbchallenge::format_machine(tm),
+            verdict,
+            decider: decider.to_string(),
+            steps,
+            seconds: time.as_secs_f64(),
+            peak_memory: peak_memory(),
+        };
+        reset_peak_memory();
+        for sink in &mut self.sinks {
+            match sink {
+                Sink::Csv(file) => writeln!(
+                    file,
+                    "{},{},{},{},{},{:.6},{}",
+                    row.id,
+                    row.machine,
+                    row.verdict,
+                    row.decider,
+                    row.steps,
+                    row.seconds,
+                    row.peak_memory
+                        .map_or(String::new(), |bytes| bytes.to_string())
+                )
+                .map_err(|why| why.to_string())?,
+                #[cfg(feature = "parquet")]
+                Sink::Parquet(writer) => writer.write(&row)?,
+                Sink::Sqlite(_, rows) => rows.push(vec![
+                    Value::Integer(row.id as i64),
+                    Value::Text(row.machine.clone()),
+                    Value::Text(row.verdict.to_string()),
+                    match row.decider.is_empty() {
+                        true => Value::Null,
+                        false => Value::Text(row.decider.clone()),
+                    },
+                    Value::Integer(i64::try_from(row.steps).unwrap_or(i64::MAX)),
+                    Value::Real(row.seconds),
+                    row.peak_memory
+                        .map_or(Value::Null, |bytes| Value::Integer(bytes as i64)),
+                ]),
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes out everything logged, which Parquet logs and databases need
+    /// to be readable once the run finishes.
+    pub fn finish(&mut self) -> Result<(), String> {
+        for sink in &mut self.sinks {
+            match sink {
+                Sink::Csv(file) => file.flush().map_err(|why| why.to_string())?,
+                #[cfg(feature = "parquet")]
+                Sink::Parquet(writer) => writer.finish()?,
+                Sink::Sqlite(path, rows) => sqlite::write(path, TABLE, &COLUMNS, rows)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The steps simulated to reach `decision` when the simulation had a
+/// budget of `max_steps` steps: the ones until the machine halted or
+/// repeated, and all of them otherwise.
+pub fn simulated(decision: &Decision, max_steps: u128) -> u128 {
+    match decision {
+        Decision::Halts { steps, .. } => *steps,
+        Decision::NeverHalts(
+            Proof::Cycler { start, period, .. } | Proof::TranslatedCycler { start, period, .. },
+        ) => start + period,
+        _ => max_steps,
+    }
+}
+
+/// The most memory resident at once since the last
+/// [`reset_peak_memory`], in bytes. Only known on Linux, and for the whole
+/// process.
+pub fn peak_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line["VmHWM:".len()..]
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Starts measuring [`peak_memory`] again from the memory resident now,
+/// where the kernel allows it.
+pub fn reset_peak_memory() {
+    let _ = fs::write("/proc/self/clear_refs", "5");
+}
+
+#[test]
+fn test_experiment() {
+    let path = std::env::temp_dir().join(format!("experiment_{}.csv", std::process::id()));
+    let tm = bbchallenge::parse_machine("1RB1LB_1LA1RZ").unwrap();
+    let halts = Decision::Halts {
+        steps: 6,
+        reason: crate::turing::HaltReason::Halt,
+    };
+    assert_eq!(simulated(&halts, 1000), 6);
+    assert_eq!(simulated(&Decision::Undecided, 1000), 1000);
+
+    let database = path.with_extension("sqlite");
+    let mut log = Log::open(Some(&path), Some(&database)).unwrap().unwrap();
+    log.record(3, &tm, &halts, "simulation", 6, Duration::from_millis(1500))
+        .unwrap();
+    log.record(
+        4,
+        &tm,
+        &Decision::Undecided,
+        "simulation",
+        1000,
+        Duration::ZERO,
+    )
+    .unwrap();
+    log.finish().unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    let lines: Vec<Vec<&str>> = content
+        .lines()
+        .map(|line| line.split(',').collect())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0].len(), 7);
+    assert_eq!(
+        lines[1][..6],
+        ["3", "1RB1LB_1LA1RZ", "halts", "simulation", "6", "1.500000"]
+    );
+    assert_eq!(
+        lines[2][..6],
+        ["4", "1RB1LB_1LA1RZ", "undecided", "", "1000", "0.000000"]
+    );
+    fs::remove_file(&path).unwrap();
+
+    let table = sqlite::read(&database, Some(TABLE)).unwrap();
+    assert_eq!(table.columns[3], "decider");
+    assert_eq!(table.rows.len(), 2);
+    assert_eq!(table.rows[0][4], Value::Integer(6));
+    assert_eq!(table.rows[1][3], Value::Null);
+    fs::remove_file(&database).unwrap();
+    assert!(Log::open(None, None).unwrap().is_none());
+}
@@ -0,0 +1,93 @@
+use std::fmt::{Display, Write};
+
+use crate::{digest::Digest, normalize::normalize, turing::TuringMachine, websocket::sha1};
+
+/// A short hash identifying a machine up to the names of its states and
+/// symbols and mirroring, and optionally how its first steps behave.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Fingerprint {
+    /// The first 8 bytes of the SHA-1 of the [`canonical`] form.
+    pub machine: u64,
+    /// The [`Digest`] of the first steps of the canonical form from the
+    /// blank tape, fewer if it halted before.
+    pub behavior: Option<Digest>,
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.machine)?;
+        if let Some(digest) = self.behavior {
+            write!(f, "/{}:{}", digest.steps, digest)?;
+        }
+        Ok(())
+    }
+}
+
+/// The transitions of the tree normal form of `tm`, a line per transition
+/// sorted by state and symbol, with the states as numbers and halting
+/// states by name: `state symbol -> new_state new_symbol direction`.
+///
+/// Unlike the format of machine files, this doesn't change, so neither do
+/// the fingerprints hashing it.
+pub fn canonical(tm: &TuringMachine) -> String {
+    let tm = normalize(tm);
+    let mut instructions: Vec<_> = tm.instructions().iter().collect();
+    instructions.sort_by_key(|i| (i.state, i.entry));
+    let mut out = String::new();
+    for i in instructions {
+        let new_state = match i.new_state {
+            Some(state) => state.to_string(),
+            None => i.halt.name().to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{} {} -> {} {} {}",
+            i.state,
+            i.entry,
+            new_state,
+            i.new_entry,
+            i.direction.letter()
+        );
+    }
+    out
+}
+
+/// The fingerprint of `tm`, with the behavior of the first `steps` steps
+/// if given.
+pub fn fingerprint(tm: &TuringMachine, steps: Option<u128>) -> Fingerprint {
+    let hash = sha1(canonical(tm).as_bytes());
+    let machine = u64::from_be_bytes(hash[..8].try_into().unwrap());
+    let behavior = steps.map(|steps| {
+        let mut tm = normalize(tm);
+        let mut digest = Digest::new();
+        while tm.num_steps < steps && tm.step() {
+            digest.record(&tm);
+        }
+        digest
+    });
+    Fingerprint { machine, behavior }
+}
+
+#[test]
+fn test_fingerprint() {
+    let tm = TuringMachine::new(std::path::Path::new(
+        "examples/busy_bever/busy_bever_2.turing",
+    ));
+    assert_eq!(
+        canonical(&tm),
+        "0 0 -> 1 1 R\n0 1 -> 1 1 L\n1 0 -> 0 1 L\n1 1 -> Halt 1 R\n"
+    );
+    let print = fingerprint(&tm, Some(100));
+    assert_eq!(print.behavior.unwrap().steps, 6);
+    assert_eq!(print.to_string(), "1b4787865a245743/6:98ba45acec941834");
+
+    // Mirrored with the states renamed, it is the same machine.
+    let other =
+        TuringMachine::parse("X 0 -> Y 1 L\nX 1 -> Y 1 R\nY 0 -> X 1 R\nY 1 -> Halt 1 L").unwrap();
+    assert_eq!(fingerprint(&other, Some(100)), print);
+    assert_eq!(fingerprint(&other, None).to_string().len(), 16);
+
+    let different =
+        TuringMachine::parse("A 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> Halt 0 R").unwrap();
+    assert_ne!(fingerprint(&different, None), fingerprint(&tm, None));
+}
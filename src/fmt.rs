@@ -0,0 +1,137 @@
+use crate::turing::{self, Instruction};
+
+/// An instruction together with the comments belonging to it.
+struct Entry {
+    comments: Vec<String>,
+    instruction: Instruction,
+    trailing: Option<String>,
+}
+
+fn comment(text: &str) -> String {
+    match text.trim() {
+        "" => "//".to_string(),
+        text => format!("// {text}"),
+    }
+}
+
+/// Formats the contents of a machine file canonically.
+///
+/// Instructions are sorted by state, in the order the states first appear,
+/// and by the symbol read, with their columns aligned. Comment lines stay in
+/// front of the instruction following them and comments at the end of a
+/// line stay on that line. A comment block at the top of the file that is
+/// separated from the instructions by an empty line is kept as a header.
+/// Metadata headers like `#name` come first, in the order they are given.
+pub fn format(content: &str) -> Result<String, String> {
+    let mut states = vec![];
+    let mut metadata = vec![];
+    let mut header = vec![];
+    let mut pending = vec![];
+    let mut entries = vec![];
+    for (number, line) in content.lines().enumerate() {
+        if let Some((key, value)) = turing::parse_metadata(line) {
+            metadata.push(format!("#{key} {value}").trim_end().to_string());
+            continue;
+        }
+        let (code, trailing) = turing::strip_comment(line);
+        match turing::parse_instruction(code, &mut states) {
+            Ok(Some(instruction)) => entries.push(Entry {
+                comments: std::mem::take(&mut pending),
+                instruction,
+                trailing: trailing.map(comment),
+            }),
+            Ok(None) => match trailing {
+                Some(text) => pending.push(comment(text)),
+                None if entries.is_empty() && header.is_empty() => {
+                    header = std::mem::take(&mut pending)
+                }
+                None => {}
+            },
+            Err(why) => return Err(format!("line {}: {}", number + 1, why)),
+        }
+    }
+    entries.sort_by_key(|entry| (entry.instruction.state, entry.instruction.entry));
+
+    let target = |instruction: &Instruction| match instruction.new_state {
+        Some(state) => states[state].clone(),
+        None => instruction.halt.name().to_string(),
+    };
+    let width = |column: &dyn Fn(&Instruction) -> usize| {
+        entries
+            .iter()
+            .map(|entry| column(&entry.instruction))
+            .max()
+            .unwrap_or(0)
+    };
+    let source_width = width(&|instruction| states[instruction.state].len());
+    let entry_width = width(&|instruction| instruction.entry.to_string().len());
+    let target_width = width(&|instruction| target(instruction).len());
+    let new_entry_width = width(&|instruction| instruction.new_entry.to_string().len());
+
+    let mut lines = metadata;
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+    if !header.is_empty() {
+        lines.extend(header);
+        lines.push(String::new());
+    }
+    for entry in &entries {
+        lines.extend(entry.comments.iter().cloned());
+        let instruction = &entry.instruction;
+        let mut line = format!(
+            "{:source_width$} {:entry_width$} -> {:target_width$} {:new_entry_width$} {}",
+            states[instruction.state],
+            instruction.entry,
+            target(instruction),
+            instruction.new_entry,
+            instruction.direction.letter()
+        );
+        if let Some(trailing) = &entry.trailing {
+            line.push(' ');
+            line.push_str(trailing);
+        }
+        lines.push(line);
+    }
+    lines.extend(pending);
+
+    let mut formatted = lines.join("\n");
+    formatted.push('\n');
+    Ok(formatted)
+}
+
+#[test]
+fn test_format() {
+    let messy = "// Two states.\n\n\
+                 A 1 -> B 1 L\n\
+                 B 1   ->  Halt 1 R   //done\n\
+                 // Start here.\n\
+                 A  0 -> B 1 R\n\n\
+                 B  0 -> A   1 L\n\
+                 // The end.";
+    let formatted = format(messy).unwrap();
+    assert_eq!(
+        formatted,
+        "// Two states.\n\
+         \n\
+         // Start here.\n\
+         A 0 -> B    1 R\n\
+         A 1 -> B    1 L\n\
+         B 0 -> A    1 L\n\
+         B 1 -> Halt 1 R // done\n\
+         // The end.\n"
+    );
+    assert_eq!(format(&formatted).unwrap(), formatted);
+
+    let annotated =
+        format("A 0 -> Halt 1 R\n#source-url  https://bbchallenge.org/1\n#name One").unwrap();
+    assert_eq!(
+        annotated,
+        "#source-url https://bbchallenge.org/1\n#name One\n\nA 0 -> Halt 1 R\n"
+    );
+    assert_eq!(format(&annotated).unwrap(), annotated);
+    assert_eq!(
+        format("A 0 -> B 1 R\nA 1 -> B 1 X"),
+        Err("line 2: couldn't parse direction 'X'".to_string())
+    );
+}
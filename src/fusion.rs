@@ -0,0 +1,187 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasherDefault, Hasher},
+};
+
+use crate::turing::{Direction, HaltReason, Instruction, TapeEntry, TuringMachine};
+
+/// Most steps fused into one transition. The window they read has to fit
+/// into the 128 bits of a cache key.
+pub const MAX_STEPS: usize = 8;
+
+/// A hasher for the cache keys, which are looked up once per transition and
+/// so have to be hashed much faster than by the default hasher. It mixes
+/// every word in with a multiplication, like the hasher of rustc.
+#[derive(Default)]
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_u128(&mut self, word: u128) {
+        self.write_u64(word as u64);
+        self.write_u64((word >> 64) as u64);
+    }
+
+    fn write_usize(&mut self, word: usize) {
+        self.write_u64(word as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// What up to [`Fusion::steps`] consecutive steps do to the window of cells
+/// around the head.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Fused {
+    /// The cells of the window afterwards.
+    pub window: Box<[TapeEntry]>,
+    /// How far the head moved.
+    pub moved: isize,
+    pub state: Option<usize>,
+    pub halt_reason: Option<HaltReason>,
+    /// Steps taken, fewer than [`Fusion::steps`] if the machine halted or
+    /// has no instruction for a configuration on the way.
+    pub steps: u32,
+    /// Index of the instruction executed by the last step.
+    pub last_instruction: Option<usize>,
+}
+
+/// Composite transitions of a machine for a fixed number of consecutive
+/// steps, to take them all at once.
+///
+/// `k` steps read at most `k - 1` cells on either side of the head, so their
+/// effect only depends on the state and this window. It is computed the
+/// first time a state and window are seen and cached, which pays off when
+/// the machine keeps meeting the same patterns, like in the sweeps of most
+/// busy beavers.
+pub struct Fusion {
+    transitions: HashMap<(usize, TapeEntry), (usize, Instruction)>,
+    steps: usize,
+    cache: HashMap<(usize, u128), Fused, BuildHasherDefault<KeyHasher>>,
+}
+
+impl Fusion {
+    /// Fuses `steps` steps of `tm`, from 1 up to [`MAX_STEPS`].
+    pub fn new(tm: &TuringMachine, steps: usize) -> Result<Self, String> {
+        if !(1..=MAX_STEPS).contains(&steps) {
+            return Err(format!(
+                "can fuse from 1 to {} steps, not {}",
+                MAX_STEPS, steps
+            ));
+        }
+        let mut transitions = HashMap::new();
+        for (index, instruction) in tm.instructions().iter().enumerate() {
+            transitions
+                .entry((instruction.state, instruction.entry))
+                .or_insert_with(|| (index, instruction.clone()));
+        }
+        Ok(Fusion {
+            transitions,
+            steps,
+            cache: HashMap::default(),
+        })
+    }
+
+    /// Steps taken at once.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Cells on either side of the head that the transitions depend on.
+    pub fn radius(&self) -> usize {
+        self.steps - 1
+    }
+
+    /// Number of transitions computed so far.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// The transition of `state` on `window`, which has [`Self::radius`]
+    /// cells on either side of the head.
+    pub fn transition(&mut self, state: usize, window: &[TapeEntry]) -> &Fused {
+        debug_assert_eq!(window.len(), 2 * self.radius() + 1);
+        let key = window
+            .iter()
+            .fold(0u128, |key, entry| key << 8 | *entry as u128);
+        let (transitions, steps) = (&self.transitions, self.steps);
+        self.cache
+            .entry((state, key))
+            .or_insert_with(|| fuse(transitions, steps, state, window))
+    }
+}
+
+/// Simulates up to `steps` steps of `state` on `window`.
+fn fuse(
+    transitions: &HashMap<(usize, TapeEntry), (usize, Instruction)>,
+    steps: usize,
+    state: usize,
+    window: &[TapeEntry],
+) -> Fused {
+    let mut fused = Fused {
+        window: window.into(),
+        moved: 0,
+        state: Some(state),
+        halt_reason: None,
+        steps: 0,
+        last_instruction: None,
+    };
+    let center = (window.len() / 2) as isize;
+    for _ in 0..steps {
+        let Some(state) = fused.state else {
+            break;
+        };
+        let cell = (center + fused.moved) as usize;
+        let Some((index, instruction)) = transitions.get(&(state, fused.window[cell])) else {
+            break;
+        };
+        fused.window[cell] = instruction.new_entry;
+        fused.moved += match instruction.direction {
+            Direction::Left => -1,
+            Direction::Right => 1,
+        };
+        fused.state = instruction.new_state;
+        if fused.state.is_none() {
+            fused.halt_reason = Some(instruction.halt);
+        }
+        fused.steps += 1;
+        fused.last_instruction = Some(*index);
+    }
+    fused
+}
+
+#[test]
+fn test_fusion() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_2.turing"));
+    assert!(Fusion::new(&tm, 0).is_err());
+    assert!(Fusion::new(&tm, MAX_STEPS + 1).is_err());
+
+    // On a blank tape, busy beaver 2 halts after 6 steps.
+    let mut fusion = Fusion::new(&tm, 8).unwrap();
+    let fused = fusion.transition(0, &[0; 15]).clone();
+    assert_eq!(fused.steps, 6);
+    assert_eq!(fused.state, None);
+    assert_eq!(fused.halt_reason, Some(HaltReason::Halt));
+    assert_eq!(fused.window.iter().filter(|entry| **entry == 1).count(), 4);
+    fusion.transition(0, &[0; 15]);
+    assert_eq!(fusion.len(), 1);
+
+    let mut fusion = Fusion::new(&tm, 2).unwrap();
+    let fused = fusion.transition(0, &[0, 0, 0]);
+    assert_eq!(fused.steps, 2);
+    assert_eq!(fused.moved, 0);
+    assert_eq!(&*fused.window, &[0, 1, 1]);
+}
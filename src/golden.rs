@@ -0,0 +1,516 @@
+use std::io::{Read, Write};
+
+use crate::{
+    lz::{Compressor, Decompressor},
+    turing::{HaltReason, TapeEntry, TuringMachine},
+};
+
+/// First bytes of a golden trace, followed by a format version.
+const MAGIC: &[u8; 4] = b"TGLD";
+const VERSION: u8 = 1;
+/// Version of traces whose runs are compressed by [`Compressor`].
+const VERSION_COMPRESSED: u8 = 2;
+
+/// What a single step did: the new state (`None` once the machine halted),
+/// how far the head moved and the symbol written.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Step {
+    pub state: Option<usize>,
+    pub moved: i64,
+    pub written: TapeEntry,
+}
+
+impl Step {
+    /// The step `tm` just took, with the head position before it.
+    fn of(tm: &TuringMachine, position: i64) -> Option<Self> {
+        let written = tm.instructions()[tm.last_instruction()?].new_entry;
+        Some(Step {
+            state: tm.state(),
+            moved: self::position(tm) - position,
+            written,
+        })
+    }
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.state {
+            Some(state) => write!(f, "state {state}")?,
+            None => write!(f, "halted")?,
+        }
+        write!(f, ", moved {:+}, wrote {}", self.moved, self.written)
+    }
+}
+
+fn position(tm: &TuringMachine) -> i64 {
+    tm.head() as i64 - tm.origin() as i64
+}
+
+fn halt_code(reason: Option<HaltReason>) -> u8 {
+    match reason {
+        None => 0,
+        Some(HaltReason::Halt) => 1,
+        Some(HaltReason::Accept) => 2,
+        Some(HaltReason::Reject) => 3,
+        Some(HaltReason::Crash) => 4,
+    }
+}
+
+fn halt_reason(code: u8) -> Result<Option<HaltReason>, String> {
+    match code {
+        0 => Ok(None),
+        1 => Ok(Some(HaltReason::Halt)),
+        2 => Ok(Some(HaltReason::Accept)),
+        3 => Ok(Some(HaltReason::Reject)),
+        4 => Ok(Some(HaltReason::Crash)),
+        _ => Err("invalid halting state in the trace".to_string()),
+    }
+}
+
+fn write_varint(out: &mut impl Write, mut value: u128) -> std::io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return out.write_all(&[byte]);
+        }
+        out.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_byte(input: &mut impl Read) -> Result<u8, String> {
+    let mut byte = [0];
+    match input.read_exact(&mut byte) {
+        Ok(()) => Ok(byte[0]),
+        Err(why) if why.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Err("the trace ends early".to_string())
+        }
+        Err(why) => Err(why.to_string()),
+    }
+}
+
+fn read_varint(input: &mut impl Read) -> Result<u128, String> {
+    let mut value = 0u128;
+    for shift in (0..128).step_by(7) {
+        let byte = read_byte(input)?;
+        value |= ((byte & 0x7f) as u128) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("invalid number in the trace".to_string())
+}
+
+fn zigzag(value: i64) -> u128 {
+    ((value << 1) ^ (value >> 63)) as u64 as u128
+}
+
+fn unzigzag(value: u128) -> i64 {
+    let value = value as u64;
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Bit of the first byte of a run telling that its count follows.
+const COUNTED: u8 = 0b100;
+/// Value of the state bits of the first byte of a run telling that the
+/// state follows.
+const LARGE_STATE: u8 = 31;
+
+fn write_run(out: &mut impl Write, step: &Step, count: u128) -> std::io::Result<()> {
+    let state = step.state.map_or(0, |state| state as u128 + 1);
+    let moved = match step.moved {
+        -1..=1 => (step.moved + 1) as u8,
+        _ => 3,
+    };
+    let mut tag = moved | (state.min(LARGE_STATE as u128) as u8) << 3;
+    if count > 1 {
+        tag |= COUNTED;
+    }
+    out.write_all(&[tag])?;
+    if count > 1 {
+        write_varint(out, count)?;
+    }
+    if state >= LARGE_STATE as u128 {
+        write_varint(out, state)?;
+    }
+    if moved == 3 {
+        write_varint(out, zigzag(step.moved))?;
+    }
+    out.write_all(&[step.written])
+}
+
+/// Records every step of a run into a golden trace, to check later runs
+/// against with a [`Verifier`].
+///
+/// The trace starts with `TGLD` and a version byte. Steps are stored as
+/// runs of identical steps, most of them in two bytes. The first byte holds
+/// the head movement plus one in its lowest two bits, whether a count
+/// follows in the next bit and the new state plus one (`0` once halted) in
+/// the upper five bits. Counts, states from 31 on and movements other than
+/// one cell follow as LEB128 varints, the last two only if the bits don't
+/// hold them (`31` and `3`), then the symbol written. A run with a count of
+/// `0` ends the trace, followed by the number of steps and how the machine
+/// halted.
+///
+/// Traces of version 2, written by [`Recorder::compressed`], have the
+/// same runs compressed after the version byte, which makes traces of
+/// hundreds of millions of steps small enough to commit as test fixtures.
+pub struct Recorder<W: Write> {
+    out: Sink<W>,
+    run: Option<(Step, u128)>,
+    position: i64,
+    steps: u128,
+}
+
+/// Where the runs of a trace are written, compressed or not.
+enum Sink<W: Write> {
+    Plain(W),
+    Compressed(Compressor<W>),
+}
+
+impl<W: Write> Write for Sink<W> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Sink::Plain(out) => out.write(data),
+            Sink::Compressed(out) => out.write(data),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Sink::Plain(out) => out.flush(),
+            Sink::Compressed(out) => out.flush(),
+        }
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut out: W, tm: &TuringMachine) -> std::io::Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        Ok(Recorder::start(Sink::Plain(out), tm))
+    }
+
+    /// Records a trace of version 2, with the runs compressed.
+    pub fn compressed(mut out: W, tm: &TuringMachine) -> std::io::Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION_COMPRESSED])?;
+        Ok(Recorder::start(Sink::Compressed(Compressor::new(out)), tm))
+    }
+
+    fn start(out: Sink<W>, tm: &TuringMachine) -> Self {
+        Recorder {
+            out,
+            run: None,
+            position: position(tm),
+            steps: 0,
+        }
+    }
+
+    /// Adds the step `tm` just took.
+    pub fn record(&mut self, tm: &TuringMachine) -> std::io::Result<()> {
+        let Some(step) = Step::of(tm, self.position) else {
+            return Ok(());
+        };
+        self.position = position(tm);
+        self.steps += 1;
+        match &mut self.run {
+            Some((previous, count)) if previous == &step => *count += 1,
+            run => {
+                if let Some((previous, count)) = run.replace((step, 1)) {
+                    write_run(&mut self.out, &previous, count)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Ends the trace with how `tm` halted.
+    pub fn finish(mut self, tm: &TuringMachine) -> std::io::Result<u128> {
+        if let Some((step, count)) = self.run.take() {
+            write_run(&mut self.out, &step, count)?;
+        }
+        self.out.write_all(&[COUNTED])?;
+        write_varint(&mut self.out, 0)?;
+        write_varint(&mut self.out, self.steps)?;
+        self.out.write_all(&[halt_code(tm.halt_reason)])?;
+        self.out.flush()?;
+        Ok(self.steps)
+    }
+}
+
+/// Checks every step of a run against a golden trace written by a
+/// [`Recorder`].
+pub struct Verifier<R: Read> {
+    input: Source<R>,
+    /// The current run and how many of its steps are left, `None` at the end
+    /// of the trace.
+    run: Option<(Step, u128)>,
+    position: i64,
+    steps: u128,
+}
+
+/// Where the runs of a trace are read from, compressed or not.
+enum Source<R: Read> {
+    Plain(R),
+    Compressed(Decompressor<R>),
+}
+
+impl<R: Read> Read for Source<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Source::Plain(input) => input.read(buf),
+            Source::Compressed(input) => input.read(buf),
+        }
+    }
+}
+
+impl<R: Read> Verifier<R> {
+    pub fn new(input: R, tm: &TuringMachine) -> Result<Self, String> {
+        let mut verifier = Verifier::open(input)?;
+        verifier.position = position(tm);
+        Ok(verifier)
+    }
+
+    fn open(mut input: R) -> Result<Self, String> {
+        let mut magic = [0; 4];
+        input
+            .read_exact(&mut magic)
+            .map_err(|_| "not a golden trace".to_string())?;
+        if &magic != MAGIC {
+            return Err("not a golden trace".to_string());
+        }
+        let input = match read_byte(&mut input)? {
+            VERSION => Source::Plain(input),
+            VERSION_COMPRESSED => Source::Compressed(Decompressor::new(input)),
+            version => return Err(format!("unsupported trace version {version}")),
+        };
+        let mut verifier = Verifier {
+            input,
+            run: None,
+            position: 0,
+            steps: 0,
+        };
+        verifier.next_run()?;
+        Ok(verifier)
+    }
+
+    fn next_run(&mut self) -> Result<(), String> {
+        let tag = read_byte(&mut self.input)?;
+        let count = match tag & COUNTED {
+            0 => 1,
+            _ => read_varint(&mut self.input)?,
+        };
+        if count == 0 {
+            self.run = None;
+            return Ok(());
+        }
+        let state = match tag >> 3 {
+            LARGE_STATE => read_varint(&mut self.input)?,
+            state => state as u128,
+        };
+        let state = match state {
+            0 => None,
+            state => Some(
+                usize::try_from(state - 1).map_err(|_| "invalid state in the trace".to_string())?,
+            ),
+        };
+        let moved = match tag & 0b11 {
+            3 => unzigzag(read_varint(&mut self.input)?),
+            moved => moved as i64 - 1,
+        };
+        let written = read_byte(&mut self.input)?;
+        self.run = Some((
+            Step {
+                state,
+                moved,
+                written,
+            },
+            count,
+        ));
+        Ok(())
+    }
+
+    /// Compares the step `tm` just took with the trace.
+    pub fn check(&mut self, tm: &TuringMachine) -> Result<(), String> {
+        let Some(step) = Step::of(tm, self.position) else {
+            return Ok(());
+        };
+        self.position = position(tm);
+        self.steps += 1;
+        let Some((expected, count)) = &mut self.run else {
+            return Err(format!(
+                "step {}: the trace ended after {} steps",
+                self.steps,
+                self.steps - 1
+            ));
+        };
+        if expected != &step {
+            return Err(format!(
+                "step {}: expected {}, got {}",
+                self.steps, expected, step
+            ));
+        }
+        *count -= 1;
+        if *count == 0 {
+            self.next_run()?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the trace ends where the run of `tm` did.
+    pub fn finish(mut self, tm: &TuringMachine) -> Result<u128, String> {
+        if let Some((expected, _)) = self.run {
+            return Err(format!(
+                "step {}: expected {}, but the run ended",
+                self.steps + 1,
+                expected
+            ));
+        }
+        let steps = read_varint(&mut self.input)?;
+        if steps != self.steps {
+            return Err(format!(
+                "the trace has {} steps, the run {}",
+                steps, self.steps
+            ));
+        }
+        if read_byte(&mut self.input)? != halt_code(tm.halt_reason) {
+            return Err("the machine halted differently".to_string());
+        }
+        if self.input.read(&mut [0]).map_err(|why| why.to_string())? != 0 {
+            return Err("unexpected data after the end of the trace".to_string());
+        }
+        Ok(self.steps)
+    }
+}
+
+/// The steps of a whole golden trace, without a machine to check them.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Trace {
+    /// Runs of identical steps with their lengths.
+    pub runs: Vec<(Step, u128)>,
+    pub steps: u128,
+    /// How the machine halted, `None` if it was stopped.
+    pub halt: Option<HaltReason>,
+}
+
+impl Trace {
+    /// Reads a golden trace written by a [`Recorder`].
+    pub fn read(input: impl Read) -> Result<Self, String> {
+        let mut verifier = Verifier::open(input)?;
+        let mut runs = vec![];
+        while let Some(run) = verifier.run.take() {
+            runs.push(run);
+            verifier.next_run()?;
+        }
+        let steps = read_varint(&mut verifier.input)?;
+        if steps != runs.iter().map(|(_, count)| count).sum::<u128>() {
+            return Err("the trace has the wrong number of steps".to_string());
+        }
+        let halt = halt_reason(read_byte(&mut verifier.input)?)?;
+        Ok(Trace { runs, steps, halt })
+    }
+}
+
+#[cfg(test)]
+fn record(mut tm: TuringMachine) -> Vec<u8> {
+    let mut trace = vec![];
+    let mut recorder = Recorder::new(&mut trace, &tm).unwrap();
+    while tm.step() {
+        recorder.record(&tm).unwrap();
+    }
+    recorder.finish(&tm).unwrap();
+    trace
+}
+
+#[cfg(test)]
+fn verify(mut tm: TuringMachine, trace: &[u8]) -> Result<u128, String> {
+    let mut verifier = Verifier::new(trace, &tm)?;
+    while tm.step() {
+        verifier.check(&tm)?;
+    }
+    verifier.finish(&tm)
+}
+
+#[test]
+fn test_golden() {
+    use std::path::Path;
+
+    let bb4 = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    let trace = record(bb4.clone());
+    assert_eq!(&trace[..5], b"TGLD\x01");
+    // Two bytes per step, besides the header and the end of the trace.
+    assert_eq!(trace.len(), 5 + 107 * 2 + 4);
+    assert_eq!(verify(bb4.clone(), &trace), Ok(107));
+
+    let changed = TuringMachine::parse(&bb4.to_turing().replacen("1 L", "0 L", 1)).unwrap();
+    assert!(verify(changed, &trace).unwrap_err().contains(", wrote 0"));
+    let bb3 = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_3.turing"));
+    assert!(verify(bb3, &trace).is_err());
+    assert_eq!(
+        verify(bb4.clone(), &trace[..trace.len() - 2]),
+        Err("the trace ends early".to_string())
+    );
+    // Runs of identical steps take the same space as a single one.
+    let mut sweep = TuringMachine::parse("A 1 -> A 1 R\nA 0 -> Halt 1 R").unwrap();
+    sweep.set_input(&[1; 1000]);
+    let trace = record(sweep.clone());
+    assert!(trace.len() < 20, "{} bytes", trace.len());
+    assert_eq!(verify(sweep, &trace), Ok(1001));
+
+    assert_eq!(
+        verify(bb4, b"TGLD\x03"),
+        Err("unsupported trace version 3".to_string())
+    );
+}
+
+#[test]
+fn test_golden_compressed() {
+    use std::path::Path;
+
+    // The bouncer never halts, so both traces end after 100 000 steps.
+    let tm = TuringMachine::new(Path::new("examples/deciders/bouncer.turing"));
+    let limited = |compressed: bool| {
+        let mut tm = tm.clone();
+        let mut trace = vec![];
+        let mut recorder = match compressed {
+            true => Recorder::compressed(&mut trace, &tm).unwrap(),
+            false => Recorder::new(&mut trace, &tm).unwrap(),
+        };
+        while tm.num_steps < 100_000 && tm.step() {
+            recorder.record(&tm).unwrap();
+        }
+        recorder.finish(&tm).unwrap();
+        trace
+    };
+    let (plain, compressed) = (limited(false), limited(true));
+    assert_eq!(&compressed[..5], b"TGLD\x02");
+    assert!(
+        compressed.len() * 4 < plain.len(),
+        "{} of {} bytes",
+        compressed.len(),
+        plain.len()
+    );
+
+    let mut run = tm.clone();
+    let mut verifier = Verifier::new(compressed.as_slice(), &run).unwrap();
+    while run.num_steps < 100_000 && run.step() {
+        verifier.check(&run).unwrap();
+    }
+    assert_eq!(verifier.finish(&run), Ok(100_000));
+
+    let bb4 = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    let mut trace = vec![];
+    let mut recorder = Recorder::compressed(&mut trace, &bb4).unwrap();
+    let mut run = bb4.clone();
+    while run.step() {
+        recorder.record(&run).unwrap();
+    }
+    recorder.finish(&run).unwrap();
+    assert_eq!(verify(bb4.clone(), &trace), Ok(107));
+    assert!(verify(bb4, &trace[..trace.len() - 1]).is_err());
+
+    let read = Trace::read(trace.as_slice()).unwrap();
+    assert_eq!((read.steps, read.halt), (107, Some(HaltReason::Halt)));
+    assert!(read.runs.iter().all(|(_, count)| *count >= 1));
+}
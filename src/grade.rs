@@ -0,0 +1,221 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    json::Json,
+    sandbox::Limits,
+    spec::{self, Outcome, Spec},
+};
+
+/// Format of a grading report.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    /// A line per student with the score and the points of every test.
+    Csv,
+    /// An array with an object per student, listing every test.
+    Json,
+}
+
+/// The results of the tests of an assignment for one submitted machine.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Grade {
+    /// The name of the machine file without its extension.
+    pub student: String,
+    /// The outcome of every test, or why the machine couldn't be read.
+    pub outcomes: Result<Vec<Outcome>, String>,
+}
+
+impl Grade {
+    /// Runs the tests `specs` of an assignment on the machine file at
+    /// `path`, every one within `limits`.
+    pub fn new(specs: &[Spec], path: &Path, limits: &Limits) -> Self {
+        let student = path
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+        let outcomes = specs
+            .iter()
+            .map(|spec| spec::check(path, spec, limits))
+            .collect();
+        Grade { student, outcomes }
+    }
+
+    /// The points of every test, 0 for the failed ones.
+    pub fn points(&self, specs: &[Spec]) -> Vec<u32> {
+        match &self.outcomes {
+            Ok(outcomes) => specs
+                .iter()
+                .zip(outcomes)
+                .map(|(spec, outcome)| match outcome.failure {
+                    None => spec.points,
+                    Some(_) => 0,
+                })
+                .collect(),
+            Err(_) => vec![0; specs.len()],
+        }
+    }
+
+    pub fn score(&self, specs: &[Spec]) -> u32 {
+        self.points(specs).iter().sum()
+    }
+}
+
+/// The submitted machines in `directory`, every `.turing` file in it by
+/// name.
+pub fn submissions(directory: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|why| format!("{}: {}", directory.display(), why))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "turing")
+        })
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// The report as CSV, with a column for the score, the most points and the
+/// points of every test, and the error of machines that couldn't be read.
+pub fn csv(specs: &[Spec], grades: &[Grade]) -> String {
+    let total: u32 = specs.iter().map(|spec| spec.points).sum();
+    let mut header = vec!["student", "score", "total"];
+    header.extend(specs.iter().map(|spec| spec.name.as_str()));
+    header.push("error");
+    let mut lines = vec![header.join(",")];
+    for grade in grades {
+        let mut fields = vec![
+            grade.student.clone(),
+            grade.score(specs).to_string(),
+            total.to_string(),
+        ];
+        fields.extend(grade.points(specs).iter().map(u32::to_string));
+        fields.push(match &grade.outcomes {
+            Ok(_) => String::new(),
+            Err(why) => format!("\"{}\"", why.replace('"', "\"\"")),
+        });
+        lines.push(fields.join(","));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// The report as JSON, with the outcome of every test.
+pub fn json(specs: &[Spec], grades: &[Grade]) -> Json {
+    let total: u32 = specs.iter().map(|spec| spec.points).sum();
+    let student = |grade: &Grade| {
+        let mut fields = vec![
+            ("student".to_string(), grade.student.as_str().into()),
+            ("score".to_string(), (grade.score(specs) as u64).into()),
+            ("total".to_string(), (total as u64).into()),
+        ];
+        match &grade.outcomes {
+            Ok(outcomes) => {
+                let tests = specs
+                    .iter()
+                    .zip(outcomes)
+                    .zip(grade.points(specs))
+                    .map(|((spec, outcome), points)| {
+                        Json::object([
+                            ("name", spec.name.as_str().into()),
+                            ("passed", outcome.failure.is_none().into()),
+                            ("points", (points as u64).into()),
+                            ("halt", outcome.halt.to_string().into()),
+                            ("steps", outcome.steps.into()),
+                            (
+                                "failure",
+                                outcome.failure.as_deref().map_or(Json::Null, Json::from),
+                            ),
+                        ])
+                    })
+                    .collect();
+                fields.push(("tests".to_string(), Json::Array(tests)));
+            }
+            Err(why) => fields.push(("error".to_string(), why.as_str().into())),
+        }
+        Json::Object(fields)
+    };
+    Json::Array(grades.iter().map(student).collect())
+}
+
+#[test]
+fn test_grade() {
+    let specs = spec::parse(
+        "[empty]\n\
+         halt = \"Accept\"\n\
+         \n\
+         [balanced]\n\
+         input = \"111222\"\n\
+         halt = \"Accept\"\n\
+         points = 3\n\
+         \n\
+         [unbalanced]\n\
+         input = \"112\"\n\
+         halt = \"Reject\"\n\
+         points = 2\n",
+    )
+    .unwrap();
+    let directory = std::env::temp_dir().join(format!("grade_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::copy(
+        "examples/recognizers/1n2n.turing",
+        directory.join("alice.turing"),
+    )
+    .unwrap();
+    // Accepts everything.
+    fs::write(
+        directory.join("bob.turing"),
+        "A 0 -> Accept 0 R\nA 1 -> A 1 R\nA 2 -> A 2 R",
+    )
+    .unwrap();
+    fs::write(directory.join("carol.turing"), "A 0 ->").unwrap();
+    // Never stops writing.
+    fs::write(
+        directory.join("dave.turing"),
+        "A 0 -> A 1 R\nA 1 -> A 1 R\nA 2 -> A 2 R",
+    )
+    .unwrap();
+    fs::write(directory.join("notes.txt"), "").unwrap();
+
+    let paths = submissions(&directory).unwrap();
+    assert_eq!(paths.len(), 4);
+    let limits = Limits {
+        max_tape_cells: 100,
+        ..Limits::NONE
+    };
+    let grades: Vec<Grade> = paths
+        .iter()
+        .map(|path| Grade::new(&specs, path, &limits))
+        .collect();
+    assert_eq!(grades[0].student, "alice");
+    assert_eq!(grades[0].score(&specs), 6);
+    assert_eq!(grades[1].points(&specs), [3, 1, 0]);
+    assert!(grades[2].outcomes.is_err());
+
+    let csv = csv(&specs, &grades);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "student,score,total,balanced,empty,unbalanced,error"
+    );
+    assert_eq!(lines[1], "alice,6,6,3,1,2,");
+    assert_eq!(lines[2], "bob,4,6,3,1,0,");
+    assert!(lines[3].starts_with("carol,0,6,0,0,0,\""));
+    assert_eq!(lines[4], "dave,0,6,0,0,0,");
+
+    let json = json(&specs, &grades);
+    let bob = &json.as_array().unwrap()[1];
+    assert_eq!(bob.int_field("score"), Ok(4));
+    let tests = bob.field("tests").unwrap().as_array().unwrap();
+    assert_eq!(
+        tests[2].str_field("failure"),
+        Ok("expected Reject, got Accept")
+    );
+    assert!(json.as_array().unwrap()[2].str_field("error").is_ok());
+    let dave = json.as_array().unwrap()[3].field("tests").unwrap();
+    assert_eq!(
+        dave.as_array().unwrap()[0].str_field("failure"),
+        Ok("stopped at the limit of 100 tape cells")
+    );
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,109 @@
+use std::{fs, path::Path};
+
+const HEADER: &str = "# touring undecided holdouts";
+
+/// A machine a batch run couldn't decide, together with what was tried.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Holdout {
+    /// Id of the machine in the input of the run that left it undecided.
+    pub id: u64,
+    /// The machine in the bbchallenge text format.
+    pub machine: String,
+    pub max_steps: u128,
+    pub depth: usize,
+    /// Names of the deciders that failed on the machine.
+    pub deciders: Vec<String>,
+}
+
+/// Writes `holdouts` to `path` as a header followed by a line per machine,
+/// e.g. `7 1RB---_0LA1RB max_steps=10000 depth=20
+/// deciders=cycler,translated_cycler,backward_reasoning`, so the first two
+/// columns are in the same format as the output of a batch run.
+pub fn write(path: &Path, holdouts: &[Holdout]) -> Result<(), String> {
+    let mut content = format!("{HEADER}\n");
+    for holdout in holdouts {
+        content += &format!(
+            "{} {} max_steps={} depth={} deciders={}\n",
+            holdout.id,
+            holdout.machine,
+            holdout.max_steps,
+            holdout.depth,
+            holdout.deciders.join(",")
+        );
+    }
+    fs::write(path, content).map_err(|why| why.to_string())
+}
+
+/// Reads holdouts written by [`write`], sorted by their id.
+pub fn read(path: &Path) -> Result<Vec<Holdout>, String> {
+    let content = fs::read_to_string(path).map_err(|why| why.to_string())?;
+    if !content.starts_with(HEADER) {
+        return Err(format!("{} isn't a holdouts file", path.display()));
+    }
+    let mut holdouts = vec![];
+    for line in content.lines().skip(1) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let [id, machine, max_steps, depth, deciders] = words.as_slice() else {
+            return Err(format!("invalid line '{line}'"));
+        };
+        holdouts.push(Holdout {
+            id: id
+                .parse()
+                .map_err(|_| format!("invalid machine id '{id}'"))?,
+            machine: machine.to_string(),
+            max_steps: field(max_steps, "max_steps", line)?
+                .parse()
+                .map_err(|_| format!("invalid max_steps in '{line}'"))?,
+            depth: field(depth, "depth", line)?
+                .parse()
+                .map_err(|_| format!("invalid depth in '{line}'"))?,
+            deciders: field(deciders, "deciders", line)?
+                .split(',')
+                .filter(|decider| !decider.is_empty())
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+    holdouts.sort_by_key(|holdout| holdout.id);
+    Ok(holdouts)
+}
+
+/// The value of `word` of the form `<name>=<value>`.
+fn field<'a>(word: &'a str, name: &str, line: &str) -> Result<&'a str, String> {
+    word.strip_prefix(name)
+        .and_then(|value| value.strip_prefix('='))
+        .ok_or_else(|| format!("expected {name}= in '{line}'"))
+}
+
+#[test]
+fn test_holdouts() {
+    let path = std::env::temp_dir().join(format!("holdouts_{}", std::process::id()));
+    let holdouts = vec![
+        Holdout {
+            id: 3,
+            machine: "1RB---_0LA1RB".to_string(),
+            max_steps: 10_000,
+            depth: 20,
+            deciders: vec!["cycler".to_string(), "translated_cycler".to_string()],
+        },
+        Holdout {
+            id: 12,
+            machine: "1RB1LA_1LA1RZ".to_string(),
+            max_steps: 5,
+            depth: 0,
+            deciders: vec![],
+        },
+    ];
+    write(&path, &holdouts).unwrap();
+    assert_eq!(read(&path).unwrap(), holdouts);
+
+    fs::write(
+        &path,
+        format!("{HEADER}\n3 1RB---_0LA1RB steps=5 depth=2 deciders=\n"),
+    )
+    .unwrap();
+    assert!(read(&path).is_err());
+    fs::write(&path, "3 1RB---_0LA1RB\n").unwrap();
+    assert!(read(&path).is_err());
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,186 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::turing::{Direction, TuringMachine};
+
+/// Finds the short sequences of transitions that a machine repeats back to
+/// back, e.g. while sweeping over a block of the tape.
+///
+/// Feed it the executed instructions with [`Self::record`]. A loop of
+/// period `p` is counted whenever the last `p` transitions were repeated at
+/// least twice in a row. Loops are identified up to rotation, so entering
+/// the same loop at a different transition counts towards the same loop, and
+/// only attributed to their smallest period.
+pub struct HotLoops {
+    max_period: usize,
+    history: VecDeque<usize>,
+    /// Number of consecutive transitions equal to the one `p` steps before,
+    /// indexed by `p - 1`.
+    runs: Vec<u128>,
+    loops: HashMap<Vec<usize>, u128>,
+    steps: u128,
+}
+
+/// A repeated sequence of transitions and how many steps were spent in it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct HotLoop {
+    /// Indices into [`TuringMachine::instructions`], in execution order.
+    pub body: Vec<usize>,
+    pub steps: u128,
+}
+
+impl HotLoop {
+    pub fn period(&self) -> usize {
+        self.body.len()
+    }
+
+    /// Net head movement of one pass through the loop.
+    pub fn shift(&self, tm: &TuringMachine) -> isize {
+        self.body
+            .iter()
+            .map(|index| match tm.instructions()[*index].direction {
+                Direction::Left => -1,
+                Direction::Right => 1,
+            })
+            .sum()
+    }
+
+    /// The transitions of the loop, written as the state and the symbol read.
+    pub fn transitions(&self, tm: &TuringMachine) -> String {
+        let transitions: Vec<String> = self
+            .body
+            .iter()
+            .map(|index| {
+                let instruction = &tm.instructions()[*index];
+                format!("{}{}", tm.states()[instruction.state], instruction.entry)
+            })
+            .collect();
+        transitions.join(" ")
+    }
+}
+
+impl HotLoops {
+    /// Looks for loops of up to `max_period` transitions.
+    pub fn new(max_period: usize) -> Self {
+        HotLoops {
+            max_period,
+            history: VecDeque::with_capacity(max_period + 1),
+            runs: vec![0; max_period],
+            loops: HashMap::new(),
+            steps: 0,
+        }
+    }
+
+    pub fn record(&mut self, instruction: usize) {
+        self.steps += 1;
+        for period in 1..=self.max_period.min(self.history.len()) {
+            if self.history[self.history.len() - period] == instruction {
+                self.runs[period - 1] += 1;
+            } else {
+                if let Some((body, steps)) = self.finished(period) {
+                    *self.loops.entry(body).or_insert(0) += steps;
+                }
+                self.runs[period - 1] = 0;
+            }
+        }
+        self.history.push_back(instruction);
+        if self.history.len() > self.max_period {
+            self.history.pop_front();
+        }
+    }
+
+    /// Number of recorded transitions.
+    pub fn steps(&self) -> u128 {
+        self.steps
+    }
+
+    /// The loops found so far, the one covering the most steps first.
+    pub fn report(&self) -> Vec<HotLoop> {
+        let mut loops = self.loops.clone();
+        for period in 1..=self.max_period {
+            if let Some((body, steps)) = self.finished(period) {
+                *loops.entry(body).or_insert(0) += steps;
+            }
+        }
+        let mut loops: Vec<HotLoop> = loops
+            .into_iter()
+            .map(|(body, steps)| HotLoop { body, steps })
+            .collect();
+        loops.sort_by(|a, b| {
+            b.steps
+                .cmp(&a.steps)
+                .then(a.period().cmp(&b.period()))
+                .then(a.body.cmp(&b.body))
+        });
+        loops
+    }
+
+    /// The loop of `period` that ends with the last recorded transition, in
+    /// its canonical rotation, and the number of steps spent in it.
+    fn finished(&self, period: usize) -> Option<(Vec<usize>, u128)> {
+        let run = self.runs[period - 1];
+        if run < period as u128 {
+            return None;
+        }
+        let body: Vec<usize> = self
+            .history
+            .range(self.history.len() - period..)
+            .copied()
+            .collect();
+        if !is_primitive(&body) {
+            return None;
+        }
+        Some((canonical(&body), run + period as u128))
+    }
+}
+
+/// Whether `body` is not a repetition of a shorter sequence.
+fn is_primitive(body: &[usize]) -> bool {
+    (1..body.len())
+        .filter(|period| body.len().is_multiple_of(*period))
+        .all(|period| (period..body.len()).any(|i| body[i] != body[i - period]))
+}
+
+/// The lexicographically smallest rotation of `body`.
+fn canonical(body: &[usize]) -> Vec<usize> {
+    (0..body.len())
+        .map(|start| [&body[start..], &body[..start]].concat())
+        .min()
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_hot_loops() {
+    let mut hot = HotLoops::new(4);
+    for instruction in [0, 1, 2, 1, 2, 1, 2, 3, 2, 1, 2, 1, 0] {
+        hot.record(instruction);
+    }
+
+    let loops = hot.report();
+    assert_eq!(hot.steps(), 13);
+    assert_eq!(
+        loops,
+        vec![HotLoop {
+            body: vec![1, 2],
+            steps: 10,
+        }]
+    );
+}
+
+#[test]
+fn test_hot_loops_busy_bever() {
+    use std::path::Path;
+
+    let mut tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    let mut hot = HotLoops::new(8);
+    while tm.step() {
+        hot.record(tm.last_instruction().unwrap());
+    }
+
+    let loops = hot.report();
+    assert_eq!(hot.steps(), tm.num_steps);
+    assert!(loops.iter().map(|l| l.steps).sum::<u128>() <= tm.num_steps);
+    assert_eq!(loops[0].period(), 5);
+    assert_eq!(loops[0].steps, 73);
+    assert_eq!(loops[0].shift(&tm), 1);
+    assert_eq!(loops[0].transitions(&tm), "A0 B1 C1 D0 D1");
+}
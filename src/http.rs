@@ -0,0 +1,239 @@
+use std::io::{self, BufRead, Read, Write};
+
+use crate::json::Json;
+
+/// Largest request body that is accepted.
+const MAX_BODY: usize = 1 << 20;
+/// Longest request line or header line that is accepted, in bytes.
+const MAX_LINE: usize = 8192;
+/// Most headers of a request.
+const MAX_HEADERS: usize = 100;
+
+/// An HTTP/1.1 request, as far as the server needs to understand it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// Path without the query string.
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Reads a request head and body from `reader`, or gives the error
+    /// response to answer with, 431 for too many or too long headers.
+    pub fn read(reader: &mut impl BufRead) -> Result<Self, Response> {
+        let bad = |why: String| Response::error(400, &why);
+        let line = read_line(reader).ok_or_else(|| bad("request line too long".to_string()))?;
+        let line = line.map_err(bad)?;
+        let mut parts = line.split_whitespace();
+        let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(_version)) => (method, target),
+            _ => return Err(bad(format!("invalid request line '{}'", line.trim_end()))),
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(key), decode(value))
+            })
+            .collect();
+
+        let mut request = Request {
+            method: method.to_string(),
+            path: decode(path),
+            query,
+            headers: vec![],
+            body: vec![],
+        };
+        loop {
+            let line = read_line(reader)
+                .ok_or_else(|| Response::error(431, "header too long"))?
+                .map_err(bad)?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if request.headers.len() == MAX_HEADERS {
+                return Err(Response::error(431, "too many headers"));
+            }
+            match line.split_once(':') {
+                Some((name, value)) => request
+                    .headers
+                    .push((name.trim().to_string(), value.trim().to_string())),
+                None => return Err(bad(format!("invalid header '{line}'"))),
+            }
+        }
+
+        let len = match request.header("Content-Length") {
+            Some(len) => len
+                .parse()
+                .map_err(|_| bad(format!("invalid Content-Length '{len}'")))?,
+            None => 0,
+        };
+        if len > MAX_BODY {
+            return Err(bad(format!("body of {len} bytes is too large")));
+        }
+        request.body = vec![0; len];
+        reader
+            .read_exact(&mut request.body)
+            .map_err(|why| bad(why.to_string()))?;
+        Ok(request)
+    }
+
+    /// Looks up a header, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Decodes `%XX` escapes and `+` in a URL component.
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// A response with a complete body.
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, json: &Json) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body: json.to_string().into_bytes(),
+        }
+    }
+
+    pub fn text(status: u16, content_type: &'static str, text: String) -> Self {
+        Response {
+            status,
+            content_type,
+            body: text.into_bytes(),
+        }
+    }
+
+    /// An error response with the message in a JSON object.
+    pub fn error(status: u16, message: &str) -> Self {
+        Response::json(status, &Json::object([("error", message.into())]))
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+             Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len()
+        )?;
+        writer.write_all(&self.body)?;
+        writer.flush()
+    }
+}
+
+/// Reads a line of up to [`MAX_LINE`] bytes, `None` if it is longer, so a
+/// client can't make the server buffer without end.
+fn read_line(reader: &mut impl BufRead) -> Option<Result<String, String>> {
+    let mut line = String::new();
+    match reader
+        .by_ref()
+        .take(MAX_LINE as u64 + 1)
+        .read_line(&mut line)
+    {
+        Ok(_) if line.len() > MAX_LINE => None,
+        Ok(_) => Some(Ok(line)),
+        Err(why) => Some(Err(why.to_string())),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+#[test]
+fn test_read_request() {
+    let raw = "POST /machines/3/run?max_steps=100&name=a%20b+c HTTP/1.1\r\n\
+               Host: localhost\r\ncontent-length: 5\r\n\r\nhello";
+    let request = Request::read(&mut raw.as_bytes()).unwrap();
+
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/machines/3/run");
+    assert_eq!(request.query("max_steps"), Some("100"));
+    assert_eq!(request.query("name"), Some("a b c"));
+    assert_eq!(request.header("Content-Length"), Some("5"));
+    assert_eq!(request.body, b"hello");
+
+    assert!(Request::read(&mut "garbage\r\n\r\n".as_bytes()).is_err());
+    let long = format!("GET / HTTP/1.1\r\nCookie: {}\r\n\r\n", "a".repeat(MAX_LINE));
+    assert_eq!(Request::read(&mut long.as_bytes()).unwrap_err().status, 431);
+    let many = format!(
+        "GET / HTTP/1.1\r\n{}\r\n",
+        "A: b\r\n".repeat(MAX_HEADERS + 1)
+    );
+    assert_eq!(Request::read(&mut many.as_bytes()).unwrap_err().status, 431);
+    let long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+    assert_eq!(Request::read(&mut long.as_bytes()).unwrap_err().status, 400);
+}
+
+#[test]
+fn test_write_response() {
+    let mut out = vec![];
+    Response::error(404, "no such machine")
+        .write_to(&mut out)
+        .unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(out.ends_with("\r\n\r\n{\"error\":\"no such machine\"}"));
+}
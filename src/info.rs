@@ -0,0 +1,237 @@
+use std::fmt::Display;
+
+use crate::turing::{Instruction, TapeEntry, TuringMachine};
+
+/// A summary of the transition table of a machine.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Info<'a> {
+    tm: &'a TuringMachine,
+    /// Number of symbols, up to the largest one used and at least `0` and
+    /// `1`.
+    pub symbols: usize,
+    pub halting: Vec<&'a Instruction>,
+    /// Strongly connected components of the state graph, where every state
+    /// has an edge to the states it can go to. Components are ordered by
+    /// their first state and list their states in order.
+    pub components: Vec<Vec<usize>>,
+    /// States and symbols without a transition.
+    pub missing: Vec<(usize, TapeEntry)>,
+}
+
+impl Info<'_> {
+    /// Number of transitions out of the possible ones, one for every state
+    /// and symbol.
+    pub fn density(&self) -> f64 {
+        let possible = self.tm.states().len() * self.symbols;
+        if possible == 0 {
+            return 0.0;
+        }
+        self.tm.instructions().len() as f64 / possible as f64
+    }
+
+    /// Whether every state has a transition for every symbol.
+    pub fn is_total(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+impl Display for Info<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tm = self.tm;
+        let names = |states: &[usize]| {
+            states
+                .iter()
+                .map(|state| tm.states()[*state].as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        for (key, value) in tm.metadata() {
+            let label = match key.as_str() {
+                "source-url" => "Source URL".to_string(),
+                key => key[..1].to_uppercase() + &key[1..],
+            };
+            writeln!(f, "{:13}{}", format!("{label}:"), value)?;
+        }
+        let all: Vec<usize> = (0..tm.states().len()).collect();
+        writeln!(f, "States:      {} ({})", all.len(), names(&all))?;
+        writeln!(f, "Symbols:     {}", self.symbols)?;
+        writeln!(
+            f,
+            "Transitions: {} of {} ({:.0}%)",
+            tm.instructions().len(),
+            all.len() * self.symbols,
+            self.density() * 100.0
+        )?;
+        writeln!(f, "Halting:     {}", self.halting.len())?;
+        for instruction in &self.halting {
+            writeln!(
+                f,
+                "  {} {} -> {} {} {}",
+                tm.states()[instruction.state],
+                instruction.entry,
+                instruction.halt.name(),
+                instruction.new_entry,
+                instruction.direction.letter()
+            )?;
+        }
+        let components: Vec<String> = self
+            .components
+            .iter()
+            .map(|component| format!("{{{}}}", names(component)))
+            .collect();
+        writeln!(f, "Components:  {}", components.join(" "))?;
+        if self.is_total() {
+            writeln!(f, "Total:       yes")
+        } else {
+            let missing: Vec<String> = self
+                .missing
+                .iter()
+                .map(|(state, entry)| format!("{} {}", tm.states()[*state], entry))
+                .collect();
+            writeln!(f, "Total:       no, missing {}", missing.join(", "))
+        }
+    }
+}
+
+/// Summarizes the transition table of `tm`.
+pub fn info(tm: &TuringMachine) -> Info<'_> {
+    let symbols = tm
+        .instructions()
+        .iter()
+        .map(|i| i.entry.max(i.new_entry) as usize + 1)
+        .max()
+        .unwrap_or(0)
+        .max(2);
+    let halting = tm
+        .instructions()
+        .iter()
+        .filter(|instruction| instruction.new_state.is_none())
+        .collect();
+    let mut missing = vec![];
+    for state in 0..tm.states().len() {
+        for entry in 0..symbols {
+            let entry = entry as TapeEntry;
+            if !tm
+                .instructions()
+                .iter()
+                .any(|instruction| instruction.state == state && instruction.entry == entry)
+            {
+                missing.push((state, entry));
+            }
+        }
+    }
+    Info {
+        tm,
+        symbols,
+        halting,
+        components: components(tm),
+        missing,
+    }
+}
+
+/// Strongly connected components of the state graph, found with Tarjan's
+/// algorithm.
+fn components(tm: &TuringMachine) -> Vec<Vec<usize>> {
+    struct Search {
+        successors: Vec<Vec<usize>>,
+        index: Vec<Option<usize>>,
+        lowlink: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next_index: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl Search {
+        fn visit(&mut self, state: usize) {
+            self.index[state] = Some(self.next_index);
+            self.lowlink[state] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(state);
+            self.on_stack[state] = true;
+
+            for successor in self.successors[state].clone() {
+                match self.index[successor] {
+                    None => {
+                        self.visit(successor);
+                        self.lowlink[state] = self.lowlink[state].min(self.lowlink[successor]);
+                    }
+                    Some(index) if self.on_stack[successor] => {
+                        self.lowlink[state] = self.lowlink[state].min(index);
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if Some(self.lowlink[state]) == self.index[state] {
+                let mut component = vec![];
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    component.push(member);
+                    if member == state {
+                        break;
+                    }
+                }
+                component.sort();
+                self.components.push(component);
+            }
+        }
+    }
+
+    let states = tm.states().len();
+    let mut successors = vec![vec![]; states];
+    for instruction in tm.instructions() {
+        if let Some(new_state) = instruction.new_state {
+            successors[instruction.state].push(new_state);
+        }
+    }
+    let mut search = Search {
+        successors,
+        index: vec![None; states],
+        lowlink: vec![0; states],
+        on_stack: vec![false; states],
+        stack: vec![],
+        next_index: 0,
+        components: vec![],
+    };
+    for state in 0..states {
+        if search.index[state].is_none() {
+            search.visit(state);
+        }
+    }
+    let mut components = search.components;
+    components.sort();
+    components
+}
+
+#[test]
+fn test_info() {
+    let tm =
+        TuringMachine::parse("#name Tiny\n#source-url https://example.org/a//b\nA 0 -> Halt 1 R")
+            .unwrap();
+    assert!(info(&tm).to_string().starts_with(
+        "Name:        Tiny\n\
+         Source URL:  https://example.org/a//b\n\
+         States:      1 (A)\n"
+    ));
+
+    let tm = TuringMachine::parse(
+        "A 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> C 1 R\nC 0 -> Halt 1 R",
+    )
+    .unwrap();
+    let info = info(&tm);
+    assert_eq!(info.symbols, 2);
+    assert_eq!(info.density(), 5.0 / 6.0);
+    assert_eq!(info.components, vec![vec![0, 1], vec![2]]);
+    assert!(!info.is_total());
+    assert_eq!(
+        info.to_string(),
+        "States:      3 (A, B, C)\n\
+         Symbols:     2\n\
+         Transitions: 5 of 6 (83%)\n\
+         Halting:     1\n  \
+         C 0 -> Halt 1 R\n\
+         Components:  {A, B} {C}\n\
+         Total:       no, missing C 1\n"
+    );
+}
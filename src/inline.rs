@@ -0,0 +1,156 @@
+use std::fmt::Write;
+
+use crate::{png, websocket::base64};
+
+/// Number of gray levels in sixel images.
+const LEVELS: usize = 16;
+/// Most base64 characters in one kitty graphics escape sequence.
+const CHUNK: usize = 4096;
+
+/// How images are shown inside the terminal.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum Protocol {
+    /// Whichever the terminal is known to support, going by its
+    /// environment variables.
+    Auto,
+    /// The graphics protocol of kitty, also spoken by WezTerm and Ghostty.
+    Kitty,
+    /// Sixel graphics, as of DEC terminals, xterm, foot and mlterm.
+    Sixel,
+}
+
+impl Protocol {
+    /// The protocol to use, which for `Auto` is the one the terminal
+    /// described by the environment supports, if any.
+    pub fn resolve(self) -> Option<Protocol> {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        match self {
+            Protocol::Auto => detect(
+                &var("TERM"),
+                &var("TERM_PROGRAM"),
+                std::env::var_os("KITTY_WINDOW_ID").is_some(),
+            ),
+            protocol => Some(protocol),
+        }
+    }
+
+    /// Escape sequences showing a grayscale image of `width` by `height`
+    /// pixels, a row after another, followed by a newline.
+    pub fn show(self, width: usize, height: usize, pixels: &[u8]) -> String {
+        match self {
+            Protocol::Sixel => sixel(width, height, pixels) + "\n",
+            _ => kitty(&png::encode_gray(width, height, pixels)) + "\n",
+        }
+    }
+}
+
+/// The protocol a terminal supports by the `TERM` and `TERM_PROGRAM`
+/// variables, and whether `KITTY_WINDOW_ID` is set. Terminals can't be
+/// asked without reading their answer from stdin, so others aren't found.
+fn detect(term: &str, program: &str, kitty: bool) -> Option<Protocol> {
+    let program = program.to_ascii_lowercase();
+    if kitty || term.contains("kitty") || ["wezterm", "ghostty"].contains(&program.as_str()) {
+        Some(Protocol::Kitty)
+    } else if term.contains("sixel")
+        || ["foot", "mlterm", "yaft", "contour"]
+            .iter()
+            .any(|name| term.starts_with(name))
+        || program == "iterm.app"
+    {
+        Some(Protocol::Sixel)
+    } else {
+        None
+    }
+}
+
+/// A PNG file in kitty graphics escape sequences, split into chunks.
+fn kitty(png: &[u8]) -> String {
+    let data = base64(png);
+    let chunks: Vec<&str> = data
+        .as_bytes()
+        .chunks(CHUNK)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .collect();
+    let mut out = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = usize::from(index + 1 < chunks.len());
+        match index {
+            0 => out.push_str(&format!("\x1b_Gf=100,a=T,m={more};{chunk}\x1b\\")),
+            _ => out.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\")),
+        }
+    }
+    out
+}
+
+/// A grayscale image as sixels, in bands of six rows with a color
+/// register per gray level and runs of equal sixels compressed.
+fn sixel(width: usize, height: usize, pixels: &[u8]) -> String {
+    let level = |x: usize, y: usize| pixels[y * width + x] as usize * LEVELS / 256;
+    let mut out = format!("\x1bPq\"1;1;{width};{height}");
+    for color in 0..LEVELS {
+        let percent = color * 100 / (LEVELS - 1);
+        let _ = write!(out, "#{color};2;{percent};{percent};{percent}");
+    }
+    for top in (0..height).step_by(6) {
+        let rows = top..(top + 6).min(height);
+        let mut colors: Vec<usize> = rows
+            .clone()
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| level(x, y))
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for color in colors {
+            let sixels: Vec<char> = (0..width)
+                .map(|x| {
+                    let bits = rows
+                        .clone()
+                        .filter(|y| level(x, *y) == color)
+                        .fold(0, |bits, y| bits | 1 << (y - top));
+                    (63 + bits) as u8 as char
+                })
+                .collect();
+            let _ = write!(out, "#{color}");
+            let mut start = 0;
+            while start < sixels.len() {
+                let end = (start..sixels.len())
+                    .find(|x| sixels[*x] != sixels[start])
+                    .unwrap_or(sixels.len());
+                match end - start {
+                    1..=3 => (start..end).for_each(|_| out.push(sixels[start])),
+                    run => {
+                        let _ = write!(out, "!{run}{}", sixels[start]);
+                    }
+                }
+                start = end;
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+#[test]
+fn test_inline() {
+    assert_eq!(detect("xterm-kitty", "", false), Some(Protocol::Kitty));
+    assert_eq!(
+        detect("xterm-256color", "WezTerm", false),
+        Some(Protocol::Kitty)
+    );
+    assert_eq!(detect("foot", "", false), Some(Protocol::Sixel));
+    assert_eq!(detect("xterm-256color", "", false), None);
+    assert_eq!(Protocol::Sixel.resolve(), Some(Protocol::Sixel));
+
+    // A white row over a black one.
+    let image = sixel(5, 2, &[255, 255, 255, 255, 255, 0, 0, 0, 0, 0]);
+    assert!(image.starts_with("\x1bPq\"1;1;5;2#0;2;0;0;0#1;2;6;6;6"));
+    assert!(image.ends_with("#0!5A$#15!5@$-\x1b\\"));
+
+    let png = vec![0; 4000];
+    let image = kitty(&png);
+    assert!(image.starts_with("\x1b_Gf=100,a=T,m=1;AAAA"));
+    assert_eq!(image.matches("\x1b\\").count(), 2);
+    assert!(image.contains("\x1b_Gm=0;AAAA"));
+}
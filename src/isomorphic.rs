@@ -0,0 +1,190 @@
+use std::{collections::VecDeque, fmt::Display};
+
+use crate::{
+    minimize,
+    transform::symbols,
+    turing::{Direction, Instruction, TapeEntry, TuringMachine},
+};
+
+/// How the states and symbols of one machine correspond to those of
+/// another.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mapping {
+    /// Pairs of state names, starting with the start states.
+    pub states: Vec<(String, String)>,
+    /// Pairs of symbols, starting with the blanks.
+    pub symbols: Vec<(TapeEntry, TapeEntry)>,
+    /// Whether every move left corresponds to a move right.
+    pub mirrored: bool,
+}
+
+impl Display for Mapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs: Vec<String> = self
+            .states
+            .iter()
+            .map(|(a, b)| format!("{a} -> {b}"))
+            .collect();
+        writeln!(f, "states: {}", pairs.join(", "))?;
+        let pairs: Vec<String> = self
+            .symbols
+            .iter()
+            .map(|(a, b)| format!("{a} -> {b}"))
+            .collect();
+        writeln!(f, "symbols: {}", pairs.join(", "))?;
+        write!(f, "mirrored: {}", self.mirrored)
+    }
+}
+
+/// Finds a renaming of the states and symbols of `a`, possibly with all
+/// moves mirrored, which makes it `b`, if there is one. The start states
+/// must correspond and so must the blanks. Only the states reachable from
+/// the start state are compared.
+///
+/// Every mapping of the non-blank symbols is tried. For one, the mapping
+/// of the states follows from the transitions by walking both machines
+/// from their start states.
+pub fn isomorphism(a: &TuringMachine, b: &TuringMachine) -> Option<Mapping> {
+    let (za, zb) = (a.with_zero_blank(), b.with_zero_blank());
+    let (symbols_a, symbols_b) = (symbols(&za), symbols(&zb));
+    let reachable = |tm: &TuringMachine| minimize::reachable(tm).len();
+    if symbols_a.len() != symbols_b.len() || reachable(&za) != reachable(&zb) {
+        return None;
+    }
+    // The symbols without the blank, which is first.
+    let mut permutation = symbols_b[1..].to_vec();
+    loop {
+        let image = |entry: TapeEntry| match symbols_a.iter().position(|s| *s == entry) {
+            Some(0) => 0,
+            Some(index) => permutation[index - 1],
+            None => entry,
+        };
+        for mirrored in [false, true] {
+            if let Some(states) = states(&za, &zb, &image, mirrored) {
+                // Back to the symbols of the machines, with their blanks.
+                let unswap = |tm: &TuringMachine, entry: TapeEntry| match entry {
+                    0 => tm.blank(),
+                    _ if entry == tm.blank() => 0,
+                    _ => entry,
+                };
+                return Some(Mapping {
+                    states: states
+                        .into_iter()
+                        .map(|(s, t)| (a.states()[s].clone(), b.states()[t].clone()))
+                        .collect(),
+                    symbols: symbols_a
+                        .iter()
+                        .map(|entry| (unswap(a, *entry), unswap(b, image(*entry))))
+                        .collect(),
+                    mirrored,
+                });
+            }
+        }
+        if !next_permutation(&mut permutation) {
+            return None;
+        }
+    }
+}
+
+/// The pairs of corresponding states if `image` maps the symbols of `a` to
+/// those of `b`, in the order they are found.
+fn states(
+    a: &TuringMachine,
+    b: &TuringMachine,
+    image: &impl Fn(TapeEntry) -> TapeEntry,
+    mirrored: bool,
+) -> Option<Vec<(usize, usize)>> {
+    let find = |tm: &TuringMachine, state: usize, entry: TapeEntry| -> Option<Instruction> {
+        tm.instructions()
+            .iter()
+            .find(|i| i.state == state && i.entry == entry)
+            .cloned()
+    };
+    let direction = |direction: Direction| match mirrored {
+        true => direction.mirrored(),
+        false => direction,
+    };
+    let entries = symbols(a);
+    let mut to = vec![None; a.states().len()];
+    let mut from = vec![None; b.states().len()];
+    let mut pairs = vec![(0, 0)];
+    let mut queue = VecDeque::from([(0, 0)]);
+    (to[0], from[0]) = (Some(0), Some(0));
+    while let Some((s, t)) = queue.pop_front() {
+        for entry in &entries {
+            match (find(a, s, *entry), find(b, t, image(*entry))) {
+                (None, None) => continue,
+                (Some(i), Some(j))
+                    if image(i.new_entry) == j.new_entry
+                        && direction(i.direction) == j.direction =>
+                {
+                    match (i.new_state, j.new_state) {
+                        (None, None) if i.halt == j.halt => {}
+                        (Some(u), Some(v)) => match (to[u], from[v]) {
+                            (None, None) => {
+                                (to[u], from[v]) = (Some(v), Some(u));
+                                pairs.push((u, v));
+                                queue.push_back((u, v));
+                            }
+                            (Some(w), _) if w == v => {}
+                            _ => return None,
+                        },
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            }
+        }
+    }
+    Some(pairs)
+}
+
+/// Rearranges `items` into the next permutation in lexicographic order,
+/// giving false after the last one.
+fn next_permutation<T: Ord>(items: &mut [T]) -> bool {
+    let Some(i) = (1..items.len()).rev().find(|i| items[i - 1] < items[*i]) else {
+        return false;
+    };
+    let j = (i..items.len())
+        .rev()
+        .find(|j| items[i - 1] < items[*j])
+        .unwrap();
+    items.swap(i - 1, j);
+    items[i..].reverse();
+    true
+}
+
+#[test]
+fn test_isomorphic() {
+    let tm = TuringMachine::parse(
+        "A 0 -> B 1 R\nA 1 -> C 2 L\nA 2 -> Halt 1 R\nB 0 -> A 2 R\nB 1 -> C 1 R\nC 0 -> B 1 L\n",
+    )
+    .unwrap();
+    // Mirrored, with the states B and C renamed and the symbols 1 and 2
+    // exchanged.
+    let other = TuringMachine::parse(
+        "A 0 -> Y 2 L\nA 2 -> X 1 R\nA 1 -> Halt 2 L\nX 0 -> Y 2 R\nY 0 -> A 1 L\nY 2 -> X 2 L\n",
+    )
+    .unwrap();
+    let mapping = isomorphism(&tm, &other).unwrap();
+    assert!(mapping.mirrored);
+    assert_eq!(mapping.symbols, [(0, 0), (1, 2), (2, 1)]);
+    assert_eq!(
+        mapping.to_string().lines().next(),
+        Some("states: A -> A, B -> Y, C -> X")
+    );
+    assert_eq!(isomorphism(&other, &tm).unwrap().symbols[1], (1, 2));
+
+    let different = TuringMachine::parse(
+        "A 0 -> Y 2 L\nA 2 -> X 1 R\nA 1 -> Halt 2 L\nX 0 -> Y 2 R\nY 0 -> A 1 L\nY 2 -> X 2 R\n",
+    )
+    .unwrap();
+    assert_eq!(isomorphism(&tm, &different), None);
+
+    let mut items = [1, 2, 3];
+    let mut count = 1;
+    while next_permutation(&mut items) {
+        count += 1;
+    }
+    assert_eq!((count, items), (6, [3, 2, 1]));
+}
@@ -0,0 +1,429 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    decide::{self, Decision},
+    json::Json,
+    sandbox::{self, Limits},
+    turing,
+};
+
+/// Depth of the backward reasoning of decide jobs.
+const DEPTH: usize = 20;
+/// Finished jobs kept, after which the oldest ones are forgotten.
+const MAX_FINISHED: usize = 10_000;
+
+/// What a job does with its machine.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Kind {
+    /// Simulates the machine on the blank tape, like `run`.
+    Run,
+    /// Runs the deciders on the machine, like `decide`.
+    Decide,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Run => "run",
+            Kind::Decide => "decide",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "run" => Ok(Kind::Run),
+            "decide" => Ok(Kind::Decide),
+            _ => Err(format!("unknown job kind '{name}'")),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Status {
+    Queued,
+    Running,
+    Done(Json),
+    Failed(String),
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Queued => "queued",
+            Status::Running => "running",
+            Status::Done(_) => "done",
+            Status::Failed(_) => "failed",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Status::Done(_) | Status::Failed(_))
+    }
+}
+
+/// A machine to analyze with a budget of steps.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub kind: Kind,
+    /// The machine file.
+    pub machine: String,
+    pub max_steps: u128,
+    pub status: Status,
+}
+
+impl Job {
+    pub fn to_json(&self) -> Json {
+        let mut fields = vec![
+            ("id".to_string(), self.id.into()),
+            ("kind".to_string(), self.kind.name().into()),
+            ("max_steps".to_string(), self.max_steps.into()),
+            ("status".to_string(), self.status.name().into()),
+        ];
+        match &self.status {
+            Status::Done(result) => fields.push(("result".to_string(), result.clone())),
+            Status::Failed(why) => fields.push(("error".to_string(), why.as_str().into())),
+            Status::Queued | Status::Running => {}
+        }
+        Json::Object(fields)
+    }
+
+    /// The record of a finished job with its machine, as persisted.
+    fn to_record(&self) -> Json {
+        let mut record = self.to_json();
+        if let Json::Object(fields) = &mut record {
+            fields.push(("machine".to_string(), self.machine.as_str().into()));
+        }
+        record
+    }
+
+    fn from_record(record: &Json) -> Result<Self, String> {
+        let status = match record.str_field("status")? {
+            "done" => Status::Done(record.field("result")?.clone()),
+            "failed" => Status::Failed(record.str_field("error")?.to_string()),
+            other => return Err(format!("job is {other}, not finished")),
+        };
+        Ok(Job {
+            id: u64::try_from(record.int_field("id")?).map_err(|_| "invalid job id")?,
+            kind: Kind::parse(record.str_field("kind")?)?,
+            machine: record.str_field("machine")?.to_string(),
+            max_steps: record.u128_field("max_steps")?,
+            status,
+        })
+    }
+
+    /// Does the work of the job within `limits`.
+    fn perform(&self, limits: &Limits) -> Status {
+        let mut tm = match turing::parse_machine(self.machine.as_bytes()) {
+            Ok(tm) => tm,
+            Err(why) => return Status::Failed(why),
+        };
+        tm.set_reject_undefined(true);
+        let max_steps = self.max_steps;
+        match self.kind {
+            Kind::Run => {
+                let limits = Limits {
+                    max_steps,
+                    ..*limits
+                };
+                let stop = sandbox::run(&mut tm, &limits);
+                Status::Done(Json::object([
+                    ("steps", tm.num_steps.into()),
+                    ("halted", tm.is_halted().into()),
+                    (
+                        "halt_reason",
+                        tm.halt_reason
+                            .map_or(Json::Null, |reason| reason.name().into()),
+                    ),
+                    ("stop", stop.name().into()),
+                    (
+                        "stop_reason",
+                        stop.reason(&limits)
+                            .map_or(Json::Null, |reason| reason.as_str().into()),
+                    ),
+                    ("tape_cells", tm.tape().len().into()),
+                ]))
+            }
+            Kind::Decide => {
+                let limits = Limits {
+                    max_steps,
+                    ..*limits
+                };
+                Status::Done(decision(&decide::decide_within(
+                    &tm.with_zero_blank(),
+                    &limits,
+                    DEPTH,
+                )))
+            }
+        }
+    }
+}
+
+fn decision(decision: &Decision) -> Json {
+    let mut fields = vec![("verdict".to_string(), decision.verdict().into())];
+    match decision {
+        Decision::Halts { steps, reason } => {
+            fields.push(("steps".to_string(), (*steps).into()));
+            fields.push(("halt_reason".to_string(), reason.name().into()));
+        }
+        Decision::NeverHalts(proof) => {
+            fields.push(("decider".to_string(), proof.decider().into()));
+            fields.push(("proof".to_string(), proof.to_string().into()));
+        }
+        Decision::Undecided => {}
+    }
+    Json::Object(fields)
+}
+
+/// Jobs submitted to the server, done in the background by a fixed number
+/// of workers so that long analyses don't hold up a request. At most
+/// `capacity` jobs wait at once. Finished jobs are appended to a file, if
+/// there is one, from which they are read again on the next start. Only the
+/// latest [`MAX_FINISHED`] finished jobs are kept in memory.
+pub struct Queue {
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    next_id: Mutex<u64>,
+    /// Notified whenever a job finishes.
+    finished: Condvar,
+    sender: Mutex<SyncSender<u64>>,
+    path: Option<PathBuf>,
+    limits: Limits,
+}
+
+impl Queue {
+    /// Starts `workers` workers doing jobs within `limits`, with the
+    /// finished jobs persisted in the JSON lines file at `path`.
+    pub fn start(
+        workers: usize,
+        capacity: usize,
+        limits: Limits,
+        path: Option<&Path>,
+    ) -> Result<Arc<Self>, String> {
+        let mut jobs = BTreeMap::new();
+        if let Some(path) = path.filter(|path| path.exists()) {
+            let content = fs::read_to_string(path).map_err(|why| why.to_string())?;
+            for (index, line) in content.lines().enumerate() {
+                let job = Json::parse(line)
+                    .and_then(|record| Job::from_record(&record))
+                    .map_err(|why| format!("{}:{}: {}", path.display(), index + 1, why))?;
+                jobs.insert(job.id, job);
+            }
+        }
+        forget_finished(&mut jobs, MAX_FINISHED);
+        let next_id = jobs.keys().next_back().map_or(0, |id| id + 1);
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let queue = Arc::new(Queue {
+            jobs: Mutex::new(jobs),
+            next_id: Mutex::new(next_id),
+            finished: Condvar::new(),
+            sender: Mutex::new(sender),
+            path: path.map(Path::to_path_buf),
+            limits,
+        });
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers.max(1) {
+            let (queue, receiver) = (queue.clone(), receiver.clone());
+            thread::spawn(move || queue.work(&receiver));
+        }
+        Ok(queue)
+    }
+
+    /// Adds a job with a budget of at most `max_steps` steps, giving its
+    /// id, or an error if too many jobs wait.
+    pub fn submit(&self, kind: Kind, machine: String, max_steps: u128) -> Result<u64, String> {
+        let max_steps = max_steps.min(self.limits.max_steps);
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id - 1
+        };
+        match self.sender.lock().unwrap().try_send(id) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => return Err("too many jobs are waiting".to_string()),
+            Err(TrySendError::Disconnected(_)) => return Err("no workers left".to_string()),
+        }
+        jobs.insert(
+            id,
+            Job {
+                id,
+                kind,
+                machine,
+                max_steps,
+                status: Status::Queued,
+            },
+        );
+        Ok(id)
+    }
+
+    pub fn job(&self, id: u64) -> Option<Job> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn jobs(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().values().cloned().collect()
+    }
+
+    /// The job `id` once it finished, or as it is after `timeout`.
+    pub fn wait(&self, id: u64, timeout: Duration) -> Option<Job> {
+        let deadline = Instant::now() + timeout;
+        let mut jobs = self.jobs.lock().unwrap();
+        loop {
+            let job = jobs.get(&id)?;
+            let left = deadline.saturating_duration_since(Instant::now());
+            if job.status.is_finished() || left.is_zero() {
+                return Some(job.clone());
+            }
+            jobs = self.finished.wait_timeout(jobs, left).unwrap().0;
+        }
+    }
+
+    fn work(&self, receiver: &Mutex<Receiver<u64>>) {
+        loop {
+            // Only hold the receiver while waiting, not during the job.
+            let id = match receiver.lock().unwrap().recv() {
+                Ok(id) => id,
+                Err(_) => return,
+            };
+            let job = {
+                let mut jobs = self.jobs.lock().unwrap();
+                let Some(job) = jobs.get_mut(&id) else {
+                    continue;
+                };
+                job.status = Status::Running;
+                job.clone()
+            };
+            let status = job.perform(&self.limits);
+            let mut jobs = self.jobs.lock().unwrap();
+            if let Some(job) = jobs.get_mut(&id) {
+                job.status = status;
+                if let Err(why) = self.persist(job) {
+                    job.status = Status::Failed(format!("can't persist the result: {why}"));
+                }
+            }
+            forget_finished(&mut jobs, MAX_FINISHED);
+            self.finished.notify_all();
+        }
+    }
+
+    fn persist(&self, job: &Job) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|why| why.to_string())?;
+        writeln!(file, "{}", job.to_record()).map_err(|why| why.to_string())
+    }
+}
+
+/// Removes the oldest finished jobs until at most `keep` are left.
+fn forget_finished(jobs: &mut BTreeMap<u64, Job>, keep: usize) {
+    let finished: Vec<u64> = jobs
+        .values()
+        .filter(|job| job.status.is_finished())
+        .map(|job| job.id)
+        .collect();
+    for id in &finished[..finished.len().saturating_sub(keep)] {
+        jobs.remove(id);
+    }
+}
+
+#[test]
+fn test_jobs() {
+    let path = std::env::temp_dir().join(format!("jobs_{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let limits = Limits {
+        max_steps: 1000,
+        ..Limits::NONE
+    };
+    let queue = Queue::start(2, 8, limits, Some(&path)).unwrap();
+    let bb2 = "A 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> Halt 1 R".to_string();
+    let cycler = fs::read_to_string("examples/deciders/cycler.turing").unwrap();
+
+    let run = queue.submit(Kind::Run, bb2.clone(), 100).unwrap();
+    let decide = queue.submit(Kind::Decide, cycler, 1000).unwrap();
+    let broken = queue.submit(Kind::Run, "A 0 ->".to_string(), 100).unwrap();
+    let forever = queue.submit(Kind::Run, "A 0 -> A 1 R".to_string(), u128::MAX);
+
+    let finished = queue.wait(run, Duration::from_secs(10)).unwrap();
+    let result = match &finished.status {
+        Status::Done(result) => result,
+        status => panic!("run job is {}", status.name()),
+    };
+    assert_eq!(result.int_field("steps"), Ok(6));
+    assert_eq!(result.str_field("halt_reason"), Ok("Halt"));
+
+    let job = queue.wait(decide, Duration::from_secs(10)).unwrap();
+    assert_eq!(
+        job.to_json().field("result").unwrap().str_field("verdict"),
+        Ok("never_halts")
+    );
+    let job = queue.wait(broken, Duration::from_secs(10)).unwrap();
+    assert_eq!(job.status.name(), "failed");
+    // The budget of a job is capped by the limits.
+    assert_eq!(queue.job(3).unwrap().max_steps, 1000);
+    let job = queue
+        .wait(forever.unwrap(), Duration::from_secs(10))
+        .unwrap();
+    let result = job.to_json();
+    assert_eq!(result.field("result").unwrap().int_field("steps"), Ok(1000));
+    assert!(queue.wait(99, Duration::ZERO).is_none());
+    assert_eq!(queue.jobs().len(), 4);
+
+    // Finished jobs come back after a restart, and new ids follow them.
+    let queue = Queue::start(1, 8, limits, Some(&path)).unwrap();
+    assert_eq!(queue.jobs().len(), 4);
+    assert_eq!(queue.job(run), Some(finished));
+    assert_eq!(queue.submit(Kind::Run, bb2, 10), Ok(4));
+    fs::remove_file(&path).unwrap();
+
+    let mut jobs: BTreeMap<u64, Job> = (0..5)
+        .map(|id| {
+            let status = match id {
+                1 => Status::Running,
+                _ => Status::Done(Json::Null),
+            };
+            let job = Job {
+                id,
+                kind: Kind::Run,
+                machine: String::new(),
+                max_steps: 0,
+                status,
+            };
+            (id, job)
+        })
+        .collect();
+    forget_finished(&mut jobs, 2);
+    assert_eq!(jobs.keys().copied().collect::<Vec<_>>(), [1, 3, 4]);
+
+    // Decide jobs give up at the time limit, even with steps to spare.
+    let limits = Limits {
+        max_time: Duration::from_millis(100),
+        ..Limits::NONE
+    };
+    let queue = Queue::start(1, 8, limits, None).unwrap();
+    let bb5 =
+        fs::read_to_string("examples/busy_bever/busy_bever_5_best_currently_known.turing").unwrap();
+    let start = Instant::now();
+    let id = queue.submit(Kind::Decide, bb5, u128::MAX).unwrap();
+    let job = queue.wait(id, Duration::from_secs(30)).unwrap();
+    assert_eq!(
+        job.to_json().field("result").unwrap().str_field("verdict"),
+        Ok("undecided")
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,346 @@
+use std::{fmt::Display, iter::Peekable, str::Chars};
+
+/// A JSON value. Integers are kept apart from other numbers so step counts
+/// survive a round trip exactly, and objects keep their key order.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Builds an object from key-value pairs.
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Self {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected '{c}' after the value")),
+        }
+    }
+
+    /// Looks up `key` in an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i128> {
+        match self {
+            Json::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Looks up a field that has to be present, for reading documents with
+    /// a fixed layout.
+    pub fn field(&self, key: &str) -> Result<&Json, String> {
+        self.get(key).ok_or(format!("missing field '{key}'"))
+    }
+
+    pub fn str_field(&self, key: &str) -> Result<&str, String> {
+        self.field(key)?
+            .as_str()
+            .ok_or(format!("field '{key}' is not a string"))
+    }
+
+    pub fn int_field(&self, key: &str) -> Result<i128, String> {
+        self.field(key)?
+            .as_int()
+            .ok_or(format!("field '{key}' is not an integer"))
+    }
+
+    /// Reads a count written from a `u128`, which may be a string of its
+    /// digits.
+    pub fn u128_field(&self, key: &str) -> Result<u128, String> {
+        match self.field(key)? {
+            Json::Int(n) => u128::try_from(*n).map_err(|_| format!("field '{key}' is negative")),
+            Json::String(s) => s
+                .parse()
+                .map_err(|_| format!("field '{key}' is not an integer")),
+            _ => Err(format!("field '{key}' is not an integer")),
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+macro_rules! json_from_int {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Json {
+            fn from(n: $t) -> Self {
+                Json::Int(n as i128)
+            }
+        })*
+    };
+}
+json_from_int!(u8, usize, isize, u64, i64);
+
+/// Counts beyond the integers of [`Json::Int`] are written as strings of
+/// their digits, which [`Json::u128_field`] reads.
+impl From<u128> for Json {
+    fn from(n: u128) -> Self {
+        match i128::try_from(n) {
+            Ok(n) => Json::Int(n),
+            Err(_) => Json::String(n.to_string()),
+        }
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Int(n) => write!(f, "{n}"),
+            // Always with a fraction or an exponent, so it reads back as a
+            // float.
+            Json::Float(x) if x.is_finite() => write!(f, "{x:?}"),
+            Json::Float(_) => write!(f, "null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, word: &str) -> Result<(), String> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("expected '{word}'"));
+        }
+    }
+    Ok(())
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        None => Err("unexpected end of input".to_string()),
+        Some('n') => expect(chars, "null").map(|_| Json::Null),
+        Some('t') => expect(chars, "true").map(|_| Json::Bool(true)),
+        Some('f') => expect(chars, "false").map(|_| Json::Bool(false)),
+        Some('"') => parse_string(chars).map(Json::String),
+        Some('[') => {
+            chars.next();
+            let mut values = vec![];
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Json::Array(values)),
+                    _ => return Err("expected ',' or ']' in array".to_string()),
+                }
+            }
+        }
+        Some('{') => {
+            chars.next();
+            let mut fields = vec![];
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(fields));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err(format!("expected ':' after key '{key}'"));
+                }
+                fields.push((key, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => {}
+                    Some('}') => return Ok(Json::Object(fields)),
+                    _ => return Err("expected ',' or '}' in object".to_string()),
+                }
+            }
+        }
+        Some(_) => parse_number(chars),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err("expected a string".to_string());
+    }
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('"') => s.push('"'),
+                Some('\\') => s.push('\\'),
+                Some('/') => s.push('/'),
+                Some('b') => s.push('\u{8}'),
+                Some('f') => s.push('\u{c}'),
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&hex, 16)
+                        .map_err(|_| format!("invalid escape '\\u{hex}'"))?;
+                    s.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                _ => return Err("invalid escape in string".to_string()),
+            },
+            Some(c) => s.push(c),
+        }
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    let mut number = String::new();
+    while let Some(c) = chars.peek() {
+        if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+            number.push(*c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    if let Ok(n) = number.parse() {
+        return Ok(Json::Int(n));
+    }
+    match number.parse() {
+        Ok(x) => Ok(Json::Float(x)),
+        Err(_) => Err(format!("invalid value '{number}'")),
+    }
+}
+
+#[test]
+fn test_json_round_trip() {
+    let value = Json::object([
+        ("name", "A \"quoted\"\nline".into()),
+        ("steps", 47_176_870u128.into()),
+        ("shift", (-3isize).into()),
+        ("ratio", Json::Float(0.5)),
+        ("tape", Json::Array(vec![0u8.into(), 1u8.into()])),
+        (
+            "nested",
+            Json::object([("ok", true.into()), ("none", Json::Null)]),
+        ),
+    ]);
+
+    let text = value.to_string();
+    assert_eq!(Json::parse(&text), Ok(value.clone()));
+    assert_eq!(value.int_field("steps"), Ok(47_176_870));
+    assert_eq!(
+        Json::parse(" { \"a\" : [ 1 , 2.5e1 ] } "),
+        Ok(Json::object([(
+            "a",
+            Json::Array(vec![Json::Int(1), Json::Float(25.0)])
+        )]))
+    );
+    let large = Json::object([("steps", u128::MAX.into()), ("ratio", Json::Float(2.0))]);
+    assert_eq!(
+        large.to_string(),
+        format!(r#"{{"steps":"{}","ratio":2.0}}"#, u128::MAX)
+    );
+    assert_eq!(Json::parse(&large.to_string()), Ok(large.clone()));
+    assert_eq!(large.u128_field("steps"), Ok(u128::MAX));
+    assert_eq!(value.u128_field("steps"), Ok(47_176_870));
+    assert!(value.u128_field("shift").is_err());
+    assert!(Json::parse("{\"a\": 1,}").is_err());
+    assert!(Json::parse("[1] 2").is_err());
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{bbchallenge, turing::TuringMachine};
+
+const HEADER: &str = "# touring leaderboard\n# states symbols record sigma steps machine\n";
+
+/// The two busy beaver functions: the most non-blank cells left on the tape
+/// and the most steps taken by a halting machine.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Record {
+    Sigma,
+    Steps,
+}
+
+impl Record {
+    fn name(&self) -> &'static str {
+        match self {
+            Record::Sigma => "sigma",
+            Record::Steps => "steps",
+        }
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// A halting machine started on the blank tape.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Entry {
+    pub states: usize,
+    pub symbols: usize,
+    /// Non-blank cells after halting.
+    pub sigma: u128,
+    pub steps: u128,
+    /// The machine in the bbchallenge text format.
+    pub machine: String,
+}
+
+impl Entry {
+    /// Runs `tm` from the blank tape, where missing transitions halt. Gives
+    /// `None` if it doesn't halt within `max_steps`.
+    pub fn of(tm: &TuringMachine, max_steps: u128) -> Option<Self> {
+        let mut tm =
+            TuringMachine::from_instructions(tm.states().to_vec(), tm.instructions().to_vec());
+        tm.set_reject_undefined(true);
+        while tm.num_steps < max_steps && tm.step() {}
+        if !tm.is_halted() {
+            return None;
+        }
+
+        let symbols = tm
+            .instructions()
+            .iter()
+            .map(|i| i.entry.max(i.new_entry) as usize + 1)
+            .max()
+            .unwrap_or(0)
+            .max(2);
+        Some(Entry {
+            states: tm.states().len(),
+            symbols,
+            sigma: tm.tape().iter().filter(|entry| **entry != 0).count() as u128,
+            steps: tm.num_steps,
+            machine: bbchallenge::format_machine(&tm),
+        })
+    }
+
+    /// Whether this entry beats `other` in `record`, using the other
+    /// function to break ties.
+    fn beats(&self, other: &Entry, record: Record) -> bool {
+        match record {
+            Record::Sigma => (self.sigma, self.steps) > (other.sigma, other.steps),
+            Record::Steps => (self.steps, self.sigma) > (other.steps, other.sigma),
+        }
+    }
+}
+
+/// The best machines found so far for every number of states and symbols,
+/// kept in a text file with one line per record.
+pub struct Leaderboard {
+    path: PathBuf,
+    records: BTreeMap<(usize, usize, Record), Entry>,
+}
+
+impl Leaderboard {
+    /// Reads the leaderboard at `path`, which is empty if it doesn't exist.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let mut records = BTreeMap::new();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(why) if why.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(why) => return Err(why.to_string()),
+        };
+        for line in content.lines() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (states, symbols, record, sigma, steps, machine) = match fields[..] {
+                [states, symbols, record, sigma, steps, machine] => {
+                    (states, symbols, record, sigma, steps, machine)
+                }
+                _ => return Err(format!("invalid line '{line}'")),
+            };
+            let number = |field: &str| {
+                field
+                    .parse::<u128>()
+                    .map_err(|_| format!("invalid number '{field}' in line '{line}'"))
+            };
+            let record = match record {
+                "sigma" => Record::Sigma,
+                "steps" => Record::Steps,
+                _ => return Err(format!("invalid record '{record}' in line '{line}'")),
+            };
+            let entry = Entry {
+                states: number(states)? as usize,
+                symbols: number(symbols)? as usize,
+                sigma: number(sigma)?,
+                steps: number(steps)?,
+                machine: machine.to_string(),
+            };
+            records.insert((entry.states, entry.symbols, record), entry);
+        }
+        Ok(Leaderboard {
+            path: path.to_path_buf(),
+            records,
+        })
+    }
+
+    /// Enters `entry` for every record it beats and returns those records.
+    pub fn submit(&mut self, entry: &Entry) -> Vec<Record> {
+        let mut improved = vec![];
+        for record in [Record::Sigma, Record::Steps] {
+            let key = (entry.states, entry.symbols, record);
+            if self
+                .records
+                .get(&key)
+                .is_none_or(|best| entry.beats(best, record))
+            {
+                self.records.insert(key, entry.clone());
+                improved.push(record);
+            }
+        }
+        improved
+    }
+
+    /// Writes the leaderboard back to its file. The file is replaced at once,
+    /// so an interrupted write leaves the old leaderboard intact.
+    pub fn save(&self) -> Result<(), String> {
+        let mut content = HEADER.to_string();
+        for ((_, _, record), entry) in &self.records {
+            content.push_str(&format!(
+                "{} {} {} {} {} {}\n",
+                entry.states, entry.symbols, record, entry.sigma, entry.steps, entry.machine
+            ));
+        }
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        fs::write(&temporary, content)
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|why| why.to_string())
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = (Record, &Entry)> {
+        self.records
+            .iter()
+            .map(|((_, _, record), entry)| (*record, entry))
+    }
+}
+
+#[test]
+fn test_leaderboard() {
+    let path = std::env::temp_dir().join(format!("leaderboard_{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let bb2 = bbchallenge::parse_machine("1RB1LB_1LA1RZ").unwrap();
+    let entry = Entry::of(&bb2, 100).unwrap();
+    assert_eq!((entry.states, entry.symbols), (2, 2));
+    assert_eq!((entry.sigma, entry.steps), (4, 6));
+    assert_eq!(Entry::of(&bb2, 5), None);
+
+    let mut leaderboard = Leaderboard::open(&path).unwrap();
+    let weaker = Entry {
+        sigma: 3,
+        ..entry.clone()
+    };
+    assert_eq!(
+        leaderboard.submit(&weaker),
+        vec![Record::Sigma, Record::Steps]
+    );
+    assert_eq!(
+        leaderboard.submit(&entry),
+        vec![Record::Sigma, Record::Steps]
+    );
+    assert_eq!(leaderboard.submit(&entry), vec![]);
+    leaderboard.save().unwrap();
+
+    let leaderboard = Leaderboard::open(&path).unwrap();
+    let records: Vec<(Record, &Entry)> = leaderboard.records().collect();
+    assert_eq!(
+        records,
+        vec![(Record::Sigma, &entry), (Record::Steps, &entry)]
+    );
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,182 @@
+use crate::turing::{Direction, HaltReason, TapeEntry, TuringMachine};
+
+/// Cells of the tape of every machine. Machines whose head leaves them are
+/// undecided, to be simulated by the normal engine.
+pub const TAPE_CELLS: usize = 1024;
+
+/// What a lockstep run found out about a machine.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Finished {
+    /// The machine halted after `steps` steps, leaving `ones` cells that
+    /// aren't blank. Missing transitions count as halting, with
+    /// [`HaltReason::Reject`].
+    Halted {
+        steps: u128,
+        reason: HaltReason,
+        ones: usize,
+    },
+    /// The machine still ran after the step budget, or left its tape.
+    Undecided,
+}
+
+/// Marks transitions that halt, and missing ones, in the table of next
+/// states.
+const HALTED: u32 = u32::MAX;
+
+/// Simulates many small machines in lockstep for up to `max_steps` steps
+/// each, giving what became of every one of them.
+///
+/// All machines take their first step, then all their second step and so
+/// on, with the transitions and tapes of all machines in flat arrays. This
+/// runs on the CPU only: no GPU backend is built, since neither wgpu nor
+/// CUDA bindings are available as dependencies. The layout is the one a
+/// data-parallel backend needs, so the loop over the machines of a step is
+/// what would run on the device. Machines halting within the
+/// budget are finished here; [`decide`](crate::decide::decide) takes care of
+/// the rest just as it would without the prefilter, as a machine halting
+/// within the budget is never found to cycle.
+pub fn run(machines: &[TuringMachine], max_steps: u128) -> Vec<Finished> {
+    let rows = machines
+        .iter()
+        .map(|tm| tm.states().len())
+        .max()
+        .unwrap_or(0);
+    let columns = machines
+        .iter()
+        .flat_map(|tm| tm.instructions())
+        .map(|instruction| instruction.entry as usize + 1)
+        .max()
+        .unwrap_or(1);
+    let cells = rows * columns;
+
+    // A cell of the table of a machine holds `None` where it has no
+    // instruction.
+    let mut transitions: Vec<Option<(u32, TapeEntry, Direction, HaltReason)>> =
+        vec![None; machines.len() * cells];
+    for (machine, tm) in machines.iter().enumerate() {
+        for instruction in tm.instructions() {
+            let cell = machine * cells + instruction.state * columns + instruction.entry as usize;
+            let new_state = instruction.new_state.map_or(HALTED, |state| state as u32);
+            transitions[cell].get_or_insert((
+                new_state,
+                instruction.new_entry,
+                instruction.direction,
+                instruction.halt,
+            ));
+        }
+    }
+
+    let mut states = vec![0u32; machines.len()];
+    let mut heads = vec![TAPE_CELLS / 2; machines.len()];
+    let mut tapes = vec![0 as TapeEntry; machines.len() * TAPE_CELLS];
+    let mut finished = vec![None; machines.len()];
+    let mut running = machines.len();
+
+    let mut steps = 0;
+    while steps < max_steps && running > 0 {
+        for machine in 0..machines.len() {
+            if finished[machine].is_some() {
+                continue;
+            }
+            let tape = &mut tapes[machine * TAPE_CELLS..(machine + 1) * TAPE_CELLS];
+            let entry = tape[heads[machine]] as usize;
+            let transition = match entry < columns {
+                true => transitions[machine * cells + states[machine] as usize * columns + entry],
+                false => None,
+            };
+            let done = match transition {
+                None => Some(halted(tape, steps, HaltReason::Reject)),
+                Some((new_state, new_entry, direction, reason)) => {
+                    tape[heads[machine]] = new_entry;
+                    states[machine] = new_state;
+                    let head = match direction {
+                        Direction::Left => heads[machine].checked_sub(1),
+                        Direction::Right => {
+                            Some(heads[machine] + 1).filter(|head| *head < TAPE_CELLS)
+                        }
+                    };
+                    match head {
+                        // Halting with the last step of the budget still
+                        // counts as running, as it does for `decide`.
+                        _ if new_state == HALTED => Some(match steps + 1 < max_steps {
+                            true => halted(tape, steps + 1, reason),
+                            false => Finished::Undecided,
+                        }),
+                        Some(head) => {
+                            heads[machine] = head;
+                            None
+                        }
+                        None => Some(Finished::Undecided),
+                    }
+                }
+            };
+            if done.is_some() {
+                finished[machine] = done;
+                running -= 1;
+            }
+        }
+        steps += 1;
+    }
+    finished
+        .into_iter()
+        .map(|finished| finished.unwrap_or(Finished::Undecided))
+        .collect()
+}
+
+fn halted(tape: &[TapeEntry], steps: u128, reason: HaltReason) -> Finished {
+    Finished::Halted {
+        steps,
+        reason,
+        ones: tape.iter().filter(|entry| **entry != 0).count(),
+    }
+}
+
+#[test]
+fn test_lockstep() {
+    use std::path::Path;
+
+    use crate::{
+        decide::{self, Decision},
+        turing,
+    };
+
+    let machines: Vec<TuringMachine> = [
+        "busy_bever/busy_bever_2.turing",
+        "busy_bever/busy_bever_4.turing",
+        "busy_bever/busy_bever_5_best_currently_known.turing",
+        "deciders/cycler.turing",
+    ]
+    .iter()
+    .map(|path| TuringMachine::new(&Path::new("examples").join(path)))
+    .collect();
+    let finished = run(&machines, 1000);
+    assert_eq!(
+        finished[1],
+        Finished::Halted {
+            steps: 107,
+            reason: HaltReason::Halt,
+            ones: 13
+        }
+    );
+    assert_eq!(finished[2], Finished::Undecided);
+    assert_eq!(finished[3], Finished::Undecided);
+
+    // Machines halting within the budget are the ones `decide` finds to
+    // halt, after the same number of steps. The head can't leave the tape
+    // in fewer steps than half its cells.
+    let machines: Vec<TuringMachine> = (0..2000)
+        .filter_map(|seed| turing::parse_machine(&turing::arbitrary_machine(seed)).ok())
+        .collect();
+    let max_steps = (TAPE_CELLS / 2) as u128;
+    for (tm, finished) in machines.iter().zip(run(&machines, max_steps)) {
+        let expected = match decide::decide(tm, max_steps, 0) {
+            Decision::Halts { steps, .. } => Some(steps),
+            _ => None,
+        };
+        let steps = match finished {
+            Finished::Halted { steps, .. } => Some(steps),
+            Finished::Undecided => None,
+        };
+        assert_eq!(steps, expected, "{tm}");
+    }
+}
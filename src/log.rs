@@ -0,0 +1,228 @@
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    io::Write,
+    sync::OnceLock,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::json::Json;
+
+/// How log lines are written to stderr.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    /// A line of text per event, for people.
+    Text,
+    /// A JSON object per line, for log collectors.
+    Json,
+}
+
+/// How important an event is, from the most to the least.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, clap::ValueEnum)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+        };
+        f.pad(name)
+    }
+}
+
+struct Logger {
+    format: Format,
+    level: Level,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+thread_local! {
+    /// Names of the spans entered on this thread, the innermost last.
+    static SPANS: RefCell<Vec<&'static str>> = const { RefCell::new(vec![]) };
+}
+
+/// Starts logging the events up to `level` to stderr. Without it, nothing
+/// is logged. Only the first call has an effect.
+pub fn init(format: Format, level: Level) {
+    let _ = LOGGER.set(Logger { format, level });
+}
+
+/// Whether events of `level` are logged.
+pub fn enabled(level: Level) -> bool {
+    LOGGER.get().is_some_and(|logger| level <= logger.level)
+}
+
+/// Logs `message` with `fields`, within the spans entered on this thread.
+pub fn event(level: Level, message: &str, fields: &[(&str, Json)]) {
+    let Some(logger) = LOGGER.get().filter(|logger| level <= logger.level) else {
+        return;
+    };
+    let line =
+        SPANS.with(|spans| format_line(logger.format, level, &spans.borrow(), message, fields));
+    let _ = writeln!(std::io::stderr().lock(), "{line}");
+}
+
+fn format_line(
+    format: Format,
+    level: Level,
+    spans: &[&str],
+    message: &str,
+    fields: &[(&str, Json)],
+) -> String {
+    let timestamp = timestamp(SystemTime::now());
+    match format {
+        Format::Text => {
+            let mut line = format!("{timestamp} {level:5} ");
+            if !spans.is_empty() {
+                line += &format!("{}: ", spans.join(":"));
+            }
+            line += message;
+            for (key, value) in fields {
+                match value {
+                    Json::String(text) => line += &format!(" {key}={text}"),
+                    value => line += &format!(" {key}={value}"),
+                }
+            }
+            line
+        }
+        Format::Json => {
+            let mut object = vec![
+                ("timestamp".to_string(), timestamp.into()),
+                ("level".to_string(), level.to_string().into()),
+                ("message".to_string(), message.into()),
+                (
+                    "spans".to_string(),
+                    Json::Array(spans.iter().map(|span| (*span).into()).collect()),
+                ),
+            ];
+            object.extend(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.clone())),
+            );
+            Json::Object(object).to_string()
+        }
+    }
+}
+
+/// `time` in UTC like `2024-03-01T12:34:56.789Z`.
+fn timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since.as_secs();
+    let (days, rest) = ((seconds / 86_400) as i64, seconds % 86_400);
+    // The civil date of a day since 1970, by Howard Hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rest / 3600,
+        rest / 60 % 60,
+        rest % 60,
+        since.subsec_millis()
+    )
+}
+
+/// A phase of the work, like parsing or simulating. The events of this
+/// thread are logged within it until it is dropped, which logs how long it
+/// took.
+pub struct Span {
+    name: &'static str,
+    fields: Vec<(&'static str, Json)>,
+    start: Instant,
+    active: bool,
+}
+
+/// Enters the span `name`, logging `fields` about it when it is entered
+/// and closed.
+pub fn span(name: &'static str, fields: &[(&'static str, Json)]) -> Span {
+    let active = enabled(Level::Info);
+    if active {
+        SPANS.with(|spans| spans.borrow_mut().push(name));
+        event(Level::Debug, "enter", fields);
+    }
+    Span {
+        name,
+        fields: match active {
+            true => fields.to_vec(),
+            false => vec![],
+        },
+        start: Instant::now(),
+        active,
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        let mut fields = std::mem::take(&mut self.fields);
+        fields.push(("seconds", Json::Float(self.start.elapsed().as_secs_f64())));
+        event(Level::Info, "close", &fields);
+        SPANS.with(|spans| {
+            let mut spans = spans.borrow_mut();
+            if let Some(index) = spans.iter().rposition(|name| *name == self.name) {
+                spans.remove(index);
+            }
+        });
+    }
+}
+
+#[test]
+fn test_log() {
+    use std::time::Duration;
+
+    assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    assert_eq!(
+        timestamp(UNIX_EPOCH + Duration::from_millis(1_709_296_496_789)),
+        "2024-03-01T12:34:56.789Z"
+    );
+    assert_eq!(
+        timestamp(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+        "2000-02-29T00:00:00.000Z"
+    );
+
+    let fields = [("decider", "cycler".into()), ("steps", 12u64.into())];
+    let text = format_line(Format::Text, Level::Info, &["decide"], "decided", &fields);
+    assert!(text.ends_with(" INFO  decide: decided decider=cycler steps=12"));
+    let json = Json::parse(&format_line(
+        Format::Json,
+        Level::Warn,
+        &["decide", "simulate"],
+        "decided",
+        &fields,
+    ))
+    .unwrap();
+    assert_eq!(json.str_field("level"), Ok("WARN"));
+    assert_eq!(json.str_field("decider"), Ok("cycler"));
+    assert_eq!(
+        json.field("spans").unwrap().to_string(),
+        r#"["decide","simulate"]"#
+    );
+
+    // Nothing is logged before `init`, and spans stay cheap.
+    assert!(!enabled(Level::Error));
+    let span = span("simulate", &[]);
+    assert!(!span.active);
+}
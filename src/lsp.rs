@@ -0,0 +1,509 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    json::Json,
+    minimize,
+    turing::{self, Instruction, TapeEntry, TuringMachine},
+};
+
+const ERROR: u8 = 1;
+const WARNING: u8 = 2;
+
+/// A word of a line, with its columns counted in UTF-16 code units as
+/// required by the protocol.
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+fn tokens(line: &str) -> Vec<Token<'_>> {
+    let line = turing::strip_comment(line).0;
+    let mut tokens = vec![];
+    let mut column = 0;
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((i, column)),
+            (true, Some((begin, start_column))) => {
+                tokens.push(Token {
+                    text: &line[begin..i],
+                    start: start_column,
+                    end: column,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+        column += c.len_utf16();
+    }
+    if let Some((begin, start_column)) = start {
+        tokens.push(Token {
+            text: &line[begin..],
+            start: start_column,
+            end: column,
+        });
+    }
+    tokens
+}
+
+/// A machine file as far as it could be read, with the line of every
+/// instruction and the lines that couldn't be read.
+struct Analysis<'a> {
+    lines: Vec<Vec<Token<'a>>>,
+    states: Vec<String>,
+    instructions: Vec<(usize, Instruction)>,
+    errors: Vec<(usize, String)>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut analysis = Analysis {
+            lines: vec![],
+            states: vec![],
+            instructions: vec![],
+            errors: vec![],
+        };
+        for (number, line) in text.lines().enumerate() {
+            analysis.lines.push(tokens(line));
+            // States of a line that can't be read don't count as defined.
+            let mut states = analysis.states.clone();
+            match turing::parse_instruction(line, &mut states) {
+                Ok(Some(instruction)) => {
+                    analysis.states = states;
+                    analysis.instructions.push((number, instruction));
+                }
+                Ok(None) => {}
+                Err(why) => analysis.errors.push((number, why)),
+            }
+        }
+        analysis
+    }
+
+    fn state(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|state| state == name)
+    }
+
+    /// The token naming `state` as the source of its first instruction.
+    fn definition(&self, state: usize) -> Option<(usize, &Token<'_>)> {
+        self.instructions
+            .iter()
+            .find(|(_, instruction)| instruction.state == state)
+            .map(|(line, _)| (*line, &self.lines[*line][0]))
+    }
+
+    /// The token naming `state` as the target of its first instruction.
+    fn first_use(&self, state: usize) -> Option<(usize, &Token<'_>)> {
+        self.instructions
+            .iter()
+            .find(|(_, instruction)| instruction.new_state == Some(state))
+            .map(|(line, _)| (*line, &self.lines[*line][3]))
+    }
+
+    /// The state named by the token at a position, if any.
+    fn state_at(&self, line: usize, character: usize) -> Option<usize> {
+        let tokens = self.lines.get(line)?;
+        let index = tokens
+            .iter()
+            .position(|token| token.start <= character && character <= token.end)?;
+        if index != 0 && index != 3 {
+            return None;
+        }
+        self.state(tokens[index].text)
+    }
+
+    fn diagnostics(&self) -> Vec<Json> {
+        let mut diagnostics: Vec<Json> = self
+            .errors
+            .iter()
+            .map(|(line, why)| {
+                let end = self.lines[*line].last().map_or(0, |token| token.end);
+                diagnostic(*line, 0, end, ERROR, why)
+            })
+            .collect();
+        if self.instructions.is_empty() {
+            return diagnostics;
+        }
+
+        let instructions = self.instructions.iter().map(|(_, i)| i.clone()).collect();
+        let tm = TuringMachine::from_instructions(self.states.clone(), instructions);
+        let reachable = minimize::reachable(&tm);
+        let mut symbols: Vec<TapeEntry> = self
+            .instructions
+            .iter()
+            .flat_map(|(_, i)| [i.entry, i.new_entry])
+            .chain([turing::DEFAULT_ENTRY])
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+
+        for (state, name) in self.states.iter().enumerate() {
+            let (line, token) = match self.definition(state).or(self.first_use(state)) {
+                Some(location) => location,
+                None => continue,
+            };
+            let mut warn = |message: String| {
+                diagnostics.push(diagnostic(line, token.start, token.end, WARNING, &message));
+            };
+            if !reachable.contains(&state) {
+                warn(format!("State {name} is unreachable from the start state"));
+                continue;
+            }
+            let missing: Vec<String> = symbols
+                .iter()
+                .filter(|entry| {
+                    !self
+                        .instructions
+                        .iter()
+                        .any(|(_, i)| i.state == state && i.entry == **entry)
+                })
+                .map(|entry| entry.to_string())
+                .collect();
+            if missing.len() == symbols.len() {
+                warn(format!("State {name} has no transitions"));
+            } else if !missing.is_empty() {
+                warn(format!(
+                    "State {name} has no transition for {} {}",
+                    if missing.len() == 1 {
+                        "symbol"
+                    } else {
+                        "symbols"
+                    },
+                    missing.join(", ")
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    fn hover(&self, text: &str, line: usize, character: usize) -> Json {
+        let state = match self.state_at(line, character) {
+            Some(state) => state,
+            None => return Json::Null,
+        };
+        let lines: Vec<&str> = text.lines().collect();
+        let transitions: Vec<&str> = self
+            .instructions
+            .iter()
+            .filter(|(_, instruction)| instruction.state == state)
+            .map(|(line, _)| lines[*line].trim())
+            .collect();
+        let entered = self
+            .instructions
+            .iter()
+            .filter(|(_, instruction)| instruction.new_state == Some(state))
+            .count();
+
+        let mut value = format!("**State {}**", self.states[state]);
+        if state == 0 {
+            value.push_str(" (start state)");
+        }
+        value.push_str(&format!(
+            "\n\n{} transitions, entered by {}",
+            transitions.len(),
+            entered
+        ));
+        if !transitions.is_empty() {
+            value.push_str(&format!("\n\n```\n{}\n```", transitions.join("\n")));
+        }
+        Json::object([(
+            "contents",
+            Json::object([("kind", "markdown".into()), ("value", value.into())]),
+        )])
+    }
+
+    fn definition_at(&self, uri: &str, line: usize, character: usize) -> Json {
+        match self
+            .state_at(line, character)
+            .and_then(|state| self.definition(state))
+        {
+            Some((line, token)) => Json::object([
+                ("uri", uri.into()),
+                ("range", range(line, token.start, token.end)),
+            ]),
+            None => Json::Null,
+        }
+    }
+}
+
+fn range(line: usize, start: usize, end: usize) -> Json {
+    let position =
+        |character: usize| Json::object([("line", line.into()), ("character", character.into())]);
+    Json::object([("start", position(start)), ("end", position(end))])
+}
+
+fn diagnostic(line: usize, start: usize, end: usize, severity: u8, message: &str) -> Json {
+    Json::object([
+        ("range", range(line, start, end)),
+        ("severity", severity.into()),
+        ("source", "touring".into()),
+        ("message", message.into()),
+    ])
+}
+
+/// Reads a message framed by a `Content-Length` header, or `None` at the end
+/// of the input. A body that isn't JSON is an error of its own, after which
+/// the next message can still be read.
+fn read_message(reader: &mut impl BufRead) -> Result<Option<Result<Json, String>>, String> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|why| why.to_string())? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                len = Some(
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid Content-Length '{}'", value.trim()))?,
+                );
+            }
+        }
+    }
+    let mut body = vec![0; len.ok_or("missing Content-Length")?];
+    reader
+        .read_exact(&mut body)
+        .map_err(|why| why.to_string())?;
+    Ok(Some(Json::parse(&String::from_utf8_lossy(&body))))
+}
+
+fn write_message(writer: &mut impl Write, message: &Json) -> Result<(), String> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| writer.flush())
+        .map_err(|why| why.to_string())
+}
+
+fn publish(writer: &mut impl Write, uri: &str, diagnostics: Vec<Json>) -> Result<(), String> {
+    write_message(
+        writer,
+        &Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            (
+                "params",
+                Json::object([
+                    ("uri", uri.into()),
+                    ("diagnostics", Json::Array(diagnostics)),
+                ]),
+            ),
+        ]),
+    )
+}
+
+/// Runs a language server for machine files, speaking the Language Server
+/// Protocol on `reader` and `writer` until the client asks it to exit.
+///
+/// The server publishes diagnostics for lines that can't be read, states
+/// that can't be reached and missing transitions, shows the transitions of
+/// a state on hover and jumps from a state name to its first transition.
+/// Returns whether the client shut the server down before exiting.
+pub fn run(reader: &mut impl BufRead, writer: &mut impl Write) -> Result<bool, String> {
+    let mut documents: HashMap<String, String> = HashMap::new();
+    let mut shutdown = false;
+    while let Some(message) = read_message(reader)? {
+        // A message that isn't JSON is answered and skipped, as JSON-RPC
+        // asks for.
+        let message = match message {
+            Ok(message) => message,
+            Err(why) => {
+                let error = Json::object([
+                    ("code", (-32700i64).into()),
+                    ("message", format!("parse error: {why}").into()),
+                ]);
+                write_message(
+                    writer,
+                    &Json::object([
+                        ("jsonrpc", "2.0".into()),
+                        ("id", Json::Null),
+                        ("error", error),
+                    ]),
+                )?;
+                continue;
+            }
+        };
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_string();
+        let position = |name: &str| {
+            params
+                .get("position")
+                .and_then(|position| position.get(name))
+                .and_then(Json::as_int)
+                .unwrap_or(0) as usize
+        };
+
+        let result = match method {
+            "initialize" => Ok(Json::object([
+                (
+                    "capabilities",
+                    Json::object([
+                        ("textDocumentSync", 1u8.into()),
+                        ("hoverProvider", true.into()),
+                        ("definitionProvider", true.into()),
+                    ]),
+                ),
+                ("serverInfo", Json::object([("name", "touring".into())])),
+            ])),
+            "shutdown" => {
+                shutdown = true;
+                Ok(Json::Null)
+            }
+            "exit" => return Ok(shutdown),
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let text = match method {
+                    "textDocument/didOpen" => params
+                        .get("textDocument")
+                        .and_then(|document| document.get("text")),
+                    // Only full updates are announced in the capabilities.
+                    _ => params
+                        .get("contentChanges")
+                        .and_then(Json::as_array)
+                        .and_then(|changes| changes.last())
+                        .and_then(|change| change.get("text")),
+                };
+                let text = text.and_then(Json::as_str).unwrap_or("").to_string();
+                publish(writer, &uri, Analysis::new(&text).diagnostics())?;
+                documents.insert(uri, text);
+                continue;
+            }
+            "textDocument/didClose" => {
+                documents.remove(&uri);
+                publish(writer, &uri, vec![])?;
+                continue;
+            }
+            "textDocument/hover" | "textDocument/definition" => {
+                let text = documents.get(&uri).map_or("", String::as_str);
+                let analysis = Analysis::new(text);
+                let (line, character) = (position("line"), position("character"));
+                Ok(match method {
+                    "textDocument/hover" => analysis.hover(text, line, character),
+                    _ => analysis.definition_at(&uri, line, character),
+                })
+            }
+            _ => Err(Json::object([
+                ("code", (-32601i64).into()),
+                ("message", format!("unknown method '{method}'").into()),
+            ])),
+        };
+
+        // Notifications have no id and get no response.
+        if let Some(id) = message.get("id") {
+            let (key, value) = match result {
+                Ok(result) => ("result", result),
+                Err(error) => ("error", error),
+            };
+            write_message(
+                writer,
+                &Json::object([("jsonrpc", "2.0".into()), ("id", id.clone()), (key, value)]),
+            )?;
+        }
+    }
+    Ok(shutdown)
+}
+
+#[test]
+fn test_diagnostics() {
+    let text = "A 0 -> B 1 R\nA 1 -> Halt 1 L\n\nB 0 -> A 1 X\nB 0 -> C 1 L\nD 0 -> A 1 R";
+    let diagnostics: Vec<String> = Analysis::new(text)
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| {
+            let start = diagnostic.get("range").unwrap().get("start").unwrap();
+            format!(
+                "{}:{} {}",
+                start.int_field("line").unwrap(),
+                start.int_field("character").unwrap(),
+                diagnostic.str_field("message").unwrap()
+            )
+        })
+        .collect();
+    assert_eq!(
+        diagnostics,
+        vec![
+            "3:0 couldn't parse direction 'X'",
+            "4:0 State B has no transition for symbol 1",
+            "4:7 State C has no transitions",
+            "5:0 State D is unreachable from the start state",
+        ]
+    );
+}
+
+#[test]
+fn test_hover_and_definition() {
+    let text = "A 0 -> B 1 R\nA 1 -> Halt 1 L\nB 0 -> A 1 L\nB 1 -> B 1 R";
+    let analysis = Analysis::new(text);
+
+    let hover = analysis.hover(text, 0, 7);
+    let value = hover.get("contents").unwrap().str_field("value").unwrap();
+    assert!(value.starts_with("**State B**\n\n2 transitions, entered by 2"));
+    assert!(value.contains("B 0 -> A 1 L\nB 1 -> B 1 R"));
+    assert_eq!(analysis.hover(text, 0, 9), Json::Null);
+
+    let definition = analysis.definition_at("file:///bb.turing", 2, 8);
+    assert_eq!(
+        definition.get("range").unwrap().to_string(),
+        range(0, 0, 1).to_string()
+    );
+}
+
+#[test]
+fn test_session() {
+    let messages = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        r#"{"jsonrpc":"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.turing","text":"A 0 -> B 1 R"}}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.turing"},"position":{"line":0,"character":0}}}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ];
+    let input: String = messages
+        .iter()
+        .map(|message| format!("Content-Length: {}\r\n\r\n{}", message.len(), message))
+        .collect();
+    let mut output = vec![];
+    assert_eq!(run(&mut input.as_bytes(), &mut output), Ok(true));
+
+    let output = String::from_utf8(output).unwrap();
+    let mut reader = output.as_bytes();
+    let mut responses = vec![];
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        responses.push(message.unwrap());
+    }
+    assert_eq!(responses.len(), 5);
+    let error = responses.remove(1);
+    assert_eq!(error.get("id"), Some(&Json::Null));
+    assert_eq!(error.get("error").unwrap().int_field("code"), Ok(-32700));
+    assert_eq!(
+        responses[0]
+            .get("result")
+            .unwrap()
+            .get("capabilities")
+            .unwrap()
+            .get("hoverProvider"),
+        Some(&Json::Bool(true))
+    );
+    assert_eq!(
+        responses[1].str_field("method"),
+        Ok("textDocument/publishDiagnostics")
+    );
+    assert_eq!(
+        responses[2].get("result").unwrap().str_field("uri"),
+        Ok("file:///a.turing")
+    );
+    assert_eq!(responses[3].get("result"), Some(&Json::Null));
+}
@@ -0,0 +1,274 @@
+use std::io::{Error, ErrorKind, Read, Write};
+
+/// Bytes compressed at once, which a match can reach back at most.
+const BLOCK: usize = 1 << 20;
+/// Shortest match worth encoding.
+const MIN_MATCH: usize = 4;
+/// Bits of the hash of the next bytes, which finds earlier matches.
+const HASH_BITS: u32 = 16;
+
+fn put_varint(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn varint_len(value: usize) -> usize {
+    (usize::BITS - value.leading_zeros()).div_ceil(7).max(1) as usize
+}
+
+fn get_varint(input: &[u8], pos: &mut usize) -> Result<usize, Error> {
+    let mut value = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let byte = *input
+            .get(*pos)
+            .ok_or_else(|| invalid("the block ends early"))?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("invalid number in a block"))
+}
+
+fn invalid(why: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, why)
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let word = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+    (word.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Compresses `data` into sequences of literal bytes followed by a match
+/// of earlier bytes, each a varint of the number of literals, the literals,
+/// a varint of the length of the match and, unless it is `0`, a varint of
+/// how far back it starts. Matches are found greedily by a hash of their
+/// first four bytes.
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let (mut pos, mut literals) = (0, 0);
+    while pos + MIN_MATCH <= data.len() {
+        let slot = &mut table[hash(&data[pos..])];
+        let candidate = std::mem::replace(slot, pos);
+        let length = match candidate {
+            usize::MAX => 0,
+            start => data[start..]
+                .iter()
+                .zip(&data[pos..])
+                .take_while(|(a, b)| a == b)
+                .count(),
+        };
+        // A match has to save more than the bytes it takes.
+        let distance = pos.wrapping_sub(candidate);
+        if length < MIN_MATCH.max(varint_len(distance) + 3) {
+            pos += 1;
+            continue;
+        }
+        put_varint(&mut out, pos - literals);
+        out.extend(&data[literals..pos]);
+        put_varint(&mut out, length);
+        put_varint(&mut out, distance);
+        for skipped in pos + 1..(pos + length).min(data.len() - MIN_MATCH) {
+            table[hash(&data[skipped..])] = skipped;
+        }
+        pos += length;
+        literals = pos;
+    }
+    put_varint(&mut out, data.len() - literals);
+    out.extend(&data[literals..]);
+    put_varint(&mut out, 0);
+    out
+}
+
+/// Reverses [`compress`], giving `size` bytes.
+fn decompress(input: &[u8], size: usize) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(size);
+    let mut pos = 0;
+    loop {
+        let literals = get_varint(input, &mut pos)?;
+        let bytes = pos
+            .checked_add(literals)
+            .and_then(|end| input.get(pos..end))
+            .ok_or_else(|| invalid("the block ends early"))?;
+        out.extend(bytes);
+        pos += literals;
+        let length = get_varint(input, &mut pos)?;
+        if length == 0 {
+            break;
+        }
+        let distance = get_varint(input, &mut pos)?;
+        if distance == 0 || distance > out.len() {
+            return Err(invalid("a match reaches before the block"));
+        }
+        if length > size.saturating_sub(out.len()) {
+            return Err(invalid("the block is larger than its size"));
+        }
+        // Matches may overlap the bytes they produce, so they are copied
+        // byte by byte.
+        for _ in 0..length {
+            out.push(out[out.len() - distance]);
+        }
+    }
+    if out.len() != size || pos != input.len() {
+        return Err(invalid("the block has the wrong size"));
+    }
+    Ok(out)
+}
+
+/// Compresses everything written to it in blocks of up to a MiB, each
+/// preceded by varints of its size and compressed size. Repeating
+/// sequences, like the steps of a machine sweeping over its tape, shrink to
+/// a few bytes. Blocks are written when full and on [`Write::flush`].
+pub struct Compressor<W: Write> {
+    out: W,
+    block: Vec<u8>,
+}
+
+impl<W: Write> Compressor<W> {
+    pub fn new(out: W) -> Self {
+        Compressor { out, block: vec![] }
+    }
+
+    fn write_block(&mut self) -> std::io::Result<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+        let compressed = compress(&self.block);
+        let mut header = vec![];
+        put_varint(&mut header, self.block.len());
+        put_varint(&mut header, compressed.len());
+        self.out.write_all(&header)?;
+        self.out.write_all(&compressed)?;
+        self.block.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Compressor<W> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let taken = data.len().min(BLOCK - self.block.len());
+        self.block.extend(&data[..taken]);
+        if self.block.len() == BLOCK {
+            self.write_block()?;
+        }
+        Ok(taken)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.write_block()?;
+        self.out.flush()
+    }
+}
+
+/// Reads what a [`Compressor`] wrote.
+pub struct Decompressor<R: Read> {
+    input: R,
+    block: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Decompressor<R> {
+    pub fn new(input: R) -> Self {
+        Decompressor {
+            input,
+            block: vec![],
+            pos: 0,
+        }
+    }
+
+    /// Reads a varint of the header of a block, `None` at the end.
+    fn header(&mut self, first: bool) -> std::io::Result<Option<usize>> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let mut byte = [0];
+            if self.input.read(&mut byte)? == 0 {
+                return match first && shift == 0 {
+                    true => Ok(None),
+                    false => Err(invalid("the block ends early")),
+                };
+            }
+            value |= ((byte[0] & 0x7f) as usize) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(value));
+            }
+        }
+        Err(invalid("invalid number in a block"))
+    }
+}
+
+impl<R: Read> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos == self.block.len() {
+            let Some(size) = self.header(true)? else {
+                return Ok(0);
+            };
+            let compressed = self.header(false)?.unwrap_or_default();
+            if size > BLOCK || compressed > 2 * BLOCK {
+                return Err(invalid("the block is too large"));
+            }
+            let mut input = vec![0; compressed];
+            self.input.read_exact(&mut input)?;
+            self.block = decompress(&input, size)?;
+            self.pos = 0;
+        }
+        let read = buf.len().min(self.block.len() - self.pos);
+        buf[..read].copy_from_slice(&self.block[self.pos..self.pos + read]);
+        self.pos += read;
+        Ok(read)
+    }
+}
+
+#[test]
+fn test_lz() {
+    let roundtrip = |data: &[u8]| {
+        let mut compressed = vec![];
+        let mut compressor = Compressor::new(&mut compressed);
+        compressor.write_all(data).unwrap();
+        compressor.flush().unwrap();
+        let mut out = vec![];
+        Decompressor::new(compressed.as_slice())
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, data);
+        compressed.len()
+    };
+    assert_eq!(roundtrip(b""), 0);
+    assert_eq!(roundtrip(b"abc"), 7);
+    // Overlapping matches repeat a pattern.
+    let repeated: Vec<u8> = b"tape".iter().copied().cycle().take(100_000).collect();
+    assert!(roundtrip(&repeated) < 20);
+    // Pseudo-random bytes don't shrink, but grow only a little, and span
+    // several blocks.
+    let mut state = 1u32;
+    let noise: Vec<u8> = (0..3 * BLOCK)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    assert!(roundtrip(&noise) < noise.len() + 100);
+
+    assert!(decompress(&[0, 4, 1], 4).is_err());
+    // A match far longer than the block, and more literals than fit.
+    let mut huge = vec![1, b'a'];
+    put_varint(&mut huge, usize::MAX >> 8);
+    put_varint(&mut huge, 1);
+    assert!(decompress(&huge, 4).is_err());
+    let mut overflow = vec![];
+    put_varint(&mut overflow, usize::MAX);
+    assert!(decompress(&overflow, 4).is_err());
+    assert!(Decompressor::new([5, 3, 1].as_slice())
+        .read_to_end(&mut vec![])
+        .is_err());
+}
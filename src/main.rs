@@ -1,33 +1,4748 @@
+mod abstraction;
+mod accel;
+mod alternating;
+mod bb;
+mod bbchallenge;
+mod bouncer;
+mod ca;
+mod collection;
+mod color;
+mod completions;
+mod config;
+mod configurations;
+mod counter;
+mod coverage;
+mod ctl;
+mod debugger;
+mod decide;
+mod diff;
+mod digest;
+mod dump;
+mod encoding;
+mod entropy;
+mod equiv;
+mod experiment;
+mod fingerprint;
+mod fmt;
+mod fusion;
+mod golden;
+mod grade;
+mod holdouts;
+mod hot_loop;
+mod http;
+mod info;
+mod inline;
+mod isomorphic;
+mod jobs;
+mod json;
+mod leaderboard;
+mod lockstep;
+mod log;
+mod lsp;
+mod lz;
+mod manifest;
+mod metrics;
+mod minimize;
+mod nondeterministic;
+mod normalize;
+mod oracle;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod pgo;
+mod pipeline;
+mod png;
+mod preprocess;
+mod probabilistic;
+mod query;
+mod ratelimit;
+mod reference;
+mod replay;
+mod report;
+mod rpc;
+mod sandbox;
+mod scan;
+mod server;
+mod spacetime;
+mod spec;
+mod sqlite;
+mod steps;
+mod tag;
+mod throughput;
+mod trajectory;
+mod transform;
 mod turing;
-use std::{path::PathBuf, time::Instant};
+mod utm;
+mod websocket;
+mod window;
+mod words;
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{BufReader, BufWriter, IsTerminal},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::atomic::AtomicBool,
+    time::Instant,
+};
 
-use clap::{command, Parser};
-use turing::TuringMachine;
+use accel::{Accel, MacroMachine, Proof};
+use bb::Span;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use color::Colors;
+use config::Config;
+use debugger::Debugger;
+use digest::Digest;
+use encoding::Encoding;
+use entropy::TapeStats;
+use fusion::Fusion;
+use hot_loop::HotLoops;
+use json::Json;
+use leaderboard::{Entry, Leaderboard, Record};
+use manifest::{Manifest, Shard};
+use pgo::Profile;
+use pipeline::{Pipeline, Timings};
+use preprocess::Param;
+use query::Filter;
+use steps::Steps;
+use throughput::{Meter, Sampler};
+use trajectory::Trajectory;
+use turing::{EdgeBehavior, HaltReason, TapeEntry, TuringMachine, Verdict};
+use utm::UtmScheme;
 
 #[derive(Debug, Parser)]
-#[command(version)]
+#[command(
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
-    /// Filename of the Turing-Machine to load.
-    filename: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Without a subcommand the machine is run as with `run`.
+    #[command(flatten)]
+    run: RunArgs,
+
+    /// Log the phases of the work, like parsing, simulating and deciding,
+    /// and the verdict of every decider to stderr, as text or as a JSON
+    /// object per line for log collectors.
+    #[arg(long, global = true, value_enum)]
+    log_format: Option<log::Format>,
+
+    /// Most detailed events logged. Implies `--log-format text` unless
+    /// another format is given.
+    #[arg(long, global = true, value_enum)]
+    log_level: Option<log::Level>,
+}
+
+// Options of `run`, which can also be given without the subcommand.
+#[derive(Debug, clap::Args)]
+struct RunArgs {
+    /// Filename of the Turing-Machine to load, or `-` to read it from stdin.
+    #[arg(required = true)]
+    filename: Option<PathBuf>,
+
+    /// Input written onto the tape, starting at the head. Either a word of
+    /// symbols or, with `--encode`, a list of numbers.
+    #[arg(long, num_args = 1..)]
+    input: Vec<String>,
+
+    /// Encode the input numbers and decode the numeric result after the
+    /// machine halts.
+    #[arg(long, value_enum)]
+    encode: Option<Encoding>,
+
+    /// Start on a tape written by `--dump-tape`, with its head position and
+    /// starting cell, or on the word in a text file.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["input", "encode"])]
+    tape_file: Option<PathBuf>,
+
+    /// Symbol of the cells never written, instead of the one of a
+    /// `%blank` line of the machine or 0.
+    #[arg(long, value_name = "SYMBOL")]
+    blank: Option<TapeEntry>,
+
+    /// Print the final tape, e.g. to pass it to `decode`.
+    #[arg(long)]
+    print_tape: bool,
+
+    /// Write the final tape with the head position and the starting cell
+    /// to this file, as raw bytes, text or JSON by its extension.
+    #[arg(long, value_name = "out.bin|out.txt|out.json")]
+    dump_tape: Option<PathBuf>,
+
+    /// Report the sequences of up to this many transitions that are
+    /// repeated most often, e.g. to choose a block size for acceleration.
+    #[arg(long, value_name = "MAX_PERIOD", num_args = 0..=1, default_missing_value = "16")]
+    hot_loops: Option<usize>,
+
+    /// Count how often every transition is taken and write the counts to
+    /// this file, to pass to `--pgo` in later runs.
+    #[arg(long, value_name = "profile.json")]
+    profile: Option<PathBuf>,
+
+    /// Report the transitions the run never took, which are dead for this
+    /// input, like the coverage of code by tests.
+    #[arg(long)]
+    coverage: bool,
+
+    /// Fail with exit code 6 unless the run took every transition, e.g. to
+    /// check that a machine uses all of its states. Implies `--coverage`.
+    #[arg(long)]
+    require_full_coverage: bool,
+
+    /// Lay out the transition table with the states taken most often in a
+    /// profile written by `--profile` first, so the hot transitions share
+    /// cache lines. The states are listed in that order.
+    #[arg(long, value_name = "profile.json")]
+    pgo: Option<PathBuf>,
+
+    /// Simulate on blocks of this many cells, crossing runs of identical
+    /// blocks in a single step. `auto` picks the block size from the tape
+    /// and re-tunes it when the acceleration stalls.
+    #[arg(
+        long,
+        value_name = "N|auto",
+        conflicts_with_all = ["hot_loops", "profile", "coverage", "print_tape", "dump_tape", "verbose", "left_edge", "bounded"]
+    )]
+    accel: Option<Accel>,
+
+    /// Take up to this many steps at once, from 1 to 8, with transitions
+    /// computed from the cells around the head and cached. The run is the
+    /// same as without.
+    #[arg(
+        long,
+        value_name = "K",
+        conflicts_with_all = ["accel", "hot_loops", "profile", "coverage", "break_state", "digest", "record_golden", "compare_golden", "trajectory", "verbose"]
+    )]
+    fuse: Option<usize>,
+
+    /// Stop after this many steps if the machine hasn't halted by then.
+    #[arg(long)]
+    max_steps: Option<u128>,
+
+    /// Stop when the tape grows beyond this many cells, so a run can't take
+    /// all memory.
+    #[arg(long, value_name = "N")]
+    max_tape_cells: Option<u128>,
+
+    /// Pause as soon as the machine enters this state.
+    #[arg(long, value_name = "STATE", conflicts_with = "accel")]
+    break_state: Option<String>,
+
+    /// Print a hash of the state, head position and written symbol of every
+    /// step, to compare runs for exactly the same behavior.
+    #[arg(long, conflicts_with = "accel")]
+    digest: bool,
+
+    /// Record the state, head movement and written symbol of every step
+    /// into a compact trace, to check later runs against.
+    #[arg(long, value_name = "TRACE", conflicts_with = "accel")]
+    record_golden: Option<PathBuf>,
+
+    /// Compress the trace of `--record-golden`, for runs of hundreds of
+    /// millions of steps to commit as test fixtures. Checking reads both.
+    #[arg(long, requires = "record_golden")]
+    compress_golden: bool,
+
+    /// Check every step against a trace recorded with `--record-golden` and
+    /// stop at the first difference, e.g. to validate changes of the engine.
+    #[arg(long, value_name = "TRACE", conflicts_with_all = ["accel", "record_golden"])]
+    compare_golden: Option<PathBuf>,
+
+    /// Write the step, the head position from the starting cell and the
+    /// length of the tape to this CSV file, to plot how the head sweeps
+    /// over the tape, e.g. with gnuplot or matplotlib.
+    #[arg(long, value_name = "out.csv", conflicts_with = "accel")]
+    trajectory: Option<PathBuf>,
+
+    /// Write a row of `--trajectory` only every this many steps, for long
+    /// runs.
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "trajectory")]
+    trajectory_every: u128,
+
+    /// Show the steps and new tape cells per second on stderr while the
+    /// machine runs, right now and as a moving average, e.g. to see an
+    /// acceleration pay off.
+    #[arg(long)]
+    progress: bool,
+
+    /// Only print the summary of the run, without the states, instructions
+    /// and timing.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Show the space-time diagram of the run inside the terminal at the
+    /// end, with kitty graphics or sixels. The run is replayed for it, with
+    /// up to 400 rows of the tape sampled evenly.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "PROTOCOL",
+        num_args = 0..=1,
+        default_missing_value = "auto",
+        conflicts_with = "accel"
+    )]
+    inline: Option<inline::Protocol>,
+
+    /// Trace the run: print the state and instruction before every step,
+    /// and with `-vv` the tape as well.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Report the Shannon entropy of the symbols on the final tape and its
+    /// compressed size in the summary, which are low for machines writing
+    /// regular patterns and high for chaotic ones.
+    #[arg(long)]
+    tape_stats: bool,
+
+    /// Format of the summary. With `json`, everything else is printed to
+    /// stderr, as it is when the machine is read from stdin.
+    #[arg(long, value_enum, default_value = "text")]
+    output: Output,
+
+    /// Don't color the output. Setting the `NO_COLOR` environment variable
+    /// has the same effect.
+    #[arg(long)]
+    no_color: bool,
+
+    /// Value of a parameter declared with `%param` in the machine file.
+    #[arg(long = "param", value_name = "NAME=VALUE")]
+    params: Vec<Param>,
+
+    #[command(flatten)]
+    tape: TapeArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run a machine and report the busy beaver score of its final tape.
+    ///
+    /// This is also what happens without a subcommand. The states and
+    /// instructions are printed first, then the time the simulation took.
+    /// Exits with 0 if the machine halted, 1 on errors, 2 if `--max-steps`
+    /// ran out, 3 if the acceleration proved that it never halts, 4 if it
+    /// paused at `--break-state`, 5 if `--max-tape-cells` ran out, 6 if
+    /// `--require-full-coverage` found transitions never taken and 7 if it
+    /// crashed on a missing transition or was rejected by `--left-edge` or
+    /// `--bounded`.
+    Run(RunArgs),
+    /// Run a machine on an input word and report whether it accepts it.
+    ///
+    /// Exits with 0 if the machine accepts (halts in `Accept` or `Halt`),
+    /// 1 if it rejects (halts in `Reject`, has no matching transition or
+    /// crashes) and 2 if the step budget is exceeded. With
+    /// `--nondeterministic`, it accepts if any branch accepts, and with
+    /// `--alternating` as the game tree of its `%universal` and other,
+    /// existential states decides.
+    Accept {
+        /// Filename of the Turing-Machine to load.
+        filename: PathBuf,
+
+        /// Input word written onto the tape, starting at the head.
+        #[arg(long, default_value = "")]
+        input: String,
+
+        /// Maximum number of steps before giving up, per branch with
+        /// `--nondeterministic`.
+        #[arg(long, default_value_t = 1_000_000)]
+        max_steps: u128,
+
+        /// Follow every instruction matching a configuration instead of only
+        /// the first, as a non-deterministic machine.
+        #[arg(long)]
+        nondeterministic: bool,
+
+        /// Write the choices of the accepting branch to this JSON file, to
+        /// show it with `--replay`.
+        #[arg(long, value_name = "PATH", requires = "nondeterministic")]
+        record_path: Option<PathBuf>,
+
+        /// Print every step of the branch taking the choices recorded in
+        /// this file with `--record-path`.
+        #[arg(long, value_name = "PATH", conflicts_with = "nondeterministic")]
+        replay: Option<PathBuf>,
+
+        /// Evaluate the machine as an alternating machine, with the steps
+        /// budget bounding the depth of the game tree.
+        #[arg(long, conflicts_with_all = ["nondeterministic", "replay"])]
+        alternating: bool,
+
+        /// Most configurations the alternating evaluation looks at.
+        #[arg(long, default_value_t = 1_000_000, requires = "alternating")]
+        max_configurations: usize,
+
+        /// Run the machine this many times as a probabilistic machine,
+        /// flipping coins weighted by `%weights` between matching
+        /// instructions, and print the share of accepting trials.
+        #[arg(long, conflicts_with_all = ["nondeterministic", "replay", "alternating"])]
+        trials: Option<u64>,
+
+        /// Seed of the coins flipped by `--trials`.
+        #[arg(long, default_value_t = 0, requires = "trials")]
+        seed: u64,
+
+        /// Answer the queries of a machine with `%oracle` states with this
+        /// oracle: a `.turing` machine deciding the queries within the
+        /// steps budget, or a file listing the words in the set, one per
+        /// line.
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["nondeterministic", "replay", "alternating", "trials"]
+        )]
+        oracle: Option<PathBuf>,
+
+        #[command(flatten)]
+        tape: TapeArgs,
+    },
+    /// Run a machine on a list of words and print a pass/fail table.
+    ///
+    /// Exits with 0 if every word got its expected verdict.
+    Test {
+        /// Filename of the Turing-Machine to load.
+        filename: PathBuf,
+
+        /// File with one input word per line. Words listed in the
+        /// `--expect` files are always tested as well.
+        words: Option<PathBuf>,
+
+        /// Files listing the words that should be accepted and rejected.
+        #[arg(long, num_args = 2, value_names = ["ACCEPT_FILE", "REJECT_FILE"])]
+        expect: Vec<PathBuf>,
+
+        /// Maximum number of steps per word before giving up.
+        #[arg(long, default_value_t = 1_000_000)]
+        max_steps: u128,
+
+        #[command(flatten)]
+        tape: TapeArgs,
+    },
+    /// Run the tests of a machine from its test file.
+    ///
+    /// The tests of `machine.turing` are read from `machine.test.toml`,
+    /// where every section is a test giving an input, parameters and a step
+    /// budget, and the expected halting state and final tape, in which `?`
+    /// matches any cell and `*` any number of cells. Exits with 0 if every
+    /// test passed.
+    Check {
+        /// Filename of the Turing-Machine to test.
+        filename: PathBuf,
+
+        /// Test file to read instead of the one next to the machine.
+        #[arg(long)]
+        spec: Option<PathBuf>,
+    },
+    /// Grade submitted machines by the tests of an assignment.
+    ///
+    /// The assignment is a test file like the ones of `check`, where every
+    /// test can be worth some `points`. Every `.turing` file in the
+    /// submissions directory is a student's machine, named like the file,
+    /// and gets a score of the points of the tests it passes. Machines that
+    /// can't be read score nothing.
+    ///
+    /// Submissions are untrusted, so every test runs within caps on its
+    /// steps, tape and time. A test reaching one fails, with the cap it
+    /// reached in the report.
+    Grade {
+        /// The tests of the assignment.
+        assignment: PathBuf,
+
+        /// Directory with a machine file per student.
+        submissions: PathBuf,
+
+        /// Format of the report.
+        #[arg(long, value_enum, default_value = "csv")]
+        format: grade::Format,
+
+        /// Write the report to this file instead of stdout.
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Most steps of a test, if fewer than its own budget.
+        #[arg(long)]
+        max_steps: Option<u128>,
+
+        /// Most cells the tape of a test may grow to.
+        #[arg(long, default_value = "1e7", value_parser = pipeline::parse_size)]
+        max_tape_cells: usize,
+
+        /// Most seconds a test may run.
+        #[arg(long, value_name = "SECONDS", default_value = "10", value_parser = sandbox::parse_seconds)]
+        timeout: std::time::Duration,
+    },
+    /// Encode a machine and its input into a tape for a universal machine.
+    ///
+    /// The printed tape can be passed as `--input` when running the
+    /// universal machine belonging to the scheme.
+    Encode {
+        /// Filename of the Turing-Machine to encode.
+        filename: PathBuf,
+
+        /// Encoding scheme, which determines the universal machine to use.
+        #[arg(long, value_enum)]
+        utm: UtmScheme,
+
+        /// Input word of the encoded machine.
+        #[arg(long, default_value = "")]
+        input: String,
+    },
+    /// Run a counter machine or compile it into a Turing machine.
+    ///
+    /// Files ending in `.cm` are 2-counter machines with the counters `a`
+    /// and `b`, other files register machines with labelled `inc` and `dec`
+    /// instructions. With `--to-turing` the compiled machine is written to
+    /// stdout unless `--output` is given, and the tape to start it on is
+    /// printed to stderr.
+    Counter {
+        /// Filename of the counter machine.
+        filename: PathBuf,
+
+        /// Starting values of the registers, separated by spaces or commas.
+        /// Missing registers start at zero.
+        #[arg(long, default_value = "")]
+        registers: String,
+
+        /// Maximum number of instructions before giving up.
+        #[arg(long, default_value_t = 1_000_000)]
+        max_steps: u128,
+
+        /// Compile into a Turing machine instead of running the program.
+        #[arg(long)]
+        to_turing: bool,
+
+        /// Run the compiled Turing machine instead of the program and read
+        /// the registers back from its tape, with the steps budget counting
+        /// its steps.
+        #[arg(long, conflicts_with = "to_turing")]
+        via_turing: bool,
+
+        /// File to write the compiled machine to.
+        #[arg(short, long, requires = "to_turing")]
+        output: Option<PathBuf>,
+    },
+    /// Simulate an elementary cellular automaton, like rule 110.
+    ///
+    /// Every row of the space-time diagram is printed, with the first and
+    /// the last cell as neighbors.
+    Ca {
+        /// Wolfram code of the rule.
+        #[arg(long, default_value_t = 110)]
+        rule: u8,
+
+        /// Number of steps after the starting row.
+        #[arg(long, default_value_t = 100)]
+        steps: usize,
+
+        /// Number of cells.
+        #[arg(long, default_value_t = 80)]
+        width: usize,
+
+        /// Starting cells in the middle of the row, in the format of
+        /// `--input`, where symbols other than `0` are live cells.
+        #[arg(long, default_value = "1")]
+        input: String,
+
+        /// Write the space-time diagram to this PNG file.
+        #[arg(long, value_name = "PATH")]
+        png: Option<PathBuf>,
+
+        /// Write the space-time diagram to this SVG file.
+        #[arg(long, value_name = "PATH")]
+        svg: Option<PathBuf>,
+
+        /// Pixels per cell in the PNG or SVG file, in both directions.
+        #[arg(long, default_value_t = 1)]
+        scale: usize,
+
+        /// Don't print the rows.
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Run a tag system, e.g. one compiled by `transform --to-tag`.
+    ///
+    /// Prints the final word. Exits with 0 if the tag system halted and
+    /// with 2 if it still ran after `--max-steps` steps.
+    Tag {
+        /// Filename of the tag system.
+        filename: PathBuf,
+
+        /// Word to start with instead of the `%word` of the file, with the
+        /// letters separated by spaces.
+        #[arg(long)]
+        word: Option<String>,
+
+        /// Maximum number of steps of the tag system before giving up.
+        #[arg(long, default_value_t = 1_000_000)]
+        max_steps: u128,
+
+        /// Run the cyclic tag system emulating the tag system instead, and
+        /// read the word back from its bits.
+        #[arg(long)]
+        cyclic: bool,
+    },
+    /// Summarize the transition table of a machine.
+    ///
+    /// Prints the number of states and symbols, how many of the possible
+    /// transitions are defined, the halting transitions, the strongly
+    /// connected components of the state graph and whether every state has
+    /// a transition for every symbol.
+    #[command(alias = "analyze")]
+    Info {
+        /// Filename of the Turing-Machine to summarize.
+        filename: PathBuf,
+    },
+    /// Remove unreachable states and merge equivalent ones.
+    ///
+    /// The smaller machine is written to stdout unless `--output` is given.
+    Minimize {
+        /// Filename of the Turing-Machine to minimize.
+        filename: PathBuf,
+
+        /// File to write the minimized machine to.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Rename states and symbols in the order a run from the blank tape
+    /// first uses them.
+    ///
+    /// The result is the tree normal form, so machines only differing in
+    /// the names of their states and symbols, or mirrored, normalize to the
+    /// same machine. It is written to stdout unless `--output` is given.
+    Normalize {
+        /// Filename of the Turing-Machine to normalize.
+        filename: PathBuf,
+
+        /// File to write the normalized machine to.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print a short hash of machines, the same for machines only
+    /// differing in the names of their states and symbols, or mirrored.
+    ///
+    /// The hash is taken of the tree normal form, so it finds duplicates and
+    /// names a machine in discussions independently of how it is written.
+    /// With `--steps`, the digest of the first steps from the blank tape
+    /// follows after the number of steps, which tells apart machines that
+    /// behave differently. A line per file has the fingerprint and the
+    /// filename.
+    Fingerprint {
+        /// Filenames of the Turing-Machines to fingerprint.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Include the behavior of this many steps.
+        #[arg(long)]
+        steps: Option<u128>,
+    },
+    /// Format machine files canonically, in place.
+    ///
+    /// Instructions are sorted by state and symbol and their columns are
+    /// aligned. Comments starting with `//` are kept.
+    Fmt {
+        /// Filenames of the Turing-Machines to format.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Don't write the files, but exit with 1 if any of them isn't
+        /// formatted.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Convert a machine into an equivalent one.
+    ///
+    /// The new machine is written to stdout unless `--output` is given.
+    #[command(alias = "convert")]
+    Transform {
+        /// Filename of the Turing-Machine to transform.
+        filename: PathBuf,
+
+        #[command(flatten)]
+        kind: TransformKind,
+
+        /// File to write the transformed machine to.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check whether two machines behave the same on a list of inputs.
+    ///
+    /// Both machines are run on every input and their halting behavior and
+    /// final tapes are compared. Exits with 1 at the first input where they
+    /// differ.
+    Equiv {
+        /// Filename of the first Turing-Machine.
+        a: PathBuf,
+
+        /// Filename of the second Turing-Machine.
+        b: PathBuf,
+
+        /// File with one input word per line. Defaults to the empty tape.
+        #[arg(long)]
+        inputs: Option<PathBuf>,
+
+        /// Maximum number of steps per machine and input.
+        #[arg(long, default_value_t = 1_000_000)]
+        max_steps: u128,
+    },
+    /// Prove facts about a machine without running it (experimental).
+    ///
+    /// Abstract interpretation follows the machine from the blank tape with
+    /// only `--window` cells on either side of the head known, and beyond
+    /// them either blanks or unknown cells. The abstract configurations
+    /// cover all real ones, so if none of them halts, the machine never
+    /// halts, and transitions none of them uses are dead. A larger window
+    /// proves more, but takes more configurations.
+    Abstract {
+        /// Filename of the Turing-Machine to analyze.
+        filename: PathBuf,
+
+        /// Number of cells known on either side of the head.
+        #[arg(long, default_value_t = 4)]
+        window: usize,
+
+        /// Most abstract configurations to find.
+        #[arg(long, default_value_t = 1_000_000)]
+        max_configurations: usize,
+    },
+    /// Write a standalone HTML page showing a machine step by step.
+    ///
+    /// The page has the transitions, the state graph, a table of the first
+    /// `--steps` steps and an animation of the tape during them. It needs no
+    /// server, so it can be handed out as a single file. With `--emit-tikz`,
+    /// the state graph is written as a TikZ picture for LaTeX instead, and
+    /// with `--emit-mermaid` as a Mermaid diagram for Markdown.
+    Report {
+        /// Filename of the Turing-Machine to show.
+        filename: PathBuf,
+
+        /// Number of steps to show.
+        #[arg(long, default_value_t = 500)]
+        steps: u128,
+
+        /// Input word written onto the tape, starting at the head.
+        #[arg(long, default_value = "")]
+        input: String,
+
+        /// File to write the page to. Defaults to stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Write the state graph as a TikZ picture instead of a page.
+        #[arg(long)]
+        emit_tikz: bool,
+
+        /// Write the state graph as a Mermaid state diagram instead of a
+        /// page.
+        #[arg(long, conflicts_with = "emit_tikz")]
+        emit_mermaid: bool,
+
+        /// Follow the TikZ picture with a table of the first `--steps`
+        /// steps.
+        #[arg(long, requires = "emit_tikz")]
+        trace: bool,
+    },
+    /// Draw the space-time diagram of a run, one row of the tape per step.
+    ///
+    /// The tape before the first step is on top, followed by the tape after
+    /// every `--sample`th step and at the halt, with a color for every
+    /// symbol and the head in red. SVG files have a legend of the colors and
+    /// stay crisp however far they are zoomed, even for posters of long
+    /// runs; PNG files are in gray.
+    Spacetime {
+        /// Filename of the Turing-Machine to draw.
+        filename: PathBuf,
+
+        /// Most steps to run.
+        #[arg(long, default_value_t = 1000)]
+        steps: u128,
+
+        /// Input word written onto the tape, starting at the head.
+        #[arg(long, default_value = "")]
+        input: String,
+
+        /// Draw only every this many steps.
+        #[arg(long, default_value_t = 1)]
+        sample: u128,
+
+        /// Write the diagram to this SVG file.
+        #[arg(long, value_name = "PATH", required_unless_present = "png")]
+        svg: Option<PathBuf>,
+
+        /// Write the diagram to this PNG file.
+        #[arg(long, value_name = "PATH")]
+        png: Option<PathBuf>,
+
+        /// Pixels per cell, in both directions.
+        #[arg(long, default_value_t = 4)]
+        scale: usize,
+    },
+    /// Find every configuration a machine reaches on a bounded tape.
+    ///
+    /// The configurations reachable from the input on a tape of `--cells`
+    /// cells are searched breadth first, with a branch for every matching
+    /// instruction of non-deterministic machines. As there are finitely
+    /// many, this tells whether the machine can halt at all. The graph of
+    /// configurations can be written for Graphviz with `--dot`.
+    #[command(alias = "explore")]
+    Configurations {
+        /// Filename of the Turing-Machine to explore.
+        filename: PathBuf,
+
+        /// Input word written onto the tape, starting at the head.
+        #[arg(long, default_value = "")]
+        input: String,
+
+        /// Number of cells of the tape, starting at the head.
+        #[arg(long)]
+        cells: usize,
+
+        /// What happens when the head tries to leave the tape.
+        #[arg(long, value_enum, default_value = "crash")]
+        on_bound: EdgeBehavior,
+
+        /// Most configurations to find.
+        #[arg(long, default_value_t = 1_000_000)]
+        max_configurations: usize,
+
+        /// Write the graph of configurations in the DOT language to this
+        /// file.
+        #[arg(long, value_name = "FILE")]
+        dot: Option<PathBuf>,
+    },
+    /// Check whether two machines are the same up to renaming.
+    ///
+    /// States and symbols may be renamed and all moves mirrored, but the
+    /// start states and the blanks must correspond. Prints the mapping of
+    /// states and symbols found, or exits with 1 if there is none.
+    Isomorphic {
+        /// Filename of the first Turing-Machine.
+        a: PathBuf,
+
+        /// Filename of the second Turing-Machine.
+        b: PathBuf,
+    },
+    /// Run two machines side by side and show where they start to differ.
+    ///
+    /// Both machines take a step at a time and every step prints their
+    /// states and tapes next to each other. The first step after which their
+    /// tapes, heads or halting differ is highlighted, e.g. to compare a
+    /// machine with a minimized copy. Exits with 1 if the machines diverged.
+    Race {
+        /// Filename of the first Turing-Machine.
+        a: PathBuf,
+
+        /// Filename of the second Turing-Machine.
+        b: PathBuf,
+
+        /// Input word written onto both tapes, starting at the head.
+        #[arg(long, default_value = "")]
+        input: String,
+
+        /// Maximum number of steps per machine.
+        #[arg(long, default_value_t = 1000)]
+        max_steps: u128,
+
+        /// Only print where the machines diverged, not every step.
+        #[arg(short, long)]
+        quiet: bool,
+
+        /// Don't color the output.
+        #[arg(long)]
+        no_color: bool,
+    },
+    /// Compare the transition tables of two machines, ignoring how their
+    /// states are named and ordered.
+    ///
+    /// States are matched by following both machines from their start
+    /// states. Exits with 0 if the tables are the same up to renaming
+    /// states and 1 otherwise.
+    Diff {
+        /// Filename of the first Turing-Machine.
+        a: PathBuf,
+
+        /// Filename of the second Turing-Machine.
+        b: PathBuf,
+    },
+    /// Try to decide whether a machine halts when started on the blank tape.
+    ///
+    /// The machine is simulated while looking for cycles and translated
+    /// cycles, then for bouncers, whose tape keeps growing in the same
+    /// pattern, then for a closed language of tapes without halting
+    /// configurations, then backward reasoning from the halting transitions
+    /// is tried, or all of it at once with `--parallel`. Missing transitions
+    /// count as halting. Exits with 0 if the question was decided and 2
+    /// otherwise.
+    Decide {
+        /// Filename of the Turing-Machine to decide.
+        filename: PathBuf,
+
+        /// Number of steps simulated while looking for cycles.
+        #[arg(long, default_value_t = 100_000)]
+        max_steps: u128,
+
+        /// Number of steps searched backwards from the halting transitions.
+        #[arg(long, default_value_t = 20)]
+        depth: usize,
+
+        /// Write a JSON certificate to this file if the machine never halts,
+        /// which can be checked with `verify-cert`.
+        #[arg(long)]
+        cert: Option<PathBuf>,
+
+        /// Run the analyses at the same time on their own threads, stopping
+        /// all of them once one decides.
+        #[arg(long)]
+        parallel: bool,
+
+        /// Run these deciders in order with their budgets instead, e.g.
+        /// `sim:1e6,cycler:1e5,tcycler:1e5,bouncer:1e4,ctl:4,backward:20`,
+        /// and print how long each took.
+        #[arg(long, conflicts_with = "parallel")]
+        pipeline: Option<Pipeline>,
+
+        /// Look for cycles and translated cycles by fingerprints of the
+        /// state and this many cells around the head first, which is
+        /// cheaper than the full deciders.
+        #[arg(long, value_name = "CELLS")]
+        window: Option<usize>,
+
+        /// Number of fingerprints remembered by `--window`, e.g. `1e6`.
+        #[arg(long, default_value = "1e6", value_parser = pipeline::parse_size, requires = "window")]
+        history: usize,
+    },
+    /// Check a certificate written by `decide --cert` by simulating the
+    /// machine.
+    ///
+    /// Exits with 0 if the certificate proves that the machine never halts.
+    VerifyCert {
+        /// Filename of the Turing-Machine the certificate is about.
+        filename: PathBuf,
+
+        /// The certificate to check.
+        certificate: PathBuf,
+    },
+    /// Cross-check a decider verification file published by bbchallenge.
+    ///
+    /// Every machine listed in the file is checked with this crate's own
+    /// deciders and certificate verification. Exits with 0 if all claims
+    /// could be confirmed.
+    CheckDvf {
+        /// The decider verification file.
+        dvf: PathBuf,
+
+        #[command(flatten)]
+        machines: MachineArgs,
+
+        /// Number of steps simulated while looking for cycles.
+        #[arg(long, default_value_t = 100_000)]
+        max_steps: u128,
+    },
+    /// Run the deciders on many machines and print a verdict per machine.
+    ///
+    /// Each line shows the machine id, the machine in the bbchallenge text
+    /// format and either `halts` with the step count, the decider that
+    /// proved it never halts or `undecided`.
+    Batch {
+        #[command(flatten)]
+        machines: MachineArgs,
+
+        /// Id of the first machine to run.
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+
+        /// Number of machines to run. Defaults to all remaining ones.
+        #[arg(long)]
+        count: Option<u64>,
+
+        /// Number of steps simulated per machine while looking for cycles.
+        #[arg(long, default_value_t = 10_000)]
+        max_steps: u128,
+
+        /// Number of steps searched backwards from the halting transitions.
+        #[arg(long, default_value_t = 20)]
+        depth: usize,
+
+        /// Only run part `K` of `N` equal parts of the machines, e.g. to
+        /// split a run between several hosts.
+        #[arg(long, value_name = "K/N")]
+        shard: Option<Shard>,
+
+        /// Record every verdict in this file and skip the machines already
+        /// recorded there, so an interrupted run can be resumed.
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+
+        /// Enter halting machines into this leaderboard file when they beat
+        /// a record.
+        #[arg(long)]
+        leaderboard: Option<PathBuf>,
+
+        /// Simulate this many machines at a time in lockstep first, leaving
+        /// only the ones that don't halt within `--max-steps` to the
+        /// deciders. Verdicts are the same as without. The lockstep
+        /// prefilter runs on the CPU; there is no GPU backend.
+        #[arg(long, value_name = "MACHINES")]
+        lockstep: Option<usize>,
+
+        /// When the run finishes, write the undecided machines to this file
+        /// with the budgets and deciders tried, to run them again with
+        /// `--holdouts`.
+        #[arg(long, value_name = "FILE")]
+        export_holdouts: Option<PathBuf>,
+
+        /// Run these deciders in order with their budgets instead of the
+        /// default ones, e.g. `sim:1e9,cycler:1e6,tcycler:1e7,ctl:4`, and
+        /// print how long each took in total. Budgets are steps, except for
+        /// the longest n-grams of `ctl` and the depth of `backward`.
+        #[arg(long)]
+        pipeline: Option<Pipeline>,
+
+        /// Write a CSV line per machine to this file with its verdict, the
+        /// decider, the steps simulated, the time taken and the peak memory
+        /// of the process while deciding it. Files ending in `.parquet` are
+        /// written in Parquet with the `parquet` feature.
+        #[arg(long, value_name = "FILE")]
+        log: Option<PathBuf>,
+
+        /// Store the same as `--log` in this SQLite database, in a table
+        /// `results` to search with `query`.
+        #[arg(long, value_name = "FILE")]
+        db: Option<PathBuf>,
+    },
+    /// Compute busy beaver values by deciding every machine of some sizes.
+    ///
+    /// The machines are enumerated in tree normal form and run from the
+    /// blank tape; the ones still running after `--max-steps` steps go to
+    /// the deciders of `decide`. Prints the champions of both busy beaver
+    /// functions for every number of states and symbols, which are exact if
+    /// no machine was left undecided. Exits with 0 if all machines were
+    /// decided and 2 otherwise.
+    Bb {
+        /// Numbers of states, e.g. `2..4` or `3`.
+        #[arg(long, default_value = "2..4")]
+        states: Span,
+
+        /// Numbers of symbols, e.g. `2..3` or `2`.
+        #[arg(long, default_value = "2")]
+        symbols: Span,
+
+        /// Number of steps simulated per machine, also while looking for
+        /// cycles.
+        #[arg(long, default_value_t = 1000)]
+        max_steps: u128,
+
+        /// Number of steps searched backwards from the halting transitions.
+        #[arg(long, default_value_t = 20)]
+        depth: usize,
+
+        /// Only decide the machines with an index in `START..END`, like
+        /// `0..50000`, of every number of states and symbols. The others are
+        /// still run to number them, unless they come after the range.
+        #[arg(long, value_name = "START..END", value_parser = bb::parse_range)]
+        range: Option<Range<u64>>,
+
+        /// Write a CSV line per decided machine to this file like `batch
+        /// --log`, with the index of the machine as its id.
+        #[arg(long, value_name = "FILE")]
+        log: Option<PathBuf>,
+
+        /// Store the same as `--log` in this SQLite database like `batch
+        /// --db`.
+        #[arg(long, value_name = "FILE")]
+        db: Option<PathBuf>,
+    },
+    /// Print the index of a machine in the enumeration of `bb`.
+    ///
+    /// The machine has to be written as `machine-at` prints it, which is in
+    /// tree normal form with only its transitions used from the blank tape
+    /// defined. Exits with 2 if it isn't in the enumeration.
+    IndexOf {
+        /// The machine in the bbchallenge format.
+        machine: String,
+
+        /// Number of symbols of the enumeration.
+        #[arg(long, default_value_t = 2)]
+        symbols: usize,
+
+        /// Number of steps simulated per machine, as given to `bb`.
+        #[arg(long, default_value_t = 1000)]
+        max_steps: u128,
+    },
+    /// Print the machine at an index in the enumeration of `bb`.
+    ///
+    /// Exits with 2 if the enumeration has fewer machines.
+    MachineAt {
+        index: u64,
+
+        /// Number of states of the enumeration.
+        #[arg(long)]
+        states: usize,
+
+        /// Number of symbols of the enumeration.
+        #[arg(long, default_value_t = 2)]
+        symbols: usize,
+
+        /// Number of steps simulated per machine, as given to `bb`.
+        #[arg(long, default_value_t = 1000)]
+        max_steps: u128,
+    },
+    /// Print the results stored by `batch --db` or `bb --db` matching a
+    /// filter.
+    ///
+    /// The filter is a condition in SQL like `verdict='undecided' AND
+    /// steps>1e8`, comparing the columns `id`, `machine`, `verdict`,
+    /// `decider`, `steps`, `seconds` and `peak_memory` with each other or
+    /// with numbers and strings, combined with `AND`, `OR`, `NOT` and
+    /// parentheses. `decider IS NULL` holds for undecided machines.
+    Query {
+        /// The condition on the rows to print.
+        filter: Filter,
+
+        /// The database to search.
+        #[arg(long, value_name = "FILE", default_value = "results.sqlite")]
+        db: PathBuf,
+    },
+    /// Keep track of the best busy beaver machines in a leaderboard file.
+    ///
+    /// For every number of states and symbols, the file holds the halting
+    /// machine leaving the most non-blank cells (sigma) and the one taking
+    /// the most steps.
+    Leaderboard {
+        #[command(subcommand)]
+        action: LeaderboardAction,
+    },
+    /// Manage a collection of machines listed in a `collection.toml`.
+    ///
+    /// Every section of the file is a machine with its file, tags to select
+    /// it by, a description, and how it is run and expected to end up, with
+    /// the keys of the test files of `check` and the exact number of
+    /// `steps`. Machine files are relative to the collection file.
+    Collection {
+        #[command(subcommand)]
+        action: CollectionAction,
+    },
+    /// Check the simulator against a simple reference implementation.
+    ///
+    /// The machine is run by the simulator and the reference side by side
+    /// and their configurations are compared after every step, then the
+    /// same is done for every given acceleration and number of fused steps.
+    /// Missing transitions reject. Exits with 1 at the first difference.
+    Selftest {
+        /// Filename of the Turing-Machine to run.
+        filename: PathBuf,
+
+        /// Number of steps to compare.
+        #[arg(long, default_value_t = 100_000)]
+        steps: u128,
+
+        /// Input word written onto the tape, starting at the head.
+        #[arg(long, default_value = "")]
+        input: String,
+
+        /// Accelerations to check as well, as block sizes or `auto`.
+        #[arg(long, value_name = "N|auto", num_args = 1..)]
+        accel: Vec<Accel>,
+
+        /// Numbers of fused steps to check as well, see `run --fuse`.
+        #[arg(long, value_name = "K", num_args = 1..)]
+        fuse: Vec<usize>,
+    },
+    /// Time the simulator on a machine, to compare its speed across changes.
+    ///
+    /// The machine is run `--runs` times from the start, until it halts or
+    /// for `--max-steps` steps. The fastest and the median run are reported,
+    /// as the fastest is the least disturbed by other load. Build with
+    /// `--release` for meaningful numbers.
+    Bench {
+        /// Filename of the Turing-Machine to run.
+        filename: PathBuf,
+
+        /// Number of runs.
+        #[arg(long, default_value_t = 5)]
+        runs: usize,
+
+        /// Stop each run after this many steps if the machine hasn't halted.
+        #[arg(long)]
+        max_steps: Option<u128>,
+    },
+    /// Step back and forth through a run, reading commands from stdin.
+    ///
+    /// The machine is copied every `--snapshot-every` steps, so that
+    /// `goto-step` reaches any step of a long run quickly, earlier ones as
+    /// well. Missing transitions reject. Type `help` for the commands.
+    Debug {
+        /// Filename of the Turing-Machine to debug.
+        filename: PathBuf,
+
+        /// Input word written onto the tape, starting at the head.
+        #[arg(long, default_value = "")]
+        input: String,
+
+        /// Number of steps between snapshots. Fewer steps take more memory
+        /// but make going back faster.
+        #[arg(long, default_value_t = 1_000_000)]
+        snapshot_every: u128,
+
+        /// Value of a parameter declared with `%param` in the machine file.
+        #[arg(long = "param", value_name = "NAME=VALUE")]
+        params: Vec<Param>,
+    },
+    /// Play back a trace recorded with `run --record-golden`, reading
+    /// commands from stdin.
+    ///
+    /// The trace holds every step, so it is replayed without simulating the
+    /// machine, from a blank tape or `--input`, on which the run has to have
+    /// started. Jump to any step, seek to a part of the trace and play it at
+    /// a chosen speed. Type `help` for the commands.
+    Replay {
+        /// The trace file.
+        trace: PathBuf,
+
+        /// Input word the run started on.
+        #[arg(long, default_value = "")]
+        input: String,
+
+        /// The machine of the run, for the names of its states and its
+        /// blank symbol.
+        #[arg(long, value_name = "FILE")]
+        machine: Option<PathBuf>,
+
+        /// Number of steps between snapshots. Fewer steps take more memory
+        /// but make going back faster.
+        #[arg(long, default_value_t = 1_000_000)]
+        snapshot_every: u128,
+    },
+    /// Print a completion script for a shell.
+    ///
+    /// Completes subcommands, options and their values, and otherwise
+    /// machine files. For bash, e.g. add `source <(turing completions bash)`
+    /// to `~/.bashrc`.
+    Completions { shell: completions::Shell },
+    /// Serve an HTTP API to load machines and step through them remotely.
+    ///
+    /// Machines are uploaded with `POST /machines` and driven with
+    /// `/machines/{id}/step`, `/machines/{id}/run` and `/machines/{id}/tape`.
+    /// All responses are JSON. `/machines/{id}/stream` upgrades to a
+    /// WebSocket that streams configurations for live animations, and
+    /// `/metrics` gives counters and latencies for Prometheus.
+    ///
+    /// Long analyses are submitted as jobs with `POST /jobs`, done by a pool
+    /// of workers in the background and polled with `/jobs/{id}`.
+    ///
+    /// The service of `proto/touring.proto` is served with the Twirp
+    /// protocol in JSON at `/twirp/touring.Simulator/{Method}`, for Twirp
+    /// clients generated from the schema. There is no gRPC server.
+    ///
+    /// With `--public`, e.g. for a classroom playground on the internet, the
+    /// budgets are capped to a few seconds and a million tape cells, every
+    /// client address may send only so many requests a minute, only so many
+    /// connections are handled at once, machines may take at most a MiB of
+    /// memory, the oldest machines make room for new ones and nothing is
+    /// written to disk.
+    Serve {
+        /// Port to listen on.
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+
+        /// Most steps a single request may simulate.
+        #[arg(long, default_value_t = 100_000_000)]
+        max_budget: u128,
+
+        /// Most cells the tape of a machine may grow to.
+        #[arg(long, default_value = "1e8", value_parser = pipeline::parse_size)]
+        max_tape_cells: usize,
+
+        /// Most seconds a single request may simulate.
+        #[arg(long, value_name = "SECONDS", default_value = "10", value_parser = sandbox::parse_seconds)]
+        timeout: std::time::Duration,
+
+        /// Number of workers doing jobs at once. Defaults to the number of
+        /// CPUs.
+        #[arg(long)]
+        workers: Option<usize>,
+
+        /// Most jobs waiting for a worker. Further jobs are refused.
+        #[arg(long, default_value_t = 64)]
+        queue_capacity: usize,
+
+        /// Most steps a single job may simulate.
+        #[arg(long, default_value_t = 10_000_000_000)]
+        max_job_steps: u128,
+
+        /// Most seconds a job may simulate.
+        #[arg(long, value_name = "SECONDS", default_value = "3600", value_parser = sandbox::parse_seconds)]
+        job_timeout: std::time::Duration,
+
+        /// Keep finished jobs in this file, to still answer for them after a
+        /// restart.
+        #[arg(long, value_name = "jobs.jsonl", conflicts_with = "public")]
+        jobs: Option<PathBuf>,
+
+        /// Harden the server for exposing it on the internet.
+        #[arg(long)]
+        public: bool,
+
+        /// Most requests a minute from a single client address. Defaults to
+        /// 120 with `--public` and no limit otherwise.
+        #[arg(long, value_name = "REQUESTS")]
+        rate_limit: Option<u32>,
+    },
+    /// Run a language server for machine files on stdin and stdout.
+    ///
+    /// Editors get diagnostics for unreadable lines, unreachable states and
+    /// missing transitions, hover information for states and go-to-definition
+    /// for state names.
+    Lsp,
+    /// Read the simulated configuration back from a universal machine's tape.
+    Decode {
+        /// Encoding scheme the tape was created with.
+        #[arg(long, value_enum)]
+        utm: UtmScheme,
+
+        /// Tape of the universal machine, as printed by `--print-tape`.
+        #[arg(required = true)]
+        tape: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum LeaderboardAction {
+    /// Print the records of a leaderboard.
+    Show {
+        /// The leaderboard file.
+        file: PathBuf,
+    },
+    /// Run a machine from the blank tape and enter it if it beats a record.
+    ///
+    /// Missing transitions halt the machine. Exits with 2 if the machine
+    /// doesn't halt within the step budget.
+    Add {
+        /// The leaderboard file, which is created if it doesn't exist.
+        file: PathBuf,
+
+        /// Filename of the Turing-Machine to enter.
+        filename: PathBuf,
+
+        /// Maximum number of steps before giving up.
+        #[arg(long, default_value_t = 100_000_000)]
+        max_steps: u128,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CollectionAction {
+    /// Run the machines and print how they halted and after how many steps.
+    Run {
+        #[command(flatten)]
+        selection: Selection,
+    },
+    /// Run the machines and compare them with their expected results.
+    ///
+    /// Exits with 1 if any machine doesn't do what is expected.
+    Check {
+        #[command(flatten)]
+        selection: Selection,
+    },
+    /// Print the machines with their tags and files.
+    List {
+        #[command(flatten)]
+        selection: Selection,
+    },
+}
+
+// Which machines of a collection a command is about.
+#[derive(Debug, clap::Args)]
+struct Selection {
+    /// The collection file.
+    #[arg(long, value_name = "FILE", default_value = collection::COLLECTION_FILE)]
+    collection: PathBuf,
+
+    /// Only the machines with this tag.
+    #[arg(long)]
+    tag: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+#[group(required = true, multiple = false)]
+struct MachineArgs {
+    /// File with one machine per line in the bbchallenge text format,
+    /// where the line number (from 0) is the machine id.
+    #[arg(long)]
+    machines: Option<PathBuf>,
+
+    /// Seed database of the bbchallenge project to stream machines from.
+    #[arg(long)]
+    db: Option<PathBuf>,
+
+    /// Undecided machines exported by `batch --export-holdouts`, to run
+    /// them again with bigger budgets. They keep their ids, while `--from`
+    /// and `--count` count the machines in the file.
+    #[arg(long)]
+    holdouts: Option<PathBuf>,
+}
+
+impl MachineArgs {
+    fn open(&self) -> Result<bbchallenge::Machines, String> {
+        match (&self.machines, &self.db, &self.holdouts) {
+            (Some(machines), _, _) => read_words(machines).map(bbchallenge::Machines::Text),
+            (None, Some(db), _) => bbchallenge::SeedDb::open(db)
+                .map(bbchallenge::Machines::Db)
+                .map_err(|why| format!("{}: {}", db.display(), why)),
+            (None, None, Some(holdouts)) => holdouts::read(holdouts)
+                .map(bbchallenge::Machines::Holdouts)
+                .map_err(|why| format!("{}: {}", holdouts.display(), why)),
+            (None, None, None) => unreachable!("clap requires one of the arguments"),
+        }
+    }
+
+    /// Identifies the input in a manifest, independent of the directory it
+    /// was given from.
+    fn name(&self) -> String {
+        let path = self
+            .machines
+            .as_ref()
+            .or(self.db.as_ref())
+            .or(self.holdouts.as_ref())
+            .unwrap();
+        path.file_name()
+            .map_or(path.as_os_str(), |name| name)
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+#[derive(Debug, clap::Args)]
+#[group(required = true, multiple = false)]
+struct TransformKind {
+    /// Encode every symbol in binary, giving a machine over `0` and `1`.
+    #[arg(long)]
+    to_binary: bool,
+
+    /// Move the state into the symbols, giving a machine with two states.
+    /// The converted machine has to start on a marked cell, see the
+    /// printed start symbol.
+    #[arg(long)]
+    to_two_states: bool,
+
+    /// Compile a machine over `0` and `1` into a 2-tag system that halts
+    /// with the tape of the machine in its word, for `tag` to run.
+    #[arg(long)]
+    to_tag: bool,
+
+    /// Swap left and right moves, giving the machine that does the same on
+    /// the mirrored tape.
+    #[arg(long)]
+    mirror: bool,
+
+    /// Replace symbols, e.g. `0=1,1=0` to swap `0` and `1`. The blank is
+    /// replaced too, so the machine does the same on the replaced tape.
+    #[arg(long, value_name = "FROM=TO,...")]
+    permute_symbols: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+struct TapeArgs {
+    /// Bound the tape on the left at the starting cell and choose what
+    /// happens when the head moves off that edge.
+    #[arg(long, value_enum)]
+    left_edge: Option<EdgeBehavior>,
+
+    /// Restrict the tape to this many cells, starting at the head, to
+    /// simulate a linear bounded automaton. Without a value the tape is
+    /// restricted to the input.
+    #[arg(long, value_name = "N")]
+    bounded: Option<Option<usize>>,
+
+    /// What happens when the head tries to leave the bounded tape.
+    #[arg(long, value_enum, default_value = "crash", requires = "bounded")]
+    on_bound: EdgeBehavior,
+}
+
+/// Where a run prints what is meant to be read by people: stdout, unless
+/// stdout carries the machine or JSON, then stderr.
+#[derive(Debug, Clone, Copy)]
+struct Human {
+    stderr: bool,
+}
+
+impl Human {
+    fn print(&self, text: impl Display) {
+        if self.stderr {
+            eprint!("{}", text);
+        } else {
+            print!("{}", text);
+        }
+    }
+
+    fn line(&self, text: impl Display) {
+        self.print(format_args!("{}\n", text));
+    }
+}
+
+/// Where a run paused, see `--break-state`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Breakpoint {
+    state: String,
+    steps: u128,
+}
+
+/// What happened in a run.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Halted {
+        steps: Steps,
+    },
+    StepLimit,
+    Paused(Breakpoint),
+    ProvenNonHalting(Proof),
+    TapeLimit {
+        cells: u128,
+    },
+    /// The run left transitions untaken, see `--require-full-coverage`.
+    Uncovered {
+        untaken: usize,
+    },
+    /// The machine stopped without a halting transition, see [`crashed`].
+    Crashed(HaltReason),
+    Error(String),
+}
+
+impl Outcome {
+    /// Name of the outcome in the JSON summary.
+    fn name(&self) -> &'static str {
+        match self {
+            Outcome::Halted { .. } => "halted",
+            Outcome::StepLimit => "step_limit",
+            Outcome::Paused(_) => "paused",
+            Outcome::ProvenNonHalting(_) => "proven_non_halting",
+            Outcome::TapeLimit { .. } => "tape_limit",
+            Outcome::Uncovered { .. } => "uncovered",
+            Outcome::Crashed(_) => "crashed",
+            Outcome::Error(_) => "error",
+        }
+    }
+
+    /// The exit status of `run`, as listed at [`Command::Run`]. A crash has
+    /// its own, so scripts can tell it from a clean halt.
+    fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Outcome::Halted { .. } => 0,
+            Outcome::Error(_) => 1,
+            Outcome::StepLimit => 2,
+            Outcome::ProvenNonHalting(_) => 3,
+            Outcome::Paused(_) => 4,
+            Outcome::TapeLimit { .. } => 5,
+            Outcome::Uncovered { .. } => 6,
+            Outcome::Crashed(_) => 7,
+        })
+    }
+}
+
+/// Format of the summary of a run on stdout.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+enum Output {
+    Text,
+    /// A JSON object with the step count, how the machine halted and the
+    /// busy beaver score. Everything else goes to stderr.
+    Json,
+}
+
+/// How much a run prints, from only the summary to a trace of every step.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Trace,
+    TraceTape,
+}
+
+impl Verbosity {
+    fn of(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Trace,
+            (false, _) => Verbosity::TraceTape,
+        }
+    }
+}
+
+impl TapeArgs {
+    fn apply(&self, tm: &mut TuringMachine, input: &[TapeEntry]) {
+        tm.set_left_edge(self.left_edge);
+        tm.set_bound(
+            self.bounded
+                .map(|cells| (cells.unwrap_or(input.len().max(1)), self.on_bound)),
+        );
+    }
+}
+
+fn main() -> ExitCode {
+    let command = match Config::load().and_then(|config| config.apply(Args::command())) {
+        Ok(command) => command,
+        Err(why) => {
+            println!("Can't read configuration: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let args = match Args::from_arg_matches(&command.get_matches()) {
+        Ok(args) => args,
+        Err(why) => why.exit(),
+    };
+    if args.log_format.is_some() || args.log_level.is_some() {
+        log::init(
+            args.log_format.unwrap_or(log::Format::Text),
+            args.log_level.unwrap_or(log::Level::Info),
+        );
+    }
+    match args.command {
+        None => run(&args.run).exit_code(),
+        Some(Command::Run(args)) => run(&args).exit_code(),
+        Some(Command::Accept {
+            filename,
+            input,
+            max_steps,
+            nondeterministic,
+            record_path,
+            replay,
+            alternating,
+            max_configurations,
+            trials,
+            seed,
+            oracle,
+            tape,
+        }) => match (alternating, trials) {
+            _ if oracle.is_some() => accept_oracle(
+                &filename,
+                &input,
+                max_steps,
+                oracle.as_deref().expect("the oracle is given"),
+                &tape,
+            ),
+            (true, _) => {
+                accept_alternating(&filename, &input, max_steps, max_configurations, &tape)
+            }
+            (false, Some(trials)) => {
+                accept_probabilistic(&filename, &input, max_steps, trials, seed, &tape)
+            }
+            (false, None) => accept(
+                &filename,
+                &input,
+                max_steps,
+                nondeterministic,
+                record_path.as_deref(),
+                replay.as_deref(),
+                &tape,
+            ),
+        },
+        Some(Command::Test {
+            filename,
+            words,
+            expect,
+            max_steps,
+            tape,
+        }) => test(&filename, words.as_deref(), &expect, max_steps, &tape),
+        Some(Command::Check { filename, spec }) => check(&filename, spec.as_deref()),
+        Some(Command::Grade {
+            assignment,
+            submissions,
+            format,
+            output,
+            max_steps,
+            max_tape_cells,
+            timeout,
+        }) => {
+            let limits = sandbox::Limits {
+                max_steps: max_steps.unwrap_or(u128::MAX),
+                max_tape_cells,
+                max_time: timeout,
+            };
+            grade(
+                &assignment,
+                &submissions,
+                format,
+                output.as_deref(),
+                &limits,
+            )
+        }
+        Some(Command::Encode {
+            filename,
+            utm,
+            input,
+        }) => encode(&filename, utm, &input),
+        Some(Command::Decode { utm, tape }) => decode(utm, &tape),
+        Some(Command::Ca {
+            rule,
+            steps,
+            width,
+            input,
+            png,
+            svg,
+            scale,
+            quiet,
+        }) => cellular_automaton(
+            rule,
+            steps,
+            width,
+            &input,
+            png.as_deref(),
+            svg.as_deref(),
+            scale,
+            quiet,
+        ),
+        Some(Command::Tag {
+            filename,
+            word,
+            max_steps,
+            cyclic,
+        }) => run_tag(&filename, word.as_deref(), max_steps, cyclic),
+        Some(Command::Counter {
+            filename,
+            registers,
+            max_steps,
+            to_turing,
+            via_turing,
+            output,
+        }) => counter(
+            &filename,
+            &registers,
+            max_steps,
+            to_turing,
+            via_turing,
+            output.as_deref(),
+        ),
+        Some(Command::Decide {
+            filename,
+            max_steps,
+            depth,
+            cert,
+            parallel,
+            pipeline,
+            window,
+            history,
+        }) => decide(
+            &filename,
+            max_steps,
+            depth,
+            cert.as_deref(),
+            parallel,
+            pipeline.as_ref(),
+            window.map(|window| (window, history)),
+        ),
+        Some(Command::CheckDvf {
+            dvf,
+            machines,
+            max_steps,
+        }) => check_dvf(&dvf, &machines, max_steps),
+        Some(Command::Batch {
+            machines,
+            from,
+            count,
+            max_steps,
+            depth,
+            shard,
+            manifest,
+            leaderboard,
+            lockstep,
+            export_holdouts,
+            pipeline,
+            log,
+            db,
+        }) => batch(
+            &machines,
+            from,
+            count,
+            max_steps,
+            depth,
+            shard,
+            manifest.as_deref(),
+            leaderboard.as_deref(),
+            lockstep,
+            export_holdouts.as_deref(),
+            pipeline.as_ref(),
+            log.as_deref(),
+            db.as_deref(),
+        ),
+        Some(Command::Bb {
+            states,
+            symbols,
+            max_steps,
+            depth,
+            range,
+            log,
+            db,
+        }) => busy_beaver(
+            states,
+            symbols,
+            max_steps,
+            depth,
+            range.unwrap_or(0..u64::MAX),
+            log.as_deref(),
+            db.as_deref(),
+        ),
+        Some(Command::IndexOf {
+            machine,
+            symbols,
+            max_steps,
+        }) => index_of(&machine, symbols, max_steps),
+        Some(Command::MachineAt {
+            index,
+            states,
+            symbols,
+            max_steps,
+        }) => machine_at(index, states, symbols, max_steps),
+        Some(Command::Query { filter, db }) => query(&filter, &db),
+        Some(Command::Leaderboard { action }) => match action {
+            LeaderboardAction::Show { file } => leaderboard_show(&file),
+            LeaderboardAction::Add {
+                file,
+                filename,
+                max_steps,
+            } => leaderboard_add(&file, &filename, max_steps),
+        },
+        Some(Command::Collection { action }) => match action {
+            CollectionAction::Run { selection } => collection_run(&selection, false),
+            CollectionAction::Check { selection } => collection_run(&selection, true),
+            CollectionAction::List { selection } => collection_list(&selection),
+        },
+        Some(Command::VerifyCert {
+            filename,
+            certificate,
+        }) => verify_cert(&filename, &certificate),
+        Some(Command::Info { filename }) => {
+            print!("{}", info::info(&TuringMachine::new(&filename)));
+            ExitCode::SUCCESS
+        }
+        Some(Command::Minimize { filename, output }) => minimize(&filename, output.as_deref()),
+        Some(Command::Fingerprint { files, steps }) => {
+            for filename in &files {
+                let tm = TuringMachine::new(filename);
+                println!(
+                    "{}  {}",
+                    fingerprint::fingerprint(&tm, steps),
+                    filename.display()
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Some(Command::Normalize { filename, output }) => write_machine(
+            &normalize::normalize(&TuringMachine::new(&filename)),
+            output.as_deref(),
+        ),
+        Some(Command::Fmt { files, check }) => format(&files, check),
+        Some(Command::Race {
+            a,
+            b,
+            input,
+            max_steps,
+            quiet,
+            no_color,
+        }) => race(&a, &b, &input, max_steps, quiet, no_color),
+        Some(Command::Diff { a, b }) => diff(&a, &b),
+        Some(Command::Isomorphic { a, b }) => isomorphic(&a, &b),
+        Some(Command::Abstract {
+            filename,
+            window,
+            max_configurations,
+        }) => analyze(&filename, window, max_configurations),
+        Some(Command::Report {
+            filename,
+            steps,
+            input,
+            output,
+            emit_tikz,
+            emit_mermaid,
+            trace,
+        }) => {
+            let format = match (emit_tikz, trace, emit_mermaid) {
+                (true, false, _) => ReportFormat::Tikz,
+                (true, true, _) => ReportFormat::TikzTrace,
+                (false, _, true) => ReportFormat::Mermaid,
+                (false, _, false) => ReportFormat::Html,
+            };
+            report(&filename, steps, &input, format, output.as_deref())
+        }
+        Some(Command::Spacetime {
+            filename,
+            steps,
+            input,
+            sample,
+            svg,
+            png,
+            scale,
+        }) => spacetime(
+            &filename,
+            steps,
+            &input,
+            sample,
+            svg.as_deref(),
+            png.as_deref(),
+            scale,
+        ),
+        Some(Command::Configurations {
+            filename,
+            input,
+            cells,
+            on_bound,
+            max_configurations,
+            dot,
+        }) => explore(
+            &filename,
+            &input,
+            cells,
+            on_bound,
+            max_configurations,
+            dot.as_deref(),
+        ),
+        Some(Command::Transform {
+            filename,
+            kind,
+            output,
+        }) => transform(&filename, &kind, output.as_deref()),
+        Some(Command::Equiv {
+            a,
+            b,
+            inputs,
+            max_steps,
+        }) => equiv(&a, &b, inputs.as_deref(), max_steps),
+        Some(Command::Serve {
+            port,
+            bind,
+            max_budget,
+            max_tape_cells,
+            timeout,
+            workers,
+            queue_capacity,
+            max_job_steps,
+            job_timeout,
+            jobs,
+            public,
+            rate_limit,
+        }) => {
+            let mut limits = sandbox::Limits {
+                max_steps: max_budget,
+                max_tape_cells,
+                max_time: timeout,
+            };
+            let mut job_limits = sandbox::Limits {
+                max_steps: max_job_steps,
+                max_tape_cells,
+                max_time: job_timeout,
+            };
+            let mut workers = workers.unwrap_or_else(|| {
+                std::thread::available_parallelism().map_or(1, |workers| workers.get())
+            });
+            let mut queue_capacity = queue_capacity;
+            let mut rate_limit = rate_limit;
+            if public {
+                limits = limits.within(server::PUBLIC_LIMITS);
+                job_limits = job_limits.within(server::PUBLIC_JOB_LIMITS);
+                workers = workers.min(4);
+                queue_capacity = queue_capacity.min(16);
+                rate_limit = rate_limit.or(Some(server::PUBLIC_RATE_LIMIT));
+            }
+            let mut server = server::Server::new(limits);
+            if public {
+                server = server
+                    .with_max_machines(server::PUBLIC_MAX_MACHINES)
+                    .with_max_machine_memory(server::PUBLIC_MAX_MACHINE_MEMORY)
+                    .with_max_connections(server::PUBLIC_MAX_CONNECTIONS);
+            }
+            if let Some(per_minute) = rate_limit {
+                server = server.with_rate_limit(per_minute);
+            }
+            serve(
+                &bind,
+                port,
+                server,
+                job_limits,
+                workers,
+                queue_capacity,
+                jobs.as_deref(),
+            )
+        }
+        Some(Command::Lsp) => lsp(),
+        Some(Command::Debug {
+            filename,
+            input,
+            snapshot_every,
+            params,
+        }) => debug(&filename, &input, snapshot_every, &params),
+        Some(Command::Replay {
+            trace,
+            input,
+            machine,
+            snapshot_every,
+        }) => replay(&trace, &input, machine.as_deref(), snapshot_every),
+        Some(Command::Completions { shell }) => {
+            print!("{}", completions::generate(shell, &mut Args::command()));
+            ExitCode::SUCCESS
+        }
+        Some(Command::Selftest {
+            filename,
+            steps,
+            input,
+            accel,
+            fuse,
+        }) => selftest(&filename, steps, &input, &accel, &fuse),
+        Some(Command::Bench {
+            filename,
+            runs,
+            max_steps,
+        }) => bench(&filename, runs, max_steps),
+    }
 }
 
-fn main() {
-    let args = Args::parse();
-    let mut tm = TuringMachine::new(&args.filename);
+fn run(args: &RunArgs) -> Outcome {
+    let filename = args.filename.as_deref().expect("clap requires a filename");
+    let (input, encode, tape) = (&args.input, args.encode, &args.tape);
+    let verbosity = Verbosity::of(args.quiet, args.verbose);
+    let colors = Colors::new(args.no_color);
+    let human = Human {
+        stderr: filename == Path::new("-") || args.output == Output::Json,
+    };
+
+    let tape_file = match args.tape_file.as_deref().map(dump::read).transpose() {
+        Ok(tape_file) => tape_file,
+        Err(why) => {
+            println!("Can't read tape file: {}", why);
+            return Outcome::Error(why);
+        }
+    };
+    if let Some(Err(why)) = args.dump_tape.as_deref().map(dump::Format::of) {
+        println!("Can't dump tape: {}", why);
+        return Outcome::Error(why);
+    }
+
+    let parse = log::span(
+        "parse",
+        &[("machine", filename.display().to_string().into())],
+    );
+    let mut tm = TuringMachine::load(filename, &args.params);
+    drop(parse);
+    if let Some(blank) = args.blank {
+        tm.set_blank(blank);
+    }
+    let input = match encode {
+        Some(encoding) => input
+            .iter()
+            .map(|number| {
+                number
+                    .parse()
+                    .map_err(|why| format!("Can't read input number '{}': {}", number, why))
+            })
+            .collect::<Result<Vec<u128>, String>>()
+            .map(|numbers| encoding.encode(&numbers, tm.blank())),
+        None => turing::parse_word(&input.join(" "))
+            .map_err(|why| format!("Can't read input '{}': {}", input.join(" "), why)),
+    };
+    let input = match input {
+        Ok(input) => input,
+        Err(why) => {
+            println!("{}", why);
+            return Outcome::Error(why);
+        }
+    };
+    if !input.is_empty() {
+        tm.set_input(&input);
+    }
+    if let Some(tape_file) = &tape_file {
+        tm.set_tape(&tape_file.cells, tape_file.head, tape_file.origin);
+    }
+    let input = tape_file.map_or(input, |tape_file| tape_file.cells);
+    tape.apply(&mut tm, &input);
+    if let Some(path) = &args.pgo {
+        match Profile::read(path) {
+            Ok(profile) => profile.apply(&mut tm),
+            Err(why) => {
+                println!("Can't read profile {}: {}", path.display(), why);
+                return Outcome::Error(why);
+            }
+        }
+    }
+
+    let replay = args.inline.map(|_| tm.clone());
 
-    tm.print_states();
-    tm.print_instructions();
+    if verbosity > Verbosity::Quiet {
+        human.print(tm.format_states());
+        human.print(format_args!(
+            "Instructions: \n{}\n",
+            tm.format_instructions(colors)
+        ));
+    }
 
+    if let Some(accel) = args.accel {
+        return run_accelerated(
+            &tm,
+            accel,
+            encode,
+            args.max_steps,
+            args.max_tape_cells,
+            args.progress,
+            args.tape_stats,
+            verbosity,
+            human,
+            args.output,
+        );
+    }
+
+    let break_state = match &args.break_state {
+        Some(name) => match tm.states().iter().position(|state| state == name) {
+            Some(state) => Some(state),
+            None => {
+                let why = format!("the machine has no state '{}'", name);
+                println!("Can't set breakpoint: {}", why);
+                return Outcome::Error(why);
+            }
+        },
+        None => None,
+    };
+
+    let mut fusion = match args.fuse {
+        Some(steps) => match Fusion::new(&tm, steps) {
+            Ok(fusion) => Some(fusion),
+            Err(why) => {
+                println!("Can't fuse steps: {}", why);
+                return Outcome::Error(why);
+            }
+        },
+        None => None,
+    };
+    let mut hot = args.hot_loops.map(HotLoops::new);
+    let coverage = args.coverage || args.require_full_coverage;
+    let mut counts =
+        (args.profile.is_some() || coverage).then(|| vec![0u128; tm.instructions().len()]);
+    let mut digest = args.digest.then(Digest::new);
+    let mut recorder = match &args.record_golden {
+        Some(path) => match File::create(path).and_then(|file| match args.compress_golden {
+            true => golden::Recorder::compressed(BufWriter::new(file), &tm),
+            false => golden::Recorder::new(BufWriter::new(file), &tm),
+        }) {
+            Ok(recorder) => Some(recorder),
+            Err(why) => {
+                println!("Can't write {}: {}", path.display(), why);
+                return Outcome::Error(why.to_string());
+            }
+        },
+        None => None,
+    };
+    let mut verifier = match &args.compare_golden {
+        Some(path) => match File::open(path)
+            .map_err(|why| why.to_string())
+            .and_then(|file| golden::Verifier::new(BufReader::new(file), &tm))
+        {
+            Ok(verifier) => Some(verifier),
+            Err(why) => {
+                println!("Can't read {}: {}", path.display(), why);
+                return Outcome::Error(why);
+            }
+        },
+        None => None,
+    };
+    let mut trajectory = match &args.trajectory {
+        Some(path) => match File::create(path)
+            .and_then(|file| Trajectory::new(BufWriter::new(file), args.trajectory_every, &tm))
+        {
+            Ok(trajectory) => Some(trajectory),
+            Err(why) => {
+                println!("Can't write {}: {}", path.display(), why);
+                return Outcome::Error(why.to_string());
+            }
+        },
+        None => None,
+    };
+    let mut sampler = args
+        .progress
+        .then(|| Sampler::new(tm.num_steps as f64, tm.tape().len() as f64));
+    let simulate = log::span("simulate", &[]);
     let start = Instant::now();
 
-    while tm.step() {}
+    let mut paused = false;
+    if hot.is_none()
+        && counts.is_none()
+        && digest.is_none()
+        && recorder.is_none()
+        && verifier.is_none()
+        && trajectory.is_none()
+        && args.max_steps.is_none()
+        && args.max_tape_cells.is_none()
+        && break_state.is_none()
+        && sampler.is_none()
+        && verbosity < Verbosity::Trace
+    {
+        match &mut fusion {
+            Some(fusion) => while tm.step_fused(fusion) {},
+            None => while tm.step() {},
+        }
+    } else {
+        let mut advance = |tm: &mut TuringMachine| match &mut fusion {
+            // Single steps close to the limit, so that it is met exactly.
+            Some(fusion)
+                if args
+                    .max_steps
+                    .is_none_or(|max_steps| max_steps - tm.num_steps >= fusion.steps() as u128) =>
+            {
+                tm.step_fused(fusion)
+            }
+            _ => tm.step(),
+        };
+        loop {
+            match verbosity {
+                Verbosity::Trace => human.line(tm.format_state(colors)),
+                Verbosity::TraceTape => human.print(tm.format_tape(true, colors)),
+                _ => {}
+            }
+            if args
+                .max_steps
+                .is_some_and(|max_steps| tm.num_steps >= max_steps)
+                || !advance(&mut tm)
+            {
+                break;
+            }
+            if let Some(hot) = &mut hot {
+                if let Some(instruction) = tm.last_instruction() {
+                    hot.record(instruction);
+                }
+            }
+            if let Some(counts) = &mut counts {
+                if let Some(instruction) = tm.last_instruction() {
+                    counts[instruction] += 1;
+                }
+            }
+            if let Some(digest) = &mut digest {
+                digest.record(&tm);
+            }
+            if let Some(sampler) = &mut sampler {
+                if sampler.sample(tm.num_steps as f64, tm.tape().len() as f64) {
+                    print_progress(&tm.num_steps, &sampler.meter);
+                }
+            }
+            if let Some(recorder) = &mut recorder {
+                if let Err(why) = recorder.record(&tm) {
+                    println!("Can't write golden trace: {}", why);
+                    return Outcome::Error(why.to_string());
+                }
+            }
+            if let Some(trajectory) = &mut trajectory {
+                if let Err(why) = trajectory.record(&tm) {
+                    println!("Can't write trajectory: {}", why);
+                    return Outcome::Error(why.to_string());
+                }
+            }
+            if let Some(verifier) = &mut verifier {
+                if let Err(why) = verifier.check(&tm) {
+                    human.line(format_args!("Golden trace differs: {}", why));
+                    return Outcome::Error(why);
+                }
+            }
+            if break_state.is_some() && tm.state() == break_state {
+                paused = true;
+                break;
+            }
+            if args
+                .max_tape_cells
+                .is_some_and(|max_cells| tm.tape().len() as u128 > max_cells)
+            {
+                break;
+            }
+        }
+    }
 
     let elapsed = start.elapsed();
+    drop(simulate);
+    end_progress(sampler.as_ref());
 
     let freq = (tm.num_steps as f32) / elapsed.as_secs_f32();
+    let cells = tm.tape().len() as u128;
+
+    if verbosity > Verbosity::Quiet {
+        human.line(format_args!("\nSimulation took {:.3?}", elapsed));
+        human.line(format_args!("{:.3e} Iterations / second", freq));
+        human.line(format_args!(
+            "Peak tape: {} cells, about {} of memory",
+            cells,
+            format_bytes(tm.memory())
+        ));
+        if let Some(fusion) = &fusion {
+            human.line(format_args!(
+                "Fused {} transitions of up to {} steps",
+                fusion.len(),
+                fusion.steps()
+            ));
+        }
+    }
+
+    if tm.edge_hits > 0 {
+        human.line(format_args!(
+            "Head tried to leave the tape {} times",
+            tm.edge_hits
+        ));
+    }
+    if let Some(reason) = tm.halt_reason.filter(|reason| reason != &HaltReason::Halt) {
+        human.line(format_args!(
+            "Machine {} after {} steps",
+            reason, tm.num_steps
+        ));
+    }
+
+    let over_limit = args
+        .max_tape_cells
+        .is_some_and(|max_cells| cells > max_cells);
+    let mut outcome = match (paused, tm.state()) {
+        (_, Some(_)) if over_limit => Outcome::TapeLimit { cells },
+        (true, Some(state)) => Outcome::Paused(Breakpoint {
+            state: tm.states()[state].clone(),
+            steps: tm.num_steps,
+        }),
+        (false, Some(_)) => Outcome::StepLimit,
+        (_, None) if crashed(&tm) => Outcome::Crashed(tm.halt_reason.unwrap_or(HaltReason::Crash)),
+        (_, None) => Outcome::Halted {
+            steps: Steps::new(tm.num_steps),
+        },
+    };
+    match &outcome {
+        Outcome::Paused(breakpoint) => human.line(format_args!(
+            "Paused in state {} after {} steps",
+            breakpoint.state, breakpoint.steps
+        )),
+        Outcome::StepLimit => human.line(format_args!(
+            "Machine still runs after {} steps",
+            tm.num_steps
+        )),
+        Outcome::TapeLimit { cells } => human.line(format_args!(
+            "Tape grew to {} cells after {} steps",
+            cells, tm.num_steps
+        )),
+        _ => {}
+    }
+
+    let (ones, zeros, steps) = tm.eval_busy_bever();
+    human.line(format_args!(
+        "Busy Bever: {} ones, {} zeros, after {} steps",
+        ones, zeros, steps
+    ));
+    let tape_stats = args.tape_stats.then(|| TapeStats::new(tm.tape()));
+    if let Some(stats) = &tape_stats {
+        print_tape_stats(stats, cells, human);
+    }
+
+    if let Some(digest) = digest {
+        human.line(format_args!(
+            "Digest: {} after {} steps",
+            digest, digest.steps
+        ));
+    }
+
+    if let Some(recorder) = recorder {
+        match recorder.finish(&tm) {
+            Ok(steps) => human.line(format_args!("Recorded golden trace of {} steps", steps)),
+            Err(why) => {
+                println!("Can't write golden trace: {}", why);
+                return Outcome::Error(why.to_string());
+            }
+        }
+    }
+
+    if let Some(trajectory) = trajectory {
+        match trajectory.finish(&tm) {
+            Ok(rows) => human.line(format_args!("Wrote {} rows of the head trajectory", rows)),
+            Err(why) => {
+                println!("Can't write trajectory: {}", why);
+                return Outcome::Error(why.to_string());
+            }
+        }
+    }
+
+    if let Some(verifier) = verifier {
+        match verifier.finish(&tm) {
+            Ok(steps) => human.line(format_args!("Golden trace matches all {} steps", steps)),
+            Err(why) => {
+                human.line(format_args!("Golden trace differs: {}", why));
+                return Outcome::Error(why);
+            }
+        }
+    }
+
+    if let Some(hot) = hot {
+        print_hot_loops(&tm, &hot, human);
+    }
+
+    let coverage = counts
+        .as_deref()
+        .filter(|_| coverage)
+        .map(coverage::Coverage::new);
+    if let Some(coverage) = &coverage {
+        human.line("");
+        for line in coverage.report(&tm) {
+            human.line(line);
+        }
+        if args.require_full_coverage && !coverage.is_full() {
+            outcome = Outcome::Uncovered {
+                untaken: coverage.untaken.len(),
+            };
+        }
+    }
+    log::event(
+        log::Level::Info,
+        "run finished",
+        &[
+            ("outcome", outcome.name().into()),
+            ("steps", tm.num_steps.into()),
+            ("tape_cells", cells.into()),
+        ],
+    );
+
+    if let (Some(path), Some(counts)) = (&args.profile, &counts) {
+        if let Err(why) = Profile::new(&tm, counts).write(path) {
+            println!("Can't write profile {}: {}", path.display(), why);
+            return Outcome::Error(why);
+        }
+    }
+
+    if args.print_tape {
+        human.print(tm.format_tape(false, colors));
+    }
+
+    if let (Some(protocol), Some(replay)) = (args.inline, &replay) {
+        match protocol.resolve() {
+            Some(protocol) => {
+                let sample = tm.num_steps.div_ceil(INLINE_ROWS).max(1);
+                let diagram = spacetime::Diagram::record(replay, tm.num_steps, sample);
+                let (width, height, pixels) = diagram.to_pixels(inline_scale(cells));
+                human.print(protocol.show(width, height, &pixels));
+            }
+            None => human.line(
+                "Can't show the diagram: the terminal isn't known to support kitty \
+                 graphics or sixels, choose one with --inline PROTOCOL",
+            ),
+        }
+    }
+
+    if let Some(path) = &args.dump_tape {
+        if let Err(why) = dump::write(path, &tm) {
+            println!("Can't dump tape: {}", why);
+            return Outcome::Error(why);
+        }
+    }
+
+    let mut result = None;
+    if let Some(encoding) = encode {
+        match encoding.decode(tm.tape(), tm.blank()) {
+            Ok(numbers) => {
+                let strings: Vec<String> = numbers.iter().map(u128::to_string).collect();
+                human.line(format_args!("Result: {}", strings.join(" ")));
+                result = Some(numbers);
+            }
+            Err(why) => human.line(format_args!("Can't decode result: {}", why)),
+        }
+    }
+
+    if args.output == Output::Json {
+        let mut summary = Json::object([
+            ("outcome", outcome.name().into()),
+            ("steps", tm.num_steps.into()),
+            ("halted", tm.is_halted().into()),
+            (
+                "reason",
+                tm.halt_reason
+                    .map_or(Json::Null, |reason| reason.name().into()),
+            ),
+            ("ones", ones.into()),
+            ("zeros", zeros.into()),
+            ("peak_tape_cells", cells.into()),
+            ("memory_bytes", tm.memory().into()),
+        ]);
+        if let (Json::Object(fields), Some(coverage)) = (&mut summary, &coverage) {
+            fields.push(("coverage".to_string(), coverage.to_json(&tm)));
+        }
+        if let (Json::Object(fields), Some(digest)) = (&mut summary, digest) {
+            fields.push(("digest".to_string(), digest.to_string().into()));
+        }
+        if let (Json::Object(fields), Some(stats)) = (&mut summary, tape_stats) {
+            fields.extend(tape_stats_json(&stats));
+        }
+        if let (Json::Object(fields), Some(result)) = (&mut summary, result) {
+            let result = result.into_iter().map(Json::from).collect();
+            fields.push(("result".to_string(), Json::Array(result)));
+        }
+        println!("{}", summary);
+    }
+
+    outcome
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_accelerated(
+    tm: &TuringMachine,
+    accel: Accel,
+    encode: Option<Encoding>,
+    max_steps: Option<u128>,
+    max_tape_cells: Option<u128>,
+    progress: bool,
+    tape_stats: bool,
+    verbosity: Verbosity,
+    human: Human,
+    output: Output,
+) -> Outcome {
+    let start = Instant::now();
+
+    let mut tm = MacroMachine::new(tm, accel);
+    let mut sampler = progress.then(|| Sampler::new(0.0, tm.tape_len() as f64));
+    let simulate = log::span("simulate", &[("block_size", tm.block_size().into())]);
+    // Macro steps can take many steps at once, so the limit can be exceeded.
+    let below_limit = |tm: &MacroMachine| match max_steps {
+        Some(max_steps) => tm
+            .num_steps
+            .to_u128()
+            .is_some_and(|steps| steps < max_steps),
+        None => true,
+    };
+    // Blank blocks at the ends are dropped, so the tape can shrink again.
+    let mut cells = tm.tape_len();
+    let below_tape_limit = |cells: u128| max_tape_cells.is_none_or(|max_cells| cells <= max_cells);
+    while below_limit(&tm) && below_tape_limit(cells) {
+        match tm.step() {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(why) => {
+                println!("Can't simulate machine: {}", why);
+                return Outcome::Error(why);
+            }
+        }
+        cells = cells.max(tm.tape_len());
+        if let Some(sampler) = &mut sampler {
+            if sampler.sample(tm.num_steps.to_f64(), cells as f64) {
+                print_progress(&tm.num_steps, &sampler.meter);
+            }
+        }
+    }
+
+    let elapsed = start.elapsed();
+    drop(simulate);
+    end_progress(sampler.as_ref());
+
+    let freq = (tm.num_steps.to_f64() as f32) / elapsed.as_secs_f32();
+
+    if verbosity > Verbosity::Quiet {
+        human.line(format_args!("\nSimulation took {:.3?}", elapsed));
+        human.line(format_args!("{:.3e} Iterations / second", freq));
+        human.line(format_args!(
+            "{} base steps in {} macro steps on blocks of {} cells",
+            tm.num_steps,
+            tm.macro_steps,
+            tm.block_size()
+        ));
+        human.line(format_args!(
+            "Peak tape: {} cells, about {} of memory",
+            cells,
+            format_bytes(tm.memory())
+        ));
+    }
+
+    // Step counts beyond 128 bits are given as strings.
+    let steps = match tm.num_steps.to_u128() {
+        Some(steps) => steps.into(),
+        None => tm.num_steps.to_string().into(),
+    };
+    if let Some(proof) = tm.proof {
+        human.line(format_args!(
+            "Machine never halts, found after {} steps: {}",
+            tm.num_steps, proof
+        ));
+        let outcome = Outcome::ProvenNonHalting(proof);
+        if output == Output::Json {
+            println!(
+                "{}",
+                Json::object([
+                    ("outcome", outcome.name().into()),
+                    ("steps", steps),
+                    ("halted", false.into()),
+                    ("runs_forever", true.into()),
+                    ("proof", proof.to_string().into()),
+                ])
+            );
+        }
+        return outcome;
+    }
+    if tm.state().is_some() && !below_tape_limit(cells) {
+        human.line(format_args!(
+            "Tape grew to {} cells after {} steps",
+            cells, tm.num_steps
+        ));
+        let outcome = Outcome::TapeLimit { cells };
+        if output == Output::Json {
+            println!(
+                "{}",
+                Json::object([
+                    ("outcome", outcome.name().into()),
+                    ("steps", steps),
+                    ("halted", false.into()),
+                    ("peak_tape_cells", cells.into()),
+                ])
+            );
+        }
+        return outcome;
+    }
+    if tm.state().is_some() {
+        human.line(format_args!(
+            "Machine still runs after {} steps",
+            tm.num_steps
+        ));
+        if output == Output::Json {
+            println!(
+                "{}",
+                Json::object([
+                    ("outcome", Outcome::StepLimit.name().into()),
+                    ("steps", steps),
+                    ("halted", false.into()),
+                ])
+            );
+        }
+        return Outcome::StepLimit;
+    }
+    if let Some(reason) = tm.halt_reason.filter(|reason| reason != &HaltReason::Halt) {
+        human.line(format_args!(
+            "Machine {} after {} steps",
+            reason, tm.num_steps
+        ));
+    }
+
+    let (ones, zeros, _) = tm.eval_busy_bever();
+    human.line(format_args!(
+        "Busy Bever: {} ones, {} zeros, after {} steps",
+        ones, zeros, tm.num_steps
+    ));
+    let tape_stats = match tape_stats.then(|| tm.cells()) {
+        Some(Some((cells, _))) => Some(TapeStats::new(&cells)),
+        Some(None) => {
+            human.line("Can't compute tape statistics: the tape is too long");
+            None
+        }
+        None => None,
+    };
+    if let Some(stats) = &tape_stats {
+        print_tape_stats(stats, cells, human);
+    }
+
+    let mut result = None;
+    if let Some(encoding) = encode {
+        match tm.cells() {
+            Some((cells, _)) => match encoding.decode(&cells.into(), tm.blank()) {
+                Ok(numbers) => {
+                    let strings: Vec<String> = numbers.iter().map(u128::to_string).collect();
+                    human.line(format_args!("Result: {}", strings.join(" ")));
+                    result = Some(numbers);
+                }
+                Err(why) => human.line(format_args!("Can't decode result: {}", why)),
+            },
+            None => human.line("Can't decode result: the tape is too long"),
+        }
+    }
+
+    let outcome = match tm.halt_reason {
+        Some(HaltReason::Crash) => Outcome::Crashed(HaltReason::Crash),
+        _ => Outcome::Halted {
+            steps: tm.num_steps.clone(),
+        },
+    };
+    if output == Output::Json {
+        let mut summary = Json::object([
+            ("outcome", outcome.name().into()),
+            ("steps", steps),
+            ("halted", true.into()),
+            (
+                "reason",
+                tm.halt_reason
+                    .map_or(Json::Null, |reason| reason.name().into()),
+            ),
+            ("ones", ones.into()),
+            ("zeros", zeros.into()),
+            ("peak_tape_cells", cells.into()),
+            ("memory_bytes", tm.memory().into()),
+        ]);
+        if let (Json::Object(fields), Some(stats)) = (&mut summary, tape_stats) {
+            fields.extend(tape_stats_json(&stats));
+        }
+        if let (Json::Object(fields), Some(result)) = (&mut summary, result) {
+            let result = result.into_iter().map(Json::from).collect();
+            fields.push(("result".to_string(), Json::Array(result)));
+        }
+        println!("{}", summary);
+    }
+
+    outcome
+}
+
+/// Whether `tm` stopped without taking a halting transition: it crashed on
+/// a missing one, or an edge of the tape rejected it. A machine halting in
+/// `Reject` by itself gave its verdict instead.
+fn crashed(tm: &TuringMachine) -> bool {
+    match tm.halt_reason {
+        Some(HaltReason::Crash) => true,
+        Some(HaltReason::Reject) => tm
+            .last_instruction()
+            .is_none_or(|index| tm.instructions()[index].new_state.is_some()),
+        _ => false,
+    }
+}
+
+fn print_tape_stats(stats: &TapeStats, cells: u128, human: Human) {
+    human.line(format_args!(
+        "Tape entropy: {:.3} bits per cell, {} compressed from {} cells",
+        stats.entropy,
+        format_bytes(stats.compressed),
+        cells
+    ));
+}
+
+fn tape_stats_json(stats: &TapeStats) -> [(String, Json); 2] {
+    [
+        ("tape_entropy".to_string(), Json::Float(stats.entropy)),
+        ("tape_compressed_bytes".to_string(), stats.compressed.into()),
+    ]
+}
+
+/// Formats a number of bytes with a binary unit, like `1.5 KiB`.
+fn format_bytes(bytes: usize) -> String {
+    let mut value = bytes as f64;
+    for unit in ["B", "KiB", "MiB"] {
+        if value < 1024.0 {
+            return match unit {
+                "B" => format!("{} B", bytes),
+                _ => format!("{:.1} {}", value, unit),
+            };
+        }
+        value /= 1024.0;
+    }
+    format!("{:.1} GiB", value)
+}
+
+/// Overwrites the progress line on stderr with the throughput after `steps`
+/// steps.
+fn print_progress(steps: &impl Display, meter: &Meter) {
+    eprint!("\r{} steps, {}\x1b[K", steps, meter.line());
+}
+
+/// Ends the progress line, if there is one.
+fn end_progress(sampler: Option<&Sampler>) {
+    if sampler.is_some_and(|sampler| !sampler.meter.time.is_zero()) {
+        eprintln!();
+    }
+}
+
+fn print_hot_loops(tm: &TuringMachine, hot: &HotLoops, human: Human) {
+    let loops = hot.report();
+    human.line("\nHot loops:");
+    if loops.is_empty() {
+        human.line(" No transitions were repeated");
+        return;
+    }
+    human.line(" Period |          Steps |  Share | Shift | Transitions");
+    human.line("--------+----------------+--------+-------+-------------");
+    for hot_loop in loops.iter().take(10) {
+        human.line(format_args!(
+            " {:6} | {:14} | {:5.1}% | {:+5} | {}",
+            hot_loop.period(),
+            hot_loop.steps,
+            100.0 * hot_loop.steps as f64 / hot.steps() as f64,
+            hot_loop.shift(tm),
+            hot_loop.transitions(tm)
+        ));
+    }
+}
+
+fn accept(
+    filename: &Path,
+    input: &str,
+    max_steps: u128,
+    nondeterministic: bool,
+    record_path: Option<&Path>,
+    replay: Option<&Path>,
+    tape: &TapeArgs,
+) -> ExitCode {
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut tm = TuringMachine::new(filename);
+    tm.set_reject_undefined(true);
+    tm.set_input(&input);
+    tape.apply(&mut tm, &input);
+
+    if nondeterministic {
+        return match nondeterministic::search(&tm, max_steps) {
+            nondeterministic::Search::Accepted { steps, choices } => {
+                println!(
+                    "Accepted after {} steps, with {} choices",
+                    steps,
+                    choices.len()
+                );
+                if let Some(path) = record_path {
+                    if let Err(why) = nondeterministic::write_path(path, steps, &choices) {
+                        println!("Can't write path: {}", why);
+                        return ExitCode::FAILURE;
+                    }
+                }
+                ExitCode::from(0)
+            }
+            nondeterministic::Search::Rejected => {
+                println!("Rejected on every branch");
+                ExitCode::from(1)
+            }
+            nondeterministic::Search::StepLimit => {
+                println!("No branch accepted within {} steps", max_steps);
+                ExitCode::from(2)
+            }
+        };
+    }
+
+    if let Some(path) = replay {
+        let colors = Colors::new(false);
+        let replayed = nondeterministic::read_path(path).and_then(|choices| {
+            println!("{}", tm.format_state(colors));
+            nondeterministic::replay(&mut tm, &choices, max_steps, |tm| {
+                print!("{}", tm.format_tape(true, colors))
+            })
+        });
+        if let Err(why) = replayed {
+            println!("Can't replay {}: {}", path.display(), why);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let verdict = tm.run_word(max_steps);
+    println!("{} after {} steps", verdict, tm.num_steps);
+
+    match verdict {
+        Verdict::Accept => ExitCode::from(0),
+        Verdict::Reject => ExitCode::from(1),
+        Verdict::StepLimit => ExitCode::from(2),
+    }
+}
+
+fn accept_alternating(
+    filename: &Path,
+    input: &str,
+    max_depth: u128,
+    max_configurations: usize,
+    tape: &TapeArgs,
+) -> ExitCode {
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut tm = TuringMachine::new(filename);
+    tm.set_input(&input);
+    tape.apply(&mut tm, &input);
+
+    let evaluation = alternating::evaluate(&tm, max_depth, max_configurations);
+    let verdict = match evaluation.value {
+        alternating::Value::Accept => "accept",
+        alternating::Value::Reject => "reject",
+        alternating::Value::Unknown => "unknown",
+    };
+    println!(
+        "{} after {} configurations",
+        verdict, evaluation.configurations
+    );
+    match evaluation.value {
+        alternating::Value::Accept => ExitCode::from(0),
+        alternating::Value::Reject => ExitCode::from(1),
+        alternating::Value::Unknown => ExitCode::from(2),
+    }
+}
+
+fn accept_oracle(
+    filename: &Path,
+    input: &str,
+    max_steps: u128,
+    oracle: &Path,
+    tape: &TapeArgs,
+) -> ExitCode {
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut tm = TuringMachine::new(filename);
+    tm.set_reject_undefined(true);
+    tm.set_input(&input);
+    tape.apply(&mut tm, &input);
+
+    let answers: Result<Box<dyn oracle::Oracle>, String> =
+        match oracle.extension().and_then(|extension| extension.to_str()) {
+            Some("turing") => TuringMachine::read(oracle, &[])
+                .map(|tm| Box::new(oracle::Machine { tm, max_steps }) as Box<dyn oracle::Oracle>),
+            _ => oracle::Words::read(oracle).map(|words| Box::new(words) as _),
+        };
+    let ran = answers.and_then(|mut answers| oracle::run(&mut tm, answers.as_mut(), max_steps));
+    match ran {
+        Ok((verdict, queries)) => {
+            println!(
+                "{} after {} steps and {} queries",
+                verdict, tm.num_steps, queries
+            );
+            match verdict {
+                Verdict::Accept => ExitCode::from(0),
+                Verdict::Reject => ExitCode::from(1),
+                Verdict::StepLimit => ExitCode::from(2),
+            }
+        }
+        Err(why) => {
+            println!("Can't run with oracle {}: {}", oracle.display(), why);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn accept_probabilistic(
+    filename: &Path,
+    input: &str,
+    max_steps: u128,
+    trials: u64,
+    seed: u64,
+    tape: &TapeArgs,
+) -> ExitCode {
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut tm = TuringMachine::new(filename);
+    tm.set_input(&input);
+    tape.apply(&mut tm, &input);
+
+    let estimate = probabilistic::monte_carlo(&tm, trials, max_steps, seed);
+    let (low, high) = estimate.interval();
+    println!(
+        "accepted {} of {} trials: p = {:.4}, 95% confidence interval [{:.4}, {:.4}]",
+        estimate.accepted,
+        estimate.trials(),
+        estimate.probability(),
+        low,
+        high
+    );
+    println!(
+        "rejected {}, step limit {}",
+        estimate.rejected, estimate.step_limit
+    );
+    ExitCode::SUCCESS
+}
+
+/// Reads a word list with one word per line. An empty line is the empty word.
+fn read_words(path: &Path) -> Result<Vec<String>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content
+            .lines()
+            .map(|line| line.trim().to_string())
+            .collect()),
+        Err(why) => Err(format!("{}: {}", path.display(), why)),
+    }
+}
+
+fn test(
+    filename: &Path,
+    words: Option<&Path>,
+    expect: &[PathBuf],
+    max_steps: u128,
+    tape: &TapeArgs,
+) -> ExitCode {
+    let lists = words.map(read_words).transpose().and_then(|listed| {
+        let (accept, reject) = match expect {
+            [accept_file, reject_file] => (read_words(accept_file)?, read_words(reject_file)?),
+            _ => (vec![], vec![]),
+        };
+        Ok((listed.unwrap_or_default(), accept, reject))
+    });
+    let (listed, accept, reject) = match lists {
+        Ok(lists) => lists,
+        Err(why) => {
+            println!("Can't read words: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let cases = match words::cases(&listed, &accept, &reject) {
+        Ok(cases) => cases,
+        Err(why) => {
+            println!("Can't test words: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut machine = TuringMachine::new(filename);
+    machine.set_reject_undefined(true);
+    let rows = match words::run(&machine, cases, max_steps, |tm, input| {
+        tape.apply(tm, input)
+    }) {
+        Ok(rows) => rows,
+        Err(why) => {
+            println!("{}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("{}", words::table(&rows));
+
+    if rows.iter().any(words::Row::failed) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn check(filename: &Path, spec_file: Option<&Path>) -> ExitCode {
+    let spec_file = spec_file.map_or_else(|| spec::spec_file(filename), Path::to_path_buf);
+    let specs = match spec::load(&spec_file) {
+        Ok(specs) => specs,
+        Err(why) => {
+            println!("Can't read {}: {}", spec_file.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let width = specs
+        .iter()
+        .map(|spec| spec.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Test".len());
+    println!(" {:width$} | Halt     |      Steps | Status", "Test");
+    println!("-{:-<width$}-+----------+------------+--------", "");
+
+    let mut failures = vec![];
+    for spec in &specs {
+        let outcome = match spec::check(filename, spec, &sandbox::Limits::NONE) {
+            Ok(outcome) => outcome,
+            Err(why) => {
+                println!("Can't load machine: {}", why);
+                return ExitCode::FAILURE;
+            }
+        };
+        let status = match &outcome.failure {
+            None => "pass",
+            Some(failure) => {
+                failures.push(format!("{}: {}", spec.name, failure));
+                "FAIL"
+            }
+        };
+        println!(
+            " {:width$} | {:8} | {:10} | {}",
+            spec.name, outcome.halt, outcome.steps, status
+        );
+    }
+
+    println!();
+    for failure in &failures {
+        println!("{}", failure);
+    }
+    println!("{} tests, {} failed", specs.len(), failures.len());
+
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn grade(
+    assignment: &Path,
+    submissions: &Path,
+    format: grade::Format,
+    output: Option<&Path>,
+    limits: &sandbox::Limits,
+) -> ExitCode {
+    let specs = match spec::load(assignment) {
+        Ok(specs) => specs,
+        Err(why) => {
+            println!("Can't read {}: {}", assignment.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let paths = match grade::submissions(submissions) {
+        Ok(paths) => paths,
+        Err(why) => {
+            println!("Can't read submissions: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let grades: Vec<grade::Grade> = paths
+        .iter()
+        .map(|path| grade::Grade::new(&specs, path, limits))
+        .collect();
+    let report = match format {
+        grade::Format::Csv => grade::csv(&specs, &grades),
+        grade::Format::Json => format!("{}\n", grade::json(&specs, &grades)),
+    };
+    match output {
+        Some(path) => {
+            if let Err(why) = fs::write(path, report) {
+                println!("Can't write {}: {}", path.display(), why);
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{}", report),
+    }
+    ExitCode::SUCCESS
+}
+
+fn encode(filename: &Path, utm: UtmScheme, input: &str) -> ExitCode {
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let tm = TuringMachine::new(filename);
+    match utm.encode(&tm, &input) {
+        Ok(tape) => {
+            let tape: Vec<String> = tape.iter().map(TapeEntry::to_string).collect();
+            println!("{}", tape.join(" "));
+            ExitCode::SUCCESS
+        }
+        Err(why) => {
+            println!("Can't encode {}: {}", filename.display(), why);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn decode(utm: UtmScheme, tape: &[String]) -> ExitCode {
+    let tape = match turing::parse_word(&tape.join(" ")) {
+        Ok(tape) => tape,
+        Err(why) => {
+            println!("Can't read tape: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match utm.decode(&tape) {
+        Ok(decoded) => {
+            match decoded.state {
+                Some(state) => println!("State: {}", state),
+                None => println!("State: Halt"),
+            }
+            let tape: Vec<String> = decoded.tape.iter().map(TapeEntry::to_string).collect();
+            println!("Tape: {}", tape.join(" "));
+            println!("Head: {}", decoded.head);
+            ExitCode::SUCCESS
+        }
+        Err(why) => {
+            println!("Can't decode tape: {}", why);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Writes a machine file to `output`, or to stdout if there is none.
+fn write_machine(tm: &TuringMachine, output: Option<&Path>) -> ExitCode {
+    match output {
+        Some(output) => {
+            if let Err(why) = fs::write(output, tm.to_turing()) {
+                println!("Can't write {}: {}", output.display(), why);
+                return ExitCode::FAILURE;
+            }
+        }
+        None => println!("{}", tm.to_turing()),
+    }
+    ExitCode::SUCCESS
+}
+
+fn counter(
+    filename: &Path,
+    registers: &str,
+    max_steps: u128,
+    to_turing: bool,
+    via_turing: bool,
+    output: Option<&Path>,
+) -> ExitCode {
+    let program = fs::read_to_string(filename)
+        .map_err(|why| why.to_string())
+        .and_then(|content| match filename.extension() {
+            Some(extension) if extension == "cm" => counter::Program::parse_counters(&content),
+            _ => counter::Program::parse_registers(&content),
+        });
+    let program = match program {
+        Ok(program) => program,
+        Err(why) => {
+            println!("Can't read {}: {}", filename.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let values: Result<Vec<u64>, String> = registers
+        .split([' ', ','])
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("invalid register value '{value}'"))
+        })
+        .collect();
+    let mut values = match values {
+        Ok(values) if values.len() <= program.registers => values,
+        Ok(values) => {
+            println!(
+                "Can't start {} registers with {} values",
+                program.registers,
+                values.len()
+            );
+            return ExitCode::FAILURE;
+        }
+        Err(why) => {
+            println!("Can't read registers: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    values.resize(program.registers, 0);
+
+    if to_turing || via_turing {
+        let mut tm = match program.to_turing() {
+            Ok(tm) => tm,
+            Err(why) => {
+                println!("Can't compile {}: {}", filename.display(), why);
+                return ExitCode::FAILURE;
+            }
+        };
+        if via_turing {
+            tm.set_input(&program.tape(&values));
+            while tm.num_steps < max_steps && tm.step() {}
+            let cells: Vec<TapeEntry> = tm.tape().iter().copied().collect();
+            values = program.registers_of(&cells);
+            let status = match tm.is_halted() {
+                true => "halted",
+                false => "still running",
+            };
+            let values: Vec<String> = values.iter().map(u64::to_string).collect();
+            println!(
+                "{} after {} Turing machine steps with registers {}",
+                status,
+                tm.num_steps,
+                values.join(" ")
+            );
+            return match tm.is_halted() {
+                true => ExitCode::SUCCESS,
+                false => ExitCode::from(2),
+            };
+        }
+        let mut tape: Vec<String> = program.tape(&values).iter().map(u8::to_string).collect();
+        // A word without spaces is read a digit per symbol.
+        if tape.len() == 1 {
+            tape.push(turing::DEFAULT_ENTRY.to_string());
+        }
+        eprintln!(
+            "{} states and {} instructions, start with --input \"{}\"",
+            tm.states().len(),
+            tm.instructions().len(),
+            tape.join(" ")
+        );
+        return write_machine(&tm, output);
+    }
+
+    let values_text = |values: &[u64]| {
+        let values: Vec<String> = values.iter().map(u64::to_string).collect();
+        values.join(" ")
+    };
+    match program.run(&mut values, max_steps) {
+        Some(steps) => {
+            println!(
+                "halted after {} steps with registers {}",
+                steps,
+                values_text(&values)
+            );
+            ExitCode::SUCCESS
+        }
+        None => {
+            println!(
+                "still running after {} steps with registers {}",
+                max_steps,
+                values_text(&values)
+            );
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cellular_automaton(
+    rule: u8,
+    steps: usize,
+    width: usize,
+    input: &str,
+    png: Option<&Path>,
+    svg: Option<&Path>,
+    scale: usize,
+    quiet: bool,
+) -> ExitCode {
+    let automaton =
+        turing::parse_word(input).and_then(|start| ca::Automaton::new(rule, width, &start));
+    let mut automaton = match automaton {
+        Ok(automaton) => automaton,
+        Err(why) => {
+            println!("Can't start the automaton: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let rows = ca::space_time(&mut automaton, steps);
+    if !quiet {
+        for row in &rows {
+            println!("{}", ca::format_row(row));
+        }
+    }
+    if let Some(path) = png {
+        let _render = log::span("render", &[("file", path.display().to_string().into())]);
+        let (width, height, pixels) = ca::pixels(&rows, scale);
+        if let Err(why) = png::write_gray(path, width, height, &pixels) {
+            println!("Can't write {}: {}", path.display(), why);
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(path) = svg {
+        let svg = spacetime::Diagram::of_cells(&rows).to_svg(scale, |entry| entry.to_string());
+        if let Err(why) = fs::write(path, svg) {
+            println!("Can't write {}: {}", path.display(), why);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_tag(filename: &Path, word: Option<&str>, max_steps: u128, cyclic: bool) -> ExitCode {
+    let mut tag = match fs::read_to_string(filename)
+        .map_err(|why| why.to_string())
+        .and_then(|content| tag::TagSystem::parse(&content))
+    {
+        Ok(tag) => tag,
+        Err(why) => {
+            println!("Can't read {}: {}", filename.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(word) = word {
+        tag.word = word
+            .split_whitespace()
+            .map(|name| tag.letter(name))
+            .collect();
+    }
+
+    let run = if cyclic {
+        // Take a cycle through the productions per step of the tag system,
+        // checking after each whether it would halt.
+        let emulation = tag::CyclicTag::emulate(&tag);
+        let letters = tag.letters.len();
+        let cycle = emulation.productions.len() as u128;
+        let mut bits = tag::CyclicTag::encode(letters, &tag.word);
+        let mut steps = 0;
+        loop {
+            let word = tag::CyclicTag::decode(letters, &bits).expect("bits encode letters");
+            let halted = tag.run(&word, 0).halted;
+            if halted || steps >= max_steps {
+                eprintln!("{} steps of the cyclic tag system", steps * cycle);
+                break tag::Run {
+                    steps,
+                    halted,
+                    word,
+                };
+            }
+            bits = emulation.run(&bits, cycle);
+            steps += 1;
+        }
+    } else {
+        tag.run(&tag.word, max_steps)
+    };
+    let status = match run.halted {
+        true => "halted",
+        false => "still running",
+    };
+    println!("{} after {} steps", status, run.steps);
+    println!("{}", tag.format_word(&run.word));
+    // Words of a compiled machine hold its configuration.
+    if let Some((state, cells)) = tag::tape(&tag, &run.word) {
+        let cells: Vec<String> = cells.iter().map(u8::to_string).collect();
+        println!(
+            "The machine is in {} with the tape {}",
+            state,
+            cells.join(" ")
+        );
+    }
+    match run.halted {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::from(2),
+    }
+}
+
+fn minimize(filename: &Path, output: Option<&Path>) -> ExitCode {
+    let tm = TuringMachine::new(filename);
+    let minimized = minimize::minimize(&tm);
+
+    eprintln!(
+        "Reduced {} states to {}",
+        tm.states().len(),
+        minimized.states().len()
+    );
+    write_machine(&minimized, output)
+}
+
+fn analyze(filename: &Path, window: usize, max_configurations: usize) -> ExitCode {
+    let tm = TuringMachine::new(filename);
+    let summary = abstraction::analyze(&tm, window, max_configurations);
+    println!(
+        "{} abstract configurations with a window of {} cells",
+        summary.configurations, window
+    );
+    if !summary.complete {
+        println!(
+            "Stopped at the maximum of {} configurations, nothing is proven",
+            max_configurations
+        );
+        return ExitCode::SUCCESS;
+    }
+    // The analysis reads the machine with its blank exchanged for 0.
+    let blank = tm.blank();
+    let tm = tm.with_zero_blank();
+    let names = |pairs: &mut dyn Iterator<Item = (usize, TapeEntry)>| {
+        let names: Vec<String> = pairs
+            .map(|(state, entry)| {
+                let entry = match entry {
+                    0 => blank,
+                    _ if entry == blank => 0,
+                    _ => entry,
+                };
+                format!("{} {}", tm.states()[state], entry)
+            })
+            .collect();
+        names.join(", ")
+    };
+    match summary.never_halts() {
+        true => println!("Never halts from the blank tape"),
+        false => println!(
+            "May halt reading {}",
+            names(&mut summary.halting.iter().copied())
+        ),
+    }
+    let mut unused = tm
+        .instructions()
+        .iter()
+        .map(|instruction| (instruction.state, instruction.entry))
+        .filter(|read| !summary.reached.contains(read));
+    let unused = names(&mut unused);
+    if !unused.is_empty() {
+        println!("Never uses the transitions for {}", unused);
+    }
+    ExitCode::SUCCESS
+}
+
+/// What `report` writes.
+enum ReportFormat {
+    Html,
+    Tikz,
+    TikzTrace,
+    Mermaid,
+}
+
+fn report(
+    filename: &Path,
+    steps: u128,
+    input: &str,
+    format: ReportFormat,
+    output: Option<&Path>,
+) -> ExitCode {
+    let tm = TuringMachine::new(filename);
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let name = filename
+        .file_stem()
+        .map_or(filename.to_string_lossy(), |stem| stem.to_string_lossy());
+    let text = match format {
+        ReportFormat::Html => report::report(&tm, &name, &input, steps),
+        ReportFormat::Tikz => report::tikz(&tm),
+        ReportFormat::Mermaid => tm.to_mermaid() + "\n",
+        ReportFormat::TikzTrace => {
+            report::tikz(&tm) + "\n" + &report::tikz_trace(&tm, &input, steps)
+        }
+    };
+    match output {
+        Some(output) => {
+            if let Err(why) = fs::write(output, text) {
+                println!("Can't write {}: {}", output.display(), why);
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{}", text),
+    }
+    ExitCode::SUCCESS
+}
+
+/// Most rows of the space-time diagram shown by `run --inline`.
+const INLINE_ROWS: u128 = 400;
+
+/// Pixels per cell of the space-time diagram shown by `run --inline`, so
+/// narrow tapes aren't tiny.
+fn inline_scale(cells: u128) -> usize {
+    (400 / cells.max(1)).clamp(1, 4) as usize
+}
+
+fn spacetime(
+    filename: &Path,
+    steps: u128,
+    input: &str,
+    sample: u128,
+    svg: Option<&Path>,
+    png: Option<&Path>,
+    scale: usize,
+) -> ExitCode {
+    let mut tm = TuringMachine::new(filename);
+    match turing::parse_word(input) {
+        Ok(input) => tm.set_input(&input),
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    }
+    let diagram = spacetime::Diagram::record(&tm, steps, sample);
+    if let Some(path) = svg {
+        if let Err(why) = fs::write(path, diagram.to_svg(scale, |entry| tm.glyph(entry))) {
+            println!("Can't write {}: {}", path.display(), why);
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(path) = png {
+        let (width, height, pixels) = diagram.to_pixels(scale);
+        if let Err(why) = png::write_gray(path, width, height, &pixels) {
+            println!("Can't write {}: {}", path.display(), why);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn explore(
+    filename: &Path,
+    input: &str,
+    cells: usize,
+    edge: EdgeBehavior,
+    max_configurations: usize,
+    dot: Option<&Path>,
+) -> ExitCode {
+    let tm = TuringMachine::new(filename);
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let graph = configurations::explore(&tm, &input, cells, edge, max_configurations);
+
+    println!(
+        "{} configurations and {} transitions",
+        graph.configurations.len(),
+        graph.transitions.len()
+    );
+    match (graph.first_halt(), graph.complete) {
+        (Some(halt), _) => println!(
+            "Halts after {} steps at the earliest ({})",
+            halt.steps,
+            halt.halt.unwrap()
+        ),
+        (None, true) => println!("Never halts, as no halting configuration is reachable"),
+        (None, false) => println!(
+            "No halting configuration among the first {}",
+            max_configurations
+        ),
+    }
+    if let Some(dot) = dot {
+        if let Err(why) = fs::write(dot, graph.to_dot(&tm)) {
+            println!("Can't write {}: {}", dot.display(), why);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn isomorphic(a: &Path, b: &Path) -> ExitCode {
+    match isomorphic::isomorphism(&TuringMachine::new(a), &TuringMachine::new(b)) {
+        Some(mapping) => {
+            println!("Machines are isomorphic");
+            println!("{}", mapping);
+            ExitCode::SUCCESS
+        }
+        None => {
+            println!("Machines aren't isomorphic");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn equiv(a: &Path, b: &Path, inputs: Option<&Path>, max_steps: u128) -> ExitCode {
+    let words = match inputs.map(read_words).transpose() {
+        Ok(words) => words.unwrap_or_else(|| vec![String::new()]),
+        Err(why) => {
+            println!("Can't read inputs: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let inputs: Result<Vec<Vec<TapeEntry>>, String> = words
+        .iter()
+        .map(|word| {
+            turing::parse_word(word).map_err(|why| format!("Can't read input '{}': {}", word, why))
+        })
+        .collect();
+    let inputs = match inputs {
+        Ok(inputs) => inputs,
+        Err(why) => {
+            println!("{}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let tm_a = TuringMachine::new(a);
+    let tm_b = TuringMachine::new(b);
+
+    match equiv::compare(&tm_a, &tm_b, &inputs, max_steps) {
+        equiv::Comparison::Equivalent { undecided } => {
+            println!("Machines agree on {} inputs", inputs.len());
+            if undecided > 0 {
+                println!(
+                    "{} inputs exceeded the step limit in both machines",
+                    undecided
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        equiv::Comparison::Diverged {
+            input,
+            a: outcome_a,
+            b: outcome_b,
+        } => {
+            let word = &words[input];
+            println!(
+                "Machines differ on input '{}'",
+                if word.is_empty() {
+                    words::EMPTY_WORD
+                } else {
+                    word
+                }
+            );
+            println!("  {}: {}", a.display(), outcome_a);
+            println!("  {}: {}", b.display(), outcome_b);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn race(a: &Path, b: &Path, input: &str, max_steps: u128, quiet: bool, no_color: bool) -> ExitCode {
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let colors = Colors::new(no_color);
+    let tm_a = TuringMachine::new(a);
+    let tm_b = TuringMachine::new(b);
+
+    let mut laps = vec![(0, equiv::view(&tm_a), equiv::view(&tm_b))];
+    let diverged = equiv::race(&tm_a, &tm_b, &input, max_steps, |step, a, b| {
+        laps.push((step, equiv::view(a), equiv::view(b)))
+    });
+    let width = laps
+        .iter()
+        .map(|(_, a, _)| a.chars().count())
+        .max()
+        .unwrap_or(0);
+    let step_width = laps.last().map_or(1, |(step, _, _)| step.to_string().len());
+    let last = laps.len() - 1;
+    for (index, (step, view_a, view_b)) in laps.iter().enumerate() {
+        let diverging = diverged.is_some() && index == last;
+        if quiet && !diverging {
+            continue;
+        }
+        let line = format!("{:>step_width$} │ {:width$} │ {}", step, view_a, view_b);
+        if diverging {
+            println!("{}  <- diverged", colors.head(&line));
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    match diverged {
+        Some(step) => {
+            println!("Machines diverge at step {}", step);
+            ExitCode::FAILURE
+        }
+        None => {
+            println!(
+                "Machines behave the same for {} steps",
+                laps.last().map_or(0, |(step, _, _)| *step)
+            );
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+fn diff(a: &Path, b: &Path) -> ExitCode {
+    let tm_a = TuringMachine::new(a);
+    let tm_b = TuringMachine::new(b);
+    let diff = diff::diff(&tm_a, &tm_b);
+    print!("{}", diff);
+    if diff.changes.is_empty() {
+        println!("Machines have the same transitions");
+        ExitCode::SUCCESS
+    } else {
+        println!("{} transitions differ", diff.changes.len());
+        ExitCode::FAILURE
+    }
+}
+
+fn transform(filename: &Path, kind: &TransformKind, output: Option<&Path>) -> ExitCode {
+    let tm = TuringMachine::new(filename);
+    if kind.to_tag {
+        let tag = match tag::from_turing(&tm) {
+            Ok(tag) => tag,
+            Err(why) => {
+                println!("Can't transform {}: {}", filename.display(), why);
+                return ExitCode::FAILURE;
+            }
+        };
+        let productions = tag.productions.iter().flatten().count();
+        eprintln!(
+            "{} letters and {} productions",
+            tag.letters.len(),
+            productions
+        );
+        match output {
+            Some(output) => {
+                if let Err(why) = fs::write(output, tag.to_text()) {
+                    println!("Can't write {}: {}", output.display(), why);
+                    return ExitCode::FAILURE;
+                }
+            }
+            None => print!("{}", tag.to_text()),
+        }
+        return ExitCode::SUCCESS;
+    }
+    let mut transformed = if kind.to_binary {
+        let symbols = transform::symbols(&tm).len();
+        eprintln!(
+            "Encoding {} symbols in blocks of {} cells",
+            symbols,
+            transform::block_size(symbols)
+        );
+        transform::to_binary(&tm)
+    } else if kind.mirror {
+        transform::mirror(&tm)
+    } else if let Some(permutation) = &kind.permute_symbols {
+        match transform::parse_permutation(permutation) {
+            Ok(permutation) => transform::permute_symbols(&tm, &permutation),
+            Err(why) => {
+                println!("Can't read permutation '{}': {}", permutation, why);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else if kind.to_two_states {
+        match transform::to_two_states(&tm) {
+            Ok((transformed, encoding)) => {
+                eprintln!(
+                    "Start with the head on symbol {} instead of a blank",
+                    encoding.start(0)
+                );
+                transformed
+            }
+            Err(why) => {
+                println!("Can't transform {}: {}", filename.display(), why);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        unreachable!("clap requires a transformation")
+    };
+
+    transformed.set_metadata(tm.metadata().to_vec());
+    eprintln!(
+        "{} states and {} instructions",
+        transformed.states().len(),
+        transformed.instructions().len()
+    );
+    write_machine(&transformed, output)
+}
+
+fn decide(
+    filename: &Path,
+    max_steps: u128,
+    depth: usize,
+    cert: Option<&Path>,
+    parallel: bool,
+    pipeline: Option<&Pipeline>,
+    window: Option<(usize, usize)>,
+) -> ExitCode {
+    let parse = log::span(
+        "parse",
+        &[("machine", filename.display().to_string().into())],
+    );
+    let tm = TuringMachine::new(filename).with_zero_blank();
+    drop(parse);
+
+    let decide = log::span("decide", &[]);
+    let repeated = window.and_then(|(window, history)| {
+        window::find(&tm, max_steps, window, history, &AtomicBool::new(false))
+    });
+    let decision = match (repeated, pipeline) {
+        (Some(proof), _) => decide::Decision::NeverHalts(proof),
+        (None, Some(pipeline)) => {
+            let (decision, _, times) = pipeline.decide(&tm);
+            for (stage, time) in pipeline.0.iter().zip(times) {
+                eprintln!("{:<20} {:>10.3} s", stage.to_string(), time.as_secs_f64());
+            }
+            decision
+        }
+        (None, None) if parallel => decide::decide_parallel(&tm, max_steps, depth),
+        (None, None) => decide::decide(&tm, max_steps, depth),
+    };
+    drop(decide);
+    match decision {
+        decide::Decision::Halts { steps, reason } => {
+            println!("Machine {} after {} steps", reason, steps);
+            ExitCode::SUCCESS
+        }
+        decide::Decision::NeverHalts(proof) => {
+            println!("Machine never halts: {}", proof);
+            if let Some(cert) = cert {
+                let certificate = decide::Certificate {
+                    machine: tm.to_turing(),
+                    proof,
+                };
+                if let Err(why) = fs::write(cert, certificate.to_json().to_string()) {
+                    println!("Can't write {}: {}", cert.display(), why);
+                    return ExitCode::FAILURE;
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        decide::Decision::Undecided => {
+            println!("Undecided after {} steps", max_steps);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn verify_cert(filename: &Path, certificate: &Path) -> ExitCode {
+    let tm = TuringMachine::new(filename).with_zero_blank();
+    let json = match fs::read_to_string(certificate) {
+        Ok(content) => content,
+        Err(why) => {
+            println!("Can't read {}: {}", certificate.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let certificate =
+        match Json::parse(&json).and_then(|json| decide::Certificate::from_json(&json)) {
+            Ok(certificate) => certificate,
+            Err(why) => {
+                println!("Can't read certificate: {}", why);
+                return ExitCode::FAILURE;
+            }
+        };
+
+    match decide::verify(&tm, &certificate) {
+        Ok(()) => {
+            println!("Certificate is valid: {}", certificate.proof);
+            ExitCode::SUCCESS
+        }
+        Err(why) => {
+            println!("Certificate is invalid: {}", why);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn check_dvf(dvf: &Path, machines: &MachineArgs, max_steps: u128) -> ExitCode {
+    let bytes = match fs::read(dvf) {
+        Ok(bytes) => bytes,
+        Err(why) => {
+            println!("Can't read {}: {}", dvf.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let entries = match bbchallenge::read_dvf(&bytes) {
+        Ok(entries) => entries,
+        Err(why) => {
+            println!("Can't read decider verification file: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut machines = match machines.open() {
+        Ok(machines) => machines,
+        Err(why) => {
+            println!("Can't open machines: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = 0;
+    for entry in &entries {
+        let result = machines
+            .machine(entry.machine_id as u64)
+            .and_then(|tm| bbchallenge::check(&tm, entry, max_steps));
+        if let Err(why) = result {
+            println!("Machine {}: {}", entry.machine_id, why);
+            failed += 1;
+        }
+    }
+
+    println!(
+        "Confirmed {} of {} entries",
+        entries.len() - failed,
+        entries.len()
+    );
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn batch(
+    machine_args: &MachineArgs,
+    from: u64,
+    count: Option<u64>,
+    max_steps: u128,
+    depth: usize,
+    shard: Option<Shard>,
+    manifest: Option<&Path>,
+    leaderboard: Option<&Path>,
+    lockstep: Option<usize>,
+    export_holdouts: Option<&Path>,
+    pipeline: Option<&Pipeline>,
+    log: Option<&Path>,
+    db: Option<&Path>,
+) -> ExitCode {
+    let mut machines = match machine_args.open() {
+        Ok(machines) => machines,
+        Err(why) => {
+            println!("Can't open machines: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    // What is tried on every machine, as recorded for the holdouts.
+    let (tried_steps, tried_depth, deciders) = match pipeline {
+        Some(pipeline) => (
+            pipeline.max_steps(pipeline.0.len()),
+            pipeline.depth(),
+            pipeline.deciders(),
+        ),
+        None => (max_steps, depth, decide::DECIDERS.to_vec()),
+    };
+    if let bbchallenge::Machines::Holdouts(holdouts) = &machines {
+        let tried = holdouts
+            .iter()
+            .filter(|holdout| holdout.max_steps >= tried_steps && holdout.depth >= tried_depth)
+            .count();
+        if tried > 0 {
+            eprintln!(
+                "{} holdouts were already tried with budgets at least as big",
+                tried
+            );
+        }
+    }
+    let end = match count {
+        Some(count) => from.saturating_add(count).min(machines.len()),
+        None => machines.len(),
+    };
+    let mut range = from.min(end)..end;
+    if let Some(shard) = shard {
+        range = shard.range(range);
+    }
+
+    let mut manifest = match manifest {
+        Some(path) => {
+            let input = format!("{} {}", machine_args.name(), machines.len());
+            match Manifest::open(path, &input, &range) {
+                Ok(manifest) => Some(manifest),
+                Err(why) => {
+                    println!("Can't open manifest: {}", why);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        None => None,
+    };
+    let mut leaderboard = match leaderboard.map(Leaderboard::open).transpose() {
+        Ok(leaderboard) => leaderboard,
+        Err(why) => {
+            println!("Can't open leaderboard: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut log = match experiment::Log::open(log, db) {
+        Ok(log) => log,
+        Err(why) => {
+            println!("Can't create log: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (mut halting, mut non_halting, mut undecided, mut resumed) = (0, 0, 0, 0);
+    let mut count = |verdict: &str| {
+        if verdict.starts_with("halts") {
+            halting += 1;
+        } else if verdict == "undecided" {
+            undecided += 1;
+        } else {
+            non_halting += 1;
+        }
+    };
+    let mut holdouts = vec![];
+    let holdout = |id: u64, tm: &TuringMachine| holdouts::Holdout {
+        id,
+        machine: bbchallenge::format_machine(tm),
+        max_steps: tried_steps,
+        depth: tried_depth,
+        deciders: deciders.iter().map(|name| name.to_string()).collect(),
+    };
+    let mut timings = pipeline.map(Timings::new);
+    let _batch = log::span(
+        "batch",
+        &[("from", range.start.into()), ("to", range.end.into())],
+    );
+    let mut indices = range.clone();
+    loop {
+        let mut chunk = vec![];
+        for index in indices.by_ref() {
+            let id = machines.id(index);
+            if let Some(verdict) = manifest
+                .as_ref()
+                .and_then(|manifest| manifest.verdict(index))
+            {
+                count(verdict);
+                resumed += 1;
+                if verdict == "undecided" && export_holdouts.is_some() {
+                    match machines.machine(id) {
+                        Ok(tm) => holdouts.push(holdout(id, &tm)),
+                        Err(why) => {
+                            println!("Can't read machine {}: {}", id, why);
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                }
+                continue;
+            }
+            match machines.machine(id) {
+                Ok(tm) => chunk.push((index, id, tm)),
+                Err(why) => {
+                    println!("Can't read machine {}: {}", id, why);
+                    return ExitCode::FAILURE;
+                }
+            }
+            if chunk.len() >= lockstep.unwrap_or(1) {
+                break;
+            }
+        }
+        if chunk.is_empty() {
+            break;
+        }
+        let start = Instant::now();
+        let finished = match lockstep {
+            Some(_) => {
+                let tms: Vec<TuringMachine> = chunk.iter().map(|(_, _, tm)| tm.clone()).collect();
+                lockstep::run(&tms, max_steps)
+            }
+            None => vec![lockstep::Finished::Undecided; chunk.len()],
+        };
+        // The machines simulated in lockstep share its time.
+        let shared = start.elapsed() / chunk.len() as u32;
+
+        for ((index, id, tm), finished) in chunk.into_iter().zip(finished) {
+            let start = Instant::now();
+            let (decision, decider, steps) = match finished {
+                lockstep::Finished::Halted { steps, reason, .. } => {
+                    (decide::Decision::Halts { steps, reason }, "lockstep", steps)
+                }
+                lockstep::Finished::Undecided => match (pipeline, &mut timings) {
+                    (Some(pipeline), Some(timings)) => {
+                        let (decision, decided, times) = pipeline.decide(&tm);
+                        timings.add(decided, &times);
+                        let budget = pipeline.max_steps(times.len());
+                        let steps = experiment::simulated(&decision, budget);
+                        (decision, "simulation", steps)
+                    }
+                    _ => {
+                        let decision = decide::decide(&tm, max_steps, depth);
+                        let steps = experiment::simulated(&decision, max_steps);
+                        (decision, "simulation", steps)
+                    }
+                },
+            };
+            if let Some(log) = &mut log {
+                let time = start.elapsed() + shared;
+                if let Err(why) = log.record(id, &tm, &decision, decider, steps, time) {
+                    println!("Can't write log: {}", why);
+                    return ExitCode::FAILURE;
+                }
+            }
+            let verdict = match decision {
+                decide::Decision::Halts { steps, .. } => {
+                    if let Some(leaderboard) = &mut leaderboard {
+                        if let Err(why) = submit(leaderboard, &tm, steps) {
+                            println!("Can't write leaderboard: {}", why);
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                    format!("halts {}", steps)
+                }
+                decide::Decision::NeverHalts(proof) => proof.decider().to_string(),
+                decide::Decision::Undecided => {
+                    if export_holdouts.is_some() {
+                        holdouts.push(holdout(id, &tm));
+                    }
+                    "undecided".to_string()
+                }
+            };
+            count(&verdict);
+            log::event(
+                log::Level::Info,
+                "decided",
+                &[
+                    ("id", id.into()),
+                    ("machine", bbchallenge::format_machine(&tm).into()),
+                    ("verdict", verdict.as_str().into()),
+                ],
+            );
+            println!("{} {} {}", id, bbchallenge::format_machine(&tm), verdict);
+            if let Some(manifest) = &mut manifest {
+                if let Err(why) = manifest.record(index, &verdict) {
+                    println!("Can't write manifest: {}", why);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+
+    if let Some(log) = &mut log {
+        if let Err(why) = log.finish() {
+            println!("Can't write log: {}", why);
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(path) = export_holdouts {
+        if let Err(why) = holdouts::write(path, &holdouts) {
+            println!("Can't write holdouts: {}", why);
+            return ExitCode::FAILURE;
+        }
+    }
+    if resumed > 0 {
+        eprintln!("Skipped {} machines already in the manifest", resumed);
+    }
+    eprintln!(
+        "{} machines: {} halt, {} never halt, {} undecided",
+        range.end - range.start,
+        halting,
+        non_halting,
+        undecided
+    );
+    if let (Some(pipeline), Some(timings)) = (pipeline, &timings) {
+        for line in timings.report(pipeline) {
+            eprintln!("{}", line);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn serve(
+    bind: &str,
+    port: u16,
+    server: server::Server,
+    job_limits: sandbox::Limits,
+    workers: usize,
+    capacity: usize,
+    jobs: Option<&Path>,
+) -> ExitCode {
+    let queue = match jobs::Queue::start(workers, capacity, job_limits, jobs) {
+        Ok(queue) => queue,
+        Err(why) => {
+            println!("Can't start job queue: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let server = server.with_jobs(queue);
+    match server::serve(&format!("{}:{}", bind, port), server) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(why) => {
+            println!("Can't serve on {}:{}: {}", bind, port, why);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn lsp() -> ExitCode {
+    // Stdout belongs to the protocol, so errors go to stderr.
+    match lsp::run(&mut std::io::stdin().lock(), &mut std::io::stdout().lock()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(why) => {
+            eprintln!("Can't run language server: {}", why);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn format(files: &[PathBuf], check: bool) -> ExitCode {
+    let mut unformatted = 0;
+    for path in files {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(why) => {
+                println!("Can't read {}: {}", path.display(), why);
+                return ExitCode::FAILURE;
+            }
+        };
+        let formatted = match fmt::format(&content) {
+            Ok(formatted) => formatted,
+            Err(why) => {
+                println!("Can't format {}: {}", path.display(), why);
+                return ExitCode::FAILURE;
+            }
+        };
+        if formatted == content {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", path.display());
+            unformatted += 1;
+        } else if let Err(why) = fs::write(path, formatted) {
+            println!("Can't write {}: {}", path.display(), why);
+            return ExitCode::FAILURE;
+        }
+    }
+    if unformatted > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Enters a machine halting after `max_steps` into the leaderboard, saving
+/// it if a record was beaten.
+fn submit(
+    leaderboard: &mut Leaderboard,
+    tm: &TuringMachine,
+    max_steps: u128,
+) -> Result<(), String> {
+    let entry = match Entry::of(tm, max_steps) {
+        Some(entry) => entry,
+        None => return Ok(()),
+    };
+    let improved = leaderboard.submit(&entry);
+    for record in &improved {
+        eprintln!(
+            "New {} record for {} states and {} symbols: {} ({} non-blank, {} steps)",
+            record, entry.states, entry.symbols, entry.machine, entry.sigma, entry.steps
+        );
+    }
+    if improved.is_empty() {
+        Ok(())
+    } else {
+        leaderboard.save()
+    }
+}
+
+fn busy_beaver(
+    states: Span,
+    symbols: Span,
+    max_steps: u128,
+    depth: usize,
+    range: Range<u64>,
+    log: Option<&Path>,
+    db: Option<&Path>,
+) -> ExitCode {
+    let mut log = match experiment::Log::open(log, db) {
+        Ok(log) => log,
+        Err(why) => {
+            println!("Can't create log: {}", why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut decided = true;
+    println!(
+        "{:>6} {:>7} {:6} {:>12} {:>16} machine",
+        "states", "symbols", "record", "sigma", "steps"
+    );
+    for states in states.iter() {
+        for symbols in symbols.iter() {
+            let search = match bb::search(
+                states,
+                symbols,
+                max_steps,
+                depth,
+                range.clone(),
+                log.as_mut(),
+            ) {
+                Ok(search) => search,
+                Err(why) => {
+                    println!("Can't write log: {}", why);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let records = [
+                (Record::Sigma, &search.sigma),
+                (Record::Steps, &search.steps),
+            ];
+            for (record, entry) in records {
+                if let Some(entry) = entry {
+                    println!(
+                        "{:>6} {:>7} {:6} {:>12} {:>16} {}",
+                        states, symbols, record, entry.sigma, entry.steps, entry.machine
+                    );
+                }
+            }
+            for machine in &search.undecided {
+                println!("{:>6} {:>7} undecided {}", states, symbols, machine);
+            }
+            eprintln!(
+                "{} states, {} symbols: {} halting, {} never halting, {} undecided",
+                states,
+                symbols,
+                search.halting,
+                search.never_halting,
+                search.undecided.len()
+            );
+            decided &= search.undecided.is_empty();
+        }
+    }
+    if let Some(Err(why)) = log.as_mut().map(experiment::Log::finish) {
+        println!("Can't write log: {}", why);
+        return ExitCode::FAILURE;
+    }
+    if !decided {
+        eprintln!("The records are only lower bounds where machines are undecided");
+        return ExitCode::from(2);
+    }
+    ExitCode::SUCCESS
+}
+
+fn index_of(machine: &str, symbols: usize, max_steps: u128) -> ExitCode {
+    match bb::index_of(machine, symbols, max_steps) {
+        Ok(Some(index)) => {
+            println!("{}", index);
+            ExitCode::SUCCESS
+        }
+        Ok(None) => {
+            println!("{} isn't in the enumeration", machine);
+            ExitCode::from(2)
+        }
+        Err(why) => {
+            println!("Can't read machine: {}", why);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn machine_at(index: u64, states: usize, symbols: usize, max_steps: u128) -> ExitCode {
+    match bb::machine_at(states, symbols, max_steps, index) {
+        Some(machine) => {
+            println!("{}", machine);
+            ExitCode::SUCCESS
+        }
+        None => {
+            println!("The enumeration has no machine {}", index);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn query(filter: &Filter, db: &Path) -> ExitCode {
+    let table = match sqlite::read(db, Some(experiment::TABLE)) {
+        Ok(table) => table,
+        Err(why) => {
+            println!("Can't read {}: {}", db.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("{}", table.columns.join(" "));
+    let mut matching = 0;
+    for row in &table.rows {
+        match filter.matches(&table.columns, row) {
+            Ok(true) => {
+                let values: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+                println!("{}", values.join(" "));
+                matching += 1;
+            }
+            Ok(false) => {}
+            Err(why) => {
+                println!("Can't query: {}", why);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    eprintln!("{} of {} results match", matching, table.rows.len());
+    ExitCode::SUCCESS
+}
+
+fn leaderboard_show(file: &Path) -> ExitCode {
+    let leaderboard = match Leaderboard::open(file) {
+        Ok(leaderboard) => leaderboard,
+        Err(why) => {
+            println!("Can't read leaderboard {}: {}", file.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "{:>6} {:>7} {:6} {:>12} {:>16} machine",
+        "states", "symbols", "record", "sigma", "steps"
+    );
+    for (record, entry) in leaderboard.records() {
+        println!(
+            "{:>6} {:>7} {:6} {:>12} {:>16} {}",
+            entry.states, entry.symbols, record, entry.sigma, entry.steps, entry.machine
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+fn load_collection(selection: &Selection) -> Option<collection::Collection> {
+    match collection::Collection::load(&selection.collection) {
+        Ok(collection) => Some(collection),
+        Err(why) => {
+            println!("Can't read {}: {}", selection.collection.display(), why);
+            None
+        }
+    }
+}
+
+fn collection_list(selection: &Selection) -> ExitCode {
+    let Some(collection) = load_collection(selection) else {
+        return ExitCode::FAILURE;
+    };
+    for member in collection.select(selection.tag.as_deref()) {
+        println!(
+            "{} [{}] {}",
+            member.name,
+            member.tags.join(", "),
+            member.file.display()
+        );
+        if let Some(description) = &member.description {
+            println!("  {}", description);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Runs the selected machines of a collection, and with `check` compares
+/// them with what is expected.
+fn collection_run(selection: &Selection, check: bool) -> ExitCode {
+    let Some(collection) = load_collection(selection) else {
+        return ExitCode::FAILURE;
+    };
+    let members: Vec<_> = collection.select(selection.tag.as_deref()).collect();
+    let width = members
+        .iter()
+        .map(|member| member.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Machine".len());
+    match check {
+        true => {
+            println!(" {:width$} | Halt     |      Steps | Status", "Machine");
+            println!("-{:-<width$}-+----------+------------+--------", "");
+        }
+        false => {
+            println!(" {:width$} | Halt     |      Steps", "Machine");
+            println!("-{:-<width$}-+----------+-----------", "");
+        }
+    }
+
+    let mut failures = vec![];
+    for member in &members {
+        let outcome = match member.check(&sandbox::Limits::NONE) {
+            Ok(outcome) => outcome,
+            Err(why) => {
+                println!("Can't load machine {}: {}", member.name, why);
+                return ExitCode::FAILURE;
+            }
+        };
+        let status = match &outcome.failure {
+            _ if !check => "",
+            None => " | pass",
+            Some(failure) => {
+                failures.push(format!("{}: {}", member.name, failure));
+                " | FAIL"
+            }
+        };
+        println!(
+            " {:width$} | {:8} | {:10}{}",
+            member.name, outcome.halt, outcome.steps, status
+        );
+    }
+
+    if !check {
+        return ExitCode::SUCCESS;
+    }
+    println!();
+    for failure in &failures {
+        println!("{}", failure);
+    }
+    println!("{} machines, {} failed", members.len(), failures.len());
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn leaderboard_add(file: &Path, filename: &Path, max_steps: u128) -> ExitCode {
+    let mut leaderboard = match Leaderboard::open(file) {
+        Ok(leaderboard) => leaderboard,
+        Err(why) => {
+            println!("Can't read leaderboard {}: {}", file.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let tm = TuringMachine::new(filename);
+    if Entry::of(&tm, max_steps).is_none() {
+        println!("Machine doesn't halt within {} steps", max_steps);
+        return ExitCode::from(2);
+    }
+    match submit(&mut leaderboard, &tm, max_steps) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(why) => {
+            println!("Can't write leaderboard {}: {}", file.display(), why);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn debug(filename: &Path, input: &str, snapshot_every: u128, params: &[Param]) -> ExitCode {
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut tm = TuringMachine::load(filename, params);
+    tm.set_reject_undefined(true);
+    if !input.is_empty() {
+        tm.set_input(&input);
+    }
+
+    let colors = Colors::new(false);
+    let interactive = std::io::stdin().is_terminal();
+    let mut debugger = Debugger::new(tm, snapshot_every);
+    println!("{}", debugger.machine().format_state(colors));
+    let mut lines = std::io::stdin().lines();
+    loop {
+        if interactive {
+            eprint!("(touring) ");
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(why)) => {
+                println!("Can't read command: {}", why);
+                return ExitCode::FAILURE;
+            }
+            None => return ExitCode::SUCCESS,
+        };
+        if ["quit", "q", "exit"].contains(&line.trim()) {
+            return ExitCode::SUCCESS;
+        }
+        match debugger.execute(&line, colors) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(why) => println!("{}", why),
+        }
+    }
+}
+
+fn replay(path: &Path, input: &str, machine: Option<&Path>, snapshot_every: u128) -> ExitCode {
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let trace = match File::open(path)
+        .map_err(|why| why.to_string())
+        .and_then(|file| golden::Trace::read(BufReader::new(file)))
+    {
+        Ok(trace) => trace,
+        Err(why) => {
+            println!("Can't read {}: {}", path.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let tm = machine.map(TuringMachine::new);
+    let blank = tm
+        .as_ref()
+        .map_or(turing::DEFAULT_ENTRY, TuringMachine::blank);
+    let mut replay = replay::Replay::new(trace, &input, blank, snapshot_every);
+    if let Some(tm) = &tm {
+        replay.set_names(tm.states().to_vec());
+    }
+
+    let interactive = std::io::stdin().is_terminal();
+    let show = |line: &str, wait: std::time::Duration| {
+        println!("{}", line);
+        if interactive {
+            std::thread::sleep(wait);
+        }
+    };
+    println!("{}", replay.format(30));
+    let mut lines = std::io::stdin().lines();
+    loop {
+        if interactive {
+            eprint!("(replay) ");
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(why)) => {
+                println!("Can't read command: {}", why);
+                return ExitCode::FAILURE;
+            }
+            None => return ExitCode::SUCCESS,
+        };
+        if ["quit", "q", "exit"].contains(&line.trim()) {
+            return ExitCode::SUCCESS;
+        }
+        match replay.execute(&line, show) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(why) => println!("{}", why),
+        }
+    }
+}
+
+fn selftest(
+    filename: &Path,
+    steps: u128,
+    input: &str,
+    accel: &[Accel],
+    fuse: &[usize],
+) -> ExitCode {
+    let input = match turing::parse_word(input) {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Can't read input '{}': {}", input, why);
+            return ExitCode::FAILURE;
+        }
+    };
+    let tm = TuringMachine::new(filename);
+
+    let mut checks = vec![(
+        "engine".to_string(),
+        reference::check_engine(&tm, &input, steps),
+    )];
+    for accel in accel {
+        let name = match accel {
+            Accel::Block(cells) => format!("blocks of {}", cells),
+            Accel::Auto => "automatic blocks".to_string(),
+        };
+        checks.push((name, reference::check_accel(&tm, &input, *accel, steps)));
+    }
+    for fused in fuse {
+        checks.push((
+            format!("{} fused steps", fused),
+            reference::check_fused(&tm, &input, *fused, steps),
+        ));
+    }
+
+    let mut failed = false;
+    for (name, check) in checks {
+        match check {
+            Ok(steps) => println!("{}: {} steps agree with the reference", name, steps),
+            Err(why) => {
+                println!("{}: {}", name, why);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn bench(filename: &Path, runs: usize, max_steps: Option<u128>) -> ExitCode {
+    let tm = TuringMachine::new(filename);
+    let max_steps = max_steps.unwrap_or(u128::MAX);
+
+    let mut times = vec![];
+    let mut steps = 0;
+    for run in 1..=runs.max(1) {
+        let mut tm = tm.clone();
+        let start = Instant::now();
+        while tm.num_steps < max_steps && tm.step() {}
+        let elapsed = start.elapsed();
+        println!("Run {}: {} steps in {:.3?}", run, tm.num_steps, elapsed);
+        steps = tm.num_steps;
+        times.push(elapsed);
+    }
+
+    times.sort();
+    let rate = |time: std::time::Duration| steps as f64 / time.as_secs_f64();
+    let (fastest, median) = (times[0], times[times.len() / 2]);
+    println!(
+        "\nFastest: {:.3?}, {:.3e} steps / second",
+        fastest,
+        rate(fastest)
+    );
+    println!(
+        "Median:  {:.3?}, {:.3e} steps / second",
+        median,
+        rate(median)
+    );
+    ExitCode::SUCCESS
+}
 
-    println!("\nSimulation took {:.3?}", elapsed);
-    println!("{:.3e} Iterations / second", freq);
+#[test]
+fn test_run_crash_exit_code() {
+    let path = std::env::temp_dir().join(format!("crash_{}.turing", std::process::id()));
+    let run_file = |machine: &str, options: &[&str]| {
+        fs::write(&path, machine).unwrap();
+        let mut argv = vec!["turing", "run", path.to_str().unwrap(), "--quiet"];
+        argv.extend(options);
+        match Args::try_parse_from(argv).unwrap().command {
+            Some(Command::Run(args)) => run(&args),
+            command => panic!("{command:?}"),
+        }
+    };
+    let halted = |outcome: &Outcome| matches!(outcome, Outcome::Halted { .. });
 
-    tm.eval_busy_bever();
+    let outcome = run_file("A 1 -> Halt 1 R\n", &[]);
+    assert_eq!(outcome, Outcome::Crashed(HaltReason::Crash));
+    assert_eq!(outcome.exit_code(), ExitCode::from(7));
+    let left_walker = "A 0 -> B 1 L\nB 0 -> Halt 1 R\n";
+    assert!(halted(&run_file(left_walker, &[])));
+    assert_eq!(
+        run_file(left_walker, &["--left-edge", "reject"]),
+        Outcome::Crashed(HaltReason::Reject)
+    );
+    assert!(halted(&run_file("A 0 -> Reject 0 R\n", &[])));
+    fs::remove_file(&path).unwrap();
 }
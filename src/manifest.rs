@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{Seek, SeekFrom, Write},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+};
+
+const HEADER: &str = "# touring batch manifest";
+
+/// One of `count` equally sized parts of a range of machines, so several
+/// hosts can work on the same input without overlapping.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Shard {
+    pub index: u64,
+    pub count: u64,
+}
+
+impl Shard {
+    /// The part of `range` belonging to this shard.
+    pub fn range(&self, range: Range<u64>) -> Range<u64> {
+        let len = (range.end - range.start) as u128;
+        let start = range.start + (len * self.index as u128 / self.count as u128) as u64;
+        let end = range.start + (len * (self.index as u128 + 1) / self.count as u128) as u64;
+        start..end
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s.split_once('/').ok_or("expected K/N")?;
+        let index = index
+            .parse()
+            .map_err(|_| format!("invalid shard '{index}'"))?;
+        let count = count
+            .parse()
+            .map_err(|_| format!("invalid shard count '{count}'"))?;
+        if index >= count {
+            return Err(format!("shard {index} doesn't exist with {count} shards"));
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+/// Records the verdict for every machine of a batch run as soon as it is
+/// known, so an interrupted run can continue where it stopped.
+///
+/// The manifest is a text file starting with a header naming the input and
+/// the range of machines, followed by a line `<id> <verdict>` per machine.
+/// Lines are only appended, and a last line cut off by a crash is dropped
+/// when the manifest is opened again.
+pub struct Manifest {
+    file: File,
+    verdicts: BTreeMap<u64, String>,
+}
+
+impl Manifest {
+    /// Opens the manifest at `path`, or creates it if it doesn't exist. An
+    /// existing manifest has to belong to the same input and range.
+    pub fn open(path: &Path, input: &str, range: &Range<u64>) -> Result<Self, String> {
+        let header = format!(
+            "{HEADER}\n# input: {input}\n# range: {} {}\n",
+            range.start, range.end
+        );
+
+        let mut verdicts = BTreeMap::new();
+        let mut valid = header.len();
+        match fs::read_to_string(path) {
+            Ok(content) => {
+                if !content.starts_with(&header) {
+                    return Err(format!(
+                        "{} belongs to a different input or range",
+                        path.display()
+                    ));
+                }
+                for line in content[header.len()..].split_inclusive('\n') {
+                    if !line.ends_with('\n') {
+                        break;
+                    }
+                    let (id, verdict) = match line.trim_end().split_once(' ') {
+                        Some((id, verdict)) => (id, verdict),
+                        None => return Err(format!("invalid line '{}'", line.trim_end())),
+                    };
+                    let id = id
+                        .parse()
+                        .map_err(|_| format!("invalid machine id '{id}'"))?;
+                    verdicts.insert(id, verdict.to_string());
+                    valid += line.len();
+                }
+            }
+            Err(why) if why.kind() == std::io::ErrorKind::NotFound => {
+                fs::write(path, &header).map_err(|why| why.to_string())?;
+            }
+            Err(why) => return Err(why.to_string()),
+        }
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(|why| why.to_string())?;
+        file.set_len(valid as u64)
+            .and_then(|_| file.seek(SeekFrom::End(0)))
+            .map_err(|why| why.to_string())?;
+        Ok(Manifest { file, verdicts })
+    }
+
+    /// The verdict recorded for machine `id`, if it was already processed.
+    pub fn verdict(&self, id: u64) -> Option<&str> {
+        self.verdicts.get(&id).map(String::as_str)
+    }
+
+    /// Appends the verdict for machine `id` and writes it to disk.
+    pub fn record(&mut self, id: u64, verdict: &str) -> Result<(), String> {
+        writeln!(self.file, "{id} {verdict}")
+            .and_then(|_| self.file.sync_data())
+            .map_err(|why| why.to_string())?;
+        self.verdicts.insert(id, verdict.to_string());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_manifest() {
+    let path = std::env::temp_dir().join(format!("manifest_{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut manifest = Manifest::open(&path, "machines.txt 10", &(0..10)).unwrap();
+    manifest.record(0, "halts 6").unwrap();
+    manifest.record(3, "cycler").unwrap();
+    drop(manifest);
+
+    // Simulate a crash in the middle of writing a line.
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    write!(file, "4 undec").unwrap();
+    drop(file);
+
+    let mut manifest = Manifest::open(&path, "machines.txt 10", &(0..10)).unwrap();
+    assert_eq!(manifest.verdict(0), Some("halts 6"));
+    assert_eq!(manifest.verdict(3), Some("cycler"));
+    assert_eq!(manifest.verdict(4), None);
+    manifest.record(4, "undecided").unwrap();
+    drop(manifest);
+
+    let manifest = Manifest::open(&path, "machines.txt 10", &(0..10)).unwrap();
+    assert_eq!(manifest.verdict(4), Some("undecided"));
+    assert!(Manifest::open(&path, "machines.txt 10", &(0..5)).is_err());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_shard() {
+    let shards: Vec<Range<u64>> = (0..3)
+        .map(|index| Shard { index, count: 3 }.range(10..20))
+        .collect();
+    assert_eq!(shards, vec![10..13, 13..16, 16..20]);
+    assert_eq!("1/4".parse(), Ok(Shard { index: 1, count: 4 }));
+    assert!("4/4".parse::<Shard>().is_err());
+}
@@ -0,0 +1,146 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Upper bounds in seconds of the buckets of a [`Histogram`].
+pub const BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 60.0];
+
+/// How many observations fell at or below every bound of [`BUCKETS`], as
+/// Prometheus keeps histograms.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Histogram {
+    /// Observations per bucket, not yet added up.
+    counts: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    pub fn observe(&mut self, seconds: f64) {
+        if let Some(bucket) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.counts[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn write(&self, out: &mut String, name: &str, help: &str) {
+        header(out, name, help, "histogram");
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(self.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", self.count);
+        let _ = writeln!(out, "{name}_sum {}", self.sum);
+        let _ = writeln!(out, "{name}_count {}", self.count);
+    }
+}
+
+/// What a server did since it started, for monitoring a long-running
+/// service with Prometheus.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Metrics {
+    pub machines_loaded: u64,
+    /// Step and run requests, by why they stopped.
+    pub runs: BTreeMap<&'static str, u64>,
+    pub steps: u128,
+    /// Machines that halted, by the reason.
+    pub halted: BTreeMap<&'static str, u64>,
+    /// Responses by their status.
+    pub responses: BTreeMap<u16, u64>,
+    /// Time to answer a request.
+    pub latency: Histogram,
+}
+
+impl Metrics {
+    /// The metrics in the text format of Prometheus.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        header(
+            &mut out,
+            "turing_machines_loaded_total",
+            "Machines uploaded.",
+            "counter",
+        );
+        let _ = writeln!(out, "turing_machines_loaded_total {}", self.machines_loaded);
+        counters(
+            &mut out,
+            "turing_runs_total",
+            "Step and run requests by why they stopped.",
+            "stop",
+            &self.runs,
+        );
+        header(
+            &mut out,
+            "turing_steps_total",
+            "Steps simulated.",
+            "counter",
+        );
+        let _ = writeln!(out, "turing_steps_total {}", self.steps);
+        counters(
+            &mut out,
+            "turing_machines_halted_total",
+            "Machines that halted by the reason.",
+            "reason",
+            &self.halted,
+        );
+        counters(
+            &mut out,
+            "turing_responses_total",
+            "Responses by their status.",
+            "status",
+            &self.responses,
+        );
+        self.latency.write(
+            &mut out,
+            "turing_request_duration_seconds",
+            "Time to answer a request.",
+        );
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// A counter with a series per value of `label`.
+fn counters<K: std::fmt::Display>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    counts: &BTreeMap<K, u64>,
+) {
+    header(out, name, help, "counter");
+    for (value, count) in counts {
+        let _ = writeln!(out, "{name}{{{label}=\"{value}\"}} {count}");
+    }
+}
+
+#[test]
+fn test_metrics() {
+    let mut metrics = Metrics {
+        machines_loaded: 2,
+        steps: 107,
+        ..Metrics::default()
+    };
+    *metrics.runs.entry("halted").or_default() += 1;
+    *metrics.halted.entry("halt").or_default() += 1;
+    *metrics.responses.entry(200).or_default() += 3;
+    metrics.latency.observe(0.002);
+    metrics.latency.observe(0.002);
+    metrics.latency.observe(120.0);
+
+    let text = metrics.render();
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines.contains(&"# TYPE turing_steps_total counter"));
+    assert!(lines.contains(&"turing_machines_loaded_total 2"));
+    assert!(lines.contains(&"turing_runs_total{stop=\"halted\"} 1"));
+    assert!(lines.contains(&"turing_responses_total{status=\"200\"} 3"));
+    assert!(lines.contains(&"turing_request_duration_seconds_bucket{le=\"0.001\"} 0"));
+    assert!(lines.contains(&"turing_request_duration_seconds_bucket{le=\"0.005\"} 2"));
+    assert!(lines.contains(&"turing_request_duration_seconds_bucket{le=\"60\"} 2"));
+    assert!(lines.contains(&"turing_request_duration_seconds_bucket{le=\"+Inf\"} 3"));
+    assert!(lines.contains(&"turing_request_duration_seconds_count 3"));
+}
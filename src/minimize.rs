@@ -0,0 +1,142 @@
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use crate::turing::{Direction, HaltReason, Instruction, TapeEntry, TuringMachine};
+
+/// What a state does when reading a symbol, with the target state replaced
+/// by its equivalence class.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum Action {
+    Undefined,
+    Halt(TapeEntry, Direction, HaltReason),
+    Goto(TapeEntry, Direction, usize),
+}
+
+/// States reachable from the start state, in breadth-first order.
+pub fn reachable(tm: &TuringMachine) -> Vec<usize> {
+    let mut seen = vec![false; tm.states().len()];
+    let mut order = vec![];
+    let mut queue = VecDeque::from([0]);
+    seen[0] = true;
+    while let Some(state) = queue.pop_front() {
+        order.push(state);
+        for instruction in tm.instructions() {
+            if instruction.state != state {
+                continue;
+            }
+            if let Some(new_state) = instruction.new_state {
+                if !seen[new_state] {
+                    seen[new_state] = true;
+                    queue.push_back(new_state);
+                }
+            }
+        }
+    }
+    order
+}
+
+/// Returns an equivalent machine without unreachable states, where states
+/// that behave identically on every symbol are merged.
+///
+/// Equivalent states are found by partition refinement: starting from a
+/// single class, states are split until all states in a class write the
+/// same symbols, move in the same directions and go to the same classes.
+/// The remaining states are renumbered in breadth-first order from the
+/// start state and keep the name of their first representative.
+pub fn minimize(tm: &TuringMachine) -> TuringMachine {
+    let states = reachable(tm);
+    let mut symbols: Vec<TapeEntry> = tm.instructions().iter().map(|i| i.entry).collect();
+    symbols.sort();
+    symbols.dedup();
+
+    let instruction = |state: usize, entry: TapeEntry| {
+        tm.instructions()
+            .iter()
+            .find(|instruction| instruction.state == state && instruction.entry == entry)
+    };
+
+    let mut class: HashMap<usize, usize> = states.iter().map(|state| (*state, 0)).collect();
+    loop {
+        let mut signatures: HashMap<(usize, Vec<Action>), usize> = HashMap::new();
+        let mut refined = HashMap::new();
+        for state in &states {
+            let actions = symbols
+                .iter()
+                .map(|entry| match instruction(*state, *entry) {
+                    None => Action::Undefined,
+                    Some(i) => match i.new_state {
+                        None => Action::Halt(i.new_entry, i.direction, i.halt),
+                        Some(new_state) => {
+                            Action::Goto(i.new_entry, i.direction, class[&new_state])
+                        }
+                    },
+                })
+                .collect();
+            let next = signatures.len();
+            let id = *signatures.entry((class[state], actions)).or_insert(next);
+            refined.insert(*state, id);
+        }
+        let done = signatures.len() == class.values().max().map_or(0, |max| max + 1);
+        class = refined;
+        if done {
+            break;
+        }
+    }
+
+    // Renumber the classes in breadth-first order of their representatives.
+    let mut representatives: Vec<usize> = vec![];
+    let mut number = HashMap::new();
+    for state in &states {
+        if let Entry::Vacant(entry) = number.entry(class[state]) {
+            entry.insert(representatives.len());
+            representatives.push(*state);
+        }
+    }
+
+    let names = representatives
+        .iter()
+        .map(|state| tm.states()[*state].clone())
+        .collect();
+    let mut instructions = vec![];
+    for (new, state) in representatives.iter().enumerate() {
+        for instruction in tm.instructions().iter().filter(|i| i.state == *state) {
+            instructions.push(Instruction {
+                state: new,
+                new_state: instruction
+                    .new_state
+                    .map(|new_state| number[&class[&new_state]]),
+                ..instruction.clone()
+            });
+        }
+    }
+    TuringMachine::from_instructions(names, instructions)
+}
+
+#[test]
+fn test_minimize() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/minimize/redundant.turing"));
+    let mut minimized = minimize(&tm);
+
+    assert_eq!(minimized.states().len(), 2);
+    assert_eq!(
+        minimized.to_turing(),
+        "A 0 -> B    1 R\nA 1 -> B    1 L\nB 0 -> A    1 L\nB 1 -> Halt 1 R"
+    );
+
+    while minimized.step() {}
+    let (ones, zeros, steps) = minimized.eval_busy_bever();
+    assert_eq!((ones, zeros, steps), (4, 0, 6));
+}
+
+#[test]
+fn test_minimize_keeps_minimal_machine() {
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new(
+        "examples/busy_bever/busy_bever_5_best_currently_known.turing",
+    ));
+    let minimized = minimize(&tm);
+
+    assert_eq!(minimized.to_turing(), tm.to_turing());
+}
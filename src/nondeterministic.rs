@@ -0,0 +1,189 @@
+use std::{collections::VecDeque, fs, path::Path};
+
+use crate::{
+    json::Json,
+    turing::{HaltReason, TuringMachine},
+};
+
+/// How a search through the branches of a non-deterministic machine
+/// ended.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Search {
+    /// A branch accepted after `steps` steps, taking `choices` at its
+    /// choice points.
+    Accepted { steps: u128, choices: Vec<usize> },
+    /// Every branch rejected within the step budget.
+    Rejected,
+    /// No branch accepted, but some still ran after the step budget.
+    StepLimit,
+}
+
+/// Follows every branch of `tm` breadth first, for up to `max_steps` steps
+/// each, until one accepts by halting in `Accept` or `Halt`. Branches take
+/// turns at their choice points, so one that never halts doesn't block the
+/// others.
+///
+/// A choice point is a configuration with several matching instructions.
+/// The choice recorded for it is the position of the instruction taken
+/// among [`TuringMachine::choices`], so it stays the same when the
+/// machine file gets instructions for other states. The number of branches
+/// can grow exponentially with the steps.
+pub fn search(tm: &TuringMachine, max_steps: u128) -> Search {
+    let mut tm = tm.clone();
+    tm.set_reject_undefined(true);
+    let mut branches = VecDeque::from([(tm, vec![])]);
+    let mut cut = false;
+    while let Some((mut tm, choices)) = branches.pop_front() {
+        loop {
+            if tm.is_halted() {
+                if accepted(&tm) {
+                    return Search::Accepted {
+                        steps: tm.num_steps,
+                        choices,
+                    };
+                }
+                break;
+            }
+            if tm.num_steps >= max_steps {
+                cut = true;
+                break;
+            }
+            let instructions = tm.choices();
+            match instructions.as_slice() {
+                // Rejects, as there is no instruction.
+                [] => {
+                    tm.step();
+                }
+                [instruction] => tm.step_with(*instruction),
+                instructions => {
+                    for (choice, instruction) in instructions.iter().enumerate() {
+                        let mut branch = tm.clone();
+                        branch.step_with(*instruction);
+                        let mut path = choices.clone();
+                        path.push(choice);
+                        branches.push_back((branch, path));
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    if cut {
+        Search::StepLimit
+    } else {
+        Search::Rejected
+    }
+}
+
+/// Replays a path of `tm` through its choice points, calling `each` after
+/// every step, until the machine halts or `max_steps` steps were taken.
+pub fn replay(
+    tm: &mut TuringMachine,
+    choices: &[usize],
+    max_steps: u128,
+    mut each: impl FnMut(&TuringMachine),
+) -> Result<(), String> {
+    tm.set_reject_undefined(true);
+    let mut choices = choices.iter();
+    while !tm.is_halted() && tm.num_steps < max_steps {
+        let instructions = tm.choices();
+        match instructions.as_slice() {
+            [] => {
+                tm.step();
+            }
+            [instruction] => tm.step_with(*instruction),
+            instructions => {
+                let choice = *choices
+                    .next()
+                    .ok_or_else(|| format!("no choice left for step {}", tm.num_steps + 1))?;
+                let instruction = instructions.get(choice).ok_or_else(|| {
+                    format!(
+                        "choice {} at step {}, but there are only {} instructions",
+                        choice,
+                        tm.num_steps + 1,
+                        instructions.len()
+                    )
+                })?;
+                tm.step_with(*instruction);
+            }
+        }
+        each(tm);
+    }
+    match choices.len() {
+        0 => Ok(()),
+        left => Err(format!("{} choices left after the machine stopped", left)),
+    }
+}
+
+/// Writes an accepting path as `{"steps": 12, "choices": [0, 2, 1]}`.
+pub fn write_path(path: &Path, steps: u128, choices: &[usize]) -> Result<(), String> {
+    let json = Json::object([
+        ("steps", steps.into()),
+        (
+            "choices",
+            Json::Array(choices.iter().map(|choice| Json::from(*choice)).collect()),
+        ),
+    ]);
+    fs::write(path, format!("{}\n", json)).map_err(|why| why.to_string())
+}
+
+/// Reads the choices of a path written by [`write_path`].
+pub fn read_path(path: &Path) -> Result<Vec<usize>, String> {
+    let content = fs::read_to_string(path).map_err(|why| why.to_string())?;
+    Json::parse(&content)?
+        .field("choices")?
+        .as_array()
+        .ok_or("choices isn't an array")?
+        .iter()
+        .map(|choice| {
+            choice
+                .as_int()
+                .and_then(|choice| usize::try_from(choice).ok())
+                .ok_or_else(|| format!("invalid choice {choice}"))
+        })
+        .collect()
+}
+
+/// Whether `tm` halted in a way that accepts.
+fn accepted(tm: &TuringMachine) -> bool {
+    matches!(tm.halt_reason, Some(HaltReason::Accept | HaltReason::Halt))
+}
+
+#[test]
+fn test_nondeterministic() {
+    // Guesses where the word ends: it accepts words of 1 and 2 ending
+    // with 1.
+    let tm = TuringMachine::parse(
+        "A 2 -> A 2 R\n\
+         A 1 -> A 1 R\n\
+         A 1 -> B 1 R\n\
+         B 0 -> Accept 0 R",
+    )
+    .unwrap();
+    let mut word = tm.clone();
+    word.set_input(&[1, 2, 1]);
+    let Search::Accepted { steps, choices } = search(&word, 100) else {
+        panic!("121 isn't accepted");
+    };
+    assert_eq!((steps, choices.as_slice()), (4, [0, 1].as_slice()));
+
+    let mut replayed = word.clone();
+    let mut states = vec![];
+    replay(&mut replayed, &choices, 100, |tm| states.push(tm.state())).unwrap();
+    assert_eq!(states, [Some(0), Some(0), Some(1), None]);
+    assert_eq!(replayed.halt_reason, Some(HaltReason::Accept));
+    assert!(replay(&mut word.clone(), &[0, 0, 0], 100, |_| {}).is_err());
+    assert!(replay(&mut word.clone(), &[2], 100, |_| {}).is_err());
+
+    let mut word = tm.clone();
+    word.set_input(&[1, 2]);
+    assert_eq!(search(&word, 100), Search::Rejected);
+    // Without an end of the word, some branch always keeps running.
+    let looping = TuringMachine::parse("A 0 -> A 0 R\nA 0 -> B 0 R").unwrap();
+    assert_eq!(search(&looping, 10), Search::StepLimit);
+
+    let path = std::env::temp_dir().join(format!("path_{}.json", std::process::id()));
+    write_path(&path, steps, &choices).unwrap();
+    assert_eq!(read_path(&path), Ok(choices));
+    fs::remove_file(&path).unwrap();
+}
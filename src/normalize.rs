@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use crate::turing::{Direction, Instruction, TapeEntry, TuringMachine};
+
+/// Most steps run from the blank tape to find the first-visit order, after
+/// which the remaining states and symbols are ordered by the table.
+const MAX_STEPS: u128 = 100_000;
+
+/// The order in which states and symbols are first met, and whether the
+/// machine first moves left.
+struct Order {
+    states: Vec<usize>,
+    symbols: Vec<TapeEntry>,
+    mirrored: Option<bool>,
+}
+
+impl Order {
+    fn visit(&mut self, state: Option<usize>, entry: TapeEntry) {
+        if let Some(state) = state.filter(|state| !self.states.contains(state)) {
+            self.states.push(state);
+        }
+        if !self.symbols.contains(&entry) {
+            self.symbols.push(entry);
+        }
+    }
+}
+
+/// Returns the machine in tree normal form, so that machines only
+/// differing in the names of their states and symbols, or mirrored, give
+/// the same result.
+///
+/// The machine is run from the blank tape, and states and non-blank
+/// symbols are numbered in the order they are first entered and written,
+/// with the start state `A` and the blank `0`. If the first move goes left,
+/// all moves are mirrored. States and symbols the run doesn't reach in
+/// [`MAX_STEPS`] steps follow in breadth-first order of the transition
+/// table. Unreachable states are dropped, and states are named `A` to `Z`,
+/// then `Q26` and on.
+pub fn normalize(tm: &TuringMachine) -> TuringMachine {
+    let tm = tm.with_zero_blank();
+    let mut order = Order {
+        states: vec![],
+        symbols: vec![],
+        mirrored: None,
+    };
+    order.visit(tm.state(), 0);
+
+    let mut run = tm.clone();
+    while run.num_steps < MAX_STEPS && run.step() {
+        let instruction = &tm.instructions()[run.last_instruction().unwrap()];
+        order.visit(instruction.new_state, instruction.new_entry);
+        if order.mirrored.is_none() {
+            order.mirrored = Some(instruction.direction == Direction::Left);
+        }
+    }
+
+    // Complete the order from the table, one state at a time.
+    let mut next = 0;
+    while next < order.states.len() {
+        let state = order.states[next];
+        let mut instructions: Vec<&Instruction> = tm
+            .instructions()
+            .iter()
+            .filter(|instruction| instruction.state == state)
+            .collect();
+        instructions.sort_by_key(|instruction| {
+            let index = order.symbols.iter().position(|s| *s == instruction.entry);
+            (index.unwrap_or(usize::MAX), instruction.entry)
+        });
+        for instruction in instructions {
+            order.visit(None, instruction.entry);
+            order.visit(instruction.new_state, instruction.new_entry);
+        }
+        next += 1;
+    }
+
+    let state: HashMap<usize, usize> = order
+        .states
+        .iter()
+        .enumerate()
+        .map(|(index, state)| (*state, index))
+        .collect();
+    let symbol: HashMap<TapeEntry, TapeEntry> = order
+        .symbols
+        .iter()
+        .enumerate()
+        .map(|(index, symbol)| (*symbol, index as TapeEntry))
+        .collect();
+    let direction = |direction: Direction| match order.mirrored {
+        Some(true) => direction.mirrored(),
+        _ => direction,
+    };
+    let mut instructions: Vec<Instruction> = tm
+        .instructions()
+        .iter()
+        .filter(|instruction| state.contains_key(&instruction.state))
+        .map(|instruction| Instruction {
+            state: state[&instruction.state],
+            entry: symbol[&instruction.entry],
+            new_state: instruction.new_state.map(|new_state| state[&new_state]),
+            halt: instruction.halt,
+            new_entry: symbol[&instruction.new_entry],
+            direction: direction(instruction.direction),
+        })
+        .collect();
+    instructions.sort_by_key(|instruction| (instruction.state, instruction.entry));
+    let names = (0..order.states.len())
+        .map(|index| match index < 26 {
+            true => ((b'A' + index as u8) as char).to_string(),
+            false => format!("Q{index}"),
+        })
+        .collect();
+    TuringMachine::from_instructions(names, instructions)
+}
+
+#[test]
+fn test_normalize() {
+    let tm =
+        TuringMachine::parse("A 0 -> B    1 R\nA 1 -> B    1 L\nB 0 -> A    1 L\nB 1 -> Halt 1 R")
+            .unwrap();
+    assert_eq!(normalize(&tm).to_turing(), tm.to_turing());
+
+    // Renamed states, another non-blank symbol, mirrored moves and an
+    // unreachable state.
+    let renamed = TuringMachine::parse(
+        "X 0 -> Y 2 L\nX 2 -> Y 2 R\nY 0 -> X 2 R\nY 2 -> Halt 2 L\nUnused 0 -> Y 0 R",
+    )
+    .unwrap();
+    assert_eq!(normalize(&renamed).to_turing(), tm.to_turing());
+}
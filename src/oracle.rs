@@ -0,0 +1,155 @@
+use std::{collections::BTreeSet, fs, path::Path};
+
+use crate::turing::{self, TapeEntry, TuringMachine, Verdict, DEFAULT_ENTRY};
+
+/// Answers the queries of a machine with an oracle, see [`run`].
+pub trait Oracle {
+    /// Whether `word` is in the set the oracle decides.
+    fn ask(&mut self, word: &[TapeEntry]) -> Result<bool, String>;
+}
+
+/// Any closure deciding words is an oracle.
+impl<F: FnMut(&[TapeEntry]) -> bool> Oracle for F {
+    fn ask(&mut self, word: &[TapeEntry]) -> Result<bool, String> {
+        Ok(self(word))
+    }
+}
+
+/// An oracle for a finite set of words.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Words(pub BTreeSet<Vec<TapeEntry>>);
+
+impl Words {
+    /// Reads the words of a file with one word per line, in the format of
+    /// `--input`. An empty line is the empty word.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|why| why.to_string())?;
+        content
+            .lines()
+            .map(|line| turing::parse_word(line.trim()))
+            .collect::<Result<_, _>>()
+            .map(Words)
+    }
+}
+
+impl Oracle for Words {
+    fn ask(&mut self, word: &[TapeEntry]) -> Result<bool, String> {
+        Ok(self.0.contains(word))
+    }
+}
+
+/// An oracle deciding words with another machine, which has to accept or
+/// reject each query within `max_steps` steps.
+#[derive(Debug, Clone)]
+pub struct Machine {
+    pub tm: TuringMachine,
+    pub max_steps: u128,
+}
+
+impl Oracle for Machine {
+    fn ask(&mut self, word: &[TapeEntry]) -> Result<bool, String> {
+        let mut tm = self.tm.clone();
+        tm.set_reject_undefined(true);
+        tm.set_input(word);
+        match tm.run_word(self.max_steps) {
+            Verdict::Accept => Ok(true),
+            Verdict::Reject => Ok(false),
+            Verdict::StepLimit => Err(format!(
+                "the oracle machine didn't decide {:?} within {} steps",
+                word, self.max_steps
+            )),
+        }
+    }
+}
+
+/// The word a query asks about: the cells from the head up to the first
+/// blank to the right of it.
+pub fn query(tm: &TuringMachine) -> Vec<TapeEntry> {
+    tm.tape()
+        .range(tm.head()..)
+        .take_while(|entry| **entry != DEFAULT_ENTRY)
+        .copied()
+        .collect()
+}
+
+/// Runs `tm` for up to `max_steps` steps, asking `oracle` about the
+/// [`query`] word whenever the machine is in its query state and going on
+/// in the state for the answer (see [`TuringMachine::set_oracle`]).
+/// Answering doesn't count as a step. Gives the verdict and the number of
+/// queries asked.
+pub fn run(
+    tm: &mut TuringMachine,
+    oracle: &mut dyn Oracle,
+    max_steps: u128,
+) -> Result<(Verdict, u64), String> {
+    let states = tm.oracle().ok_or("the machine has no %oracle states")?;
+    let mut queries = 0;
+    loop {
+        if tm.state() == Some(states.query) {
+            let yes = oracle.ask(&query(tm))?;
+            tm.set_state(if yes { states.yes } else { states.no });
+            queries += 1;
+        }
+        if tm.num_steps >= max_steps || !tm.step() {
+            break;
+        }
+    }
+    Ok((tm.run_word(max_steps), queries))
+}
+
+#[test]
+fn test_oracle() {
+    // Appends 2 1 to the input, asks whether that is in the oracle set and
+    // accepts if it is.
+    let tm = || {
+        let mut tm = TuringMachine::parse(
+            "%oracle Q Yes No\n\
+             A 1 -> B 1 R\n\
+             B 0 -> C 2 R\n\
+             C 0 -> D 1 L\n\
+             D 2 -> Q 2 L\n\
+             Yes 1 -> Accept 1 R\n\
+             No 1 -> Reject 1 R",
+        )
+        .unwrap();
+        tm.set_input(&[1]);
+        tm
+    };
+    let mut words = Words([vec![1, 2, 1]].into());
+    assert_eq!(run(&mut tm(), &mut words, 100), Ok((Verdict::Accept, 1)));
+    let mut nothing = Words::default();
+    assert_eq!(run(&mut tm(), &mut nothing, 100), Ok((Verdict::Reject, 1)));
+
+    let mut asked = vec![];
+    let mut closure = |word: &[TapeEntry]| {
+        asked.push(word.to_vec());
+        word.len() == 3
+    };
+    assert_eq!(run(&mut tm(), &mut closure, 100), Ok((Verdict::Accept, 1)));
+    assert_eq!(asked, [vec![1, 2, 1]]);
+
+    // Another machine as the oracle: words starting with 1 and 2.
+    let decider = TuringMachine::parse("A 1 -> B 1 R\nB 2 -> Accept 2 R").unwrap();
+    let mut machine = Machine {
+        tm: decider.clone(),
+        max_steps: 10,
+    };
+    assert_eq!(run(&mut tm(), &mut machine, 100), Ok((Verdict::Accept, 1)));
+    let mut looping = Machine {
+        tm: TuringMachine::parse("A 1 -> A 1 R\nA 2 -> A 2 R\nA 0 -> A 0 R").unwrap(),
+        max_steps: 10,
+    };
+    assert!(run(&mut tm(), &mut looping, 100).is_err());
+    assert!(run(&mut decider.clone(), &mut words, 100).is_err());
+
+    assert!(TuringMachine::parse("%oracle Q Yes\nA 0 -> Q 0 R").is_err());
+    assert!(TuringMachine::parse("%oracle Q Yes No\nA 0 -> Q 0 R").is_err());
+
+    let path = std::env::temp_dir().join(format!("oracle_{}.txt", std::process::id()));
+    fs::write(&path, "121\n\n0 12\n").unwrap();
+    assert_eq!(
+        Words::read(&path),
+        Ok(Words([vec![1, 2, 1], vec![], vec![0, 12]].into()))
+    );
+    fs::remove_file(&path).unwrap();
+}
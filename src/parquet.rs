@@ -0,0 +1,361 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::experiment::Row;
+
+const MAGIC: &[u8] = b"PAR1";
+/// Rows buffered before they are written out as a row group.
+const ROW_GROUP: usize = 1 << 20;
+
+// Physical types, repetitions and encodings of the Parquet format.
+const INT64: i32 = 2;
+const DOUBLE: i32 = 5;
+const BYTE_ARRAY: i32 = 6;
+const REQUIRED: i32 = 0;
+const OPTIONAL: i32 = 1;
+const UTF8: i32 = 0;
+const PLAIN: i32 = 0;
+const RLE: i32 = 3;
+
+/// The Thrift compact protocol the metadata of Parquet files is written in.
+#[derive(Default)]
+struct Thrift {
+    bytes: Vec<u8>,
+    /// The last field id of every struct being written.
+    fields: Vec<i16>,
+}
+
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+impl Thrift {
+    fn varint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.bytes.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8);
+    }
+
+    fn zigzag(&mut self, n: i64) {
+        self.varint(((n << 1) ^ (n >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.fields.last_mut().expect("fields are in a struct");
+        let delta = id - *last;
+        *last = id;
+        match delta {
+            1..=15 => self.bytes.push((delta as u8) << 4 | kind),
+            _ => {
+                self.bytes.push(kind);
+                self.zigzag(id as i64);
+            }
+        }
+    }
+
+    fn begin(&mut self) {
+        self.fields.push(0);
+    }
+
+    fn end(&mut self) {
+        self.bytes.push(0);
+        self.fields.pop();
+    }
+
+    fn list(&mut self, id: i16, kind: u8, len: usize) {
+        self.field(id, LIST);
+        match len {
+            0..=14 => self.bytes.push((len as u8) << 4 | kind),
+            _ => {
+                self.bytes.push(0xf0 | kind);
+                self.varint(len as u64);
+            }
+        }
+    }
+
+    fn i32(&mut self, id: i16, n: i32) {
+        self.field(id, I32);
+        self.zigzag(n as i64);
+    }
+
+    fn i64(&mut self, id: i16, n: i64) {
+        self.field(id, I64);
+        self.zigzag(n);
+    }
+
+    fn string(&mut self, id: i16, s: &str) {
+        self.field(id, BINARY);
+        self.varint(s.len() as u64);
+        self.bytes.extend(s.as_bytes());
+    }
+}
+
+/// A column of an experiment log.
+struct Column {
+    name: &'static str,
+    kind: i32,
+    optional: bool,
+    /// The values of the buffered rows in the plain encoding, and for
+    /// optional columns whether each row has one.
+    values: Vec<u8>,
+    defined: Vec<bool>,
+}
+
+impl Column {
+    fn new(name: &'static str, kind: i32, optional: bool) -> Self {
+        Column {
+            name,
+            kind,
+            optional,
+            values: vec![],
+            defined: vec![],
+        }
+    }
+
+    fn int(&mut self, n: i64) {
+        self.values.extend(n.to_le_bytes());
+    }
+
+    fn string(&mut self, s: &str) {
+        self.values.extend((s.len() as u32).to_le_bytes());
+        self.values.extend(s.as_bytes());
+    }
+
+    /// The definition levels as a bit-packed run of the RLE hybrid
+    /// encoding, prefixed with its length.
+    fn levels(&self) -> Vec<u8> {
+        let mut packed = vec![];
+        let groups = self.defined.len().div_ceil(8);
+        let mut header = Thrift::default();
+        header.varint((groups as u64) << 1 | 1);
+        packed.extend(header.bytes);
+        for group in self.defined.chunks(8) {
+            packed.push(
+                group
+                    .iter()
+                    .enumerate()
+                    .map(|(bit, defined)| (*defined as u8) << bit)
+                    .sum(),
+            );
+        }
+        let mut levels = (packed.len() as u32).to_le_bytes().to_vec();
+        levels.extend(packed);
+        levels
+    }
+}
+
+/// Where a column chunk was written, for the metadata.
+struct Chunk {
+    offset: u64,
+    size: u64,
+    values: usize,
+}
+
+/// Writes the rows of an experiment log to a Parquet file, uncompressed
+/// and in the plain encoding, with the same columns as the CSV log. Steps
+/// beyond the 64 bits of Parquet integers are cut off at the largest one.
+pub struct Writer {
+    file: BufWriter<File>,
+    offset: u64,
+    columns: Vec<Column>,
+    rows: usize,
+    row_groups: Vec<(usize, Vec<Chunk>)>,
+}
+
+fn io(why: std::io::Error) -> String {
+    why.to_string()
+}
+
+impl Writer {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let mut file = BufWriter::new(File::create(path).map_err(io)?);
+        file.write_all(MAGIC).map_err(io)?;
+        Ok(Writer {
+            file,
+            offset: MAGIC.len() as u64,
+            columns: vec![
+                Column::new("id", INT64, false),
+                Column::new("machine", BYTE_ARRAY, false),
+                Column::new("verdict", BYTE_ARRAY, false),
+                Column::new("decider", BYTE_ARRAY, true),
+                Column::new("steps", INT64, false),
+                Column::new("seconds", DOUBLE, false),
+                Column::new("peak_memory", INT64, true),
+            ],
+            rows: 0,
+            row_groups: vec![],
+        })
+    }
+
+    pub fn write(&mut self, row: &Row) -> Result<(), String> {
+        let [id, machine, verdict, decider, steps, seconds, peak_memory] = &mut self.columns[..]
+        else {
+            unreachable!("the log has seven columns");
+        };
+        id.int(row.id as i64);
+        machine.string(&row.machine);
+        verdict.string(row.verdict);
+        decider.defined.push(!row.decider.is_empty());
+        if !row.decider.is_empty() {
+            decider.string(&row.decider);
+        }
+        steps.int(i64::try_from(row.steps).unwrap_or(i64::MAX));
+        seconds.values.extend(row.seconds.to_le_bytes());
+        peak_memory.defined.push(row.peak_memory.is_some());
+        if let Some(bytes) = row.peak_memory {
+            peak_memory.int(bytes as i64);
+        }
+        self.rows += 1;
+        if self.rows == ROW_GROUP {
+            self.row_group()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered rows as a row group with a page per column.
+    fn row_group(&mut self) -> Result<(), String> {
+        let mut chunks = vec![];
+        for column in &mut self.columns {
+            let mut page = match column.optional {
+                true => column.levels(),
+                false => vec![],
+            };
+            page.extend(&column.values);
+            let mut header = Thrift::default();
+            header.begin();
+            header.i32(1, 0);
+            header.i32(2, page.len() as i32);
+            header.i32(3, page.len() as i32);
+            header.field(5, STRUCT);
+            header.begin();
+            header.i32(1, self.rows as i32);
+            header.i32(2, PLAIN);
+            header.i32(3, RLE);
+            header.i32(4, RLE);
+            header.end();
+            header.end();
+            self.file.write_all(&header.bytes).map_err(io)?;
+            self.file.write_all(&page).map_err(io)?;
+            let size = (header.bytes.len() + page.len()) as u64;
+            chunks.push(Chunk {
+                offset: self.offset,
+                size,
+                values: self.rows,
+            });
+            self.offset += size;
+            column.values.clear();
+            column.defined.clear();
+        }
+        self.row_groups.push((self.rows, chunks));
+        self.rows = 0;
+        Ok(())
+    }
+
+    /// Writes the last rows and the metadata, without which the file can't
+    /// be read.
+    pub fn finish(&mut self) -> Result<(), String> {
+        if self.rows > 0 || self.row_groups.is_empty() {
+            self.row_group()?;
+        }
+        let mut meta = Thrift::default();
+        meta.begin();
+        meta.i32(1, 1);
+        meta.list(2, STRUCT, self.columns.len() + 1);
+        meta.begin();
+        meta.string(4, "schema");
+        meta.i32(5, self.columns.len() as i32);
+        meta.end();
+        for column in &self.columns {
+            meta.begin();
+            meta.i32(1, column.kind);
+            meta.i32(3, if column.optional { OPTIONAL } else { REQUIRED });
+            meta.string(4, column.name);
+            if column.kind == BYTE_ARRAY {
+                meta.i32(6, UTF8);
+            }
+            meta.end();
+        }
+        let rows: usize = self.row_groups.iter().map(|(rows, _)| rows).sum();
+        meta.i64(3, rows as i64);
+        meta.list(4, STRUCT, self.row_groups.len());
+        for (rows, chunks) in &self.row_groups {
+            meta.begin();
+            meta.list(1, STRUCT, chunks.len());
+            for (column, chunk) in self.columns.iter().zip(chunks) {
+                meta.begin();
+                meta.i64(2, chunk.offset as i64);
+                meta.field(3, STRUCT);
+                meta.begin();
+                meta.i32(1, column.kind);
+                meta.list(2, I32, 2);
+                meta.zigzag(PLAIN as i64);
+                meta.zigzag(RLE as i64);
+                meta.list(3, BINARY, 1);
+                meta.varint(column.name.len() as u64);
+                meta.bytes.extend(column.name.as_bytes());
+                meta.i32(4, 0);
+                meta.i64(5, chunk.values as i64);
+                meta.i64(6, chunk.size as i64);
+                meta.i64(7, chunk.size as i64);
+                meta.i64(9, chunk.offset as i64);
+                meta.end();
+                meta.end();
+            }
+            let size: u64 = chunks.iter().map(|chunk| chunk.size).sum();
+            meta.i64(2, size as i64);
+            meta.i64(3, *rows as i64);
+            meta.end();
+        }
+        meta.string(6, "touring");
+        meta.end();
+        self.file.write_all(&meta.bytes).map_err(io)?;
+        self.file
+            .write_all(&(meta.bytes.len() as u32).to_le_bytes())
+            .map_err(io)?;
+        self.file.write_all(MAGIC).map_err(io)?;
+        self.file.flush().map_err(io)
+    }
+}
+
+#[test]
+fn test_parquet() {
+    let mut thrift = Thrift::default();
+    thrift.begin();
+    thrift.i32(1, -1);
+    thrift.i64(20, 300);
+    thrift.end();
+    assert_eq!(thrift.bytes, [0x15, 0x01, 0x06, 0x28, 0xd8, 0x04, 0x00]);
+
+    let mut column = Column::new("decider", BYTE_ARRAY, true);
+    column.defined = vec![true, false, true, true, false, false, false, false, true];
+    assert_eq!(column.levels(), [3, 0, 0, 0, 0x05, 0b1101, 1]);
+
+    let path = std::env::temp_dir().join(format!("parquet_{}.parquet", std::process::id()));
+    let mut writer = Writer::create(&path).unwrap();
+    for id in 0..3 {
+        writer
+            .write(&Row {
+                id,
+                machine: "1RB1LB_1LA1RZ".to_string(),
+                verdict: "halts",
+                decider: "simulation".to_string(),
+                steps: 6,
+                seconds: 0.5,
+                peak_memory: None,
+            })
+            .unwrap();
+    }
+    writer.finish().unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(bytes.starts_with(MAGIC) && bytes.ends_with(MAGIC));
+    let footer = u32::from_le_bytes(bytes[bytes.len() - 8..bytes.len() - 4].try_into().unwrap());
+    assert!((footer as usize) < bytes.len());
+    std::fs::remove_file(&path).unwrap();
+}
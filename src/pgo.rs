@@ -0,0 +1,123 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{
+    json::Json,
+    turing::{TapeEntry, TuringMachine},
+};
+
+/// How often a run took every transition, written by `run --profile` and
+/// read by `run --pgo` to lay out the transition table of the next runs.
+/// Transitions are named by their state and symbol, so a profile still
+/// applies to a machine with its states numbered differently.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Profile {
+    pub counts: BTreeMap<(String, TapeEntry), u128>,
+}
+
+impl Profile {
+    /// The profile of a run of `tm` that took the instruction at every
+    /// index of [`TuringMachine::instructions`] as often as in `counts`.
+    pub fn new(tm: &TuringMachine, counts: &[u128]) -> Self {
+        let mut profile = Profile::default();
+        for (instruction, count) in tm.instructions().iter().zip(counts) {
+            let state = tm.states()[instruction.state].clone();
+            *profile
+                .counts
+                .entry((state, instruction.entry))
+                .or_default() += count;
+        }
+        profile
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object([(
+            "transitions",
+            Json::Array(
+                self.counts
+                    .iter()
+                    .map(|((state, entry), count)| {
+                        Json::object([
+                            ("state", state.as_str().into()),
+                            ("entry", (*entry).into()),
+                            ("count", (*count).into()),
+                        ])
+                    })
+                    .collect(),
+            ),
+        )])
+    }
+
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let mut profile = Profile::default();
+        let transitions = json
+            .field("transitions")?
+            .as_array()
+            .ok_or("field 'transitions' is not an array")?;
+        for transition in transitions {
+            let state = transition.str_field("state")?.to_string();
+            let entry = TapeEntry::try_from(transition.int_field("entry")?)
+                .map_err(|_| "invalid symbol in a transition")?;
+            let count = transition.u128_field("count")?;
+            *profile.counts.entry((state, entry)).or_default() += count;
+        }
+        Ok(profile)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|why| why.to_string())?;
+        Profile::from_json(&Json::parse(&text)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, format!("{}\n", self.to_json())).map_err(|why| why.to_string())
+    }
+
+    /// The states of `tm` from the most to the least often run, the ones
+    /// the profile doesn't know last in their order.
+    pub fn order(&self, tm: &TuringMachine) -> Vec<usize> {
+        let mut heat = vec![0u128; tm.states().len()];
+        for ((state, _), count) in &self.counts {
+            if let Some(index) = tm.states().iter().position(|name| name == state) {
+                heat[index] += count;
+            }
+        }
+        let mut order: Vec<usize> = (0..heat.len()).collect();
+        order.sort_by_key(|state| std::cmp::Reverse(heat[*state]));
+        order
+    }
+
+    /// Lays out the transition table of `tm` with the hottest states next
+    /// to each other at its start, where they share cache lines.
+    pub fn apply(&self, tm: &mut TuringMachine) {
+        tm.reorder_states(&self.order(tm));
+    }
+}
+
+#[test]
+fn test_pgo() {
+    use crate::bbchallenge;
+
+    let mut tm = bbchallenge::parse_machine("1RB1LB_1LA0LC_1RZ1LD_1RD0RA").unwrap();
+    let mut counts = vec![0; tm.instructions().len()];
+    while tm.step() {
+        counts[tm.last_instruction().unwrap()] += 1;
+    }
+    let profile = Profile::new(&tm, &counts);
+    assert_eq!(profile.counts.values().sum::<u128>(), 107);
+    let json = Json::parse(&profile.to_json().to_string()).unwrap();
+    assert_eq!(Profile::from_json(&json).as_ref(), Ok(&profile));
+
+    // The same run with the hottest states first.
+    let mut fresh = bbchallenge::parse_machine("1RB1LB_1LA0LC_1RZ1LD_1RD0RA").unwrap();
+    let order = profile.order(&fresh);
+    assert_ne!(order, [0, 1, 2, 3]);
+    profile.apply(&mut fresh);
+    assert_eq!(fresh.states()[0], tm.states()[order[0]]);
+    assert_eq!(
+        fresh.state(),
+        Some(order.iter().position(|s| *s == 0).unwrap())
+    );
+    while fresh.step() {}
+    assert_eq!(fresh.num_steps, 107);
+    assert_eq!(fresh.eval_busy_bever(), tm.eval_busy_bever());
+}
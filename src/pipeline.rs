@@ -0,0 +1,336 @@
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::atomic::AtomicBool,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bouncer, ctl,
+    decide::{self, Analyses, Decision, Proof},
+    turing::TuringMachine,
+    window,
+};
+
+/// A decider and its budget, as run by a [`Pipeline`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Stage {
+    /// Steps simulated only to find machines that halt.
+    Simulate(u128),
+    /// Steps simulated while looking for cycles.
+    Cycler(u128),
+    /// Steps simulated while looking for translated cycles.
+    TranslatedCycler(u128),
+    /// Steps simulated while looking for repeated windows around the head,
+    /// see [`window::find`].
+    Window(u128),
+    /// Steps simulated while looking for bouncers.
+    Bouncer(u128),
+    /// Longest n-grams tried for a closed tape language.
+    ClosedTapeLanguage(usize),
+    /// Number of steps searched backwards from the halting transitions.
+    BackwardReasoning(usize),
+}
+
+impl Stage {
+    /// The name of the stage in a pipeline spec.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Simulate(_) => "sim",
+            Stage::Cycler(_) => "cycler",
+            Stage::TranslatedCycler(_) => "tcycler",
+            Stage::Window(_) => "window",
+            Stage::Bouncer(_) => "bouncer",
+            Stage::ClosedTapeLanguage(_) => "ctl",
+            Stage::BackwardReasoning(_) => "backward",
+        }
+    }
+
+    /// Name of the decider of the stage as in holdouts, like
+    /// [`Proof::decider`] but `window` for the windows around the head, none
+    /// for the plain simulation.
+    pub fn decider(&self) -> Option<&'static str> {
+        match self {
+            Stage::Simulate(_) => None,
+            Stage::Cycler(_) => Some("cycler"),
+            Stage::TranslatedCycler(_) => Some("translated_cycler"),
+            Stage::Window(_) => Some("window"),
+            Stage::Bouncer(_) => Some("bouncer"),
+            Stage::ClosedTapeLanguage(_) => Some("closed_tape_language"),
+            Stage::BackwardReasoning(_) => Some("backward_reasoning"),
+        }
+    }
+
+    fn budget(&self) -> u128 {
+        match self {
+            Stage::Simulate(budget)
+            | Stage::Cycler(budget)
+            | Stage::TranslatedCycler(budget)
+            | Stage::Window(budget)
+            | Stage::Bouncer(budget) => *budget,
+            Stage::ClosedTapeLanguage(budget) | Stage::BackwardReasoning(budget) => *budget as u128,
+        }
+    }
+
+    pub fn decide(&self, tm: &TuringMachine) -> Decision {
+        let stop = AtomicBool::new(false);
+        let proof = match *self {
+            Stage::Simulate(steps) => {
+                return decide::simulate(tm, steps, usize::MAX, Analyses::NONE, &stop)
+            }
+            Stage::Cycler(steps) => {
+                let analyses = Analyses {
+                    cycler: true,
+                    translated_cycler: false,
+                };
+                return decide::simulate(tm, steps, usize::MAX, analyses, &stop);
+            }
+            Stage::TranslatedCycler(steps) => {
+                let analyses = Analyses {
+                    cycler: false,
+                    translated_cycler: true,
+                };
+                return decide::simulate(tm, steps, usize::MAX, analyses, &stop);
+            }
+            Stage::Window(steps) => window::find(tm, steps, window::WINDOW, window::HISTORY, &stop),
+            Stage::Bouncer(steps) => bouncer::find(tm, steps, usize::MAX, &stop)
+                .map(|(start, formula)| Proof::Bouncer { start, formula }),
+            Stage::ClosedTapeLanguage(max_n) => {
+                ctl::find(tm, max_n).map(|language| Proof::ClosedTapeLanguage { language })
+            }
+            Stage::BackwardReasoning(depth) => {
+                decide::backward_reasoning(tm, depth).then_some(Proof::BackwardReasoning { depth })
+            }
+        };
+        proof.map_or(Decision::Undecided, Decision::NeverHalts)
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.name(), self.budget())
+    }
+}
+
+/// A budget like `1000` or `1e6`.
+fn parse_budget(s: &str) -> Result<u128, String> {
+    let invalid = || format!("invalid budget '{s}'");
+    match s.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => {
+            let mantissa: u128 = mantissa.parse().map_err(|_| invalid())?;
+            let exponent: u32 = exponent.parse().map_err(|_| invalid())?;
+            10u128
+                .checked_pow(exponent)
+                .and_then(|power| mantissa.checked_mul(power))
+                .ok_or_else(invalid)
+        }
+        None => s.parse().map_err(|_| invalid()),
+    }
+}
+
+/// A budget that fits in a `usize`, as a command line argument.
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    let budget = parse_budget(s)?;
+    usize::try_from(budget).map_err(|_| format!("budget {budget} too big"))
+}
+
+impl FromStr for Stage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, budget) = s
+            .split_once(':')
+            .ok_or(format!("expected NAME:BUDGET instead of '{s}'"))?;
+        let budget = parse_budget(budget)?;
+        let small = || usize::try_from(budget).map_err(|_| format!("budget {budget} too big"));
+        Ok(match name {
+            "sim" => Stage::Simulate(budget),
+            "cycler" => Stage::Cycler(budget),
+            "tcycler" => Stage::TranslatedCycler(budget),
+            "window" => Stage::Window(budget),
+            "bouncer" => Stage::Bouncer(budget),
+            "ctl" => Stage::ClosedTapeLanguage(small()?),
+            "backward" => Stage::BackwardReasoning(small()?),
+            _ => return Err(format!("unknown decider '{name}'")),
+        })
+    }
+}
+
+/// Deciders run one after the other until one of them decides, written as
+/// a spec like `sim:1e9,cycler:1e6,tcycler:1e7,bouncer:1e4`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Pipeline(pub Vec<Stage>);
+
+impl Pipeline {
+    /// Decides `tm` with the stages in order. Gives the decision, the index
+    /// of the stage that decided and how long each stage run took.
+    pub fn decide(&self, tm: &TuringMachine) -> (Decision, Option<usize>, Vec<Duration>) {
+        let mut times = vec![];
+        for (index, stage) in self.0.iter().enumerate() {
+            let start = Instant::now();
+            let decision = decide::logged(stage.name(), || stage.decide(tm));
+            times.push(start.elapsed());
+            if decision != Decision::Undecided {
+                return (decision, Some(index), times);
+            }
+        }
+        (Decision::Undecided, None, times)
+    }
+
+    /// The most steps simulated by one of the first `stages` stages.
+    pub fn max_steps(&self, stages: usize) -> u128 {
+        self.0[..stages]
+            .iter()
+            .filter(|stage| {
+                !matches!(
+                    stage,
+                    Stage::ClosedTapeLanguage(_) | Stage::BackwardReasoning(_)
+                )
+            })
+            .map(Stage::budget)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The deepest backward reasoning of the stages, `0` without one.
+    pub fn depth(&self) -> usize {
+        self.0
+            .iter()
+            .filter_map(|stage| match stage {
+                Stage::BackwardReasoning(depth) => Some(*depth),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Names of the deciders of the stages, in order and without repeats.
+    pub fn deciders(&self) -> Vec<&'static str> {
+        let mut deciders = vec![];
+        for decider in self.0.iter().filter_map(Stage::decider) {
+            if !deciders.contains(&decider) {
+                deciders.push(decider);
+            }
+        }
+        deciders
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stages: Vec<Stage> = s.split(',').map(str::parse).collect::<Result<_, _>>()?;
+        Ok(Pipeline(stages))
+    }
+}
+
+/// Time spent in and machines decided by every stage of a pipeline over
+/// many machines.
+#[derive(Debug, Clone)]
+pub struct Timings {
+    pub runs: Vec<u64>,
+    pub decided: Vec<u64>,
+    pub times: Vec<Duration>,
+}
+
+impl Timings {
+    pub fn new(pipeline: &Pipeline) -> Self {
+        let stages = pipeline.0.len();
+        Timings {
+            runs: vec![0; stages],
+            decided: vec![0; stages],
+            times: vec![Duration::ZERO; stages],
+        }
+    }
+
+    /// Adds the outcome of a [`Pipeline::decide`].
+    pub fn add(&mut self, decided: Option<usize>, times: &[Duration]) {
+        for (index, time) in times.iter().enumerate() {
+            self.runs[index] += 1;
+            self.times[index] += *time;
+        }
+        if let Some(index) = decided {
+            self.decided[index] += 1;
+        }
+    }
+
+    /// A line per stage with its runs, decisions and time.
+    pub fn report(&self, pipeline: &Pipeline) -> Vec<String> {
+        pipeline
+            .0
+            .iter()
+            .enumerate()
+            .map(|(index, stage)| {
+                format!(
+                    "{:<20} {:>9} runs {:>9} decided {:>10.3} s",
+                    stage.to_string(),
+                    self.runs[index],
+                    self.decided[index],
+                    self.times[index].as_secs_f64()
+                )
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_pipeline() {
+    use std::path::Path;
+
+    let pipeline: Pipeline = "sim:1e3,cycler:1000,tcycler:2E3,bouncer:500,ctl:2,backward:5"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        pipeline,
+        Pipeline(vec![
+            Stage::Simulate(1000),
+            Stage::Cycler(1000),
+            Stage::TranslatedCycler(2000),
+            Stage::Bouncer(500),
+            Stage::ClosedTapeLanguage(2),
+            Stage::BackwardReasoning(5),
+        ])
+    );
+    assert_eq!(pipeline.0[2].to_string(), "tcycler:2000");
+    assert!("sim".parse::<Pipeline>().is_err());
+    assert!("sim:1e50".parse::<Pipeline>().is_err());
+    assert!("magic:10".parse::<Pipeline>().is_err());
+    assert_eq!("window:1e3".parse(), Ok(Stage::Window(1000)));
+    assert_eq!(
+        (pipeline.max_steps(pipeline.0.len()), pipeline.depth()),
+        (2000, 5)
+    );
+    assert_eq!(
+        pipeline.deciders(),
+        [
+            "cycler",
+            "translated_cycler",
+            "bouncer",
+            "closed_tape_language",
+            "backward_reasoning"
+        ]
+    );
+
+    let mut timings = Timings::new(&pipeline);
+    let tm = TuringMachine::new(Path::new("examples/deciders/translated_cycler.turing"));
+    let (decision, decided, times) = pipeline.decide(&tm);
+    assert!(matches!(
+        decision,
+        Decision::NeverHalts(Proof::TranslatedCycler { .. })
+    ));
+    assert_eq!((decided, times.len()), (Some(2), 3));
+    assert_eq!(pipeline.max_steps(times.len()), 2000);
+    timings.add(decided, &times);
+
+    // Only the stages asked for run.
+    let tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    let (decision, decided, _) = Pipeline(vec![Stage::Cycler(10)]).decide(&tm);
+    assert_eq!((decision, decided), (Decision::Undecided, None));
+    let (decision, decided, times) = pipeline.decide(&tm);
+    assert!(matches!(decision, Decision::Halts { steps: 107, .. }));
+    timings.add(decided, &times);
+    assert_eq!(timings.runs, [2, 1, 1, 0, 0, 0]);
+    assert_eq!(timings.decided, [1, 0, 1, 0, 0, 0]);
+    assert_eq!(timings.report(&pipeline).len(), 6);
+}
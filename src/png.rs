@@ -0,0 +1,109 @@
+use std::{fs, path::Path};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Most bytes of a stored deflate block.
+const BLOCK: usize = 0xffff;
+
+/// Encodes an 8-bit grayscale image with a byte per pixel, row by row, as
+/// a PNG file. The image data is stored without compressing it, which
+/// keeps the encoder small; images of cells compress well with any tool.
+pub fn encode_gray(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    assert_eq!(pixels.len(), width * height, "a byte per pixel");
+    let mut header = vec![];
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // Bit depth 8, grayscale, deflate, adaptive filtering, no interlacing.
+    header.extend([8, 0, 0, 0, 0]);
+
+    // Every row starts with the filter type, 0 for none.
+    let mut raw = Vec::with_capacity((width + 1) * height);
+    for row in 0..height {
+        raw.push(0);
+        raw.extend(&pixels[row * width..(row + 1) * width]);
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Writes an image as [`encode_gray`] encodes it.
+pub fn write_gray(path: &Path, width: usize, height: usize, pixels: &[u8]) -> Result<(), String> {
+    fs::write(path, encode_gray(width, height, pixels)).map_err(|why| why.to_string())
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// A zlib stream holding `data` in stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        stream.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        stream.extend(len.to_le_bytes());
+        stream.extend((!len).to_le_bytes());
+        stream.extend(block);
+    }
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb88320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+#[test]
+fn test_png() {
+    assert_eq!(crc32(b"IEND"), 0xae426082);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+
+    let png = encode_gray(3, 2, &[0, 255, 0, 255, 0, 255]);
+    assert_eq!(png[..8], SIGNATURE);
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(png[16..24], [0, 0, 0, 3, 0, 0, 0, 2]);
+    assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    // The rows follow the zlib header and the header of the only block.
+    let idat = 8 + 25 + 8;
+    assert_eq!(&png[idat - 4..idat], b"IDAT");
+    assert_eq!(png[idat..idat + 7], [0x78, 0x01, 1, 8, 0, !8, 0xff]);
+    assert_eq!(png[idat + 7..idat + 15], [0, 0, 255, 0, 0, 255, 0, 255]);
+
+    // Big images take several blocks.
+    let stream = zlib_stored(&vec![7; BLOCK + 1]);
+    assert_eq!(stream.len(), 2 + 2 * 5 + BLOCK + 1 + 4);
+    assert_eq!(stream[2], 0);
+    assert_eq!(stream[2 + 5 + BLOCK], 1);
+}
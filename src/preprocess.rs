@@ -0,0 +1,456 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::turing::strip_comment;
+
+/// Names of the halting states, which are never prefixed.
+const HALTING: [&str; 3] = ["Halt", "Accept", "Reject"];
+
+/// Expands the directives of a machine file read from `path`, which is `-`
+/// for stdin.
+///
+/// Directives are lines starting with `%`:
+///
+/// - `%include other.turing` inserts the instructions of another file, with
+///   the path relative to the including file. With `as prefix`, the names
+///   of the states of the included file start with `prefix`, except for the
+///   halting states and names starting with `^`, which refer to a state of
+///   the including file. Bindings like `done=next` after that make `^done`
+///   refer to the state `next` instead, so copies of a fragment can be
+///   chained.
+/// - `%repeat 8 {` up to a line with a single `}` repeats the lines in
+///   between, where `{i}` is replaced with the number of the repetition,
+///   starting at 0. Arithmetic like `{i+1}` or `{i-1}` is allowed, and
+///   `%repeat 8 as j {` names the variable `j` for nested repetitions.
+/// - `%param N` declares a parameter, which is used like a variable and
+///   can be given a default with `%param N = 8`. Values come from `params`
+///   or, for included files, from bindings like `N=3` of the `%include`.
+/// - `%glyphs 0=· 1=█` is kept as it is, for the machine to show these
+///   symbols as the glyphs when printing the tape.
+/// - `%blank 2` is kept as well, filling the cells never written with `2`
+///   instead of `0`.
+/// - `%halt Z H` is kept as well, for machines from elsewhere whose
+///   halting states have these names, and so is `%halt-writing 3`, for
+///   machines that halt by writing the symbol `3`. Included files get their
+///   prefix on the states.
+/// - `%universal B C` is kept as well, marking states of an alternating
+///   machine as universal. Included files get their prefix on the states.
+/// - `%weights A 0 1 3` is kept too, weighing the instructions of a
+///   probabilistic machine for state `A` on symbol `0`, here 1:3 in the
+///   order of the file. Included files get their prefix on the state.
+/// - `%oracle Q Y N` is kept as well, naming the query state of a machine
+///   with an oracle and the states it goes on in when the answer is yes or
+///   no. Included files get their prefix on the states.
+pub fn preprocess(content: &str, path: &Path, params: &[Param]) -> Result<String, String> {
+    let arguments = params
+        .iter()
+        .map(|param| (param.name.clone(), param.value.to_string()))
+        .collect();
+    let mut file = Scope {
+        path,
+        arguments: &arguments,
+        used: BTreeSet::new(),
+    };
+    let lines = expand_file(content, &mut file, &mut vec![])?;
+    if let Some(param) = params.iter().find(|param| !file.used.contains(&param.name)) {
+        return Err(format!(
+            "{}: the machine has no parameter '{}'",
+            path.display(),
+            param.name
+        ));
+    }
+    for line in &lines {
+        let code = strip_comment(line).0;
+        if let Some(state) = code.split_whitespace().find(|word| word.starts_with('^')) {
+            return Err(format!(
+                "{}: {} refers to a state of the including file, but no file includes it",
+                path.display(),
+                state
+            ));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+/// A value for a `%param` of a machine, given as `NAME=VALUE`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Param {
+    pub name: String,
+    pub value: i128,
+}
+
+impl FromStr for Param {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=VALUE, got '{s}'"))?;
+        match value.trim().parse() {
+            Ok(value) => Ok(Param {
+                name: name.trim().to_string(),
+                value,
+            }),
+            Err(_) => Err(format!("expected an integer, got '{}'", value.trim())),
+        }
+    }
+}
+
+/// A file being expanded, with the values given for its parameters.
+struct Scope<'a> {
+    path: &'a Path,
+    /// Values given for parameters, which for included files are all the
+    /// bindings of the `%include`, the others binding states.
+    arguments: &'a BTreeMap<String, String>,
+    /// Arguments that turned out to be parameters.
+    used: BTreeSet<String>,
+}
+
+/// Expands the lines of a file, leaving `^` in the state names it refers to
+/// the includer with.
+fn expand_file(
+    content: &str,
+    file: &mut Scope,
+    stack: &mut Vec<PathBuf>,
+) -> Result<Vec<String>, String> {
+    let lines: Vec<(usize, &str)> = content
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line))
+        .collect();
+    stack.push(canonical(file.path));
+    let expanded = expand(&lines, BTreeMap::new(), file, stack);
+    stack.pop();
+    expanded
+}
+
+fn expand(
+    lines: &[(usize, &str)],
+    mut variables: BTreeMap<String, i128>,
+    file: &mut Scope,
+    stack: &mut Vec<PathBuf>,
+) -> Result<Vec<String>, String> {
+    let path = file.path;
+    let mut expanded = vec![];
+    let mut index = 0;
+    while index < lines.len() {
+        let (number, line) = lines[index];
+        let error = |why: String| format!("{}:{}: {}", path.display(), number, why);
+        index += 1;
+
+        let (code, comment) = strip_comment(line);
+        let code = substitute(code, &variables).map_err(error)?;
+        let Some(directive) = code.trim_start().strip_prefix('%') else {
+            expanded.push(match comment {
+                Some(comment) => format!("{}//{}", code, comment),
+                None => code,
+            });
+            continue;
+        };
+
+        let words: Vec<&str> = directive.split_whitespace().collect();
+        match words.as_slice() {
+            ["include", file, options @ ..] => {
+                let (prefix, bindings) = match options {
+                    ["as", prefix, bindings @ ..] => (*prefix, bindings),
+                    bindings => ("", bindings),
+                };
+                let mut arguments = BTreeMap::new();
+                for binding in bindings {
+                    let (name, value) = binding.split_once('=').ok_or_else(|| {
+                        error(format!("expected 'name=state' instead of '{binding}'"))
+                    })?;
+                    arguments.insert(name.to_string(), value.to_string());
+                }
+                let file = path.parent().unwrap_or(Path::new("")).join(file);
+                if stack.contains(&canonical(&file)) {
+                    return Err(error(format!("{} includes itself", file.display())));
+                }
+                let content = fs::read_to_string(&file)
+                    .map_err(|why| error(format!("can't read {}: {}", file.display(), why)))?;
+                let mut included = Scope {
+                    path: &file,
+                    arguments: &arguments,
+                    used: BTreeSet::new(),
+                };
+                let lines = expand_file(&content, &mut included, stack)?;
+                let renames = arguments
+                    .iter()
+                    .filter(|(name, _)| !included.used.contains(*name))
+                    .map(|(name, state)| (format!("^{name}"), state.clone()))
+                    .collect();
+                for line in lines {
+                    expanded.push(add_prefix(&line, prefix, &renames));
+                }
+            }
+            ["param", declaration @ ..] => {
+                let declaration = declaration.join(" ");
+                let (name, default) = match declaration.split_once('=') {
+                    Some((name, default)) => (name.trim(), Some(default.trim())),
+                    None => (declaration.as_str(), None),
+                };
+                let value = match (file.arguments.get(name), default) {
+                    (Some(value), _) => {
+                        file.used.insert(name.to_string());
+                        value.as_str()
+                    }
+                    (None, Some(default)) => default,
+                    (None, None) => {
+                        return Err(error(format!(
+                            "parameter '{name}' needs a value, like --param {name}=5"
+                        )))
+                    }
+                };
+                let value = evaluate(value, &variables)
+                    .map_err(|why| error(format!("parameter '{name}': {why}")))?;
+                variables.insert(name.to_string(), value);
+            }
+            ["glyphs" | "blank" | "halt" | "halt-writing" | "universal" | "weights" | "oracle", ..] => {
+                expanded.push(match comment {
+                    Some(comment) => format!("{}//{}", code, comment),
+                    None => code,
+                })
+            }
+            ["repeat", count, "{"] | ["repeat", count, "as", _, "{"] => {
+                let count = evaluate(count, &variables).map_err(error)?;
+                let name = if words.len() == 5 { words[3] } else { "i" };
+                let body = block(&lines[index..]).ok_or_else(|| error("missing '}'".into()))?;
+                for repetition in 0..count.max(0) {
+                    let mut variables = variables.clone();
+                    variables.insert(name.to_string(), repetition);
+                    expanded.extend(expand(body, variables, file, stack)?);
+                }
+                index += body.len() + 1;
+            }
+            _ => return Err(error(format!("unknown directive '%{}'", directive.trim()))),
+        }
+    }
+    Ok(expanded)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The lines of a `%repeat` block up to its closing `}`.
+fn block<'a, 'b>(lines: &'a [(usize, &'b str)]) -> Option<&'a [(usize, &'b str)]> {
+    let mut depth = 0;
+    for (index, (_, line)) in lines.iter().enumerate() {
+        let code = strip_comment(line).0.trim();
+        if code.starts_with('%') && code.ends_with('{') {
+            depth += 1;
+        } else if code == "}" {
+            if depth == 0 {
+                return Some(&lines[..index]);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// Replaces every `{expression}` in `code` with its value.
+fn substitute(code: &str, variables: &BTreeMap<String, i128>) -> Result<String, String> {
+    let mut substituted = String::new();
+    let mut rest = code;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        substituted += &rest[..start];
+        let expression = &rest[start + 1..start + end];
+        if expression.trim().is_empty() {
+            // `{` and `}` that don't enclose a value, as in `%repeat 8 {`.
+            substituted += &rest[start..=start + end];
+        } else {
+            substituted += &evaluate(expression, variables)?.to_string();
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(substituted + rest)
+}
+
+/// Evaluates a number, a variable, or sums and differences of them.
+fn evaluate(expression: &str, variables: &BTreeMap<String, i128>) -> Result<i128, String> {
+    let mut terms = vec![(1, String::new())];
+    for c in expression.chars() {
+        match c {
+            '+' | '-' => {
+                let sign = if c == '+' { 1 } else { -1 };
+                if terms.len() == 1 && terms[0].1.trim().is_empty() {
+                    // A sign in front of the first term.
+                    terms[0].0 = sign;
+                } else {
+                    terms.push((sign, String::new()));
+                }
+            }
+            _ => terms.last_mut().expect("there is a term").1.push(c),
+        }
+    }
+    let mut value = 0i128;
+    for (sign, term) in terms {
+        let term = term.trim();
+        let number = match (term.parse::<i128>(), variables.get(term)) {
+            (Ok(number), _) | (_, Some(&number)) => number,
+            _ if term.is_empty() => return Err(format!("invalid value '{expression}'")),
+            _ => return Err(format!("unknown variable '{term}'")),
+        };
+        value = value
+            .checked_add(sign * number)
+            .ok_or_else(|| format!("'{expression}' is too large"))?;
+    }
+    Ok(value)
+}
+
+/// Prefixes the names of the states in an instruction of an included file
+/// and replaces the names bound to states of the including file.
+fn add_prefix(line: &str, prefix: &str, renames: &BTreeMap<String, String>) -> String {
+    let (code, comment) = strip_comment(line);
+    let words: Vec<&str> = code.split_whitespace().collect();
+    let state = |name: &str| match name.strip_prefix('^') {
+        _ if renames.contains_key(name) => renames[name].clone(),
+        Some(name) => name.to_string(),
+        None if HALTING.contains(&name) => name.to_string(),
+        None => format!("{prefix}{name}"),
+    };
+    if let [directive @ ("%universal" | "%oracle" | "%halt"), states @ ..] = words.as_slice() {
+        let states: Vec<String> = states.iter().map(|name| state(name)).collect();
+        return match comment {
+            Some(comment) => format!("{} {} //{}", directive, states.join(" "), comment),
+            None => format!("{} {}", directive, states.join(" ")),
+        };
+    }
+    if let ["%weights", name, rest @ ..] = words.as_slice() {
+        let code = format!("%weights {} {}", state(name), rest.join(" "));
+        return match comment {
+            Some(comment) => format!("{} //{}", code, comment),
+            None => code,
+        };
+    }
+    if words.len() != 6 {
+        return line.to_string();
+    }
+    let code = format!(
+        "{} {} {} {} {} {}",
+        state(words[0]),
+        words[1],
+        words[2],
+        state(words[3]),
+        words[4],
+        words[5]
+    );
+    match comment {
+        Some(comment) => format!("{} //{}", code, comment),
+        None => code,
+    }
+}
+
+#[test]
+fn test_preprocess() {
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("preprocess_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("gadget.turing"),
+        "A 0 -> B 1 R // write\nB 0 -> ^next 1 R\nB 1 -> Halt 1 R",
+    )
+    .unwrap();
+    fs::write(dir.join("loop.turing"), "%include loop.turing").unwrap();
+
+    let machine = "%repeat 2 {\n\
+                   %include gadget.turing as g{i}_\n\
+                   }\n\
+                   next 0 -> g1_A 0 R\n\
+                   %include gadget.turing as h_ next=h_A\n\
+                   %repeat 2 as j {\n\
+                   %repeat 2 {\n\
+                   c{j}{i} 0 -> c{j}{i+1} 1 L\n\
+                   }\n\
+                   }";
+    assert_eq!(
+        preprocess(machine, &dir.join("machine.turing"), &[]).unwrap(),
+        "g0_A 0 -> g0_B 1 R // write\n\
+         g0_B 0 -> next 1 R\n\
+         g0_B 1 -> Halt 1 R\n\
+         g1_A 0 -> g1_B 1 R // write\n\
+         g1_B 0 -> next 1 R\n\
+         g1_B 1 -> Halt 1 R\n\
+         next 0 -> g1_A 0 R\n\
+         h_A 0 -> h_B 1 R // write\n\
+         h_B 0 -> h_A 1 R\n\
+         h_B 1 -> Halt 1 R\n\
+         c00 0 -> c01 1 L\n\
+         c01 0 -> c02 1 L\n\
+         c10 0 -> c11 1 L\n\
+         c11 0 -> c12 1 L"
+    );
+
+    let plain = "A 0 -> B 1 R\n// a comment with {braces}";
+    assert_eq!(preprocess(plain, Path::new("-"), &[]).unwrap(), plain);
+    let glyphs = "%glyphs 0=. 1=# // shown on the tape\nA 0 -> Halt 1 R";
+    assert_eq!(preprocess(glyphs, Path::new("-"), &[]).unwrap(), glyphs);
+    let blank = "%blank 2\nA 2 -> Halt 1 R";
+    assert_eq!(preprocess(blank, Path::new("-"), &[]).unwrap(), blank);
+    assert_eq!(
+        add_prefix("%universal B ^C", "g_", &BTreeMap::new()),
+        "%universal g_B C"
+    );
+    assert_eq!(
+        add_prefix("%weights B 0 1 3", "g_", &BTreeMap::new()),
+        "%weights g_B 0 1 3"
+    );
+    assert_eq!(
+        add_prefix("%oracle Q Y ^N", "g_", &BTreeMap::new()),
+        "%oracle g_Q g_Y N"
+    );
+    assert_eq!(add_prefix("%halt Z", "g_", &BTreeMap::new()), "%halt g_Z");
+
+    let error = |machine: &str| preprocess(machine, &dir.join("machine.turing"), &[]).unwrap_err();
+    assert!(error("%include loop.turing").ends_with("loop.turing includes itself"));
+    assert!(error("%repeat 2 {\nA 0 -> A 0 R").ends_with(":1: missing '}'"));
+    assert!(error("A 0 -> B{k} 0 R").ends_with(":1: unknown variable 'k'"));
+    assert!(error("A 0 -> ^B 0 R").contains("^B refers to a state of the including file"));
+    assert!(error("%include gadget.turing as g_ next").contains("expected 'name=state'"));
+    assert!(error("%define X").ends_with(":1: unknown directive '%define X'"));
+
+    fs::write(
+        dir.join("counter.turing"),
+        "%param WIDTH\n%repeat WIDTH {\nc{i} 1 -> c{i+1} 0 R\n}",
+    )
+    .unwrap();
+    let family = "%param N = 2\n\
+                  %include counter.turing as c_ WIDTH={N+1}\n\
+                  A 0 -> A {N} R";
+    let expand = |params: &[Param]| preprocess(family, &dir.join("family.turing"), params);
+    assert_eq!(
+        expand(&[]).unwrap(),
+        "c_c0 1 -> c_c1 0 R\nc_c1 1 -> c_c2 0 R\nc_c2 1 -> c_c3 0 R\nA 0 -> A 2 R"
+    );
+    let n = |value| Param {
+        name: "N".to_string(),
+        value,
+    };
+    assert_eq!(expand(&[n(0)]).unwrap(), "c_c0 1 -> c_c1 0 R\nA 0 -> A 0 R");
+    assert_eq!("N = 5".parse(), Ok(n(5)));
+    assert!("N".parse::<Param>().is_err());
+    let m = Param {
+        name: "M".to_string(),
+        value: 1,
+    };
+    assert!(expand(&[m])
+        .unwrap_err()
+        .ends_with("the machine has no parameter 'M'"));
+    assert!(error("%include counter.turing")
+        .ends_with("parameter 'WIDTH' needs a value, like --param WIDTH=5"));
+
+    fs::remove_dir_all(dir).unwrap();
+
+    let mut tm = crate::turing::TuringMachine::new(Path::new("examples/compose/ones.turing"));
+    while tm.step() {}
+    assert_eq!(tm.eval_busy_bever().0, 8);
+}
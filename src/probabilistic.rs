@@ -0,0 +1,147 @@
+use crate::turing::{TuringMachine, Verdict};
+
+/// The normal quantile for a 95% confidence interval.
+const Z_95: f64 = 1.959964;
+
+/// A seeded xorshift64 generator, so runs with the same seed flip the
+/// same coins.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng {
+            state: seed.wrapping_mul(0x9e3779b97f4a7c15) | 1,
+        }
+    }
+
+    /// A number below `bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state % bound
+    }
+}
+
+/// Runs `tm` once for up to `max_steps` steps, flipping a coin whenever
+/// several instructions match to pick one of them with the odds of their
+/// weights (see [`TuringMachine::weights`]).
+pub fn trial(tm: &TuringMachine, rng: &mut Rng, max_steps: u128) -> Verdict {
+    let mut tm = tm.clone();
+    tm.set_reject_undefined(true);
+    while !tm.is_halted() && tm.num_steps < max_steps {
+        let choices = tm.choices();
+        match choices.as_slice() {
+            // Rejects, as there is no instruction.
+            [] => {
+                tm.step();
+            }
+            [instruction] => tm.step_with(*instruction),
+            choices => {
+                let weights = tm.weights();
+                let total: u64 = weights.iter().map(|weight| *weight as u64).sum();
+                let mut coin = rng.below(total);
+                let picked = weights
+                    .iter()
+                    .position(|weight| match coin.checked_sub(*weight as u64) {
+                        Some(rest) => {
+                            coin = rest;
+                            false
+                        }
+                        None => true,
+                    })
+                    .expect("the coin is below the total weight");
+                tm.step_with(choices[picked]);
+            }
+        }
+    }
+    tm.run_word(max_steps)
+}
+
+/// Verdicts of many trials of a machine.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Estimate {
+    pub accepted: u64,
+    pub rejected: u64,
+    pub step_limit: u64,
+}
+
+impl Estimate {
+    pub fn trials(&self) -> u64 {
+        self.accepted + self.rejected + self.step_limit
+    }
+
+    /// The share of trials that accepted.
+    pub fn probability(&self) -> f64 {
+        self.accepted as f64 / self.trials().max(1) as f64
+    }
+
+    /// The Wilson score interval holding the acceptance probability with
+    /// 95% confidence, which stays inside 0 to 1 even when few or all
+    /// trials accept.
+    pub fn interval(&self) -> (f64, f64) {
+        let n = self.trials().max(1) as f64;
+        let p = self.probability();
+        let z2 = Z_95 * Z_95;
+        let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let margin = Z_95 / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+        ((center - margin).max(0.0), (center + margin).min(1.0))
+    }
+}
+
+/// Runs `trials` trials of `tm` with coins seeded by `seed`. Trials
+/// running into `max_steps` count neither as accepting nor as rejecting.
+pub fn monte_carlo(tm: &TuringMachine, trials: u64, max_steps: u128, seed: u64) -> Estimate {
+    let mut rng = Rng::new(seed);
+    let mut estimate = Estimate::default();
+    for _ in 0..trials {
+        match trial(tm, &mut rng, max_steps) {
+            Verdict::Accept => estimate.accepted += 1,
+            Verdict::Reject => estimate.rejected += 1,
+            Verdict::StepLimit => estimate.step_limit += 1,
+        }
+    }
+    estimate
+}
+
+#[test]
+fn test_probabilistic() {
+    // Flips a coin weighted 1:3 and accepts on the heavy side.
+    let tm = TuringMachine::parse(
+        "%weights A 0 1 3\n\
+         A 0 -> Reject 0 R\n\
+         A 0 -> Accept 0 R",
+    )
+    .unwrap();
+    assert_eq!(tm.weights(), [1, 3]);
+    let estimate = monte_carlo(&tm, 10_000, 10, 7);
+    assert_eq!(estimate.trials(), 10_000);
+    assert_eq!(estimate.step_limit, 0);
+    let (low, high) = estimate.interval();
+    assert!(low < 0.75 && 0.75 < high, "{low} {high}");
+    assert!(high - low < 0.02);
+    assert_eq!(monte_carlo(&tm, 100, 10, 7), monte_carlo(&tm, 100, 10, 7));
+
+    // Without weights both instructions are as likely.
+    let fair = TuringMachine::parse("A 0 -> Reject 0 R\nA 0 -> Accept 0 R").unwrap();
+    assert_eq!(fair.weights(), [1, 1]);
+    let (low, high) = monte_carlo(&fair, 10_000, 10, 7).interval();
+    assert!(low < 0.5 && 0.5 < high);
+    let looping = TuringMachine::parse("A 0 -> A 0 R").unwrap();
+    assert_eq!(monte_carlo(&looping, 3, 10, 0).step_limit, 3);
+    let all = Estimate {
+        accepted: 10,
+        ..Default::default()
+    };
+    // All trials accepting still leaves room below.
+    let (low, high) = all.interval();
+    assert!(0.7 < low && low < 0.75);
+    assert!((high - 1.0).abs() < 1e-9);
+
+    assert!(TuringMachine::parse("%weights A 0 1\nA 0 -> A 0 R\nA 0 -> B 0 R").is_err());
+    assert!(TuringMachine::parse("%weights A 0 0 1\nA 0 -> A 0 R\nA 0 -> B 0 R").is_err());
+    assert!(TuringMachine::parse("%weights C 0 1\nA 0 -> A 0 R").is_err());
+}
@@ -0,0 +1,309 @@
+use std::cmp::Ordering;
+
+use crate::sqlite::Value;
+
+/// A comparison between two operands.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operand {
+    Column(String),
+    Value(Value),
+}
+
+/// A condition on the rows of a table in the syntax of SQL, like
+/// `verdict='undecided' AND steps>1e8`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Filter {
+    Compare(Operand, Comparison, Operand),
+    /// `IS NULL`, or `IS NOT NULL` if negated.
+    IsNull(Operand),
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Word(String),
+    Value(Value),
+    Comparison(Comparison),
+    Open,
+    Close,
+}
+
+fn tokens(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '=' => {
+                chars.next_if_eq(&'=');
+                Token::Comparison(Comparison::Equal)
+            }
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Comparison(Comparison::NotEqual),
+            '<' if chars.next_if_eq(&'>').is_some() => Token::Comparison(Comparison::NotEqual),
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Comparison(Comparison::LessOrEqual),
+            '<' => Token::Comparison(Comparison::Less),
+            '>' if chars.next_if_eq(&'=').is_some() => {
+                Token::Comparison(Comparison::GreaterOrEqual)
+            }
+            '>' => Token::Comparison(Comparison::Greater),
+            '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        // A quote is written twice inside a string.
+                        Some('\'') if chars.next_if_eq(&'\'').is_some() => text.push('\''),
+                        Some('\'') => break,
+                        Some(c) => text.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                Token::Value(Value::Text(text))
+            }
+            _ if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.') {
+                    number.push(c);
+                    // The sign of an exponent.
+                    if matches!(c, 'e' | 'E') {
+                        if let Some(sign) = chars.next_if(|c| matches!(c, '+' | '-')) {
+                            number.push(sign);
+                        }
+                    }
+                }
+                let value = match number.parse() {
+                    Ok(n) => Value::Integer(n),
+                    Err(_) => Value::Real(
+                        number
+                            .parse()
+                            .map_err(|_| format!("invalid number '{number}'"))?,
+                    ),
+                };
+                Token::Value(value)
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            _ => return Err(format!("unexpected '{c}'")),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.at) {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.at += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+        while self.keyword("OR") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.not()?;
+        while self.keyword("AND") {
+            filter = Filter::And(Box::new(filter), Box::new(self.not()?));
+        }
+        Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter, String> {
+        if self.keyword("NOT") {
+            return Ok(Filter::Not(Box::new(self.not()?)));
+        }
+        if self.tokens.get(self.at) == Some(&Token::Open) {
+            self.at += 1;
+            let filter = self.or()?;
+            if self.tokens.get(self.at) != Some(&Token::Close) {
+                return Err("missing ')'".to_string());
+            }
+            self.at += 1;
+            return Ok(filter);
+        }
+        let left = self.operand()?;
+        if self.keyword("IS") {
+            let negated = self.keyword("NOT");
+            if !self.keyword("NULL") {
+                return Err("expected NULL after IS".to_string());
+            }
+            let filter = Filter::IsNull(left);
+            return Ok(match negated {
+                true => Filter::Not(Box::new(filter)),
+                false => filter,
+            });
+        }
+        let comparison = match self.tokens.get(self.at) {
+            Some(Token::Comparison(comparison)) => *comparison,
+            _ => return Err("expected a comparison".to_string()),
+        };
+        self.at += 1;
+        Ok(Filter::Compare(left, comparison, self.operand()?))
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        let operand = match self.tokens.get(self.at) {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("NULL") => {
+                Operand::Value(Value::Null)
+            }
+            Some(Token::Word(word)) => Operand::Column(word.clone()),
+            Some(Token::Value(value)) => Operand::Value(value.clone()),
+            Some(token) => return Err(format!("unexpected {token:?}")),
+            None => return Err("unexpected end of the filter".to_string()),
+        };
+        self.at += 1;
+        Ok(operand)
+    }
+}
+
+impl std::str::FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokens(s)?,
+            at: 0,
+        };
+        let filter = parser.or()?;
+        match parser.tokens.get(parser.at) {
+            None => Ok(filter),
+            Some(token) => Err(format!("unexpected {token:?}")),
+        }
+    }
+}
+
+/// Orders numbers by value and texts alphabetically. Other values aren't
+/// comparable, so comparisons with `NULL` are never true like in SQL.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Integer(a), Value::Real(b)) => (*a as f64).partial_cmp(b),
+        (Value::Real(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
+        (Value::Real(a), Value::Real(b)) => a.partial_cmp(b),
+        (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+impl Filter {
+    /// Whether `row` of a table with `columns` matches.
+    pub fn matches(&self, columns: &[String], row: &[Value]) -> Result<bool, String> {
+        let value = |operand: &Operand| match operand {
+            Operand::Column(name) => columns
+                .iter()
+                .position(|column| column.eq_ignore_ascii_case(name))
+                .map(|index| row[index].clone())
+                .ok_or(format!("there is no column '{name}'")),
+            Operand::Value(value) => Ok(value.clone()),
+        };
+        Ok(match self {
+            Filter::Compare(left, comparison, right) => {
+                let ordering = compare(&value(left)?, &value(right)?);
+                ordering.is_some_and(|ordering| match comparison {
+                    Comparison::Equal => ordering.is_eq(),
+                    Comparison::NotEqual => ordering.is_ne(),
+                    Comparison::Less => ordering.is_lt(),
+                    Comparison::LessOrEqual => ordering.is_le(),
+                    Comparison::Greater => ordering.is_gt(),
+                    Comparison::GreaterOrEqual => ordering.is_ge(),
+                })
+            }
+            Filter::IsNull(operand) => value(operand)? == Value::Null,
+            Filter::Not(filter) => !filter.matches(columns, row)?,
+            Filter::And(left, right) => {
+                left.matches(columns, row)? && right.matches(columns, row)?
+            }
+            Filter::Or(left, right) => {
+                left.matches(columns, row)? || right.matches(columns, row)?
+            }
+        })
+    }
+}
+
+#[test]
+fn test_query() {
+    let filter: Filter = "verdict='undecided' AND steps>1e8".parse().unwrap();
+    assert_eq!(
+        filter,
+        Filter::And(
+            Box::new(Filter::Compare(
+                Operand::Column("verdict".to_string()),
+                Comparison::Equal,
+                Operand::Value(Value::Text("undecided".to_string()))
+            )),
+            Box::new(Filter::Compare(
+                Operand::Column("steps".to_string()),
+                Comparison::Greater,
+                Operand::Value(Value::Real(1e8))
+            ))
+        )
+    );
+
+    let columns = [
+        "verdict".to_string(),
+        "steps".to_string(),
+        "decider".to_string(),
+    ];
+    let row = |verdict: &str, steps, decider: Value| {
+        vec![
+            Value::Text(verdict.to_string()),
+            Value::Integer(steps),
+            decider,
+        ]
+    };
+    let undecided = row("undecided", 200_000_000, Value::Null);
+    let cycler = row("never_halts", 10, Value::Text("cycler".to_string()));
+    assert_eq!(filter.matches(&columns, &undecided), Ok(true));
+    assert_eq!(filter.matches(&columns, &cycler), Ok(false));
+
+    let filter: Filter = "not (decider = 'cycler' or steps <= -1) and decider is not null"
+        .parse()
+        .unwrap();
+    assert_eq!(filter.matches(&columns, &cycler), Ok(false));
+    assert_eq!(filter.matches(&columns, &undecided), Ok(false));
+    let filter: Filter = "decider IS NULL OR decider <> 'it''s'".parse().unwrap();
+    assert_eq!(filter.matches(&columns, &undecided), Ok(true));
+    assert_eq!(filter.matches(&columns, &cycler), Ok(true));
+    // Nothing equals NULL.
+    let filter: Filter = "decider = NULL".parse().unwrap();
+    assert_eq!(filter.matches(&columns, &undecided), Ok(false));
+
+    let filter: Filter = "sigma > 3".parse().unwrap();
+    assert!(filter.matches(&columns, &cycler).is_err());
+    assert!("steps >".parse::<Filter>().is_err());
+    assert!("(steps > 1".parse::<Filter>().is_err());
+    assert!("steps > 1 1".parse::<Filter>().is_err());
+    assert!("verdict = 'halts".parse::<Filter>().is_err());
+}
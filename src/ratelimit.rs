@@ -0,0 +1,72 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Most clients remembered before the ones with full buckets are forgotten.
+const MAX_CLIENTS: usize = 10_000;
+
+/// Limits the requests of every client address with a token bucket: a
+/// client may send `burst` requests at once, and then another one every
+/// `interval`.
+pub struct RateLimiter {
+    interval: Duration,
+    burst: u32,
+    /// The tokens left of every client when it last sent a request.
+    buckets: Mutex<HashMap<IpAddr, (f64, Instant)>>,
+}
+
+impl RateLimiter {
+    /// Allows `per_minute` requests a minute per client, up to a tenth of a
+    /// minute's worth at once.
+    pub fn per_minute(per_minute: u32) -> Self {
+        let per_minute = per_minute.max(1);
+        RateLimiter {
+            interval: Duration::from_secs(60) / per_minute,
+            burst: (per_minute / 10).max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `client` may send a request at `now`, which takes a token if
+    /// so.
+    pub fn allow(&self, client: IpAddr, now: Instant) -> bool {
+        let burst = self.burst as f64;
+        let refill = |(tokens, last): (f64, Instant)| {
+            let elapsed = now.saturating_duration_since(last);
+            (burst).min(tokens + elapsed.as_secs_f64() / self.interval.as_secs_f64())
+        };
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_CLIENTS {
+            buckets.retain(|_, bucket| refill(*bucket) < burst);
+        }
+        let tokens = buckets.get(&client).copied().map_or(burst, refill);
+        let allowed = tokens >= 1.0;
+        let tokens = match allowed {
+            true => tokens - 1.0,
+            false => tokens,
+        };
+        buckets.insert(client, (tokens, now));
+        allowed
+    }
+}
+
+#[test]
+fn test_ratelimit() {
+    let limiter = RateLimiter::per_minute(60);
+    let (alice, bob): (IpAddr, IpAddr) = ("10.0.0.1".parse().unwrap(), "::1".parse().unwrap());
+    let now = Instant::now();
+    // A burst of 6, then one a second.
+    assert!((0..6).all(|_| limiter.allow(alice, now)));
+    assert!(!limiter.allow(alice, now));
+    assert!(limiter.allow(bob, now));
+    assert!(!limiter.allow(alice, now + Duration::from_millis(500)));
+    assert!(limiter.allow(alice, now + Duration::from_millis(1000)));
+    assert!(!limiter.allow(alice, now + Duration::from_millis(1100)));
+    // Waiting fills the bucket up to the burst only.
+    let later = now + Duration::from_secs(3600);
+    assert!((0..6).all(|_| limiter.allow(alice, later)));
+    assert!(!limiter.allow(alice, later));
+}
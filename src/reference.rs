@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    accel::{Accel, MacroMachine},
+    fusion::Fusion,
+    scan,
+    turing::{Direction, HaltReason, TapeEntry, TuringMachine, DEFAULT_ENTRY},
+};
+
+/// Steps between comparisons of the whole tape with the engine. The cell
+/// written is compared after every step.
+const TAPE_CHECK_INTERVAL: u128 = 4096;
+
+/// A deliberately simple simulator to check the optimized ones against.
+///
+/// The tape is a map from positions to symbols, and every step looks up the
+/// first instruction matching the state and symbol. A missing transition
+/// rejects without taking a step.
+pub struct Reference<'a> {
+    tm: &'a TuringMachine,
+    cells: BTreeMap<i64, TapeEntry>,
+    /// Position of the head relative to the starting cell.
+    pub head: i64,
+    pub state: Option<usize>,
+    pub halt_reason: Option<HaltReason>,
+    pub steps: u128,
+}
+
+impl<'a> Reference<'a> {
+    /// Starts `tm` in its start state with `input` written from the head on.
+    pub fn new(tm: &'a TuringMachine, input: &[TapeEntry]) -> Self {
+        Reference {
+            tm,
+            cells: (0..).zip(input.iter().copied()).collect(),
+            head: 0,
+            state: Some(0),
+            halt_reason: None,
+            steps: 0,
+        }
+    }
+
+    pub fn read(&self, position: i64) -> TapeEntry {
+        self.cells.get(&position).copied().unwrap_or(0)
+    }
+
+    pub fn step(&mut self) -> bool {
+        let state = match self.state {
+            Some(state) => state,
+            None => return false,
+        };
+        let symbol = self.read(self.head);
+        let instruction = self
+            .tm
+            .instructions()
+            .iter()
+            .find(|instruction| instruction.state == state && instruction.entry == symbol);
+        let instruction = match instruction {
+            Some(instruction) => instruction,
+            None => {
+                self.state = None;
+                self.halt_reason = Some(HaltReason::Reject);
+                return false;
+            }
+        };
+
+        self.cells.insert(self.head, instruction.new_entry);
+        self.head += match instruction.direction {
+            Direction::Left => -1,
+            Direction::Right => 1,
+        };
+        self.state = instruction.new_state;
+        if self.state.is_none() {
+            self.halt_reason = Some(instruction.halt);
+        }
+        self.steps += 1;
+        true
+    }
+
+    /// Position of the first non-blank cell and the cells up to the last
+    /// non-blank one.
+    pub fn trimmed(&self) -> (i64, Vec<TapeEntry>) {
+        let mut cells = self.cells.iter().filter(|(_, entry)| **entry != 0);
+        let first = match cells.next() {
+            Some((first, _)) => *first,
+            None => return (0, vec![]),
+        };
+        let last = cells.next_back().map_or(first, |(last, _)| *last);
+        (
+            first,
+            (first..=last).map(|position| self.read(position)).collect(),
+        )
+    }
+}
+
+/// Position of the first non-blank cell relative to index `origin` and the
+/// cells up to the last non-blank one.
+fn trim(cells: &[TapeEntry], origin: usize) -> (i64, Vec<TapeEntry>) {
+    let first = scan::first_written(cells, DEFAULT_ENTRY);
+    let last = scan::last_written(cells, DEFAULT_ENTRY);
+    match (first, last) {
+        (Some(first), Some(last)) => (first as i64 - origin as i64, cells[first..=last].to_vec()),
+        _ => (0, vec![]),
+    }
+}
+
+fn name(tm: &TuringMachine, state: Option<usize>, halt_reason: Option<HaltReason>) -> String {
+    match (state, halt_reason) {
+        (Some(state), _) => tm.states()[state].clone(),
+        (None, Some(reason)) => reason.name().to_string(),
+        (None, None) => "none".to_string(),
+    }
+}
+
+/// Runs `tm` on `input` for up to `max_steps` steps and the reference along
+/// with it, comparing their configurations. Missing transitions reject.
+/// Returns the number of steps compared.
+pub fn check_engine(
+    tm: &TuringMachine,
+    input: &[TapeEntry],
+    max_steps: u128,
+) -> Result<u128, String> {
+    let mut engine = tm.clone();
+    engine.set_reject_undefined(true);
+    if !input.is_empty() {
+        engine.set_input(input);
+    }
+    let mut reference = Reference::new(tm, input);
+
+    loop {
+        let written = reference.head;
+        let stepped = (engine.step(), reference.step());
+        let head = engine.head() as i64 - engine.origin() as i64;
+        if stepped.0 != stepped.1
+            || engine.state() != reference.state
+            || engine.halt_reason != reference.halt_reason
+            || head != reference.head
+            || engine.num_steps != reference.steps
+        {
+            return Err(format!(
+                "after {} steps the engine is in {} at {}, the reference in {} at {}",
+                reference.steps,
+                name(tm, engine.state(), engine.halt_reason),
+                head,
+                name(tm, reference.state, reference.halt_reason),
+                reference.head
+            ));
+        }
+        let stopped = !stepped.0 || reference.steps >= max_steps;
+        if stepped.0 {
+            let index = written + engine.origin() as i64;
+            if engine.tape()[index as usize] != reference.read(written) {
+                return Err(format!(
+                    "after {} steps the engine wrote {} at {}, the reference {}",
+                    reference.steps,
+                    engine.tape()[index as usize],
+                    written,
+                    reference.read(written)
+                ));
+            }
+        }
+        if stopped || reference.steps.is_multiple_of(TAPE_CHECK_INTERVAL) {
+            let cells: Vec<TapeEntry> = engine.tape().iter().copied().collect();
+            if trim(&cells, engine.origin()) != reference.trimmed() {
+                return Err(format!(
+                    "after {} steps the tapes of the engine and the reference differ",
+                    reference.steps
+                ));
+            }
+        }
+        if stopped {
+            return Ok(reference.steps);
+        }
+    }
+}
+
+/// Runs the macro machine for `tm` on `input` until it passed `max_steps`
+/// steps and compares its configuration to the reference after every macro
+/// step. Returns the number of steps compared.
+pub fn check_accel(
+    tm: &TuringMachine,
+    input: &[TapeEntry],
+    accel: Accel,
+    max_steps: u128,
+) -> Result<u128, String> {
+    let mut start = tm.clone();
+    start.set_reject_undefined(true);
+    if !input.is_empty() {
+        start.set_input(input);
+    }
+    let mut accelerated = MacroMachine::new(&start, accel);
+    let mut reference = Reference::new(tm, input);
+
+    loop {
+        let target = match accelerated.num_steps.to_u128() {
+            Some(target) => target,
+            None => return Ok(reference.steps),
+        };
+        while reference.steps < target && reference.step() {}
+
+        if reference.steps != target {
+            return Err(format!(
+                "the macro machine took {} steps, but the reference stopped after {}",
+                target, reference.steps
+            ));
+        }
+        if accelerated.proof.is_some() {
+            return Ok(reference.steps);
+        }
+        if accelerated.state() != reference.state
+            || accelerated.halt_reason != reference.halt_reason
+        {
+            return Err(format!(
+                "after {} steps the macro machine is in {}, the reference in {}",
+                target,
+                name(tm, accelerated.state(), accelerated.halt_reason),
+                name(tm, reference.state, reference.halt_reason)
+            ));
+        }
+        if let Some((cells, head)) = accelerated.cells() {
+            // The macro machine doesn't know where the starting cell is, so
+            // positions are compared relative to the first non-blank cell.
+            // Once it halted within a block, it only knows the block.
+            let (first, tape) = trim(&cells, head);
+            let (reference_first, reference_tape) = reference.trimmed();
+            let moved = !tape.is_empty()
+                && reference.state.is_some()
+                && first != reference_first - reference.head;
+            if tape != reference_tape || moved {
+                return Err(format!(
+                    "after {} steps the tapes of the macro machine and the reference differ",
+                    target
+                ));
+            }
+        }
+        if reference.state.is_none() || target >= max_steps {
+            return Ok(reference.steps);
+        }
+        if let Err(why) = accelerated.step() {
+            return Err(format!(
+                "after {} steps the macro machine failed: {}",
+                target, why
+            ));
+        }
+    }
+}
+
+/// Runs `tm` on `input` with `steps` steps fused until it passed
+/// `max_steps` steps and compares its configuration to the reference after
+/// every transition, and the tapes from time to time. Returns the number of
+/// steps compared.
+pub fn check_fused(
+    tm: &TuringMachine,
+    input: &[TapeEntry],
+    steps: usize,
+    max_steps: u128,
+) -> Result<u128, String> {
+    let mut engine = tm.clone();
+    engine.set_reject_undefined(true);
+    if !input.is_empty() {
+        engine.set_input(input);
+    }
+    let mut fusion = Fusion::new(tm, steps)?;
+    let mut reference = Reference::new(tm, input);
+    let mut next_tape_check = 0;
+
+    loop {
+        let stepped = engine.step_fused(&mut fusion);
+        while reference.steps < engine.num_steps && reference.step() {}
+        if !stepped {
+            // Lets the reference find out it has no instruction either.
+            reference.step();
+        }
+        let head = engine.head() as i64 - engine.origin() as i64;
+        if engine.state() != reference.state
+            || engine.halt_reason != reference.halt_reason
+            || head != reference.head
+            || engine.num_steps != reference.steps
+        {
+            return Err(format!(
+                "after {} steps the fused engine is in {} at {}, the reference in {} at {}",
+                reference.steps,
+                name(tm, engine.state(), engine.halt_reason),
+                head,
+                name(tm, reference.state, reference.halt_reason),
+                reference.head
+            ));
+        }
+        let stopped = !stepped || reference.steps >= max_steps;
+        if stopped || reference.steps >= next_tape_check {
+            next_tape_check = reference.steps + TAPE_CHECK_INTERVAL;
+            let cells: Vec<TapeEntry> = engine.tape().iter().copied().collect();
+            if trim(&cells, engine.origin()) != reference.trimmed() {
+                return Err(format!(
+                    "after {} steps the tapes of the fused engine and the reference differ",
+                    reference.steps
+                ));
+            }
+        }
+        if stopped {
+            return Ok(reference.steps);
+        }
+    }
+}
+
+#[test]
+fn test_reference() {
+    use std::path::Path;
+
+    for path in [
+        "examples/busy_bever/busy_bever_3.turing",
+        "examples/busy_bever/busy_bever_4.turing",
+        "examples/busy_bever/busy_bever_2_states_3_symbols.turing",
+    ] {
+        let tm = TuringMachine::new(Path::new(path));
+        let steps = check_engine(&tm, &[], 10_000).unwrap();
+        for accel in [Accel::Block(1), Accel::Block(3), Accel::Auto] {
+            assert_eq!(check_accel(&tm, &[], accel, 10_000), Ok(steps), "{path}");
+        }
+        for fused in [1, 3, 8] {
+            assert!(check_fused(&tm, &[], fused, 10_000).is_ok(), "{path}");
+        }
+    }
+
+    let tm = TuringMachine::new(Path::new("examples/recognizers/1n2n.turing"));
+    assert!(check_engine(&tm, &[1, 2, 1], 1000).is_ok());
+    assert!(check_accel(&tm, &[1, 1, 2, 2], Accel::Block(2), 1000).is_ok());
+    assert_eq!(
+        check_fused(&tm, &[1, 1, 2, 1], 4, 1000),
+        check_engine(&tm, &[1, 1, 2, 1], 1000)
+    );
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Write,
+};
+
+use crate::{golden::Trace, turing::TapeEntry};
+
+/// Help text of the commands of [`Replay::execute`].
+pub const HELP: &str = "\
+Commands:
+  step [N]        replay N steps, 1 by default
+  back [N]        go back N steps, 1 by default
+  goto-step N     jump to step N, forward or back
+  seek P%         jump to P percent of the trace
+  play [N]        replay N steps, to the end by default, showing them at
+                  the speed
+  speed S         show S steps per second when playing, 10 by default
+  tape            print the whole tape
+  help            print this help
+  quit            leave the replay";
+
+/// Cells shown on either side of the head.
+const CONTEXT: usize = 30;
+/// Most lines printed per second when playing, more steps are left out.
+const MAX_FRAMES: u128 = 60;
+
+/// Where the replay is: the configuration after `step` steps, rebuilt from
+/// the steps of the trace.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Cursor {
+    step: u128,
+    /// Index of the run of the next step, and how many steps of it are
+    /// done.
+    run: usize,
+    done: u128,
+    state: Option<usize>,
+    tape: VecDeque<TapeEntry>,
+    head: usize,
+}
+
+/// Plays back a golden trace recorded by `run --record-golden`, without
+/// the machine, from the tape it started on.
+///
+/// Like the [`crate::debugger::Debugger`], the configuration is copied
+/// every `every` steps going forward, so any step is at most `every` steps
+/// of the trace away.
+pub struct Replay {
+    trace: Trace,
+    /// Names of the states, numbered otherwise.
+    names: Vec<String>,
+    blank: TapeEntry,
+    cursor: Cursor,
+    every: u128,
+    snapshots: BTreeMap<u128, Cursor>,
+    /// Steps per second when playing.
+    speed: f64,
+}
+
+impl Replay {
+    pub fn new(trace: Trace, input: &[TapeEntry], blank: TapeEntry, every: u128) -> Self {
+        let mut tape: VecDeque<TapeEntry> = input.iter().copied().collect();
+        if tape.is_empty() {
+            tape.push_back(blank);
+        }
+        let cursor = Cursor {
+            step: 0,
+            run: 0,
+            done: 0,
+            state: Some(0),
+            tape,
+            head: 0,
+        };
+        Replay {
+            trace,
+            names: vec![],
+            blank,
+            snapshots: BTreeMap::from([(0, cursor.clone())]),
+            cursor,
+            every: every.max(1),
+            speed: 10.0,
+        }
+    }
+
+    /// Names the states, by their numbers in the trace.
+    pub fn set_names(&mut self, names: Vec<String>) {
+        self.names = names;
+    }
+
+    /// Replays up to `steps` steps, fewer at the end of the trace, taking
+    /// snapshots on the way.
+    pub fn forward(&mut self, steps: u128) -> u128 {
+        let start = self.cursor.step;
+        while self.cursor.step - start < steps {
+            let Some((step, count)) = self.trace.runs.get(self.cursor.run) else {
+                break;
+            };
+            let cursor = &mut self.cursor;
+            cursor.tape[cursor.head] = step.written;
+            let head = cursor.head as i64 + step.moved;
+            if head < 0 {
+                for _ in head..0 {
+                    cursor.tape.push_front(self.blank);
+                }
+                cursor.head = 0;
+            } else {
+                cursor.head = head as usize;
+                while cursor.head >= cursor.tape.len() {
+                    cursor.tape.push_back(self.blank);
+                }
+            }
+            cursor.state = step.state;
+            cursor.step += 1;
+            cursor.done += 1;
+            if cursor.done == *count {
+                (cursor.run, cursor.done) = (cursor.run + 1, 0);
+            }
+            if cursor.step.is_multiple_of(self.every) {
+                self.snapshots
+                    .entry(cursor.step)
+                    .or_insert_with(|| cursor.clone());
+            }
+        }
+        self.cursor.step - start
+    }
+
+    /// Goes to the configuration after `step` steps.
+    pub fn goto(&mut self, step: u128) -> Result<(), String> {
+        if step > self.trace.steps {
+            return Err(format!("the trace ends after {} steps", self.trace.steps));
+        }
+        let (snapshot, cursor) = self
+            .snapshots
+            .range(..=step)
+            .next_back()
+            .expect("there is a snapshot of the start");
+        if step < self.cursor.step || *snapshot > self.cursor.step {
+            self.cursor = cursor.clone();
+        }
+        self.forward(step - self.cursor.step);
+        Ok(())
+    }
+
+    /// The step, the state and the cells around the head, in brackets.
+    pub fn format(&self, context: usize) -> String {
+        let cursor = &self.cursor;
+        let state = match cursor.state {
+            Some(state) => self.names.get(state).cloned().unwrap_or(state.to_string()),
+            None => match self.trace.halt {
+                Some(reason) if cursor.step == self.trace.steps => reason.name().to_string(),
+                _ => "Halt".to_string(),
+            },
+        };
+        let from = cursor.head.saturating_sub(context);
+        let to = (cursor.head + context + 1).min(cursor.tape.len());
+        let mut line = format!("step {:>10}  {:>6} ", cursor.step, state);
+        if from > 0 {
+            line.push_str(" …");
+        }
+        for cell in from..to {
+            let _ = match cell == cursor.head {
+                true => write!(line, " [{}]", cursor.tape[cell]),
+                false => write!(line, " {}", cursor.tape[cell]),
+            };
+        }
+        if to < cursor.tape.len() {
+            line.push_str(" …");
+        }
+        line
+    }
+
+    /// Replays `steps` steps, calling `show` with a line for every step
+    /// shown, which are as many per second as the speed allows, and how
+    /// long to wait before the next one.
+    pub fn play(&mut self, steps: u128, mut show: impl FnMut(&str, std::time::Duration)) {
+        let frames = (self.speed.max(1.0) as u128).clamp(1, MAX_FRAMES);
+        let per_frame = (self.speed.max(1.0) as u128 / frames).max(1);
+        let wait = std::time::Duration::from_secs_f64(per_frame as f64 / self.speed.max(1e-3));
+        let end = self.cursor.step.saturating_add(steps);
+        while self.cursor.step < end {
+            if self.forward(per_frame.min(end - self.cursor.step)) == 0 {
+                break;
+            }
+            show(&self.format(CONTEXT), wait);
+        }
+    }
+
+    /// Executes a command described in [`HELP`], showing the lines of
+    /// `play` with `show`, and gives what to print.
+    pub fn execute(
+        &mut self,
+        line: &str,
+        show: impl FnMut(&str, std::time::Duration),
+    ) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let count = |word: Option<&&str>, default: u128| match word {
+            Some(word) => word
+                .replace('_', "")
+                .parse::<u128>()
+                .map_err(|why| format!("invalid number '{word}': {why}")),
+            None => Ok(default),
+        };
+        match words.as_slice() {
+            [] => Ok(String::new()),
+            ["step" | "s", rest @ ..] if rest.len() <= 1 => {
+                self.forward(count(rest.first(), 1)?);
+                Ok(self.format(CONTEXT))
+            }
+            ["back" | "b", rest @ ..] if rest.len() <= 1 => {
+                self.goto(self.cursor.step.saturating_sub(count(rest.first(), 1)?))?;
+                Ok(self.format(CONTEXT))
+            }
+            ["goto-step" | "g", step] => {
+                self.goto(count(Some(step), 0)?)?;
+                Ok(self.format(CONTEXT))
+            }
+            ["seek", percent] => {
+                let percent: f64 = percent
+                    .trim_end_matches('%')
+                    .parse()
+                    .map_err(|why| format!("invalid percentage '{percent}': {why}"))?;
+                if !(0.0..=100.0).contains(&percent) {
+                    return Err(format!("invalid percentage '{percent}'"));
+                }
+                self.goto((self.trace.steps as f64 * percent / 100.0) as u128)?;
+                Ok(self.format(CONTEXT))
+            }
+            ["play" | "p", rest @ ..] if rest.len() <= 1 => {
+                self.play(count(rest.first(), u128::MAX)?, show);
+                Ok(String::new())
+            }
+            ["speed", speed] => match speed.parse::<f64>() {
+                Ok(speed) if speed > 0.0 => {
+                    self.speed = speed;
+                    Ok(format!("Playing {speed} steps per second"))
+                }
+                _ => Err(format!("invalid speed '{speed}'")),
+            },
+            ["tape" | "t"] => Ok(self.format(usize::MAX / 2)),
+            ["help" | "h"] => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command '{}', try 'help'", line.trim())),
+        }
+    }
+}
+
+#[test]
+fn test_replay() {
+    use std::path::Path;
+
+    use crate::{golden::Recorder, turing::TuringMachine};
+
+    let bb4 = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
+    let mut tm = bb4.clone();
+    let mut trace = vec![];
+    let mut recorder = Recorder::new(&mut trace, &tm).unwrap();
+    let mut tapes = vec![tm.tape().clone()];
+    while tm.step() {
+        recorder.record(&tm).unwrap();
+        tapes.push(tm.tape().clone());
+    }
+    recorder.finish(&tm).unwrap();
+
+    let mut replay = Replay::new(Trace::read(trace.as_slice()).unwrap(), &[], 0, 10);
+    replay.set_names(bb4.states().to_vec());
+    assert_eq!(replay.forward(200), 107);
+    assert_eq!(replay.cursor.tape, tapes[107]);
+    for step in [23, 5, 100, 0, 64] {
+        replay.goto(step).unwrap();
+        assert_eq!(replay.cursor.tape, tapes[step as usize], "step {step}");
+    }
+    assert!(replay.goto(108).is_err());
+
+    let mut shown = vec![];
+    let mut show = |line: &str, _| shown.push(line.to_string());
+    assert!(replay
+        .execute("goto-step 1", &mut show)
+        .unwrap()
+        .starts_with("step          1       B  1 [0]"));
+    replay.execute("speed 1000", &mut show).unwrap();
+    replay.execute("play", &mut show).unwrap();
+    assert_eq!(replay.cursor.step, 107);
+    replay.execute("seek 50%", &mut show).unwrap();
+    assert_eq!(replay.cursor.step, 53);
+    assert!(replay.execute("seek 150%", &mut show).is_err());
+    assert!(replay.execute("jump", &mut show).is_err());
+    // A thousand steps per second are shown in frames of 16 steps.
+    assert_eq!(shown.len(), 7);
+    assert!(shown.last().unwrap().contains("Halt"));
+}
@@ -0,0 +1,528 @@
+use std::{collections::BTreeMap, f64::consts::PI, fmt::Write};
+
+use crate::{
+    info,
+    json::Json,
+    turing::{HaltReason, TapeEntry, TuringMachine},
+};
+
+/// Radius of a state in the graph.
+const NODE_RADIUS: f64 = 22.0;
+
+/// A step of the run shown in the report.
+struct Step {
+    state: String,
+    /// Position of the head before the step, relative to the starting cell.
+    position: isize,
+    read: TapeEntry,
+    write: TapeEntry,
+    direction: &'static str,
+    /// The new state, or how the machine halted.
+    next: String,
+}
+
+/// Escapes `text` for HTML, in text and in attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Position of the head relative to the starting cell.
+fn position(tm: &TuringMachine) -> isize {
+    tm.head() as isize - tm.origin() as isize
+}
+
+/// Writes a standalone HTML page about `tm`, titled `name`: its summary
+/// and transitions, its state graph as SVG, a table of the first `steps`
+/// steps from the tape with `input` and an animation of the tape during
+/// them. The page needs no server or network, so it can be shared as a
+/// single file.
+pub fn report(tm: &TuringMachine, name: &str, input: &[TapeEntry], steps: u128) -> String {
+    let start = start(tm, input);
+    let (table, outcome) = trace(&start, steps);
+
+    let mut out = String::new();
+    let title = escape(name);
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    );
+    let _ = writeln!(
+        out,
+        "<h2>Machine</h2>\n<pre>{}</pre>\n<pre>{}</pre>",
+        escape(info::info(tm).to_string().trim_end()),
+        escape(&tm.to_turing())
+    );
+    let _ = writeln!(out, "<h2>State graph</h2>\n{}", state_graph(tm));
+    let _ = writeln!(
+        out,
+        "<h2>Tape</h2>\n<p>{}</p>\n<div id=\"tape\" class=\"tape\"></div>\n\
+         <p><button id=\"play\">Play</button> \
+         <input id=\"frame\" type=\"range\" min=\"0\" max=\"{}\" value=\"0\"> \
+         <span id=\"status\"></span></p>",
+        escape(&outcome),
+        table.len()
+    );
+    let _ = writeln!(out, "<h2>Steps</h2>\n<table>");
+    out.push_str(
+        "<tr><th>Step</th><th>State</th><th>Head</th><th>Read</th><th>Write</th>\
+         <th>Move</th><th>Next</th></tr>\n",
+    );
+    for (index, step) in table.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            index + 1,
+            escape(&step.state),
+            step.position,
+            escape(&tm.glyph(step.read)),
+            escape(&tm.glyph(step.write)),
+            step.direction,
+            escape(&step.next)
+        );
+    }
+    out.push_str("</table>\n");
+
+    // The animation replays the writes on the starting tape.
+    let glyphs = tm
+        .glyphs()
+        .iter()
+        .map(|(entry, glyph)| (entry.to_string(), glyph.as_str().into()))
+        .collect();
+    let data = Json::object([
+        (
+            "tape",
+            Json::Array(start.tape().iter().map(|entry| (*entry).into()).collect()),
+        ),
+        ("blank", tm.blank().into()),
+        ("glyphs", Json::Object(glyphs)),
+        (
+            "start",
+            start
+                .state()
+                .map_or(Json::Null, |state| tm.states()[state].as_str().into()),
+        ),
+        (
+            "steps",
+            Json::Array(
+                table
+                    .iter()
+                    .map(|step| {
+                        Json::Array(vec![
+                            step.position.into(),
+                            step.write.into(),
+                            step.direction.into(),
+                            step.next.as_str().into(),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ]);
+    // A `</script>` in a state name must not end the script.
+    let data = data.to_string().replace("</", "<\\/");
+    let _ = write!(
+        out,
+        "<script>\nconst run = {data};\n{SCRIPT}</script>\n</body>\n</html>\n"
+    );
+    out
+}
+
+/// A copy of `tm` on the tape with `input`, where missing transitions
+/// reject.
+fn start(tm: &TuringMachine, input: &[TapeEntry]) -> TuringMachine {
+    let mut tm = tm.clone();
+    tm.set_reject_undefined(true);
+    tm.set_input(input);
+    tm
+}
+
+/// The first `steps` steps of `start`, and how the run ended up.
+fn trace(start: &TuringMachine, steps: u128) -> (Vec<Step>, String) {
+    let tm = start;
+    let mut run = start.clone();
+    let mut table = vec![];
+    while run.num_steps < steps && !run.is_halted() {
+        let (state, position, read) = (run.state(), position(&run), run.tape()[run.head()]);
+        if !run.step() {
+            break;
+        }
+        let Some(index) = run.last_instruction() else {
+            break;
+        };
+        let instruction = &run.instructions()[index];
+        table.push(Step {
+            state: state.map_or(String::new(), |state| tm.states()[state].clone()),
+            position,
+            read,
+            write: instruction.new_entry,
+            direction: instruction.direction.letter(),
+            next: match instruction.new_state {
+                Some(state) => tm.states()[state].clone(),
+                None => instruction.halt.name().to_string(),
+            },
+        });
+    }
+    let outcome = match run.halt_reason.filter(|_| run.is_halted()) {
+        Some(reason) => format!("{} after {} steps", reason, run.num_steps),
+        None => format!("still running after {} steps", run.num_steps),
+    };
+    (table, outcome)
+}
+
+/// The state graph as SVG, with the states on a circle followed by a node
+/// for every way the machine halts, and an edge labeled `read/write move`
+/// for every transition.
+pub fn state_graph(tm: &TuringMachine) -> String {
+    let halts = halts(tm);
+    let mut names: Vec<String> = tm.states().to_vec();
+    names.extend(halts.iter().map(|reason| reason.name().to_string()));
+    let target = |state: Option<usize>, halt: HaltReason| match state {
+        Some(state) => state,
+        None => tm.states().len() + halts.iter().position(|h| *h == halt).unwrap(),
+    };
+
+    let count = names.len().max(1) as f64;
+    let radius = (count * 30.0).max(80.0);
+    let center = radius + 70.0;
+    let node = |index: usize| {
+        let angle = 2.0 * PI * index as f64 / count - PI / 2.0;
+        (center + radius * angle.cos(), center + radius * angle.sin())
+    };
+
+    // Labels of the transitions, grouped by the pair of states.
+    let mut edges: BTreeMap<(usize, usize), Vec<String>> = BTreeMap::new();
+    for instruction in tm.instructions() {
+        edges
+            .entry((
+                instruction.state,
+                target(instruction.new_state, instruction.halt),
+            ))
+            .or_default()
+            .push(format!(
+                "{}/{}{}",
+                tm.glyph(instruction.entry),
+                tm.glyph(instruction.new_entry),
+                instruction.direction.letter()
+            ));
+    }
+
+    let size = 2.0 * center;
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size:.0}\" height=\"{size:.0}\" \
+         viewBox=\"0 0 {size:.0} {size:.0}\" class=\"graph\">\n<defs><marker id=\"arrow\" \
+         viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"7\" markerHeight=\"7\" \
+         orient=\"auto-start-reverse\"><path d=\"M 0 0 L 10 5 L 0 10 z\"/></marker></defs>"
+    );
+    for ((from, to), labels) in &edges {
+        let label = escape(&labels.join(", "));
+        let (x, y) = node(*from);
+        if from == to {
+            // A loop on the outside of the circle.
+            let (ux, uy) = unit(x - center, y - center);
+            let (vx, vy) = (-uy, ux);
+            let at = |along: f64, across: f64| {
+                (x + ux * along + vx * across, y + uy * along + vy * across)
+            };
+            let (s, c1, c2, e) = (
+                at(NODE_RADIUS * 0.8, -NODE_RADIUS * 0.6),
+                at(NODE_RADIUS + 45.0, -30.0),
+                at(NODE_RADIUS + 45.0, 30.0),
+                at(NODE_RADIUS * 0.8, NODE_RADIUS * 0.6),
+            );
+            let l = at(NODE_RADIUS + 45.0, 0.0);
+            let _ = writeln!(
+                out,
+                "<path d=\"M {:.1} {:.1} C {:.1} {:.1} {:.1} {:.1} {:.1} {:.1}\" marker-end=\"url(#arrow)\"/>\
+                 <text x=\"{:.1}\" y=\"{:.1}\">{label}</text>",
+                s.0, s.1, c1.0, c1.1, c2.0, c2.1, e.0, e.1, l.0, l.1
+            );
+            continue;
+        }
+        // Bent to the side, so edges in both directions don't overlap.
+        let (x2, y2) = node(*to);
+        let (dx, dy) = unit(x2 - x, y2 - y);
+        let (cx, cy) = ((x + x2) / 2.0 - dy * 30.0, (y + y2) / 2.0 + dx * 30.0);
+        let (sx, sy) = unit(cx - x, cy - y);
+        let (ex, ey) = unit(cx - x2, cy - y2);
+        let (lx, ly) = (
+            0.25 * x + 0.5 * cx + 0.25 * x2,
+            0.25 * y + 0.5 * cy + 0.25 * y2,
+        );
+        let _ = writeln!(
+            out,
+            "<path d=\"M {:.1} {:.1} Q {cx:.1} {cy:.1} {:.1} {:.1}\" marker-end=\"url(#arrow)\"/>\
+             <text x=\"{lx:.1}\" y=\"{ly:.1}\">{label}</text>",
+            x + sx * NODE_RADIUS,
+            y + sy * NODE_RADIUS,
+            x2 + ex * NODE_RADIUS,
+            y2 + ey * NODE_RADIUS
+        );
+    }
+    for (index, name) in names.iter().enumerate() {
+        let (x, y) = node(index);
+        let class = match index < tm.states().len() {
+            true => "state",
+            false => "halt",
+        };
+        let _ = writeln!(
+            out,
+            "<g class=\"{class}\"><circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"{NODE_RADIUS}\"/>\
+             <text x=\"{x:.1}\" y=\"{y:.1}\">{}</text></g>",
+            escape(name)
+        );
+    }
+    out.push_str("</svg>");
+    out
+}
+
+/// Escapes `text` for LaTeX outside of math mode.
+pub fn latex_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The state graph as a TikZ picture with the `automata` library, laid
+/// out like [`state_graph`]. Edges are labeled `read/write,move`.
+pub fn tikz(tm: &TuringMachine) -> String {
+    let halts = halts(tm);
+    let mut names: Vec<String> = tm.states().to_vec();
+    names.extend(halts.iter().map(|reason| reason.name().to_string()));
+    let count = names.len().max(1);
+    let radius = (count as f64 * 0.8).max(2.0);
+    let angle = |index: usize| 90 - (360 * index / count) as i64;
+
+    let mut out = String::from(
+        "% Needs \\usepackage{tikz} and \\usetikzlibrary{automata, arrows.meta}.\n\
+         \\begin{tikzpicture}[->, >=Stealth, auto, semithick, \
+         every state/.style={minimum size=1cm}]\n",
+    );
+    for (index, name) in names.iter().enumerate() {
+        let style = match index {
+            0 if !tm.states().is_empty() => "state, initial",
+            _ if index >= tm.states().len() => "state, accepting",
+            _ => "state",
+        };
+        let _ = writeln!(
+            out,
+            "  \\node[{style}] (q{index}) at ({}:{radius:.1}cm) {{{}}};",
+            angle(index),
+            latex_escape(name)
+        );
+    }
+
+    let mut edges: BTreeMap<(usize, usize), Vec<String>> = BTreeMap::new();
+    for instruction in tm.instructions() {
+        let target = match instruction.new_state {
+            Some(state) => state,
+            None => tm.states().len() + halts.iter().position(|h| *h == instruction.halt).unwrap(),
+        };
+        edges
+            .entry((instruction.state, target))
+            .or_default()
+            .push(format!(
+                "{}/{},{}",
+                latex_escape(&tm.glyph(instruction.entry)),
+                latex_escape(&tm.glyph(instruction.new_entry)),
+                instruction.direction.letter()
+            ));
+    }
+    out.push_str("  \\path");
+    for ((from, to), labels) in &edges {
+        let style = match (from == to, edges.contains_key(&(*to, *from))) {
+            (true, _) => format!(
+                "loop, out={}, in={}, looseness=6",
+                angle(*from) + 20,
+                angle(*from) - 20
+            ),
+            (false, true) => "bend left".to_string(),
+            (false, false) => String::new(),
+        };
+        let _ = write!(
+            out,
+            "\n    (q{from}) edge[{style}] node {{\\footnotesize {}}} (q{to})",
+            labels.join("; ")
+        );
+    }
+    out.push_str(";\n\\end{tikzpicture}\n");
+    out
+}
+
+/// The first `steps` steps from the tape with `input` as a LaTeX table.
+pub fn tikz_trace(tm: &TuringMachine, input: &[TapeEntry], steps: u128) -> String {
+    let (table, outcome) = trace(&start(tm, input), steps);
+    let mut out = String::from(
+        "\\begin{tabular}{rlrcccl}\n\
+         Step & State & Head & Read & Write & Move & Next \\\\\n\\hline\n",
+    );
+    for (index, step) in table.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "{} & {} & {} & {} & {} & {} & {} \\\\",
+            index + 1,
+            latex_escape(&step.state),
+            step.position,
+            latex_escape(&tm.glyph(step.read)),
+            latex_escape(&tm.glyph(step.write)),
+            step.direction,
+            latex_escape(&step.next)
+        );
+    }
+    let _ = write!(
+        out,
+        "\\hline\n\\multicolumn{{7}}{{l}}{{{}}}\n\\end{{tabular}}\n",
+        latex_escape(&outcome)
+    );
+    out
+}
+
+/// The ways `tm` halts, in the order of its first transition for each,
+/// which get a node after the states in the graphs.
+fn halts(tm: &TuringMachine) -> Vec<HaltReason> {
+    let mut halts = vec![];
+    for instruction in tm.instructions() {
+        if instruction.new_state.is_none() && !halts.contains(&instruction.halt) {
+            halts.push(instruction.halt);
+        }
+    }
+    halts
+}
+
+/// The vector of length 1 in the direction of `(x, y)`.
+fn unit(x: f64, y: f64) -> (f64, f64) {
+    let length = (x * x + y * y).sqrt();
+    match length > 0.0 {
+        true => (x / length, y / length),
+        false => (0.0, -1.0),
+    }
+}
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+pre { background: #f4f4f4; padding: 0.5em; }
+table { border-collapse: collapse; }
+td, th { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: center; }
+.graph path { fill: none; stroke: #555; }
+.graph marker path { fill: #555; stroke: none; }
+.graph text { font-size: 12px; text-anchor: middle; dominant-baseline: middle; }
+.graph circle { fill: #e8f0fe; stroke: #1a73e8; stroke-width: 2; }
+.graph .halt circle { fill: #fce8e6; stroke: #d93025; stroke-width: 4; }
+.tape { display: flex; overflow-x: auto; font-family: monospace; font-size: 1.2em; }
+.tape span { border: 1px solid #999; min-width: 1.6em; padding: 0.3em 0; text-align: center; }
+.tape .head { background: #fbbc04; font-weight: bold; }
+";
+
+const SCRIPT: &str = "
+const glyph = entry => run.glyphs[entry] ?? String(entry);
+let lowest = 0, highest = run.tape.length - 1, head = 0;
+for (const [position, , direction] of run.steps) {
+  head = position + (direction === 'R' ? 1 : -1);
+  lowest = Math.min(lowest, head);
+  highest = Math.max(highest, head);
+}
+const tape = document.getElementById('tape');
+const slider = document.getElementById('frame');
+const status = document.getElementById('status');
+function show(frame) {
+  const cells = new Map(run.tape.map((entry, position) => [position, entry]));
+  let head = 0, state = run.start;
+  for (const [position, write, direction, next] of run.steps.slice(0, frame)) {
+    cells.set(position, write);
+    head = position + (direction === 'R' ? 1 : -1);
+    state = next;
+  }
+  tape.replaceChildren();
+  for (let position = lowest; position <= highest; position++) {
+    const cell = document.createElement('span');
+    cell.textContent = glyph(cells.get(position) ?? run.blank);
+    if (position === head) cell.className = 'head';
+    tape.appendChild(cell);
+  }
+  status.textContent = 'step ' + frame + ', state ' + state;
+}
+let timer = null;
+document.getElementById('play').onclick = event => {
+  if (timer) {
+    clearInterval(timer);
+    timer = null;
+    event.target.textContent = 'Play';
+    return;
+  }
+  event.target.textContent = 'Pause';
+  timer = setInterval(() => {
+    if (Number(slider.value) >= run.steps.length) slider.value = 0;
+    else slider.value = Number(slider.value) + 1;
+    show(Number(slider.value));
+  }, 200);
+};
+slider.oninput = () => show(Number(slider.value));
+show(0);
+";
+
+#[test]
+fn test_tikz() {
+    let tm =
+        TuringMachine::parse("A 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> Halt 1 R").unwrap();
+    let picture = tikz(&tm);
+    let lines: Vec<&str> = picture.lines().collect();
+    assert!(lines.contains(&"  \\node[state, initial] (q0) at (90:2.4cm) {A};"));
+    assert!(lines.contains(&"  \\node[state, accepting] (q2) at (-150:2.4cm) {Halt};"));
+    assert!(lines.contains(&"    (q0) edge[bend left] node {\\footnotesize 0/1,R; 1/1,L} (q1)"));
+    assert!(lines.contains(&"    (q1) edge[] node {\\footnotesize 1/1,R} (q2);"));
+    assert!(picture.ends_with("\\end{tikzpicture}\n"));
+
+    let table = tikz_trace(&tm, &[], 2);
+    assert!(table.contains("\n1 & A & 0 & 0 & 1 & R & B \\\\\n"));
+    assert!(table.contains("{still running after 2 steps}"));
+    assert_eq!(latex_escape("q_1 & 50%"), "q\\_1 \\& 50\\%");
+}
+
+#[test]
+fn test_report() {
+    let tm =
+        TuringMachine::parse("A 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> Halt 1 R").unwrap();
+    let html = report(&tm, "<BB2>", &[], 100);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>&lt;BB2&gt;</title>"));
+    assert!(html.contains("halted after 6 steps"));
+    assert!(html.contains(
+        "<tr><td>6</td><td>B</td><td>-1</td><td>1</td><td>1</td><td>R</td><td>Halt</td></tr>"
+    ));
+    assert!(html.contains("max=\"6\""));
+    assert!(html.contains("\"steps\":[[0,1,\"R\",\"B\"],"));
+    assert!(html.ends_with("</html>\n"));
+
+    let svg = state_graph(&tm);
+    assert_eq!(svg.matches("<circle").count(), 3);
+    assert_eq!(svg.matches("<path d=\"M").count(), 4);
+    assert!(svg.contains(">0/1R, 1/1L</text>"));
+    assert!(svg.contains("<g class=\"halt\">"));
+
+    assert_eq!(escape("a<b & \"c\""), "a&lt;b &amp; &quot;c&quot;");
+    let html = report(&tm, "short", &[], 2);
+    assert!(html.contains("still running after 2 steps"));
+}
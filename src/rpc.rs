@@ -0,0 +1,216 @@
+use crate::{
+    http::{Request, Response},
+    json::Json,
+};
+
+/// Name of the service of `proto/touring.proto`, whose calls are served at
+/// `POST /twirp/touring.Simulator/{Method}`.
+pub const SERVICE: &str = "touring.Simulator";
+
+/// Answers a call of the `Simulator` service in the Twirp protocol with
+/// JSON bodies, for Twirp clients generated from the schema. This isn't
+/// gRPC: without HTTP/2 and the binary encoding of protobuf, gRPC clients
+/// can't connect.
+///
+/// Every call is turned into the request of the HTTP API doing the same and
+/// answered by `route`, so it has the same budgets. Messages follow the
+/// JSON mapping of proto3: the fields are in lower camel case and 64 bit
+/// numbers are strings, which are also accepted as numbers.
+pub fn call(method: &str, request: &Request, route: impl Fn(&Request) -> Response) -> Response {
+    if request
+        .header("Content-Type")
+        .is_some_and(|content_type| !content_type.starts_with("application/json"))
+    {
+        return error(404, "bad_route", "only JSON bodies are supported");
+    }
+    let input = match std::str::from_utf8(&request.body)
+        .map_err(|why| why.to_string())
+        .and_then(Json::parse)
+    {
+        Ok(input) => input,
+        Err(why) => return error(400, "malformed", &why),
+    };
+    let call = match method {
+        "LoadMachine" => input
+            .get("definition")
+            .and_then(Json::as_str)
+            .ok_or("missing definition".to_string())
+            .map(|definition| http("POST", "/machines", vec![], definition)),
+        "StepN" => id(&input).and_then(|id| {
+            let query = number(&input, "count")?.map(|count| ("count", count));
+            Ok(http("POST", &format!("/machines/{id}/step"), query, ""))
+        }),
+        "RunWithBudget" => id(&input).and_then(|id| {
+            let query = number(&input, "maxSteps")?.map(|steps| ("max_steps", steps));
+            Ok(http("POST", &format!("/machines/{id}/run"), query, ""))
+        }),
+        "GetTape" => id(&input).and_then(|id| {
+            let query = [
+                ("from", number(&input, "from")?),
+                ("to", number(&input, "to")?),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?)));
+            Ok(http("GET", &format!("/machines/{id}/tape"), query, ""))
+        }),
+        "GetStats" => id(&input).map(|id| http("GET", &format!("/machines/{id}"), vec![], "")),
+        _ => return error(404, "bad_route", &format!("no method {SERVICE}/{method}")),
+    };
+    let call = match call {
+        Ok(call) => call,
+        Err(why) => return error(400, "invalid_argument", &why),
+    };
+
+    let response = route(&call);
+    let output = String::from_utf8(response.body)
+        .map_err(|why| why.to_string())
+        .and_then(|body| Json::parse(&body));
+    match (response.status, output) {
+        (200 | 201, Ok(output)) if method == "GetTape" => Response::json(200, &tape(&output)),
+        (200 | 201, Ok(output)) => Response::json(200, &stats(&output)),
+        (status, output) => {
+            let message = output
+                .ok()
+                .and_then(|output| Some(output.get("error")?.as_str()?.to_string()))
+                .unwrap_or_default();
+            let code = match status {
+                400 => "invalid_argument",
+                404 => "not_found",
+                413 | 429 => "resource_exhausted",
+                503 => "unavailable",
+                _ => "internal",
+            };
+            error(status, code, &message)
+        }
+    }
+}
+
+/// A Twirp error.
+fn error(status: u16, code: &str, message: &str) -> Response {
+    Response::json(
+        status,
+        &Json::object([("code", code.into()), ("msg", message.into())]),
+    )
+}
+
+fn http<'a>(
+    method: &str,
+    path: &str,
+    query: impl IntoIterator<Item = (&'a str, i128)>,
+    body: &str,
+) -> Request {
+    Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        headers: vec![],
+        body: body.as_bytes().to_vec(),
+    }
+}
+
+/// A number of a message, which proto3 writes as a string for 64 bits.
+fn number(input: &Json, field: &str) -> Result<Option<i128>, String> {
+    match input.get(field) {
+        None | Some(Json::Null) => Ok(None),
+        Some(Json::Int(n)) => Ok(Some(*n)),
+        Some(Json::String(s)) => s
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("invalid {field} '{s}'")),
+        Some(_) => Err(format!("{field} is not a number")),
+    }
+}
+
+fn id(input: &Json) -> Result<i128, String> {
+    number(input, "id")?.ok_or("missing id".to_string())
+}
+
+/// The `Stats` message of the statistics of the HTTP API.
+fn stats(output: &Json) -> Json {
+    let mut fields = vec![];
+    for (from, to, is_long) in [
+        ("id", "id", true),
+        ("states", "states", false),
+        ("state", "state", false),
+        ("steps", "steps", true),
+        ("head", "head", true),
+        ("halted", "halted", false),
+        ("halt_reason", "haltReason", false),
+        ("edge_hits", "edgeHits", true),
+        ("stop", "stop", false),
+        ("stop_reason", "stopReason", false),
+    ] {
+        match output.get(from) {
+            None | Some(Json::Null) => {}
+            Some(Json::Int(n)) if is_long => fields.push((to.to_string(), n.to_string().into())),
+            Some(value) => fields.push((to.to_string(), value.clone())),
+        }
+    }
+    Json::Object(fields)
+}
+
+/// The `Tape` message of the tape of the HTTP API.
+fn tape(output: &Json) -> Json {
+    let long = |key| output.get(key).map_or(Json::Null, |n| n.to_string().into());
+    Json::object([
+        ("from", long("from")),
+        ("head", long("head")),
+        (
+            "cells",
+            output.get("tape").cloned().unwrap_or(Json::Array(vec![])),
+        ),
+    ])
+}
+
+#[test]
+fn test_rpc() {
+    use crate::{sandbox::Limits, server::Server};
+
+    let server = Server::new(Limits {
+        max_steps: 1000,
+        ..Limits::NONE
+    });
+    let rpc = |method: &str, body: &str| {
+        let request = http("POST", &format!("/twirp/{SERVICE}/{method}"), vec![], body);
+        let response = server.handle(&request);
+        let body = Json::parse(&String::from_utf8(response.body).unwrap()).unwrap();
+        (response.status, body)
+    };
+    let machine = std::fs::read_to_string("examples/busy_bever/busy_bever_2.turing").unwrap();
+    let (status, stats) = rpc(
+        "LoadMachine",
+        &Json::object([("definition", machine.into())]).to_string(),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(stats.str_field("id"), Ok("0"));
+    assert_eq!(stats.str_field("state"), Ok("A"));
+
+    let (_, stats) = rpc("StepN", r#"{"id": "0", "count": "2"}"#);
+    assert_eq!(stats.str_field("steps"), Ok("2"));
+    let (_, stats) = rpc("RunWithBudget", r#"{"id": 0}"#);
+    assert_eq!(stats.str_field("steps"), Ok("6"));
+    assert_eq!(stats.str_field("haltReason"), Ok("Halt"));
+    assert_eq!(stats.get("state"), None);
+    let (_, stats) = rpc("GetStats", r#"{"id": "0"}"#);
+    assert_eq!(stats.str_field("edgeHits"), Ok("0"));
+    let (_, tape) = rpc("GetTape", r#"{"id": "0", "from": "-2", "to": "1"}"#);
+    assert_eq!(
+        tape.to_string(),
+        r#"{"from":"-2","head":"0","cells":[1,1,1,1]}"#
+    );
+
+    let (status, error) = rpc("GetStats", r#"{"id": "7"}"#);
+    assert_eq!((status, error.str_field("code")), (404, Ok("not_found")));
+    let (status, error) = rpc("StepN", "{}");
+    assert_eq!(
+        (status, error.str_field("code")),
+        (400, Ok("invalid_argument"))
+    );
+    let (status, error) = rpc("Fly", "{}");
+    assert_eq!((status, error.str_field("code")), (404, Ok("bad_route")));
+    let (status, error) = rpc("GetStats", "{");
+    assert_eq!((status, error.str_field("code")), (400, Ok("malformed")));
+}
@@ -0,0 +1,155 @@
+use std::time::{Duration, Instant};
+
+use crate::turing::TuringMachine;
+
+/// Steps between looking at the clock, which costs more than a step.
+const CLOCK_INTERVAL: u128 = 4096;
+
+/// Hard caps on a run of a machine from an untrusted source, like a
+/// submission to grade or a machine uploaded to the server. They hold
+/// whatever the machine does, independent of the limits of the operating
+/// system.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Limits {
+    /// Most steps performed.
+    pub max_steps: u128,
+    /// Most cells the tape may grow to.
+    pub max_tape_cells: usize,
+    /// Most time spent stepping.
+    pub max_time: Duration,
+}
+
+impl Limits {
+    /// No caps at all, for trusted machines.
+    pub const NONE: Limits = Limits {
+        max_steps: u128::MAX,
+        max_tape_cells: usize::MAX,
+        max_time: Duration::MAX,
+    };
+
+    /// The smaller of every cap of both.
+    pub fn within(self, other: Limits) -> Limits {
+        Limits {
+            max_steps: self.max_steps.min(other.max_steps),
+            max_tape_cells: self.max_tape_cells.min(other.max_tape_cells),
+            max_time: self.max_time.min(other.max_time),
+        }
+    }
+}
+
+/// Why a run under [`Limits`] stopped.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Stop {
+    /// The machine halted by itself.
+    Halted,
+    Steps,
+    TapeCells,
+    Time,
+}
+
+impl Stop {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stop::Halted => "halted",
+            Stop::Steps => "steps",
+            Stop::TapeCells => "tape_cells",
+            Stop::Time => "time",
+        }
+    }
+
+    /// The limit that stopped the run, none if the machine halted.
+    pub fn reason(&self, limits: &Limits) -> Option<String> {
+        match self {
+            Stop::Halted => None,
+            Stop::Steps => Some(format!("the limit of {} steps", limits.max_steps)),
+            Stop::TapeCells => Some(format!("the limit of {} tape cells", limits.max_tape_cells)),
+            Stop::Time => Some(format!(
+                "the time limit of {:.3} seconds",
+                limits.max_time.as_secs_f64()
+            )),
+        }
+    }
+}
+
+/// Steps `tm` until it halts or reaches one of the `limits`, counting the
+/// steps from the ones already performed. A run stopped by a limit leaves
+/// the machine in the configuration it reached, so it can be inspected and
+/// also resumed. The tape stops the run as soon as it grew past its limit,
+/// so it holds at most one cell more.
+pub fn run(tm: &mut TuringMachine, limits: &Limits) -> Stop {
+    let start = Instant::now();
+    let first = tm.num_steps;
+    let end = first.saturating_add(limits.max_steps);
+    loop {
+        if tm.tape().len() > limits.max_tape_cells {
+            return Stop::TapeCells;
+        }
+        if tm.num_steps >= end {
+            return Stop::Steps;
+        }
+        if (tm.num_steps - first).is_multiple_of(CLOCK_INTERVAL)
+            && start.elapsed() >= limits.max_time
+        {
+            return Stop::Time;
+        }
+        if !tm.step() {
+            return Stop::Halted;
+        }
+    }
+}
+
+/// A number of seconds as a command line argument.
+pub fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let seconds: f64 = s
+        .parse()
+        .map_err(|_| format!("invalid number of seconds '{s}'"))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid number of seconds '{s}'"))
+}
+
+#[test]
+fn test_sandbox() {
+    use crate::bbchallenge;
+
+    let bb4 = "1RB1LB_1LA0LC_1RZ1LD_1RD0RA";
+    let mut tm = bbchallenge::parse_machine(bb4).unwrap();
+    assert_eq!(run(&mut tm, &Limits::NONE), Stop::Halted);
+    assert_eq!(tm.num_steps, 107);
+
+    let mut tm = bbchallenge::parse_machine(bb4).unwrap();
+    let limits = Limits {
+        max_steps: 50,
+        ..Limits::NONE
+    };
+    assert_eq!(run(&mut tm, &limits), Stop::Steps);
+    assert_eq!(tm.num_steps, 50);
+    assert_eq!(
+        Stop::Steps.reason(&limits).as_deref(),
+        Some("the limit of 50 steps")
+    );
+    // Resumes where it stopped.
+    assert_eq!(run(&mut tm, &limits), Stop::Steps);
+    assert_eq!(tm.num_steps, 100);
+    assert_eq!(run(&mut tm, &limits), Stop::Halted);
+    assert_eq!(tm.num_steps, 107);
+
+    // Runs right forever, growing the tape by a cell every step.
+    let mut tm = bbchallenge::parse_machine("1RA---").unwrap();
+    let limits = Limits {
+        max_tape_cells: 10,
+        ..Limits::NONE
+    };
+    assert_eq!(run(&mut tm, &limits), Stop::TapeCells);
+    assert_eq!(tm.tape().len(), 11);
+    assert!(!tm.is_halted());
+
+    let limits = Limits {
+        max_time: Duration::ZERO,
+        ..Limits::NONE
+    };
+    assert_eq!(run(&mut tm, &limits), Stop::Time);
+    assert_eq!(Stop::Halted.reason(&limits), None);
+
+    assert_eq!(parse_seconds("1.5"), Ok(Duration::from_millis(1500)));
+    assert!(parse_seconds("-1").is_err());
+    assert!(parse_seconds("soon").is_err());
+}
@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+use crate::turing::TapeEntry;
+
+// The scans look at eight cells at once in a `u64`, which needs cells of a
+// single byte.
+const _: () = assert!(std::mem::size_of::<TapeEntry>() == 1);
+
+/// Cells looked at at once.
+const WORD: usize = 8;
+const LOW_BITS: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+fn word(cells: &[TapeEntry]) -> u64 {
+    u64::from_le_bytes(cells.try_into().expect("a word has eight cells"))
+}
+
+/// Sets the highest bit of every byte of `word` that is zero, and clears all
+/// other bits.
+fn zero_bytes(word: u64) -> u64 {
+    !(((word & LOW_BITS) + LOW_BITS) | word | LOW_BITS)
+}
+
+/// Number of cells holding `symbol`.
+pub fn count(cells: &[TapeEntry], symbol: TapeEntry) -> usize {
+    let pattern = u64::from_ne_bytes([symbol; WORD]);
+    let words = cells.chunks_exact(WORD);
+    let rest = words.remainder();
+    let counted: usize = words
+        .map(|cells| zero_bytes(word(cells) ^ pattern).count_ones() as usize)
+        .sum();
+    counted + rest.iter().filter(|entry| **entry == symbol).count()
+}
+
+/// Index of the first cell that isn't `blank`.
+pub fn first_written(cells: &[TapeEntry], blank: TapeEntry) -> Option<usize> {
+    let blanks = u64::from_ne_bytes([blank; WORD]);
+    let words = cells.chunks_exact(WORD);
+    let rest = words.remainder();
+    for (index, cells) in words.enumerate() {
+        let word = word(cells) ^ blanks;
+        if word != 0 {
+            return Some(index * WORD + word.trailing_zeros() as usize / 8);
+        }
+    }
+    let start = cells.len() - rest.len();
+    (start..cells.len()).find(|index| cells[*index] != blank)
+}
+
+/// Index of the last cell that isn't `blank`.
+pub fn last_written(cells: &[TapeEntry], blank: TapeEntry) -> Option<usize> {
+    let blanks = u64::from_ne_bytes([blank; WORD]);
+    let words = cells.rchunks_exact(WORD);
+    let rest = words.remainder();
+    for (index, word_cells) in words.enumerate() {
+        let word = word(word_cells) ^ blanks;
+        if word != 0 {
+            let end = cells.len() - index * WORD;
+            return Some(end - 1 - word.leading_zeros() as usize / 8);
+        }
+    }
+    rest.iter().rposition(|entry| *entry != blank)
+}
+
+/// The first and the last cell of `tape` that aren't `blank`.
+pub fn written(tape: &VecDeque<TapeEntry>, blank: TapeEntry) -> Option<(usize, usize)> {
+    let (front, back) = tape.as_slices();
+    let first =
+        first_written(front, blank).or_else(|| Some(front.len() + first_written(back, blank)?))?;
+    let last = match last_written(back, blank) {
+        Some(last) => front.len() + last,
+        None => last_written(front, blank).expect("there is a written cell"),
+    };
+    Some((first, last))
+}
+
+/// Number of cells of `tape` holding `symbol`.
+pub fn count_tape(tape: &VecDeque<TapeEntry>, symbol: TapeEntry) -> usize {
+    let (front, back) = tape.as_slices();
+    count(front, symbol) + count(back, symbol)
+}
+
+#[test]
+fn test_scan() {
+    assert_eq!(zero_bytes(0x0100_ff00_0000_0001), 0x0080_0080_8080_8000);
+
+    let mut cells = vec![0; 37];
+    assert_eq!(first_written(&cells, 0), None);
+    assert_eq!(last_written(&cells, 0), None);
+    assert_eq!(count(&cells, 0), 37);
+    for (index, length) in [(0, 37), (5, 37), (8, 37), (36, 37), (3, 4), (9, 16)] {
+        let mut cells = vec![0; length];
+        cells[index] = 2;
+        assert_eq!(first_written(&cells, 0), Some(index), "{index} of {length}");
+        assert_eq!(last_written(&cells, 0), Some(index), "{index} of {length}");
+    }
+    cells[2] = 1;
+    cells[19] = 1;
+    cells[35] = 1;
+    cells[30] = 255;
+    assert_eq!(count(&cells, 1), 3);
+    assert_eq!(count(&cells, 255), 1);
+    assert_eq!(count(&cells, 0), 33);
+    assert_eq!(first_written(&cells, 0), Some(2));
+    assert_eq!(last_written(&cells, 0), Some(35));
+    // Other blanks than zero.
+    let mut cells = vec![2; 21];
+    assert_eq!(first_written(&cells, 2), None);
+    cells[10] = 0;
+    cells[13] = 1;
+    assert_eq!(first_written(&cells, 2), Some(10));
+    assert_eq!(last_written(&cells, 2), Some(13));
+
+    // A tape whose cells wrapped around the end of the buffer.
+    let mut tape = VecDeque::with_capacity(16);
+    tape.extend([0, 0, 0, 0, 0, 1, 0]);
+    for _ in 0..3 {
+        tape.push_front(0);
+    }
+    tape[1] = 1;
+    assert!(!tape.as_slices().1.is_empty());
+    assert_eq!(written(&tape, 0), Some((1, 8)));
+    assert_eq!(count_tape(&tape, 0), 8);
+    assert_eq!(written(&VecDeque::from([0, 0]), 0), None);
+}
@@ -0,0 +1,922 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Write},
+    net::{IpAddr, Ipv4Addr, TcpListener, TcpStream},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    http::{Request, Response},
+    jobs::{self, Queue},
+    json::Json,
+    log::{self, Level},
+    metrics::Metrics,
+    ratelimit::RateLimiter,
+    rpc,
+    sandbox::{self, Limits, Stop},
+    throughput::Meter,
+    turing::{self, TuringMachine},
+    websocket,
+};
+
+/// Cells on either side of the head returned by the tape endpoint when no
+/// window is given.
+const DEFAULT_WINDOW: isize = 20;
+/// Largest number of cells returned by the tape endpoint.
+const MAX_WINDOW: isize = 100_000;
+/// Frames per second sent by the stream endpoint when no rate is given.
+const DEFAULT_FPS: u32 = 30;
+const MAX_FPS: u32 = 1000;
+/// Longest time a request waits for a job to finish.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+/// Longest time a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Caps on a request of a public server, see `serve --public`.
+pub const PUBLIC_LIMITS: Limits = Limits {
+    max_steps: 10_000_000,
+    max_tape_cells: 1_000_000,
+    max_time: Duration::from_secs(2),
+};
+/// Caps on a job of a public server.
+pub const PUBLIC_JOB_LIMITS: Limits = Limits {
+    max_steps: 1_000_000_000,
+    max_tape_cells: 1_000_000,
+    max_time: Duration::from_secs(60),
+};
+/// Most machines a public server keeps, after which uploads replace the
+/// oldest ones.
+pub const PUBLIC_MAX_MACHINES: usize = 1000;
+/// Most bytes a machine of a public server may take when loaded, most of
+/// which is its transition table.
+pub const PUBLIC_MAX_MACHINE_MEMORY: usize = 1 << 20;
+/// Requests a minute a client of a public server may send.
+pub const PUBLIC_RATE_LIMIT: u32 = 120;
+/// Most connections a public server handles at once, each on a thread.
+pub const PUBLIC_MAX_CONNECTIONS: usize = 256;
+
+/// Machines uploaded to the server, each of which can be driven by the
+/// clients independently.
+///
+/// The API, with all responses but the metrics in JSON:
+///
+/// - `POST /machines` with a machine file as body loads the machine and
+///   returns its id and statistics.
+/// - `GET /machines` lists the ids of all machines.
+/// - `GET /machines/{id}` returns the statistics of a machine, with the
+///   steps and new tape cells per second of its step and run requests in
+///   `throughput`, of the last one and as a moving average.
+/// - `POST /machines/{id}/step?count=N` performs up to `N` steps, one by
+///   default.
+/// - `POST /machines/{id}/run?max_steps=N` runs until the machine halts or
+///   `N` steps were taken, at most the server's budget.
+/// - `GET /machines/{id}/tape?from=A&to=B` returns the cells from `A` to
+///   `B`, relative to the starting cell. Defaults to a window around the
+///   head.
+/// - `GET /machines/{id}/stream?fps=F&steps=N&window=W` upgrades to a
+///   WebSocket streaming the configuration, see [`Server::stream`].
+/// - `DELETE /machines/{id}` removes a machine.
+/// - `POST /jobs?kind=K&max_steps=N` with a machine file as body queues a
+///   job that runs the machine or, with `kind=decide`, decides it in the
+///   background, and returns the job with its id.
+/// - `GET /jobs` lists all jobs.
+/// - `GET /jobs/{id}?wait=S` returns a job with its result once finished,
+///   waiting up to `S` seconds for it to finish.
+/// - `GET /metrics` returns the [`Metrics`] of the server in the text format
+///   of Prometheus.
+/// - `POST /twirp/touring.Simulator/{Method}` calls a method of the service
+///   of `proto/touring.proto` with Twirp and JSON, not gRPC, see
+///   [`rpc::call`].
+///
+/// Uploaded machines are untrusted, so the steps of a request, the tape of a
+/// machine and the time spent on a request are capped by the server's
+/// [`Limits`]. The step and run endpoints answer with the statistics and why
+/// they stopped in `stop` and `stop_reason`. A server exposed to the
+/// internet also limits the requests of every client address, answering
+/// 429 beyond that, and the machines it keeps.
+pub struct Server {
+    machines: Mutex<BTreeMap<u64, Arc<Mutex<TuringMachine>>>>,
+    next_id: Mutex<u64>,
+    /// Throughput of the step and run requests of every machine.
+    meters: Mutex<BTreeMap<u64, Meter>>,
+    metrics: Mutex<Metrics>,
+    jobs: Option<Arc<Queue>>,
+    /// Caps on a single request, where the steps are the most a request may
+    /// ask for. The tape cells are the most of a machine over all requests.
+    limits: Limits,
+    rate_limiter: Option<RateLimiter>,
+    /// Most machines kept, after which the oldest are removed.
+    max_machines: usize,
+    /// Most bytes of memory a machine may take when loaded.
+    max_machine_memory: usize,
+    /// Connections being handled, and how many may be at once.
+    connections: AtomicUsize,
+    max_connections: usize,
+}
+
+impl Server {
+    pub fn new(limits: Limits) -> Self {
+        Server {
+            machines: Mutex::new(BTreeMap::new()),
+            next_id: Mutex::new(0),
+            meters: Mutex::new(BTreeMap::new()),
+            metrics: Mutex::new(Metrics::default()),
+            jobs: None,
+            limits,
+            rate_limiter: None,
+            max_machines: usize::MAX,
+            max_machine_memory: usize::MAX,
+            connections: AtomicUsize::new(0),
+            max_connections: usize::MAX,
+        }
+    }
+
+    /// Accepts jobs into `queue`.
+    pub fn with_jobs(mut self, queue: Arc<Queue>) -> Self {
+        self.jobs = Some(queue);
+        self
+    }
+
+    /// Allows every client address `per_minute` requests a minute.
+    pub fn with_rate_limit(mut self, per_minute: u32) -> Self {
+        self.rate_limiter = Some(RateLimiter::per_minute(per_minute));
+        self
+    }
+
+    /// Keeps at most `max_machines` machines, removing the oldest ones to
+    /// load new ones.
+    pub fn with_max_machines(mut self, max_machines: usize) -> Self {
+        self.max_machines = max_machines.max(1);
+        self
+    }
+
+    /// Refuses machines and jobs whose machine takes more than
+    /// `max_machine_memory` bytes of memory, see [`TuringMachine::memory`].
+    pub fn with_max_machine_memory(mut self, max_machine_memory: usize) -> Self {
+        self.max_machine_memory = max_machine_memory;
+        self
+    }
+
+    /// Handles at most `max_connections` connections at once, answering
+    /// others with 503 right away.
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections.max(1);
+        self
+    }
+
+    /// Counts a new connection unless there are too many already.
+    fn open_connection(&self) -> bool {
+        self.connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                (open < self.max_connections).then_some(open + 1)
+            })
+            .is_ok()
+    }
+
+    fn close_connection(&self) {
+        self.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Whether `client` may send another request, which counts it.
+    fn admits(&self, client: IpAddr) -> bool {
+        self.rate_limiter
+            .as_ref()
+            .is_none_or(|limiter| limiter.allow(client, Instant::now()))
+    }
+
+    fn refuse(&self) -> Response {
+        self.count(Instant::now(), Response::error(429, "too many requests"))
+    }
+
+    /// Answers `request`, counting the response and its latency.
+    pub fn handle(&self, request: &Request) -> Response {
+        let start = Instant::now();
+        self.count(start, self.route(request))
+    }
+
+    fn count(&self, start: Instant, response: Response) -> Response {
+        let mut metrics = self.metrics.lock().unwrap();
+        *metrics.responses.entry(response.status).or_default() += 1;
+        metrics.latency.observe(start.elapsed().as_secs_f64());
+        response
+    }
+
+    fn route(&self, request: &Request) -> Response {
+        let segments: Vec<&str> = request
+            .path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["machines"]) => self.load(request),
+            ("POST", ["twirp", service, method]) if *service == rpc::SERVICE => {
+                rpc::call(method, request, |request| self.route(request))
+            }
+            (method, ["jobs", rest @ ..]) => self.jobs(request, method, rest),
+            ("GET", ["metrics"]) => Response::text(
+                200,
+                "text/plain; version=0.0.4",
+                self.metrics.lock().unwrap().render(),
+            ),
+            ("GET", ["machines"]) => {
+                let ids = self
+                    .machines
+                    .lock()
+                    .unwrap()
+                    .keys()
+                    .map(|id| (*id).into())
+                    .collect();
+                Response::json(200, &Json::object([("machines", Json::Array(ids))]))
+            }
+            (method, ["machines", id, rest @ ..]) => {
+                let id: u64 = match id.parse() {
+                    Ok(id) => id,
+                    Err(_) => return Response::error(404, "no such machine"),
+                };
+                if let ("DELETE", []) = (method, rest) {
+                    self.meters.lock().unwrap().remove(&id);
+                    return match self.machines.lock().unwrap().remove(&id) {
+                        Some(_) => Response::json(200, &Json::object([("deleted", id.into())])),
+                        None => Response::error(404, "no such machine"),
+                    };
+                }
+                let machine = match self.machine(id) {
+                    Some(machine) => machine,
+                    None => return Response::error(404, "no such machine"),
+                };
+                let mut tm = machine.lock().unwrap();
+                match (method, rest) {
+                    ("GET", []) => Response::json(200, &self.stats(id, &tm)),
+                    ("POST", ["step"]) => match self.budget(request, "count", 1) {
+                        Ok(count) => self.run(id, &mut tm, count),
+                        Err(response) => response,
+                    },
+                    ("POST", ["run"]) => match self.budget(request, "max_steps", u128::MAX) {
+                        Ok(max_steps) => self.run(id, &mut tm, max_steps),
+                        Err(response) => response,
+                    },
+                    ("GET", ["tape"]) => tape(request, &tm),
+                    ("GET", ["stream"]) => Response::error(400, "expected a WebSocket upgrade"),
+                    (_, [] | ["step" | "run" | "tape" | "stream"]) => {
+                        Response::error(405, "method not allowed")
+                    }
+                    _ => Response::error(404, "no such endpoint"),
+                }
+            }
+            _ => Response::error(404, "no such endpoint"),
+        }
+    }
+
+    fn jobs(&self, request: &Request, method: &str, rest: &[&str]) -> Response {
+        let Some(queue) = &self.jobs else {
+            return Response::error(404, "no job queue");
+        };
+        match (method, rest) {
+            ("POST", []) => {
+                let kind = match jobs::Kind::parse(request.query("kind").unwrap_or("run")) {
+                    Ok(kind) => kind,
+                    Err(why) => return Response::error(400, &why),
+                };
+                let max_steps = match query_or(request, "max_steps", u128::MAX) {
+                    Ok(max_steps) => max_steps,
+                    Err(response) => return response,
+                };
+                // Rejects broken machines right away rather than in a worker.
+                if let Err(response) = self.parse(request) {
+                    return response;
+                }
+                let machine = String::from_utf8_lossy(&request.body).to_string();
+                match queue.submit(kind, machine, max_steps) {
+                    Ok(id) => match queue.job(id) {
+                        Some(job) => Response::json(202, &job.to_json()),
+                        None => Response::error(404, "no such job"),
+                    },
+                    Err(why) => Response::error(503, &why),
+                }
+            }
+            ("GET", []) => {
+                let jobs = queue
+                    .jobs()
+                    .iter()
+                    .map(|job| {
+                        Json::object([
+                            ("id", job.id.into()),
+                            ("kind", job.kind.name().into()),
+                            ("status", job.status.name().into()),
+                        ])
+                    })
+                    .collect();
+                Response::json(200, &Json::object([("jobs", Json::Array(jobs))]))
+            }
+            ("GET", [id]) => {
+                let seconds = match query_or(request, "wait", 0.0f64) {
+                    Ok(seconds) if seconds >= 0.0 => seconds,
+                    _ => return Response::error(400, "invalid wait"),
+                };
+                let wait = Duration::try_from_secs_f64(seconds)
+                    .unwrap_or(MAX_WAIT)
+                    .min(MAX_WAIT);
+                match id.parse().ok().and_then(|id| queue.wait(id, wait)) {
+                    Some(job) => Response::json(200, &job.to_json()),
+                    None => Response::error(404, "no such job"),
+                }
+            }
+            (_, [] | [_]) => Response::error(405, "method not allowed"),
+            _ => Response::error(404, "no such endpoint"),
+        }
+    }
+
+    fn machine(&self, id: u64) -> Option<Arc<Mutex<TuringMachine>>> {
+        self.machines.lock().unwrap().get(&id).cloned()
+    }
+
+    /// The statistics of a machine with its throughput.
+    fn stats(&self, id: u64, tm: &TuringMachine) -> Json {
+        let mut json = stats(id, tm);
+        if let Json::Object(fields) = &mut json {
+            let meters = self.meters.lock().unwrap();
+            fields.push((
+                "throughput".to_string(),
+                meters.get(&id).map_or(Json::Null, Meter::to_json),
+            ));
+        }
+        json
+    }
+
+    /// Performs up to `steps` steps within the server's limits.
+    fn run(&self, id: u64, tm: &mut TuringMachine, steps: u128) -> Response {
+        let limits = Limits {
+            max_steps: steps,
+            ..self.limits
+        };
+        let (steps, cells) = (tm.num_steps, tm.tape().len());
+        let start = Instant::now();
+        let stop = self.simulate(tm, &limits);
+        *self
+            .metrics
+            .lock()
+            .unwrap()
+            .runs
+            .entry(stop.name())
+            .or_default() += 1;
+        self.meters.lock().unwrap().entry(id).or_default().record(
+            (tm.num_steps - steps) as f64,
+            tm.tape().len().saturating_sub(cells) as f64,
+            start.elapsed(),
+        );
+        let mut json = self.stats(id, tm);
+        if let Json::Object(fields) = &mut json {
+            fields.push(("stop".to_string(), stop.name().into()));
+            fields.push((
+                "stop_reason".to_string(),
+                stop.reason(&limits)
+                    .map_or(Json::Null, |reason| reason.as_str().into()),
+            ));
+        }
+        Response::json(200, &json)
+    }
+
+    /// Steps `tm` within `limits`, counting the steps and whether it halted.
+    fn simulate(&self, tm: &mut TuringMachine, limits: &Limits) -> Stop {
+        let (steps, halted) = (tm.num_steps, tm.is_halted());
+        let stop = sandbox::run(tm, limits);
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.steps += tm.num_steps - steps;
+        if let (false, Some(reason)) = (halted, tm.halt_reason) {
+            *metrics.halted.entry(reason.name()).or_default() += 1;
+        }
+        stop
+    }
+
+    /// Answers a WebSocket upgrade of `GET /machines/{id}/stream` by sending
+    /// a frame with the configuration `fps` times per second, performing
+    /// `steps` steps between frames. Every frame shows `window` cells on
+    /// either side of the head. The stream ends after the machine halted,
+    /// its tape reached the server's limit, the stream took the steps or
+    /// the time of the server's limits or the client went away.
+    pub fn stream(&self, request: &Request, writer: &mut impl Write) -> Result<(), String> {
+        let segments: Vec<&str> = request
+            .path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let machine = match segments.as_slice() {
+            ["machines", id, "stream"] => id.parse().ok().and_then(|id| self.machine(id)),
+            _ => None,
+        };
+        let parameters = (
+            query_or(request, "fps", DEFAULT_FPS),
+            self.budget(request, "steps", 1),
+            query_or(request, "window", DEFAULT_WINDOW),
+        );
+        let (machine, fps, steps, window) = match (machine, parameters) {
+            (None, _) => return self.reject(writer, Response::error(404, "no such machine")),
+            (Some(machine), (Ok(fps), Ok(steps), Ok(window))) => (machine, fps, steps, window),
+            (_, (Err(response), _, _) | (_, Err(response), _) | (_, _, Err(response))) => {
+                return self.reject(writer, response)
+            }
+        };
+        if !(1..=MAX_FPS).contains(&fps) || !(0..=MAX_WINDOW / 2).contains(&window) {
+            return self.reject(writer, Response::error(400, "invalid fps or window"));
+        }
+
+        websocket::accept(request, writer)?;
+        let interval = Duration::from_secs(1) / fps;
+        let start = Instant::now();
+        let mut next = start;
+        // Steps of the whole stream, which the limits cap like a request.
+        let mut streamed = 0;
+        loop {
+            let (frame, stopped) = {
+                let mut tm = machine.lock().unwrap();
+                let frame = frame(&tm, window);
+                let limits = Limits {
+                    max_steps: steps.min(self.limits.max_steps - streamed),
+                    ..self.limits
+                };
+                let stopped = tm.is_halted()
+                    || tm.tape().len() > limits.max_tape_cells
+                    || limits.max_steps == 0
+                    || start.elapsed() >= self.limits.max_time;
+                if !stopped {
+                    let before = tm.num_steps;
+                    self.simulate(&mut tm, &limits);
+                    streamed += tm.num_steps - before;
+                }
+                (frame, stopped)
+            };
+            websocket::write_text(writer, &frame.to_string()).map_err(|why| why.to_string())?;
+            if stopped {
+                return websocket::write_close(writer).map_err(|why| why.to_string());
+            }
+            next += interval;
+            thread::sleep(next.saturating_duration_since(Instant::now()));
+        }
+    }
+
+    fn reject(&self, writer: &mut impl Write, response: Response) -> Result<(), String> {
+        response.write_to(writer).map_err(|why| why.to_string())
+    }
+
+    /// The machine in the body of `request`, unless it is broken or too
+    /// big.
+    fn parse(&self, request: &Request) -> Result<TuringMachine, Response> {
+        let tm = turing::parse_machine(&request.body).map_err(|why| Response::error(400, &why))?;
+        if tm.memory() > self.max_machine_memory {
+            return Err(Response::error(
+                413,
+                &format!(
+                    "the machine takes more than {} bytes of memory",
+                    self.max_machine_memory
+                ),
+            ));
+        }
+        Ok(tm)
+    }
+
+    fn load(&self, request: &Request) -> Response {
+        let mut tm = match self.parse(request) {
+            Ok(tm) => tm,
+            Err(response) => return response,
+        };
+        tm.set_reject_undefined(true);
+
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id - 1
+        };
+        let response = Response::json(201, &stats(id, &tm));
+        self.metrics.lock().unwrap().machines_loaded += 1;
+        let mut machines = self.machines.lock().unwrap();
+        while machines.len() >= self.max_machines {
+            if let Some((oldest, _)) = machines.pop_first() {
+                self.meters.lock().unwrap().remove(&oldest);
+            }
+        }
+        machines.insert(id, Arc::new(Mutex::new(tm)));
+        response
+    }
+
+    /// Reads a step count from the query, capped at the server's budget.
+    fn budget(&self, request: &Request, name: &str, default: u128) -> Result<u128, Response> {
+        match request.query(name) {
+            None => Ok(default.min(self.limits.max_steps)),
+            Some(value) => match value.parse::<u128>() {
+                Ok(steps) => Ok(steps.min(self.limits.max_steps)),
+                Err(_) => Err(Response::error(400, &format!("invalid {name} '{value}'"))),
+            },
+        }
+    }
+}
+
+/// Position of the head relative to the starting cell.
+fn position(tm: &TuringMachine) -> isize {
+    tm.head() as isize - tm.origin() as isize
+}
+
+fn stats(id: u64, tm: &TuringMachine) -> Json {
+    Json::object([
+        ("id", id.into()),
+        (
+            "states",
+            Json::Array(tm.states().iter().map(|s| s.as_str().into()).collect()),
+        ),
+        (
+            "state",
+            tm.state()
+                .map_or(Json::Null, |state| tm.states()[state].as_str().into()),
+        ),
+        ("steps", tm.num_steps.into()),
+        ("head", position(tm).into()),
+        ("halted", tm.is_halted().into()),
+        (
+            "halt_reason",
+            tm.halt_reason
+                .map_or(Json::Null, |reason| reason.name().into()),
+        ),
+        ("edge_hits", tm.edge_hits.into()),
+    ])
+}
+
+/// Reads a number from the query, or `default` if it isn't given.
+fn query_or<T: FromStr>(request: &Request, name: &str, default: T) -> Result<T, Response> {
+    match request.query(name) {
+        None => Ok(default),
+        Some(value) => value
+            .parse()
+            .map_err(|_| Response::error(400, &format!("invalid {name} '{value}'"))),
+    }
+}
+
+fn tape(request: &Request, tm: &TuringMachine) -> Response {
+    let head = position(tm);
+    let (from, to) = match (
+        query_or(request, "from", head - DEFAULT_WINDOW),
+        query_or(request, "to", head + DEFAULT_WINDOW),
+    ) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    let origin = tm.origin() as isize;
+    if !to
+        .checked_sub(from)
+        .is_some_and(|window| (0..MAX_WINDOW).contains(&window))
+        || from.checked_add(origin).is_none()
+        || to.checked_add(origin).is_none()
+    {
+        return Response::error(400, "invalid tape window");
+    }
+
+    Response::json(
+        200,
+        &Json::object([
+            ("from", from.into()),
+            ("head", head.into()),
+            ("tape", cells(tm, from, to)),
+        ]),
+    )
+}
+
+/// The cells from `from` to `to`, relative to the starting cell, which
+/// must be an index of the tape when added to its origin.
+fn cells(tm: &TuringMachine, from: isize, to: isize) -> Json {
+    let cells = (from..=to)
+        .map(|position| {
+            let index = position + tm.origin() as isize;
+            let cell = if index < 0 {
+                0
+            } else {
+                tm.tape().get(index as usize).copied().unwrap_or(0)
+            };
+            cell.into()
+        })
+        .collect();
+    Json::Array(cells)
+}
+
+/// The configuration sent to clients of the stream endpoint.
+fn frame(tm: &TuringMachine, window: isize) -> Json {
+    let head = position(tm);
+    Json::object([
+        ("steps", tm.num_steps.into()),
+        (
+            "state",
+            tm.state()
+                .map_or(Json::Null, |state| tm.states()[state].as_str().into()),
+        ),
+        ("head", head.into()),
+        ("from", (head - window).into()),
+        ("tape", cells(tm, head - window, head + window)),
+        ("halted", tm.is_halted().into()),
+    ])
+}
+
+fn connection(server: &Server, stream: TcpStream) {
+    let client = stream
+        .peer_addr()
+        .map_or(Ipv4Addr::UNSPECIFIED.into(), |address| address.ip());
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let mut reader = BufReader::new(&stream);
+    let request = Request::read(&mut reader);
+    let _span = log::span(
+        "request",
+        &[
+            (
+                "method",
+                request.as_ref().map_or("", |r| r.method.as_str()).into(),
+            ),
+            (
+                "path",
+                request.as_ref().map_or("", |r| r.path.as_str()).into(),
+            ),
+        ],
+    );
+    let response = match request {
+        Ok(_) if !server.admits(client) => server.refuse(),
+        Ok(request) if websocket::is_upgrade(&request) => {
+            let _ = stream.set_read_timeout(None);
+            if let Err(why) = server.stream(&request, &mut &stream) {
+                log::event(Level::Warn, "stream failed", &[("error", why.into())]);
+            }
+            return;
+        }
+        Ok(request) => server.handle(&request),
+        Err(response) => response,
+    };
+    log::event(
+        Level::Info,
+        "response",
+        &[("status", (response.status as u64).into())],
+    );
+    let _ = response.write_to(&mut &stream);
+}
+
+/// Serves the API of [`Server`] on `address`, handling every connection on
+/// its own thread, up to the most connections of the server.
+/// A connection counted by [`Server::open_connection`].
+struct Open<'a>(&'a Server);
+
+impl Drop for Open<'_> {
+    fn drop(&mut self) {
+        self.0.close_connection();
+    }
+}
+
+pub fn serve(address: &str, server: Server) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|why| why.to_string())?;
+    let server = Arc::new(server);
+    // Log collectors get the messages as events instead.
+    match log::enabled(Level::Info) {
+        true => log::event(Level::Info, "listening", &[("address", address.into())]),
+        false => eprintln!("Listening on http://{}", address),
+    }
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) if !server.open_connection() => {
+                let _ = Response::error(503, "too many connections").write_to(&mut &stream);
+            }
+            Ok(stream) => {
+                let server = server.clone();
+                thread::spawn(move || {
+                    // Frees the slot even if the handler panics.
+                    let _open = Open(&server);
+                    connection(&server, stream);
+                });
+            }
+            Err(why) => match log::enabled(Level::Error) {
+                true => log::event(
+                    Level::Error,
+                    "accept failed",
+                    &[("error", why.to_string().into())],
+                ),
+                false => eprintln!("Can't accept connection: {}", why),
+            },
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn request(method: &str, target: &str, body: &str) -> Request {
+    let raw = format!(
+        "{method} {target} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    Request::read(&mut raw.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_server() {
+    let machine = std::fs::read_to_string("examples/busy_bever/busy_bever_2.turing").unwrap();
+    let server = Server::new(Limits {
+        max_steps: 1000,
+        ..Limits::NONE
+    });
+    let body =
+        |response: Response| Json::parse(&String::from_utf8(response.body).unwrap()).unwrap();
+
+    let response = server.handle(&request("POST", "/machines", &machine));
+    assert_eq!(response.status, 201);
+    assert_eq!(body(response).int_field("id"), Ok(0));
+
+    let response = server.handle(&request("POST", "/machines/0/step?count=2", ""));
+    assert_eq!(body(response).int_field("steps"), Ok(2));
+
+    let stats = body(server.handle(&request("POST", "/machines/0/run", "")));
+    assert_eq!(stats.int_field("steps"), Ok(6));
+    assert_eq!(stats.get("halted"), Some(&Json::Bool(true)));
+    assert_eq!(stats.str_field("stop"), Ok("halted"));
+    let throughput = body(server.handle(&request("GET", "/machines/0", "")));
+    let throughput = throughput.field("throughput").unwrap();
+    assert!(throughput.field("average").is_ok());
+
+    let metrics = server.handle(&request("GET", "/metrics", ""));
+    assert_eq!(metrics.content_type, "text/plain; version=0.0.4");
+    let metrics = String::from_utf8(metrics.body).unwrap();
+    for line in [
+        "turing_machines_loaded_total 1",
+        "turing_steps_total 6",
+        "turing_runs_total{stop=\"halted\"} 1",
+        "turing_runs_total{stop=\"steps\"} 1",
+        "turing_machines_halted_total{reason=\"Halt\"} 1",
+        "turing_responses_total{status=\"200\"} 3",
+        "turing_request_duration_seconds_count 4",
+    ] {
+        assert!(metrics.lines().any(|l| l == line), "{line}");
+    }
+
+    let tape = body(server.handle(&request("GET", "/machines/0/tape?from=-2&to=1", "")));
+    assert_eq!(tape.get("tape").unwrap().to_string(), "[1,1,1,1]");
+    for window in [
+        "from=-9223372036854775808&to=9223372036854775807",
+        "from=0&to=100000",
+        "from=2&to=1",
+    ] {
+        let response = server.handle(&request("GET", &format!("/machines/0/tape?{window}"), ""));
+        assert_eq!(response.status, 400);
+    }
+
+    assert_eq!(
+        server.handle(&request("GET", "/machines/7", "")).status,
+        404
+    );
+    assert_eq!(
+        server.handle(&request("PUT", "/machines/0", "")).status,
+        405
+    );
+    assert_eq!(
+        server
+            .handle(&request("POST", "/machines", "A 0 -> B"))
+            .status,
+        400
+    );
+    assert_eq!(
+        server.handle(&request("DELETE", "/machines/0", "")).status,
+        200
+    );
+    assert_eq!(
+        server.handle(&request("GET", "/machines/0", "")).status,
+        404
+    );
+
+    let server = Server::new(Limits {
+        max_steps: 1000,
+        max_tape_cells: 10,
+        ..Limits::NONE
+    });
+    server.handle(&request("POST", "/machines", "A 0 -> A 1 R"));
+    let stats = body(server.handle(&request("POST", "/machines/0/run", "")));
+    assert_eq!(stats.str_field("stop"), Ok("tape_cells"));
+    assert_eq!(
+        stats.str_field("stop_reason"),
+        Ok("the limit of 10 tape cells")
+    );
+    assert_eq!(stats.get("halted"), Some(&Json::Bool(false)));
+    assert_eq!(server.handle(&request("GET", "/jobs", "")).status, 404);
+
+    let limits = Limits {
+        max_steps: 1000,
+        ..Limits::NONE
+    };
+    let server = Server::new(limits).with_jobs(Queue::start(1, 4, limits, None).unwrap());
+    let job = server.handle(&request("POST", "/jobs?kind=decide", &machine));
+    assert_eq!(job.status, 202);
+    assert_eq!(body(job).int_field("id"), Ok(0));
+    let job = body(server.handle(&request("GET", "/jobs/0?wait=10", "")));
+    assert_eq!(job.str_field("status"), Ok("done"));
+    assert_eq!(
+        job.field("result").unwrap().str_field("verdict"),
+        Ok("halts")
+    );
+    let jobs = body(server.handle(&request("GET", "/jobs", "")));
+    assert_eq!(jobs.field("jobs").unwrap().as_array().unwrap().len(), 1);
+    for (method, target, status) in [
+        ("POST", "/jobs?kind=guess", 400),
+        ("POST", "/jobs?max_steps=-1", 400),
+        ("GET", "/jobs/7", 404),
+        ("DELETE", "/jobs/0", 405),
+    ] {
+        let response = server.handle(&request(method, target, &machine));
+        assert_eq!(response.status, status, "{method} {target}");
+    }
+
+    // A dense table of a thousand states and 256 symbols takes about 3 MB.
+    let server = server.with_max_machine_memory(PUBLIC_MAX_MACHINE_MEMORY);
+    let large: String = (0..1000)
+        .map(|i| format!("S{i} 255 -> S{} 0 R\n", i + 1))
+        .collect();
+    for target in ["/machines", "/jobs"] {
+        assert_eq!(server.handle(&request("POST", target, &large)).status, 413);
+    }
+    assert_eq!(
+        server
+            .handle(&request("POST", "/machines", &machine))
+            .status,
+        201
+    );
+
+    let server = Server::new(PUBLIC_LIMITS)
+        .with_rate_limit(10)
+        .with_max_machines(2)
+        .with_max_connections(1);
+    assert!(server.open_connection());
+    assert!(!server.open_connection());
+    server.close_connection();
+    assert!(server.open_connection());
+    for _ in 0..3 {
+        server.handle(&request("POST", "/machines", &machine));
+    }
+    let machines = body(server.handle(&request("GET", "/machines", "")));
+    assert_eq!(machines.get("machines").unwrap().to_string(), "[1,2]");
+    let client = IpAddr::from([192, 0, 2, 1]);
+    assert!(server.admits(client));
+    assert!(!server.admits(client));
+    assert!(server.admits(IpAddr::from([192, 0, 2, 2])));
+    assert_eq!(server.refuse().status, 429);
+}
+
+#[test]
+fn test_stream() {
+    let machine = std::fs::read_to_string("examples/busy_bever/busy_bever_2.turing").unwrap();
+    let server = Server::new(Limits {
+        max_steps: 1000,
+        ..Limits::NONE
+    });
+    server.handle(&request("POST", "/machines", &machine));
+
+    let mut upgrade = request("GET", "/machines/0/stream?fps=1000&steps=2&window=1", "");
+    upgrade.headers = vec![
+        ("Upgrade".to_string(), "websocket".to_string()),
+        (
+            "Sec-WebSocket-Key".to_string(),
+            "dGhlIHNhbXBsZSBub25jZQ==".to_string(),
+        ),
+    ];
+    let mut out = vec![];
+    server.stream(&upgrade, &mut out).unwrap();
+
+    let head_end = out.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    assert!(out.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+    let mut frames = vec![];
+    let mut rest = &out[head_end..];
+    while !rest.is_empty() {
+        let len = rest[1] as usize;
+        frames.push((
+            rest[0],
+            String::from_utf8_lossy(&rest[2..2 + len]).to_string(),
+        ));
+        rest = &rest[2 + len..];
+    }
+    // Frames after 0, 2, 4 and 6 steps, then the close frame.
+    assert_eq!(frames.len(), 5);
+    assert_eq!(frames[4].0, 0x88);
+    let last = Json::parse(&frames[3].1).unwrap();
+    assert_eq!(last.int_field("steps"), Ok(6));
+    assert_eq!(last.get("tape").unwrap().to_string(), "[1,1,1]");
+
+    // A machine that never halts is cut off after the steps of the limits,
+    // here after 0, 2, ... 10 steps.
+    let looping = "A 0 -> B 1 R\nB 0 -> A 0 L\nA 1 -> B 1 R\nB 1 -> A 0 L";
+    let server = Server::new(Limits {
+        max_steps: 10,
+        ..Limits::NONE
+    });
+    server.handle(&request("POST", "/machines", looping));
+    let mut out = vec![];
+    server.stream(&upgrade, &mut out).unwrap();
+    let frames = out[head_end..].iter().filter(|byte| **byte == 0x81).count();
+    assert_eq!(frames, 6);
+    assert!(out.ends_with(&[0x88, 2, 0x03, 0xe8]));
+
+    let mut out = vec![];
+    upgrade.path = "/machines/1/stream".to_string();
+    server.stream(&upgrade, &mut out).unwrap();
+    assert!(out.starts_with(b"HTTP/1.1 404 Not Found\r\n"));
+}
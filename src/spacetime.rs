@@ -0,0 +1,241 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::turing::{TapeEntry, TuringMachine};
+
+/// Colors of the non-blank symbols in order, after which they are spread
+/// around the color wheel.
+const PALETTE: [&str; 7] = [
+    "#202124", "#1a73e8", "#34a853", "#fbbc04", "#a142f4", "#ff6d01", "#24c1e0",
+];
+const HEAD: &str = "#d93025";
+/// Height in pixels of a line of the legend.
+const LEGEND_LINE: usize = 18;
+
+/// The tape at one step of a run.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Row {
+    pub step: u128,
+    /// Position of the first cell relative to the starting cell.
+    pub from: isize,
+    pub cells: Vec<TapeEntry>,
+    pub head: Option<isize>,
+}
+
+/// A space-time diagram: the tape at steps of a run, the first on top.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagram {
+    pub rows: Vec<Row>,
+    pub blank: TapeEntry,
+}
+
+impl Diagram {
+    /// Runs a copy of `tm` on its tape for up to `steps` steps, drawing the
+    /// tape before the first one, after every `sample`th and at the halt.
+    pub fn record(tm: &TuringMachine, steps: u128, sample: u128) -> Self {
+        let mut tm = tm.clone();
+        tm.set_reject_undefined(true);
+        let sample = sample.max(1);
+        let row = |tm: &TuringMachine| Row {
+            step: tm.num_steps,
+            from: -(tm.origin() as isize),
+            cells: tm.tape().iter().copied().collect(),
+            head: Some(tm.head() as isize - tm.origin() as isize),
+        };
+        let mut rows = vec![row(&tm)];
+        while tm.num_steps < steps && tm.step() {
+            if tm.num_steps.is_multiple_of(sample) || tm.is_halted() {
+                rows.push(row(&tm));
+            }
+        }
+        Diagram {
+            rows,
+            blank: tm.blank(),
+        }
+    }
+
+    /// The rows of a cellular automaton, with live cells as `1`.
+    pub fn of_cells(rows: &[Vec<bool>]) -> Self {
+        Diagram {
+            rows: rows
+                .iter()
+                .enumerate()
+                .map(|(step, row)| Row {
+                    step: step as u128,
+                    from: 0,
+                    cells: row.iter().map(|cell| *cell as TapeEntry).collect(),
+                    head: None,
+                })
+                .collect(),
+            blank: 0,
+        }
+    }
+
+    /// The positions of the first and the last cell over all rows.
+    fn span(&self) -> (isize, isize) {
+        let from = self.rows.iter().map(|row| row.from).min().unwrap_or(0);
+        let to = self
+            .rows
+            .iter()
+            .map(|row| row.from + row.cells.len() as isize - 1)
+            .max()
+            .unwrap_or(0);
+        (from, to.max(from))
+    }
+
+    /// The non-blank symbols on the tape in any row, in order.
+    fn symbols(&self) -> Vec<TapeEntry> {
+        let symbols: BTreeSet<TapeEntry> = self
+            .rows
+            .iter()
+            .flat_map(|row| row.cells.iter().copied())
+            .filter(|entry| *entry != self.blank)
+            .collect();
+        symbols.into_iter().collect()
+    }
+
+    /// The diagram as SVG, `scale` pixels per cell, with blanks white, a
+    /// color per symbol and the head in red. Runs of a symbol in a row are
+    /// drawn as one rectangle, which keeps huge diagrams small, and a
+    /// legend below names the colors with `glyph`.
+    pub fn to_svg(&self, scale: usize, glyph: impl Fn(TapeEntry) -> String) -> String {
+        let (from, to) = self.span();
+        let symbols = self.symbols();
+        let color = |entry: TapeEntry| {
+            let index = symbols.iter().position(|s| *s == entry).unwrap_or(0);
+            match PALETTE.get(index) {
+                Some(color) => color.to_string(),
+                None => format!("hsl({}, 70%, 45%)", index * 47 % 360),
+            }
+        };
+        let width = (to - from + 1) as usize * scale;
+        let height = self.rows.len() * scale;
+        let legend = symbols.len() + 1 + usize::from(self.has_head());
+        let total = height + legend * LEGEND_LINE + 8;
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{total}\" \
+             viewBox=\"0 0 {} {total}\" shape-rendering=\"crispEdges\">\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"white\"/>",
+            width.max(160),
+            width.max(160)
+        );
+        for (y, row) in self.rows.iter().enumerate() {
+            let mut start = 0;
+            while start < row.cells.len() {
+                let entry = row.cells[start];
+                let end = (start..row.cells.len())
+                    .find(|cell| row.cells[*cell] != entry)
+                    .unwrap_or(row.cells.len());
+                if entry != self.blank {
+                    let x = (row.from + start as isize - from) as usize;
+                    let _ = writeln!(
+                        out,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{scale}\" fill=\"{}\"/>",
+                        x * scale,
+                        y * scale,
+                        (end - start) * scale,
+                        color(entry)
+                    );
+                }
+                start = end;
+            }
+            if let Some(head) = row.head {
+                let _ = writeln!(
+                    out,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{scale}\" height=\"{scale}\" fill=\"{HEAD}\" fill-opacity=\"0.7\"/>",
+                    (head - from) as usize * scale,
+                    y * scale
+                );
+            }
+        }
+
+        let mut entries = vec![(
+            "white".to_string(),
+            format!("{} (blank)", glyph(self.blank)),
+        )];
+        entries.extend(symbols.iter().map(|entry| (color(*entry), glyph(*entry))));
+        if self.has_head() {
+            entries.push((HEAD.to_string(), "head".to_string()));
+        }
+        for (line, (color, label)) in entries.iter().enumerate() {
+            let y = height + 8 + line * LEGEND_LINE;
+            let _ = writeln!(
+                out,
+                "<rect x=\"4\" y=\"{y}\" width=\"12\" height=\"12\" fill=\"{color}\" stroke=\"#999\"/>\
+                 <text x=\"22\" y=\"{}\" font-family=\"sans-serif\" font-size=\"12\">{}</text>",
+                y + 11,
+                crate::report::escape(label)
+            );
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Grayscale pixels of the diagram, `scale` by `scale` per cell, with
+    /// blanks white and the other symbols darker the larger they are. Gives
+    /// the width, the height and the pixels for [`crate::png::write_gray`].
+    pub fn to_pixels(&self, scale: usize) -> (usize, usize, Vec<u8>) {
+        let (from, to) = self.span();
+        let symbols = self.symbols();
+        let gray = |entry: TapeEntry| match symbols.iter().position(|s| *s == entry) {
+            _ if entry == self.blank => 255,
+            Some(index) => (200 - 200 * index / symbols.len().max(1)) as u8,
+            None => 255,
+        };
+        let cells = (to - from + 1) as usize;
+        let mut pixels = Vec::with_capacity(cells * scale * self.rows.len() * scale);
+        for row in &self.rows {
+            let line: Vec<u8> = (from..=to)
+                .flat_map(|position| {
+                    let entry = usize::try_from(position - row.from)
+                        .ok()
+                        .and_then(|cell| row.cells.get(cell))
+                        .copied()
+                        .unwrap_or(self.blank);
+                    std::iter::repeat_n(gray(entry), scale)
+                })
+                .collect();
+            for _ in 0..scale {
+                pixels.extend(&line);
+            }
+        }
+        (cells * scale, self.rows.len() * scale, pixels)
+    }
+
+    fn has_head(&self) -> bool {
+        self.rows.iter().any(|row| row.head.is_some())
+    }
+}
+
+#[test]
+fn test_spacetime() {
+    let tm =
+        TuringMachine::parse("A 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> Halt 1 R").unwrap();
+    let diagram = Diagram::record(&tm, 100, 1);
+    assert_eq!(diagram.rows.len(), 7);
+    assert_eq!(diagram.span(), (-2, 1));
+    assert_eq!(diagram.rows[6].cells, [1, 1, 1, 1]);
+    assert_eq!(diagram.rows[6].head, Some(0));
+
+    let sampled = Diagram::record(&tm, 100, 4);
+    let steps: Vec<u128> = sampled.rows.iter().map(|row| row.step).collect();
+    assert_eq!(steps, [0, 4, 6]);
+
+    let svg = diagram.to_svg(2, |entry| entry.to_string());
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"160\""));
+    // The last row is a single run of four ones.
+    assert!(svg.contains("<rect x=\"0\" y=\"12\" width=\"8\" height=\"2\" fill=\"#202124\"/>"));
+    assert!(svg.contains(">0 (blank)</text>"));
+    assert!(svg.contains(">head</text>"));
+
+    let (width, height, pixels) = diagram.to_pixels(1);
+    assert_eq!((width, height), (4, 7));
+    assert_eq!(&pixels[..4], [255, 255, 255, 255]);
+    assert_eq!(&pixels[24..], [200, 200, 200, 200]);
+
+    let automaton = Diagram::of_cells(&[vec![false, true], vec![true, true]]);
+    assert!(!automaton
+        .to_svg(1, |entry| entry.to_string())
+        .contains(">head<"));
+}
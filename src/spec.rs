@@ -0,0 +1,385 @@
+use std::{collections::BTreeMap, fmt::Display, fs, path::Path};
+
+use crate::{
+    config::Config,
+    preprocess::Param,
+    sandbox::{self, Limits, Stop},
+    scan,
+    turing::{self, HaltReason, TapeEntry, TuringMachine},
+};
+
+/// Steps a test may take unless it sets `max-steps`.
+const DEFAULT_MAX_STEPS: u128 = 100_000;
+
+/// How a machine is expected to end up, by the name of its halting state or
+/// `running` if it should still run after the step budget.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Halt {
+    Halted(HaltReason),
+    Running,
+}
+
+impl Halt {
+    fn of(tm: &TuringMachine) -> Self {
+        match tm.halt_reason {
+            Some(reason) => Halt::Halted(reason),
+            None => Halt::Running,
+        }
+    }
+}
+
+impl Display for Halt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Halt::Halted(reason) => reason.name(),
+            Halt::Running => "running",
+        })
+    }
+}
+
+/// A cell of a tape pattern.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Cell {
+    Symbol(TapeEntry),
+    /// `?`, any single cell.
+    Any,
+    /// `*`, any number of cells, including none.
+    Anything,
+}
+
+/// An expected final tape, written like an input word where `?` matches any
+/// cell and `*` any number of cells. It is matched against the tape without
+/// the blank cells at both ends.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Pattern {
+    text: String,
+    cells: Vec<Cell>,
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let symbols: Vec<String> = if text.contains(char::is_whitespace) {
+            text.split_whitespace().map(str::to_string).collect()
+        } else {
+            text.chars().map(String::from).collect()
+        };
+        let cells = symbols
+            .iter()
+            .map(|symbol| match symbol.as_str() {
+                "?" => Ok(Cell::Any),
+                "*" => Ok(Cell::Anything),
+                symbol => match symbol.parse() {
+                    Ok(entry) => Ok(Cell::Symbol(entry)),
+                    Err(why) => Err(format!("invalid symbol '{symbol}': {why}")),
+                },
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Pattern {
+            text: text.to_string(),
+            cells,
+        })
+    }
+
+    /// Whether the cells of `tape` between the `blank` cells at both ends
+    /// match.
+    pub fn matches(&self, tape: &[TapeEntry], blank: TapeEntry) -> bool {
+        matches(&self.cells, written(tape, blank))
+    }
+}
+
+/// The tape without the blank cells at both ends.
+fn written(tape: &[TapeEntry], blank: TapeEntry) -> &[TapeEntry] {
+    match (
+        scan::first_written(tape, blank),
+        scan::last_written(tape, blank),
+    ) {
+        (Some(start), Some(end)) => &tape[start..=end],
+        _ => &[],
+    }
+}
+
+/// Writes `word` the way [`turing::parse_word`] reads it.
+fn format_word(word: &[TapeEntry]) -> String {
+    let symbols: Vec<String> = word.iter().map(TapeEntry::to_string).collect();
+    if word.iter().all(|entry| entry < &10) {
+        symbols.concat()
+    } else {
+        symbols.join(" ")
+    }
+}
+
+fn matches(cells: &[Cell], tape: &[TapeEntry]) -> bool {
+    match (cells.first(), tape.first()) {
+        (None, _) => tape.is_empty(),
+        (Some(Cell::Anything), _) => {
+            (0..=tape.len()).any(|skipped| matches(&cells[1..], &tape[skipped..]))
+        }
+        (Some(_), None) => false,
+        (Some(Cell::Any), Some(_)) => matches(&cells[1..], &tape[1..]),
+        (Some(Cell::Symbol(symbol)), Some(entry)) => {
+            symbol == entry && matches(&cells[1..], &tape[1..])
+        }
+    }
+}
+
+/// One test of a machine, a section of its test file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Spec {
+    pub name: String,
+    pub input: Vec<TapeEntry>,
+    pub params: Vec<Param>,
+    pub max_steps: u128,
+    pub halt: Option<Halt>,
+    pub tape: Option<Pattern>,
+    /// What passing the test is worth when grading.
+    pub points: u32,
+}
+
+/// Result of running a [`Spec`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Outcome {
+    pub halt: Halt,
+    pub steps: u128,
+    /// Why the test failed, if it did.
+    pub failure: Option<String>,
+}
+
+/// Test file of the machine file at `path`, `machine.test.toml` for
+/// `machine.turing`.
+pub fn spec_file(path: &Path) -> std::path::PathBuf {
+    path.with_extension("test.toml")
+}
+
+/// Reads the tests of a test file.
+///
+/// Every `[name]` section is a test with these keys, all optional:
+///
+/// - `input`, the input word,
+/// - `param`, an array of `NAME=VALUE` values for the parameters of the
+///   machine,
+/// - `max-steps`, the step budget, 100 000 by default,
+/// - `halt`, the expected halting state or `running`,
+/// - `tape`, the expected final tape as a [`Pattern`],
+/// - `points`, what passing the test is worth when grading, 1 by default.
+///
+/// Keys outside of a section are defaults for every test.
+pub fn parse(content: &str) -> Result<Vec<Spec>, String> {
+    let config = Config::parse(content)?;
+    let sections = config.sections();
+    let defaults = sections.get("").cloned().unwrap_or_default();
+    let mut specs = vec![];
+    for (name, options) in sections.iter().filter(|(name, _)| !name.is_empty()) {
+        let mut options = options.clone();
+        for (key, value) in &defaults {
+            options.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        specs.push(from_options(name, &options)?);
+    }
+    Ok(specs)
+}
+
+/// Reads the test `name` from the keys of its section, see [`parse`].
+pub fn from_options(name: &str, options: &BTreeMap<String, Vec<String>>) -> Result<Spec, String> {
+    let error = |why: String| format!("[{name}]: {why}");
+    let mut spec = Spec {
+        name: name.to_string(),
+        input: vec![],
+        params: vec![],
+        max_steps: DEFAULT_MAX_STEPS,
+        halt: None,
+        tape: None,
+        points: 1,
+    };
+    for (key, values) in options {
+        let value = || match values.as_slice() {
+            [value] => Ok(value.as_str()),
+            _ => Err(error(format!("'{key}' takes a single value"))),
+        };
+        match key.as_str() {
+            "input" => spec.input = turing::parse_word(value()?).map_err(error)?,
+            "param" => {
+                spec.params = values
+                    .iter()
+                    .map(|value| value.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(error)?
+            }
+            "max-steps" => {
+                spec.max_steps = value()?
+                    .parse()
+                    .map_err(|why| error(format!("invalid max-steps: {why}")))?
+            }
+            "halt" => {
+                spec.halt = Some(match value()? {
+                    "Halt" => Halt::Halted(HaltReason::Halt),
+                    "Accept" => Halt::Halted(HaltReason::Accept),
+                    "Reject" => Halt::Halted(HaltReason::Reject),
+                    "Crash" => Halt::Halted(HaltReason::Crash),
+                    "running" => Halt::Running,
+                    other => return Err(error(format!("unknown halting state '{other}'"))),
+                })
+            }
+            "tape" => spec.tape = Some(Pattern::parse(value()?).map_err(error)?),
+            "points" => {
+                spec.points = value()?
+                    .parse()
+                    .map_err(|why| error(format!("invalid points: {why}")))?
+            }
+            _ => return Err(error(format!("unknown key '{key}'"))),
+        }
+    }
+    Ok(spec)
+}
+
+/// Reads the tests of the test file at `path`.
+pub fn load(path: &Path) -> Result<Vec<Spec>, String> {
+    let content = fs::read_to_string(path).map_err(|why| why.to_string())?;
+    parse(&content)
+}
+
+/// Runs the machine file at `path` for the test `spec` within `limits`. A
+/// missing transition rejects the input. Reaching a limit fails the test,
+/// unless it is the step budget of the test itself.
+pub fn check(path: &Path, spec: &Spec, limits: &Limits) -> Result<Outcome, String> {
+    let mut tm = TuringMachine::read(path, &spec.params)?;
+    tm.set_reject_undefined(true);
+    if !spec.input.is_empty() {
+        tm.set_input(&spec.input);
+    }
+    let limits = Limits {
+        max_steps: spec.max_steps.min(limits.max_steps),
+        ..*limits
+    };
+    let stop = sandbox::run(&mut tm, &limits);
+    let truncated = match stop {
+        Stop::Halted => false,
+        Stop::Steps => limits.max_steps < spec.max_steps,
+        Stop::TapeCells | Stop::Time => true,
+    };
+
+    let halt = Halt::of(&tm);
+    let tape: Vec<TapeEntry> = tm.tape().iter().copied().collect();
+    let failure = match (&spec.halt, &spec.tape) {
+        _ if truncated => stop
+            .reason(&limits)
+            .map(|reason| format!("stopped at {reason}")),
+        (Some(expected), _) if expected != &halt => {
+            Some(format!("expected {expected}, got {halt}"))
+        }
+        (_, Some(pattern)) if !pattern.matches(&tape, tm.blank()) => Some(format!(
+            "expected tape '{}', got '{}'",
+            pattern.text,
+            format_word(written(&tape, tm.blank()))
+        )),
+        _ => None,
+    };
+    Ok(Outcome {
+        halt,
+        steps: tm.num_steps,
+        failure,
+    })
+}
+
+#[test]
+fn test_check() {
+    let pattern = Pattern::parse("1?1*").unwrap();
+    assert!(pattern.matches(&[0, 1, 0, 1, 0], 0));
+    assert!(pattern.matches(&[1, 2, 1, 3, 3], 0));
+    assert!(!pattern.matches(&[1, 2, 2], 0));
+    assert!(Pattern::parse("").unwrap().matches(&[0, 0], 0));
+    assert!(Pattern::parse("12 * 3").unwrap().matches(&[12, 3], 0));
+    assert!(Pattern::parse("1x").is_err());
+
+    let specs = parse(
+        "max-steps = 50\n\
+         \n\
+         [default]\n\
+         halt = \"Halt\"\n\
+         tape = \"11111111\"\n\
+         \n\
+         [three]\n\
+         param = [\"N=3\"]\n\
+         tape = \"1*\"\n\
+         \n\
+         [budget]\n\
+         max-steps = 2\n\
+         halt = \"running\"\n\
+         \n\
+         [wrong]\n\
+         param = [\"N=2\"]\n\
+         tape = \"111\"\n",
+    )
+    .unwrap();
+    assert_eq!(specs.len(), 4);
+    assert_eq!(specs[0].name, "budget");
+    assert_eq!(specs[0].max_steps, 2);
+    assert_eq!(specs[1].max_steps, 50);
+
+    let path = Path::new("examples/compose/n_ones.turing");
+    let outcomes: Vec<Outcome> = specs
+        .iter()
+        .map(|spec| check(path, spec, &Limits::NONE).unwrap())
+        .collect();
+    assert_eq!(outcomes[0].halt, Halt::Running);
+    assert_eq!(outcomes[1].steps, 9);
+    assert!(outcomes[..3]
+        .iter()
+        .all(|outcome| outcome.failure.is_none()));
+    assert_eq!(
+        outcomes[3].failure.as_deref(),
+        Some("expected tape '111', got '11'")
+    );
+    let limits = Limits {
+        max_tape_cells: 4,
+        ..Limits::NONE
+    };
+    assert_eq!(
+        check(path, &specs[1], &limits).unwrap().failure.as_deref(),
+        Some("stopped at the limit of 4 tape cells")
+    );
+    // A cap no lower than the budget of the test changes nothing.
+    assert_eq!(
+        check(
+            path,
+            &specs[0],
+            &Limits {
+                max_steps: 2,
+                ..Limits::NONE
+            }
+        )
+        .unwrap()
+        .failure,
+        None
+    );
+    assert_eq!(
+        check(
+            path,
+            &specs[1],
+            &Limits {
+                max_steps: 2,
+                ..Limits::NONE
+            }
+        )
+        .unwrap()
+        .failure
+        .as_deref(),
+        Some("stopped at the limit of 2 steps")
+    );
+
+    // The blank of the machine is trimmed, not 0.
+    let path = std::env::temp_dir().join(format!("blank_{}.turing", std::process::id()));
+    std::fs::write(&path, "%blank 2\nA 2 -> B 1 R\nB 2 -> Halt 1 R\n").unwrap();
+    let specs = parse("[ones]\ntape = \"11\"\n").unwrap();
+    let outcome = check(&path, &specs[0], &Limits::NONE);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(outcome.unwrap().failure, None);
+
+    assert_eq!(
+        parse("[a]\nhalt = \"Stop\"").unwrap_err(),
+        "[a]: unknown halting state 'Stop'"
+    );
+    assert_eq!(
+        parse("[a]\nsteps = 1").unwrap_err(),
+        "[a]: unknown key 'steps'"
+    );
+}
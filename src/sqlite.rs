@@ -0,0 +1,465 @@
+use std::{fmt::Display, fs, path::Path};
+
+const MAGIC: &[u8] = b"SQLite format 3\0";
+const PAGE: usize = 4096;
+const LEAF: u8 = 0x0d;
+const INTERIOR: u8 = 0x05;
+
+/// A value in a table.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Integer(n) => write!(f, "{n}"),
+            Value::Real(x) => write!(f, "{x}"),
+            Value::Text(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+/// Appends the variable length integer of SQLite, big-endian with seven
+/// bits per byte and all eight in the ninth.
+fn varint(out: &mut Vec<u8>, n: u64) {
+    if n >> 56 != 0 {
+        let mut bytes = [0u8; 9];
+        bytes[8] = n as u8;
+        let mut rest = n >> 8;
+        for byte in bytes[..8].iter_mut().rev() {
+            *byte = rest as u8 & 0x7f | 0x80;
+            rest >>= 7;
+        }
+        out.extend(bytes);
+        return;
+    }
+    let mut bytes = vec![];
+    let mut rest = n;
+    loop {
+        bytes.push(rest as u8 & 0x7f | 0x80);
+        rest >>= 7;
+        if rest == 0 {
+            break;
+        }
+    }
+    bytes[0] &= 0x7f;
+    out.extend(bytes.iter().rev());
+}
+
+fn read_varint(bytes: &[u8], at: &mut usize) -> Result<u64, String> {
+    let mut n = 0u64;
+    for index in 0..9 {
+        let byte = *bytes.get(*at).ok_or("truncated varint")?;
+        *at += 1;
+        if index == 8 {
+            return Ok(n << 8 | byte as u64);
+        }
+        n = n << 7 | (byte & 0x7f) as u64;
+        if byte < 0x80 {
+            break;
+        }
+    }
+    Ok(n)
+}
+
+/// The record format of a row: a header with the serial type of every
+/// value, followed by the values.
+fn record(values: &[Value]) -> Vec<u8> {
+    let (mut types, mut body) = (vec![], vec![]);
+    for value in values {
+        match value {
+            Value::Null => varint(&mut types, 0),
+            Value::Integer(0) => varint(&mut types, 8),
+            Value::Integer(1) => varint(&mut types, 9),
+            Value::Integer(n) => {
+                let (kind, len) = match *n {
+                    -0x80..=0x7f => (1, 1),
+                    -0x8000..=0x7fff => (2, 2),
+                    -0x80_0000..=0x7f_ffff => (3, 3),
+                    -0x8000_0000..=0x7fff_ffff => (4, 4),
+                    -0x8000_0000_0000..=0x7fff_ffff_ffff => (5, 6),
+                    _ => (6, 8),
+                };
+                varint(&mut types, kind);
+                body.extend(&n.to_be_bytes()[8 - len..]);
+            }
+            Value::Real(x) => {
+                varint(&mut types, 7);
+                body.extend(x.to_be_bytes());
+            }
+            Value::Text(s) => {
+                varint(&mut types, 13 + 2 * s.len() as u64);
+                body.extend(s.as_bytes());
+            }
+        }
+    }
+    // The size of the header counts itself.
+    let mut size = types.len() + 1;
+    let mut header = vec![];
+    loop {
+        header.clear();
+        varint(&mut header, size as u64);
+        if header.len() + types.len() == size {
+            break;
+        }
+        size = header.len() + types.len();
+    }
+    header.extend(types);
+    header.extend(body);
+    header
+}
+
+fn read_record(payload: &[u8]) -> Result<Vec<Value>, String> {
+    let mut at = 0;
+    let size = read_varint(payload, &mut at)? as usize;
+    let mut body = size;
+    let mut values = vec![];
+    while at < size {
+        let kind = read_varint(payload, &mut at)?;
+        let len = match kind {
+            0 | 8 | 9 => 0,
+            1..=4 => kind as usize,
+            5 => 6,
+            6 | 7 => 8,
+            12.. => (kind as usize - 12) / 2,
+            _ => return Err(format!("unknown serial type {kind}")),
+        };
+        let bytes = payload.get(body..body + len).ok_or("truncated record")?;
+        body += len;
+        values.push(match kind {
+            0 => Value::Null,
+            8 => Value::Integer(0),
+            9 => Value::Integer(1),
+            1..=6 => {
+                // Sign extend the big-endian bytes.
+                let fill = if bytes[0] & 0x80 != 0 { 0xff } else { 0 };
+                let mut full = [fill; 8];
+                full[8 - len..].copy_from_slice(bytes);
+                Value::Integer(i64::from_be_bytes(full))
+            }
+            7 => Value::Real(f64::from_be_bytes(bytes.try_into().unwrap())),
+            _ if kind % 2 == 1 => Value::Text(String::from_utf8_lossy(bytes).into_owned()),
+            _ => return Err("BLOBs aren't supported".to_string()),
+        });
+    }
+    Ok(values)
+}
+
+/// A b-tree page in the making, with the cells growing down from its end.
+struct Page {
+    /// Offset of the page header, 100 on the first page.
+    start: usize,
+    kind: u8,
+    cells: Vec<Vec<u8>>,
+    right: Option<u32>,
+}
+
+impl Page {
+    fn new(kind: u8) -> Self {
+        Page {
+            start: 0,
+            kind,
+            cells: vec![],
+            right: None,
+        }
+    }
+
+    fn header(&self) -> usize {
+        match self.kind {
+            LEAF => 8,
+            _ => 12,
+        }
+    }
+
+    fn used(&self) -> usize {
+        self.start + self.header() + self.cells.iter().map(|cell| cell.len() + 2).sum::<usize>()
+    }
+
+    fn fits(&self, cell: &[u8]) -> bool {
+        self.used() + cell.len() + 2 <= PAGE
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        let mut page = vec![0; PAGE];
+        let mut end = PAGE;
+        let mut pointers = vec![];
+        for cell in &self.cells {
+            end -= cell.len();
+            page[end..end + cell.len()].copy_from_slice(cell);
+            pointers.extend((end as u16).to_be_bytes());
+        }
+        let header = &mut page[self.start..];
+        header[0] = self.kind;
+        header[3..5].copy_from_slice(&(self.cells.len() as u16).to_be_bytes());
+        header[5..7].copy_from_slice(&(end as u16).to_be_bytes());
+        if let Some(right) = self.right {
+            header[8..12].copy_from_slice(&right.to_be_bytes());
+        }
+        let at = self.start + self.header();
+        page[at..at + pointers.len()].copy_from_slice(&pointers);
+        page
+    }
+}
+
+/// Builds the b-tree of a table from its rows in the order of their row
+/// ids, starting at page `first`. Gives its pages, the root coming last.
+fn table(rows: &[Vec<u8>], first: u32) -> Result<Vec<Page>, String> {
+    let mut pages = vec![];
+    // The pages of the level below with the largest row id in each.
+    let mut children: Vec<(u32, u64)> = vec![];
+    let mut leaf = Page::new(LEAF);
+    for (index, payload) in rows.iter().enumerate() {
+        let rowid = index as u64 + 1;
+        // Longer rows would spill to overflow pages.
+        if payload.len() > PAGE - 35 {
+            return Err(format!("row {rowid} is too big"));
+        }
+        let mut cell = vec![];
+        varint(&mut cell, payload.len() as u64);
+        varint(&mut cell, rowid);
+        cell.extend(payload);
+        if !leaf.fits(&cell) {
+            pages.push(std::mem::replace(&mut leaf, Page::new(LEAF)));
+            children.push((first + pages.len() as u32 - 1, rowid - 1));
+        }
+        leaf.cells.push(cell);
+    }
+    pages.push(leaf);
+    children.push((first + pages.len() as u32 - 1, rows.len() as u64));
+
+    while children.len() > 1 {
+        let mut parents = vec![];
+        let mut page = Page::new(INTERIOR);
+        // The last child of a page is its right pointer instead of a cell.
+        let mut last: Option<(u32, u64)> = None;
+        for (child, key) in children {
+            if let Some((previous, previous_key)) = last {
+                let mut cell = previous.to_be_bytes().to_vec();
+                varint(&mut cell, previous_key);
+                if page.fits(&cell) {
+                    page.cells.push(cell);
+                } else {
+                    page.right = Some(previous);
+                    pages.push(std::mem::replace(&mut page, Page::new(INTERIOR)));
+                    parents.push((first + pages.len() as u32 - 1, previous_key));
+                }
+            }
+            last = Some((child, key));
+        }
+        let (child, key) = last.expect("a level has pages");
+        page.right = Some(child);
+        pages.push(page);
+        parents.push((first + pages.len() as u32 - 1, key));
+        children = parents;
+    }
+    Ok(pages)
+}
+
+/// Writes a database with the table `name` holding `rows`, whose columns
+/// are declared as `columns`, like `id INTEGER`. It replaces `path`.
+pub fn write(path: &Path, name: &str, columns: &[&str], rows: &[Vec<Value>]) -> Result<(), String> {
+    let rows: Vec<Vec<u8>> = rows.iter().map(|row| record(row)).collect();
+    let pages = table(&rows, 2)?;
+    let root = pages.len() as u32 + 1;
+    let sql = format!("CREATE TABLE {name}({})", columns.join(", "));
+    let schema = record(&[
+        Value::Text("table".to_string()),
+        Value::Text(name.to_string()),
+        Value::Text(name.to_string()),
+        Value::Integer(root as i64),
+        Value::Text(sql),
+    ]);
+    let mut cell = vec![];
+    varint(&mut cell, schema.len() as u64);
+    varint(&mut cell, 1);
+    cell.extend(schema);
+    let first = Page {
+        start: 100,
+        cells: vec![cell],
+        ..Page::new(LEAF)
+    };
+
+    let mut bytes = first.bytes();
+    let header = &mut bytes[..100];
+    header[..16].copy_from_slice(MAGIC);
+    header[16..18].copy_from_slice(&(PAGE as u16).to_be_bytes());
+    // File format versions, reserved bytes and payload fractions.
+    header[18..24].copy_from_slice(&[1, 1, 0, 64, 32, 32]);
+    header[24..28].copy_from_slice(&1u32.to_be_bytes());
+    header[28..32].copy_from_slice(&(pages.len() as u32 + 1).to_be_bytes());
+    // Schema cookie and format, and UTF-8 text.
+    header[40..44].copy_from_slice(&1u32.to_be_bytes());
+    header[44..48].copy_from_slice(&4u32.to_be_bytes());
+    header[56..60].copy_from_slice(&1u32.to_be_bytes());
+    header[92..96].copy_from_slice(&1u32.to_be_bytes());
+    header[96..100].copy_from_slice(&3_045_000u32.to_be_bytes());
+    for page in &pages {
+        bytes.extend(page.bytes());
+    }
+    fs::write(path, bytes).map_err(|why| why.to_string())
+}
+
+/// The rows of a table with the names of its columns.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Reads the rows of the b-tree at page `number` in order.
+fn rows(
+    bytes: &[u8],
+    size: usize,
+    number: u64,
+    rows: &mut Vec<(u64, Vec<Value>)>,
+) -> Result<(), String> {
+    let offset = (number as usize)
+        .checked_sub(1)
+        .map(|index| index * size)
+        .filter(|offset| offset + size <= bytes.len())
+        .ok_or(format!("page {number} doesn't exist"))?;
+    let page = &bytes[offset..offset + size];
+    let start = if number == 1 { 100 } else { 0 };
+    let header = &page[start..];
+    let count = u16::from_be_bytes([header[3], header[4]]) as usize;
+    let pointers = match header[0] {
+        LEAF => &header[8..],
+        INTERIOR => &header[12..],
+        kind => return Err(format!("page {number} of kind {kind} isn't of a table")),
+    };
+    for pointer in pointers.chunks(2).take(count) {
+        let mut at = u16::from_be_bytes([pointer[0], pointer[1]]) as usize;
+        if header[0] == INTERIOR {
+            let child = page.get(at..at + 4).ok_or("invalid cell")?;
+            let child = u32::from_be_bytes(child.try_into().unwrap());
+            self::rows(bytes, size, child as u64, rows)?;
+            continue;
+        }
+        let len = read_varint(page, &mut at)? as usize;
+        let rowid = read_varint(page, &mut at)?;
+        if len > size - 35 {
+            return Err("rows spilling to overflow pages aren't supported".to_string());
+        }
+        let payload = page.get(at..at + len).ok_or("truncated cell")?;
+        rows.push((rowid, read_record(payload)?));
+    }
+    if header[0] == INTERIOR {
+        let right = u32::from_be_bytes(header[8..12].try_into().unwrap());
+        self::rows(bytes, size, right as u64, rows)?;
+    }
+    Ok(())
+}
+
+/// Reads the table `name` of the database at `path`, or its first table.
+pub fn read(path: &Path, name: Option<&str>) -> Result<Table, String> {
+    let bytes = fs::read(path).map_err(|why| why.to_string())?;
+    if !bytes.starts_with(MAGIC) || bytes.len() < 100 {
+        return Err(format!("{} isn't a SQLite database", path.display()));
+    }
+    let size = match u16::from_be_bytes([bytes[16], bytes[17]]) {
+        1 => 65536,
+        size => size as usize,
+    };
+    let mut schema = vec![];
+    rows(&bytes, size, 1, &mut schema)?;
+    let (root, sql) = schema
+        .iter()
+        .find_map(|(_, row)| match &row[..] {
+            [Value::Text(kind), Value::Text(table), _, Value::Integer(root), Value::Text(sql)]
+                if kind == "table" && name.is_none_or(|name| name == table) =>
+            {
+                Some((*root, sql))
+            }
+            _ => None,
+        })
+        .ok_or(match name {
+            Some(name) => format!("there is no table '{name}'"),
+            None => "there are no tables".to_string(),
+        })?;
+
+    // The column definitions between the outer parentheses of the SQL.
+    let definitions = sql
+        .find('(')
+        .zip(sql.rfind(')'))
+        .map(|(start, end)| &sql[start + 1..end])
+        .ok_or("invalid table definition")?;
+    let mut columns = vec![];
+    // An integer primary key is stored as the row id.
+    let mut key = None;
+    for definition in definitions.split(',') {
+        let words: Vec<String> = definition
+            .split_whitespace()
+            .map(str::to_uppercase)
+            .collect();
+        let Some(column) = definition.split_whitespace().next() else {
+            continue;
+        };
+        if words
+            .first()
+            .is_some_and(|word| word == "PRIMARY" || word == "UNIQUE")
+        {
+            continue;
+        }
+        if words.get(1).is_some_and(|kind| kind == "INTEGER")
+            && words[2..].starts_with(&["PRIMARY".to_string(), "KEY".to_string()])
+        {
+            key = Some(columns.len());
+        }
+        columns.push(column.trim_matches(['"', '`']).to_string());
+    }
+
+    let mut table = vec![];
+    rows(&bytes, size, root as u64, &mut table)?;
+    let rows = table
+        .into_iter()
+        .map(|(rowid, mut row)| {
+            // Columns added later are missing in older rows.
+            row.resize(columns.len(), Value::Null);
+            if let Some(key) = key {
+                row[key] = Value::Integer(rowid as i64);
+            }
+            row
+        })
+        .collect();
+    Ok(Table { columns, rows })
+}
+
+#[test]
+fn test_sqlite() {
+    for n in [0, 1, 127, 128, 300, 1 << 40, u64::MAX] {
+        let mut bytes = vec![];
+        varint(&mut bytes, n);
+        assert_eq!(read_varint(&bytes, &mut 0), Ok(n));
+    }
+    let mut bytes = vec![];
+    varint(&mut bytes, 300);
+    assert_eq!(bytes, [0x82, 0x2c]);
+
+    let row = vec![
+        Value::Integer(-300),
+        Value::Text("halts".to_string()),
+        Value::Null,
+        Value::Real(0.25),
+        Value::Integer(1),
+        Value::Integer(i64::MAX),
+    ];
+    assert_eq!(read_record(&record(&row)).as_ref(), Ok(&row));
+
+    // Enough rows for two levels of interior pages.
+    let path = std::env::temp_dir().join(format!("sqlite_{}.sqlite", std::process::id()));
+    let rows: Vec<Vec<Value>> = (0..200_000)
+        .map(|id| vec![Value::Integer(id), Value::Text(format!("machine {id}"))])
+        .collect();
+    write(&path, "results", &["id INTEGER", "machine TEXT"], &rows).unwrap();
+    let table = read(&path, Some("results")).unwrap();
+    assert_eq!(table.columns, ["id", "machine"]);
+    assert_eq!(table.rows, rows);
+    assert!(read(&path, Some("other")).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
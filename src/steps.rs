@@ -0,0 +1,178 @@
+use std::fmt::Display;
+
+/// A count of simulated steps.
+///
+/// A single macro step can cross astronomically many steps, so the count is
+/// only ever increased through [`Steps::add`], which fails instead of
+/// wrapping around. With the `big-steps` feature the count has arbitrary
+/// precision and never fails, otherwise it is limited to 128 bits.
+#[cfg(not(feature = "big-steps"))]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Steps(u128);
+
+#[cfg(not(feature = "big-steps"))]
+impl Steps {
+    pub fn new(steps: u128) -> Self {
+        Steps(steps)
+    }
+
+    /// Adds `count` times `steps`.
+    pub fn add(&mut self, steps: u128, count: u128) -> Result<(), String> {
+        self.0 = steps
+            .checked_mul(count)
+            .and_then(|product| self.0.checked_add(product))
+            .ok_or("the step count overflowed 128 bits, enable the big-steps feature")?;
+        Ok(())
+    }
+
+    /// The count, if it fits into a `u128`.
+    pub fn to_u128(&self) -> Option<u128> {
+        Some(self.0)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.0 as f64
+    }
+}
+
+#[cfg(not(feature = "big-steps"))]
+impl Display for Steps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Little-endian 32 bit limbs without leading zeros, so the derived
+/// comparison for equality is correct.
+#[cfg(feature = "big-steps")]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Steps(Vec<u32>);
+
+#[cfg(feature = "big-steps")]
+fn limbs(n: u128) -> Vec<u32> {
+    let mut limbs: Vec<u32> = (0..4).map(|i| (n >> (32 * i)) as u32).collect();
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    limbs
+}
+
+#[cfg(feature = "big-steps")]
+impl Steps {
+    pub fn new(steps: u128) -> Self {
+        Steps(limbs(steps))
+    }
+
+    /// Adds `count` times `steps`.
+    pub fn add(&mut self, steps: u128, count: u128) -> Result<(), String> {
+        let (steps, count) = (limbs(steps), limbs(count));
+        let mut product = vec![0u32; steps.len() + count.len()];
+        for (i, a) in steps.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in count.iter().enumerate() {
+                let n = *a as u64 * *b as u64 + product[i + j] as u64 + carry;
+                product[i + j] = n as u32;
+                carry = n >> 32;
+            }
+            product[i + count.len()] = carry as u32;
+        }
+
+        let len = self.0.len().max(product.len()) + 1;
+        self.0.resize(len, 0);
+        let mut carry = 0u64;
+        for (i, limb) in self.0.iter_mut().enumerate() {
+            let n = *limb as u64 + *product.get(i).unwrap_or(&0) as u64 + carry;
+            *limb = n as u32;
+            carry = n >> 32;
+        }
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+        Ok(())
+    }
+
+    /// The count, if it fits into a `u128`.
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.0.len() > 4 {
+            return None;
+        }
+        Some(
+            self.0
+                .iter()
+                .enumerate()
+                .map(|(i, limb)| (*limb as u128) << (32 * i))
+                .sum(),
+        )
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.0
+            .iter()
+            .rev()
+            .fold(0.0, |value, limb| value * 4_294_967_296.0 + *limb as f64)
+    }
+}
+
+#[cfg(feature = "big-steps")]
+impl Display for Steps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Split off nine decimal digits at a time.
+        let mut limbs = self.0.clone();
+        let mut groups = vec![];
+        while !limbs.is_empty() {
+            let mut remainder = 0u64;
+            for limb in limbs.iter_mut().rev() {
+                let n = (remainder << 32) | *limb as u64;
+                *limb = (n / 1_000_000_000) as u32;
+                remainder = n % 1_000_000_000;
+            }
+            groups.push(remainder);
+            while limbs.last() == Some(&0) {
+                limbs.pop();
+            }
+        }
+        let mut digits = match groups.pop() {
+            Some(first) => first.to_string(),
+            None => "0".to_string(),
+        };
+        for group in groups.iter().rev() {
+            digits.push_str(&format!("{group:09}"));
+        }
+        f.pad(&digits)
+    }
+}
+
+impl PartialEq<u128> for Steps {
+    fn eq(&self, other: &u128) -> bool {
+        self.to_u128() == Some(*other)
+    }
+}
+
+#[test]
+fn test_steps() {
+    let mut steps = Steps::new(47_176_870);
+    steps.add(3, 10).unwrap();
+    assert_eq!(steps, 47_176_900);
+    assert_eq!(steps.to_string(), "47176900");
+    assert_eq!(steps.to_f64(), 47_176_900.0);
+
+    let overflowed = Steps::new(u128::MAX).add(u128::MAX, 2);
+    if cfg!(feature = "big-steps") {
+        assert_eq!(overflowed, Ok(()));
+    } else {
+        assert!(overflowed.is_err());
+    }
+}
+
+#[cfg(feature = "big-steps")]
+#[test]
+fn test_big_steps() {
+    let mut steps = Steps::new(u128::MAX);
+    steps.add(u128::MAX, u128::MAX).unwrap();
+    // (2^128 - 1) + (2^128 - 1)^2 = 2^256 - 2^128
+    assert_eq!(
+        steps.to_string(),
+        "115792089237316195423570985008687907852929702298719625575994209400481361428480"
+    );
+    assert_eq!(steps.to_u128(), None);
+}
@@ -0,0 +1,423 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::turing::{strip_comment, Direction, TapeEntry, TuringMachine};
+
+/// A tag system: every step reads the first letter of the word, deletes
+/// the first `deletion` letters and appends the production of the letter
+/// read. It halts on a letter without a production or when the word gets
+/// shorter than `deletion`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TagSystem {
+    pub deletion: usize,
+    pub letters: Vec<String>,
+    /// The production of every letter, `None` for letters that halt.
+    pub productions: Vec<Option<Vec<usize>>>,
+    /// The word to start with.
+    pub word: Vec<usize>,
+    index: HashMap<String, usize>,
+}
+
+/// How a run of a tag system ended.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Run {
+    pub steps: u128,
+    pub halted: bool,
+    pub word: Vec<usize>,
+}
+
+impl TagSystem {
+    pub fn new(deletion: usize) -> Self {
+        TagSystem {
+            deletion,
+            letters: vec![],
+            productions: vec![],
+            word: vec![],
+            index: HashMap::new(),
+        }
+    }
+
+    /// The index of the letter `name`, added without a production if it
+    /// is new.
+    pub fn letter(&mut self, name: &str) -> usize {
+        match self.index.get(name) {
+            Some(letter) => *letter,
+            None => {
+                self.letters.push(name.to_string());
+                self.productions.push(None);
+                self.index.insert(name.to_string(), self.letters.len() - 1);
+                self.letters.len() - 1
+            }
+        }
+    }
+
+    fn produce(&mut self, name: &str, production: &[String]) {
+        let letter = self.letter(name);
+        let production = production.iter().map(|name| self.letter(name)).collect();
+        self.productions[letter] = Some(production);
+    }
+
+    /// Reads a tag system written by [`Self::to_text`]: a `%deletion 2`
+    /// line, a `%word` line with the starting word and a line
+    /// `a -> b c` per production, with letters separated by whitespace.
+    /// Letters without a production halt. Comments start with `//`.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut tag = TagSystem::new(2);
+        let mut word = vec![];
+        for line in content.lines() {
+            let words: Vec<&str> = strip_comment(line).0.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                ["%deletion", deletion] => match deletion.parse() {
+                    Ok(deletion) if deletion > 0 => tag.deletion = deletion,
+                    _ => return Err(format!("invalid deletion number '{deletion}'")),
+                },
+                ["%word", letters @ ..] => word = letters.to_vec(),
+                [letter, "->", production @ ..] => {
+                    let production: Vec<String> =
+                        production.iter().map(|name| name.to_string()).collect();
+                    tag.produce(letter, &production);
+                }
+                _ => return Err(format!("invalid line '{line}'")),
+            }
+        }
+        tag.word = word.iter().map(|name| tag.letter(name)).collect();
+        Ok(tag)
+    }
+
+    /// Writes the tag system in the format read by [`Self::parse`].
+    pub fn to_text(&self) -> String {
+        let names = |letters: &[usize]| {
+            let names: Vec<&str> = letters
+                .iter()
+                .map(|letter| self.letters[*letter].as_str())
+                .collect();
+            names.join(" ")
+        };
+        let mut text = format!("%deletion {}\n%word {}\n", self.deletion, names(&self.word));
+        for (letter, production) in self.productions.iter().enumerate() {
+            if let Some(production) = production {
+                let line = format!("{} -> {}", self.letters[letter], names(production));
+                text += line.trim_end();
+                text += "\n";
+            }
+        }
+        text
+    }
+
+    /// Runs the tag system on `word` for up to `max_steps` steps.
+    pub fn run(&self, word: &[usize], max_steps: u128) -> Run {
+        let mut word: VecDeque<usize> = word.iter().copied().collect();
+        let mut steps = 0;
+        let halted = loop {
+            if word.len() < self.deletion {
+                break true;
+            }
+            let Some(production) = &self.productions[word[0]] else {
+                break true;
+            };
+            if steps >= max_steps {
+                break false;
+            }
+            word.drain(..self.deletion);
+            word.extend(production);
+            steps += 1;
+        };
+        Run {
+            steps,
+            halted,
+            word: word.into(),
+        }
+    }
+
+    /// The words of the letters, separated by spaces.
+    pub fn format_word(&self, word: &[usize]) -> String {
+        let names: Vec<&str> = word
+            .iter()
+            .map(|letter| self.letters[*letter].as_str())
+            .collect();
+        names.join(" ")
+    }
+}
+
+/// A cyclic tag system over the bits of its word: step `i` deletes the
+/// first bit and, if it was a 1, appends production `i` modulo the number
+/// of productions.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CyclicTag {
+    pub productions: Vec<Vec<bool>>,
+}
+
+impl CyclicTag {
+    /// Emulates `tag` with Cook's construction: letter `i` of `n` becomes
+    /// `n` bits with only bit `i` set, and the productions are those of
+    /// the letters, then `n * (deletion - 1)` empty ones. Every step of
+    /// `tag` takes [`Self::productions`]`.len()` steps. Letters that halt
+    /// get empty productions, as cyclic tag systems don't halt.
+    pub fn emulate(tag: &TagSystem) -> Self {
+        let n = tag.letters.len();
+        let mut productions: Vec<Vec<bool>> = tag
+            .productions
+            .iter()
+            .map(|production| match production {
+                Some(production) => Self::encode(n, production),
+                None => vec![],
+            })
+            .collect();
+        productions.extend(vec![vec![]; n * (tag.deletion - 1)]);
+        CyclicTag { productions }
+    }
+
+    /// The bits of `word` over `letters` letters.
+    pub fn encode(letters: usize, word: &[usize]) -> Vec<bool> {
+        word.iter()
+            .flat_map(|letter| (0..letters).map(move |bit| bit == *letter))
+            .collect()
+    }
+
+    /// The letters of a word from [`Self::encode`], if it is one.
+    pub fn decode(letters: usize, bits: &[bool]) -> Option<Vec<usize>> {
+        if letters == 0 || !bits.len().is_multiple_of(letters) {
+            return None;
+        }
+        bits.chunks(letters)
+            .map(|block| match block.iter().filter(|bit| **bit).count() {
+                1 => block.iter().position(|bit| *bit),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Runs `steps` steps from step 0 on `word`, stopping early if the
+    /// word runs empty.
+    pub fn run(&self, word: &[bool], steps: u128) -> Vec<bool> {
+        let mut word: VecDeque<bool> = word.iter().copied().collect();
+        let mut step = 0;
+        while step < steps {
+            let Some(bit) = word.pop_front() else {
+                break;
+            };
+            if bit {
+                let production =
+                    &self.productions[(step % self.productions.len() as u128) as usize];
+                word.extend(production);
+            }
+            step += 1;
+        }
+        word.into()
+    }
+}
+
+/// Compiles a machine over `0` and `1` into a 2-tag system, following the
+/// construction of Cocke and Minsky.
+///
+/// A configuration in state `Q` is the word `A:Q x (a:Q x)^M B:Q x
+/// (b:Q x)^N`, where `M` holds the cells left of the head as a binary
+/// number with the closest cell as the lowest bit, and `N` the head cell
+/// and the cells right of it the same way. The letters `x` and `y` are
+/// fillers that are never read. Every step of the machine takes a few
+/// passes over the word: the first two halve `N`, which leaves the parity
+/// of the word deciding whether the next pass reads letters for reading
+/// `0` or `1`, then one pass writes the new configuration with `M` doubled,
+/// or two more halve `M` the same way to move left. Halting, and missing
+/// instructions, lead to `A:Halt`, `A:Accept` or `A:Reject`, which have
+/// no production, so the tag system halts with the tape of the machine in
+/// the word, see [`tape`].
+pub fn from_turing(tm: &TuringMachine) -> Result<TagSystem, String> {
+    let binary = tm
+        .instructions()
+        .iter()
+        .all(|instruction| instruction.entry <= 1 && instruction.new_entry <= 1);
+    if !binary {
+        return Err("only machines over 0 and 1 can be compiled, see --to-binary".into());
+    }
+    let mut tag = TagSystem::new(2);
+    let named = |kind: &str, state: &str| format!("{kind}:{state}");
+    let pairs = |kind: &str, state: &str, count: usize| -> Vec<String> {
+        (0..count)
+            .flat_map(|_| [named(kind, state), "x".to_string()])
+            .collect()
+    };
+    let filler = |r: usize| match r {
+        0 => vec!["y".to_string()],
+        _ => vec![],
+    };
+
+    for (state, q) in tm.states().iter().enumerate() {
+        // Halve N, with its parity deciding the alignment of the next pass.
+        tag.produce(&named("A", q), &[named("C", q), "x".into()]);
+        tag.produce(&named("a", q), &[named("c", q), "x".into()]);
+        tag.produce(&named("B", q), &[named("S", q)]);
+        tag.produce(&named("b", q), &[named("s", q)]);
+        for kind in ["C", "c", "S", "s"] {
+            let pair = [named(&format!("{kind}1"), q), named(&format!("{kind}0"), q)];
+            tag.produce(&named(kind, q), &pair);
+        }
+
+        for r in [0, 1] {
+            let instruction = tm
+                .instructions()
+                .iter()
+                .find(|instruction| instruction.state == state && instruction.entry == r as u8);
+            let (target, w, direction) = match instruction {
+                Some(instruction) => (
+                    match instruction.new_state {
+                        Some(new_state) => tm.states()[new_state].clone(),
+                        None => instruction.halt.name().to_string(),
+                    },
+                    instruction.new_entry as usize,
+                    match instruction.new_state {
+                        Some(_) => instruction.direction,
+                        // Halting leaves the same tape either way.
+                        None => Direction::Right,
+                    },
+                ),
+                None => ("Halt".to_string(), r, Direction::Right),
+            };
+            let t = target.as_str();
+            let qr = |kind: &str| named(&format!("{kind}{r}"), q);
+            match direction {
+                // M becomes 2M + w and N its half.
+                Direction::Right => {
+                    let start = [filler(r), pairs("A", t, 1), pairs("a", t, w)].concat();
+                    tag.produce(&qr("C"), &start);
+                    tag.produce(&qr("c"), &pairs("a", t, 2));
+                    tag.produce(&qr("S"), &pairs("B", t, 1));
+                    tag.produce(&qr("s"), &pairs("b", t, 1));
+                }
+                // Halve M, with its parity h deciding the alignment of E and
+                // e, and then of the next pass. N becomes 4N' + 2w + h.
+                Direction::Left => {
+                    let qrh = |kind: &str, h: usize| named(&format!("{kind}{r}{h}"), q);
+                    tag.produce(&qr("C"), &[filler(r), vec![qr("D")]].concat());
+                    tag.produce(&qr("c"), &[qr("d")]);
+                    tag.produce(&qr("S"), &[qrh("E", 1), qrh("E", 0)]);
+                    tag.produce(&qr("s"), &[qrh("e", 1), qrh("e", 0)]);
+                    tag.produce(&qr("D"), &[qrh("F", 1), qrh("F", 0)]);
+                    tag.produce(&qr("d"), &[qrh("f", 1), qrh("f", 0)]);
+                    let v = |h: usize| named(&format!("V{}", 2 * w + h), t);
+                    tag.produce(&qrh("E", 1), &[v(1), "x".into()]);
+                    tag.produce(&qrh("E", 0), &["x".into(), v(0)]);
+                    for h in [0, 1] {
+                        tag.produce(&qrh("e", h), &[named("u", t), named("u", t)]);
+                        tag.produce(&qrh("F", h), &[filler(h), pairs("A", t, 1)].concat());
+                        tag.produce(&qrh("f", h), &pairs("a", t, 1));
+                        let production = [pairs("B", t, 1), pairs("b", t, 2 * w + h)].concat();
+                        tag.produce(&v(h), &production);
+                    }
+                    tag.produce(&named("u", t), &pairs("b", t, 4));
+                }
+            }
+        }
+    }
+    tag.word = start(&tag, tm, &[]);
+    Ok(tag)
+}
+
+/// The word of the tag system compiled by [`from_turing`] for `tm` started
+/// on `input`, with the head on its first symbol.
+pub fn start(tag: &TagSystem, tm: &TuringMachine, input: &[TapeEntry]) -> Vec<usize> {
+    let q = &tm.states()[0];
+    let n: usize = input
+        .iter()
+        .enumerate()
+        .map(|(bit, symbol)| (*symbol as usize & 1) << bit)
+        .sum();
+    let letter = |name: String| tag.index[&name];
+    let mut word = vec![letter(format!("A:{q}")), letter("x".into())];
+    word.extend([letter(format!("B:{q}")), letter("x".into())]);
+    for _ in 0..n {
+        word.extend([letter(format!("b:{q}")), letter("x".into())]);
+    }
+    word
+}
+
+/// The state and the tape, from the leftmost written cell to the rightmost
+/// one, of a machine compiled by [`from_turing`], from a word of its tag
+/// system that starts with a configuration.
+pub fn tape(tag: &TagSystem, word: &[usize]) -> Option<(String, Vec<TapeEntry>)> {
+    let (kind, state) = tag.letters[*word.first()?].split_once(':')?;
+    if kind != "A" {
+        return None;
+    }
+    let count = |kind: &str| {
+        let name = format!("{kind}:{state}");
+        word.iter()
+            .filter(|letter| tag.letters[**letter] == name)
+            .count()
+    };
+    let bits = |number: usize| {
+        (0..usize::BITS - number.leading_zeros())
+            .map(|bit| (number >> bit & 1) as TapeEntry)
+            .collect::<Vec<_>>()
+    };
+    let mut cells = bits(count("a"));
+    cells.reverse();
+    cells.extend(bits(count("b")));
+    let first = cells.iter().position(|cell| *cell != 0);
+    let last = cells.iter().rposition(|cell| *cell != 0);
+    let cells = match (first, last) {
+        (Some(first), Some(last)) => cells[first..=last].to_vec(),
+        _ => vec![],
+    };
+    Some((state.to_string(), cells))
+}
+
+#[test]
+fn test_tag() {
+    use std::path::Path;
+
+    // The compiled busy beavers halt with the same tape.
+    for name in ["busy_bever_2", "busy_bever_3"] {
+        let path = format!("examples/busy_bever/{name}.turing");
+        let mut tm = TuringMachine::new(Path::new(&path));
+        let tag = from_turing(&tm).unwrap();
+        let run = tag.run(&tag.word, 1_000_000);
+        assert!(run.halted);
+        while tm.step() {}
+        let cells: Vec<TapeEntry> = tm.tape().iter().copied().collect();
+        let first = cells.iter().position(|cell| *cell != 0).unwrap();
+        let last = cells.iter().rposition(|cell| *cell != 0).unwrap();
+        assert_eq!(
+            tape(&tag, &run.word),
+            Some(("Halt".to_string(), cells[first..=last].to_vec()))
+        );
+        let parsed = TagSystem::parse(&tag.to_text()).unwrap();
+        let reparsed = parsed.run(&parsed.word, 1_000_000);
+        assert_eq!(reparsed.steps, run.steps);
+        assert_eq!(
+            parsed.format_word(&reparsed.word),
+            tag.format_word(&run.word)
+        );
+    }
+
+    // Moving left onto a written cell and starting on an input.
+    let tm = TuringMachine::parse(
+        "A 1 -> A 1 R\n\
+         A 0 -> B 1 L\n\
+         B 1 -> B 0 L\n\
+         B 0 -> Accept 1 L",
+    )
+    .unwrap();
+    let mut tag = from_turing(&tm).unwrap();
+    tag.word = start(&tag, &tm, &[1, 1]);
+    let run = tag.run(&tag.word, 1_000_000);
+    assert_eq!(
+        tape(&tag, &run.word),
+        Some(("Accept".into(), vec![1, 0, 0, 1]))
+    );
+
+    // The cyclic tag system goes through the same words.
+    let cyclic = CyclicTag::emulate(&tag);
+    let n = tag.letters.len();
+    assert_eq!(cyclic.productions.len(), 2 * n);
+    for steps in [0, 1, 5, 20] {
+        let word = tag.run(&tag.word, steps).word;
+        let bits = cyclic.run(&CyclicTag::encode(n, &tag.word), steps * 2 * n as u128);
+        assert_eq!(CyclicTag::decode(n, &bits), Some(word));
+    }
+
+    let wide = TuringMachine::parse("A 0 -> A 2 R").unwrap();
+    assert!(from_turing(&wide).is_err());
+    assert!(TagSystem::parse("%deletion 0").is_err());
+    assert!(TagSystem::parse("a b c").is_err());
+}
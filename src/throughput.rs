@@ -0,0 +1,171 @@
+use std::time::{Duration, Instant};
+
+use crate::json::Json;
+
+/// Time over which the moving average forgets older samples to about a
+/// third.
+const SMOOTHING: Duration = Duration::from_secs(5);
+/// Time between samples of a [`Sampler`].
+const INTERVAL: Duration = Duration::from_millis(500);
+/// Calls of [`Sampler::sample`] between looking at the clock, which costs
+/// more than a step.
+const CLOCK_INTERVAL: u32 = 1 << 16;
+
+/// Steps and new tape cells per second.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Rates {
+    pub steps: f64,
+    /// Cells the head touched for the first time, by which the tape grew.
+    pub cells: f64,
+}
+
+impl Rates {
+    fn to_json(self) -> Json {
+        Json::object([
+            ("steps_per_second", Json::Float(self.steps)),
+            ("cells_per_second", Json::Float(self.cells)),
+        ])
+    }
+}
+
+/// The throughput of a run, both over the last sample and as an exponential
+/// moving average over all of them, which shows the effect of acceleration
+/// while the run goes on.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Meter {
+    /// The rates of the last sample.
+    pub instant: Rates,
+    /// The moving average of the rates, weighing every sample by its
+    /// duration.
+    pub average: Rates,
+    /// Time covered by all samples.
+    pub time: Duration,
+}
+
+impl Meter {
+    /// Records that `steps` steps grew the tape by `cells` cells in
+    /// `elapsed`. Samples of no time are ignored.
+    pub fn record(&mut self, steps: f64, cells: f64, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if seconds <= 0.0 {
+            return;
+        }
+        self.instant = Rates {
+            steps: steps / seconds,
+            cells: cells / seconds,
+        };
+        let weight = match self.time.is_zero() {
+            true => 1.0,
+            false => 1.0 - (-seconds / SMOOTHING.as_secs_f64()).exp(),
+        };
+        self.average = Rates {
+            steps: self.average.steps + weight * (self.instant.steps - self.average.steps),
+            cells: self.average.cells + weight * (self.instant.cells - self.average.cells),
+        };
+        self.time += elapsed;
+    }
+
+    /// A line like `1.234e8 steps/s (average 1.200e8), 5.000e2 cells/s
+    /// (average 4.800e2)`.
+    pub fn line(&self) -> String {
+        format!(
+            "{:.3e} steps/s (average {:.3e}), {:.3e} cells/s (average {:.3e})",
+            self.instant.steps, self.average.steps, self.instant.cells, self.average.cells
+        )
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object([
+            ("instant", self.instant.to_json()),
+            ("average", self.average.to_json()),
+            ("seconds", Json::Float(self.time.as_secs_f64())),
+        ])
+    }
+}
+
+/// Feeds a [`Meter`] from a running loop, taking a sample about every
+/// [`INTERVAL`].
+pub struct Sampler {
+    pub meter: Meter,
+    last: Instant,
+    steps: f64,
+    cells: f64,
+    calls: u32,
+}
+
+impl Sampler {
+    /// Starts measuring a run that already took `steps` steps on a tape of
+    /// `cells` cells.
+    pub fn new(steps: f64, cells: f64) -> Self {
+        Sampler {
+            meter: Meter::default(),
+            last: Instant::now(),
+            steps,
+            cells,
+            calls: 0,
+        }
+    }
+
+    /// Takes a sample of the run, which has taken `steps` steps on a tape
+    /// of `cells` cells by now, if it is time to. Gives whether it was.
+    pub fn sample(&mut self, steps: f64, cells: f64) -> bool {
+        self.calls += 1;
+        if self.calls < CLOCK_INTERVAL {
+            return false;
+        }
+        self.calls = 0;
+        let elapsed = self.last.elapsed();
+        if elapsed < INTERVAL {
+            return false;
+        }
+        self.meter
+            .record(steps - self.steps, (cells - self.cells).max(0.0), elapsed);
+        self.last += elapsed;
+        self.steps = steps;
+        self.cells = cells;
+        true
+    }
+}
+
+#[test]
+fn test_throughput() {
+    let mut meter = Meter::default();
+    meter.record(1000.0, 10.0, Duration::from_secs(1));
+    assert_eq!(
+        meter.instant,
+        Rates {
+            steps: 1000.0,
+            cells: 10.0
+        }
+    );
+    assert_eq!(meter.average, meter.instant);
+
+    // A long sample outweighs the ones before.
+    meter.record(60_000.0, 0.0, Duration::from_secs(30));
+    assert_eq!(meter.instant.steps, 2000.0);
+    assert!(meter.average.steps > 1990.0 && meter.average.steps < 2000.0);
+    assert!(meter.average.cells < 0.1);
+    assert_eq!(meter.time, Duration::from_secs(31));
+
+    // A short one barely moves the average.
+    let average = meter.average;
+    meter.record(0.0, 0.0, Duration::from_millis(1));
+    assert_eq!(meter.instant.steps, 0.0);
+    assert!(meter.average.steps > 0.99 * average.steps);
+    meter.record(5.0, 5.0, Duration::ZERO);
+    assert_eq!(meter.instant.steps, 0.0);
+
+    assert_eq!(
+        Meter::default().line(),
+        "0.000e0 steps/s (average 0.000e0), 0.000e0 cells/s (average 0.000e0)"
+    );
+    let json = meter.to_json();
+    assert!(json
+        .field("average")
+        .unwrap()
+        .field("steps_per_second")
+        .is_ok());
+
+    let mut sampler = Sampler::new(0.0, 1.0);
+    assert!(!sampler.sample(1.0, 1.0));
+}
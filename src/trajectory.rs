@@ -0,0 +1,79 @@
+use std::io::Write;
+
+use crate::turing::TuringMachine;
+
+/// Writes the path of the head as CSV with the columns `step`, `head` and
+/// `tape_length`, to plot how far a machine sweeps over its tape. The head
+/// is counted from the starting cell, negative to its left.
+///
+/// Rows are written before the first step, after every `every`th and after
+/// the last one.
+pub struct Trajectory<W: Write> {
+    out: W,
+    every: u128,
+    /// The step of the last row.
+    last: u128,
+    rows: u128,
+}
+
+impl<W: Write> Trajectory<W> {
+    /// Writes the header and the row of `tm` before it runs.
+    pub fn new(out: W, every: u128, tm: &TuringMachine) -> std::io::Result<Self> {
+        let mut trajectory = Trajectory {
+            out,
+            every: every.max(1),
+            last: tm.num_steps,
+            rows: 0,
+        };
+        writeln!(trajectory.out, "step,head,tape_length")?;
+        trajectory.row(tm)?;
+        Ok(trajectory)
+    }
+
+    fn row(&mut self, tm: &TuringMachine) -> std::io::Result<()> {
+        let head = tm.head() as i128 - tm.origin() as i128;
+        writeln!(self.out, "{},{},{}", tm.num_steps, head, tm.tape().len())?;
+        self.last = tm.num_steps;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Writes a row for the step `tm` just took, if it is one of every
+    /// `every` steps.
+    pub fn record(&mut self, tm: &TuringMachine) -> std::io::Result<()> {
+        match tm.num_steps.is_multiple_of(self.every) {
+            true => self.row(tm),
+            false => Ok(()),
+        }
+    }
+
+    /// Writes the row of the last step unless it was written already and
+    /// gives the number of rows.
+    pub fn finish(mut self, tm: &TuringMachine) -> std::io::Result<u128> {
+        if self.last != tm.num_steps {
+            self.row(tm)?;
+        }
+        self.out.flush()?;
+        Ok(self.rows)
+    }
+}
+
+#[test]
+fn test_trajectory() {
+    let mut tm = TuringMachine::new(std::path::Path::new(
+        "examples/busy_bever/busy_bever_3.turing",
+    ));
+    let mut csv = vec![];
+    let mut trajectory = Trajectory::new(&mut csv, 5, &tm).unwrap();
+    while tm.step() {
+        trajectory.record(&tm).unwrap();
+    }
+    assert_eq!(trajectory.finish(&tm).unwrap(), 4);
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "step,head,tape_length");
+    assert_eq!(lines[1], "0,0,1");
+    assert!(lines[2].starts_with("5,"));
+    assert!(lines[4].starts_with("14,"));
+    assert_eq!(lines.len(), 5);
+}
@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+
+use crate::turing::{Direction, HaltReason, Instruction, TapeEntry, TuringMachine};
+
+/// All symbols the machine reads or writes, including the blank.
+pub fn symbols(tm: &TuringMachine) -> Vec<TapeEntry> {
+    let mut symbols: Vec<TapeEntry> = tm
+        .instructions()
+        .iter()
+        .flat_map(|instruction| [instruction.entry, instruction.new_entry])
+        .chain([0])
+        .collect();
+    symbols.sort();
+    symbols.dedup();
+    symbols
+}
+
+/// Number of cells needed to encode `symbols` symbols in binary.
+pub fn block_size(symbols: usize) -> usize {
+    let mut bits = 1;
+    while 1 << bits < symbols {
+        bits += 1;
+    }
+    bits
+}
+
+/// Returns the machine with every move in the other direction, which does
+/// the same on the mirrored tape.
+pub fn mirror(tm: &TuringMachine) -> TuringMachine {
+    let instructions = tm
+        .instructions()
+        .iter()
+        .map(|instruction| Instruction {
+            direction: instruction.direction.mirrored(),
+            ..instruction.clone()
+        })
+        .collect();
+    let mut mirrored = TuringMachine::from_instructions(tm.states().to_vec(), instructions);
+    mirrored.set_blank(tm.blank());
+    mirrored
+}
+
+/// Reads a permutation of symbols like `0=1,1=0`, where every symbol is
+/// replaced by the one after `=`. Symbols not given stay the same.
+pub fn parse_permutation(text: &str) -> Result<Vec<(TapeEntry, TapeEntry)>, String> {
+    let mut pairs = vec![];
+    for pair in text
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+    {
+        let symbol = |text: &str| {
+            text.trim()
+                .parse::<TapeEntry>()
+                .map_err(|_| format!("invalid symbol '{}'", text.trim()))
+        };
+        let Some((from, to)) = pair.split_once('=') else {
+            return Err(format!("expected FROM=TO, got '{pair}'"));
+        };
+        pairs.push((symbol(from)?, symbol(to)?));
+    }
+    let mut from: Vec<TapeEntry> = pairs.iter().map(|(from, _)| *from).collect();
+    let mut to: Vec<TapeEntry> = pairs.iter().map(|(_, to)| *to).collect();
+    from.sort();
+    to.sort();
+    if from.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err("a symbol is replaced twice".to_string());
+    }
+    if from != to {
+        return Err("the replacements aren't a permutation of the symbols".to_string());
+    }
+    Ok(pairs)
+}
+
+/// Returns the machine with its symbols replaced according to
+/// `permutation`, see [`parse_permutation`]. The blank is replaced too, so
+/// the machine does the same with the symbols replaced on the tape.
+pub fn permute_symbols(
+    tm: &TuringMachine,
+    permutation: &[(TapeEntry, TapeEntry)],
+) -> TuringMachine {
+    let image = |entry: TapeEntry| {
+        permutation
+            .iter()
+            .find(|(from, _)| *from == entry)
+            .map_or(entry, |(_, to)| *to)
+    };
+    let instructions = tm
+        .instructions()
+        .iter()
+        .map(|instruction| Instruction {
+            entry: image(instruction.entry),
+            new_entry: image(instruction.new_entry),
+            ..instruction.clone()
+        })
+        .collect();
+    let mut permuted = TuringMachine::from_instructions(tm.states().to_vec(), instructions);
+    permuted.set_blank(image(tm.blank()));
+    permuted
+}
+
+/// Collects instructions for a generated machine, naming states as they
+/// are first used.
+pub struct Builder {
+    states: Vec<String>,
+    index: HashMap<String, usize>,
+    instructions: Vec<Instruction>,
+}
+
+/// Target of a generated instruction.
+pub enum Target {
+    State(String),
+    Halt(HaltReason),
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Builder {
+            states: vec![],
+            index: HashMap::new(),
+            instructions: vec![],
+        }
+    }
+
+    pub fn state(&mut self, name: &str) -> usize {
+        match self.index.get(name) {
+            Some(state) => *state,
+            None => {
+                self.states.push(name.to_string());
+                self.index.insert(name.to_string(), self.states.len() - 1);
+                self.states.len() - 1
+            }
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        state: &str,
+        entry: TapeEntry,
+        target: &Target,
+        new_entry: TapeEntry,
+        direction: Direction,
+    ) {
+        let state = self.state(state);
+        let (new_state, halt) = match target {
+            Target::State(name) => (Some(self.state(name)), HaltReason::Halt),
+            Target::Halt(reason) => (None, *reason),
+        };
+        self.instructions.push(Instruction {
+            state,
+            entry,
+            new_state,
+            halt,
+            new_entry,
+            direction,
+        });
+    }
+
+    pub fn build(self) -> TuringMachine {
+        TuringMachine::from_instructions(self.states, self.instructions)
+    }
+}
+
+/// Converts a machine with `m` symbols into an equivalent machine over `0`
+/// and `1`.
+///
+/// Every cell of the original tape becomes a block of `⌈log₂ m⌉` cells,
+/// holding the index of the symbol in [`symbols`] in binary with the most
+/// significant bit first, so the blank stays all zeros. The head of the
+/// original machine corresponds to the leftmost cell of a block. A step
+/// reads the block from left to right, writes the new symbol from right to
+/// left and then moves to the leftmost cell of the next block. Halting
+/// transitions halt as soon as the block is written, one cell into the
+/// neighboring block.
+///
+/// Original states keep their names, intermediate states are named after
+/// them: `A.01` has read `01` in state `A`, `B.w2L1` writes bit 1 of symbol
+/// index 2 before moving left into `B`, and `B.L3` still has to move three
+/// cells left.
+pub fn to_binary(tm: &TuringMachine) -> TuringMachine {
+    let symbols = symbols(tm);
+    let bits = block_size(symbols.len());
+    let bit = |code: usize, i: usize| ((code >> (bits - 1 - i)) & 1) as TapeEntry;
+    let code = |symbol: TapeEntry| symbols.iter().position(|s| *s == symbol).unwrap();
+
+    let mut builder = Builder::new();
+    for (state, name) in tm.states().iter().enumerate() {
+        builder.state(name);
+        // Read the block from left to right. `prefix` holds the bits read
+        // so far as a number.
+        for read in 0..bits {
+            for prefix in 0..1 << read {
+                let from = if read == 0 {
+                    name.clone()
+                } else {
+                    format!("{}.{:0read$b}", name, prefix)
+                };
+                for entry in [0, 1] {
+                    let value = prefix << 1 | entry as usize;
+                    if read + 1 < bits {
+                        let to = format!("{}.{:0width$b}", name, value, width = read + 1);
+                        builder.add(&from, entry, &Target::State(to), entry, Direction::Right);
+                        continue;
+                    }
+
+                    let instruction = tm.instructions().iter().find(|instruction| {
+                        instruction.state == state && symbols.get(value) == Some(&instruction.entry)
+                    });
+                    let instruction = match instruction {
+                        Some(instruction) => instruction,
+                        None => continue,
+                    };
+                    let new_code = code(instruction.new_entry);
+                    let target = match instruction.new_state {
+                        Some(new_state) => tm.states()[new_state].clone(),
+                        None => instruction.halt.name().to_string(),
+                    };
+                    let writer = |i: usize| {
+                        format!(
+                            "{}.w{}{}{}",
+                            target,
+                            new_code,
+                            instruction.direction.letter(),
+                            i
+                        )
+                    };
+                    if bits == 1 {
+                        let to = match instruction.new_state {
+                            Some(_) => Target::State(target.clone()),
+                            None => Target::Halt(instruction.halt),
+                        };
+                        builder.add(&from, entry, &to, bit(new_code, 0), instruction.direction);
+                    } else {
+                        let to = Target::State(writer(bits - 2));
+                        builder.add(&from, entry, &to, bit(new_code, bits - 1), Direction::Left);
+                    }
+                }
+            }
+        }
+    }
+
+    // Write the remaining bits from right to left, then move on to the
+    // next block. The writers and movers are shared between all
+    // transitions with the same symbol, direction and target.
+    let mut writers: Vec<(String, usize, Direction, Option<usize>, HaltReason)> = vec![];
+    for instruction in tm.instructions() {
+        let target = match instruction.new_state {
+            Some(new_state) => tm.states()[new_state].clone(),
+            None => instruction.halt.name().to_string(),
+        };
+        let writer = (
+            target,
+            code(instruction.new_entry),
+            instruction.direction,
+            instruction.new_state,
+            instruction.halt,
+        );
+        if bits > 1 && !writers.contains(&writer) {
+            writers.push(writer);
+        }
+    }
+    let mut movers = vec![];
+    for (target, new_code, direction, new_state, halt) in writers {
+        let dir = direction.letter();
+        for i in (0..bits - 1).rev() {
+            let from = format!("{}.w{}{}{}", target, new_code, dir, i);
+            let to = match (i, new_state) {
+                (0, None) => Target::Halt(halt),
+                (0, Some(_)) => Target::State(format!("{}.{}{}", target, dir, bits - 1)),
+                (_, _) => Target::State(format!("{}.w{}{}{}", target, new_code, dir, i - 1)),
+            };
+            let direction = if i == 0 { direction } else { Direction::Left };
+            for entry in [0, 1] {
+                builder.add(&from, entry, &to, bit(new_code, i), direction);
+            }
+        }
+        if new_state.is_some() && !movers.contains(&(target.clone(), direction)) {
+            movers.push((target.clone(), direction));
+            for remaining in (1..bits).rev() {
+                let from = format!("{}.{}{}", target, dir, remaining);
+                let to = if remaining == 1 {
+                    target.clone()
+                } else {
+                    format!("{}.{}{}", target, dir, remaining - 1)
+                };
+                for entry in [0, 1] {
+                    builder.add(&from, entry, &Target::State(to.clone()), entry, direction);
+                }
+            }
+        }
+    }
+
+    builder.build()
+}
+
+/// Symbol numbering of a machine created by [`to_two_states`].
+///
+/// Plain cells keep the original symbols. On top of that come symbols for
+/// the cell the head just left ("source", still holding part of the state
+/// to transfer) and the cell it is moving to ("target", holding the part
+/// of the state transferred so far), each with the original symbol, a
+/// count between 1 and the number of states, and the direction from the
+/// source to the target.
+pub struct TwoStates {
+    symbols: Vec<TapeEntry>,
+    states: usize,
+    base: usize,
+}
+
+impl TwoStates {
+    fn new(tm: &TuringMachine) -> Self {
+        let symbols = symbols(tm);
+        let base = *symbols.last().unwrap() as usize + 1;
+        TwoStates {
+            symbols,
+            states: tm.states().len(),
+            base,
+        }
+    }
+
+    fn code(&self, symbol: TapeEntry) -> usize {
+        self.symbols.iter().position(|s| *s == symbol).unwrap()
+    }
+
+    fn number(&self, symbol: TapeEntry, count: usize, direction: Direction) -> usize {
+        (self.code(symbol) * self.states + count - 1) * 2
+            + match direction {
+                Direction::Left => 0,
+                Direction::Right => 1,
+            }
+    }
+
+    fn source(&self, symbol: TapeEntry, count: usize, direction: Direction) -> usize {
+        self.base + self.number(symbol, count, direction)
+    }
+
+    fn target(&self, symbol: TapeEntry, count: usize, direction: Direction) -> usize {
+        self.base + 2 * self.symbols.len() * self.states + self.number(symbol, count, direction)
+    }
+
+    /// Symbol that has to be under the head when starting the converted
+    /// machine, if the original machine starts on `symbol`.
+    pub fn start(&self, symbol: TapeEntry) -> TapeEntry {
+        self.target(symbol, 1, Direction::Right) as TapeEntry
+    }
+}
+
+/// Converts a machine into one with only two states, at the cost of
+/// `4 * states * symbols` additional symbols (Shannon's construction).
+///
+/// The state of the original machine is carried on the tape. After a
+/// transition to state `q`, the head bounces between the old and the new
+/// cell, counting `q` down in the old cell and up in the new cell. State
+/// `A` means "transfer finished" when reading a target cell and `B` means
+/// "keep counting". On a plain cell, which is always reached right after a
+/// transition, the state instead tells the direction the head came from.
+///
+/// Two states are not enough to also start on a blank cell, so the head
+/// must start on the symbol returned by [`TwoStates::start`] for the
+/// original start symbol. Plain symbols are unchanged and the converted
+/// machine halts with the same tape and head position as the original.
+pub fn to_two_states(tm: &TuringMachine) -> Result<(TuringMachine, TwoStates), String> {
+    let encoding = TwoStates::new(tm);
+    let total = encoding.target(
+        *encoding.symbols.last().unwrap(),
+        encoding.states,
+        Direction::Right,
+    ) + 1;
+    if total > TapeEntry::MAX as usize + 1 {
+        return Err(format!(
+            "{total} symbols needed, but at most 256 are supported"
+        ));
+    }
+
+    let mut builder = Builder::new();
+    let (done, count) = ("A", "B");
+    builder.state(done);
+    builder.state(count);
+    let arrived = |direction: Direction| match direction {
+        Direction::Left => count,
+        Direction::Right => done,
+    };
+    let opposite = |direction: Direction| match direction {
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+    };
+
+    for &symbol in &encoding.symbols {
+        // Reached a new cell right after a transition.
+        for direction in [Direction::Left, Direction::Right] {
+            let target = encoding.target(symbol, 1, direction) as TapeEntry;
+            let back = Target::State(done.to_string());
+            builder.add(
+                arrived(direction),
+                symbol,
+                &back,
+                target,
+                opposite(direction),
+            );
+        }
+
+        for direction in [Direction::Left, Direction::Right] {
+            for counted in 1..=encoding.states {
+                // The transfer is finished: run the original transition.
+                let target = encoding.target(symbol, counted, direction) as TapeEntry;
+                let instruction = tm.instructions().iter().find(|instruction| {
+                    instruction.state == counted - 1 && instruction.entry == symbol
+                });
+                if let Some(instruction) = instruction {
+                    match instruction.new_state {
+                        None => builder.add(
+                            done,
+                            target,
+                            &Target::Halt(instruction.halt),
+                            instruction.new_entry,
+                            instruction.direction,
+                        ),
+                        Some(new_state) => {
+                            let source = encoding.source(
+                                instruction.new_entry,
+                                new_state + 1,
+                                instruction.direction,
+                            );
+                            let next = Target::State(arrived(instruction.direction).to_string());
+                            builder.add(
+                                done,
+                                target,
+                                &next,
+                                source as TapeEntry,
+                                instruction.direction,
+                            );
+                        }
+                    }
+                }
+
+                // Count up in the new cell.
+                if counted < encoding.states {
+                    let next = encoding.target(symbol, counted + 1, direction) as TapeEntry;
+                    let back = Target::State(done.to_string());
+                    builder.add(count, target, &back, next, opposite(direction));
+                }
+
+                // Count down in the old cell.
+                let source = encoding.source(symbol, counted, direction) as TapeEntry;
+                if counted == 1 {
+                    builder.add(
+                        done,
+                        source,
+                        &Target::State(done.to_string()),
+                        symbol,
+                        direction,
+                    );
+                } else {
+                    let next = encoding.source(symbol, counted - 1, direction) as TapeEntry;
+                    builder.add(
+                        done,
+                        source,
+                        &Target::State(count.to_string()),
+                        next,
+                        direction,
+                    );
+                }
+            }
+        }
+    }
+
+    Ok((builder.build(), encoding))
+}
+
+/// Reads the original tape back from the tape of a machine created by
+/// [`to_binary`], as `(position relative to the start, symbol)` pairs.
+#[cfg(test)]
+fn decode_binary(tm: &TuringMachine, symbols: &[TapeEntry]) -> HashMap<isize, TapeEntry> {
+    let bits = block_size(symbols.len());
+    let origin = tm.origin() as isize;
+    let mut decoded = HashMap::new();
+    for (i, entry) in tm.tape().iter().enumerate() {
+        let position = i as isize - origin;
+        let block = position.div_euclid(bits as isize);
+        let code = decoded.entry(block).or_insert(0);
+        *code |= (*entry as usize) << (bits - 1 - position.rem_euclid(bits as isize) as usize);
+    }
+    decoded
+        .into_iter()
+        .map(|(block, code)| (block, symbols[code]))
+        .filter(|(_, symbol)| *symbol != 0)
+        .collect()
+}
+
+#[test]
+fn test_mirror_and_permute_symbols() {
+    use crate::{equiv::outcome, isomorphic::isomorphism};
+    use std::path::Path;
+
+    let tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_3.turing"));
+    let mirrored = mirror(&tm);
+    let plain = outcome(&tm, &[], 1000);
+    let outcome_mirrored = outcome(&mirrored, &[], 1000);
+    assert_eq!(outcome_mirrored.halt, plain.halt);
+    assert_eq!(outcome_mirrored.tape.len(), plain.tape.len());
+    assert!(isomorphism(&tm, &mirrored).unwrap().mirrored);
+    assert_eq!(mirror(&mirrored).to_turing(), tm.to_turing());
+
+    let permutation = parse_permutation("0=1, 1=0").unwrap();
+    let permuted = permute_symbols(&tm, &permutation);
+    assert_eq!(permuted.blank(), 1);
+    let outcome_permuted = outcome(&permuted, &[], 1000);
+    assert_eq!(outcome_permuted.halt, plain.halt);
+    assert_eq!(permutation, [(0, 1), (1, 0)],);
+    assert!(isomorphism(&tm, &permuted).is_some());
+
+    assert!(parse_permutation("0=1").is_err());
+    assert!(parse_permutation("0=1,0=0").is_err());
+    assert!(parse_permutation("1-2").is_err());
+    assert_eq!(parse_permutation(""), Ok(vec![]));
+}
+
+#[test]
+fn test_to_binary() {
+    use std::path::Path;
+
+    for (path, steps) in [
+        ("examples/busy_bever/busy_bever_4.turing", 107),
+        (
+            "examples/busy_bever/busy_bever_2_states_3_symbols.turing",
+            38,
+        ),
+    ] {
+        let mut tm = TuringMachine::new(Path::new(path));
+        let alphabet = symbols(&tm);
+        let mut binary = to_binary(&tm);
+
+        assert!(symbols(&binary).iter().all(|symbol| *symbol <= 1));
+        let instructions = binary.instructions();
+        for (i, instruction) in instructions.iter().enumerate() {
+            assert!(!instructions[..i]
+                .iter()
+                .any(|other| other.state == instruction.state && other.entry == instruction.entry));
+        }
+
+        while tm.step() {}
+        while binary.step() {}
+        assert_eq!(tm.num_steps, steps);
+
+        let mut expected = HashMap::new();
+        for (i, entry) in tm.tape().iter().enumerate() {
+            if *entry != 0 {
+                expected.insert(i as isize - tm.origin() as isize, *entry);
+            }
+        }
+        assert_eq!(decode_binary(&binary, &alphabet), expected, "{path}");
+    }
+}
+
+#[test]
+fn test_to_two_states() {
+    use crate::equiv::outcome;
+    use std::path::Path;
+
+    for path in [
+        "examples/busy_bever/busy_bever_4.turing",
+        "examples/busy_bever/busy_bever_2_states_3_symbols.turing",
+        "examples/minimize/redundant.turing",
+    ] {
+        let tm = TuringMachine::new(Path::new(path));
+        let (two_states, encoding) = to_two_states(&tm).unwrap();
+        assert_eq!(two_states.states().len(), 2);
+
+        let original = outcome(&tm, &[0], 10_000);
+        let converted = outcome(&two_states, &[encoding.start(0)], 1_000_000);
+        assert_eq!(original, converted, "{path}");
+    }
+}
@@ -1,15 +1,49 @@
-use std::{collections::VecDeque, fmt::Display, fs::File, io::Read, path::Path, sync::RwLock, vec};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Display,
+    fs::File,
+    io::Read,
+    path::Path,
+    vec,
+};
 
-type TapeEntry = u8;
-static DEFAULT_ENTRY: TapeEntry = 0;
-static STATES_LOCK: RwLock<Vec<String>> = RwLock::new(vec![]);
+use crate::{
+    color::Colors,
+    fusion::Fusion,
+    log::{self, Level},
+    preprocess::{preprocess, Param},
+    scan,
+};
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub type TapeEntry = u8;
+/// The blank symbol of machines without a `%blank` line, which the
+/// deciders assume, see [`TuringMachine::with_zero_blank`].
+pub static DEFAULT_ENTRY: TapeEntry = 0;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Direction {
     Left,
     Right,
 }
 
+impl Direction {
+    /// Letter used for the direction in machine files.
+    pub fn letter(&self) -> &'static str {
+        match self {
+            Direction::Left => "L",
+            Direction::Right => "R",
+        }
+    }
+
+    /// The opposite direction.
+    pub fn mirrored(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
 impl Display for Direction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.pad(match self {
@@ -19,43 +53,168 @@ impl Display for Direction {
     }
 }
 
+/// What happens when the head tries to move off the edge of a bounded tape.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum EdgeBehavior {
+    /// The head stays on the leftmost cell.
+    Stay,
+    /// The machine stops with an error.
+    Crash,
+    /// The machine halts and rejects its input.
+    Reject,
+}
+
+/// Why a Turing-Machine stopped.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum HaltReason {
+    Halt,
+    Accept,
+    Reject,
+    Crash,
+}
+
+impl HaltReason {
+    /// Name of the halting state as written in machine files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HaltReason::Halt => "Halt",
+            HaltReason::Accept => "Accept",
+            HaltReason::Reject => "Reject",
+            HaltReason::Crash => "Crash",
+        }
+    }
+}
+
+impl Display for HaltReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            HaltReason::Halt => "halted",
+            HaltReason::Accept => "accepted",
+            HaltReason::Reject => "rejected",
+            HaltReason::Crash => "crashed",
+        })
+    }
+}
+
+/// Result of running a machine on an input word.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Verdict {
+    Accept,
+    Reject,
+    StepLimit,
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Verdict::Accept => "accept",
+            Verdict::Reject => "reject",
+            Verdict::StepLimit => "step limit",
+        })
+    }
+}
+
+/// Parses an input word. Without whitespace every character is one
+/// symbol (`"0110"`), otherwise the word is split on whitespace so
+/// multi-digit symbols can be given (`"0 12 3"`).
+pub fn parse_word(word: &str) -> Result<Vec<TapeEntry>, String> {
+    if word.contains(char::is_whitespace) {
+        word.split_whitespace()
+            .map(|symbol| {
+                symbol
+                    .parse()
+                    .map_err(|why| format!("invalid symbol '{symbol}': {why}"))
+            })
+            .collect()
+    } else {
+        word.chars()
+            .map(|symbol| match symbol.to_digit(10) {
+                Some(digit) => Ok(digit as TapeEntry),
+                None => Err(format!("invalid symbol '{symbol}'")),
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
-struct Instruction {
-    state: usize,
-    entry: TapeEntry,
-    new_state: Option<usize>,
-    new_entry: TapeEntry,
-    direction: Direction,
+pub struct Instruction {
+    pub state: usize,
+    pub entry: TapeEntry,
+    pub new_state: Option<usize>,
+    /// How the machine halts if `new_state` is `None`.
+    pub halt: HaltReason,
+    pub new_entry: TapeEntry,
+    pub direction: Direction,
+}
+
+/// An [`Instruction`] together with the state names of its machine.
+struct NamedInstruction<'a> {
+    instruction: &'a Instruction,
+    states: &'a [String],
 }
 
-impl Display for Instruction {
+impl Display for NamedInstruction<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let states = STATES_LOCK.read().unwrap();
+        let instruction = self.instruction;
         f.pad(&format!(
             "({}, {}) -> ({}, {}, {})",
-            states[self.state],
-            self.entry,
-            match self.new_state {
-                Some(state) => &states[state],
-                None => "Halt",
+            self.states[instruction.state],
+            instruction.entry,
+            match instruction.new_state {
+                Some(state) => &self.states[state],
+                None => instruction.halt.name(),
             },
-            self.new_entry,
-            self.direction
+            instruction.new_entry,
+            instruction.direction
         ))
     }
 }
 
+/// Makes `state` a name of the `Halt` state for machines written in other
+/// conventions, like `Z` in the format of bbchallenge.
+fn halt_in(
+    states: &mut Vec<String>,
+    instructions: &mut [Instruction],
+    state: usize,
+) -> Result<(), String> {
+    if state == 0 {
+        return Err(format!("Can't halt in the start state '{}'", states[0]));
+    }
+    if instructions
+        .iter()
+        .any(|instruction| instruction.state == state)
+    {
+        return Err(format!(
+            "Can't halt in state '{}', which has instructions",
+            states[state]
+        ));
+    }
+    states.remove(state);
+    for instruction in instructions {
+        instruction.state -= (instruction.state > state) as usize;
+        instruction.new_state = match instruction.new_state {
+            Some(new_state) if new_state == state => {
+                instruction.halt = HaltReason::Halt;
+                None
+            }
+            Some(new_state) if new_state > state => Some(new_state - 1),
+            new_state => new_state,
+        };
+    }
+    Ok(())
+}
+
 enum InstructionParseError {
     EmptyLine,
     ParseError { why: String },
 }
 
-impl TryFrom<&str> for Instruction {
-    type Error = InstructionParseError;
-
-    fn try_from(line: &str) -> Result<Self, Self::Error> {
-        let mut states = STATES_LOCK.write().unwrap();
-        if line.is_empty() {
+impl Instruction {
+    /// Parses one line of a machine file. State names not seen before are
+    /// appended to `states`.
+    fn parse(line: &str, states: &mut Vec<String>) -> Result<Self, InstructionParseError> {
+        let line = strip_comment(line).0;
+        if line.trim().is_empty() {
             return Err(InstructionParseError::EmptyLine);
         }
 
@@ -80,7 +239,12 @@ impl TryFrom<&str> for Instruction {
         };
 
         let target_state = line[3].to_string();
-        let target_state = if target_state == "Halt" {
+        let halt = match target_state.as_str() {
+            "Accept" => HaltReason::Accept,
+            "Reject" => HaltReason::Reject,
+            _ => HaltReason::Halt,
+        };
+        let target_state = if ["Halt", "Accept", "Reject"].contains(&target_state.as_str()) {
             None
         } else {
             match states.iter().position(|state| state == &target_state) {
@@ -115,152 +279,1005 @@ impl TryFrom<&str> for Instruction {
         } else if line[5] == "R" {
             Direction::Right
         } else {
-            panic!("couldn't parse direction '{}'", line[5])
+            return Err(InstructionParseError::ParseError {
+                why: format!("couldn't parse direction '{}'", line[5]),
+            });
         };
 
         Ok(Instruction {
             state: source_state,
             entry: source_entry,
             new_state: target_state,
+            halt,
             new_entry: target_entry,
             direction,
         })
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Splits the comment starting with `//` off a line of a machine file.
+pub fn strip_comment(line: &str) -> (&str, Option<&str>) {
+    match line.split_once("//") {
+        Some((code, comment)) => (code, Some(comment)),
+        None => (line, None),
+    }
+}
+
+/// Keys of the metadata headers of machine files, like `#author Tibor Radó`,
+/// which tell where a machine comes from.
+pub const METADATA_KEYS: [&str; 4] = ["name", "author", "description", "source-url"];
+
+/// Reads a metadata header like `#name Busy Beaver`, giving the key and the
+/// value, or `None` for other lines. The value is the rest of the line, so
+/// it may hold `//` as URLs do.
+pub fn parse_metadata(line: &str) -> Option<(String, String)> {
+    let header = line.trim_start().strip_prefix('#')?;
+    let (key, value) = header
+        .split_once(char::is_whitespace)
+        .unwrap_or((header, ""));
+    METADATA_KEYS
+        .contains(&key)
+        .then(|| (key.to_string(), value.trim().to_string()))
+}
+
+/// Parses one line of a machine file, giving `None` for a line without an
+/// instruction. State names not seen before are appended to `states`.
+pub fn parse_instruction(
+    line: &str,
+    states: &mut Vec<String>,
+) -> Result<Option<Instruction>, String> {
+    match Instruction::parse(line, states) {
+        Ok(instruction) => Ok(Some(instruction)),
+        Err(InstructionParseError::EmptyLine) => Ok(None),
+        Err(InstructionParseError::ParseError { why }) => Err(why),
+    }
+}
+
+/// Parses the symbol and the weights of a `%weights A 0 1 3` line.
+fn parse_weights(entry: &str, numbers: &[&str]) -> Result<(TapeEntry, Vec<u32>), String> {
+    let entry = entry
+        .parse()
+        .map_err(|why| format!("invalid symbol '{entry}': {why}"))?;
+    let weights = numbers
+        .iter()
+        .map(|number| match number.parse() {
+            Ok(weight) if weight > 0 => Ok(weight),
+            _ => Err(format!(
+                "invalid weight '{number}', expected a positive integer"
+            )),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((entry, weights))
+}
+
+/// Reads the aliases of a `%glyphs` line, like `0=· 1=█`, into `glyphs`.
+fn parse_glyphs(aliases: &[&str], glyphs: &mut BTreeMap<TapeEntry, String>) -> Result<(), String> {
+    for alias in aliases {
+        let (symbol, glyph) = match alias.split_once('=') {
+            Some((symbol, glyph)) if !glyph.is_empty() => (symbol, glyph),
+            _ => return Err(format!("expected 'symbol=glyph' instead of '{alias}'")),
+        };
+        let symbol = symbol
+            .parse()
+            .map_err(|why| format!("invalid symbol '{symbol}': {why}"))?;
+        glyphs.insert(symbol, glyph.to_string());
+    }
+    Ok(())
+}
+
+/// Reads a machine from the raw contents of a machine file. Unlike
+/// [`TuringMachine::new`] this never panics, whatever the input, and the
+/// machine it gives has at least one instruction, so it can be fuzzed.
+pub fn parse_machine(bytes: &[u8]) -> Result<TuringMachine, String> {
+    let content = std::str::from_utf8(bytes)
+        .map_err(|why| format!("invalid UTF-8 after {} bytes", why.valid_up_to()))?;
+    let tm = TuringMachine::parse(content)?;
+    if tm.instructions.is_empty() {
+        return Err("the machine has no instructions".to_string());
+    }
+    Ok(tm)
+}
+
+/// Marks cells of a [`Table`] without an instruction.
+const NO_INSTRUCTION: u32 = u32::MAX;
+
+/// The instructions laid out for [`TuringMachine::step`], as one array per
+/// field indexed by `state * columns + entry`. Looking up the next
+/// instruction is a single index instead of a search, and the arrays of a
+/// small machine fit in a few cache lines.
+///
+/// After the rows of the states comes a row without instructions for
+/// halted machines, so the state of a machine is always a valid row. The
+/// last column is empty as well and taken for every symbol no instruction
+/// reads. That way any state and symbol can be looked up without checking
+/// either.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Table {
+    /// One more than the largest symbol read by an instruction.
+    columns: usize,
+    /// The row of halted machines.
+    halted: usize,
+    /// Index into the instructions, [`NO_INSTRUCTION`] where there is none.
+    instruction: Box<[u32]>,
+    new_state: Box<[u32]>,
+    new_entry: Box<[TapeEntry]>,
+    direction: Box<[Direction]>,
+    /// How the machine halts, `None` for instructions that keep it running.
+    halt: Box<[Option<HaltReason>]>,
+}
+
+impl Table {
+    /// Lays out `instructions` for `states` states. Where several
+    /// instructions match, the first one is taken.
+    fn new(states: usize, instructions: &[Instruction]) -> Self {
+        let columns = instructions
+            .iter()
+            .map(|instruction| instruction.entry as usize + 2)
+            .max()
+            .unwrap_or(1);
+        // A machine without states still starts in state 0, which has no
+        // instructions rather than being halted.
+        let halted = states.max(1);
+        let narrow = |value: usize| u32::try_from(value).expect("too many instructions");
+        let cells = (halted + 1) * columns;
+        let mut table = Table {
+            columns,
+            halted,
+            instruction: vec![NO_INSTRUCTION; cells].into(),
+            new_state: vec![narrow(halted); cells].into(),
+            new_entry: vec![DEFAULT_ENTRY; cells].into(),
+            direction: vec![Direction::Right; cells].into(),
+            halt: vec![None; cells].into(),
+        };
+        for (index, instruction) in instructions.iter().enumerate() {
+            let new_state = instruction.new_state.unwrap_or(halted);
+            assert!(
+                instruction.state < halted && new_state <= halted,
+                "instruction {index} refers to a state out of range"
+            );
+            let cell = instruction.state * columns + instruction.entry as usize;
+            if table.instruction[cell] == NO_INSTRUCTION {
+                table.instruction[cell] = narrow(index);
+                table.new_state[cell] = narrow(new_state);
+                table.new_entry[cell] = instruction.new_entry;
+                table.direction[cell] = instruction.direction;
+                table.halt[cell] = instruction.new_state.is_none().then_some(instruction.halt);
+            }
+        }
+        table
+    }
+
+    /// Index into the arrays of the cell for `state` and `entry`, which is
+    /// always in bounds for states up to [`Self::halted`].
+    #[inline]
+    fn cell(&self, state: usize, entry: TapeEntry) -> usize {
+        let cell = state * self.columns + (entry as usize).min(self.columns - 1);
+        debug_assert!(cell < self.instruction.len());
+        cell
+    }
+
+    fn memory(&self) -> usize {
+        let cell = 2 * std::mem::size_of::<u32>()
+            + std::mem::size_of::<TapeEntry>()
+            + std::mem::size_of::<Direction>()
+            + std::mem::size_of::<Option<HaltReason>>();
+        self.instruction.len() * cell
+    }
+}
+
+/// States of a machine with an oracle: entering `query` asks the oracle
+/// about the query word, and the machine goes on in `yes` or `no`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct OracleStates {
+    pub query: usize,
+    pub yes: usize,
+    pub no: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TuringMachine {
-    state: Option<usize>,
+    states: Box<[String]>,
+    /// Index of the current state, [`Table::halted`] once stopped.
+    state: usize,
     instructions: Box<[Instruction]>,
+    table: Table,
     tape: VecDeque<TapeEntry>,
     pos: usize,
     offset: usize,
+    left_edge: Option<EdgeBehavior>,
+    bound: Option<(usize, EdgeBehavior)>,
+    reject_undefined: bool,
+    last_instruction: Option<usize>,
+    /// How symbols are shown on the tape, see [`Self::set_glyphs`].
+    glyphs: BTreeMap<TapeEntry, String>,
+    /// The symbol of the cells never written, see [`Self::set_blank`].
+    blank: TapeEntry,
+    /// States of an alternating machine that are universal.
+    universal: BTreeSet<usize>,
+    /// Weights of the instructions matching a state and a symbol, for a
+    /// probabilistic machine.
+    weights: BTreeMap<(usize, TapeEntry), Vec<u32>>,
+    /// The states asking an oracle and taking its answer, see
+    /// [`Self::set_oracle`].
+    oracle: Option<OracleStates>,
+    /// Metadata headers in the order of the file, see [`METADATA_KEYS`].
+    metadata: Vec<(String, String)>,
 
     pub num_steps: u128,
+    pub edge_hits: u128,
+    pub halt_reason: Option<HaltReason>,
 }
 
 #[allow(dead_code)]
 impl TuringMachine {
+    /// Reads the machine file at `path`, or from stdin if `path` is `-`.
     pub fn new(path: &Path) -> Self {
-        let mut instructions = vec![];
+        TuringMachine::load(path, &[])
+    }
+
+    /// Reads the machine file at `path` like [`Self::new`], with values for
+    /// the parameters it declares.
+    pub fn load(path: &Path, params: &[Param]) -> Self {
+        match TuringMachine::read(path, params) {
+            Ok(tm) => tm,
+            Err(why) => panic!("{}", why),
+        }
+    }
 
-        let mut file = match File::open(path) {
-            Ok(file) => file,
-            Err(why) => panic!("couldn't open {}: {}", path.display(), why),
+    /// Reads the machine file at `path` like [`Self::load`], but gives an
+    /// error instead of panicking.
+    pub fn read(path: &Path, params: &[Param]) -> Result<Self, String> {
+        let mut file: Box<dyn Read> = if path == Path::new("-") {
+            Box::new(std::io::stdin())
+        } else {
+            match File::open(path) {
+                Ok(file) => Box::new(file),
+                Err(why) => return Err(format!("couldn't open {}: {}", path.display(), why)),
+            }
         };
 
         let mut content = String::new();
         match file.read_to_string(&mut content) {
-            Err(why) => panic!("Couldn't read {}: {}", path.display(), why),
-            Ok(_size) => {
-                for line in content.lines() {
-                    match Instruction::try_from(line) {
-                        Ok(instruction) => instructions.push(instruction),
-                        Err(InstructionParseError::EmptyLine) => {}
-                        Err(InstructionParseError::ParseError { why }) => {
-                            panic!("Can't read instruction from line '{}': {}", &line, &why)
-                        }
+            Err(why) => Err(format!("Couldn't read {}: {}", path.display(), why)),
+            Ok(_size) => preprocess(&content, path, params).and_then(|c| TuringMachine::parse(&c)),
+        }
+    }
+
+    /// Reads a machine from the contents of a machine file, after
+    /// [`preprocess`] expanded its directives except `%glyphs`,
+    /// `%blank`, `%halt`, `%halt-writing`, `%universal`, `%weights` and
+    /// `%oracle`.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut states = vec![];
+        let mut instructions = vec![];
+        let mut glyphs = BTreeMap::new();
+        let mut universal = vec![];
+        let mut weights = vec![];
+        let mut oracle = None;
+        let mut blank = DEFAULT_ENTRY;
+        let mut halting = vec![];
+        let mut halt_writing = vec![];
+        let mut metadata = vec![];
+        for line in content.lines() {
+            if let Some(header) = parse_metadata(line) {
+                metadata.push(header);
+                continue;
+            }
+            let words: Vec<&str> = strip_comment(line).0.split_whitespace().collect();
+            match words.as_slice() {
+                ["%glyphs", aliases @ ..] => {
+                    parse_glyphs(aliases, &mut glyphs).map_err(|why| {
+                        format!("Can't read glyphs from line '{}': {}", &line, &why)
+                    })?;
+                    continue;
+                }
+                ["%blank", entry] => {
+                    blank = entry.parse().map_err(|why| {
+                        format!("Can't read blank from line '{}': {}", &line, &why)
+                    })?;
+                    continue;
+                }
+                ["%halt", names @ ..] => {
+                    halting.extend(names.iter().map(|name| name.to_string()));
+                    continue;
+                }
+                ["%halt-writing", entries @ ..] => {
+                    for entry in entries {
+                        halt_writing.push(entry.parse::<TapeEntry>().map_err(|why| {
+                            format!("Can't read halting symbol from line '{}': {}", &line, &why)
+                        })?);
                     }
+                    continue;
                 }
-
-                TuringMachine {
-                    state: Some(0),
-                    instructions: instructions.into(),
-                    tape: vec![DEFAULT_ENTRY].into(),
-                    pos: 0,
-                    offset: 0,
-                    num_steps: 0,
+                ["%universal", names @ ..] => {
+                    universal.extend(names.iter().map(|name| name.to_string()));
+                    continue;
                 }
+                ["%weights", state, entry, numbers @ ..] => {
+                    let parsed = parse_weights(entry, numbers).map_err(|why| {
+                        format!("Can't read weights from line '{}': {}", &line, &why)
+                    })?;
+                    weights.push((state.to_string(), parsed));
+                    continue;
+                }
+                ["%oracle", names @ ..] => {
+                    let [query, yes, no] = names else {
+                        return Err(format!(
+                            "Can't read oracle from line '{}': expected '%oracle QUERY YES NO'",
+                            &line
+                        ));
+                    };
+                    oracle = Some([query.to_string(), yes.to_string(), no.to_string()]);
+                    continue;
+                }
+                _ => {}
+            }
+            match parse_instruction(line, &mut states) {
+                Ok(Some(instruction)) => instructions.push(instruction),
+                Ok(None) => {}
+                Err(why) => {
+                    return Err(format!(
+                        "Can't read instruction from line '{}': {}",
+                        &line, &why
+                    ))
+                }
+            }
+        }
+
+        for name in &halting {
+            if let Some(state) = states.iter().position(|state| state == name) {
+                halt_in(&mut states, &mut instructions, state)?;
+            }
+        }
+        for instruction in &mut instructions {
+            if halt_writing.contains(&instruction.new_entry) {
+                instruction.new_state = None;
+                instruction.halt = HaltReason::Halt;
+            }
+        }
+
+        let position = |name: &String| states.iter().position(|state| state == name);
+        let universal = universal
+            .iter()
+            .map(|name| match position(name) {
+                Some(state) => Ok(state),
+                None => Err(format!("Can't mark unknown state '{}' as universal", name)),
+            })
+            .collect::<Result<_, _>>()?;
+        let oracle = match oracle {
+            Some(names) => {
+                let states = names
+                    .iter()
+                    .map(|name| match position(name) {
+                        Some(state) => Ok(state),
+                        None => Err(format!("Can't use unknown state '{}' for the oracle", name)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Some(OracleStates {
+                    query: states[0],
+                    yes: states[1],
+                    no: states[2],
+                })
+            }
+            None => None,
+        };
+        let mut tm = TuringMachine::from_instructions(states, instructions);
+        tm.set_glyphs(glyphs);
+        tm.set_blank(blank);
+        tm.set_universal(universal);
+        tm.set_oracle(oracle);
+        tm.set_metadata(metadata);
+        for (name, (entry, weights)) in weights {
+            let state = tm.states.iter().position(|state| *state == name);
+            let state = state.ok_or_else(|| format!("Can't weigh unknown state '{}'", name))?;
+            let matching = tm
+                .instructions
+                .iter()
+                .filter(|instruction| instruction.state == state && instruction.entry == entry)
+                .count();
+            if matching != weights.len() {
+                return Err(format!(
+                    "Can't weigh {} instructions of '{} {}' with {} weights",
+                    matching,
+                    name,
+                    entry,
+                    weights.len()
+                ));
+            }
+            tm.weights.insert((state, entry), weights);
+        }
+        Ok(tm)
+    }
+
+    /// Creates a machine from its state names and instructions. The first
+    /// state is the start state.
+    pub fn from_instructions(states: Vec<String>, instructions: Vec<Instruction>) -> Self {
+        let table = Table::new(states.len(), &instructions);
+        TuringMachine {
+            states: states.into(),
+            state: 0,
+            instructions: instructions.into(),
+            table,
+            tape: vec![DEFAULT_ENTRY].into(),
+            pos: 0,
+            offset: 0,
+            left_edge: None,
+            bound: None,
+            reject_undefined: false,
+            last_instruction: None,
+            glyphs: BTreeMap::new(),
+            blank: DEFAULT_ENTRY,
+            universal: BTreeSet::new(),
+            weights: BTreeMap::new(),
+            oracle: None,
+            metadata: vec![],
+            num_steps: 0,
+            edge_hits: 0,
+            halt_reason: None,
+        }
+    }
+
+    /// Writes the instructions in the format read by [`Self::new`], grouped
+    /// by state so the start state comes first. Reading the result gives the
+    /// same machine, only with the states maybe numbered differently, and
+    /// writing that again gives the same text.
+    pub fn to_turing(&self) -> String {
+        let name = |state: Option<usize>, halt: HaltReason| match state {
+            Some(state) => self.states[state].as_str(),
+            None => halt.name(),
+        };
+        let width = |column: &dyn Fn(&Instruction) -> usize| {
+            self.instructions.iter().map(column).max().unwrap_or(0)
+        };
+        let source_width = width(&|instruction| self.states[instruction.state].len());
+        let entry_width = width(&|instruction| instruction.entry.to_string().len());
+        let target_width =
+            width(&|instruction| name(instruction.new_state, instruction.halt).len());
+        let new_entry_width = width(&|instruction| instruction.new_entry.to_string().len());
+
+        // Reading the file numbers the states in the order they are first
+        // mentioned, so the next state written is the one mentioned first.
+        // Otherwise writing the machine read back could change the order.
+        let has_instructions = |state: usize| self.instructions.iter().any(|i| i.state == state);
+        let mut mentioned: Vec<usize> = vec![];
+        let mut written = vec![false; self.states.len()];
+        let mut instructions: Vec<&Instruction> = vec![];
+        loop {
+            let next = mentioned
+                .iter()
+                .copied()
+                .chain(0..self.states.len())
+                .find(|state| !written[*state] && has_instructions(*state));
+            let Some(state) = next else {
+                break;
+            };
+            written[state] = true;
+            for instruction in self.instructions.iter().filter(|i| i.state == state) {
+                mentioned.push(state);
+                mentioned.extend(instruction.new_state);
+                instructions.push(instruction);
             }
         }
+
+        let mut lines: Vec<String> = instructions
+            .iter()
+            .map(|instruction| {
+                format!(
+                    "{:source_width$} {:entry_width$} -> {:target_width$} {:new_entry_width$} {}",
+                    self.states[instruction.state],
+                    instruction.entry,
+                    name(instruction.new_state, instruction.halt),
+                    instruction.new_entry,
+                    instruction.direction.letter()
+                )
+            })
+            .collect();
+        if self.blank != DEFAULT_ENTRY {
+            lines.insert(0, format!("%blank {}", self.blank));
+        }
+        for (key, value) in self.metadata.iter().rev() {
+            lines.insert(0, format!("#{key} {value}").trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+
+    /// Writes the state graph as a Mermaid state diagram, e.g. for Markdown
+    /// on GitHub. Halting goes to the end state, the other ways to halt to a
+    /// state of their own. Edges are labeled `read/write,move`.
+    pub fn to_mermaid(&self) -> String {
+        let mut lines = vec!["stateDiagram-v2".to_string()];
+        // Names can hold anything, so states get ids and the name as label.
+        for (state, name) in self.states.iter().enumerate() {
+            lines.push(format!(
+                "    state \"{}\" as s{state}",
+                name.replace('"', "#quot;")
+            ));
+        }
+        if !self.states.is_empty() {
+            lines.push("    [*] --> s0".to_string());
+        }
+        let mut edges: Vec<(String, String, Vec<String>)> = vec![];
+        for instruction in &self.instructions {
+            let target = match (instruction.new_state, instruction.halt) {
+                (Some(state), _) => format!("s{state}"),
+                (None, HaltReason::Halt) => "[*]".to_string(),
+                (None, reason) => reason.name().to_string(),
+            };
+            let label = format!(
+                "{}/{},{}",
+                self.glyph(instruction.entry),
+                self.glyph(instruction.new_entry),
+                instruction.direction.letter()
+            );
+            let source = format!("s{}", instruction.state);
+            match edges
+                .iter_mut()
+                .find(|(s, t, _)| *s == source && *t == target)
+            {
+                Some((_, _, labels)) => labels.push(label),
+                None => edges.push((source, target, vec![label])),
+            }
+        }
+        for (source, target, labels) in edges {
+            lines.push(format!("    {source} --> {target} : {}", labels.join("; ")));
+        }
+        lines.join("\n")
     }
 
     pub fn step(&mut self) -> bool {
-        match &self.state {
-            None => false,
-            Some(state) => {
-                self.num_steps += 1;
-                for instruction in self.instructions.iter() {
-                    if state == &instruction.state && self.tape[self.pos] == instruction.entry {
-                        self.state = instruction.new_state;
-                        self.tape[self.pos] = instruction.new_entry;
-
-                        match instruction.direction {
-                            Direction::Left => {
-                                if self.pos == 0 {
-                                    self.extend_left();
-                                }
-                                self.pos -= 1;
-                            }
-                            Direction::Right => {
-                                self.pos += 1;
-                                if self.pos == self.tape.len() {
-                                    self.extend_right();
-                                }
-                            }
-                        }
-                        return true;
+        let table = &self.table;
+        let cell = table.cell(self.state, self.tape[self.pos]);
+        // SAFETY: `Table::cell` is in bounds for every state up to the halted
+        // one, and `Table::new` checked that instructions only lead to those.
+        let instruction = unsafe { *table.instruction.get_unchecked(cell) };
+        if instruction == NO_INSTRUCTION {
+            return self.undefined();
+        }
+
+        self.num_steps += 1;
+        self.last_instruction = Some(instruction as usize);
+        // SAFETY: as above, all arrays have the same length.
+        unsafe {
+            self.state = *table.new_state.get_unchecked(cell) as usize;
+            self.halt_reason = *table.halt.get_unchecked(cell);
+            self.tape[self.pos] = *table.new_entry.get_unchecked(cell);
+        }
+        debug_assert!(self.state <= self.table.halted);
+
+        // SAFETY: as above.
+        self.move_head(unsafe { *self.table.direction.get_unchecked(cell) });
+        true
+    }
+
+    /// Indices of the instructions matching the current state and the
+    /// symbol under the head. A machine with several of them is
+    /// non-deterministic, and [`Self::step`] always takes the first.
+    pub fn choices(&self) -> Vec<usize> {
+        let Some(state) = self.state() else {
+            return vec![];
+        };
+        let entry = self.tape[self.pos];
+        (0..self.instructions.len())
+            .filter(|index| {
+                let instruction = &self.instructions[*index];
+                instruction.state == state && instruction.entry == entry
+            })
+            .collect()
+    }
+
+    /// Takes a step with the instruction at `index`, one of
+    /// [`Self::choices`].
+    pub fn step_with(&mut self, index: usize) {
+        let instruction = &self.instructions[index];
+        debug_assert!(self.state() == Some(instruction.state));
+        let direction = instruction.direction;
+        self.num_steps += 1;
+        self.last_instruction = Some(index);
+        self.tape[self.pos] = instruction.new_entry;
+        self.halt_reason = instruction.new_state.is_none().then_some(instruction.halt);
+        self.state = instruction.new_state.unwrap_or(self.table.halted);
+        self.move_head(direction);
+    }
+
+    #[inline(always)]
+    fn move_head(&mut self, direction: Direction) {
+        match direction {
+            Direction::Left => {
+                if self.pos == 0 {
+                    let edge = match self.bound {
+                        Some((_, edge)) => Some(edge),
+                        None => self.left_edge,
+                    };
+                    match edge {
+                        None => self.extend_left(),
+                        Some(edge) => return self.hit_edge(edge),
                     }
                 }
-                let states = STATES_LOCK.read();
-                match states {
-                    Ok(states) => {
-                        dbg!(&states);
-                    }
-                    Err(why) => {
-                        println!("Can't get read-lock for states: {}", why);
+                self.pos -= 1;
+            }
+            Direction::Right => {
+                if let Some((cells, edge)) = self.bound {
+                    if self.pos + 1 >= cells {
+                        return self.hit_edge(edge);
                     }
-                };
-                dbg!(self);
-                panic!("No Instruction matched Turing-Machine");
+                }
+                self.pos += 1;
+                if self.pos == self.tape.len() {
+                    self.extend_right();
+                }
             }
         }
     }
 
+    /// Takes up to [`Fusion::steps`] steps at once with a transition of
+    /// `fusion`, or a single one where that doesn't work. Returns whether
+    /// any step was taken, like [`Self::step`].
+    ///
+    /// Near the ends of the tape, where the window doesn't fit, and on
+    /// bounded tapes the machine takes single steps, so the tape is the same
+    /// as without fusion.
+    pub fn step_fused(&mut self, fusion: &mut Fusion) -> bool {
+        // One more cell on either side keeps the head on the tape after the
+        // transition moved it out of the window.
+        let radius = fusion.radius();
+        if self.is_halted()
+            || self.left_edge.is_some()
+            || self.bound.is_some()
+            || self.pos <= radius
+            || self.pos + radius + 1 >= self.tape.len()
+        {
+            return self.step();
+        }
+        let window = &mut self.tape.make_contiguous()[self.pos - radius..=self.pos + radius];
+        let fused = fusion.transition(self.state, window);
+        if fused.steps == 0 {
+            return self.step();
+        }
+
+        window.copy_from_slice(&fused.window);
+        self.pos = self.pos.wrapping_add_signed(fused.moved);
+        self.state = fused.state.unwrap_or(self.table.halted);
+        self.halt_reason = fused.halt_reason;
+        self.num_steps += fused.steps as u128;
+        self.last_instruction = fused.last_instruction;
+        true
+    }
+
+    /// Handles a step without an instruction, which halted machines never
+    /// have. The machine rejects with [`Self::set_reject_undefined`] and
+    /// crashes otherwise, which `run` doesn't count as halting.
+    #[cold]
+    fn undefined(&mut self) -> bool {
+        if self.state == self.table.halted {
+            return false;
+        }
+        if self.reject_undefined {
+            self.stop(HaltReason::Reject);
+            return false;
+        }
+        log::event(
+            Level::Error,
+            "no instruction matched",
+            &[
+                (
+                    "state",
+                    self.states
+                        .get(self.state)
+                        .map_or("", String::as_str)
+                        .into(),
+                ),
+                ("symbol", self.tape[self.pos].into()),
+                ("steps", self.num_steps.into()),
+            ],
+        );
+        self.stop(HaltReason::Crash);
+        false
+    }
+
+    /// Bounds the tape on the left at the starting cell, so the tape is
+    /// semi-infinite. `None` restores the default two-way infinite tape.
+    pub fn set_left_edge(&mut self, left_edge: Option<EdgeBehavior>) {
+        self.left_edge = left_edge;
+    }
+
+    /// Restricts the tape to `cells` cells starting at the head position, as
+    /// in a linear bounded automaton. Trying to leave this region on either
+    /// side is handled according to `edge` and takes precedence over
+    /// [`Self::set_left_edge`].
+    pub fn set_bound(&mut self, bound: Option<(usize, EdgeBehavior)>) {
+        self.bound = bound;
+    }
+
+    /// Treats a missing transition as rejecting the input instead of
+    /// aborting the simulation, as is usual for language recognizers.
+    pub fn set_reject_undefined(&mut self, reject_undefined: bool) {
+        self.reject_undefined = reject_undefined;
+    }
+
+    /// Shows the symbols in `glyphs` as the glyph given for them when
+    /// printing the tape, and all others as their number.
+    pub fn set_glyphs(&mut self, glyphs: BTreeMap<TapeEntry, String>) {
+        self.glyphs = glyphs;
+    }
+
+    pub fn glyphs(&self) -> &BTreeMap<TapeEntry, String> {
+        &self.glyphs
+    }
+
+    /// Sets the metadata headers, pairs of a key of [`METADATA_KEYS`] and
+    /// a value, which are written first by [`Self::to_turing`].
+    pub fn set_metadata(&mut self, metadata: Vec<(String, String)>) {
+        self.metadata = metadata;
+    }
+
+    pub fn metadata(&self) -> &[(String, String)] {
+        &self.metadata
+    }
+
+    /// Fills the cells never written with `blank` instead of
+    /// [`DEFAULT_ENTRY`]. Starts over on a blank tape, so an input has to be
+    /// written afterwards.
+    pub fn set_blank(&mut self, blank: TapeEntry) {
+        self.blank = blank;
+        self.tape = vec![blank].into();
+        self.pos = 0;
+        self.offset = 0;
+    }
+
+    pub fn blank(&self) -> TapeEntry {
+        self.blank
+    }
+
+    /// The same machine with its blank exchanged for [`DEFAULT_ENTRY`] in
+    /// every instruction, which halts after the same steps. The deciders
+    /// take machines like that.
+    pub fn with_zero_blank(&self) -> TuringMachine {
+        if self.blank == DEFAULT_ENTRY {
+            return self.clone();
+        }
+        let swap = |entry: TapeEntry| match entry {
+            _ if entry == self.blank => DEFAULT_ENTRY,
+            _ if entry == DEFAULT_ENTRY => self.blank,
+            _ => entry,
+        };
+        let instructions = self
+            .instructions
+            .iter()
+            .map(|instruction| Instruction {
+                entry: swap(instruction.entry),
+                new_entry: swap(instruction.new_entry),
+                ..instruction.clone()
+            })
+            .collect();
+        TuringMachine::from_instructions(self.states.to_vec(), instructions)
+    }
+
+    /// Marks `states` as universal, so an alternating machine accepts from
+    /// them if all their branches accept. The other states are existential
+    /// and accept if any branch accepts.
+    pub fn set_universal(&mut self, states: BTreeSet<usize>) {
+        self.universal = states;
+    }
+
+    pub fn is_universal(&self, state: usize) -> bool {
+        self.universal.contains(&state)
+    }
+
+    /// Makes the machine ask an oracle whenever it enters
+    /// `oracle.query`, see [`crate::oracle::run`].
+    pub fn set_oracle(&mut self, oracle: Option<OracleStates>) {
+        self.oracle = oracle;
+    }
+
+    pub fn oracle(&self) -> Option<OracleStates> {
+        self.oracle
+    }
+
+    /// Renumbers the states so that state `order[i]` becomes state `i`, and
+    /// lays out the transition table again in that order. Instructions,
+    /// `%universal`, `%weights` and `%oracle` keep referring to the same
+    /// states, and the machine stays in its current state.
+    pub fn reorder_states(&mut self, order: &[usize]) {
+        let mut renamed = vec![usize::MAX; self.states.len()];
+        for (index, state) in order.iter().enumerate() {
+            renamed[*state] = index;
+        }
+        assert!(
+            order.len() == self.states.len() && !renamed.contains(&usize::MAX),
+            "the order isn't a permutation of the states"
+        );
+        self.states = order
+            .iter()
+            .map(|state| self.states[*state].clone())
+            .collect();
+        for instruction in self.instructions.iter_mut() {
+            instruction.state = renamed[instruction.state];
+            instruction.new_state = instruction.new_state.map(|state| renamed[state]);
+        }
+        // The row of halted machines stays the same, and so does state 0 of
+        // a machine without states.
+        self.table = Table::new(self.states.len(), &self.instructions);
+        if let Some(state) = renamed.get(self.state) {
+            self.state = *state;
+        }
+        self.universal = self.universal.iter().map(|state| renamed[*state]).collect();
+        self.weights = std::mem::take(&mut self.weights)
+            .into_iter()
+            .map(|((state, entry), weights)| ((renamed[state], entry), weights))
+            .collect();
+        self.oracle = self.oracle.map(|oracle| OracleStates {
+            query: renamed[oracle.query],
+            yes: renamed[oracle.yes],
+            no: renamed[oracle.no],
+        });
+    }
+
+    /// Moves the machine to `state` without taking a step, as an oracle
+    /// answering a query does. Panics unless `state` is one of the states,
+    /// which [`Self::step`] relies on.
+    pub fn set_state(&mut self, state: usize) {
+        assert!(state < self.states.len(), "no state {state}");
+        self.state = state;
+    }
+
+    /// Weights of the instructions in [`Self::choices`] for the current
+    /// configuration, in the same order. Without a `%weights` line they
+    /// all weigh the same.
+    pub fn weights(&self) -> Vec<u32> {
+        let weights = self
+            .state()
+            .and_then(|state| self.weights.get(&(state, self.tape[self.pos])));
+        match weights {
+            Some(weights) => weights.clone(),
+            None => vec![1; self.choices().len()],
+        }
+    }
+
+    /// How `entry` is shown on the tape.
+    pub fn glyph(&self, entry: TapeEntry) -> String {
+        match self.glyphs.get(&entry) {
+            Some(glyph) => glyph.clone(),
+            None => entry.to_string(),
+        }
+    }
+
+    /// Writes `input` onto the tape, starting at the head.
+    pub fn set_input(&mut self, input: &[TapeEntry]) {
+        self.tape = input.iter().copied().collect();
+        if self.tape.is_empty() {
+            self.tape.push_back(self.blank);
+        }
+        self.pos = 0;
+        self.offset = 0;
+    }
+
+    /// Replaces the tape by `cells`, with the head on cell `head` and the
+    /// starting cell at `origin`. Bounds set with [`Self::set_left_edge`] and
+    /// [`Self::set_bound`] start at the first of the cells.
+    pub fn set_tape(&mut self, cells: &[TapeEntry], head: usize, origin: usize) {
+        self.set_input(cells);
+        assert!(head < self.tape.len() && origin < self.tape.len());
+        self.pos = head;
+        self.offset = origin;
+    }
+
+    /// The cells visited so far. The tape never shrinks while running, so
+    /// its length is also the most cells the run needed.
+    pub fn tape(&self) -> &VecDeque<TapeEntry> {
+        &self.tape
+    }
+
+    /// Approximate number of bytes taken by the tape and the instructions.
+    pub fn memory(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.tape.capacity() * std::mem::size_of::<TapeEntry>()
+            + self.instructions.len() * std::mem::size_of::<Instruction>()
+            + self.table.memory()
+            + self.states.iter().map(String::capacity).sum::<usize>()
+    }
+
+    /// Index of the head on [`Self::tape`].
+    pub fn head(&self) -> usize {
+        self.pos
+    }
+
+    /// Index of the starting cell on [`Self::tape`].
+    pub fn origin(&self) -> usize {
+        self.offset
+    }
+
+    pub fn states(&self) -> &[String] {
+        &self.states
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Index of the current state, `None` once the machine stopped.
+    pub fn state(&self) -> Option<usize> {
+        (self.state != self.table.halted).then_some(self.state)
+    }
+
+    /// Index into [`Self::instructions`] of the instruction executed by the
+    /// last step, if any.
+    pub fn last_instruction(&self) -> Option<usize> {
+        self.last_instruction
+    }
+
+    /// Returns whether the machine has stopped, for whatever reason.
+    pub fn is_halted(&self) -> bool {
+        self.state == self.table.halted
+    }
+
+    /// Runs until the machine halts or `max_steps` steps have been taken.
+    pub fn run_word(&mut self, max_steps: u128) -> Verdict {
+        while self.num_steps < max_steps && self.step() {}
+        match self.halt_reason {
+            None => Verdict::StepLimit,
+            Some(HaltReason::Halt | HaltReason::Accept) => Verdict::Accept,
+            Some(HaltReason::Reject | HaltReason::Crash) => Verdict::Reject,
+        }
+    }
+
+    /// Keeps the head on the edge of a bounded tape. A machine that halted
+    /// with this move keeps its halt reason.
+    fn hit_edge(&mut self, edge: EdgeBehavior) {
+        self.edge_hits += 1;
+        if self.is_halted() {
+            return;
+        }
+        match edge {
+            EdgeBehavior::Stay => {}
+            EdgeBehavior::Crash => self.stop(HaltReason::Crash),
+            EdgeBehavior::Reject => self.stop(HaltReason::Reject),
+        }
+    }
+
+    fn stop(&mut self, reason: HaltReason) {
+        self.state = self.table.halted;
+        self.halt_reason = Some(reason);
+    }
+
     fn extend_left(&mut self) {
-        self.tape.push_front(DEFAULT_ENTRY);
+        self.tape.push_front(self.blank);
         self.pos += 1;
         self.offset += 1;
     }
 
     fn extend_right(&mut self) {
-        self.tape.push_back(DEFAULT_ENTRY);
+        self.tape.push_back(self.blank);
     }
 
-    pub fn print_tape(&self, include_pos_marker: bool) {
-        let states = STATES_LOCK.read().unwrap();
-        let mut tape = "".to_string();
-        for entry in &self.tape {
-            tape += &format!(" {entry}");
-        }
+    pub fn print_tape(&self, include_pos_marker: bool, colors: Colors) {
+        print!("{}", self.format_tape(include_pos_marker, colors));
+    }
 
-        let mut instruction = None;
-        match &self.state {
-            Some(state) => {
-                for inst in self.instructions.iter() {
-                    if state == &inst.state && self.tape[self.pos] == inst.entry {
-                        instruction = Some(inst);
-                    }
-                }
-            }
-            None => {}
+    /// The current state and instruction, then the tape with the head cell
+    /// highlighted and, if `include_pos_marker` is set, a line marking the
+    /// head with `^` and the starting cell with `|`. Cells are padded to the
+    /// widest symbol so the markers line up.
+    pub fn format_tape(&self, include_pos_marker: bool, colors: Colors) -> String {
+        let width = self
+            .tape
+            .iter()
+            .map(|entry| self.glyph(*entry).chars().count())
+            .max()
+            .unwrap_or(1);
+        let mut tape = String::new();
+        for (i, entry) in self.tape.iter().enumerate() {
+            let cell = format!("{:>width$}", self.glyph(*entry));
+            tape.push(' ');
+            tape += &if i == self.pos {
+                colors.head(&cell)
+            } else if *entry != self.blank {
+                colors.bold(&cell)
+            } else {
+                cell
+            };
         }
 
-        let state = match self.state {
-            Some(state) => &states[state],
-            None => "Halt",
-        };
-
-        let instruction = match instruction {
-            Some(instruction) => format!("{}", instruction),
-            None => "No Instruction".to_string(),
-        };
-
-        println!(
-            "State: {}, {}, {} steps",
-            state, &instruction, self.num_steps
-        );
-        println!("{}", tape);
+        let mut lines = format!("{}\n{}\n", self.format_state(colors), tape);
 
         if include_pos_marker {
             let mut indicator = "".to_string();
@@ -272,57 +1289,231 @@ impl TuringMachine {
                     " "
                 };
 
-                indicator = indicator + frame + marker;
+                indicator = indicator + frame + &format!("{marker:>width$}");
             }
-            println!("{}", indicator);
+            lines += indicator.trim_end();
+            lines.push('\n');
         }
+        lines
     }
 
-    pub fn print_instructions(&self) {
-        println!("Instructions: ");
-        for instruction in self.instructions.iter() {
-            println!("{instruction}");
+    /// The current state and the tape drawn with box-drawing characters, a
+    /// border around every cell and a `▲` under the head. Below, every
+    /// `every` cells are numbered relative to the starting cell.
+    pub fn format_tape_boxed(&self, every: usize, colors: Colors) -> String {
+        let width = self
+            .tape
+            .iter()
+            .map(|entry| self.glyph(*entry).chars().count())
+            .max()
+            .unwrap_or(1);
+        let border = "─".repeat(width + 2);
+        let (mut top, mut cells, mut bottom) = ("┌".to_string(), "│".to_string(), "└".to_string());
+        for (i, entry) in self.tape.iter().enumerate() {
+            let cell = format!(" {:>width$} ", self.glyph(*entry));
+            cells += &if i == self.pos {
+                colors.head(&cell)
+            } else if *entry != self.blank {
+                colors.bold(&cell)
+            } else {
+                cell
+            };
+            cells.push('│');
+            top += &border;
+            bottom += &if i == self.pos {
+                let half = "─".repeat(width.div_ceil(2));
+                format!("{half}▲{}", "─".repeat(width + 1 - half.chars().count()))
+            } else {
+                border.clone()
+            };
+            let last = i + 1 == self.tape.len();
+            top.push(if last { '┐' } else { '┬' });
+            bottom.push(if last { '┘' } else { '┴' });
         }
-        println!();
+
+        // An index is written from the left of its cell if it doesn't run
+        // into the one before.
+        let every = every.max(1) as isize;
+        let mut indices = String::new();
+        for i in 0..self.tape.len() {
+            let index = i as isize - self.offset as isize;
+            let column = i * (width + 3) + 1;
+            let chars = indices.chars().count();
+            if index % every == 0 && chars < column {
+                indices += &" ".repeat(column - chars);
+                indices += &index.to_string();
+            }
+        }
+
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n",
+            self.format_state(colors),
+            top,
+            cells,
+            bottom,
+            indices.trim_end()
+        )
     }
 
-    pub fn print_states(&self) {
-        let states = STATES_LOCK.read().unwrap();
-        println!("States: ");
-        println!(" Number | Name ");
-        println!("--------+------");
-        for i in 0..states.len() {
-            println!(" {:6} | '{}' ", i, { &states[i] })
+    /// The current state, the instruction it takes next and the number of
+    /// steps so far.
+    pub fn format_state(&self, colors: Colors) -> String {
+        let instruction = match self.state() {
+            Some(state) => self
+                .instructions
+                .iter()
+                .find(|inst| inst.state == state && self.tape[self.pos] == inst.entry),
+            None => None,
+        };
+
+        let state = match self.state() {
+            Some(state) => &self.states[state],
+            None => self.halt_reason.map_or("Halt", |reason| reason.name()),
+        };
+
+        let instruction = match instruction {
+            Some(instruction) => format!("{}", self.named(instruction)),
+            None => "No Instruction".to_string(),
+        };
+
+        format!(
+            "State: {}, {}, {} steps",
+            colors.state(state),
+            &instruction,
+            self.num_steps
+        )
+    }
+
+    pub fn print_instructions(&self, colors: Colors) {
+        print!("Instructions: \n{}\n", self.format_instructions(colors));
+    }
+
+    /// One line per instruction with the arrows aligned.
+    pub fn format_instructions(&self, colors: Colors) -> String {
+        let width = self
+            .instructions
+            .iter()
+            .map(|instruction| {
+                self.states[instruction.state].len() + instruction.entry.to_string().len()
+            })
+            .max()
+            .unwrap_or(0);
+        let mut lines = String::new();
+        for instruction in self.instructions.iter() {
+            let target = match instruction.new_state {
+                Some(state) => &self.states[state],
+                None => instruction.halt.name(),
+            };
+            let source = &self.states[instruction.state];
+            let padding = width - source.len() - instruction.entry.to_string().len();
+            lines += &format!(
+                "({}, {}){:padding$} -> ({}, {}, {})\n",
+                colors.state(source),
+                instruction.entry,
+                "",
+                colors.state(target),
+                instruction.new_entry,
+                instruction.direction
+            );
         }
-        println!();
+        lines
     }
 
-    pub fn eval_busy_bever(&self) -> (u128, u128, u128) {
-        let mut ones: u128 = 0;
-        let mut zeros: u128 = 0;
-
-        for entry in &self.tape {
-            if *entry == 1 {
-                ones += 1;
-            } else if *entry == 0 {
-                zeros += 1;
+    /// The state graph as a line per state with its outgoing transitions,
+    /// labeled with the symbol read, the symbol written and the direction,
+    /// like `A ─0:1R→ B`. The current state is marked with `▶` and the last
+    /// transition taken flashes.
+    pub fn format_graph(&self, colors: Colors) -> String {
+        let width = self.states.iter().map(String::len).max().unwrap_or(0);
+        let mut lines = String::new();
+        for (state, name) in self.states.iter().enumerate() {
+            let marker = if self.state() == Some(state) {
+                "▶"
+            } else {
+                " "
+            };
+            let mut line = format!(
+                "{} {}{:padding$}",
+                marker,
+                colors.state(name),
+                "",
+                padding = width - name.len()
+            );
+            for (index, instruction) in self.instructions.iter().enumerate() {
+                if instruction.state != state {
+                    continue;
+                }
+                let target = match instruction.new_state {
+                    Some(state) => &self.states[state],
+                    None => instruction.halt.name(),
+                };
+                let edge = format!(
+                    "─{}:{}{}→ {}",
+                    instruction.entry,
+                    instruction.new_entry,
+                    instruction.direction.letter(),
+                    target
+                );
+                line.push(' ');
+                line += &if self.last_instruction == Some(index) {
+                    colors.flash(&edge)
+                } else {
+                    edge
+                };
             }
+            lines += line.trim_end();
+            lines.push('\n');
         }
-        println!(
-            "Busy Bever: {} ones, {} zeros, after {} steps",
-            ones, zeros, self.num_steps
-        );
+        lines
+    }
+
+    fn named<'a>(&'a self, instruction: &'a Instruction) -> NamedInstruction<'a> {
+        NamedInstruction {
+            instruction,
+            states: &self.states,
+        }
+    }
+
+    /// The instruction at `index` with the names of its states, like
+    /// `(A, 0) -> (B, 1, Right)`.
+    pub fn format_instruction(&self, index: usize) -> String {
+        self.named(&self.instructions[index]).to_string()
+    }
 
+    pub fn print_states(&self) {
+        print!("{}", self.format_states());
+    }
+
+    /// A table of the numbers and names of the states.
+    pub fn format_states(&self) -> String {
+        let mut table = "States: \n Number | Name \n--------+------\n".to_string();
+        for (i, state) in self.states.iter().enumerate() {
+            table += &format!(" {:6} | '{}' \n", i, state);
+        }
+        table + "\n"
+    }
+
+    pub fn eval_busy_bever(&self) -> (u128, u128, u128) {
+        let ones = scan::count_tape(&self.tape, 1) as u128;
+        let zeros = scan::count_tape(&self.tape, 0) as u128;
         (ones, zeros, self.num_steps)
     }
 }
 
+/// Writes the machine in the format of machine files, as
+/// [`TuringMachine::to_turing`] does.
+impl Display for TuringMachine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_turing())
+    }
+}
+
 #[test]
 fn test_busy_bever_1() {
     let mut tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_1.turing"));
 
     tm.print_states();
-    tm.print_instructions();
+    tm.print_instructions(Colors::off());
 
     let mut num_steps = 0;
     while tm.step() {
@@ -341,7 +1532,7 @@ fn test_busy_bever_2() {
     let mut tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_2.turing"));
 
     tm.print_states();
-    tm.print_instructions();
+    tm.print_instructions(Colors::off());
 
     let mut num_steps = 0;
     while tm.step() {
@@ -360,7 +1551,7 @@ fn test_busy_bever_3() {
     let mut tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_3.turing"));
 
     tm.print_states();
-    tm.print_instructions();
+    tm.print_instructions(Colors::off());
 
     let mut num_steps = 0;
     while tm.step() {
@@ -379,7 +1570,7 @@ fn test_busy_bever_4() {
     let mut tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_4.turing"));
 
     tm.print_states();
-    tm.print_instructions();
+    tm.print_instructions(Colors::off());
 
     let mut num_steps = 0;
     while tm.step() {
@@ -400,7 +1591,7 @@ fn test_busy_bever_5() {
     ));
 
     tm.print_states();
-    tm.print_instructions();
+    tm.print_instructions(Colors::off());
 
     let mut num_steps = 0;
     while tm.step() {
@@ -413,3 +1604,426 @@ fn test_busy_bever_5() {
     assert_eq!(zeros, 8191);
     assert_eq!(num_steps, 47176870);
 }
+
+#[test]
+fn test_left_edge_infinite() {
+    let mut tm = TuringMachine::new(Path::new("examples/semi_infinite/left_walker.turing"));
+
+    while tm.step() {}
+
+    let (ones, _zeros, steps) = tm.eval_busy_bever();
+
+    assert_eq!(tm.halt_reason, Some(HaltReason::Halt));
+    assert_eq!(ones, 2);
+    assert_eq!(steps, 2);
+}
+
+#[test]
+fn test_left_edge_stay() {
+    let mut tm = TuringMachine::new(Path::new("examples/semi_infinite/left_walker.turing"));
+    tm.set_left_edge(Some(EdgeBehavior::Stay));
+
+    while tm.step() {}
+
+    let (ones, _zeros, steps) = tm.eval_busy_bever();
+
+    assert_eq!(tm.halt_reason, Some(HaltReason::Halt));
+    assert_eq!(ones, 0);
+    assert_eq!(steps, 2);
+}
+
+#[test]
+fn test_left_edge_crash_and_reject() {
+    for (edge, reason) in [
+        (EdgeBehavior::Crash, HaltReason::Crash),
+        (EdgeBehavior::Reject, HaltReason::Reject),
+    ] {
+        let mut tm = TuringMachine::new(Path::new("examples/semi_infinite/left_walker.turing"));
+        tm.set_left_edge(Some(edge));
+
+        while tm.step() {}
+
+        assert_eq!(tm.halt_reason, Some(reason));
+        assert_eq!(tm.num_steps, 1);
+    }
+}
+
+#[test]
+fn test_left_edge_halt() {
+    for edge in [
+        EdgeBehavior::Stay,
+        EdgeBehavior::Crash,
+        EdgeBehavior::Reject,
+    ] {
+        let mut tm = TuringMachine::parse("A 0 -> Halt 1 L").unwrap();
+        tm.set_left_edge(Some(edge));
+
+        while tm.step() {}
+
+        assert_eq!(tm.halt_reason, Some(HaltReason::Halt));
+        assert_eq!((tm.head(), tm.num_steps, tm.edge_hits), (0, 1, 1));
+        assert_eq!(tm.tape(), &[1]);
+    }
+}
+
+#[test]
+fn test_bounded() {
+    let path = Path::new("examples/busy_bever/busy_bever_2.turing");
+
+    let mut tm = TuringMachine::new(path);
+    tm.set_bound(Some((1, EdgeBehavior::Reject)));
+    while tm.step() {}
+    assert_eq!(tm.halt_reason, Some(HaltReason::Reject));
+    assert_eq!(tm.num_steps, 1);
+
+    let mut tm = TuringMachine::new(path);
+    tm.set_bound(Some((4, EdgeBehavior::Crash)));
+    while tm.step() {}
+    assert_eq!(tm.halt_reason, Some(HaltReason::Crash));
+    assert_eq!(tm.num_steps, 3);
+
+    let mut tm = TuringMachine::new(path);
+    tm.set_bound(Some((4, EdgeBehavior::Stay)));
+    while tm.step() {}
+    assert_eq!(tm.halt_reason, Some(HaltReason::Halt));
+    assert_eq!(tm.edge_hits, 1);
+    assert_eq!(tm.num_steps, 4);
+}
+
+#[test]
+fn test_metadata() {
+    let text = "#name  Busy beaver 2\n#author Tibor Radó\n#source-url https://bbchallenge.org\n\
+                A 0 -> Halt 1 R // done";
+    let tm = TuringMachine::parse(text).unwrap();
+    assert_eq!(
+        tm.metadata()[0],
+        ("name".to_string(), "Busy beaver 2".to_string())
+    );
+    assert_eq!(tm.metadata()[2].1, "https://bbchallenge.org");
+    assert_eq!(
+        tm.to_turing(),
+        "#name Busy beaver 2\n#author Tibor Radó\n#source-url https://bbchallenge.org\n\
+         A 0 -> Halt 1 R"
+    );
+    assert_eq!(
+        TuringMachine::parse(&tm.to_turing()).unwrap().metadata(),
+        tm.metadata()
+    );
+    assert_eq!(parse_metadata("#tags busy"), None);
+    assert_eq!(
+        parse_metadata("#description"),
+        Some(("description".to_string(), String::new()))
+    );
+}
+
+#[test]
+fn test_blank() {
+    // Busy beaver 2 on a tape of 2s.
+    let text = "%blank 2\nA 2 -> B 1 R\nA 1 -> B 1 L\nB 2 -> A 1 L\nB 1 -> Halt 1 R";
+    let mut tm = TuringMachine::parse(text).unwrap();
+    assert_eq!(tm.blank(), 2);
+    assert_eq!(tm.to_turing().lines().next(), Some("%blank 2"));
+    assert_eq!(TuringMachine::parse(&tm.to_turing()).unwrap().blank(), 2);
+    let mut zero = tm.with_zero_blank();
+    assert_eq!(zero.blank(), DEFAULT_ENTRY);
+    while tm.step() {}
+    while zero.step() {}
+    assert_eq!((tm.num_steps, zero.num_steps), (6, 6));
+    assert_eq!(tm.eval_busy_bever().0, 4);
+    assert_eq!(zero.eval_busy_bever().0, 4);
+
+    // The same machine on a blank tape of 0s gets stuck at once.
+    let mut tm = TuringMachine::parse(text).unwrap();
+    tm.set_blank(0);
+    tm.set_reject_undefined(true);
+    assert!(!tm.step());
+    assert_eq!(tm.halt_reason, Some(HaltReason::Reject));
+}
+
+#[test]
+fn test_halting_conventions() {
+    let tm =
+        TuringMachine::parse("%halt Z H\nA 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> Z 1 R")
+            .unwrap();
+    assert_eq!(tm.states(), ["A", "B"]);
+    assert_eq!(tm.instructions()[3].new_state, None);
+    let mut tm = tm;
+    while tm.step() {}
+    assert_eq!((tm.halt_reason, tm.num_steps), (Some(HaltReason::Halt), 6));
+
+    // Writing a 3 halts instead of moving on.
+    let mut tm =
+        TuringMachine::parse("%halt-writing 3\nA 0 -> B 1 R\nB 0 -> B 3 R\nB 3 -> B 3 R").unwrap();
+    while tm.step() {}
+    assert_eq!((tm.halt_reason, tm.num_steps), (Some(HaltReason::Halt), 2));
+
+    assert!(TuringMachine::parse("%halt A\nA 0 -> B 1 R").is_err());
+    assert!(TuringMachine::parse("%halt B\nA 0 -> B 1 R\nB 0 -> A 1 R").is_err());
+    assert!(TuringMachine::parse("%halt-writing x\nA 0 -> A 1 R").is_err());
+}
+
+#[test]
+fn test_accept() {
+    let path = Path::new("examples/recognizers/1n2n.turing");
+    for (word, verdict) in [
+        ("", Verdict::Accept),
+        ("12", Verdict::Accept),
+        ("111222", Verdict::Accept),
+        ("1 1 2 2", Verdict::Accept),
+        ("112", Verdict::Reject),
+        ("21", Verdict::Reject),
+        ("1212", Verdict::Reject),
+    ] {
+        let mut tm = TuringMachine::new(path);
+        tm.set_reject_undefined(true);
+        tm.set_input(&parse_word(word).unwrap());
+        assert_eq!(tm.run_word(1000), verdict, "word '{word}'");
+    }
+
+    let mut tm = TuringMachine::new(Path::new(
+        "examples/busy_bever/busy_bever_5_best_currently_known.turing",
+    ));
+    assert_eq!(tm.run_word(1000), Verdict::StepLimit);
+}
+
+#[test]
+fn test_undefined() {
+    // Without an instruction for the configuration, the machine crashes
+    // instead of aborting, or rejects if asked to.
+    let mut tm = TuringMachine::parse("A 0 -> B 1 R").unwrap();
+    assert!(tm.step());
+    assert!(!tm.step());
+    assert_eq!(tm.halt_reason, Some(HaltReason::Crash));
+    assert_eq!(tm.num_steps, 1);
+    assert!(!tm.step());
+
+    let mut tm = TuringMachine::parse("A 0 -> B 1 R").unwrap();
+    tm.set_reject_undefined(true);
+    while tm.step() {}
+    assert_eq!(tm.halt_reason, Some(HaltReason::Reject));
+}
+
+#[test]
+fn test_comments() {
+    let plain = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_2.turing"));
+    let commented = TuringMachine::parse(
+        "// The two-state busy beaver.\n\
+         A 0 -> B    1 R // start here\n\
+         A 1 -> B    1 L\n   \n\
+         B 0 -> A    1 L\n\
+         B 1 -> Halt 1 R//done",
+    )
+    .unwrap();
+    assert_eq!(commented, plain);
+}
+
+#[test]
+fn test_instruction_table() {
+    // The first of several matching instructions is taken.
+    let mut tm = TuringMachine::parse("A 0 -> B 1 R\nA 0 -> Halt 0 R\nB 0 -> Halt 2 L").unwrap();
+    while tm.step() {}
+    assert_eq!(tm.num_steps, 2);
+    assert_eq!(tm.last_instruction(), Some(2));
+    // Halted machines are in the empty row after the states.
+    assert_eq!(tm.state(), None);
+    assert!(!tm.step());
+    assert_eq!(tm.num_steps, 2);
+
+    // Symbols larger than any instruction reads have no instruction.
+    let mut tm = TuringMachine::parse("A 0 -> A 1 R\nA 1 -> Halt 1 R").unwrap();
+    tm.set_reject_undefined(true);
+    tm.set_input(&[0, 7]);
+    while tm.step() {}
+    assert_eq!(tm.halt_reason, Some(HaltReason::Reject));
+    assert_eq!(tm.num_steps, 1);
+}
+
+/// A pseudo-random number generator for generating test inputs, giving
+/// numbers below the bound it is called with.
+#[cfg(test)]
+pub fn random(seed: u64) -> impl FnMut(usize) -> usize {
+    let mut state = seed.wrapping_mul(0x9e3779b97f4a7c15) | 1;
+    move |n| {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % n as u64) as usize
+    }
+}
+
+/// Generates a machine file from `seed`: mostly instructions built from a
+/// few state names, symbols and directions, some of them invalid, mixed
+/// with comments, blank lines and random bytes.
+#[cfg(test)]
+pub fn arbitrary_machine(seed: u64) -> Vec<u8> {
+    let mut next = random(seed);
+    let states = ["A", "B", "C", "Halt", "Accept", "Reject"];
+    let symbols = ["0", "1", "2", "255"];
+    let invalid = ["", "->", "256", "-1", "S", "x"];
+
+    let mut bytes = vec![];
+    for _ in 0..next(12) {
+        match next(10) {
+            0 => bytes.extend(b"// a comment"),
+            1 => {}
+            2 => bytes.extend((0..next(16)).map(|_| next(256) as u8)),
+            _ => {
+                let mut fields = vec![
+                    states[next(states.len())],
+                    symbols[next(symbols.len())],
+                    "->",
+                    states[next(states.len())],
+                    symbols[next(symbols.len())],
+                    ["L", "R"][next(2)],
+                ];
+                match next(20) {
+                    0 => fields.truncate(next(fields.len())),
+                    1 => fields[next(6)] = invalid[next(invalid.len())],
+                    _ => {}
+                }
+                bytes.extend(fields.join(" ").as_bytes());
+            }
+        }
+        bytes.extend(if next(4) == 0 { "\r\n" } else { "\n" }.as_bytes());
+    }
+    bytes
+}
+
+#[test]
+fn test_parse_machine() {
+    assert_eq!(
+        parse_machine(b"A 0 -> Halt 1 R\n"),
+        Ok(TuringMachine::parse("A 0 -> Halt 1 R").unwrap())
+    );
+    assert!(parse_machine(b"").is_err());
+    assert!(parse_machine(b"// nothing\n\n").is_err());
+    assert!(parse_machine(b"A 0 -> Halt 1 \xff").is_err());
+    assert!(parse_machine(b"A 0 -> Halt 1").is_err());
+
+    // Whatever the file, parsing and simulating it doesn't panic.
+    let edges = [None, Some(EdgeBehavior::Stay), Some(EdgeBehavior::Crash)];
+    let mut parsed = 0;
+    for seed in 0..5000 {
+        let bytes = arbitrary_machine(seed);
+        let Ok(mut tm) = parse_machine(&bytes) else {
+            continue;
+        };
+        parsed += 1;
+        tm.set_reject_undefined(true);
+        tm.set_left_edge(edges[seed as usize % edges.len()]);
+        while tm.num_steps < 1000 && tm.step() {}
+    }
+    assert!(parsed > 1000, "only {parsed} machines were valid");
+}
+
+#[test]
+fn test_round_trip() {
+    for seed in 0..5000 {
+        let Ok(tm) = parse_machine(&arbitrary_machine(seed)) else {
+            continue;
+        };
+        let text = tm.to_string();
+        let parsed = TuringMachine::parse(&text).unwrap();
+        let named = |tm: &TuringMachine| {
+            let mut lines: Vec<String> = tm
+                .instructions()
+                .iter()
+                .map(|instruction| tm.named(instruction).to_string())
+                .collect();
+            lines.sort();
+            lines
+        };
+        assert_eq!(named(&parsed), named(&tm), "{text}");
+        assert_eq!(parsed.to_string(), text);
+    }
+}
+
+#[test]
+fn test_format_tape() {
+    let mut tm = TuringMachine::parse("A 0 -> B 12 L\nB 0 -> A 3 R\nA 12 -> Halt 0 R").unwrap();
+    tm.step();
+    assert_eq!(
+        tm.format_tape(true, Colors::off()),
+        "State: B, (B, 0) -> (A, 3, Right), 1 steps\n  0 12\n  ^|  |\n"
+    );
+    assert_eq!(
+        tm.format_tape(false, Colors::on()),
+        "State: \x1b[36mB\x1b[0m, (B, 0) -> (A, 3, Right), 1 steps\n \x1b[7m 0\x1b[0m \x1b[1m12\x1b[0m\n"
+    );
+    assert_eq!(
+        tm.format_instructions(Colors::off()),
+        "(A, 0)  -> (B, 12, Left)\n(B, 0)  -> (A, 3, Right)\n(A, 12) -> (Halt, 0, Right)\n"
+    );
+}
+
+#[test]
+fn test_glyphs() {
+    let mut tm = TuringMachine::parse(
+        "%glyphs 0=· 1=█ // blank and mark\nA 0 -> B 1 R\nB 0 -> A 2 R\nA 2 -> Halt 0 R",
+    )
+    .unwrap();
+    assert_eq!(tm.glyph(1), "█");
+    assert_eq!(tm.glyph(2), "2");
+    tm.step();
+    tm.step();
+    assert_eq!(
+        tm.format_tape(true, Colors::off()),
+        "State: A, (A, 0) -> (B, 1, Right), 2 steps\n █ 2 ·\n| |  ^\n"
+    );
+    assert!(TuringMachine::parse("%glyphs 0=").is_err());
+    assert!(TuringMachine::parse("%glyphs x=a").is_err());
+}
+
+#[test]
+fn test_format_tape_boxed() {
+    let mut tm = TuringMachine::parse("A 0 -> B 12 L\nB 0 -> A 3 R\nA 12 -> Halt 0 R").unwrap();
+    tm.step();
+    assert_eq!(
+        tm.format_tape_boxed(1, Colors::off()),
+        "State: B, (B, 0) -> (A, 3, Right), 1 steps\n\
+         ┌────┬────┐\n\
+         │  0 │ 12 │\n\
+         └─▲──┴────┘\n \
+         -1   0\n"
+    );
+    tm.set_glyphs(BTreeMap::from([(0, "·".to_string())]));
+    for _ in 0..3 {
+        tm.step();
+    }
+    assert_eq!(
+        tm.format_tape_boxed(2, Colors::off()),
+        "State: Halt, No Instruction, 3 steps\n\
+         ┌───┬───┬───┐\n\
+         │ 3 │ · │ · │\n\
+         └───┴───┴─▲─┘\n     \
+         0\n"
+    );
+}
+
+#[test]
+fn test_to_mermaid() {
+    let tm = TuringMachine::parse(
+        "A 0 -> B 1 R\nA 1 -> B 1 L\nB 0 -> A 1 L\nB 1 -> Halt 1 R\nB 2 -> Reject 2 R",
+    )
+    .unwrap();
+    assert_eq!(
+        tm.to_mermaid(),
+        "stateDiagram-v2\n    state \"A\" as s0\n    state \"B\" as s1\n    [*] --> s0\n    \
+         s0 --> s1 : 0/1,R; 1/1,L\n    s1 --> s0 : 0/1,L\n    s1 --> [*] : 1/1,R\n    \
+         s1 --> Reject : 2/2,R"
+    );
+}
+
+#[test]
+fn test_format_graph() {
+    let mut tm = TuringMachine::new(Path::new("examples/busy_bever/busy_bever_2.turing"));
+    tm.step();
+    assert_eq!(
+        tm.format_graph(Colors::off()),
+        "  A ─0:1R→ B ─1:1L→ B\n▶ B ─0:1L→ A ─1:1R→ Halt\n"
+    );
+    assert!(tm
+        .format_graph(Colors::on())
+        .contains("\x1b[5m─0:1R→ B\x1b[0m ─1:1L→ B"));
+}
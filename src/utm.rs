@@ -0,0 +1,177 @@
+use crate::turing::{Direction, TapeEntry, TuringMachine};
+
+/// Encoding of a machine and its input for a bundled universal machine.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum UtmScheme {
+    /// Tape layout of `examples/utm/utm_unary.turing`, which simulates
+    /// machines over the symbols `0` and `1`.
+    ///
+    /// The tape starts with one block per state, the first block belonging
+    /// to the start state. A block is a block marker followed by the records
+    /// for reading `0` and `1`. A record is the number of the new state in
+    /// unary (no ticks for halting), followed by the symbol to write and the
+    /// direction. After the blocks a separator is followed by the simulated
+    /// tape, with the cell under the simulated head marked.
+    Unary,
+}
+
+const TICK: TapeEntry = 1;
+const WRITE_0: TapeEntry = 3;
+const WRITE_1: TapeEntry = 4;
+const LEFT: TapeEntry = 5;
+const RIGHT: TapeEntry = 6;
+const BLOCK: TapeEntry = 7;
+const CURRENT_BLOCK: TapeEntry = 8;
+const SEPARATOR: TapeEntry = 9;
+const CELL_0: TapeEntry = 10;
+const CELL_1: TapeEntry = 11;
+const HEAD_0: TapeEntry = 12;
+const HEAD_1: TapeEntry = 13;
+
+/// Configuration of the simulated machine read back from a universal
+/// machine's tape.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Decoded {
+    /// Index of the current state, `None` once the simulated machine halted.
+    pub state: Option<usize>,
+    pub tape: Vec<TapeEntry>,
+    pub head: usize,
+}
+
+impl UtmScheme {
+    /// Encodes `tm` and `input` into the initial tape of the universal
+    /// machine. Missing transitions are encoded as halting without changing
+    /// the tape.
+    pub fn encode(
+        &self,
+        tm: &TuringMachine,
+        input: &[TapeEntry],
+    ) -> Result<Vec<TapeEntry>, String> {
+        if let Some(symbol) = input.iter().find(|symbol| **symbol > 1) {
+            return Err(format!("input symbol {symbol} is not 0 or 1"));
+        }
+
+        let mut tape = vec![];
+        for state in 0..tm.states().len() {
+            tape.push(if state == 0 { CURRENT_BLOCK } else { BLOCK });
+            for entry in [0, 1] {
+                let instruction = tm
+                    .instructions()
+                    .iter()
+                    .find(|instruction| instruction.state == state && instruction.entry == entry);
+                let (new_state, new_entry, direction) = match instruction {
+                    Some(instruction) => (
+                        instruction.new_state,
+                        instruction.new_entry,
+                        instruction.direction,
+                    ),
+                    None => (None, entry, Direction::Right),
+                };
+                let ticks = new_state.map_or(0, |new_state| new_state + 1);
+                tape.extend((0..ticks).map(|_| TICK));
+                tape.push(match new_entry {
+                    0 => WRITE_0,
+                    1 => WRITE_1,
+                    _ => return Err(format!("symbol {new_entry} is not 0 or 1")),
+                });
+                tape.push(match direction {
+                    Direction::Left => LEFT,
+                    Direction::Right => RIGHT,
+                });
+            }
+        }
+
+        tape.push(SEPARATOR);
+        if input.is_empty() {
+            tape.push(HEAD_0);
+        }
+        for (i, symbol) in input.iter().enumerate() {
+            tape.push(match (i, symbol) {
+                (0, 0) => HEAD_0,
+                (0, _) => HEAD_1,
+                (_, 0) => CELL_0,
+                (_, _) => CELL_1,
+            });
+        }
+        Ok(tape)
+    }
+
+    /// Reads the simulated configuration from the tape of the universal
+    /// machine. Only meaningful between two simulated steps, e.g. after the
+    /// universal machine halts.
+    pub fn decode(&self, tape: &[TapeEntry]) -> Result<Decoded, String> {
+        let separator = match tape.iter().position(|entry| *entry == SEPARATOR) {
+            Some(separator) => separator,
+            None => return Err("no separator between program and tape".to_string()),
+        };
+
+        let state = tape[..separator]
+            .iter()
+            .filter(|entry| **entry == BLOCK || **entry == CURRENT_BLOCK)
+            .position(|entry| *entry == CURRENT_BLOCK);
+
+        let mut cells = vec![];
+        let mut head = None;
+        for (i, entry) in tape[separator + 1..].iter().enumerate() {
+            cells.push(match *entry {
+                0 | CELL_0 => 0,
+                CELL_1 => 1,
+                HEAD_0 | HEAD_1 => {
+                    head = Some(i);
+                    (entry - HEAD_0) as TapeEntry
+                }
+                _ => return Err(format!("unexpected symbol {entry} on the simulated tape")),
+            });
+        }
+        let head = match head {
+            Some(head) => head,
+            None => return Err("no head on the simulated tape".to_string()),
+        };
+        while cells.len() > head + 1 && cells.last() == Some(&0) {
+            cells.pop();
+        }
+
+        Ok(Decoded {
+            state,
+            tape: cells,
+            head,
+        })
+    }
+}
+
+#[test]
+fn test_utm_unary() {
+    use std::path::Path;
+
+    for (path, input) in [
+        ("examples/busy_bever/busy_bever_2.turing", vec![]),
+        ("examples/busy_bever/busy_bever_3.turing", vec![]),
+        ("examples/busy_bever/busy_bever_4.turing", vec![]),
+        (
+            "examples/busy_bever/busy_bever_4.turing",
+            vec![0, 1, 1, 0, 1],
+        ),
+    ] {
+        let mut tm = TuringMachine::new(Path::new(path));
+        if !input.is_empty() {
+            tm.set_input(&input);
+        }
+
+        let mut utm = TuringMachine::new(Path::new("examples/utm/utm_unary.turing"));
+        utm.set_input(&UtmScheme::Unary.encode(&tm, &input).unwrap());
+
+        while tm.step() {}
+        while utm.step() {}
+
+        let utm_tape: Vec<TapeEntry> = utm.tape().iter().copied().collect();
+        let decoded = UtmScheme::Unary.decode(&utm_tape).unwrap();
+
+        let mut tape: Vec<TapeEntry> = tm.tape().iter().copied().collect();
+        while tape.len() > tm.head() + 1 && tape.last() == Some(&0) {
+            tape.pop();
+        }
+        assert_eq!(decoded.state, None, "{path}");
+        assert_eq!(decoded.tape, tape, "{path}");
+        assert_eq!(decoded.head, tm.head(), "{path}");
+    }
+}
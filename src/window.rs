@@ -0,0 +1,155 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    hash::{Hash, Hasher},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    decide::{self, Certificate, Configuration, Proof},
+    turing::{Direction, TuringMachine},
+};
+
+/// Default number of cells around the head a fingerprint covers.
+pub const WINDOW: usize = 32;
+/// Default number of fingerprints remembered.
+pub const HISTORY: usize = 1_000_000;
+/// Largest number of repetitions checked by running the machine again.
+const MAX_CANDIDATES: usize = 64;
+/// Steps between looking whether another analysis asked to stop.
+const STOP_CHECK_INTERVAL: u128 = 4096;
+
+/// Position of the head relative to the starting cell.
+fn position(tm: &TuringMachine) -> isize {
+    tm.head() as isize - tm.origin() as isize
+}
+
+/// A hash of the state and the `window` cells centered on the head.
+fn fingerprint(tm: &TuringMachine, window: usize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    tm.state().hash(&mut hasher);
+    let first = tm.head() as isize - (window / 2) as isize;
+    for index in first..first + window as isize {
+        let entry = match index {
+            0.. => tm.tape().get(index as usize).copied().unwrap_or(0),
+            _ => 0,
+        };
+        entry.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Whether a fresh copy of `tm` repeats the steps from `start` to
+/// `start + period` forever, moving by `drift` cells each time. The proof
+/// is only given if [`decide::verify`] accepts it.
+fn prove(tm: &TuringMachine, start: u128, period: u128, drift: isize) -> Option<Proof> {
+    let mut copy =
+        TuringMachine::from_instructions(tm.states().to_vec(), tm.instructions().to_vec());
+    copy.set_reject_undefined(true);
+    while copy.num_steps < start && copy.step() {}
+    let before = Configuration::of(&copy);
+    let head = position(&copy);
+    let s = drift.signum();
+    let mut back = 0;
+    while copy.num_steps < start + period && copy.step() {
+        back = back.max(s * (head - position(&copy)));
+    }
+    let after = Configuration::of(&copy);
+    let proof = match drift {
+        0 => Proof::Cycler {
+            start,
+            period,
+            configuration: before,
+        },
+        _ => Proof::TranslatedCycler {
+            start,
+            period,
+            direction: match drift > 0 {
+                true => Direction::Right,
+                false => Direction::Left,
+            },
+            window: back as usize,
+            before,
+            after,
+        },
+    };
+    let certificate = Certificate {
+        machine: tm.to_turing(),
+        proof,
+    };
+    decide::verify(tm, &certificate)
+        .is_ok()
+        .then_some(certificate.proof)
+}
+
+/// Runs `tm` on the blank tape for up to `max_steps` steps, remembering the
+/// last `history` fingerprints of the state and the `window` cells around
+/// the head. When one repeats, the machine may be a cycler, or a translated
+/// cycler if the head moved in between, which a second run confirms. Much
+/// cheaper per step than the translated cycler decider of
+/// [`decide::simulate`], but it only finds cycles whose tape ahead of the
+/// head looks the same within the window.
+pub fn find(
+    tm: &TuringMachine,
+    max_steps: u128,
+    window: usize,
+    history: usize,
+    stop: &AtomicBool,
+) -> Option<Proof> {
+    let mut tm = TuringMachine::from_instructions(tm.states().to_vec(), tm.instructions().to_vec());
+    tm.set_reject_undefined(true);
+
+    let mut seen: HashMap<u64, (u128, isize)> = HashMap::new();
+    // The fingerprints in the order they were last seen, to forget the
+    // oldest ones.
+    let mut order: VecDeque<(u64, u128)> = VecDeque::new();
+    let mut candidates = 0;
+    while tm.num_steps < max_steps && tm.step() {
+        if tm.num_steps.is_multiple_of(STOP_CHECK_INTERVAL) && stop.load(Ordering::Relaxed) {
+            return None;
+        }
+        let key = fingerprint(&tm, window);
+        let head = position(&tm);
+        if let Some((start, before)) = seen.insert(key, (tm.num_steps, head)) {
+            if candidates < MAX_CANDIDATES {
+                candidates += 1;
+                if let Some(proof) = prove(&tm, start, tm.num_steps - start, head - before) {
+                    return Some(proof);
+                }
+            }
+        }
+        order.push_back((key, tm.num_steps));
+        while order.len() > history {
+            let (key, step) = order.pop_front().expect("order is not empty");
+            // Unless it was seen again since.
+            if seen.get(&key).is_some_and(|(last, _)| *last == step) {
+                seen.remove(&key);
+            }
+        }
+    }
+    None
+}
+
+#[test]
+fn test_window() {
+    use std::path::Path;
+
+    let stop = AtomicBool::new(false);
+    let tm = TuringMachine::new(Path::new("examples/deciders/cycler.turing"));
+    assert!(matches!(
+        find(&tm, 1000, WINDOW, HISTORY, &stop),
+        Some(Proof::Cycler { .. })
+    ));
+    let tm = TuringMachine::new(Path::new("examples/deciders/translated_cycler.turing"));
+    let proof = find(&tm, 1000, WINDOW, HISTORY, &stop).unwrap();
+    assert!(matches!(proof, Proof::TranslatedCycler { .. }));
+    let certificate = Certificate {
+        machine: tm.to_turing(),
+        proof,
+    };
+    assert_eq!(decide::verify(&tm, &certificate), Ok(()));
+
+    // A history too short to hold a period finds nothing.
+    assert_eq!(find(&tm, 1000, WINDOW, 1, &stop), None);
+    let tm = crate::bbchallenge::parse_machine("1RB1LB_1LA1RZ").unwrap();
+    assert_eq!(find(&tm, 1000, WINDOW, HISTORY, &stop), None);
+}
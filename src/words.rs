@@ -0,0 +1,178 @@
+use std::fmt::Write;
+
+use crate::turing::{self, TapeEntry, TuringMachine, Verdict};
+
+/// How the empty word is shown in tables.
+pub const EMPTY_WORD: &str = "(empty)";
+
+/// A word to run a machine on, with the verdict expected for it if any.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Case {
+    pub word: String,
+    pub expected: Option<Verdict>,
+}
+
+/// The `words` without an expected verdict unless they are listed in
+/// `accept` or `reject` as well, followed by the other words of those. A
+/// word listed in both is an error.
+pub fn cases(words: &[String], accept: &[String], reject: &[String]) -> Result<Vec<Case>, String> {
+    let mut cases: Vec<Case> = words
+        .iter()
+        .map(|word| Case {
+            word: word.clone(),
+            expected: None,
+        })
+        .collect();
+    for (list, verdict) in [(accept, Verdict::Accept), (reject, Verdict::Reject)] {
+        for word in list {
+            match cases.iter_mut().find(|case| &case.word == word) {
+                Some(case) if case.expected.is_some_and(|expected| expected != verdict) => {
+                    let word = if word.is_empty() { EMPTY_WORD } else { word };
+                    return Err(format!("'{word}' is expected to be accepted and rejected"));
+                }
+                Some(case) => case.expected = Some(verdict),
+                None => cases.push(Case {
+                    word: word.clone(),
+                    expected: Some(verdict),
+                }),
+            }
+        }
+    }
+    Ok(cases)
+}
+
+/// What a machine did on a [`Case`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Row {
+    pub case: Case,
+    pub verdict: Verdict,
+    pub steps: u128,
+}
+
+impl Row {
+    /// Whether the verdict isn't the expected one.
+    pub fn failed(&self) -> bool {
+        self.case
+            .expected
+            .is_some_and(|expected| expected != self.verdict)
+    }
+}
+
+/// Runs a copy of `machine` on every case for up to `max_steps` steps,
+/// after `prepare` set up its tape for the input.
+pub fn run(
+    machine: &TuringMachine,
+    cases: Vec<Case>,
+    max_steps: u128,
+    prepare: impl Fn(&mut TuringMachine, &[TapeEntry]),
+) -> Result<Vec<Row>, String> {
+    let mut rows = vec![];
+    for case in cases {
+        let input = turing::parse_word(&case.word)
+            .map_err(|why| format!("Can't read input '{}': {}", case.word, why))?;
+        let mut tm = machine.clone();
+        tm.set_input(&input);
+        prepare(&mut tm, &input);
+        let verdict = tm.run_word(max_steps);
+        rows.push(Row {
+            case,
+            verdict,
+            steps: tm.num_steps,
+        });
+    }
+    Ok(rows)
+}
+
+/// The pass/fail table of `rows`, followed by the number of failures.
+pub fn table(rows: &[Row]) -> String {
+    let width = rows
+        .iter()
+        .map(|row| row.case.word.len())
+        .max()
+        .unwrap_or(0)
+        .max(EMPTY_WORD.len());
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        " {:width$} | Expected | Result     |      Steps | Status",
+        "Word"
+    );
+    let _ = writeln!(
+        out,
+        "-{:-<width$}-+----------+------------+------------+--------",
+        ""
+    );
+    for row in rows {
+        let (expected, status) = match row.case.expected {
+            None => ("-".to_string(), "-"),
+            Some(expected) if row.failed() => (expected.to_string(), "FAIL"),
+            Some(expected) => (expected.to_string(), "pass"),
+        };
+        let word = &row.case.word;
+        let _ = writeln!(
+            out,
+            " {:width$} | {:8} | {:10} | {:10} | {}",
+            if word.is_empty() { EMPTY_WORD } else { word },
+            expected,
+            row.verdict,
+            row.steps,
+            status
+        );
+    }
+    let failed = rows.iter().filter(|row| row.failed()).count();
+    let _ = write!(out, "\n{} words, {} failed", rows.len(), failed);
+    out
+}
+
+#[test]
+fn test_words() {
+    let words = |words: &[&str]| -> Vec<String> { words.iter().map(|w| w.to_string()).collect() };
+    let listed = cases(
+        &words(&["12", "21"]),
+        &words(&["", "12", "1122"]),
+        &words(&["21", "112"]),
+    )
+    .unwrap();
+    let expected: Vec<(&str, Option<Verdict>)> = listed
+        .iter()
+        .map(|case| (case.word.as_str(), case.expected))
+        .collect();
+    assert_eq!(
+        expected,
+        vec![
+            ("12", Some(Verdict::Accept)),
+            ("21", Some(Verdict::Reject)),
+            ("", Some(Verdict::Accept)),
+            ("1122", Some(Verdict::Accept)),
+            ("112", Some(Verdict::Reject)),
+        ]
+    );
+    assert_eq!(
+        cases(&[], &words(&["1", "12"]), &words(&["12"])),
+        Err("'12' is expected to be accepted and rejected".to_string())
+    );
+
+    let mut machine = TuringMachine::new(std::path::Path::new("examples/recognizers/1n2n.turing"));
+    machine.set_reject_undefined(true);
+    let mut wrong = listed.clone();
+    wrong[1].expected = Some(Verdict::Accept);
+    wrong.push(Case {
+        word: "1".to_string(),
+        expected: None,
+    });
+    let rows = run(&machine, wrong, 1000, |_, _| {}).unwrap();
+    assert_eq!(rows.iter().filter(|row| row.failed()).count(), 1);
+    let table = table(&rows);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(
+        lines[0],
+        " Word    | Expected | Result     |      Steps | Status"
+    );
+    assert!(lines[3].starts_with(" 21      | accept   | reject"));
+    assert!(lines[3].ends_with("| FAIL"));
+    assert!(lines[4].starts_with(" (empty) | accept   | accept"));
+    assert!(lines[7].ends_with("| -"));
+    assert_eq!(lines[9], "6 words, 1 failed");
+    let invalid = cases(&words(&["1 x"]), &[], &[]).unwrap();
+    assert!(run(&machine, invalid, 1000, |_, _| {}).is_err());
+}